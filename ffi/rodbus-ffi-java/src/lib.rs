@@ -8,6 +8,7 @@
     clippy::not_unsafe_ptr_arg_deref,
     clippy::uninlined_format_args,
     unused_variables,
+    static_mut_refs,
     dead_code
 )]
 // ^ these lints don't matter in the generated code
@@ -10,19 +10,10 @@ fn write_tracing_ffi() {
         .unwrap();
 }
 
-fn write_tokio_ffi() {
-    let mut file =
-        std::fs::File::create(Path::new(&env::var_os("OUT_DIR").unwrap()).join("runtime.rs"))
-            .unwrap();
-    file.write_all(sfio_tokio_ffi::get_impl_file().as_bytes())
-        .unwrap();
-}
-
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 
     write_tracing_ffi();
-    write_tokio_ffi();
 
     match rodbus_schema::build_lib() {
         Ok(lib) => {
@@ -36,7 +36,7 @@ pub(crate) unsafe fn client_channel_create_tcp(
     let runtime = runtime.as_ref().ok_or(ffi::ParamError::NullParameter)?;
 
     // enter the runtime context so we can spawn
-    let _enter = runtime.enter();
+    let _enter = runtime.enter()?;
 
     let channel = rodbus::client::spawn_tcp_client_task(
         get_host_addr(host, port)?,
@@ -48,7 +48,7 @@ pub(crate) unsafe fn client_channel_create_tcp(
 
     Ok(Box::into_raw(Box::new(ClientChannel {
         inner: FfiChannel::new(channel),
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
     })))
 }
 
@@ -78,10 +78,10 @@ pub(crate) unsafe fn client_channel_create_rtu(
     let runtime = runtime.as_ref().ok_or(ffi::ParamError::NullParameter)?;
 
     // enter the runtime context so we can spawn
-    let _enter = runtime.enter();
+    let _enter = runtime.enter()?;
 
     let channel = rodbus::client::spawn_rtu_client_task(
-        &path.to_string_lossy(),
+        path.to_string_lossy().as_ref(),
         serial_params.into(),
         max_queued_requests as usize,
         retry_strategy.into(),
@@ -91,7 +91,7 @@ pub(crate) unsafe fn client_channel_create_rtu(
 
     Ok(Box::into_raw(Box::new(ClientChannel {
         inner: FfiChannel::new(channel),
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
     })))
 }
 
@@ -127,7 +127,7 @@ pub(crate) unsafe fn client_channel_create_tls(
     let host_addr = get_host_addr(host, port)?;
 
     // enter the runtime context so we can spawn
-    let _enter = runtime.enter();
+    let _enter = runtime.enter()?;
 
     let channel = rodbus::client::spawn_tls_client_task(
         host_addr,
@@ -140,7 +140,7 @@ pub(crate) unsafe fn client_channel_create_tls(
 
     Ok(Box::into_raw(Box::new(ClientChannel {
         inner: FfiChannel::new(channel),
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
     })))
 }
 
@@ -247,7 +247,7 @@ pub(crate) unsafe fn client_channel_write_multiple_coils(
 ) -> Result<(), ffi::ParamError> {
     let channel = channel.as_mut().ok_or(ffi::ParamError::NullParameter)?;
     let items = items.as_ref().ok_or(ffi::ParamError::NullParameter)?;
-    let args = WriteMultiple::from(start, items.inner.clone())?;
+    let args = WriteMultiple::from_iter_checked(start, items.inner.iter().copied())?;
     let callback = sfio_promise::wrap(callback);
     channel
         .inner
@@ -264,7 +264,7 @@ pub(crate) unsafe fn client_channel_write_multiple_registers(
 ) -> Result<(), ffi::ParamError> {
     let channel = channel.as_mut().ok_or(ffi::ParamError::NullParameter)?;
     let items = items.as_ref().ok_or(ffi::ParamError::NullParameter)?;
-    let args = WriteMultiple::from(start, items.inner.clone())?;
+    let args = WriteMultiple::from_iter_checked(start, items.inner.iter().copied())?;
     let callback = sfio_promise::wrap(callback);
     channel
         .inner
@@ -303,9 +303,13 @@ impl From<ClientState> for ffi::ClientState {
             ClientState::Disabled => ffi::ClientState::Disabled,
             ClientState::Connecting => ffi::ClientState::Connecting,
             ClientState::Connected => ffi::ClientState::Connected,
-            ClientState::WaitAfterFailedConnect(_) => ffi::ClientState::WaitAfterFailedConnect,
-            ClientState::WaitAfterDisconnect(_) => ffi::ClientState::WaitAfterDisconnect,
+            ClientState::WaitAfterFailedConnect { .. } => ffi::ClientState::WaitAfterFailedConnect,
+            ClientState::WaitAfterDisconnect { .. } => ffi::ClientState::WaitAfterDisconnect,
+            ClientState::Idle => ffi::ClientState::Idle,
+            ClientState::Failed { .. } => ffi::ClientState::Failed,
             ClientState::Shutdown => ffi::ClientState::Shutdown,
+            // states added to rodbus are reported as disabled until the schema maps them
+            _ => ffi::ClientState::Disabled,
         }
     }
 }
@@ -315,9 +319,15 @@ impl From<rodbus::client::PortState> for ffi::PortState {
     fn from(x: rodbus::client::PortState) -> Self {
         match x {
             rodbus::client::PortState::Disabled => ffi::PortState::Disabled,
-            rodbus::client::PortState::Wait(_) => ffi::PortState::Wait,
+            rodbus::client::PortState::WaitAfterFailedOpen(_) => {
+                ffi::PortState::WaitAfterFailedOpen
+            }
+            rodbus::client::PortState::WaitAfterPortError(_) => ffi::PortState::WaitAfterPortError,
             rodbus::client::PortState::Open => ffi::PortState::Open,
+            rodbus::client::PortState::Reconfiguring => ffi::PortState::Reconfiguring,
             rodbus::client::PortState::Shutdown => ffi::PortState::Shutdown,
+            // states added to rodbus are reported as disabled until the schema maps them
+            _ => ffi::PortState::Disabled,
         }
     }
 }
@@ -424,9 +434,6 @@ impl From<FfiChannelError> for ParamError {
 
 impl From<ffi::RequestParam> for RequestParam {
     fn from(value: ffi::RequestParam) -> Self {
-        Self {
-            id: UnitId::new(value.unit_id),
-            response_timeout: value.timeout(),
-        }
+        Self::new(UnitId::new(value.unit_id), value.timeout())
     }
 }
@@ -1,3 +1,4 @@
+use rodbus::server::ServerSpawnError;
 use rodbus::{InvalidRange, InvalidRequest};
 use std::net::AddrParseError;
 
@@ -20,3 +21,15 @@ impl From<InvalidRequest> for ffi::ParamError {
         ffi::ParamError::InvalidRequest
     }
 }
+
+impl From<ServerSpawnError> for ffi::ParamError {
+    fn from(err: ServerSpawnError) -> Self {
+        // the error code can't carry the cause, so log it
+        tracing::error!("unable to spawn server: {}", err);
+        match err {
+            #[cfg(feature = "tls")]
+            ServerSpawnError::TlsConfig(err) => err.into(),
+            _ => ffi::ParamError::ServerBindError,
+        }
+    }
+}
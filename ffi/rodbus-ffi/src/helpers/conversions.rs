@@ -24,6 +24,8 @@ impl From<ffi::DecodeLevel> for rodbus::DecodeLevel {
                 ffi::PhysDecodeLevel::Length => rodbus::PhysDecodeLevel::Length,
                 ffi::PhysDecodeLevel::Data => rodbus::PhysDecodeLevel::Data,
             },
+            bytes: rodbus::BytesFormat::default(),
+            redact_values: false,
         }
     }
 }
@@ -40,6 +42,10 @@ impl From<rodbus::RequestError> for ffi::RequestError {
             rodbus::RequestError::Exception(ex) => ex.into(),
             rodbus::RequestError::Io(_) => ffi::RequestError::IoError,
             rodbus::RequestError::BadResponse(_) => ffi::RequestError::BadResponse,
+            rodbus::RequestError::RetriesExhausted(x) => rodbus::RequestError::from(x.cause).into(),
+            rodbus::RequestError::TooManyRequests => ffi::RequestError::TooManyRequests,
+            rodbus::RequestError::Cancelled => ffi::RequestError::Cancelled,
+            _ => ffi::RequestError::InternalError,
         }
     }
 }
@@ -123,6 +129,7 @@ impl From<ffi::SerialPortSettings> for rodbus::SerialSettings {
                 ffi::StopBits::One => rodbus::StopBits::One,
                 ffi::StopBits::Two => rodbus::StopBits::Two,
             },
+            rs485: rodbus::Rs485Direction::None,
         }
     }
 }
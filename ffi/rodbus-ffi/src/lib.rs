@@ -7,6 +7,7 @@ mod error;
 mod iterator;
 mod list;
 mod runtime;
+mod serial;
 mod server;
 mod tracing;
 
@@ -23,6 +24,7 @@ pub use database::*;
 pub use iterator::*;
 pub use list::*;
 pub use runtime::*;
+pub use serial::*;
 pub use server::*;
 use std::str::Utf8Error;
 
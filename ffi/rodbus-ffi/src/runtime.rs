@@ -1 +1,184 @@
-include!(concat!(env!("OUT_DIR"), "/runtime.rs"));
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+
+use crate::ffi;
+
+#[derive(Copy, Clone, Debug)]
+pub enum RuntimeError {
+    RuntimeDestroyed,
+    CannotBlockWithinAsync,
+    FailedToCreateRuntime,
+}
+
+pub struct Runtime {
+    inner: Option<tokio::runtime::Runtime>,
+    shutdown_timeout: Option<Duration>,
+    // number of runtime threads that have started and not stopped yet
+    running_threads: Arc<AtomicUsize>,
+}
+
+impl Runtime {
+    fn new(inner: tokio::runtime::Runtime, running_threads: Arc<AtomicUsize>) -> Self {
+        Self {
+            inner: Some(inner),
+            shutdown_timeout: None,
+            running_threads,
+        }
+    }
+
+    pub(crate) fn handle(&self) -> Result<RuntimeHandle, RuntimeError> {
+        let inner = self.inner.as_ref().ok_or(RuntimeError::RuntimeDestroyed)?;
+        Ok(RuntimeHandle {
+            inner: inner.handle().clone(),
+        })
+    }
+
+    pub(crate) fn enter(&self) -> Result<tokio::runtime::EnterGuard<'_>, RuntimeError> {
+        let inner = self.inner.as_ref().ok_or(RuntimeError::RuntimeDestroyed)?;
+        Ok(inner.enter())
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // already shut down by runtime_shutdown_with_timeout
+        let runtime = match self.inner.take() {
+            Some(x) => x,
+            None => return,
+        };
+        match self.shutdown_timeout {
+            Some(timeout) => {
+                tracing::info!("beginning runtime shutdown (timeout == {timeout:?})");
+                runtime.shutdown_timeout(timeout);
+                tracing::info!("runtime shutdown complete");
+            }
+            None => {
+                tracing::info!("beginning runtime shutdown (no timeout)");
+                drop(runtime);
+                tracing::info!("runtime shutdown complete");
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RuntimeHandle {
+    inner: Handle,
+}
+
+impl RuntimeHandle {
+    pub(crate) fn new(inner: Handle) -> Self {
+        Self { inner }
+    }
+
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> Result<F::Output, RuntimeError> {
+        if Handle::try_current().is_ok() {
+            return Err(RuntimeError::CannotBlockWithinAsync);
+        }
+        Ok(self.inner.block_on(future))
+    }
+
+    pub(crate) fn spawn<F>(&self, future: F) -> Result<(), RuntimeError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn(future);
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn runtime_create(
+    config: ffi::RuntimeConfig,
+) -> Result<*mut crate::runtime::Runtime, RuntimeError> {
+    let num_threads = if config.num_core_threads == 0 {
+        num_cpus::get()
+    } else {
+        config.num_core_threads as usize
+    };
+
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.worker_threads(num_threads).enable_all();
+
+    let prefix = config
+        .thread_name_prefix()
+        .to_str()
+        .map_err(|_| RuntimeError::FailedToCreateRuntime)?
+        .to_string();
+    if !prefix.is_empty() {
+        let next_id = AtomicUsize::new(0);
+        builder.thread_name_fn(move || {
+            let id = next_id.fetch_add(1, Ordering::Relaxed);
+            format!("{prefix}-{id}")
+        });
+    }
+
+    if config.stack_size != 0 {
+        builder.thread_stack_size(config.stack_size as usize);
+    }
+
+    let running_threads = Arc::new(AtomicUsize::new(0));
+    {
+        let running_threads = running_threads.clone();
+        builder.on_thread_start(move || {
+            running_threads.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    {
+        let running_threads = running_threads.clone();
+        builder.on_thread_stop(move || {
+            running_threads.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    tracing::info!("creating runtime with {} threads", num_threads);
+    let runtime = builder
+        .build()
+        .map_err(|_| RuntimeError::FailedToCreateRuntime)?;
+    Ok(Box::into_raw(Box::new(Runtime::new(
+        runtime,
+        running_threads,
+    ))))
+}
+
+pub(crate) unsafe fn runtime_destroy(runtime: *mut crate::runtime::Runtime) {
+    if !runtime.is_null() {
+        drop(Box::from_raw(runtime));
+    };
+}
+
+pub(crate) unsafe fn runtime_set_shutdown_timeout(instance: *mut Runtime, timeout: Duration) {
+    if let Some(rt) = instance.as_mut() {
+        rt.shutdown_timeout = Some(timeout);
+    }
+}
+
+pub(crate) unsafe fn runtime_shutdown_with_timeout(
+    instance: *mut Runtime,
+    timeout: Duration,
+) -> ffi::RuntimeShutdownStatus {
+    let rt = match instance.as_mut() {
+        Some(x) => x,
+        None => return ffi::RuntimeShutdownStatus::Complete,
+    };
+    let runtime = match rt.inner.take() {
+        Some(x) => x,
+        None => return ffi::RuntimeShutdownStatus::Complete,
+    };
+
+    tracing::info!("beginning runtime shutdown (timeout == {timeout:?})");
+    runtime.shutdown_timeout(timeout);
+    // every thread runs the stop callback before shutdown_timeout stops waiting for it
+    let remaining = rt.running_threads.load(Ordering::SeqCst);
+    if remaining == 0 {
+        tracing::info!("runtime shutdown complete");
+        ffi::RuntimeShutdownStatus::Complete
+    } else {
+        tracing::warn!("runtime shutdown timed out with {remaining} threads still running");
+        ffi::RuntimeShutdownStatus::TimedOut
+    }
+}
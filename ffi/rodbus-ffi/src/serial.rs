@@ -0,0 +1,77 @@
+use std::ffi::CString;
+
+use crate::ffi;
+
+struct PortEntry {
+    path: CString,
+    product: CString,
+    usb: Option<(u16, u16)>,
+}
+
+pub struct SerialPortInfoIterator<'a> {
+    ports: Vec<PortEntry>,
+    next: usize,
+    current: ffi::SerialPortInfo,
+    _lifetime: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> SerialPortInfoIterator<'a> {
+    #[cfg(feature = "serial")]
+    fn new(ports: Vec<rodbus::SerialPortInfo>) -> Self {
+        let ports = ports
+            .into_iter()
+            .map(|port| PortEntry {
+                path: CString::new(port.path).unwrap_or_default(),
+                product: CString::new(port.product.unwrap_or_default()).unwrap_or_default(),
+                usb: port.usb_vid.zip(port.usb_pid),
+            })
+            .collect();
+
+        Self {
+            ports,
+            next: 0,
+            current: ffi::SerialPortInfo {
+                path: std::ptr::null(),
+                is_usb: false,
+                usb_vid: 0,
+                usb_pid: 0,
+                product: std::ptr::null(),
+            },
+            _lifetime: std::marker::PhantomData,
+        }
+    }
+}
+
+pub(crate) unsafe fn serial_port_info_iterator_next(
+    it: *mut crate::SerialPortInfoIterator<'_>,
+) -> Option<&ffi::SerialPortInfo> {
+    let it = it.as_mut()?;
+    let entry = it.ports.get(it.next)?;
+    it.next += 1;
+    it.current.path = entry.path.as_ptr();
+    it.current.is_usb = entry.usb.is_some();
+    it.current.usb_vid = entry.usb.map(|(vid, _)| vid).unwrap_or(0);
+    it.current.usb_pid = entry.usb.map(|(_, pid)| pid).unwrap_or(0);
+    it.current.product = entry.product.as_ptr();
+    Some(&it.current)
+}
+
+#[cfg(not(feature = "serial"))]
+pub(crate) fn serial_port_list(
+    _callback: ffi::SerialPortListCallback,
+) -> Result<(), ffi::ParamError> {
+    Err(ffi::ParamError::NoSupport)
+}
+
+#[cfg(feature = "serial")]
+pub(crate) fn serial_port_list(
+    callback: ffi::SerialPortListCallback,
+) -> Result<(), ffi::ParamError> {
+    let ports = rodbus::available_ports().map_err(|err| {
+        tracing::warn!("unable to enumerate serial ports: {}", err);
+        ffi::ParamError::SerialPortEnumerationFailure
+    })?;
+    let mut iter = SerialPortInfoIterator::new(ports);
+    callback.on_ports(&mut iter);
+    Ok(())
+}
@@ -299,14 +299,11 @@ pub(crate) unsafe fn server_create_tcp(
         decode_level.into(),
     );
 
-    let handle = runtime
-        .handle()
-        .block_on(create_server)?
-        .map_err(|_| ffi::ParamError::ServerBindError)?;
+    let handle = runtime.handle()?.block_on(create_server)??;
 
     let server_handle = Server {
         inner: handle,
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
         map: handler_map,
     };
 
@@ -339,10 +336,10 @@ pub(crate) unsafe fn server_create_rtu(
     let handler_map = endpoints.drain_and_convert();
 
     // enter the runtime context so we can spawn
-    let _enter = runtime.enter();
+    let _enter = runtime.enter()?;
 
     let handle = rodbus::server::spawn_rtu_server_task(
-        &path.to_string_lossy(),
+        path.to_string_lossy().as_ref(),
         serial_params.into(),
         retry.into(),
         handler_map.clone(),
@@ -352,7 +349,7 @@ pub(crate) unsafe fn server_create_rtu(
 
     let server_handle = Server {
         inner: handle,
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
         map: handler_map,
     };
 
@@ -476,10 +473,7 @@ pub(crate) unsafe fn server_create_tls_impl(
                 decode_level.into(),
             );
 
-            runtime
-                .handle()
-                .block_on(create_server)?
-                .map_err(|_| ffi::ParamError::ServerBindError)?
+            runtime.handle()?.block_on(create_server)??
         }
         None => {
             let create_server = rodbus::server::spawn_tls_server_task(
@@ -491,16 +485,13 @@ pub(crate) unsafe fn server_create_tls_impl(
                 decode_level.into(),
             );
 
-            runtime
-                .handle()
-                .block_on(create_server)?
-                .map_err(|_| ffi::ParamError::ServerBindError)?
+            runtime.handle()?.block_on(create_server)??
         }
     };
 
     let server_handle = Server {
         inner: handle,
-        runtime: runtime.handle(),
+        runtime: runtime.handle()?,
         map: handler_map,
     };
 
@@ -543,28 +534,60 @@ pub(crate) unsafe fn server_set_decode_level(
     Ok(())
 }
 
-pub enum AddressFilter {
+pub(crate) unsafe fn server_set_address_filter(
+    server: *mut crate::Server,
+    filter: *mut crate::AddressFilter,
+    disconnect: bool,
+) -> Result<(), ffi::ParamError> {
+    let server = server.as_mut().ok_or(ffi::ParamError::NullParameter)?;
+    let filter = filter.as_ref().ok_or(ffi::ParamError::NullParameter)?;
+    let filter = filter.into();
+    let result = if disconnect {
+        server
+            .runtime
+            .block_on(server.inner.set_address_filter_and_disconnect(filter))
+    } else {
+        server
+            .runtime
+            .block_on(server.inner.set_address_filter(filter))
+    };
+    result??;
+    Ok(())
+}
+
+pub struct AddressFilter {
+    allow: AllowFilter,
+    deny: Vec<IpSubnet>,
+}
+
+enum AllowFilter {
     Any,
     WildcardIpv4(WildcardIPv4),
-    AnyOf(std::collections::HashSet<std::net::IpAddr>),
+    AnyOf(Vec<IpSubnet>),
 }
 
 pub fn address_filter_any() -> *mut AddressFilter {
-    Box::into_raw(Box::new(AddressFilter::Any))
+    Box::into_raw(Box::new(AddressFilter {
+        allow: AllowFilter::Any,
+        deny: Vec::new(),
+    }))
 }
 
-fn parse_address_filter(s: &str) -> Result<AddressFilter, ffi::ParamError> {
-    // first try to parse it as a normal IP
+fn parse_subnet(s: &str) -> Result<IpSubnet, ffi::ParamError> {
+    // first try to parse it as a normal IP, then as a subnet in CIDR notation
     match s.parse::<IpAddr>() {
-        Ok(x) => {
-            let mut set = std::collections::HashSet::new();
-            set.insert(x);
-            Ok(AddressFilter::AnyOf(set))
-        }
+        Ok(x) => Ok(IpSubnet::host(x)),
+        Err(_) => Ok(s.parse()?),
+    }
+}
+
+fn parse_allow_filter(s: &str) -> Result<AllowFilter, ffi::ParamError> {
+    match parse_subnet(s) {
+        Ok(x) => Ok(AllowFilter::AnyOf(vec![x])),
         Err(_) => {
             // now try to parse as a wildcard
             let wc: WildcardIPv4 = s.parse()?;
-            Ok(AddressFilter::WildcardIpv4(wc))
+            Ok(AllowFilter::WildcardIpv4(wc))
         }
     }
 }
@@ -575,9 +598,18 @@ impl From<BadIpv4Wildcard> for ffi::ParamError {
     }
 }
 
+impl From<BadIpSubnet> for ffi::ParamError {
+    fn from(_: BadIpSubnet) -> Self {
+        ffi::ParamError::InvalidIpAddress
+    }
+}
+
 pub fn address_filter_create(address: &CStr) -> Result<*mut AddressFilter, ffi::ParamError> {
-    let address = parse_address_filter(address.to_string_lossy().as_ref())?;
-    Ok(Box::into_raw(Box::new(address)))
+    let allow = parse_allow_filter(address.to_string_lossy().as_ref())?;
+    Ok(Box::into_raw(Box::new(AddressFilter {
+        allow,
+        deny: Vec::new(),
+    })))
 }
 
 pub unsafe fn address_filter_add(
@@ -587,17 +619,17 @@ pub unsafe fn address_filter_add(
     let address_filter = address_filter
         .as_mut()
         .ok_or(ffi::ParamError::NullParameter)?;
-    let address = address.to_string_lossy().parse()?;
+    let address = parse_subnet(address.to_string_lossy().as_ref())?;
 
-    match address_filter {
-        AddressFilter::Any => {
+    match &mut address_filter.allow {
+        AllowFilter::Any => {
             // can't add addresses to an "any" specification
             return Err(ffi::ParamError::InvalidIpAddress);
         }
-        AddressFilter::AnyOf(set) => {
-            set.insert(address);
+        AllowFilter::AnyOf(list) => {
+            list.push(address);
         }
-        AddressFilter::WildcardIpv4(_) => {
+        AllowFilter::WildcardIpv4(_) => {
             // can't add addresses to a wildcard specification
             return Err(ffi::ParamError::InvalidIpAddress);
         }
@@ -605,6 +637,18 @@ pub unsafe fn address_filter_add(
     Ok(())
 }
 
+pub unsafe fn address_filter_deny(
+    address_filter: *mut AddressFilter,
+    address: &CStr,
+) -> Result<(), ffi::ParamError> {
+    let address_filter = address_filter
+        .as_mut()
+        .ok_or(ffi::ParamError::NullParameter)?;
+    let address = parse_subnet(address.to_string_lossy().as_ref())?;
+    address_filter.deny.push(address);
+    Ok(())
+}
+
 pub unsafe fn address_filter_destroy(address_filter: *mut AddressFilter) {
     if !address_filter.is_null() {
         drop(Box::from_raw(address_filter));
@@ -613,10 +657,19 @@ pub unsafe fn address_filter_destroy(address_filter: *mut AddressFilter) {
 
 impl From<&AddressFilter> for rodbus::server::AddressFilter {
     fn from(from: &AddressFilter) -> Self {
-        match from {
-            AddressFilter::Any => rodbus::server::AddressFilter::Any,
-            AddressFilter::AnyOf(set) => rodbus::server::AddressFilter::AnyOf(set.clone()),
-            AddressFilter::WildcardIpv4(wc) => rodbus::server::AddressFilter::WildcardIpv4(*wc),
+        let allow = match &from.allow {
+            AllowFilter::Any => rodbus::server::AddressFilter::Any,
+            AllowFilter::AnyOf(list) => rodbus::server::AddressFilter::AnyOfSubnets(list.clone()),
+            AllowFilter::WildcardIpv4(wc) => rodbus::server::AddressFilter::WildcardIpv4(*wc),
+        };
+
+        if from.deny.is_empty() {
+            allow
+        } else {
+            rodbus::server::AddressFilter::Deny {
+                deny: from.deny.clone(),
+                allow: Box::new(allow),
+            }
         }
     }
 }
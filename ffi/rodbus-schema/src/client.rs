@@ -279,8 +279,19 @@ fn define_port_state_listener(lib: &mut LibraryBuilder) -> BackTraced<Asynchrono
     let port_state = lib
         .define_enum("port_state")?
         .push("disabled", "Disabled until enabled")?
-        .push("wait", "Waiting to perform an open retry")?
+        .push(
+            "wait_after_failed_open",
+            "Failed to open the port, waiting to retry",
+        )?
+        .push(
+            "wait_after_port_error",
+            "The open port failed and was closed, waiting to re-open it",
+        )?
         .push("open", "Port is open")?
+        .push(
+            "reconfiguring",
+            "The port was closed to apply new settings and is about to be re-opened",
+        )?
         .push("shutdown", "Task has been shut down")?
         .doc(
             doc("State of the serial port.")
@@ -318,6 +329,14 @@ fn define_tcp_client_state_listener(lib: &mut LibraryBuilder) -> BackTraced<Asyn
             "wait_after_disconnect",
             "Client was disconnected, waiting before retrying",
         )?
+        .push(
+            "idle",
+            "Client closed the connection due to inactivity and will reconnect on the next request",
+        )?
+        .push(
+            "failed",
+            "Client gave up after reaching the maximum number of connection attempts",
+        )?
         .push("shutdown", "Client is shutting down")?
         .doc(
             doc("State of the client connection.")
@@ -31,7 +31,7 @@ impl CommonDefinitions {
             error_type: error_type.clone(),
             nothing,
             decode_level,
-            runtime_handle: sfio_tokio_ffi::define(lib, error_type)?,
+            runtime_handle: crate::runtime::define(lib, error_type)?,
             error_info: build_request_error(lib)?,
             address_range: build_address_range(lib)?,
             request_param: build_request_param(lib)?,
@@ -130,6 +130,10 @@ fn build_error_type(lib: &mut LibraryBuilder) -> BackTraced<ErrorTypeHandle> {
             "too_many_requests",
             "Number of requests exceeds configured limit",
         )?
+        .add_error(
+            "serial_port_enumeration_failure",
+            "Failed to enumerate the serial ports on the system",
+        )?
         .doc("Error type that indicates a bad parameter or bad programmer logic")?
         .build()?;
 
@@ -261,6 +265,17 @@ fn build_request_error(lib: &mut LibraryBuilder) -> BackTraced<ErrorTypeHandle>
         builder = builder.add_error(format!("modbus_exception_{name}"), desc)?;
     }
 
+    // added after the exceptions so that the values of the existing errors don't change
+    builder = builder
+        .add_error(
+            "too_many_requests",
+            "The request queue of the channel was full, see the max_queued_requests argument of the channel",
+        )?
+        .add_error(
+            "cancelled",
+            "The request was removed from the queue of the channel before it was sent",
+        )?;
+
     let definition = builder.build()?;
 
     Ok(definition)
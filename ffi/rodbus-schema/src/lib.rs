@@ -8,6 +8,8 @@ use oo_bindgen::model::*;
 mod client;
 mod common;
 mod decoding;
+mod runtime;
+mod serial;
 mod server;
 
 // derived from Cargo.toml
@@ -70,6 +72,7 @@ pub fn build_lib() -> BackTraced<Library> {
 
     client::build(&mut builder, &common)?;
     server::build(&mut builder, &common)?;
+    serial::build(&mut builder, &common)?;
 
     let library = builder.build()?;
 
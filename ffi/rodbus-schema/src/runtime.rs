@@ -0,0 +1,127 @@
+use oo_bindgen::model::*;
+
+/// Define the runtime class and return a handle to it
+///
+/// This extends the runtime of `sfio-tokio-ffi` with thread options and a bounded shutdown.
+pub(crate) fn define(
+    lib: &mut LibraryBuilder,
+    error_type: ErrorTypeHandle,
+) -> BackTraced<ClassDeclarationHandle> {
+    // Forward declare the class
+    let runtime = lib.declare_class("runtime")?;
+
+    let config_struct = define_runtime_config(lib)?;
+    let shutdown_status = define_shutdown_status(lib)?;
+
+    let constructor = lib
+        .define_constructor(runtime.clone())?
+        .param("config", config_struct, "Runtime configuration")?
+        .fails_with(error_type)?
+        .doc(
+            doc("Creates a new runtime for running the protocol stack.")
+                .warning("The runtime should be kept alive for as long as it's needed and it should be released with {class:runtime.[destructor]}")
+        )?
+        .build()?;
+
+    let destructor = lib.define_destructor(
+        runtime.clone(),
+        doc("Destroy a runtime.")
+            .details("This method will gracefully wait for all asynchronous operation to end before returning"),
+    )?;
+
+    let set_shutdown_timeout = lib
+        .define_method("set_shutdown_timeout", runtime.clone())?
+        .doc(
+            doc("By default, when the runtime shuts down, it does so without a timeout and waits indefinitely for all spawned tasks to yield.")
+                .details("Setting this value will put a maximum time bound on the eventual shutdown. Threads that have not exited within this timeout are not terminated, they are detached and keep running until the process exits.")
+                .warning("This can leak memory. This method should only be used if the the entire application is being shut down so that memory can be cleaned up by the OS.")
+        )?
+        .param(
+            "timeout",
+            BasicType::Duration(DurationType::Seconds),
+            "Maximum number of seconds to wait for the runtime to shut down",
+        )?
+        .build()?;
+
+    let shutdown_with_timeout = lib
+        .define_method("shutdown_with_timeout", runtime.clone())?
+        .doc(
+            doc("Shut down the runtime, waiting at most for the specified timeout for the threads to exit.")
+                .details("Objects created on the runtime stop running and methods that require the runtime fail with {enum:param_error.runtime_destroyed}. The runtime must still be released with {class:runtime.[destructor]}, which then returns immediately.")
+                .warning("Threads that have not exited within the timeout are leaked. This method should only be used if the entire application is being shut down.")
+        )?
+        .param(
+            "timeout",
+            BasicType::Duration(DurationType::Milliseconds),
+            "Maximum number of milliseconds to wait for the threads to exit",
+        )?
+        .returns(shutdown_status, "Whether every thread exited before the timeout")?
+        .build()?;
+
+    let runtime = lib
+        .define_class(&runtime)?
+        .constructor(constructor)?
+        .destructor(destructor)?
+        .method(set_shutdown_timeout)?
+        .method(shutdown_with_timeout)?
+        .custom_destroy("shutdown")?
+        .doc("Handle to the underlying runtime")?
+        .build()?;
+
+    Ok(runtime.declaration())
+}
+
+fn define_runtime_config(lib: &mut LibraryBuilder) -> BackTraced<FunctionArgStructHandle> {
+    let num_core_threads = Name::create("num_core_threads")?;
+    let thread_name_prefix = Name::create("thread_name_prefix")?;
+    let stack_size = Name::create("stack_size")?;
+
+    let config_struct = lib.declare_function_argument_struct("runtime_config")?;
+    let config_struct = lib
+        .define_function_argument_struct(config_struct)?
+        .add(
+            &num_core_threads,
+            Primitive::U16,
+            doc("Number of runtime threads to spawn. For a guess of the number of CPU cores, use 0.")
+                .details("Even if tons of connections are expected, it is preferred to use a value around the number of CPU cores for better performances. The library uses an efficient thread pool polling mechanism."),
+        )?
+        .add(
+            &thread_name_prefix,
+            StringType,
+            doc("Prefix of the names of the runtime threads, followed by a dash and the number of the thread.")
+                .details("An empty prefix keeps the default name of the threads. Linux truncates thread names to 15 characters."),
+        )?
+        .add(
+            &stack_size,
+            Primitive::U32,
+            doc("Stack size of each runtime thread in bytes. Use 0 for the default of 2 MiB."),
+        )?
+        .doc("Runtime configuration")?
+        .end_fields()?
+        .begin_initializer(
+            "init",
+            InitializerType::Normal,
+            "Initialize the configuration to default values",
+        )?
+        .default(&num_core_threads, NumberValue::U16(0))?
+        .default_string(&thread_name_prefix, "rodbus")?
+        .default(&stack_size, NumberValue::U32(0))?
+        .end_initializer()?
+        .build()?;
+
+    Ok(config_struct)
+}
+
+fn define_shutdown_status(lib: &mut LibraryBuilder) -> BackTraced<EnumHandle> {
+    let status = lib
+        .define_enum("runtime_shutdown_status")?
+        .push("complete", "Every thread exited before the timeout")?
+        .push(
+            "timed_out",
+            "Some threads were still running when the timeout elapsed and were leaked",
+        )?
+        .doc("Result of {class:runtime.shutdown_with_timeout()}")?
+        .build()?;
+
+    Ok(status)
+}
@@ -0,0 +1,75 @@
+use crate::common::CommonDefinitions;
+use oo_bindgen::model::*;
+
+pub(crate) fn build(lib: &mut LibraryBuilder, common: &CommonDefinitions) -> BackTraced<()> {
+    let port_info = lib.declare_universal_struct("serial_port_info")?;
+    let port_info = lib
+        .define_universal_struct(port_info)?
+        .add(
+            "path",
+            StringType,
+            "Path or name of the port, e.g. /dev/ttyUSB0 or COM3",
+        )?
+        .add(
+            "is_usb",
+            Primitive::Bool,
+            "True if the port is a USB device, in which case the USB fields are populated",
+        )?
+        .add(
+            "usb_vid",
+            Primitive::U16,
+            "USB vendor id, 0 if not a USB device",
+        )?
+        .add(
+            "usb_pid",
+            Primitive::U16,
+            "USB product id, 0 if not a USB device",
+        )?
+        .add(
+            "product",
+            StringType,
+            "USB product string, empty if not a USB device or if the device doesn't report one",
+        )?
+        .doc("Information about a serial port present on the system")?
+        .end_fields()?
+        .build()?;
+
+    let port_info_iterator =
+        lib.define_iterator_with_lifetime("serial_port_info_iterator", port_info)?;
+
+    let callback = lib
+        .define_interface(
+            "serial_port_list_callback",
+            "Callback that receives the serial ports present on the system",
+        )?
+        .begin_callback("on_ports", "Called once with the list of serial ports")?
+        .param(
+            "ports",
+            port_info_iterator,
+            "Iterator over the serial ports, sorted by path",
+        )?
+        .enable_functional_transform()
+        .end_callback()?
+        .build_sync()?;
+
+    let list_fn = lib
+        .define_function("serial_port_list")?
+        .param(
+            "callback",
+            callback,
+            "Callback invoked synchronously with the list of ports",
+        )?
+        .fails_with(common.error_type.clone())?
+        .doc(
+            doc("Enumerate the serial ports currently present on the system")
+                .details("The iterator is only valid for the duration of the callback."),
+        )?
+        .build_static("list")?;
+
+    lib.define_static_class("serial_port")?
+        .static_method(list_fn)?
+        .doc("Serial port utilities")?
+        .build()?;
+
+    Ok(())
+}
@@ -188,6 +188,25 @@ pub(crate) fn build_server(
         .doc("Set the decoding level for the server")?
         .build()?;
 
+    let set_address_filter_fn = lib
+        .define_method("set_address_filter", server.clone())?
+        .param(
+            "filter",
+            address_filter.declaration(),
+            "Filter applied to future connections",
+        )?
+        .param(
+            "disconnect",
+            Primitive::Bool,
+            "If true, also close existing sessions whose peer no longer matches the filter",
+        )?
+        .fails_with(common.error_type.clone())?
+        .doc(
+            doc("Replace the address filter of a TCP or TLS server without restarting it")
+                .details("Has no effect on RTU servers"),
+        )?
+        .build()?;
+
     let server = lib.define_class(&server)?
         .static_method(tcp_constructor)?
         .static_method(rtu_constructor)?
@@ -195,6 +214,7 @@ pub(crate) fn build_server(
         .static_method(tls_constructor_raw)?
         .method(update_fn)?
         .method(set_decode_level_fn)?
+        .method(set_address_filter_fn)?
         .destructor(destructor)?
         .custom_destroy("shutdown")?
         .doc("Handle to the running server. The server runs on a background task until this class is destroyed.")?
@@ -737,18 +757,28 @@ fn define_address_filter(
         .fails_with(common.error_type.clone())?
         .doc(
             doc("Create an address filter that matches one or more IP addresses. Ipv4 or IPv6 addresses are allowed.")
-                .details("Examples: 192.168.1.26, 192.168.0.*, *.*.*.*")
-                .details("Wildcards are only supported for IPv4 addresses"),
+                .details("Examples: 192.168.1.26, 192.168.0.*, *.*.*.*, 10.0.0.0/8, fe80::/10")
+                .details("Wildcards are only supported for IPv4 addresses. Subnets in CIDR notation are supported for both IPv4 and IPv6."),
         )?
         .build()?;
 
     let add = lib
         .define_method("add", address_filter.clone())?
-        .param("address", StringType, "IP address to add")?
+        .param("address", StringType, "IP address or subnet to add")?
+        .fails_with(common.error_type.clone())?
+        .doc(
+            doc("Add an allowed IP address or subnet (e.g. 10.0.0.0/8) to the filter")
+                .details("This function may only be called if the AddressFilter was initially constructed with a single static address or subnet")
+        )?
+        .build()?;
+
+    let deny = lib
+        .define_method("deny", address_filter.clone())?
+        .param("address", StringType, "IP address or subnet to deny")?
         .fails_with(common.error_type.clone())?
         .doc(
-            doc("Add an allowed IP address to the filter")
-                .details("This function may only be called if the AddressFilter was initially constructed with a single static address")
+            doc("Reject connections from an IP address or subnet (e.g. fe80::/10)")
+                .details("Denied addresses are evaluated before the allowed addresses and may be added to any filter, including one that accepts any address")
         )?
         .build()?;
 
@@ -760,6 +790,7 @@ fn define_address_filter(
         .destructor(destructor)?
         .static_method(address_filter_any_fn)?
         .method(add)?
+        .method(deny)?
         .doc("Filter used to restrict which IP addresses may communicate with a server")?
         .build()?;
 
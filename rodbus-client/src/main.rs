@@ -1,12 +1,14 @@
 //! Command-line Modbus client
 
+use std::collections::BTreeMap;
 use std::fmt::Formatter;
 use std::net::{AddrParseError, SocketAddr};
 use std::num::ParseIntError;
 use std::str::{FromStr, ParseBoolError};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use clap::{App, Arg, ArgMatches, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use rustyline::error::ReadlineError;
 
 use rodbus::client::*;
 use rodbus::*;
@@ -19,6 +21,9 @@ enum Error {
     BadInt(std::num::ParseIntError),
     BadBool(std::str::ParseBoolError),
     BadCharInBitString(char),
+    BadProbe(String),
+    BadUnitIdRange(u8, u8),
+    BadDecodeLevel(String),
     Request(rodbus::RequestError),
     MissingSubCommand,
     Shutdown,
@@ -33,6 +38,52 @@ enum Command {
     WriteSingleCoil(Indexed<bool>),
     WriteMultipleCoils(WriteMultiple<bool>),
     WriteMultipleRegisters(WriteMultiple<u16>),
+    Scan(Scan),
+    Repl,
+}
+
+/// Request used to find out if a unit id responds
+#[derive(Copy, Clone)]
+enum Probe {
+    Coils(AddressRange),
+    DiscreteInputs(AddressRange),
+    HoldingRegisters(AddressRange),
+    InputRegisters(AddressRange),
+}
+
+struct Scan {
+    first: u8,
+    last: u8,
+    probe: Probe,
+    timeout: Duration,
+}
+
+/// How a unit id answered a probe
+enum ProbeResult {
+    Data,
+    Exception(ExceptionCode),
+    InvalidResponse,
+}
+
+#[derive(Copy, Clone)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Value {
+    Bit(bool),
+    Register(u16),
+}
+
+/// Prints the values returned by the read requests of each poll
+struct Output {
+    format: Format,
+    only_changes: bool,
+    // values of the previous poll, None before the first poll
+    previous: Option<BTreeMap<u16, Value>>,
 }
 
 struct Args {
@@ -40,6 +91,8 @@ struct Args {
     id: UnitId,
     command: Command,
     period: Option<Duration>,
+    count: Option<usize>,
+    output: Output,
 }
 
 struct ConnectionListener {
@@ -63,17 +116,112 @@ impl Listener<ClientState> for ConnectionListener {
     }
 }
 
+/// Logs the addresses of each connection, e.g. to troubleshoot NAT or policy routing
+struct ConnectionInfoLogger;
+
+impl Listener<ConnectionInfo> for ConnectionInfoLogger {
+    fn update(&mut self, info: ConnectionInfo) -> MaybeAsync<()> {
+        tracing::info!("connection: {info}");
+        MaybeAsync::ready(())
+    }
+}
+
 impl Args {
-    fn new(address: SocketAddr, id: UnitId, command: Command, period: Option<Duration>) -> Self {
+    fn new(
+        address: SocketAddr,
+        id: UnitId,
+        command: Command,
+        period: Option<Duration>,
+        count: Option<usize>,
+        output: Output,
+    ) -> Self {
         Self {
             address,
             id,
             command,
             period,
+            count,
+            output,
         }
     }
 }
 
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Value::Bit(x) => write!(f, "{x}"),
+            Value::Register(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl Output {
+    fn new(format: Format, only_changes: bool) -> Self {
+        Self {
+            format,
+            only_changes,
+            previous: None,
+        }
+    }
+
+    fn print(&mut self, values: Vec<(u16, Value)>) {
+        let first = self.previous.is_none();
+        let previous = self.previous.get_or_insert_with(BTreeMap::new);
+        let only_changes = self.only_changes;
+        let values: Vec<(u16, Value)> = values
+            .into_iter()
+            .filter(|(index, value)| {
+                let changed = previous.insert(*index, *value) != Some(*value);
+                changed || !only_changes
+            })
+            .collect();
+
+        if values.is_empty() {
+            return;
+        }
+
+        let timestamp = timestamp();
+        match self.format {
+            Format::Table => {
+                for (index, value) in values {
+                    if only_changes {
+                        println!("{timestamp} index: {index} value: {value}")
+                    } else {
+                        println!("index: {index} value: {value}")
+                    }
+                }
+            }
+            Format::Csv => {
+                if first {
+                    println!("timestamp,index,value");
+                }
+                for (index, value) in values {
+                    println!("{timestamp},{index},{value}")
+                }
+            }
+            Format::Json => {
+                // keys are indices and values are numbers or booleans, nothing needs escaping
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(index, value)| format!("\"{index}\":{value}"))
+                    .collect();
+                println!(
+                    "{{\"timestamp\":{timestamp},\"values\":{{{}}}}}",
+                    values.join(",")
+                );
+            }
+        }
+    }
+}
+
+/// Seconds since the UNIX epoch with millisecond resolution
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
@@ -94,13 +242,19 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     let (listener, mut rx) = ConnectionListener::create();
 
-    let mut channel = spawn_tcp_client_task(
-        HostAddr::ip(args.address.ip(), args.address.port()),
-        1,
-        default_retry_strategy(),
-        AppDecodeLevel::DataValues.into(),
-        Some(Box::new(listener)),
-    );
+    // logging every probe would hide the table of results
+    let decode = match args.command {
+        Command::Scan(_) => DecodeLevel::nothing(),
+        _ => AppDecodeLevel::DataValues.into(),
+    };
+
+    let mut channel =
+        ClientChannelConfig::tcp(HostAddr::ip(args.address.ip(), args.address.port()))
+            .max_queued_requests(1)
+            .decode(decode)
+            .listener(Box::new(listener))
+            .connection_listener(Box::new(ConnectionInfoLogger))
+            .spawn();
     channel.enable().await?;
 
     'connect: loop {
@@ -109,18 +263,36 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         match state {
             ClientState::Disabled | ClientState::Connecting => {}
             ClientState::Connected => break 'connect,
+            ClientState::WaitAfterFailedConnect { reason, .. } | ClientState::Failed { reason } => {
+                return Err(format!("unable to connect: {reason}").into())
+            }
+            ClientState::WaitAfterDisconnect { reason, .. } => {
+                return Err(format!("disconnected: {reason}").into())
+            }
             _ => return Err("unable to connect".into()),
         }
     }
 
     let params = RequestParam::new(args.id, Duration::from_secs(1));
+    let mut output = args.output;
+
+    if let Command::Repl = args.command {
+        return run_repl(&mut channel, params).await;
+    }
 
     match args.period {
-        None => run_command(&args.command, &mut channel, params).await,
-        Some(period) => loop {
-            run_command(&args.command, &mut channel, params).await?;
-            tokio::time::sleep(period).await
-        },
+        None => run_command(&args.command, &mut channel, params, &mut output).await,
+        Some(period) => {
+            let mut polls = 0;
+            loop {
+                run_command(&args.command, &mut channel, params, &mut output).await?;
+                polls += 1;
+                if args.count.is_some_and(|count| polls >= count) {
+                    return Ok(());
+                }
+                tokio::time::sleep(period).await
+            }
+        }
     }
 }
 
@@ -128,27 +300,24 @@ async fn run_command(
     command: &Command,
     channel: &mut Channel,
     params: RequestParam,
+    output: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match command {
         Command::ReadCoils(range) => {
-            for x in channel.read_coils(params, *range).await? {
-                println!("index: {} value: {}", x.index, x.value)
-            }
+            let values = channel.read_coils(params, *range).await?;
+            output.print(bits(values));
         }
         Command::ReadDiscreteInputs(range) => {
-            for x in channel.read_discrete_inputs(params, *range).await? {
-                println!("index: {} value: {}", x.index, x.value)
-            }
+            let values = channel.read_discrete_inputs(params, *range).await?;
+            output.print(bits(values));
         }
         Command::ReadHoldingRegisters(range) => {
-            for x in channel.read_holding_registers(params, *range).await? {
-                println!("index: {} value: {}", x.index, x.value)
-            }
+            let values = channel.read_holding_registers(params, *range).await?;
+            output.print(registers(values));
         }
         Command::ReadInputRegisters(range) => {
-            for x in channel.read_input_registers(params, *range).await? {
-                println!("index: {} value: {}", x.index, x.value)
-            }
+            let values = channel.read_input_registers(params, *range).await?;
+            output.print(registers(values));
         }
         Command::WriteSingleRegister(arg) => {
             channel.write_single_register(params, *arg).await?;
@@ -164,10 +333,187 @@ async fn run_command(
                 .write_multiple_registers(params, arg.clone())
                 .await?;
         }
+        Command::Scan(scan) => run_scan(scan, channel).await?,
+        Command::Repl => return Err("the REPL is already running".into()),
     }
     Ok(())
 }
 
+const REPL_HELP: &str = "\
+commands:
+  rc, rdi, rhr, rir, wsc, wsr, wmc, wmr, scan   send a request, e.g. `rhr -s 0 -q 10`
+  unit <id>                                     change the unit id
+  timeout <ms>                                  change the response timeout
+  decode <nothing|function|headers|data>        change the decode level
+  help                                          print this message
+  exit                                          close the channel and exit
+
+Ctrl-C cancels the request in progress, Ctrl-D exits.";
+
+async fn run_repl(
+    channel: &mut Channel,
+    mut params: RequestParam,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = rustyline::DefaultEditor::new()?;
+    println!("type `help` for the list of commands");
+    loop {
+        // reading a line blocks the thread, so the editor is moved to a blocking task and back
+        let (line, returned) = tokio::task::spawn_blocking(move || {
+            let line = editor.readline("> ");
+            (line, editor)
+        })
+        .await?;
+        editor = returned;
+
+        let line = match line {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        if let ["exit"] | ["quit"] = words.as_slice() {
+            return Ok(());
+        }
+        if let Err(err) = run_repl_line(&words, channel, &mut params).await {
+            println!("error: {err}");
+        }
+    }
+}
+
+async fn run_repl_line(
+    words: &[&str],
+    channel: &mut Channel,
+    params: &mut RequestParam,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match words {
+        ["help"] => println!("{REPL_HELP}"),
+        ["unit", id] => params.id = UnitId::new(u8::from_str(id)?),
+        ["timeout", ms] => params.response_timeout = get_period_ms(ms)?,
+        ["decode", level] => {
+            let level = get_decode_level(level)?;
+            channel.set_decode_level(level.into()).await?;
+        }
+        _ => {
+            let matches = match App::new("rodbus-client")
+                .setting(AppSettings::NoBinaryName)
+                .setting(AppSettings::SubcommandRequired)
+                .subcommands(request_subcommands())
+                .subcommand(
+                    SubCommand::with_name("repl")
+                        .about("open the channel once and read commands from the console"),
+                )
+                .get_matches_from_safe(words.iter().copied())
+            {
+                Ok(matches) => matches,
+                Err(err)
+                    if err.kind == clap::ErrorKind::HelpDisplayed
+                        || err.kind == clap::ErrorKind::VersionDisplayed =>
+                {
+                    println!("{}", err.message);
+                    return Ok(());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let command = get_command(&matches)?;
+            let mut output = Output::new(Format::Table, false);
+            tokio::select! {
+                result = run_command(&command, channel, *params, &mut output) => result?,
+                _ = tokio::signal::ctrl_c() => println!("request cancelled"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_decode_level(level: &str) -> Result<AppDecodeLevel, Error> {
+    match level {
+        "nothing" => Ok(AppDecodeLevel::Nothing),
+        "function" => Ok(AppDecodeLevel::FunctionCode),
+        "headers" => Ok(AppDecodeLevel::DataHeaders),
+        "data" => Ok(AppDecodeLevel::DataValues),
+        other => Err(Error::BadDecodeLevel(other.to_string())),
+    }
+}
+
+fn bits(values: Vec<Indexed<bool>>) -> Vec<(u16, Value)> {
+    values
+        .into_iter()
+        .map(|x| (x.index, Value::Bit(x.value)))
+        .collect()
+}
+
+fn registers(values: Vec<Indexed<u16>>) -> Vec<(u16, Value)> {
+    values
+        .into_iter()
+        .map(|x| (x.index, Value::Register(x.value)))
+        .collect()
+}
+
+async fn run_scan(scan: &Scan, channel: &mut Channel) -> Result<(), Box<dyn std::error::Error>> {
+    println!("unit id | rtt (ms) | result");
+    let mut found = 0;
+    for id in scan.first..=scan.last {
+        let params = RequestParam::new(UnitId::new(id), scan.timeout);
+        let start = Instant::now();
+        let result = match probe(scan.probe, channel, params).await {
+            Ok(()) => ProbeResult::Data,
+            // a gateway answers for devices that don't respond
+            Err(RequestError::Exception(
+                ExceptionCode::GatewayTargetDeviceFailedToRespond
+                | ExceptionCode::GatewayPathUnavailable,
+            ))
+            | Err(RequestError::ResponseTimeout) => continue,
+            // an exception still proves that the device exists
+            Err(RequestError::Exception(ex)) => ProbeResult::Exception(ex),
+            // something answered, e.g. two devices with the same id on a serial bus
+            Err(RequestError::BadFrame(_)) | Err(RequestError::BadResponse(_)) => {
+                ProbeResult::InvalidResponse
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let rtt = start.elapsed().as_secs_f64() * 1000.0;
+        let result = match result {
+            ProbeResult::Data => "data".to_string(),
+            ProbeResult::Exception(ex) => format!("exception: {ex:?}"),
+            ProbeResult::InvalidResponse => "invalid response".to_string(),
+        };
+        println!("{id:>7} | {rtt:>8.1} | {result}");
+        found += 1;
+    }
+    println!(
+        "{found} of {} unit ids responded",
+        scan.last as usize - scan.first as usize + 1
+    );
+    Ok(())
+}
+
+async fn probe(
+    probe: Probe,
+    channel: &mut Channel,
+    params: RequestParam,
+) -> Result<(), RequestError> {
+    match probe {
+        Probe::Coils(range) => channel.read_coils(params, range).await.map(|_| ()),
+        Probe::DiscreteInputs(range) => channel
+            .read_discrete_inputs(params, range)
+            .await
+            .map(|_| ()),
+        Probe::HoldingRegisters(range) => channel
+            .read_holding_registers(params, range)
+            .await
+            .map(|_| ()),
+        Probe::InputRegisters(range) => channel
+            .read_input_registers(params, range)
+            .await
+            .map(|_| ()),
+    }
+}
+
 fn get_index(arg: &ArgMatches) -> Result<u16, ParseIntError> {
     u16::from_str(arg.value_of("index").unwrap())
 }
@@ -266,9 +612,39 @@ fn get_command(matches: &ArgMatches) -> Result<Command, Error> {
         )?));
     }
 
+    if let Some(matches) = matches.subcommand_matches("scan") {
+        return Ok(Command::Scan(get_scan(matches)?));
+    }
+
+    if matches.subcommand_matches("repl").is_some() {
+        return Ok(Command::Repl);
+    }
+
     Err(Error::MissingSubCommand)
 }
 
+fn get_scan(arg: &ArgMatches) -> Result<Scan, Error> {
+    let first = u8::from_str(arg.value_of("first").unwrap())?;
+    let last = u8::from_str(arg.value_of("last").unwrap())?;
+    if first > last {
+        return Err(Error::BadUnitIdRange(first, last));
+    }
+    let range = AddressRange::try_from(u16::from_str(arg.value_of("address").unwrap())?, 1)?;
+    let probe = match arg.value_of("function").unwrap() {
+        "rc" => Probe::Coils(range),
+        "rdi" => Probe::DiscreteInputs(range),
+        "rhr" => Probe::HoldingRegisters(range),
+        "rir" => Probe::InputRegisters(range),
+        other => return Err(Error::BadProbe(other.to_string())),
+    };
+    Ok(Scan {
+        first,
+        last,
+        probe,
+        timeout: get_period_ms(arg.value_of("timeout").unwrap())?,
+    })
+}
+
 fn parse_args() -> Result<Args, Error> {
     let matches = App::new("Modbus Client Console")
         .version("0.1.0")
@@ -299,166 +675,31 @@ fn parse_args() -> Result<Args, Error> {
                 .required(false)
                 .help("Optional polling period in milliseconds"),
         )
-        .subcommand(
-            SubCommand::with_name("rc")
-                .about("read coils")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address"),
-                )
-                .arg(
-                    Arg::with_name("quantity")
-                        .short("q")
-                        .long("quantity")
-                        .required(true)
-                        .takes_value(true)
-                        .help("quantity of values"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("rdi")
-                .about("read discrete inputs")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address"),
-                )
-                .arg(
-                    Arg::with_name("quantity")
-                        .short("q")
-                        .long("quantity")
-                        .required(true)
-                        .takes_value(true)
-                        .help("quantity of values"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("rhr")
-                .about("read holding registers")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address"),
-                )
-                .arg(
-                    Arg::with_name("quantity")
-                        .short("q")
-                        .long("quantity")
-                        .required(true)
-                        .takes_value(true)
-                        .help("quantity of values"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("rir")
-                .about("read input registers")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address"),
-                )
-                .arg(
-                    Arg::with_name("quantity")
-                        .short("q")
-                        .long("quantity")
-                        .required(true)
-                        .takes_value(true)
-                        .help("quantity of values"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("wsc")
-                .about("write single coil")
-                .arg(
-                    Arg::with_name("index")
-                        .short("i")
-                        .long("index")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the address of the coil"),
-                )
-                .arg(
-                    Arg::with_name("value")
-                        .short("v")
-                        .long("value")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the value of the coil (ON or OFF)"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("wsr")
-                .about("write single register")
-                .arg(
-                    Arg::with_name("index")
-                        .short("i")
-                        .long("index")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the address of the register"),
-                )
-                .arg(
-                    Arg::with_name("value")
-                        .short("v")
-                        .long("value")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the value of the register"),
-                ),
+        .arg(
+            Arg::with_name("count")
+                .short("n")
+                .long("count")
+                .takes_value(true)
+                .required(false)
+                .requires("period")
+                .help("Stop after this number of polls"),
         )
-        .subcommand(
-            SubCommand::with_name("wmc")
-                .about("write multiple coils")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address of the coils"),
-                )
-                .arg(
-                    Arg::with_name("values")
-                        .short("v")
-                        .long("values")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the values of the coils specified as a string of 1 and 0 (e.g. 10100011)"),
-                ),
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["table", "csv", "json"])
+                .default_value("table")
+                .help("Output format of the values returned by read requests"),
         )
-        .subcommand(
-            SubCommand::with_name("wmr")
-                .about("write multiple registers")
-                .arg(
-                    Arg::with_name("start")
-                        .short("s")
-                        .long("start")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the starting address of the registers"),
-                )
-                .arg(
-                    Arg::with_name("values")
-                        .short("v")
-                        .long("values")
-                        .required(true)
-                        .takes_value(true)
-                        .help("the values of the registers specified as a comma delimited list (e.g. 1,4,7)"),
-                ),
+        .arg(
+            Arg::with_name("only-changes")
+                .short("c")
+                .long("only-changes")
+                .help("Only print the values that changed since the previous poll"),
         )
+        .subcommands(request_subcommands())
         .get_matches();
 
     let address = SocketAddr::from_str(matches.value_of("host").unwrap())?;
@@ -467,9 +708,211 @@ fn parse_args() -> Result<Args, Error> {
         Some(s) => Some(get_period_ms(s)?),
         None => None,
     };
+    let count = match matches.value_of("count") {
+        Some(s) => Some(usize::from_str(s)?),
+        None => None,
+    };
+    let format = match matches.value_of("format").unwrap() {
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        _ => Format::Table,
+    };
+    let output = Output::new(format, matches.is_present("only-changes"));
     let command = get_command(&matches)?;
 
-    Ok(Args::new(address, id, command, period))
+    Ok(Args::new(address, id, command, period, count, output))
+}
+
+/// Subcommands that send requests, shared by the command line and the REPL
+fn request_subcommands() -> Vec<App<'static, 'static>> {
+    vec![
+        SubCommand::with_name("rc")
+            .about("read coils")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address"),
+            )
+            .arg(
+                Arg::with_name("quantity")
+                    .short("q")
+                    .long("quantity")
+                    .required(true)
+                    .takes_value(true)
+                    .help("quantity of values"),
+            ),
+        SubCommand::with_name("rdi")
+            .about("read discrete inputs")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address"),
+            )
+            .arg(
+                Arg::with_name("quantity")
+                    .short("q")
+                    .long("quantity")
+                    .required(true)
+                    .takes_value(true)
+                    .help("quantity of values"),
+            ),
+        SubCommand::with_name("rhr")
+            .about("read holding registers")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address"),
+            )
+            .arg(
+                Arg::with_name("quantity")
+                    .short("q")
+                    .long("quantity")
+                    .required(true)
+                    .takes_value(true)
+                    .help("quantity of values"),
+            ),
+        SubCommand::with_name("rir")
+            .about("read input registers")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address"),
+            )
+            .arg(
+                Arg::with_name("quantity")
+                    .short("q")
+                    .long("quantity")
+                    .required(true)
+                    .takes_value(true)
+                    .help("quantity of values"),
+            ),
+        SubCommand::with_name("wsc")
+            .about("write single coil")
+            .arg(
+                Arg::with_name("index")
+                    .short("i")
+                    .long("index")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the address of the coil"),
+            )
+            .arg(
+                Arg::with_name("value")
+                    .short("v")
+                    .long("value")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the value of the coil (ON or OFF)"),
+            ),
+        SubCommand::with_name("wsr")
+            .about("write single register")
+            .arg(
+                Arg::with_name("index")
+                    .short("i")
+                    .long("index")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the address of the register"),
+            )
+            .arg(
+                Arg::with_name("value")
+                    .short("v")
+                    .long("value")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the value of the register"),
+            ),
+        SubCommand::with_name("wmc")
+            .about("write multiple coils")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address of the coils"),
+            )
+            .arg(
+                Arg::with_name("values")
+                    .short("v")
+                    .long("values")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the values of the coils specified as a string of 1 and 0 (e.g. 10100011)"),
+            ),
+        SubCommand::with_name("wmr")
+            .about("write multiple registers")
+            .arg(
+                Arg::with_name("start")
+                    .short("s")
+                    .long("start")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the starting address of the registers"),
+            )
+            .arg(
+                Arg::with_name("values")
+                    .short("v")
+                    .long("values")
+                    .required(true)
+                    .takes_value(true)
+                    .help("the values of the registers specified as a comma delimited list (e.g. 1,4,7)"),
+            ),
+        SubCommand::with_name("scan")
+            .about("find the unit ids that respond to a read request")
+            .arg(
+                Arg::with_name("first")
+                    .short("f")
+                    .long("first")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("the first unit id to probe"),
+            )
+            .arg(
+                Arg::with_name("last")
+                    .short("l")
+                    .long("last")
+                    .takes_value(true)
+                    .default_value("247")
+                    .help("the last unit id to probe"),
+            )
+            .arg(
+                Arg::with_name("function")
+                    .short("c")
+                    .long("function")
+                    .takes_value(true)
+                    .default_value("rhr")
+                    .help("the read request sent to each unit id (rc, rdi, rhr or rir)"),
+            )
+            .arg(
+                Arg::with_name("address")
+                    .short("a")
+                    .long("address")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("the address read by the request"),
+            )
+            .arg(
+                Arg::with_name("timeout")
+                    .short("t")
+                    .long("timeout")
+                    .takes_value(true)
+                    .default_value("100")
+                    .help("the response timeout of each request in milliseconds"),
+            ),
+    ]
 }
 
 impl std::error::Error for Error {}
@@ -482,6 +925,14 @@ impl std::fmt::Display for Error {
             Error::BadInt(err) => err.fmt(f),
             Error::BadBool(err) => err.fmt(f),
             Error::BadCharInBitString(char) => write!(f, "Bad character in bit string: {char}"),
+            Error::BadProbe(name) => write!(f, "Unknown probe request: {name}"),
+            Error::BadUnitIdRange(first, last) => {
+                write!(
+                    f,
+                    "First unit id ({first}) is greater than the last ({last})"
+                )
+            }
+            Error::BadDecodeLevel(level) => write!(f, "Unknown decode level: {level}"),
             Error::Request(err) => err.fmt(f),
             Error::MissingSubCommand => f.write_str("No sub-command provided"),
             Error::Shutdown => f.write_str("channel was shut down"),
@@ -0,0 +1,106 @@
+//! Encoding of request frames and decoding of response frames, MBAP and RTU
+
+// criterion_group! generates an undocumented function
+#![allow(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rodbus::bench::ServerReply;
+use rodbus::client::{FrameFormat, WriteMultiple};
+use rodbus::codec::{decode_response, encode_request, Header, Request};
+use rodbus::constants::limits::{MAX_READ_REGISTERS_COUNT, MAX_WRITE_REGISTERS_COUNT};
+use rodbus::server::{DatabaseLayout, MemoryDatabase};
+use rodbus::{AddressRange, UnitId};
+
+fn formats() -> [(&'static str, FrameFormat); 2] {
+    [("mbap", FrameFormat::Mbap), ("rtu", FrameFormat::Rtu)]
+}
+
+fn encode(c: &mut Criterion) {
+    let read = AddressRange::try_from(0, MAX_READ_REGISTERS_COUNT).unwrap();
+    let write = WriteMultiple::from(0, vec![0xCAFE; MAX_WRITE_REGISTERS_COUNT as usize]).unwrap();
+    let header = Header::new(UnitId::new(1)).with_tx_id(7);
+
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(1));
+    for (name, format) in formats() {
+        let mut buffer = [0; 260];
+        group.bench_with_input(
+            BenchmarkId::new("read_holding_registers", name),
+            &format,
+            |b, format| {
+                b.iter(|| {
+                    encode_request(
+                        &mut buffer,
+                        *format,
+                        header,
+                        Request::ReadHoldingRegisters(read),
+                    )
+                    .unwrap()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("write_multiple_registers", name),
+            &format,
+            |b, format| {
+                b.iter(|| {
+                    encode_request(
+                        &mut buffer,
+                        *format,
+                        header,
+                        Request::WriteMultipleRegisters(&write),
+                    )
+                    .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let range = AddressRange::try_from(0, MAX_READ_REGISTERS_COUNT).unwrap();
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for address in range {
+        database.add_holding_register(address, address);
+    }
+    let pdu = [0x03, 0x00, 0x00, 0x00, MAX_READ_REGISTERS_COUNT as u8];
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(1));
+    for (name, format, mut server) in [
+        ("mbap", FrameFormat::Mbap, ServerReply::tcp()),
+        ("rtu", FrameFormat::Rtu, ServerReply::rtu()),
+    ] {
+        let frame = server.reply(&mut database, &pdu).to_vec();
+        group.bench_with_input(
+            BenchmarkId::new("read_holding_registers", name),
+            &frame,
+            |b, frame| {
+                b.iter(|| {
+                    decode_response(frame, format, Request::ReadHoldingRegisters(range)).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let values = vec![0xCAFE; MAX_WRITE_REGISTERS_COUNT as usize];
+
+    let mut group = c.benchmark_group("build");
+    group.throughput(Throughput::Elements(1));
+    // copies the slice into an owned vector first, as required by WriteMultiple::from
+    group.bench_function("write_multiple_registers_from_vec", |b| {
+        b.iter(|| WriteMultiple::from(0, values.to_vec()).unwrap())
+    });
+    group.bench_function("write_multiple_registers_from_iter", |b| {
+        b.iter(|| WriteMultiple::from_iter_checked(0, values.iter().copied()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode, build);
+criterion_main!(benches);
@@ -0,0 +1,143 @@
+//! Requests per second between a client and a server over a localhost TCP socket
+//!
+//! The `with_updates` variant modifies every register from another thread at 1 kHz while the
+//! client reads them, which shows how much the application updates slow down the server.
+//!
+//! The measurement runs for a fixed duration. In CI, run it once as a smoke test with:
+//!
+//! `cargo bench --bench loopback -- --test`
+
+// criterion_group! generates an undocumented function
+#![allow(missing_docs)]
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use rodbus::client::*;
+use rodbus::constants::limits::MAX_READ_REGISTERS_COUNT;
+use rodbus::server::*;
+use rodbus::*;
+
+/// Updates every register of the database in a single transaction at 1 kHz until dropped
+struct Updater {
+    running: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Updater {
+    fn start(database: ServerHandlerType<MemoryDatabase>, range: AddressRange) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = running.clone();
+            std::thread::spawn(move || {
+                let mut value: u16 = 0;
+                while running.load(Ordering::Relaxed) {
+                    value = value.wrapping_add(1);
+                    database.update(|db| {
+                        for address in range {
+                            db.update_holding_register(address, value);
+                        }
+                    });
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Updater {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn read_holding_registers(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let addr = SocketAddr::from_str("127.0.0.1:40100").unwrap();
+    let range = AddressRange::try_from(0, MAX_READ_REGISTERS_COUNT).unwrap();
+
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for address in range {
+        database.add_holding_register(address, address);
+    }
+    let database = database.wrap();
+
+    let (_server, mut channel) = runtime.block_on(async {
+        let server = spawn_tcp_server_task(
+            1,
+            addr,
+            ServerHandlerMap::single(UnitId::new(1), database.clone()),
+            AddressFilter::Any,
+            DecodeLevel::nothing(),
+        )
+        .await
+        .unwrap();
+
+        let channel = spawn_tcp_client_task(
+            addr.into(),
+            1,
+            default_retry_strategy(),
+            DecodeLevel::nothing(),
+            None,
+        );
+        channel.enable().await.unwrap();
+
+        (server, channel)
+    });
+
+    let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+
+    let mut group = c.benchmark_group("loopback");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("read_holding_registers", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(channel.read_holding_registers(param, range))
+                .unwrap()
+        })
+    });
+    let mut values = Vec::new();
+    group.bench_function("read_holding_registers_into", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(channel.read_holding_registers_into(param, range, &mut values))
+                .unwrap()
+        })
+    });
+    let updater = Updater::start(database, range);
+    group.bench_function("read_holding_registers_with_updates", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(channel.read_holding_registers(param, range))
+                .unwrap()
+        })
+    });
+    drop(updater);
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_secs(1))
+        .measurement_time(Duration::from_secs(5))
+        .sample_size(10);
+    targets = read_holding_registers
+);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+//! Server-side parsing of requests and formatting of replies
+
+// criterion_group! generates an undocumented function
+#![allow(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rodbus::bench::ServerReply;
+use rodbus::constants::limits::MAX_READ_REGISTERS_COUNT;
+use rodbus::server::{DatabaseLayout, MemoryDatabase};
+
+fn read_holding_registers(c: &mut Criterion) {
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for address in 0..MAX_READ_REGISTERS_COUNT {
+        database.add_holding_register(address, address);
+    }
+    // FC03 for the maximum number of registers starting at zero
+    let pdu = [0x03, 0x00, 0x00, 0x00, MAX_READ_REGISTERS_COUNT as u8];
+
+    let mut group = c.benchmark_group("server");
+    group.throughput(Throughput::Elements(1));
+    for (name, mut server) in [("mbap", ServerReply::tcp()), ("rtu", ServerReply::rtu())] {
+        group.bench_with_input(
+            BenchmarkId::new("read_holding_registers", name),
+            &pdu,
+            |b, pdu| b.iter(|| server.reply(&mut database, pdu).len()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read_holding_registers);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+//! Callback-based client example for applications whose main loop isn't async
+//!
+//! The channel runs on a Tokio runtime owned by the application. Callbacks run on that
+//! runtime, so they only forward each result to the main thread over a `std::sync::mpsc` channel.
+
+use std::error::Error;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use rodbus::client::*;
+use rodbus::*;
+
+/// Results delivered to the main thread
+enum Event {
+    Coils(Result<Vec<Indexed<bool>>, RequestError>),
+    Registers(Result<Vec<Indexed<u16>>, RequestError>),
+}
+
+/// Create a callback that sends its result on `tx`, wrapped by `event`
+fn forward<T, F>(
+    tx: &mpsc::Sender<Event>,
+    event: F,
+) -> impl FnOnce(Result<T, RequestError>) + Send + Sync + 'static
+where
+    F: FnOnce(Result<T, RequestError>) -> Event + Send + Sync + 'static,
+{
+    let tx = tx.clone();
+    move |result| {
+        // never block here, the main thread may have stopped receiving
+        let _ = tx.send(event(result));
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .init();
+
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    // spawning the channel requires the runtime context
+    let channel = {
+        let _guard = runtime.enter();
+        spawn_tcp_client_task(
+            HostAddr::ip("127.0.0.1".parse()?, 502),
+            10,
+            default_retry_strategy(),
+            DecodeLevel::default(),
+            None,
+        )
+    };
+    runtime.block_on(channel.enable())?;
+
+    let callbacks = CallbackChannel::new(channel);
+    let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+    let (tx, rx) = mpsc::channel();
+
+    loop {
+        callbacks.read_coils(
+            param,
+            AddressRange::try_from(0, 5).unwrap(),
+            forward(&tx, Event::Coils),
+        )?;
+        callbacks.read_holding_registers(
+            param,
+            AddressRange::try_from(0, 5).unwrap(),
+            forward(&tx, Event::Registers),
+        )?;
+
+        // process both results on the main thread
+        for _ in 0..2 {
+            match rx.recv()? {
+                Event::Coils(result) => println!("coils: {result:?}"),
+                Event::Registers(result) => println!("registers: {result:?}"),
+            }
+        }
+
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,90 @@
+//! Exposes Rodbus metrics to Prometheus
+//!
+//! Run with `cargo run --example metrics --features metrics` and then scrape
+//! `http://127.0.0.1:9000/metrics` while the client polls the server.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use metrics_exporter_prometheus::PrometheusBuilder;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use rodbus::client::*;
+use rodbus::server::*;
+use rodbus::*;
+
+struct Handler;
+
+impl RequestHandler for Handler {
+    fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+        // only the first 10 registers exist
+        if address < 10 {
+            Ok(address)
+        } else {
+            Err(ExceptionCode::IllegalDataAddress)
+        }
+    }
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .init();
+
+    // install the recorder before spawning any Rodbus tasks
+    let recorder = PrometheusBuilder::new().install_recorder()?;
+
+    let addr: SocketAddr = "127.0.0.1:10502".parse()?;
+    let _server = spawn_tcp_server_task(
+        10,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), Handler.wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await?;
+
+    let mut channel = spawn_tcp_client_task(
+        addr.into(),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await?;
+
+    // poll the server, every other request fails with an exception
+    tokio::spawn(async move {
+        let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+        let mut start = 0;
+        loop {
+            let range = AddressRange::try_from(start, 10).unwrap();
+            if let Err(err) = channel.read_holding_registers(params, range).await {
+                tracing::warn!("request failed: {}", err);
+            }
+            start = 10 - start;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    // minimal HTTP endpoint that renders the metrics for every request
+    let listener = TcpListener::bind("127.0.0.1:9000").await?;
+    println!("serving metrics on http://127.0.0.1:9000/metrics");
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let body = recorder.render();
+        tokio::spawn(async move {
+            let mut buffer = [0; 1024];
+            let _ = socket.read(&mut buffer).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
@@ -12,15 +12,6 @@ use rodbus::*;
 
 use clap::Parser;
 
-struct Handler;
-
-impl RequestHandler for Handler {
-    fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
-        // value is always the address
-        Ok(address)
-    }
-}
-
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
@@ -64,7 +55,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ip = IpAddr::from_str("127.0.0.1")?;
     let addr = SocketAddr::new(ip, args.port);
 
-    let handler = Handler {}.wrap();
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for address in 0..MAX_READ_REGISTERS_COUNT {
+        // value is always the address
+        database.add_holding_register(address, address);
+    }
+    let handler = database.wrap();
 
     let _handle = spawn_tcp_server_task(
         args.sessions,
@@ -0,0 +1,74 @@
+//! Server example that persists its holding registers across restarts
+//!
+//! The values are restored from `registers.json` on startup and written back to it every 10 seconds,
+//! or as soon as clients change 100 registers. Type `x` to write the final values and exit.
+
+use std::time::Duration;
+
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+use rodbus::server::*;
+use rodbus::*;
+
+const PATH: &str = "registers.json";
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // initialize logging
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .init();
+
+    // default values of the points, used the first time the server runs
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for index in 0..10 {
+        database.add_holding_register(index, 0);
+    }
+
+    // ANCHOR: restore_snapshot
+    match std::fs::read(PATH) {
+        Ok(data) => {
+            let snapshot: DatabaseSnapshot = serde_json::from_slice(&data)?;
+            database.restore(&snapshot);
+            tracing::info!("restored values from {PATH}");
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    // ANCHOR_END: restore_snapshot
+
+    let database = database.wrap();
+    let map = ServerHandlerMap::single(UnitId::new(1), database.clone());
+
+    let _server = spawn_tcp_server_task(
+        1,
+        "127.0.0.1:502".parse()?,
+        map,
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await?;
+
+    // ANCHOR: spawn_persist_task
+    let persist = spawn_persist_task(
+        database,
+        PATH,
+        PersistPolicy::interval(Duration::from_secs(10)).change_threshold(100),
+        Box::new(|snapshot: &DatabaseSnapshot| Ok(serde_json::to_vec_pretty(snapshot)?)),
+    );
+    // ANCHOR_END: spawn_persist_task
+
+    let mut reader = FramedRead::new(tokio::io::stdin(), LinesCodec::new());
+    loop {
+        match reader.next().await.unwrap()?.as_str() {
+            "x" => break,
+            _ => println!("unknown command"),
+        }
+    }
+
+    // write the latest values before exiting
+    persist.shutdown().await;
+    Ok(())
+}
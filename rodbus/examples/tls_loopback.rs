@@ -0,0 +1,93 @@
+//! Secure Modbus client and server that authenticate each other with certificates generated at startup
+//!
+//! The server authorizes every request from the role in the client certificate: reads are allowed
+//! and writes are denied. The example makes one of each and prints the results.
+
+use std::time::Duration;
+
+use rodbus::client::*;
+use rodbus::server::*;
+use rodbus::testing::certs::generate_certificates;
+use rodbus::*;
+
+const ROLE: &str = "viewer";
+
+/// Allows reads for any role and denies writes
+struct ReadOnlyRoles;
+
+impl AuthorizationHandler for ReadOnlyRoles {
+    fn read_holding_registers(
+        &self,
+        _unit_id: UnitId,
+        _range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        tracing::info!("allowing read for role: {role}");
+        Authorization::Allow
+    }
+
+    fn write_single_register(&self, _unit_id: UnitId, _idx: u16, role: &str) -> Authorization {
+        tracing::info!("denying write for role: {role}");
+        Authorization::Deny
+    }
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // initialize logging
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .init();
+
+    let dir = std::env::temp_dir().join(format!("rodbus-tls-loopback-{}", std::process::id()));
+    let certs = generate_certificates(&dir, "localhost", ROLE)?;
+    println!("certificates written to {}", dir.display());
+
+    let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+    for index in 0..10 {
+        database.add_holding_register(index, index);
+    }
+    let map = ServerHandlerMap::single(UnitId::new(1), database.wrap());
+
+    let addr = "127.0.0.1:8802".parse()?;
+    let _server = spawn_tls_server_task_with_authz(
+        1,
+        addr,
+        map,
+        ReadOnlyRoles.wrap(),
+        certs.server_config()?,
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await?;
+
+    let mut channel = spawn_tls_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        1,
+        default_retry_strategy(),
+        certs.client_config()?,
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await?;
+    if !channel.wait_for_connected(Duration::from_secs(5)).await? {
+        return Err("unable to connect to the server".into());
+    }
+
+    let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+
+    let read = channel
+        .read_holding_registers(param, AddressRange::try_from(0, 3).unwrap())
+        .await;
+    println!("read as {ROLE}: {read:?}");
+
+    // denied requests are answered with the IllegalFunction exception
+    let write = channel
+        .write_single_register(param, Indexed::new(0, 42))
+        .await;
+    println!("write as {ROLE}: {write:?}");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
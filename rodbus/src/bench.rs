@@ -0,0 +1,58 @@
+//! Entry points for the criterion benchmarks in `rodbus/benches`
+//!
+//! These types are not part of the public API and are not covered by semver.
+
+use crate::common::frame::{FrameDestination, FrameHeader, FrameWriter, TxId};
+use crate::common::function::FunctionCode;
+use crate::decode::DecodeLevel;
+use crate::server::request::Request;
+use crate::server::RequestHandler;
+use crate::types::UnitId;
+
+use scursor::ReadCursor;
+
+/// Parses request PDUs and formats replies the same way a server session does
+pub struct ServerReply {
+    writer: FrameWriter,
+    header: FrameHeader,
+}
+
+impl ServerReply {
+    /// Create an instance that formats MBAP replies
+    pub fn tcp() -> Self {
+        Self {
+            writer: FrameWriter::tcp(),
+            header: FrameHeader::new_tcp_header(UnitId::new(1), TxId::new(0)),
+        }
+    }
+
+    /// Create an instance that formats RTU replies
+    pub fn rtu() -> Self {
+        Self {
+            writer: FrameWriter::rtu(),
+            header: FrameHeader::new_rtu_header(FrameDestination::UnitId(UnitId::new(1))),
+        }
+    }
+
+    /// Parse a request PDU (function code included) and format the complete reply frame
+    ///
+    /// Panics if the request cannot be parsed or the reply cannot be formatted
+    pub fn reply(&mut self, handler: &mut dyn RequestHandler, pdu: &[u8]) -> &[u8] {
+        let mut cursor = ReadCursor::new(pdu);
+        let function = cursor
+            .read_u8()
+            .ok()
+            .and_then(FunctionCode::get)
+            .expect("unknown function code");
+        let request = Request::parse(function, &mut cursor).expect("bad request");
+        request
+            .get_reply(
+                self.header,
+                handler,
+                &mut self.writer,
+                DecodeLevel::nothing(),
+                false,
+            )
+            .expect("unable to format reply")
+    }
+}
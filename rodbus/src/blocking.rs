@@ -0,0 +1,307 @@
+use std::future::Future;
+use std::time::Instant;
+
+use crate::client::{Channel, RequestParam, TimeoutTable, WriteMultiple};
+use crate::{AddressRange, DecodeLevel, Indexed, RequestError, Shutdown};
+
+/// Errors returned by [`BlockingChannel`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockingError {
+    /// Blocking calls were made from within an async context, e.g. a Tokio task
+    ///
+    /// Blocking the thread of a runtime can deadlock it, so the call is refused
+    CannotBlockWithinAsync,
+    /// The runtime or the thread driving it couldn't be created
+    FailedToCreateRuntime,
+    /// The deadline passed before the operation completed
+    DeadlineElapsed,
+    /// The request failed
+    Request(RequestError),
+}
+
+impl std::error::Error for BlockingError {}
+
+impl std::fmt::Display for BlockingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            BlockingError::CannotBlockWithinAsync => {
+                f.write_str("blocking calls cannot be made from within an async context")
+            }
+            BlockingError::FailedToCreateRuntime => f.write_str("failed to create the runtime"),
+            BlockingError::DeadlineElapsed => f.write_str("deadline elapsed"),
+            BlockingError::Request(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<RequestError> for BlockingError {
+    fn from(err: RequestError) -> Self {
+        BlockingError::Request(err)
+    }
+}
+
+impl From<Shutdown> for BlockingError {
+    fn from(_: Shutdown) -> Self {
+        BlockingError::Request(RequestError::Shutdown)
+    }
+}
+
+/// Client channel for applications that don't use async
+///
+/// The channel runs on a small current-thread runtime driven by a dedicated thread that
+/// is stopped when the `BlockingChannel` is dropped. Every method blocks the calling thread
+/// until the operation completes or the deadline passes.
+#[derive(Debug)]
+pub struct BlockingChannel {
+    // dropped before the runtime is stopped so that the client task shuts down
+    inner: Option<Channel>,
+    handle: tokio::runtime::Handle,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl BlockingChannel {
+    /// Create the channel on the internal runtime by calling one of the client spawn functions
+    ///
+    /// ```no_run
+    /// use rodbus::*;
+    /// use rodbus::blocking::BlockingChannel;
+    /// use rodbus::client::*;
+    ///
+    /// let channel = BlockingChannel::spawn(|| {
+    ///     spawn_tcp_client_task(
+    ///         HostAddr::ip("127.0.0.1".parse().unwrap(), 502),
+    ///         10,
+    ///         default_retry_strategy(),
+    ///         DecodeLevel::default(),
+    ///         None,
+    ///     )
+    /// });
+    /// ```
+    pub fn spawn<F>(create: F) -> Result<Self, BlockingError>
+    where
+        F: FnOnce() -> Channel,
+    {
+        check_not_async()?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|_| BlockingError::FailedToCreateRuntime)?;
+
+        let channel = {
+            let _guard = runtime.enter();
+            create()
+        };
+
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = tokio::sync::oneshot::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("rodbus-blocking".to_string())
+            .spawn(move || {
+                // drives the client task until the BlockingChannel is dropped
+                runtime.block_on(async move {
+                    let _ = stopped.await;
+                });
+            })
+            .map_err(|_| BlockingError::FailedToCreateRuntime)?;
+
+        Ok(Self {
+            inner: Some(channel),
+            handle,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    /// Enable communications, see [`Channel::enable`]
+    pub fn enable(&mut self, deadline: Instant) -> Result<(), BlockingError> {
+        self.block_on(deadline, |channel| async move { channel.enable().await })?;
+        Ok(())
+    }
+
+    /// Disable communications, see [`Channel::disable`]
+    pub fn disable(&mut self, deadline: Instant) -> Result<(), BlockingError> {
+        self.block_on(deadline, |channel| async move { channel.disable().await })?;
+        Ok(())
+    }
+
+    /// Read coils from the server
+    pub fn read_coils(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        deadline: Instant,
+    ) -> Result<Vec<Indexed<bool>>, BlockingError> {
+        self.block_on(deadline, |channel| channel.read_coils(param, range))
+    }
+
+    /// Read discrete inputs from the server
+    pub fn read_discrete_inputs(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        deadline: Instant,
+    ) -> Result<Vec<Indexed<bool>>, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.read_discrete_inputs(param, range)
+        })
+    }
+
+    /// Read holding registers from the server
+    pub fn read_holding_registers(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        deadline: Instant,
+    ) -> Result<Vec<Indexed<u16>>, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.read_holding_registers(param, range)
+        })
+    }
+
+    /// Read input registers from the server
+    pub fn read_input_registers(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        deadline: Instant,
+    ) -> Result<Vec<Indexed<u16>>, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.read_input_registers(param, range)
+        })
+    }
+
+    /// Write a single coil on the server
+    pub fn write_single_coil(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<bool>,
+        deadline: Instant,
+    ) -> Result<Indexed<bool>, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.write_single_coil(param, request)
+        })
+    }
+
+    /// Write a single register on the server
+    pub fn write_single_register(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<u16>,
+        deadline: Instant,
+    ) -> Result<Indexed<u16>, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.write_single_register(param, request)
+        })
+    }
+
+    /// Write multiple contiguous coils on the server
+    pub fn write_multiple_coils(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<bool>,
+        deadline: Instant,
+    ) -> Result<AddressRange, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.write_multiple_coils(param, request)
+        })
+    }
+
+    /// Write multiple contiguous registers on the server
+    pub fn write_multiple_registers(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<u16>,
+        deadline: Instant,
+    ) -> Result<AddressRange, BlockingError> {
+        self.block_on(deadline, |channel| {
+            channel.write_multiple_registers(param, request)
+        })
+    }
+
+    /// Retrieve the parameters of the TLS session, see [`Channel::tls_info`]
+    #[cfg(feature = "tls")]
+    pub fn tls_info(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Option<crate::client::TlsInfo>, BlockingError> {
+        self.block_on(deadline, |channel| async move { channel.tls_info().await })
+    }
+
+    /// Change the protocol decoding level of the channel
+    pub fn set_decode_level(
+        &mut self,
+        level: DecodeLevel,
+        deadline: Instant,
+    ) -> Result<(), BlockingError> {
+        self.block_on(deadline, |channel| channel.set_decode_level(level))
+    }
+
+    /// Change the response timeouts used for requests made with [`RequestParam::default_timeout`]
+    pub fn set_timeout_table(
+        &mut self,
+        table: TimeoutTable,
+        deadline: Instant,
+    ) -> Result<(), BlockingError> {
+        self.block_on(deadline, |channel| channel.set_timeout_table(table))
+    }
+
+    fn block_on<'a, F, Fut, T, E>(
+        &'a mut self,
+        deadline: Instant,
+        op: F,
+    ) -> Result<T, BlockingError>
+    where
+        F: FnOnce(&'a mut Channel) -> Fut,
+        Fut: Future<Output = Result<T, E>> + 'a,
+        BlockingError: From<E>,
+    {
+        check_not_async()?;
+        if Instant::now() >= deadline {
+            return Err(BlockingError::DeadlineElapsed);
+        }
+        // always present until dropped
+        let channel = self
+            .inner
+            .as_mut()
+            .ok_or(BlockingError::Request(RequestError::Shutdown))?;
+        let future = op(channel);
+        let result = self.handle.block_on(async move {
+            tokio::time::timeout_at(tokio::time::Instant::from_std(deadline), future).await
+        });
+        match result {
+            Ok(x) => Ok(x?),
+            Err(_) => Err(BlockingError::DeadlineElapsed),
+        }
+    }
+}
+
+impl Drop for BlockingChannel {
+    fn drop(&mut self) {
+        // the client task shuts down once the channel is dropped
+        self.inner.take();
+        self.shutdown.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn check_not_async() -> Result<(), BlockingError> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(BlockingError::CannotBlockWithinAsync);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_to_be_created_within_async() {
+        let result = BlockingChannel::spawn(|| unreachable!("the channel must not be created"));
+        assert_eq!(result.unwrap_err(), BlockingError::CannotBlockWithinAsync);
+    }
+}
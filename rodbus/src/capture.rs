@@ -0,0 +1,270 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Direction of captured bytes relative to the local channel or server
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Bytes written to the socket or serial port
+    Tx,
+    /// Bytes read from the socket or serial port
+    Rx,
+}
+
+/// Receives a copy of the raw bytes sent and received by a client channel or server
+///
+/// Bytes are reported exactly as they were written to or read from the socket or serial port,
+/// including the MBAP header or RTU framing. Each write contains a complete ADU. A read contains
+/// whatever the operating system returned, which may be part of an ADU or several of them.
+///
+/// The sink is invoked from the task that performs the I/O, so it must not block for long.
+/// Sessions of a server share the same sink.
+pub trait FrameSink: Send + 'static {
+    /// Record the bytes of a single write or read
+    fn record(&mut self, timestamp: SystemTime, direction: Direction, data: &[u8]);
+}
+
+/// Sink shared by a channel or all of the sessions of a server
+pub(crate) type Capture = Arc<Mutex<Box<dyn FrameSink>>>;
+
+pub(crate) fn new_capture(sink: Box<dyn FrameSink>) -> Capture {
+    Arc::new(Mutex::new(sink))
+}
+
+/// Pass bytes to the sink with the current time
+pub(crate) fn record(capture: &Capture, direction: Direction, data: &[u8]) {
+    let timestamp = SystemTime::now();
+    capture.lock().unwrap().record(timestamp, direction, data);
+}
+
+/// Role of the local endpoint, which determines on which side of the capture port 502 appears
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Captures of a client channel
+    Client,
+    /// Captures of a server
+    Server,
+}
+
+/// [`FrameSink`] that writes a pcap file which Wireshark opens directly
+///
+/// Each record is wrapped in synthetic IPv4 and TCP headers between 10.0.0.1 (local) and 10.0.0.2
+/// (remote), with the server side on port 502, so that Wireshark decodes it as Modbus/TCP.
+/// Captures of RTU framing (serial or RTU over TCP) must be decoded as Modbus/RTU using
+/// "Decode As...".
+///
+/// Write errors are logged and the record is dropped.
+pub struct PcapWriter<W>
+where
+    W: Write + Send + 'static,
+{
+    writer: W,
+    endpoint: Endpoint,
+    // next TCP sequence number of the local and the remote side
+    local_seq: u32,
+    remote_seq: u32,
+}
+
+impl PcapWriter<std::io::BufWriter<std::fs::File>> {
+    /// Create a pcap file at `path`, truncating it if it exists
+    pub fn create(
+        path: impl AsRef<std::path::Path>,
+        endpoint: Endpoint,
+    ) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::create(path)?;
+        Self::new(std::io::BufWriter::new(file), endpoint)
+    }
+}
+
+impl<W> PcapWriter<W>
+where
+    W: Write + Send + 'static,
+{
+    /// Write the pcap file header to `writer`
+    pub fn new(mut writer: W, endpoint: Endpoint) -> Result<Self, std::io::Error> {
+        writer.write_all(&pcap::MAGIC.to_le_bytes())?;
+        writer.write_all(&pcap::VERSION_MAJOR.to_le_bytes())?;
+        writer.write_all(&pcap::VERSION_MINOR.to_le_bytes())?;
+        // time zone offset and timestamp accuracy
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&pcap::SNAP_LENGTH.to_le_bytes())?;
+        writer.write_all(&pcap::LINKTYPE_IPV4.to_le_bytes())?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            endpoint,
+            local_seq: 0,
+            remote_seq: 0,
+        })
+    }
+
+    /// Consume the writer, returning the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_record(
+        &mut self,
+        timestamp: SystemTime,
+        direction: Direction,
+        data: &[u8],
+    ) -> Result<(), std::io::Error> {
+        let local = (pcap::LOCAL_ADDR, self.local_port());
+        let remote = (pcap::REMOTE_ADDR, self.remote_port());
+        let (src, dst, seq, ack) = match direction {
+            Direction::Tx => (local, remote, &mut self.local_seq, self.remote_seq),
+            Direction::Rx => (remote, local, &mut self.remote_seq, self.local_seq),
+        };
+
+        let ip_length = pcap::IPV4_HEADER_LENGTH + pcap::TCP_HEADER_LENGTH + data.len();
+        let ip_length = u16::try_from(ip_length)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+
+        let mut headers = [0u8; pcap::IPV4_HEADER_LENGTH + pcap::TCP_HEADER_LENGTH];
+        let (ip, tcp) = headers.split_at_mut(pcap::IPV4_HEADER_LENGTH);
+        // version 4, 5 x 32-bit words, don't fragment, TTL of 64, TCP
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&ip_length.to_be_bytes());
+        ip[6] = 0x40;
+        ip[8] = 64;
+        ip[9] = 6;
+        ip[12..16].copy_from_slice(&src.0.octets());
+        ip[16..20].copy_from_slice(&dst.0.octets());
+        let checksum = pcap::checksum(ip);
+        ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+        // the TCP checksum is left empty, Wireshark doesn't validate it by default
+        tcp[0..2].copy_from_slice(&src.1.to_be_bytes());
+        tcp[2..4].copy_from_slice(&dst.1.to_be_bytes());
+        tcp[4..8].copy_from_slice(&seq.to_be_bytes());
+        tcp[8..12].copy_from_slice(&ack.to_be_bytes());
+        // 5 x 32-bit words, PSH and ACK
+        tcp[12] = 0x50;
+        tcp[13] = 0x18;
+        tcp[14..16].copy_from_slice(&u16::MAX.to_be_bytes());
+        *seq = seq.wrapping_add(data.len() as u32);
+
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let length = ip_length as u32;
+        self.writer
+            .write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&headers)?;
+        self.writer.write_all(data)?;
+        // the file remains readable while the capture is running
+        self.writer.flush()
+    }
+
+    fn local_port(&self) -> u16 {
+        match self.endpoint {
+            Endpoint::Client => pcap::CLIENT_PORT,
+            Endpoint::Server => pcap::SERVER_PORT,
+        }
+    }
+
+    fn remote_port(&self) -> u16 {
+        match self.endpoint {
+            Endpoint::Client => pcap::SERVER_PORT,
+            Endpoint::Server => pcap::CLIENT_PORT,
+        }
+    }
+}
+
+impl<W> FrameSink for PcapWriter<W>
+where
+    W: Write + Send + 'static,
+{
+    fn record(&mut self, timestamp: SystemTime, direction: Direction, data: &[u8]) {
+        if let Err(err) = self.write_record(timestamp, direction, data) {
+            tracing::warn!("unable to write capture record: {}", err);
+        }
+    }
+}
+
+mod pcap {
+    use std::net::Ipv4Addr;
+
+    // microsecond resolution timestamps
+    pub(super) const MAGIC: u32 = 0xA1B2_C3D4;
+    pub(super) const VERSION_MAJOR: u16 = 2;
+    pub(super) const VERSION_MINOR: u16 = 4;
+    pub(super) const SNAP_LENGTH: u32 = 65535;
+    // raw IPv4 packets without a link layer header
+    pub(super) const LINKTYPE_IPV4: u32 = 228;
+
+    pub(super) const IPV4_HEADER_LENGTH: usize = 20;
+    pub(super) const TCP_HEADER_LENGTH: usize = 20;
+
+    pub(super) const LOCAL_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    pub(super) const REMOTE_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+    // registered port of Modbus/TCP
+    pub(super) const SERVER_PORT: u16 = 502;
+    pub(super) const CLIENT_PORT: u16 = 49152;
+
+    /// internet checksum of a header whose checksum field is zero
+    pub(super) fn checksum(header: &[u8]) -> u16 {
+        let mut sum: u32 = header
+            .chunks(2)
+            .map(|x| u16::from_be_bytes([x[0], x.get(1).copied().unwrap_or(0)]) as u32)
+            .sum();
+        while sum > 0xFFFF {
+            sum = (sum & 0xFFFF) + (sum >> 16);
+        }
+        !(sum as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    #[test]
+    fn computes_ipv4_header_checksum() {
+        // example from https://en.wikipedia.org/wiki/Internet_checksum
+        let header = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        assert_eq!(pcap::checksum(&header), 0xB861);
+    }
+
+    #[test]
+    fn writes_pcap_records() {
+        let mut writer = PcapWriter::new(Vec::new(), Endpoint::Client).unwrap();
+        let timestamp = UNIX_EPOCH + Duration::from_micros(1_000_002);
+        writer.record(timestamp, Direction::Tx, &[0x01, 0x02]);
+        writer.record(timestamp, Direction::Rx, &[0x03]);
+        let bytes = writer.into_inner();
+
+        assert_eq!(&bytes[0..4], &[0xD4, 0xC3, 0xB2, 0xA1]);
+        assert_eq!(&bytes[20..24], &228u32.to_le_bytes());
+
+        let tx = &bytes[24..];
+        // seconds, microseconds, captured and original length
+        assert_eq!(&tx[0..4], &1u32.to_le_bytes());
+        assert_eq!(&tx[4..8], &2u32.to_le_bytes());
+        assert_eq!(&tx[8..12], &42u32.to_le_bytes());
+        assert_eq!(&tx[12..16], &42u32.to_le_bytes());
+        let packet = &tx[16..58];
+        assert_eq!(&packet[12..16], &Ipv4Addr::new(10, 0, 0, 1).octets());
+        assert_eq!(&packet[16..20], &Ipv4Addr::new(10, 0, 0, 2).octets());
+        assert_eq!(pcap::checksum(&packet[0..20]), 0);
+        // client port to port 502
+        assert_eq!(&packet[20..22], &49152u16.to_be_bytes());
+        assert_eq!(&packet[22..24], &502u16.to_be_bytes());
+        assert_eq!(&packet[40..], &[0x01, 0x02]);
+
+        let rx = &tx[58..];
+        let packet = &rx[16..];
+        assert_eq!(&packet[20..22], &502u16.to_be_bytes());
+        // acknowledges the bytes sent by the client
+        assert_eq!(&packet[24..28], &0u32.to_be_bytes());
+        assert_eq!(&packet[28..32], &2u32.to_be_bytes());
+        assert_eq!(&packet[40..], &[0x03]);
+    }
+}
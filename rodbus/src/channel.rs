@@ -1,17 +1,175 @@
+use tokio::sync::mpsc;
+
 use crate::Shutdown;
 
-/// wrap a Tokio receiver and only provide a recv() that returns a Result<T, Shutdown>
-/// that makes it harder to misuse.
-pub(crate) struct Receiver<T>(tokio::sync::mpsc::Receiver<T>);
+/// Maximum number of high priority values received in a row while normal priority values are waiting
+pub(crate) const MAX_CONSECUTIVE_HIGH_PRIORITY: usize = 4;
+
+/// Values that may be queued ahead of others
+pub(crate) trait Prioritized {
+    fn is_high_priority(&self) -> bool;
+}
+
+/// Create a queue with two priority levels, each bounded by `capacity`
+pub(crate) fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let (normal_tx, normal_rx) = mpsc::channel(capacity);
+    let (high_tx, high_rx) = mpsc::channel(capacity);
+    (
+        Sender {
+            normal: normal_tx,
+            high: high_tx,
+        },
+        Receiver {
+            normal: normal_rx,
+            high: high_rx,
+            consecutive_high: 0,
+        },
+    )
+}
+
+pub(crate) struct Sender<T> {
+    normal: mpsc::Sender<T>,
+    high: mpsc::Sender<T>,
+}
+
+impl<T> std::fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender")
+            .field("normal", &self.normal)
+            .field("high", &self.high)
+            .finish()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            normal: self.normal.clone(),
+            high: self.high.clone(),
+        }
+    }
+}
 
-impl<T> From<tokio::sync::mpsc::Receiver<T>> for Receiver<T> {
-    fn from(value: tokio::sync::mpsc::Receiver<T>) -> Self {
-        Self(value)
+impl<T> Sender<T> {
+    /// Returns `true` if the receiver was dropped
+    pub(crate) fn is_closed(&self) -> bool {
+        // both receivers are dropped together
+        self.normal.is_closed()
     }
 }
 
+impl<T: Prioritized> Sender<T> {
+    pub(crate) async fn send(&self, value: T) -> Result<(), mpsc::error::SendError<T>> {
+        if value.is_high_priority() {
+            self.high.send(value).await
+        } else {
+            self.normal.send(value).await
+        }
+    }
+
+    /// Reserve a slot in the queue of the specified priority without waiting
+    pub(crate) fn try_reserve(
+        &self,
+        high_priority: bool,
+    ) -> Result<mpsc::Permit<'_, T>, mpsc::error::TrySendError<()>> {
+        if high_priority {
+            self.high.try_reserve()
+        } else {
+            self.normal.try_reserve()
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    pub(crate) fn try_send(&self, value: T) -> Result<(), mpsc::error::TrySendError<T>> {
+        if value.is_high_priority() {
+            self.high.try_send(value)
+        } else {
+            self.normal.try_send(value)
+        }
+    }
+}
+
+/// wrap the Tokio receivers of both priority levels and only provide a recv() that returns
+/// a Result<T, Shutdown> that makes it harder to misuse.
+///
+/// High priority values are received first, values of the same priority are received in order
+pub(crate) struct Receiver<T> {
+    normal: mpsc::Receiver<T>,
+    high: mpsc::Receiver<T>,
+    consecutive_high: usize,
+}
+
 impl<T> Receiver<T> {
+    /// receive a value that is already queued without waiting, high priority values first
+    pub(crate) fn try_recv(&mut self) -> Option<T> {
+        self.high
+            .try_recv()
+            .or_else(|_| self.normal.try_recv())
+            .ok()
+    }
+
+    /// cancel safe, nothing is lost if the future is dropped
     pub(crate) async fn recv(&mut self) -> Result<T, Shutdown> {
-        self.0.recv().await.ok_or(Shutdown)
+        // let a waiting normal priority value through so that continuous high priority traffic can't starve it
+        if self.consecutive_high >= MAX_CONSECUTIVE_HIGH_PRIORITY {
+            if let Ok(value) = self.normal.try_recv() {
+                self.consecutive_high = 0;
+                return Ok(value);
+            }
+        }
+
+        tokio::select! {
+            biased;
+            Some(value) = self.high.recv() => {
+                self.consecutive_high += 1;
+                Ok(value)
+            }
+            Some(value) = self.normal.recv() => {
+                self.consecutive_high = 0;
+                Ok(value)
+            }
+            else => Err(Shutdown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Prioritized for (bool, usize) {
+        fn is_high_priority(&self) -> bool {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn receives_high_priority_values_first_in_order() {
+        let (tx, mut rx) = channel(16);
+        for value in [(false, 0), (false, 1), (true, 2), (true, 3)] {
+            tx.send(value).await.unwrap();
+        }
+        drop(tx);
+
+        let mut received = Vec::new();
+        while let Ok((_, x)) = rx.recv().await {
+            received.push(x);
+        }
+        assert_eq!(received, vec![2, 3, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn continuous_high_priority_values_do_not_starve_normal_priority() {
+        let (tx, mut rx) = channel(16);
+        tx.send((false, 0)).await.unwrap();
+        for x in 1..=10 {
+            tx.send((true, x)).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..=MAX_CONSECUTIVE_HIGH_PRIORITY {
+            received.push(rx.recv().await.unwrap().1);
+        }
+        assert_eq!(received, vec![1, 2, 3, 4, 0]);
     }
 }
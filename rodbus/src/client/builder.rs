@@ -0,0 +1,193 @@
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use crate::client::{
+    Channel, ClientOptions, ClientState, ConnectionInfo, HostAddr, Listener, NullListener,
+    RetryStrategy, SocketCallback,
+};
+use crate::decode::DecodeLevel;
+
+/// Default maximum size of the request queue of a [`ClientChannelConfig`]
+pub const DEFAULT_MAX_QUEUED_REQUESTS: usize = 16;
+
+enum Transport {
+    Tcp,
+    RtuOverTcp,
+    #[cfg(feature = "tls")]
+    Tls(crate::client::TlsClientConfig),
+}
+
+/// Builder for the channels spawned by [`spawn_tcp_client_task`](crate::client::spawn_tcp_client_task),
+/// [`spawn_rtu_over_tcp_client_task`](crate::client::spawn_rtu_over_tcp_client_task), and
+/// [`spawn_tls_client_task`](crate::client::spawn_tls_client_task)
+///
+/// Settings that aren't specified use the following defaults:
+///
+/// * a request queue of [`DEFAULT_MAX_QUEUED_REQUESTS`]. Requests made while the queue is full wait
+///   for room, so the queue only needs to be larger to buffer bursts of requests from many tasks.
+/// * the [`default_retry_strategy`](crate::client::default_retry_strategy)
+/// * [`DecodeLevel::nothing`]
+/// * no state or connection listener
+/// * the defaults of [`ClientOptions`]
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::time::Duration;
+/// use rodbus::client::*;
+///
+/// let channel = ClientChannelConfig::tcp(HostAddr::ip("127.0.0.1".parse()?, 502))
+///     .max_queued_requests(32)
+///     .connect_timeout(Duration::from_secs(5))
+///     .spawn();
+/// channel.enable().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClientChannelConfig {
+    host: HostAddr,
+    transport: Transport,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+    connection_listener: Option<Box<dyn Listener<ConnectionInfo>>>,
+    options: ClientOptions,
+}
+
+impl ClientChannelConfig {
+    /// Configure a channel that connects to `host` over TCP
+    pub fn tcp(host: HostAddr) -> Self {
+        Self::new(host, Transport::Tcp)
+    }
+
+    /// Configure a channel that exchanges RTU frames with `host` over TCP
+    pub fn rtu_over_tcp(host: HostAddr) -> Self {
+        Self::new(host, Transport::RtuOverTcp)
+    }
+
+    /// Configure a channel that connects to `host` over TLS
+    #[cfg(feature = "tls")]
+    pub fn tls(host: HostAddr, tls_config: crate::client::TlsClientConfig) -> Self {
+        Self::new(host, Transport::Tls(tls_config))
+    }
+
+    fn new(host: HostAddr, transport: Transport) -> Self {
+        Self {
+            host,
+            transport,
+            max_queued_requests: DEFAULT_MAX_QUEUED_REQUESTS,
+            retry: crate::client::default_retry_strategy(),
+            decode: DecodeLevel::nothing(),
+            listener: None,
+            connection_listener: None,
+            options: ClientOptions::default(),
+        }
+    }
+
+    /// Maximum number of requests waiting to be sent
+    pub fn max_queued_requests(mut self, value: usize) -> Self {
+        self.max_queued_requests = value;
+        self
+    }
+
+    /// Controls the delay between connection attempts
+    pub fn retry(mut self, value: Box<dyn RetryStrategy>) -> Self {
+        self.retry = value;
+        self
+    }
+
+    /// Initial decode level, see [`Channel::set_decode_level`]
+    pub fn decode(mut self, value: DecodeLevel) -> Self {
+        self.decode = value;
+        self
+    }
+
+    /// Callback that monitors the state of the connection
+    pub fn listener(mut self, value: Box<dyn Listener<ClientState>>) -> Self {
+        self.listener = Some(value);
+        self
+    }
+
+    /// Callback that receives the local and peer address each time the channel connects
+    pub fn connection_listener(mut self, value: Box<dyn Listener<ConnectionInfo>>) -> Self {
+        self.connection_listener = Some(value);
+        self
+    }
+
+    /// See [`ClientOptions::connect_timeout`]
+    pub fn connect_timeout(mut self, value: Duration) -> Self {
+        self.options = self.options.connect_timeout(Some(value));
+        self
+    }
+
+    /// See [`ClientOptions::max_connect_attempts`]
+    pub fn max_connect_attempts(mut self, value: NonZeroUsize) -> Self {
+        self.options = self.options.max_connect_attempts(Some(value));
+        self
+    }
+
+    /// See [`ClientOptions::idle_disconnect`]
+    pub fn idle_disconnect(mut self, value: Duration) -> Self {
+        self.options = self.options.idle_disconnect(Some(value));
+        self
+    }
+
+    /// See [`ClientOptions::local_address`]
+    pub fn local_address(mut self, value: SocketAddr) -> Self {
+        self.options = self.options.local_address(Some(value));
+        self
+    }
+
+    /// See [`ClientOptions::on_socket`]
+    pub fn on_socket(mut self, value: SocketCallback) -> Self {
+        self.options = self.options.on_socket(Some(value));
+        self
+    }
+
+    /// Replace all of the [`ClientOptions`], including those set by the methods above
+    pub fn options(mut self, value: ClientOptions) -> Self {
+        self.options = value;
+        self
+    }
+
+    /// Spawn the channel task onto the runtime
+    ///
+    /// The channel is disabled until [`Channel::enable`] is called. The task completes when the
+    /// returned channel handle is dropped.
+    ///
+    /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+    pub fn spawn(self) -> Channel {
+        let listener = self.listener.unwrap_or_else(|| NullListener::create());
+        let connection_listener = self
+            .connection_listener
+            .unwrap_or_else(|| NullListener::create());
+        let connection_handler = match self.transport {
+            Transport::Tcp => crate::tcp::client::TcpTaskConnectionHandler::Tcp,
+            Transport::RtuOverTcp => crate::tcp::client::TcpTaskConnectionHandler::RtuOverTcp,
+            #[cfg(feature = "tls")]
+            Transport::Tls(tls_config) => {
+                return crate::tcp::tls::client::spawn_tls_channel(
+                    self.host,
+                    self.max_queued_requests,
+                    self.retry,
+                    tls_config,
+                    self.decode,
+                    listener,
+                    connection_listener,
+                    self.options,
+                )
+            }
+        };
+        crate::tcp::client::spawn_tcp_channel(
+            self.host,
+            connection_handler,
+            self.max_queued_requests,
+            self.retry,
+            self.decode,
+            listener,
+            connection_listener,
+            self.options,
+        )
+    }
+}
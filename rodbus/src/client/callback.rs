@@ -0,0 +1,242 @@
+use crate::client::channel::wrap;
+use crate::client::message::{Command, Promise, RequestDetails};
+use crate::client::requests::read_bits::ReadBits;
+use crate::client::requests::read_registers::ReadRegisters;
+use crate::client::requests::write_multiple::{MultipleWriteRequest, WriteMultiple};
+use crate::client::requests::write_single::SingleWrite;
+use crate::client::{Channel, RequestParam};
+use crate::error::{InvalidRange, InvalidRequest, RequestError};
+use crate::types::{AddressRange, BitIterator, Indexed, RegisterIterator};
+
+use tokio::sync::mpsc::error::TrySendError;
+
+/// Non-async channel that reports the result of each request to a callback
+///
+/// Requests are queued without blocking, so the methods may be called from any thread,
+/// with or without a Tokio runtime.
+///
+/// Callbacks are invoked exactly once, from the task that runs the channel on the Tokio runtime.
+/// They must return quickly and must not block, otherwise they delay every other request on the
+/// channel. Applications that process results on their own threads should forward them, e.g.
+/// by sending them on a `std::sync::mpsc` channel from within the callback.
+#[derive(Debug, Clone)]
+pub struct CallbackChannel {
+    tx: crate::channel::Sender<Command>,
+}
+
+/// Reasons a request could not be queued by a [`CallbackChannel`]
+///
+/// The callback of the request is never invoked when one of these errors is returned
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// The request queue of the channel is full
+    Full,
+    /// The task processing requests has terminated
+    Shutdown,
+    /// The request is invalid
+    BadRequest(InvalidRequest),
+}
+
+impl std::error::Error for QueueError {}
+
+impl std::fmt::Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            QueueError::Full => f.write_str("request queue is full"),
+            QueueError::Shutdown => f.write_str("task shutdown"),
+            QueueError::BadRequest(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<InvalidRange> for QueueError {
+    fn from(err: InvalidRange) -> Self {
+        QueueError::BadRequest(err.into())
+    }
+}
+
+impl From<TrySendError<()>> for QueueError {
+    fn from(err: TrySendError<()>) -> Self {
+        match err {
+            TrySendError::Full(_) => QueueError::Full,
+            TrySendError::Closed(_) => QueueError::Shutdown,
+        }
+    }
+}
+
+impl CallbackChannel {
+    /// Create a [`CallbackChannel`] that sends requests to the same task as `channel`
+    pub fn new(channel: Channel) -> Self {
+        Self { tx: channel.tx }
+    }
+
+    /// Read coils from the server
+    pub fn read_coils<C>(
+        &self,
+        param: RequestParam,
+        range: AddressRange,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Vec<Indexed<bool>>, RequestError>) + Send + Sync + 'static,
+    {
+        let range = validate(range)?.of_read_bits()?;
+        self.send(param, || {
+            RequestDetails::ReadCoils(ReadBits::new(range, bits_promise(callback)))
+        })
+    }
+
+    /// Read discrete inputs from the server
+    pub fn read_discrete_inputs<C>(
+        &self,
+        param: RequestParam,
+        range: AddressRange,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Vec<Indexed<bool>>, RequestError>) + Send + Sync + 'static,
+    {
+        let range = validate(range)?.of_read_bits()?;
+        self.send(param, || {
+            RequestDetails::ReadDiscreteInputs(ReadBits::new(range, bits_promise(callback)))
+        })
+    }
+
+    /// Read holding registers from the server
+    pub fn read_holding_registers<C>(
+        &self,
+        param: RequestParam,
+        range: AddressRange,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Vec<Indexed<u16>>, RequestError>) + Send + Sync + 'static,
+    {
+        let range = validate(range)?.of_read_registers()?;
+        self.send(param, || {
+            RequestDetails::ReadHoldingRegisters(ReadRegisters::new(
+                range,
+                registers_promise(callback),
+            ))
+        })
+    }
+
+    /// Read input registers from the server
+    pub fn read_input_registers<C>(
+        &self,
+        param: RequestParam,
+        range: AddressRange,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Vec<Indexed<u16>>, RequestError>) + Send + Sync + 'static,
+    {
+        let range = validate(range)?.of_read_registers()?;
+        self.send(param, || {
+            RequestDetails::ReadInputRegisters(ReadRegisters::new(
+                range,
+                registers_promise(callback),
+            ))
+        })
+    }
+
+    /// Write a single coil on the server
+    pub fn write_single_coil<C>(
+        &self,
+        param: RequestParam,
+        value: Indexed<bool>,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Indexed<bool>, RequestError>) + Send + Sync + 'static,
+    {
+        self.send(param, || {
+            RequestDetails::WriteSingleCoil(SingleWrite::new(value, Promise::new(callback)))
+        })
+    }
+
+    /// Write a single register on the server
+    pub fn write_single_register<C>(
+        &self,
+        param: RequestParam,
+        value: Indexed<u16>,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<Indexed<u16>, RequestError>) + Send + Sync + 'static,
+    {
+        self.send(param, || {
+            RequestDetails::WriteSingleRegister(SingleWrite::new(value, Promise::new(callback)))
+        })
+    }
+
+    /// Write multiple contiguous coils on the server
+    pub fn write_multiple_coils<C>(
+        &self,
+        param: RequestParam,
+        value: WriteMultiple<bool>,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<AddressRange, RequestError>) + Send + Sync + 'static,
+    {
+        self.send(param, || {
+            RequestDetails::WriteMultipleCoils(MultipleWriteRequest::new(
+                value,
+                Promise::new(callback),
+            ))
+        })
+    }
+
+    /// Write multiple contiguous registers on the server
+    pub fn write_multiple_registers<C>(
+        &self,
+        param: RequestParam,
+        value: WriteMultiple<u16>,
+        callback: C,
+    ) -> Result<(), QueueError>
+    where
+        C: FnOnce(Result<AddressRange, RequestError>) + Send + Sync + 'static,
+    {
+        self.send(param, || {
+            RequestDetails::WriteMultipleRegisters(MultipleWriteRequest::new(
+                value,
+                Promise::new(callback),
+            ))
+        })
+    }
+
+    fn send<F>(&self, param: RequestParam, details: F) -> Result<(), QueueError>
+    where
+        F: FnOnce() -> RequestDetails,
+    {
+        // reserve the slot first so that the callback is only created once the request can be queued,
+        // dropping an unsent request would invoke its callback with a shutdown error
+        let permit = self.tx.try_reserve(param.high_priority)?;
+        permit.send(wrap(param, details()));
+        Ok(())
+    }
+}
+
+/// the fields of a range are public, so it may not have been validated by [`AddressRange::try_from`]
+fn validate(range: AddressRange) -> Result<AddressRange, InvalidRange> {
+    AddressRange::try_from(range.start, range.count)
+}
+
+fn bits_promise<C>(callback: C) -> crate::client::requests::read_bits::Promise
+where
+    C: FnOnce(Result<Vec<Indexed<bool>>, RequestError>) + Send + Sync + 'static,
+{
+    crate::client::requests::read_bits::Promise::new(move |x: Result<BitIterator, RequestError>| {
+        callback(x.map(|x| x.collect()))
+    })
+}
+
+fn registers_promise<C>(callback: C) -> crate::client::requests::read_registers::Promise
+where
+    C: FnOnce(Result<Vec<Indexed<u16>>, RequestError>) + Send + Sync + 'static,
+{
+    crate::client::requests::read_registers::Promise::new(
+        move |x: Result<RegisterIterator, RequestError>| callback(x.map(|x| x.collect())),
+    )
+}
@@ -1,27 +1,297 @@
 use std::time::Duration;
 
+use crate::client::coalesce::{ReadCoalescer, ReadCoalescing, ReadKey};
 use crate::client::message::{Command, Promise, Request, RequestDetails, Setting};
 use crate::client::requests::read_bits::ReadBits;
 use crate::client::requests::read_registers::ReadRegisters;
 use crate::client::requests::write_multiple::{MultipleWriteRequest, WriteMultiple};
 use crate::client::requests::write_single::SingleWrite;
+use crate::client::{CancellationToken, ChannelState, Listener, TimeoutTable};
+use crate::common::function::FunctionCode;
 use crate::error::*;
+use crate::exception::ExceptionCode;
+use crate::profile::DeviceProfile;
 use crate::types::{AddressRange, BitIterator, Indexed, RegisterIterator, UnitId};
 use crate::DecodeLevel;
 
 /// Async channel used to make requests
+///
+/// `Channel` is `Send` and `Sync`, and cloning it is cheap. Clones share the same queue of
+/// requests and may be used from any number of tasks:
+///
+/// * Requests are executed one at a time, and each request completes when its response is
+///   received, so requests complete in the order in which they are executed.
+/// * Requests of the same priority are executed in the order in which they enter the queue,
+///   regardless of the clone used. A request enters the queue when the future returned by the
+///   request method is first polled, or, if the queue is full, when space becomes available.
+///   Requests waiting for space are admitted in the order in which they started waiting.
+/// * Requests made concurrently from different tasks are therefore ordered by when they enter
+///   the queue, not by when their futures were created. Awaiting each request before making the
+///   next one guarantees the order.
+/// * [`RequestParam::high_priority`] requests are executed before queued normal priority
+///   requests, and a request that is retried after a reconnect is executed before any other.
 #[derive(Debug, Clone)]
 pub struct Channel {
-    pub(crate) tx: tokio::sync::mpsc::Sender<Command>,
+    pub(crate) tx: crate::channel::Sender<Command>,
+    // attached to every request made on this clone
+    cancel: Option<CancellationToken>,
+    // shared by all the clones
+    pub(crate) coalescer: std::sync::Arc<ReadCoalescer>,
 }
 
 /// Request parameters to dispatch the request to the proper device
+///
+/// Created with [`RequestParam::new`] or [`RequestParam::default_timeout`] and adjusted with the
+/// `with_*` methods.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct RequestParam {
     /// Unit ID of the target device
     pub id: UnitId,
-    /// Response timeout
+    /// Response timeout, measured from when the request has been transmitted
+    ///
+    /// Zero selects the timeout for the function code from the [`TimeoutTable`](crate::client::TimeoutTable) of the channel
     pub response_timeout: Duration,
+    // an instant of the Tokio clock is meaningless in another process, so it's not serialized
+    // and a deserialized value has no deadline
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) deadline: Option<tokio::time::Instant>,
+    /// Maximum time allowed to write the request, `None` waits indefinitely
+    ///
+    /// If the write doesn't complete in time, the request fails with an I/O error and the connection is closed.
+    pub write_timeout: Option<Duration>,
+    /// Number of times a failed request is sent again, zero disables retries
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retries: u8,
+    /// Errors that cause the request to be retried
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retry_on: RetryOn,
+    /// Also retry write requests
+    ///
+    /// Only read requests are retried by default because writes are not idempotent in general
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retry_writes: bool,
+    /// Send the request before any queued normal priority requests
+    ///
+    /// The request never preempts the transaction in progress
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub high_priority: bool,
+    /// Application defined id recorded on the `Transaction` span and returned in the [`ResponseMeta`]
+    ///
+    /// Unlike the transaction id, it doesn't wrap and is chosen by the caller
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub correlation_id: Option<u64>,
+    /// Time waited between the write and the read back of the verified writes such as
+    /// [`Channel::write_single_register_verified`], `None` reads back immediately
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub verify_delay: Option<Duration>,
+}
+
+/// Metadata of a completed request returned by the `_with_meta` methods of [`Channel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseMeta {
+    /// Transaction id of the last attempt, `None` if the request was never sent
+    ///
+    /// It is only transmitted in the MBAP header of TCP and TLS channels, but RTU channels
+    /// also record it on the `Transaction` span
+    pub tx_id: Option<u16>,
+    /// Correlation id of the [`RequestParam`]
+    pub correlation_id: Option<u64>,
+    /// Time spent in the queue of the channel before the first attempt started
+    ///
+    /// Includes the time spent behind other requests and waiting for a connection.
+    pub queue_wait: Duration,
+    /// Time from when the first attempt started until the request completed, including retries
+    pub elapsed: Duration,
+}
+
+impl ResponseMeta {
+    fn unsent(correlation_id: Option<u64>) -> Self {
+        Self {
+            tx_id: None,
+            correlation_id,
+            queue_wait: Duration::ZERO,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+/// Counters maintained by a channel, see [`Channel::statistics`]
+///
+/// The counters are never reset and persist across reconnects.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChannelStatistics {
+    /// Number of responses discarded because their transaction id didn't match the request
+    ///
+    /// These are usually late responses to requests that already timed out.
+    pub stale_responses: u64,
+    /// Number of responses discarded because their unit id didn't match the request
+    ///
+    /// See [`Channel::set_accept_mismatched_unit_id`].
+    pub mismatched_unit_ids: u64,
+    /// Number of transactions that completed with a valid response
+    pub successes: u64,
+    /// Number of transactions answered with an exception
+    pub exceptions: u64,
+    /// Number of transactions without a response before the timeout
+    pub timeouts: u64,
+    /// Number of transactions with a response that couldn't be parsed or didn't match the request
+    pub bad_responses: u64,
+    /// Number of transactions that failed because of the connection or serial port
+    pub io_errors: u64,
+}
+
+impl ChannelStatistics {
+    pub(crate) fn record(&mut self, outcome: Outcome) {
+        let counter = match outcome {
+            Outcome::Ok => &mut self.successes,
+            Outcome::Exception(_) => &mut self.exceptions,
+            Outcome::Timeout => &mut self.timeouts,
+            Outcome::BadResponse => &mut self.bad_responses,
+            Outcome::IoError => &mut self.io_errors,
+        };
+        *counter += 1;
+    }
+}
+
+/// How a transaction completed, see [`TransactionOutcome`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The device returned a valid response
+    Ok,
+    /// The device is reachable but rejected the request with an exception
+    Exception(ExceptionCode),
+    /// No response was received before the timeout
+    Timeout,
+    /// The device responded, but the response couldn't be parsed or didn't match the request
+    BadResponse,
+    /// The connection or serial port failed
+    IoError,
+}
+
+impl Outcome {
+    /// outcome of a transaction, or `None` if the error doesn't involve the device
+    pub(crate) fn from_result(result: &Result<(), RequestError>) -> Option<Self> {
+        let outcome = match result {
+            Ok(()) => Self::Ok,
+            Err(RequestError::Exception(ex)) => Self::Exception(*ex),
+            Err(RequestError::ResponseTimeout) => Self::Timeout,
+            Err(RequestError::BadFrame(_))
+            | Err(RequestError::BadResponse(_))
+            | Err(RequestError::VerifyFailed(_)) => Self::BadResponse,
+            Err(RequestError::Io(_)) | Err(RequestError::NoConnection) => Self::IoError,
+            Err(RequestError::BadRequest(_))
+            | Err(RequestError::Internal(_))
+            | Err(RequestError::Shutdown)
+            | Err(RequestError::TooManyRequests)
+            | Err(RequestError::RetriesExhausted(_))
+            | Err(RequestError::Cancelled) => return None,
+        };
+        Some(outcome)
+    }
+}
+
+/// Transaction completed by a channel, see [`Channel::set_transaction_listener`]
+///
+/// Unlike the [`ClientState`](crate::client::ClientState) of the channel, the outcome tells a
+/// device that is reachable but rejects requests apart from a device that can't be reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionOutcome {
+    /// Unit id of the request
+    pub unit_id: UnitId,
+    /// Function code of the request
+    pub function: FunctionCode,
+    /// How the transaction completed
+    pub outcome: Outcome,
+}
+
+/// Ranges read by [`Channel::read_snapshot`], `None` skips the point type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotSpec {
+    /// Range of coils to read
+    pub coils: Option<AddressRange>,
+    /// Range of discrete inputs to read
+    pub discrete_inputs: Option<AddressRange>,
+    /// Range of holding registers to read
+    pub holding_registers: Option<AddressRange>,
+    /// Range of input registers to read
+    pub input_registers: Option<AddressRange>,
+}
+
+/// Result of [`Channel::read_snapshot`]
+///
+/// Each block succeeds or fails independently, and is `None` if its range wasn't requested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Result of reading the coils
+    pub coils: Option<Result<Vec<Indexed<bool>>, RequestError>>,
+    /// Result of reading the discrete inputs
+    pub discrete_inputs: Option<Result<Vec<Indexed<bool>>, RequestError>>,
+    /// Result of reading the holding registers
+    pub holding_registers: Option<Result<Vec<Indexed<u16>>, RequestError>>,
+    /// Result of reading the input registers
+    pub input_registers: Option<Result<Vec<Indexed<u16>>, RequestError>>,
+}
+
+/// Errors returned by [`Channel::change_serial_settings`]
+#[cfg(feature = "serial")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialSettingsError {
+    /// The channel doesn't use a serial port
+    NotSerial,
+    /// The task processing requests has terminated
+    Shutdown,
+}
+
+#[cfg(feature = "serial")]
+impl std::error::Error for SerialSettingsError {}
+
+#[cfg(feature = "serial")]
+impl std::fmt::Display for SerialSettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            SerialSettingsError::NotSerial => f.write_str("channel doesn't use a serial port"),
+            SerialSettingsError::Shutdown => f.write_str("task shutdown"),
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
+impl From<Shutdown> for SerialSettingsError {
+    fn from(_: Shutdown) -> Self {
+        SerialSettingsError::Shutdown
+    }
+}
+
+/// Action taken when a TCP or TLS channel receives a response whose transaction id doesn't
+/// match the request in progress, see [`Channel::set_stale_response_policy`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StaleResponsePolicy {
+    /// Discard the response and keep waiting for the expected one
+    #[default]
+    Discard,
+    /// Discard the response, but close the connection once the specified number of consecutive
+    /// stale responses is received
+    ///
+    /// The request in progress fails with [`FrameParseError::StaleResponses`] and the channel
+    /// reconnects. A response with the expected transaction id resets the count.
+    AbortSession(std::num::NonZeroUsize),
+}
+
+/// Errors that cause a request to be retried when [`RequestParam::retries`] is non-zero
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RetryOn {
+    /// Retry when no response is received within the response timeout
+    #[default]
+    Timeout,
+    /// Also retry when the response cannot be parsed, e.g. an RTU CRC failure
+    ///
+    /// Bad frames close the connection, so the request is retried before any other
+    /// once the connection has been re-established
+    TimeoutOrBadFrame,
 }
 
 impl RequestParam {
@@ -30,14 +300,84 @@ impl RequestParam {
         Self {
             id,
             response_timeout,
+            deadline: None,
+            write_timeout: None,
+            retries: 0,
+            retry_on: RetryOn::Timeout,
+            retry_writes: false,
+            high_priority: false,
+            correlation_id: None,
+            verify_delay: None,
         }
     }
+
+    /// Create a new `RequestParam` that uses the response timeout for the function code
+    /// from the [`TimeoutTable`](crate::client::TimeoutTable) of the channel
+    pub fn default_timeout(id: UnitId) -> Self {
+        Self::new(id, Duration::ZERO)
+    }
+
+    /// Require the response to be received by `deadline` instead of within the response timeout
+    ///
+    /// The deadline is shared by all attempts when the request is retried. If it passes while the
+    /// request is queued, the request is never sent and fails with [`RequestError::ResponseTimeout`].
+    ///
+    /// The deadline is not serialized with the `serde` feature, a deserialized `RequestParam`
+    /// has no deadline.
+    pub fn with_deadline(mut self, deadline: tokio::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Absolute time by which the response must be received, set with [`RequestParam::with_deadline`]
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.deadline
+    }
+
+    /// Limit how long writing the request may take
+    pub fn with_write_timeout(mut self, value: Option<Duration>) -> Self {
+        self.write_timeout = value;
+        self
+    }
+
+    /// Send the request again up to `retries` times when it fails with one of the `retry_on` errors
+    pub fn with_retries(mut self, retries: u8, retry_on: RetryOn) -> Self {
+        self.retries = retries;
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Send the request before any queued normal priority requests
+    ///
+    /// Normal priority requests are still serviced periodically when high priority requests are made continuously
+    pub fn high_priority(mut self) -> Self {
+        self.high_priority = true;
+        self
+    }
+
+    /// Allow write requests to be retried
+    pub fn with_write_retries(mut self, value: bool) -> Self {
+        self.retry_writes = value;
+        self
+    }
+
+    /// Record `id` on the `Transaction` span and return it in the [`ResponseMeta`]
+    pub fn with_correlation_id(mut self, id: u64) -> Self {
+        self.correlation_id = Some(id);
+        self
+    }
+
+    /// Wait before reading back the values written by the verified writes
+    pub fn with_verify_delay(mut self, value: Option<Duration>) -> Self {
+        self.verify_delay = value;
+        self
+    }
 }
 
 impl Channel {
     #[cfg(feature = "serial")]
     pub(crate) fn spawn_rtu(
-        path: &str,
+        port: crate::serial::PortSource,
         serial_settings: crate::serial::SerialSettings,
         max_queued_requests: usize,
         retry: Box<dyn crate::retry::RetryStrategy>,
@@ -45,7 +385,7 @@ impl Channel {
         listener: Option<Box<dyn crate::client::Listener<crate::client::PortState>>>,
     ) -> Self {
         let (handle, task) = Self::create_rtu_handle_and_task(
-            path,
+            port,
             serial_settings,
             max_queued_requests,
             retry,
@@ -58,7 +398,7 @@ impl Channel {
 
     #[cfg(feature = "serial")]
     pub(crate) fn create_rtu_handle_and_task(
-        path: &str,
+        port: crate::serial::PortSource,
         serial_settings: crate::serial::SerialSettings,
         max_queued_requests: usize,
         retry: Box<dyn crate::retry::RetryStrategy>,
@@ -67,48 +407,179 @@ impl Channel {
     ) -> (Self, impl std::future::Future<Output = ()>) {
         use tracing::Instrument;
 
-        let path = path.to_string();
-        let (tx, rx) = tokio::sync::mpsc::channel(max_queued_requests);
+        let span = tracing::info_span!("Modbus-Client-RTU", "port" = %port);
+        let (tx, rx) = crate::channel::channel(max_queued_requests);
         let task = async move {
             let _ = crate::serial::client::SerialChannelTask::new(
-                &path,
+                port,
                 serial_settings,
-                rx.into(),
+                rx,
                 retry,
                 decode,
                 listener.unwrap_or_else(|| crate::client::NullListener::create()),
             )
             .run()
-            .instrument(tracing::info_span!("Modbus-Client-RTU", "port" = ?path))
+            .instrument(span)
             .await;
         };
-        (Channel { tx }, task)
+        (Channel::new(tx), task)
     }
 
     /// Enable communications
+    ///
+    /// Returns once the task has left the disabled state and started connecting
     pub async fn enable(&self) -> Result<(), Shutdown> {
-        self.tx.send(Command::Setting(Setting::Enable)).await?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::Enable(Some(tx))))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Enable communications without waiting for the task to act on it
+    pub async fn enable_nowait(&self) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::Enable(None)))
+            .await?;
         Ok(())
     }
 
     /// Disable communications
+    ///
+    /// Returns once any established connection has been closed and the channel is disabled
     pub async fn disable(&self) -> Result<(), Shutdown> {
-        self.tx.send(Command::Setting(Setting::Disable)).await?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::Disable(Some(tx))))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Disable communications without waiting for the task to act on it
+    pub async fn disable_nowait(&self) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::Disable(None)))
+            .await?;
         Ok(())
     }
 
+    pub(crate) fn new(tx: crate::channel::Sender<Command>) -> Self {
+        Self {
+            tx,
+            cancel: None,
+            coalescer: Default::default(),
+        }
+    }
+
+    /// Create a clone of the channel whose requests are cancelled by `token`
+    ///
+    /// Once the token is cancelled, queued requests made on the clone fail with
+    /// [`RequestError::Cancelled`] without being sent, and a request in progress stops waiting for
+    /// its response. The late response is discarded: TCP and TLS channels recognize it by its
+    /// transaction id, whereas RTU channels keep waiting for it until the response timeout elapses
+    /// before sending the next request. Requests made on other clones are not affected.
+    pub fn with_cancellation(&self, token: CancellationToken) -> Channel {
+        Self {
+            tx: self.tx.clone(),
+            cancel: Some(token),
+            coalescer: self.coalescer.clone(),
+        }
+    }
+
+    /// Returns `true` if the task processing requests has terminated
+    ///
+    /// Requests made on a closed channel fail with [`RequestError::Shutdown`]. Once closed, a
+    /// channel never re-opens.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
     /// Read coils from the server
     pub async fn read_coils(
         &mut self,
         param: RequestParam,
         range: AddressRange,
     ) -> Result<Vec<Indexed<bool>>, RequestError> {
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        if !self.coalesces_reads() {
+            return self.send_read_coils(param, range).await;
+        }
+        let key = ReadKey::new(param.id, FunctionCode::ReadCoils, range);
+        let mut channel = self.clone();
+        self.coalesce(ReadCoalescer::bits, key, async move {
+            channel.send_read_coils(param, range).await
+        })
+        .await
+    }
+
+    /// Read up to one range of each point type
+    ///
+    /// The requests are queued together in the order coils, discrete inputs, holding registers,
+    /// and input registers, so they are sent back to back without waiting for the caller in
+    /// between. A failed block doesn't prevent the others from being read.
+    pub async fn read_snapshot(&self, param: RequestParam, spec: SnapshotSpec) -> Snapshot {
+        let (mut coils, mut discrete_inputs, mut holding_registers, mut input_registers) =
+            (self.clone(), self.clone(), self.clone(), self.clone());
+        let (coils, discrete_inputs, holding_registers, input_registers) = tokio::join!(
+            read_optional(spec.coils, move |range| async move {
+                coils.read_coils(param, range).await
+            }),
+            read_optional(spec.discrete_inputs, move |range| async move {
+                discrete_inputs.read_discrete_inputs(param, range).await
+            }),
+            read_optional(spec.holding_registers, move |range| async move {
+                holding_registers.read_holding_registers(param, range).await
+            }),
+            read_optional(spec.input_registers, move |range| async move {
+                input_registers.read_input_registers(param, range).await
+            }),
+        );
+        Snapshot {
+            coils,
+            discrete_inputs,
+            holding_registers,
+            input_registers,
+        }
+    }
+
+    /// Read coils from the server into `values`
+    ///
+    /// `values` is cleared and filled with the value of each coil in the range,
+    /// reusing its allocation across requests
+    pub async fn read_coils_into(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        values: &mut Vec<bool>,
+    ) -> Result<(), RequestError> {
+        let range = range.of_read_bits()?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
         let request = wrap(
             param,
-            RequestDetails::ReadCoils(ReadBits::channel(range.of_read_bits()?, tx)),
+            RequestDetails::ReadCoils(ReadBits::buffer(range, std::mem::take(values), tx)),
         );
-        self.tx.send(request).await?;
+        self.send_into(request, rx, values).await
+    }
+
+    /// Read coils from the server, invoking `f` with an iterator that borrows the response
+    ///
+    /// `f` runs on the client task as soon as the response is parsed, so it should return quickly.
+    /// Its return value is the result of the request.
+    pub async fn read_coils_with<F, R>(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        f: F,
+    ) -> Result<R, RequestError>
+    where
+        F: FnOnce(BitIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<R, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadCoils(ReadBits::with(range.of_read_bits()?, f, tx)),
+        );
+        self.send(request).await?;
         rx.await?
     }
 
@@ -118,12 +589,56 @@ impl Channel {
         param: RequestParam,
         range: AddressRange,
     ) -> Result<Vec<Indexed<bool>>, RequestError> {
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        if !self.coalesces_reads() {
+            return self.send_read_discrete_inputs(param, range).await;
+        }
+        let key = ReadKey::new(param.id, FunctionCode::ReadDiscreteInputs, range);
+        let mut channel = self.clone();
+        self.coalesce(ReadCoalescer::bits, key, async move {
+            channel.send_read_discrete_inputs(param, range).await
+        })
+        .await
+    }
+
+    /// Read discrete inputs from the server into `values`
+    ///
+    /// `values` is cleared and filled with the value of each discrete input in the range,
+    /// reusing its allocation across requests
+    pub async fn read_discrete_inputs_into(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        values: &mut Vec<bool>,
+    ) -> Result<(), RequestError> {
+        let range = range.of_read_bits()?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
         let request = wrap(
             param,
-            RequestDetails::ReadDiscreteInputs(ReadBits::channel(range.of_read_bits()?, tx)),
+            RequestDetails::ReadDiscreteInputs(ReadBits::buffer(range, std::mem::take(values), tx)),
         );
-        self.tx.send(request).await?;
+        self.send_into(request, rx, values).await
+    }
+
+    /// Read discrete inputs from the server, invoking `f` with an iterator that borrows the response
+    ///
+    /// `f` runs on the client task as soon as the response is parsed, so it should return quickly.
+    /// Its return value is the result of the request.
+    pub async fn read_discrete_inputs_with<F, R>(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        f: F,
+    ) -> Result<R, RequestError>
+    where
+        F: FnOnce(BitIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<R, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadDiscreteInputs(ReadBits::with(range.of_read_bits()?, f, tx)),
+        );
+        self.send(request).await?;
         rx.await?
     }
 
@@ -133,15 +648,64 @@ impl Channel {
         param: RequestParam,
         range: AddressRange,
     ) -> Result<Vec<Indexed<u16>>, RequestError> {
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        if !self.coalesces_reads() {
+            return self.send_read_holding_registers(param, range).await;
+        }
+        let key = ReadKey::new(param.id, FunctionCode::ReadHoldingRegisters, range);
+        let mut channel = self.clone();
+        self.coalesce(ReadCoalescer::registers, key, async move {
+            channel.send_read_holding_registers(param, range).await
+        })
+        .await
+    }
+
+    /// Read holding registers from the server into `values`
+    ///
+    /// `values` is cleared and filled with the value of each holding register in the range,
+    /// reusing its allocation across requests
+    pub async fn read_holding_registers_into(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        values: &mut Vec<u16>,
+    ) -> Result<(), RequestError> {
+        let range = range.of_read_registers()?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
         let request = wrap(
             param,
-            RequestDetails::ReadHoldingRegisters(ReadRegisters::channel(
+            RequestDetails::ReadHoldingRegisters(ReadRegisters::buffer(
+                range,
+                std::mem::take(values),
+                tx,
+            )),
+        );
+        self.send_into(request, rx, values).await
+    }
+
+    /// Read holding registers from the server, invoking `f` with an iterator that borrows the response
+    ///
+    /// `f` runs on the client task as soon as the response is parsed, so it should return quickly.
+    /// Its return value is the result of the request.
+    pub async fn read_holding_registers_with<F, R>(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        f: F,
+    ) -> Result<R, RequestError>
+    where
+        F: FnOnce(RegisterIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<R, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadHoldingRegisters(ReadRegisters::with(
                 range.of_read_registers()?,
+                f,
                 tx,
             )),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
@@ -151,15 +715,64 @@ impl Channel {
         param: RequestParam,
         range: AddressRange,
     ) -> Result<Vec<Indexed<u16>>, RequestError> {
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        if !self.coalesces_reads() {
+            return self.send_read_input_registers(param, range).await;
+        }
+        let key = ReadKey::new(param.id, FunctionCode::ReadInputRegisters, range);
+        let mut channel = self.clone();
+        self.coalesce(ReadCoalescer::registers, key, async move {
+            channel.send_read_input_registers(param, range).await
+        })
+        .await
+    }
+
+    /// Read input registers from the server into `values`
+    ///
+    /// `values` is cleared and filled with the value of each input register in the range,
+    /// reusing its allocation across requests
+    pub async fn read_input_registers_into(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        values: &mut Vec<u16>,
+    ) -> Result<(), RequestError> {
+        let range = range.of_read_registers()?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
         let request = wrap(
             param,
-            RequestDetails::ReadInputRegisters(ReadRegisters::channel(
+            RequestDetails::ReadInputRegisters(ReadRegisters::buffer(
+                range,
+                std::mem::take(values),
+                tx,
+            )),
+        );
+        self.send_into(request, rx, values).await
+    }
+
+    /// Read input registers from the server, invoking `f` with an iterator that borrows the response
+    ///
+    /// `f` runs on the client task as soon as the response is parsed, so it should return quickly.
+    /// Its return value is the result of the request.
+    pub async fn read_input_registers_with<F, R>(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        f: F,
+    ) -> Result<R, RequestError>
+    where
+        F: FnOnce(RegisterIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<R, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadInputRegisters(ReadRegisters::with(
                 range.of_read_registers()?,
+                f,
                 tx,
             )),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
@@ -174,7 +787,7 @@ impl Channel {
             param,
             RequestDetails::WriteSingleCoil(SingleWrite::new(request, Promise::channel(tx))),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
@@ -189,7 +802,7 @@ impl Channel {
             param,
             RequestDetails::WriteSingleRegister(SingleWrite::new(request, Promise::channel(tx))),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
@@ -207,7 +820,7 @@ impl Channel {
                 Promise::channel(tx),
             )),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
@@ -225,17 +838,623 @@ impl Channel {
                 Promise::channel(tx),
             )),
         );
-        self.tx.send(request).await?;
+        self.send(request).await?;
         rx.await?
     }
 
+    /// Read coils from the server after checking that `range` lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn read_coils_checked(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        profile: &DeviceProfile,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        profile.validate(FunctionCode::ReadCoils, range)?;
+        self.read_coils(param, range).await
+    }
+
+    /// Read discrete inputs from the server after checking that `range` lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn read_discrete_inputs_checked(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        profile: &DeviceProfile,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        profile.validate(FunctionCode::ReadDiscreteInputs, range)?;
+        self.read_discrete_inputs(param, range).await
+    }
+
+    /// Read holding registers from the server after checking that `range` lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn read_holding_registers_checked(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        profile: &DeviceProfile,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        profile.validate(FunctionCode::ReadHoldingRegisters, range)?;
+        self.read_holding_registers(param, range).await
+    }
+
+    /// Read input registers from the server after checking that `range` lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn read_input_registers_checked(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+        profile: &DeviceProfile,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        profile.validate(FunctionCode::ReadInputRegisters, range)?;
+        self.read_input_registers(param, range).await
+    }
+
+    /// Write a single coil on the server after checking that its index lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn write_single_coil_checked(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<bool>,
+        profile: &DeviceProfile,
+    ) -> Result<Indexed<bool>, RequestError> {
+        profile.validate_address(FunctionCode::WriteSingleCoil, request.index)?;
+        self.write_single_coil(param, request).await
+    }
+
+    /// Write a single register on the server after checking that its index lies within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn write_single_register_checked(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<u16>,
+        profile: &DeviceProfile,
+    ) -> Result<Indexed<u16>, RequestError> {
+        profile.validate_address(FunctionCode::WriteSingleRegister, request.index)?;
+        self.write_single_register(param, request).await
+    }
+
+    /// Write multiple contiguous coils on the server after checking that they lie within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn write_multiple_coils_checked(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<bool>,
+        profile: &DeviceProfile,
+    ) -> Result<AddressRange, RequestError> {
+        profile.validate(FunctionCode::WriteMultipleCoils, request.range)?;
+        self.write_multiple_coils(param, request).await
+    }
+
+    /// Write multiple contiguous registers on the server after checking that they lie within `profile`
+    ///
+    /// Fails with [`InvalidRequest::OutOfProfile`] without sending anything otherwise.
+    pub async fn write_multiple_registers_checked(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<u16>,
+        profile: &DeviceProfile,
+    ) -> Result<AddressRange, RequestError> {
+        profile.validate(FunctionCode::WriteMultipleRegisters, request.range)?;
+        self.write_multiple_registers(param, request).await
+    }
+
+    /// Write a single coil on the server, then read it back
+    ///
+    /// The coil is read after the [`RequestParam::verify_delay`] with the same parameters.
+    /// Fails with [`RequestError::VerifyFailed`] if it doesn't have the written value.
+    pub async fn write_single_coil_verified(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<bool>,
+    ) -> Result<Indexed<bool>, RequestError> {
+        let response = self.write_single_coil(param, request).await?;
+        let range = AddressRange::try_from(request.index, 1)?;
+        Self::wait_verify_delay(param).await;
+        let actual = self.send_read_coils(param, range).await?;
+        verify(std::iter::once(request), actual)?;
+        Ok(response)
+    }
+
+    /// Write a single register on the server, then read it back
+    ///
+    /// The holding register is read after the [`RequestParam::verify_delay`] with the same
+    /// parameters. Fails with [`RequestError::VerifyFailed`] if it doesn't have the written value.
+    pub async fn write_single_register_verified(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<u16>,
+    ) -> Result<Indexed<u16>, RequestError> {
+        let response = self.write_single_register(param, request).await?;
+        let range = AddressRange::try_from(request.index, 1)?;
+        Self::wait_verify_delay(param).await;
+        let actual = self.send_read_holding_registers(param, range).await?;
+        verify(std::iter::once(request), actual)?;
+        Ok(response)
+    }
+
+    /// Write multiple contiguous coils on the server, then read them back
+    ///
+    /// The coils are read after the [`RequestParam::verify_delay`] with the same parameters.
+    /// Fails with [`RequestError::VerifyFailed`] on the first coil that doesn't have the written value.
+    pub async fn write_multiple_coils_verified(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<bool>,
+    ) -> Result<AddressRange, RequestError> {
+        let expected: Vec<Indexed<bool>> = request.iter().collect();
+        let range = self.write_multiple_coils(param, request).await?;
+        Self::wait_verify_delay(param).await;
+        let actual = self.send_read_coils(param, range).await?;
+        verify(expected, actual)?;
+        Ok(range)
+    }
+
+    /// Write multiple contiguous registers on the server, then read them back
+    ///
+    /// The holding registers are read after the [`RequestParam::verify_delay`] with the same
+    /// parameters. Fails with [`RequestError::VerifyFailed`] on the first register that doesn't
+    /// have the written value.
+    pub async fn write_multiple_registers_verified(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<u16>,
+    ) -> Result<AddressRange, RequestError> {
+        let expected: Vec<Indexed<u16>> = request.iter().collect();
+        let range = self.write_multiple_registers(param, request).await?;
+        Self::wait_verify_delay(param).await;
+        let actual = self.send_read_holding_registers(param, range).await?;
+        verify(expected, actual)?;
+        Ok(range)
+    }
+
+    async fn wait_verify_delay(param: RequestParam) {
+        if let Some(delay) = param.verify_delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Read coils from the server, also returning the [`ResponseMeta`] of the request
+    pub async fn read_coils_with_meta(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> (Result<Vec<Indexed<bool>>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        let details = match range.of_read_bits() {
+            Ok(range) => RequestDetails::ReadCoils(ReadBits::channel(range, tx)),
+            Err(err) => return (Err(err.into()), ResponseMeta::unsent(param.correlation_id)),
+        };
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Read discrete inputs from the server, also returning the [`ResponseMeta`] of the request
+    pub async fn read_discrete_inputs_with_meta(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> (Result<Vec<Indexed<bool>>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        let details = match range.of_read_bits() {
+            Ok(range) => RequestDetails::ReadDiscreteInputs(ReadBits::channel(range, tx)),
+            Err(err) => return (Err(err.into()), ResponseMeta::unsent(param.correlation_id)),
+        };
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Read holding registers from the server, also returning the [`ResponseMeta`] of the request
+    pub async fn read_holding_registers_with_meta(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> (Result<Vec<Indexed<u16>>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        let details = match range.of_read_registers() {
+            Ok(range) => RequestDetails::ReadHoldingRegisters(ReadRegisters::channel(range, tx)),
+            Err(err) => return (Err(err.into()), ResponseMeta::unsent(param.correlation_id)),
+        };
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Read input registers from the server, also returning the [`ResponseMeta`] of the request
+    pub async fn read_input_registers_with_meta(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> (Result<Vec<Indexed<u16>>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        let details = match range.of_read_registers() {
+            Ok(range) => RequestDetails::ReadInputRegisters(ReadRegisters::channel(range, tx)),
+            Err(err) => return (Err(err.into()), ResponseMeta::unsent(param.correlation_id)),
+        };
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Write a single coil on the server, also returning the [`ResponseMeta`] of the request
+    pub async fn write_single_coil_with_meta(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<bool>,
+    ) -> (Result<Indexed<bool>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Indexed<bool>, RequestError>>();
+        let details =
+            RequestDetails::WriteSingleCoil(SingleWrite::new(request, Promise::channel(tx)));
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Write a single register on the server, also returning the [`ResponseMeta`] of the request
+    pub async fn write_single_register_with_meta(
+        &mut self,
+        param: RequestParam,
+        request: Indexed<u16>,
+    ) -> (Result<Indexed<u16>, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Indexed<u16>, RequestError>>();
+        let details =
+            RequestDetails::WriteSingleRegister(SingleWrite::new(request, Promise::channel(tx)));
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Write multiple contiguous coils on the server, also returning the [`ResponseMeta`] of the request
+    pub async fn write_multiple_coils_with_meta(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<bool>,
+    ) -> (Result<AddressRange, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<AddressRange, RequestError>>();
+        let details = RequestDetails::WriteMultipleCoils(MultipleWriteRequest::new(
+            request,
+            Promise::channel(tx),
+        ));
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Write multiple contiguous registers on the server, also returning the [`ResponseMeta`] of the request
+    pub async fn write_multiple_registers_with_meta(
+        &mut self,
+        param: RequestParam,
+        request: WriteMultiple<u16>,
+    ) -> (Result<AddressRange, RequestError>, ResponseMeta) {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<AddressRange, RequestError>>();
+        let details = RequestDetails::WriteMultipleRegisters(MultipleWriteRequest::new(
+            request,
+            Promise::channel(tx),
+        ));
+        self.send_with_meta(Request::new(param, details), rx).await
+    }
+
+    /// Whether the reads of this clone are coalesced, checked without locking the coalescer
+    fn coalesces_reads(&self) -> bool {
+        // the requests of a clone with a token must be cancellable on their own
+        self.cancel.is_none() && self.coalescer.is_enabled()
+    }
+
+    /// Attach a read to an identical one, or run it on its own task so that reads attached to
+    /// it still complete if this caller stops waiting
+    async fn coalesce<T, R>(
+        &self,
+        select: fn(&ReadCoalescer) -> &crate::client::coalesce::Coalescer<T>,
+        key: ReadKey,
+        read: R,
+    ) -> Result<Vec<Indexed<T>>, RequestError>
+    where
+        T: Clone + Send + 'static,
+        R: std::future::Future<Output = Result<Vec<Indexed<T>>, RequestError>> + Send + 'static,
+    {
+        crate::client::coalesce::read(&self.coalescer, select, key, read).await
+    }
+
+    async fn send_read_coils(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadCoils(ReadBits::channel(range.of_read_bits()?, tx)),
+        );
+        self.send(request).await?;
+        rx.await?
+    }
+
+    async fn send_read_discrete_inputs(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<bool>>, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadDiscreteInputs(ReadBits::channel(range.of_read_bits()?, tx)),
+        );
+        self.send(request).await?;
+        rx.await?
+    }
+
+    async fn send_read_holding_registers(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadHoldingRegisters(ReadRegisters::channel(
+                range.of_read_registers()?,
+                tx,
+            )),
+        );
+        self.send(request).await?;
+        rx.await?
+    }
+
+    async fn send_read_input_registers(
+        &mut self,
+        param: RequestParam,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<Indexed<u16>>, RequestError>>();
+        let request = wrap(
+            param,
+            RequestDetails::ReadInputRegisters(ReadRegisters::channel(
+                range.of_read_registers()?,
+                tx,
+            )),
+        );
+        self.send(request).await?;
+        rx.await?
+    }
+
+    /// Send a command, attaching the cancellation token of this clone to requests
+    async fn send(
+        &self,
+        mut command: Command,
+    ) -> Result<(), tokio::sync::mpsc::error::SendError<Command>> {
+        if let Command::Request(request) = &mut command {
+            request.cancel.clone_from(&self.cancel);
+        }
+        self.tx.send(command).await
+    }
+
+    async fn send_with_meta<T>(
+        &mut self,
+        mut request: Request,
+        rx: tokio::sync::oneshot::Receiver<Result<T, RequestError>>,
+    ) -> (Result<T, RequestError>, ResponseMeta) {
+        let unsent = ResponseMeta::unsent(request.correlation_id);
+        let (meta_tx, meta_rx) = tokio::sync::oneshot::channel();
+        request.meta = Some(meta_tx);
+        if let Err(err) = self.send(Command::Request(request)).await {
+            return (Err(err.into()), unsent);
+        }
+        let result = rx.await.unwrap_or(Err(RequestError::Shutdown));
+        // requests failed without being sent drop the sender
+        let meta = meta_rx.await.unwrap_or(unsent);
+        (result, meta)
+    }
+
+    async fn send_into<T>(
+        &mut self,
+        request: Command,
+        rx: tokio::sync::oneshot::Receiver<(Vec<T>, Result<(), RequestError>)>,
+        values: &mut Vec<T>,
+    ) -> Result<(), RequestError> {
+        // a request that can't be sent is dropped here, which hands the buffer back via its promise
+        let sent = self.send(request).await.map_err(RequestError::from);
+        let (buffer, result) = rx.await?;
+        *values = buffer;
+        sent?;
+        result
+    }
+
+    /// Query the state of the channel, as last reported to its listener
+    pub async fn current_state(&self) -> Result<ChannelState, Shutdown> {
+        let state = self.subscribe_state().await?;
+        let current = *state.borrow();
+        Ok(current)
+    }
+
+    /// Wait until the channel is connected, or its serial port is open, for up to `timeout`
+    ///
+    /// Returns `false` if the timeout elapsed first. The channel must be enabled separately.
+    pub async fn wait_for_connected(&self, timeout: Duration) -> Result<bool, Shutdown> {
+        let wait = async {
+            let mut state = self.subscribe_state().await?;
+            // fails if the task shuts down while waiting
+            state
+                .wait_for(ChannelState::is_connected)
+                .await
+                .map_err(|_| Shutdown)?;
+            Ok::<(), Shutdown>(())
+        };
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(result) => result.map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    async fn subscribe_state(
+        &self,
+    ) -> Result<tokio::sync::watch::Receiver<ChannelState>, Shutdown> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::SubscribeState(tx)))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Retrieve the parameters of the TLS session and the certificate presented by the server
+    ///
+    /// Returns `None` if the channel isn't currently connected or doesn't use TLS
+    #[cfg(feature = "tls")]
+    pub async fn tls_info(&self) -> Result<Option<crate::tcp::tls::TlsInfo>, Shutdown> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::GetTlsInfo(tx)))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Fail every queued request with [`RequestError::Cancelled`], returning how many were failed
+    ///
+    /// The purge overtakes queued normal priority requests, but not high priority requests that
+    /// were queued before it. A request waiting to be retried after a reconnect is also failed,
+    /// whereas the request in progress completes normally. Settings in the queue still apply.
+    pub async fn purge_queue(&self) -> Result<usize, Shutdown> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::PurgeQueue(tx)))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Set a listener that receives the outcome of every transaction, or remove it with `None`
+    ///
+    /// Every attempt to send a request is reported separately, so a request that is retried
+    /// produces an outcome for each attempt. Requests that fail without being sent, e.g. because
+    /// they were cancelled, are not reported. The outcomes are counted in the
+    /// [`ChannelStatistics`] whether or not a listener is set.
+    ///
+    /// The channel waits for the listener before processing the next request.
+    pub async fn set_transaction_listener(
+        &mut self,
+        listener: Option<Box<dyn Listener<TransactionOutcome>>>,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::TransactionListener(listener)))
+            .await?;
+        Ok(())
+    }
+
+    /// Retrieve the counters maintained by the channel
+    pub async fn statistics(&self) -> Result<ChannelStatistics, Shutdown> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::GetStatistics(tx)))
+            .await?;
+        rx.await.map_err(|_| Shutdown)
+    }
+
+    /// Close the serial port of an RTU channel and re-open it with different settings
+    ///
+    /// Queued requests are kept and sent once the port is re-open. The [`PortState`](crate::client::PortState)
+    /// listener observes [`PortState::Reconfiguring`](crate::client::PortState::Reconfiguring)
+    /// followed by the outcome of opening the port. If the channel is disabled or waiting to
+    /// retry, the settings are used the next time the port is opened.
+    ///
+    /// Channels that don't use a serial port return [`SerialSettingsError::NotSerial`].
+    #[cfg(feature = "serial")]
+    pub async fn change_serial_settings(
+        &mut self,
+        settings: crate::serial::SerialSettings,
+    ) -> Result<(), SerialSettingsError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Command::Setting(Setting::SerialSettings(settings, tx)))
+            .await
+            .map_err(|_| SerialSettingsError::Shutdown)?;
+        rx.await.map_err(|_| SerialSettingsError::Shutdown)?
+    }
+
+    /// Change how responses with an unexpected transaction id are handled
+    ///
+    /// Defaults to [`StaleResponsePolicy::Discard`]. RTU frames don't have a transaction id,
+    /// so the policy has no effect on RTU channels.
+    pub async fn set_stale_response_policy(
+        &mut self,
+        policy: StaleResponsePolicy,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::StaleResponsePolicy(policy)))
+            .await?;
+        Ok(())
+    }
+
+    /// Accept responses whose unit id differs from the unit id of the request
+    ///
+    /// By default, such responses are discarded with a warning and counted in
+    /// [`ChannelStatistics::mismatched_unit_ids`], and the channel keeps waiting for the response
+    /// until the timeout elapses. Some devices always respond with the same unit id regardless of
+    /// the request, enabling this option tolerates them.
+    pub async fn set_accept_mismatched_unit_id(&mut self, accept: bool) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::AcceptMismatchedUnitId(accept)))
+            .await?;
+        Ok(())
+    }
+
+    /// Coalesce identical reads made on this channel and its clones, or stop coalescing with `None`
+    ///
+    /// While enabled, a call to [`Channel::read_coils`], [`Channel::read_discrete_inputs`],
+    /// [`Channel::read_holding_registers`] or [`Channel::read_input_registers`] that targets the
+    /// same unit id, function code, and range as a queued or in flight read attaches to that
+    /// transaction instead of sending its own request, and every caller receives a copy of the
+    /// result or of the error. The transaction uses the [`RequestParam`] of the first read.
+    /// With [`ReadCoalescing::cache_ttl`], successful results are also returned to identical
+    /// reads made shortly after the transaction completes, without sending anything.
+    ///
+    /// Writes, the `_into` and `_with` variants of the reads, and requests made on clones created
+    /// with [`Channel::with_cancellation`] are never coalesced. Changing the setting forgets the
+    /// cached results but reads that are already attached to a transaction still complete.
+    pub fn set_read_coalescing(&self, coalescing: Option<ReadCoalescing>) {
+        self.coalescer.configure(coalescing);
+    }
+
     /// Dynamically change the protocol decoding level of the channel
+    ///
+    /// The level takes effect for the next request on TCP, TLS and RTU channels alike.
     pub async fn set_decode_level(&mut self, level: DecodeLevel) -> Result<(), Shutdown> {
         self.tx
             .send(Command::Setting(Setting::DecodeLevel(level)))
             .await?;
         Ok(())
     }
+
+    /// Wait for the specified duration after each transaction before sending the next request
+    ///
+    /// Some serial devices miss requests that arrive too soon after they finish transmitting a
+    /// response. The delay applies after successful and failed transactions, including between
+    /// the attempts of a retried request. `None` (the default) sends the next request right away.
+    pub async fn set_inter_request_delay(
+        &mut self,
+        delay: Option<std::time::Duration>,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::InterRequestDelay(delay)))
+            .await?;
+        Ok(())
+    }
+
+    /// Install a sink that receives the raw bytes sent and received by the channel
+    ///
+    /// `None` removes any sink. The sink is kept across reconnects and is dropped when it's
+    /// replaced or the channel shuts down.
+    pub async fn set_capture(
+        &mut self,
+        sink: Option<Box<dyn crate::capture::FrameSink>>,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::Capture(
+                sink.map(crate::capture::new_capture),
+            )))
+            .await?;
+        Ok(())
+    }
+
+    /// Change the response timeouts used for requests made with [`RequestParam::default_timeout`]
+    pub async fn set_timeout_table(&mut self, table: TimeoutTable) -> Result<(), Shutdown> {
+        self.tx
+            .send(Command::Setting(Setting::Timeouts(table)))
+            .await?;
+        Ok(())
+    }
 }
 
 /// Callback-based session
@@ -245,11 +1464,11 @@ impl Channel {
 /// interacting with the channel directly.
 #[deprecated(
     since = "1.4.0",
-    note = "Use Channel. This type will be removed in 2.0"
+    note = "Use Channel. This type will be removed in a future major release"
 )]
 #[derive(Debug, Clone)]
 pub struct CallbackSession {
-    tx: tokio::sync::mpsc::Sender<Command>,
+    tx: crate::channel::Sender<Command>,
     param: RequestParam,
 }
 
@@ -390,6 +1609,41 @@ impl CallbackSession {
     }
 }
 
+async fn read_optional<T, F, R>(
+    range: Option<AddressRange>,
+    read: F,
+) -> Option<Result<T, RequestError>>
+where
+    F: FnOnce(AddressRange) -> R,
+    R: std::future::Future<Output = Result<T, RequestError>>,
+{
+    match range {
+        Some(range) => Some(read(range).await),
+        None => None,
+    }
+}
+
+/// Compare the values written by a verified write with the values read back
+///
+/// The values are read back with the `send_read_*` methods so that they never come from the
+/// read coalescing cache.
+fn verify<T, E>(expected: E, actual: Vec<Indexed<T>>) -> Result<(), VerifyError>
+where
+    T: Copy + PartialEq + Into<u16>,
+    E: IntoIterator<Item = Indexed<T>>,
+{
+    for (expected, actual) in expected.into_iter().zip(actual) {
+        if expected.value != actual.value {
+            return Err(VerifyError {
+                index: expected.index,
+                expected: expected.value.into(),
+                actual: actual.value.into(),
+            });
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn wrap(param: RequestParam, details: RequestDetails) -> Command {
-    Command::Request(Request::new(param.id, param.response_timeout, details))
+    Command::Request(Request::new(param, details))
 }
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::common::function::FunctionCode;
+use crate::error::RequestError;
+use crate::types::{AddressRange, Indexed, UnitId};
+
+/// Settings of the read coalescing of a [`Channel`](crate::client::Channel), see
+/// [`Channel::set_read_coalescing`](crate::client::Channel::set_read_coalescing)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadCoalescing {
+    pub(crate) window: Duration,
+    pub(crate) cache_ttl: Option<Duration>,
+}
+
+impl ReadCoalescing {
+    /// Attach identical reads to a transaction for up to `coalesce_window` after it was queued
+    ///
+    /// Once the window has elapsed, an identical read starts a new transaction even if the first
+    /// one is still queued or in flight.
+    pub fn new(coalesce_window: Duration) -> Self {
+        Self {
+            window: coalesce_window,
+            cache_ttl: None,
+        }
+    }
+
+    /// Serve the successful result of a transaction to identical reads made up to `ttl` after it
+    /// completed, without sending anything
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+}
+
+/// Identifies reads that may share the same transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ReadKey {
+    unit_id: UnitId,
+    function: FunctionCode,
+    start: u16,
+    count: u16,
+}
+
+impl ReadKey {
+    pub(crate) fn new(unit_id: UnitId, function: FunctionCode, range: AddressRange) -> Self {
+        Self {
+            unit_id,
+            function,
+            start: range.start,
+            count: range.count,
+        }
+    }
+}
+
+type ReadResult<T> = Result<Vec<Indexed<T>>, RequestError>;
+type Waiter<T> = tokio::sync::oneshot::Sender<ReadResult<T>>;
+
+/// Coalescing state shared by all the clones of a channel
+#[derive(Debug, Default)]
+pub(crate) struct ReadCoalescer {
+    // read without locking the coalescers so that reads of channels without coalescing are cheap
+    enabled: AtomicBool,
+    bits: Coalescer<bool>,
+    registers: Coalescer<u16>,
+}
+
+impl ReadCoalescer {
+    pub(crate) fn configure(&self, config: Option<ReadCoalescing>) {
+        self.bits.configure(config);
+        self.registers.configure(config);
+        self.enabled.store(config.is_some(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn bits(&self) -> &Coalescer<bool> {
+        &self.bits
+    }
+
+    pub(crate) fn registers(&self) -> &Coalescer<u16> {
+        &self.registers
+    }
+
+    #[cfg(test)]
+    pub(crate) fn entries(&self) -> usize {
+        self.bits.entries() + self.registers.entries()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Coalescer<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Default for Coalescer<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                config: None,
+                next_id: 0,
+                open: BTreeMap::new(),
+                waiters: BTreeMap::new(),
+                cache: BTreeMap::new(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    config: Option<ReadCoalescing>,
+    next_id: u64,
+    // transactions that identical reads may still attach to
+    open: BTreeMap<ReadKey, Open>,
+    // callers waiting for each transaction, including the one that started it
+    waiters: BTreeMap<u64, Vec<Waiter<T>>>,
+    cache: BTreeMap<ReadKey, Cached<T>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Open {
+    id: u64,
+    started: Instant,
+}
+
+#[derive(Debug)]
+struct Cached<T> {
+    expires: Instant,
+    values: Vec<Indexed<T>>,
+}
+
+pub(crate) enum Join<T> {
+    /// Result of a transaction that completed less than the cache TTL ago
+    Cached(Vec<Indexed<T>>),
+    /// Attached to a queued or in flight transaction
+    Wait(tokio::sync::oneshot::Receiver<ReadResult<T>>),
+    /// Must start the transaction with this id and complete it
+    Lead(u64, tokio::sync::oneshot::Receiver<ReadResult<T>>),
+}
+
+impl<T> Coalescer<T>
+where
+    T: Clone,
+{
+    fn configure(&self, config: Option<ReadCoalescing>) {
+        let mut state = self.state.lock().unwrap();
+        state.config = config;
+        // transactions in progress still complete their waiters
+        state.open.clear();
+        state.cache.clear();
+    }
+
+    /// Returns `None` if coalescing is disabled
+    pub(crate) fn join(&self, key: ReadKey) -> Option<Join<T>> {
+        let mut state = self.state.lock().unwrap();
+        let config = state.config?;
+        let now = Instant::now();
+
+        state.cache.retain(|_, x| x.expires > now);
+        if let Some(cached) = state.cache.get(&key) {
+            return Some(Join::Cached(cached.values.clone()));
+        }
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        if let Some(open) = state.open.get(&key).copied() {
+            if now.duration_since(open.started) <= config.window {
+                if let Some(waiters) = state.waiters.get_mut(&open.id) {
+                    waiters.push(tx);
+                    return Some(Join::Wait(rx));
+                }
+            }
+        }
+
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        state.open.insert(key, Open { id, started: now });
+        state.waiters.insert(id, vec![tx]);
+        Some(Join::Lead(id, rx))
+    }
+
+    /// Reply to every caller waiting for the transaction, returning how long the result is cached
+    pub(crate) fn complete(
+        &self,
+        key: ReadKey,
+        id: u64,
+        result: ReadResult<T>,
+    ) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.open.get(&key).is_some_and(|x| x.id == id) {
+            state.open.remove(&key);
+        }
+
+        let ttl = match (&result, state.config.and_then(|x| x.cache_ttl)) {
+            (Ok(values), Some(ttl)) => {
+                state.cache.insert(
+                    key,
+                    Cached {
+                        expires: Instant::now() + ttl,
+                        values: values.clone(),
+                    },
+                );
+                Some(ttl)
+            }
+            _ => None,
+        };
+
+        for waiter in state.waiters.remove(&id).unwrap_or_default() {
+            // the caller may have given up waiting
+            let _ = waiter.send(result.clone());
+        }
+
+        ttl
+    }
+
+    pub(crate) fn purge_expired(&self) {
+        let now = Instant::now();
+        self.state
+            .lock()
+            .unwrap()
+            .cache
+            .retain(|_, x| x.expires > now);
+    }
+
+    #[cfg(test)]
+    fn entries(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.open.len() + state.waiters.len() + state.cache.len()
+    }
+}
+
+/// Perform a read, or attach it to an identical one, if coalescing is enabled
+///
+/// The transaction runs on its own task so that the callers attached to it still receive the
+/// result if the caller that started it stops waiting.
+pub(crate) async fn read<T, R>(
+    coalescer: &Arc<ReadCoalescer>,
+    select: fn(&ReadCoalescer) -> &Coalescer<T>,
+    key: ReadKey,
+    read: R,
+) -> ReadResult<T>
+where
+    T: Clone + Send + 'static,
+    R: std::future::Future<Output = ReadResult<T>> + Send + 'static,
+{
+    let join = match select(coalescer).join(key) {
+        None => return read.await,
+        Some(join) => join,
+    };
+
+    match join {
+        Join::Cached(values) => Ok(values),
+        Join::Wait(rx) => rx.await?,
+        Join::Lead(id, rx) => {
+            let coalescer = coalescer.clone();
+            tokio::spawn(async move {
+                let result = read.await;
+                if let Some(ttl) = select(&coalescer).complete(key, id, result) {
+                    // so that the cache doesn't keep the result once reads stop
+                    tokio::time::sleep(ttl).await;
+                    select(&coalescer).purge_expired();
+                }
+            });
+            rx.await?
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> ReadKey {
+        ReadKey::new(
+            UnitId::new(1),
+            FunctionCode::ReadHoldingRegisters,
+            AddressRange::try_from(0, 2).unwrap(),
+        )
+    }
+
+    fn coalescer(config: ReadCoalescing) -> Arc<ReadCoalescer> {
+        let coalescer = Arc::new(ReadCoalescer::default());
+        coalescer.configure(Some(config));
+        coalescer
+    }
+
+    fn values() -> Vec<Indexed<u16>> {
+        vec![Indexed::new(0, 7), Indexed::new(1, 8)]
+    }
+
+    type Transaction = tokio::sync::oneshot::Sender<ReadResult<u16>>;
+
+    /// Start a read whose transaction completes with the value sent on the returned sender
+    fn start_read(
+        coalescer: &Arc<ReadCoalescer>,
+    ) -> (Transaction, tokio::task::JoinHandle<ReadResult<u16>>) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let coalescer = coalescer.clone();
+        let task = tokio::spawn(async move {
+            read(&coalescer, ReadCoalescer::registers, key(), async move {
+                rx.await.unwrap()
+            })
+            .await
+        });
+        (tx, task)
+    }
+
+    #[test]
+    fn disabled_coalescer_never_joins() {
+        let coalescer = ReadCoalescer::default();
+        assert!(!coalescer.is_enabled());
+        assert!(coalescer.registers().join(key()).is_none());
+        coalescer.configure(Some(ReadCoalescing::new(Duration::from_secs(1))));
+        assert!(coalescer.is_enabled());
+        coalescer.configure(None);
+        assert!(!coalescer.is_enabled());
+        assert!(coalescer.registers().join(key()).is_none());
+        assert_eq!(coalescer.entries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn identical_reads_share_the_result_or_error() {
+        let coalescer = coalescer(ReadCoalescing::new(Duration::from_secs(1)));
+
+        for result in [Ok(values()), Err(RequestError::ResponseTimeout)] {
+            let (tx, first) = start_read(&coalescer);
+            tokio::task::yield_now().await;
+            let (unused, second) = start_read(&coalescer);
+            tokio::task::yield_now().await;
+
+            tx.send(result.clone()).unwrap();
+            assert_eq!(first.await.unwrap(), result);
+            assert_eq!(second.await.unwrap(), result);
+            // the transaction of the second read was never started
+            assert!(unused.is_closed());
+            assert_eq!(coalescer.entries(), 0);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reads_after_the_window_start_a_new_transaction() {
+        let coalescer = coalescer(ReadCoalescing::new(Duration::from_millis(10)));
+
+        let (first_tx, first) = start_read(&coalescer);
+        tokio::task::yield_now().await;
+        tokio::time::advance(Duration::from_millis(11)).await;
+        let (second_tx, second) = start_read(&coalescer);
+        tokio::task::yield_now().await;
+
+        assert!(!second_tx.is_closed());
+        first_tx.send(Ok(values())).unwrap();
+        second_tx.send(Err(RequestError::NoConnection)).unwrap();
+        assert_eq!(first.await.unwrap(), Ok(values()));
+        assert_eq!(second.await.unwrap(), Err(RequestError::NoConnection));
+        assert_eq!(coalescer.entries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn attached_reads_complete_when_the_first_caller_gives_up() {
+        let coalescer = coalescer(ReadCoalescing::new(Duration::from_secs(1)));
+
+        let (tx, first) = start_read(&coalescer);
+        tokio::task::yield_now().await;
+        let (_unused, second) = start_read(&coalescer);
+        tokio::task::yield_now().await;
+
+        first.abort();
+        tx.send(Ok(values())).unwrap();
+        assert_eq!(second.await.unwrap(), Ok(values()));
+        assert_eq!(coalescer.entries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cached_results_expire_once_reads_stop() {
+        let coalescer = coalescer(
+            ReadCoalescing::new(Duration::from_secs(1)).cache_ttl(Duration::from_millis(100)),
+        );
+
+        let (tx, first) = start_read(&coalescer);
+        tx.send(Ok(values())).unwrap();
+        assert_eq!(first.await.unwrap(), Ok(values()));
+
+        // served from the cache without starting a transaction
+        let (unused, second) = start_read(&coalescer);
+        assert_eq!(second.await.unwrap(), Ok(values()));
+        assert!(unused.is_closed());
+        assert_eq!(coalescer.entries(), 1);
+
+        // errors are never cached
+        tokio::time::sleep(Duration::from_millis(101)).await;
+        assert_eq!(coalescer.entries(), 0);
+        let (tx, third) = start_read(&coalescer);
+        tx.send(Err(RequestError::ResponseTimeout)).unwrap();
+        assert_eq!(third.await.unwrap(), Err(RequestError::ResponseTimeout));
+        assert_eq!(coalescer.entries(), 0);
+    }
+}
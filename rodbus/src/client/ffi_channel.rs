@@ -14,7 +14,7 @@ use tokio::sync::mpsc::error::TrySendError;
 /// No semver guarantees are applied to this type.
 #[derive(Debug, Clone)]
 pub struct FfiChannel {
-    tx: tokio::sync::mpsc::Sender<Command>,
+    tx: crate::channel::Sender<Command>,
 }
 
 /// Errors returned on methods of the FfiSession
@@ -36,12 +36,12 @@ impl FfiChannel {
 
     /// Enable the channel
     pub fn enable(&mut self) -> Result<(), FfiChannelError> {
-        self.send(Command::Setting(Setting::Enable))
+        self.send(Command::Setting(Setting::Enable(None)))
     }
 
     /// Disable the channel
     pub fn disable(&mut self) -> Result<(), FfiChannelError> {
-        self.send(Command::Setting(Setting::Disable))
+        self.send(Command::Setting(Setting::Disable(None)))
     }
 
     /// Set the decode level for the channel
@@ -210,9 +210,17 @@ impl FfiChannel {
     }
 
     fn send(&mut self, command: Command) -> Result<(), FfiChannelError> {
-        // dropping the command will automatically fail requests with SHUTDOWN
-        self.tx.try_send(command)?;
-        Ok(())
+        match self.tx.try_send(command) {
+            Ok(()) => Ok(()),
+            // the queue bounds the memory used by callers that out-pace the channel, so the
+            // request is failed right away rather than waiting for room
+            Err(TrySendError::Full(Command::Request(mut request))) => {
+                request.details.fail(RequestError::TooManyRequests);
+                Ok(())
+            }
+            // dropping the command will automatically fail requests with SHUTDOWN
+            Err(err) => Err(err.into()),
+        }
     }
 }
 
@@ -230,3 +238,48 @@ impl<T> From<TrySendError<T>> for FfiChannelError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::UnitId;
+
+    #[test]
+    fn rejects_requests_while_the_queue_is_full() {
+        const CAPACITY: usize = 4;
+        const REQUESTS: usize = 1000;
+
+        // nothing receives the requests, as if the channel couldn't keep up
+        let (tx, rx) = crate::channel::channel(CAPACITY);
+        let mut channel = FfiChannel::new(Channel::new(tx));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+
+        for _ in 0..REQUESTS {
+            let errors = errors.clone();
+            channel
+                .read_holding_registers(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                    AddressRange::try_from(0, 1).unwrap(),
+                    move |result| errors.lock().unwrap().push(result.err()),
+                )
+                .unwrap();
+        }
+
+        // only the queued requests are retained
+        assert_eq!(
+            *errors.lock().unwrap(),
+            vec![Some(RequestError::TooManyRequests); REQUESTS - CAPACITY]
+        );
+
+        drop(rx);
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), REQUESTS);
+        assert_eq!(
+            errors[REQUESTS - CAPACITY..],
+            [Some(RequestError::Shutdown); CAPACITY]
+        );
+    }
+}
@@ -27,6 +27,7 @@ impl<T> Listener<T> for NullListener {
 
 /// State of TCP/TLS client connection
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ClientState {
     /// Client is disabled
     Disabled,
@@ -35,23 +36,185 @@ pub enum ClientState {
     /// Client is connected
     Connected,
     /// Client is waiting to retry after a failed attempt to connect
-    WaitAfterFailedConnect(std::time::Duration),
+    WaitAfterFailedConnect {
+        /// Why the attempt failed
+        reason: ConnectFailReason,
+        /// Delay before the next attempt
+        retry_in: std::time::Duration,
+    },
     /// Client is waiting to retry after a disconnection
-    WaitAfterDisconnect(std::time::Duration),
+    WaitAfterDisconnect {
+        /// Why the connection was closed
+        reason: DisconnectReason,
+        /// Delay before the next attempt
+        retry_in: std::time::Duration,
+    },
+    /// Client closed the connection due to inactivity and will reconnect on the next request
+    Idle,
+    /// Client gave up after reaching the maximum number of connection attempts
+    ///
+    /// The channel remains idle until it is explicitly enabled again.
+    Failed {
+        /// Why the last attempt failed
+        reason: ConnectFailReason,
+    },
     /// Client has been shut down
     Shutdown,
 }
 
+/// Addresses of an established TCP or TLS connection
+///
+/// Delivered to the connection listener of a [`ClientChannelConfig`](crate::client::ClientChannelConfig)
+/// each time the channel connects, just before [`ClientState::Connected`] is reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// Address of the local end of the connection, including the ephemeral port
+    pub local: std::net::SocketAddr,
+    /// Address of the remote device that accepted the connection
+    pub peer: std::net::SocketAddr,
+}
+
+impl std::fmt::Display for ConnectionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.local, self.peer)
+    }
+}
+
+/// State of a channel regardless of its transport, see [`Channel::current_state`](crate::client::Channel::current_state)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChannelState {
+    /// State of a TCP, TLS, UDP, or in-memory channel
+    Client(ClientState),
+    /// State of a serial channel
+    #[cfg(feature = "serial")]
+    Port(PortState),
+}
+
+impl ChannelState {
+    /// True if the channel is connected or its serial port is open
+    pub fn is_connected(&self) -> bool {
+        match self {
+            ChannelState::Client(state) => *state == ClientState::Connected,
+            #[cfg(feature = "serial")]
+            ChannelState::Port(state) => *state == PortState::Open,
+        }
+    }
+}
+
+impl From<ClientState> for ChannelState {
+    fn from(state: ClientState) -> Self {
+        ChannelState::Client(state)
+    }
+}
+
+#[cfg(feature = "serial")]
+impl From<PortState> for ChannelState {
+    fn from(state: PortState) -> Self {
+        ChannelState::Port(state)
+    }
+}
+
+/// Listener that records each state so that it can be queried through the channel
+pub(crate) struct StateTracker<T> {
+    inner: Box<dyn Listener<T>>,
+    state: std::sync::Arc<tokio::sync::watch::Sender<ChannelState>>,
+}
+
+impl<T> StateTracker<T> {
+    pub(crate) fn new(
+        inner: Box<dyn Listener<T>>,
+        state: std::sync::Arc<tokio::sync::watch::Sender<ChannelState>>,
+    ) -> Self {
+        Self { inner, state }
+    }
+}
+
+impl<T> Listener<T> for StateTracker<T>
+where
+    T: Into<ChannelState> + Copy + Send,
+{
+    fn update(&mut self, value: T) -> MaybeAsync<()> {
+        self.state.send_replace(value.into());
+        self.inner.update(value)
+    }
+}
+
+/// Reason why an attempt to establish a connection failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectFailReason {
+    /// The host name could not be resolved to an address
+    Dns,
+    /// The remote host refused the connection
+    ConnectionRefused,
+    /// The remote host did not answer in time
+    TimedOut,
+    /// Any other I/O error, e.g. the network is unreachable
+    Io(std::io::ErrorKind),
+    /// The TLS handshake failed for a reason other than the certificates
+    TlsHandshake,
+    /// The certificate presented by the server was rejected, or the server rejected ours
+    BadCertificate,
+}
+
+impl ConnectFailReason {
+    pub(crate) fn from_io(err: &std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::ConnectionRefused => Self::ConnectionRefused,
+            std::io::ErrorKind::TimedOut => Self::TimedOut,
+            kind => Self::Io(kind),
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectFailReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Dns => f.write_str("DNS resolution failed"),
+            Self::ConnectionRefused => f.write_str("connection refused"),
+            Self::TimedOut => f.write_str("connection attempt timed out"),
+            Self::Io(kind) => write!(f, "I/O error: {kind}"),
+            Self::TlsHandshake => f.write_str("TLS handshake failed"),
+            Self::BadCertificate => f.write_str("certificate rejected"),
+        }
+    }
+}
+
+/// Reason why an established connection was closed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisconnectReason {
+    /// I/O error, including the server closing the connection
+    Io(std::io::ErrorKind),
+    /// The server sent a frame that could not be parsed
+    BadFrame,
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(kind) => write!(f, "I/O error: {kind}"),
+            Self::BadFrame => f.write_str("bad frame"),
+        }
+    }
+}
+
 /// State of the serial port
 #[cfg(feature = "serial")]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PortState {
     /// Disabled and idle until enabled
     Disabled,
-    /// Waiting to perform an open retry
-    Wait(std::time::Duration),
+    /// Failed to open the port, waiting to retry
+    WaitAfterFailedOpen(std::time::Duration),
+    /// The open port failed and was closed, waiting to re-open it
+    WaitAfterPortError(std::time::Duration),
     /// Port is open
     Open,
+    /// The port was closed to apply new settings and is about to be re-opened
+    Reconfiguring,
     /// Port has been shut down
     Shutdown,
 }
@@ -1,25 +1,65 @@
 use crate::common::function::FunctionCode;
 use crate::common::traits::Loggable;
-use crate::decode::AppDecodeLevel;
-use crate::error::AduParseError;
+use crate::decode::AppDecode;
 use crate::error::*;
-use crate::exception::ExceptionCode;
 use crate::DecodeLevel;
 
+use crate::client::requests::parse_function;
 use crate::client::requests::read_bits::ReadBits;
 use crate::client::requests::read_registers::ReadRegisters;
 use crate::client::requests::write_multiple::MultipleWriteRequest;
 use crate::client::requests::write_single::SingleWrite;
+use crate::client::{RequestParam, ResponseMeta, RetryOn};
+use crate::common::frame::TxId;
 use crate::common::traits::Serialize;
-use crate::types::{Indexed, UnitId};
+use crate::types::{AddressRange, Indexed, UnitId, ValueDisplay};
 
 use scursor::{ReadCursor, WriteCursor};
 use std::time::Duration;
 
+/// Completed by the task once a setting has taken effect
+pub(crate) type Completion = tokio::sync::oneshot::Sender<()>;
+
+/// Receives the [`ResponseMeta`] of a request once it completes
+pub(crate) type MetaSender = tokio::sync::oneshot::Sender<ResponseMeta>;
+
 pub(crate) enum Setting {
     DecodeLevel(DecodeLevel),
-    Enable,
-    Disable,
+    /// Enable the channel, completed once a connection attempt has started
+    Enable(Option<Completion>),
+    /// Disable the channel, completed once any connection has been closed
+    Disable(Option<Completion>),
+    /// Response timeouts for requests that don't specify one
+    Timeouts(crate::client::TimeoutTable),
+    /// Quiet period after each transaction before the next request is sent
+    InterRequestDelay(Option<std::time::Duration>),
+    /// Copy the raw bytes of the connection to a sink
+    Capture(Option<crate::capture::Capture>),
+    /// Subscribe to the state reported to the listener of the channel
+    SubscribeState(
+        tokio::sync::oneshot::Sender<tokio::sync::watch::Receiver<crate::client::ChannelState>>,
+    ),
+    /// Action taken when a response has an unexpected transaction id
+    StaleResponsePolicy(crate::client::StaleResponsePolicy),
+    /// Accept responses whose unit id differs from the request
+    AcceptMismatchedUnitId(bool),
+    /// Re-open the serial port with different settings
+    #[cfg(feature = "serial")]
+    SerialSettings(
+        crate::serial::SerialSettings,
+        tokio::sync::oneshot::Sender<Result<(), crate::client::SerialSettingsError>>,
+    ),
+    /// Fail the queued requests, replying with how many were failed
+    PurgeQueue(tokio::sync::oneshot::Sender<usize>),
+    /// Receives the outcome of every transaction
+    TransactionListener(
+        Option<Box<dyn crate::client::Listener<crate::client::TransactionOutcome>>>,
+    ),
+    /// Query the counters of the channel
+    GetStatistics(tokio::sync::oneshot::Sender<crate::client::ChannelStatistics>),
+    /// Query the parameters of the current TLS session
+    #[cfg(feature = "tls")]
+    GetTlsInfo(tokio::sync::oneshot::Sender<Option<crate::tcp::tls::TlsInfo>>),
 }
 
 pub(crate) enum Command {
@@ -29,9 +69,38 @@ pub(crate) enum Command {
     Setting(Setting),
 }
 
+impl crate::channel::Prioritized for Command {
+    fn is_high_priority(&self) -> bool {
+        match self {
+            Command::Request(request) => request.high_priority,
+            // must overtake the requests that it purges
+            Command::Setting(Setting::PurgeQueue(_)) => true,
+            Command::Setting(_) => false,
+        }
+    }
+}
+
 pub(crate) struct Request {
     pub(crate) id: UnitId,
-    pub(crate) timeout: Duration,
+    // None selects the timeout from the table of the channel
+    pub(crate) timeout: Option<Duration>,
+    // overrides the timeout when present
+    pub(crate) deadline: Option<tokio::time::Instant>,
+    pub(crate) cancel: Option<crate::client::CancellationToken>,
+    pub(crate) write_timeout: Option<Duration>,
+    // remaining number of retries
+    pub(crate) retries: u8,
+    // None if the request may not be retried
+    pub(crate) retry_on: Option<RetryOn>,
+    // number of times the request has been sent
+    pub(crate) attempts: u16,
+    pub(crate) high_priority: bool,
+    pub(crate) correlation_id: Option<u64>,
+    // when the request was created, just before it was queued
+    pub(crate) queued: tokio::time::Instant,
+    // when the first attempt started
+    pub(crate) started: Option<tokio::time::Instant>,
+    pub(crate) meta: Option<MetaSender>,
     pub(crate) details: RequestDetails,
 }
 
@@ -48,74 +117,66 @@ pub(crate) enum RequestDetails {
 }
 
 impl Request {
-    pub(crate) fn new(id: UnitId, timeout: Duration, details: RequestDetails) -> Self {
+    pub(crate) fn new(param: RequestParam, details: RequestDetails) -> Self {
         Self {
-            id,
-            timeout,
+            id: param.id,
+            timeout: Some(param.response_timeout).filter(|x| !x.is_zero()),
+            deadline: param.deadline,
+            cancel: None,
+            write_timeout: param.write_timeout,
+            retries: param.retries,
+            retry_on: Some(param.retry_on).filter(|_| details.is_read() || param.retry_writes),
+            attempts: 0,
+            high_priority: param.high_priority,
+            correlation_id: param.correlation_id,
+            queued: tokio::time::Instant::now(),
+            started: None,
+            meta: None,
             details,
         }
     }
 
+    /// Time spent in the queue before the first attempt started
+    pub(crate) fn queue_wait(&self) -> Duration {
+        self.started
+            .map(|x| x.duration_since(self.queued))
+            .unwrap_or_default()
+    }
+
+    /// Report the metadata of the completed request to the caller, if requested
+    pub(crate) fn complete_meta(&mut self, tx_id: TxId, elapsed: Duration) {
+        if let Some(tx) = self.meta.take() {
+            let _ = tx.send(ResponseMeta {
+                tx_id: Some(tx_id.to_u16()),
+                correlation_id: self.correlation_id,
+                queue_wait: self.queue_wait(),
+                elapsed,
+            });
+        }
+    }
+
+    /// Cause that allows the request to be retried after it failed with the specified error
+    pub(crate) fn retry_cause(&self, err: RequestError) -> Option<RetryCause> {
+        let cause = match (self.retry_on?, err) {
+            (_, RequestError::ResponseTimeout) => RetryCause::ResponseTimeout,
+            (RetryOn::TimeoutOrBadFrame, RequestError::BadFrame(x)) => RetryCause::BadFrame(x),
+            _ => return None,
+        };
+        Some(cause)
+    }
+
     pub(crate) fn handle_response(
         &mut self,
         payload: &[u8],
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
-        let expected_function = self.details.function();
         let mut cursor = ReadCursor::new(payload);
-        let function = match cursor.read_u8() {
-            Ok(x) => x,
-            Err(err) => {
-                tracing::warn!("unable to read function code");
-                return Err(err.into());
-            }
-        };
-
-        if function != expected_function.get_value() {
-            return Err(Self::get_error_for(function, expected_function, cursor));
-        }
+        parse_function(&mut cursor, self.details.function())?;
 
         // If we made it this far, then everything's alright
         // call the request-specific response handler
         self.details.handle_response(cursor, decode)
     }
-
-    fn get_error_for(
-        function: u8,
-        expected_function: FunctionCode,
-        mut cursor: ReadCursor,
-    ) -> RequestError {
-        if function == expected_function.as_error() {
-            match cursor.read_u8() {
-                Ok(x) => {
-                    let exception = ExceptionCode::from(x);
-                    if cursor.is_empty() {
-                        tracing::warn!(
-                            "PDU RX - Modbus exception {:?} ({:#04X})",
-                            exception,
-                            u8::from(exception)
-                        );
-                        RequestError::Exception(exception)
-                    } else {
-                        tracing::warn!("invalid modbus exception");
-                        RequestError::BadResponse(AduParseError::TrailingBytes(cursor.remaining()))
-                    }
-                }
-                Err(err) => err.into(),
-            }
-        } else {
-            tracing::warn!(
-                "function code {:#04X} does not match the expected {:#04X}",
-                function,
-                expected_function.get_value()
-            );
-            RequestError::BadResponse(AduParseError::UnknownResponseFunction(
-                function,
-                expected_function.get_value(),
-                expected_function.as_error(),
-            ))
-        }
-    }
 }
 
 impl RequestDetails {
@@ -132,6 +193,16 @@ impl RequestDetails {
         }
     }
 
+    pub(crate) fn is_read(&self) -> bool {
+        matches!(
+            self,
+            RequestDetails::ReadCoils(_)
+                | RequestDetails::ReadDiscreteInputs(_)
+                | RequestDetails::ReadHoldingRegisters(_)
+                | RequestDetails::ReadInputRegisters(_)
+        )
+    }
+
     pub(crate) fn fail(&mut self, err: RequestError) {
         match self {
             RequestDetails::ReadCoils(x) => x.failure(err),
@@ -148,7 +219,7 @@ impl RequestDetails {
     fn handle_response(
         &mut self,
         cursor: ReadCursor,
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
         let function = self.function();
         match self {
@@ -185,20 +256,34 @@ impl Loggable for RequestDetails {
     fn log(
         &self,
         _payload: &[u8],
-        level: AppDecodeLevel,
+        level: AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         write!(f, "{}", RequestDetailsDisplay::new(level, self))
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        let range = match self {
+            RequestDetails::ReadCoils(x) => x.request.get(),
+            RequestDetails::ReadDiscreteInputs(x) => x.request.get(),
+            RequestDetails::ReadHoldingRegisters(x) => x.request.get(),
+            RequestDetails::ReadInputRegisters(x) => x.request.get(),
+            RequestDetails::WriteSingleCoil(x) => return x.request.address_range(),
+            RequestDetails::WriteSingleRegister(x) => return x.request.address_range(),
+            RequestDetails::WriteMultipleCoils(x) => x.request.range,
+            RequestDetails::WriteMultipleRegisters(x) => x.request.range,
+        };
+        Some(range)
+    }
 }
 
 pub(crate) struct RequestDetailsDisplay<'a> {
     request: &'a RequestDetails,
-    level: AppDecodeLevel,
+    level: AppDecode,
 }
 
 impl<'a> RequestDetailsDisplay<'a> {
-    pub(crate) fn new(level: AppDecodeLevel, request: &'a RequestDetails) -> Self {
+    pub(crate) fn new(level: AppDecode, request: &'a RequestDetails) -> Self {
         Self { request, level }
     }
 }
@@ -220,16 +305,16 @@ impl std::fmt::Display for RequestDetailsDisplay<'_> {
                     write!(f, "{}", details.request.get())?;
                 }
                 RequestDetails::WriteSingleCoil(details) => {
-                    write!(f, "{}", details.request)?;
+                    write!(f, "{}", ValueDisplay::new(self.level, &details.request))?;
                 }
                 RequestDetails::WriteSingleRegister(details) => {
-                    write!(f, "{}", details.request)?;
+                    write!(f, "{}", ValueDisplay::new(self.level, &details.request))?;
                 }
                 RequestDetails::WriteMultipleCoils(details) => {
                     write!(f, "{}", details.request.range)?;
                     if self.level.data_values() {
                         for x in details.request.iter() {
-                            write!(f, "\n{x}")?;
+                            write!(f, "\n{}", ValueDisplay::new(self.level, &x))?;
                         }
                     }
                 }
@@ -237,7 +322,7 @@ impl std::fmt::Display for RequestDetailsDisplay<'_> {
                     write!(f, "{}", details.request.range)?;
                     if self.level.data_values() {
                         for x in details.request.iter() {
-                            write!(f, "\n{x}")?;
+                            write!(f, "\n{}", ValueDisplay::new(self.level, &x))?;
                         }
                     }
                 }
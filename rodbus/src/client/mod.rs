@@ -1,21 +1,54 @@
 use std::net::{IpAddr, SocketAddr};
 
+#[cfg(feature = "runtime")]
 use crate::decode::DecodeLevel;
 
+#[cfg(feature = "runtime")]
+pub(crate) mod builder;
+#[cfg(feature = "runtime")]
+pub(crate) mod callback;
+#[cfg(feature = "runtime")]
+pub(crate) mod cancel;
 /// persistent communication channel such as a TCP connection
+#[cfg(feature = "runtime")]
 pub(crate) mod channel;
+#[cfg(feature = "runtime")]
+pub(crate) mod coalesce;
+#[cfg(feature = "runtime")]
 pub(crate) mod listener;
+#[cfg(feature = "runtime")]
 pub(crate) mod message;
+#[cfg(feature = "runtime")]
+pub(crate) mod options;
 pub(crate) mod requests;
+#[cfg(feature = "runtime")]
 pub(crate) mod task;
+#[cfg(feature = "runtime")]
+pub(crate) mod timeouts;
 
 #[cfg(feature = "ffi")]
 /// Only enabled for FFI builds
 mod ffi_channel;
 
+#[cfg(feature = "runtime")]
+pub use crate::client::builder::*;
+#[cfg(feature = "runtime")]
+pub use crate::client::callback::*;
+#[cfg(feature = "runtime")]
+pub use crate::client::cancel::*;
+#[cfg(feature = "runtime")]
 pub use crate::client::channel::*;
+#[cfg(feature = "runtime")]
+pub use crate::client::coalesce::ReadCoalescing;
+#[cfg(feature = "runtime")]
 pub use crate::client::listener::*;
-pub use crate::client::requests::write_multiple::WriteMultiple;
+#[cfg(feature = "runtime")]
+pub use crate::client::options::*;
+pub use crate::client::requests::write_multiple::{
+    WriteMultiple, WriteMultipleBuilder, WriteMultipleValue,
+};
+#[cfg(feature = "runtime")]
+pub use crate::client::timeouts::*;
 pub use crate::retry::*;
 
 #[cfg(feature = "ffi")]
@@ -28,6 +61,7 @@ pub use crate::tcp::tls::*;
 
 /// Represents the address of a remote host
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HostAddr {
     addr: HostType,
     port: u16,
@@ -49,6 +83,7 @@ impl std::fmt::Display for HostAddr {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum HostType {
     Dns(String),
     IpAddr(IpAddr),
@@ -70,15 +105,128 @@ impl HostAddr {
             port,
         }
     }
+}
+
+#[cfg(feature = "runtime")]
+impl HostAddr {
+    /// resolve the address and connect to it using the socket options and timeout, classifying any failure
+    pub(crate) async fn connect(
+        &self,
+        options: &ClientOptions,
+    ) -> Result<tokio::net::TcpStream, (ConnectFailReason, std::io::Error)> {
+        let connect = async {
+            let addrs = self.resolve().await?;
+            Self::connect_any(&addrs, options).await
+        };
+        match options.connect_timeout {
+            None => connect.await,
+            Some(timeout) => match tokio::time::timeout(timeout, connect).await {
+                Ok(result) => result,
+                Err(_) => Err((
+                    ConnectFailReason::TimedOut,
+                    std::io::Error::from(std::io::ErrorKind::TimedOut),
+                )),
+            },
+        }
+    }
+
+    /// try each address in turn, returning the error of the last attempt if all of them fail
+    async fn connect_any(
+        addrs: &[SocketAddr],
+        options: &ClientOptions,
+    ) -> Result<tokio::net::TcpStream, (ConnectFailReason, std::io::Error)> {
+        let mut last_err = None;
+        for addr in addrs {
+            // the local address can only be bound to a socket of the same IP version
+            if let Some(local) = options.local_address {
+                if local.is_ipv4() != addr.is_ipv4() {
+                    continue;
+                }
+            }
+            match Self::connect_one(*addr, options).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        let err = last_err.unwrap_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::AddrNotAvailable,
+                "no resolved address matches the IP version of the local address",
+            )
+        });
+        Err((ConnectFailReason::from_io(&err), err))
+    }
 
-    pub(crate) async fn connect(&self) -> std::io::Result<tokio::net::TcpStream> {
+    async fn connect_one(
+        addr: SocketAddr,
+        options: &ClientOptions,
+    ) -> Result<tokio::net::TcpStream, std::io::Error> {
+        let socket = match addr {
+            SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
+        };
+        if let Some(local) = options.local_address {
+            socket.bind(local)?;
+        }
+        if let Some(hook) = &options.on_socket {
+            hook.apply(&socket)?;
+        }
+        socket.connect(addr).await
+    }
+
+    /// bind an ephemeral UDP socket and connect it to the first resolved address
+    pub(crate) async fn connect_udp(
+        &self,
+    ) -> Result<tokio::net::UdpSocket, (ConnectFailReason, std::io::Error)> {
+        let remote = self.resolve().await?[0];
+        let local: SocketAddr = match remote {
+            SocketAddr::V4(_) => (std::net::Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = tokio::net::UdpSocket::bind(local)
+            .await
+            .map_err(|err| (ConnectFailReason::from_io(&err), err))?;
+        socket
+            .connect(remote)
+            .await
+            .map_err(|err| (ConnectFailReason::from_io(&err), err))?;
+        Ok(socket)
+    }
+
+    /// resolve the address to a non-empty list of socket addresses
+    async fn resolve(&self) -> Result<Vec<SocketAddr>, (ConnectFailReason, std::io::Error)> {
         match &self.addr {
-            HostType::Dns(x) => tokio::net::TcpStream::connect((x.as_str(), self.port)).await,
-            HostType::IpAddr(x) => tokio::net::TcpStream::connect((*x, self.port)).await,
+            HostType::Dns(x) => {
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((x.as_str(), self.port))
+                    .await
+                    .map_err(|err| (ConnectFailReason::Dns, err))?
+                    .collect();
+                if addrs.is_empty() {
+                    return Err((
+                        ConnectFailReason::Dns,
+                        std::io::Error::new(
+                            std::io::ErrorKind::NotFound,
+                            format!("no address found for {x}"),
+                        ),
+                    ));
+                }
+                Ok(addrs)
+            }
+            HostType::IpAddr(x) => Ok(vec![SocketAddr::new(*x, self.port)]),
         }
     }
 }
 
+/// Framing of the Modbus ADU on UDP and in-memory transports
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameFormat {
+    /// MBAP header followed by the PDU, as used by Modbus TCP
+    Mbap,
+    /// Unit id, PDU, and CRC, as used by Modbus RTU
+    Rtu,
+}
+
 /// Spawns a channel task onto the runtime that maintains a TCP connection and processes
 /// requests. The task completes when the returned channel handle is dropped.
 ///
@@ -90,16 +238,162 @@ impl HostAddr {
 /// * `decode` - Decode log level
 /// * `listener` - Optional callback to monitor the TCP connection state
 ///
+/// [`ClientChannelConfig`] provides the same settings with defaults.
+///
 /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
 pub fn spawn_tcp_client_task(
     host: HostAddr,
     max_queued_requests: usize,
     retry: Box<dyn RetryStrategy>,
     decode: DecodeLevel,
     listener: Option<Box<dyn Listener<ClientState>>>,
+) -> Channel {
+    spawn_tcp_client_task_with_options(
+        host,
+        max_queued_requests,
+        retry,
+        decode,
+        listener,
+        ClientOptions::default(),
+    )
+}
+
+/// Same as [`spawn_tcp_client_task`] but accepts additional [`ClientOptions`]
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
+pub fn spawn_tcp_client_task_with_options(
+    host: HostAddr,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+    options: ClientOptions,
+) -> Channel {
+    crate::tcp::client::spawn_tcp_channel(
+        host,
+        crate::tcp::client::TcpTaskConnectionHandler::Tcp,
+        max_queued_requests,
+        retry,
+        decode,
+        listener.unwrap_or_else(|| NullListener::create()),
+        NullListener::create(),
+        options,
+    )
+}
+
+/// Spawns a channel task onto the runtime that maintains a TCP connection carrying RTU frames,
+/// i.e. a unit id, PDU, and CRC without an MBAP header. This is the framing used by many
+/// radio modems and serial device servers that tunnel a serial line over TCP.
+///
+/// The arguments are the same as [`spawn_tcp_client_task`].
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
+pub fn spawn_rtu_over_tcp_client_task(
+    host: HostAddr,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+) -> Channel {
+    spawn_rtu_over_tcp_client_task_with_options(
+        host,
+        max_queued_requests,
+        retry,
+        decode,
+        listener,
+        ClientOptions::default(),
+    )
+}
+
+/// Same as [`spawn_rtu_over_tcp_client_task`] but accepts additional [`ClientOptions`]
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
+pub fn spawn_rtu_over_tcp_client_task_with_options(
+    host: HostAddr,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+    options: ClientOptions,
 ) -> Channel {
     crate::tcp::client::spawn_tcp_channel(
         host,
+        crate::tcp::client::TcpTaskConnectionHandler::RtuOverTcp,
+        max_queued_requests,
+        retry,
+        decode,
+        listener.unwrap_or_else(|| NullListener::create()),
+        NullListener::create(),
+        options,
+    )
+}
+
+/// Spawns a channel task onto the runtime that exchanges datagrams with a UDP server and
+/// processes requests. The task completes when the returned channel handle is dropped.
+///
+/// Each request and response is a single datagram framed according to `format`. Lost
+/// datagrams are reported as a [`RequestError::ResponseTimeout`](crate::RequestError::ResponseTimeout)
+/// and do not affect subsequent requests.
+///
+/// * `host` - Address/port of the remote server. Can be a IP address or name on which to perform DNS resolution.
+/// * `format` - Framing of the ADU within each datagram
+/// * `max_queued_requests` - The maximum size of the request queue
+/// * `retry` - A boxed trait object that controls when the socket is re-created after a failure
+/// * `decode` - Decode log level
+/// * `listener` - Optional callback to monitor the state of the socket
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
+pub fn spawn_udp_client_task(
+    host: HostAddr,
+    format: FrameFormat,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+) -> Channel {
+    crate::udp::client::spawn_udp_channel(
+        host,
+        format,
+        max_queued_requests,
+        retry,
+        decode,
+        listener.unwrap_or_else(|| NullListener::create()),
+    )
+}
+
+/// Spawns a channel task onto the runtime that communicates over in-memory connections instead
+/// of sockets, e.g. with a server in the same process. The task completes when the returned
+/// channel handle is dropped.
+///
+/// `connect` is called on every connection attempt. When the other end of the connection is
+/// dropped, the channel uses the provided [`RetryStrategy`] to pause and then calls `connect`
+/// again, as it would reconnect a socket. See [`in_memory_pair`](crate::memory::in_memory_pair).
+///
+/// * `connect` - Produces the connection of each attempt
+/// * `format` - Framing of the Modbus ADUs on the connection
+/// * `max_queued_requests` - The maximum size of the request queue
+/// * `retry` - A boxed trait object that controls when the connection is retried on failure
+/// * `decode` - Decode log level
+/// * `listener` - Optional callback to monitor the connection state
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "runtime")]
+pub fn spawn_in_memory_client_task(
+    connect: crate::memory::MemoryConnector,
+    format: FrameFormat,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+) -> Channel {
+    crate::memory::client::spawn_memory_channel(
+        connect,
+        format,
         max_queued_requests,
         retry,
         decode,
@@ -115,6 +409,7 @@ pub fn spawn_tcp_client_task(
 /// serial port or after the serial port fails.
 ///
 /// * `path` - Path to the serial device. Generally `/dev/tty0` on Linux and `COM1` on Windows.
+///   Accepts a [`SerialPath`](crate::SerialPath) to try multiple candidate paths or a pattern.
 /// * `serial_settings` = Serial port settings
 /// * `max_queued_requests` - The maximum size of the request queue
 /// * `retry` - A boxed trait object that controls when opening the serial port is retried on failure
@@ -124,7 +419,7 @@ pub fn spawn_tcp_client_task(
 /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
 #[cfg(feature = "serial")]
 pub fn spawn_rtu_client_task(
-    path: &str,
+    path: impl Into<crate::serial::SerialPath>,
     serial_settings: crate::serial::SerialSettings,
     max_queued_requests: usize,
     retry: Box<dyn RetryStrategy>,
@@ -132,7 +427,7 @@ pub fn spawn_rtu_client_task(
     listener: Option<Box<dyn Listener<PortState>>>,
 ) -> Channel {
     Channel::spawn_rtu(
-        path,
+        crate::serial::PortSource::System(path.into()),
         serial_settings,
         max_queued_requests,
         retry,
@@ -163,6 +458,30 @@ pub fn spawn_tls_client_task(
     tls_config: TlsClientConfig,
     decode: DecodeLevel,
     listener: Option<Box<dyn Listener<ClientState>>>,
+) -> Channel {
+    spawn_tls_client_task_with_options(
+        host,
+        max_queued_requests,
+        retry,
+        tls_config,
+        decode,
+        listener,
+        ClientOptions::default(),
+    )
+}
+
+/// Same as [`spawn_tls_client_task`] but accepts additional [`ClientOptions`]
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "tls")]
+pub fn spawn_tls_client_task_with_options(
+    host: HostAddr,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    tls_config: TlsClientConfig,
+    decode: DecodeLevel,
+    listener: Option<Box<dyn Listener<ClientState>>>,
+    options: ClientOptions,
 ) -> Channel {
     spawn_tls_channel(
         host,
@@ -171,5 +490,7 @@ pub fn spawn_tls_client_task(
         tls_config,
         decode,
         listener.unwrap_or_else(|| NullListener::create()),
+        NullListener::create(),
+        options,
     )
 }
@@ -0,0 +1,110 @@
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+type SocketFn = dyn Fn(&tokio::net::TcpSocket) -> Result<(), std::io::Error> + Send + Sync;
+
+/// Callback that configures each TCP socket before it connects, see [`ClientOptions::on_socket`]
+pub type SocketCallback = Box<SocketFn>;
+
+/// Optional settings that control the behavior of a TCP or TLS client channel
+///
+/// The default value preserves the historical behavior of the channel, i.e. retrying
+/// failed connection attempts forever.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientOptions {
+    pub(crate) max_connect_attempts: Option<NonZeroUsize>,
+    pub(crate) idle_disconnect: Option<Duration>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) local_address: Option<SocketAddr>,
+    pub(crate) on_socket: Option<SocketHook>,
+}
+
+/// Shared [`SocketCallback`] so that the options remain cloneable
+#[derive(Clone)]
+pub(crate) struct SocketHook(Arc<SocketFn>);
+
+impl SocketHook {
+    pub(crate) fn apply(&self, socket: &tokio::net::TcpSocket) -> Result<(), std::io::Error> {
+        (self.0)(socket)
+    }
+}
+
+impl std::fmt::Debug for SocketHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SocketHook")
+    }
+}
+
+// callbacks can't be compared, two hooks are only equal if they are the same callback
+impl PartialEq for SocketHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SocketHook {}
+
+impl ClientOptions {
+    /// Limit the number of consecutive failed connection attempts
+    ///
+    /// Once the limit is reached, the channel transitions to [`ClientState::Failed`](crate::client::ClientState::Failed),
+    /// fails all requests with [`RequestError::NoConnection`](crate::RequestError::NoConnection), and
+    /// remains idle until [`Channel::enable`](crate::client::Channel::enable) is called again which
+    /// resets the attempt counter. Disabling the channel also resets the counter.
+    ///
+    /// `None` (the default) retries forever.
+    pub fn max_connect_attempts(mut self, value: Option<NonZeroUsize>) -> Self {
+        self.max_connect_attempts = value;
+        self
+    }
+
+    /// Close the connection after the specified duration without any requests
+    ///
+    /// While disconnected, the channel reports [`ClientState::Idle`](crate::client::ClientState::Idle).
+    /// The next request triggers an immediate reconnect without waiting for the [`RetryStrategy`](crate::client::RetryStrategy)
+    /// delay, and requests made while reconnecting are queued rather than failed.
+    ///
+    /// `None` (the default) keeps the connection open indefinitely.
+    pub fn idle_disconnect(mut self, value: Option<Duration>) -> Self {
+        self.idle_disconnect = value;
+        self
+    }
+
+    /// Fail connection attempts that take longer than the specified duration
+    ///
+    /// The attempt is reported with [`ConnectFailReason::TimedOut`](crate::client::ConnectFailReason::TimedOut)
+    /// and retried according to the [`RetryStrategy`](crate::client::RetryStrategy). The timeout
+    /// covers DNS resolution and the TCP handshake, but not a TLS handshake.
+    ///
+    /// `None` (the default) relies on the timeout of the operating system.
+    pub fn connect_timeout(mut self, value: Option<Duration>) -> Self {
+        self.connect_timeout = value;
+        self
+    }
+
+    /// Bind the local end of the connection to the specified address before connecting
+    ///
+    /// This selects the source address, e.g. on a host with several interfaces. A port of zero
+    /// lets the operating system choose an ephemeral port. Resolved remote addresses of a
+    /// different IP version are skipped.
+    ///
+    /// `None` (the default) lets the operating system choose the local address.
+    pub fn local_address(mut self, value: Option<SocketAddr>) -> Self {
+        self.local_address = value;
+        self
+    }
+
+    /// Invoke a callback with each socket after it is created and bound, but before it connects
+    ///
+    /// The callback may set socket options that aren't exposed by the library, e.g. the IP
+    /// type of service. An error fails the connection attempt which is then retried according to
+    /// the [`RetryStrategy`](crate::client::RetryStrategy).
+    ///
+    /// `None` (the default) doesn't invoke a callback.
+    pub fn on_socket(mut self, value: Option<SocketCallback>) -> Self {
+        self.on_socket = value.map(|x| SocketHook(Arc::from(x)));
+        self
+    }
+}
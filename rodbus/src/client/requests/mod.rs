@@ -1,4 +1,70 @@
+use crate::common::function::FunctionCode;
+use crate::error::{AduParseError, RequestError};
+use crate::exception::ExceptionCode;
+
+use scursor::ReadCursor;
+
 pub(crate) mod read_bits;
 pub(crate) mod read_registers;
 pub(crate) mod write_multiple;
 pub(crate) mod write_single;
+
+/// Read the function code of a response, converting exception responses into an error
+pub(crate) fn parse_function(
+    cursor: &mut ReadCursor,
+    expected_function: FunctionCode,
+) -> Result<(), RequestError> {
+    let function = match cursor.read_u8() {
+        Ok(x) => x,
+        Err(err) => {
+            tracing::warn!("unable to read function code");
+            return Err(err.into());
+        }
+    };
+
+    if function != expected_function.get_value() {
+        return Err(get_error_for(function, expected_function, *cursor));
+    }
+
+    Ok(())
+}
+
+fn get_error_for(
+    function: u8,
+    expected_function: FunctionCode,
+    mut cursor: ReadCursor,
+) -> RequestError {
+    if function == expected_function.as_error() {
+        match cursor.read_u8() {
+            Ok(x) => {
+                let exception = ExceptionCode::from(x);
+                if cursor.is_empty() {
+                    tracing::warn!(
+                        fc = function,
+                        exception = u8::from(exception),
+                        "PDU RX - Modbus exception {:?} ({:#04X})",
+                        exception,
+                        u8::from(exception)
+                    );
+                    RequestError::Exception(exception)
+                } else {
+                    tracing::warn!("invalid modbus exception");
+                    RequestError::BadResponse(AduParseError::TrailingBytes(cursor.remaining()))
+                }
+            }
+            Err(err) => err.into(),
+        }
+    } else {
+        tracing::warn!(
+            fc = function,
+            "function code {:#04X} does not match the expected {:#04X}",
+            function,
+            expected_function.get_value()
+        );
+        RequestError::BadResponse(AduParseError::UnknownResponseFunction(
+            function,
+            expected_function.get_value(),
+            expected_function.as_error(),
+        ))
+    }
+}
@@ -1,21 +1,22 @@
 use crate::common::function::FunctionCode;
 use crate::common::traits::Serialize;
-use crate::decode::AppDecodeLevel;
+use crate::decode::AppDecode;
 use crate::error::RequestError;
 use crate::types::{AddressRange, BitIterator, BitIteratorDisplay, ReadBitsRange};
+#[cfg(feature = "runtime")]
 use crate::Indexed;
 
 use scursor::{ReadCursor, WriteCursor};
 
 pub(crate) trait BitsCallback:
-    FnOnce(Result<BitIterator, RequestError>) + Send + Sync + 'static
+    FnOnce(Result<BitIterator, RequestError>) + Send + 'static
 {
 }
-impl<T> BitsCallback for T where T: FnOnce(Result<BitIterator, RequestError>) + Send + Sync + 'static
-{}
+impl<T> BitsCallback for T where T: FnOnce(Result<BitIterator, RequestError>) + Send + 'static {}
 
 pub(crate) struct Promise {
-    callback: Option<Box<dyn BitsCallback>>,
+    // the mutex keeps the request Sync while only requiring the callback to be Send
+    callback: std::sync::Mutex<Option<Box<dyn BitsCallback>>>,
 }
 
 impl Drop for Promise {
@@ -30,7 +31,7 @@ impl Promise {
         T: BitsCallback,
     {
         Self {
-            callback: Some(Box::new(callback)),
+            callback: std::sync::Mutex::new(Some(Box::new(callback))),
         }
     }
 
@@ -43,7 +44,12 @@ impl Promise {
     }
 
     fn complete(&mut self, result: Result<BitIterator, RequestError>) {
-        if let Some(callback) = self.callback.take() {
+        let callback = self
+            .callback
+            .get_mut()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take();
+        if let Some(callback) = callback {
             callback(result)
         }
     }
@@ -59,6 +65,7 @@ impl ReadBits {
         Self { request, promise }
     }
 
+    #[cfg(feature = "runtime")]
     pub(crate) fn channel(
         request: ReadBitsRange,
         tx: tokio::sync::oneshot::Sender<Result<Vec<Indexed<bool>>, RequestError>>,
@@ -71,6 +78,42 @@ impl ReadBits {
         )
     }
 
+    #[cfg(feature = "runtime")]
+    pub(crate) fn buffer(
+        request: ReadBitsRange,
+        values: Vec<bool>,
+        tx: tokio::sync::oneshot::Sender<(Vec<bool>, Result<(), RequestError>)>,
+    ) -> Self {
+        Self::new(
+            request,
+            Promise::new(move |x: Result<BitIterator, RequestError>| {
+                // the buffer is handed back on failure so that its allocation is never lost
+                let mut values = values;
+                values.clear();
+                let result = x.map(|x| values.extend(x.map(|x| x.value)));
+                let _ = tx.send((values, result));
+            }),
+        )
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn with<F, R>(
+        request: ReadBitsRange,
+        f: F,
+        tx: tokio::sync::oneshot::Sender<Result<R, RequestError>>,
+    ) -> Self
+    where
+        F: FnOnce(BitIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::new(
+            request,
+            Promise::new(move |x: Result<BitIterator, RequestError>| {
+                let _ = tx.send(x.map(f));
+            }),
+        )
+    }
+
     pub(crate) fn serialize(&self, cursor: &mut WriteCursor) -> Result<(), RequestError> {
         self.request.get().serialize(cursor)
     }
@@ -83,12 +126,16 @@ impl ReadBits {
         &mut self,
         mut cursor: ReadCursor,
         function: FunctionCode,
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
         let response = Self::parse_bits_response(self.request.get(), &mut cursor)?;
 
         if decode.enabled() {
+            let range = self.request.get();
             tracing::info!(
+                fc = function.get_value(),
+                start = range.start,
+                count = range.count,
                 "PDU RX - {} {}",
                 function,
                 BitIteratorDisplay::new(decode, response)
@@ -99,12 +146,14 @@ impl ReadBits {
         Ok(())
     }
 
-    fn parse_bits_response<'a>(
+    pub(crate) fn parse_bits_response<'a>(
         range: AddressRange,
         cursor: &'a mut ReadCursor,
     ) -> Result<BitIterator<'a>, RequestError> {
-        // there's a byte-count here that we don't actually need
-        cursor.read_u8()?;
+        crate::common::parse::read_byte_count(
+            cursor,
+            crate::common::bits::num_bytes_for_bits(range.count),
+        )?;
         // the rest is a sequence of bits
         BitIterator::parse_all(range, cursor)
     }
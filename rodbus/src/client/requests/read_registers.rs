@@ -1,24 +1,25 @@
 use crate::common::function::FunctionCode;
 use crate::common::traits::Serialize;
-use crate::decode::AppDecodeLevel;
+use crate::decode::AppDecode;
 use crate::error::RequestError;
-use crate::types::{
-    AddressRange, Indexed, ReadRegistersRange, RegisterIterator, RegisterIteratorDisplay,
-};
+#[cfg(feature = "runtime")]
+use crate::types::Indexed;
+use crate::types::{AddressRange, ReadRegistersRange, RegisterIterator, RegisterIteratorDisplay};
 
 use scursor::{ReadCursor, WriteCursor};
 
 pub(crate) trait RegistersCallback:
-    FnOnce(Result<RegisterIterator, RequestError>) + Send + Sync + 'static
+    FnOnce(Result<RegisterIterator, RequestError>) + Send + 'static
 {
 }
 impl<T> RegistersCallback for T where
-    T: FnOnce(Result<RegisterIterator, RequestError>) + Send + Sync + 'static
+    T: FnOnce(Result<RegisterIterator, RequestError>) + Send + 'static
 {
 }
 
 pub(crate) struct Promise {
-    callback: Option<Box<dyn RegistersCallback>>,
+    // the mutex keeps the request Sync while only requiring the callback to be Send
+    callback: std::sync::Mutex<Option<Box<dyn RegistersCallback>>>,
 }
 
 impl Drop for Promise {
@@ -33,7 +34,7 @@ impl Promise {
         T: RegistersCallback,
     {
         Self {
-            callback: Some(Box::new(callback)),
+            callback: std::sync::Mutex::new(Some(Box::new(callback))),
         }
     }
 
@@ -46,7 +47,12 @@ impl Promise {
     }
 
     fn complete(&mut self, x: Result<RegisterIterator, RequestError>) {
-        if let Some(callback) = self.callback.take() {
+        let callback = self
+            .callback
+            .get_mut()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take();
+        if let Some(callback) = callback {
             callback(x)
         }
     }
@@ -62,6 +68,7 @@ impl ReadRegisters {
         Self { request, promise }
     }
 
+    #[cfg(feature = "runtime")]
     pub(crate) fn channel(
         request: ReadRegistersRange,
         tx: tokio::sync::oneshot::Sender<Result<Vec<Indexed<u16>>, RequestError>>,
@@ -74,6 +81,42 @@ impl ReadRegisters {
         )
     }
 
+    #[cfg(feature = "runtime")]
+    pub(crate) fn buffer(
+        request: ReadRegistersRange,
+        values: Vec<u16>,
+        tx: tokio::sync::oneshot::Sender<(Vec<u16>, Result<(), RequestError>)>,
+    ) -> Self {
+        Self::new(
+            request,
+            Promise::new(move |x: Result<RegisterIterator, RequestError>| {
+                // the buffer is handed back on failure so that its allocation is never lost
+                let mut values = values;
+                values.clear();
+                let result = x.map(|x| values.extend(x.map(|x| x.value)));
+                let _ = tx.send((values, result));
+            }),
+        )
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn with<F, R>(
+        request: ReadRegistersRange,
+        f: F,
+        tx: tokio::sync::oneshot::Sender<Result<R, RequestError>>,
+    ) -> Self
+    where
+        F: FnOnce(RegisterIterator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::new(
+            request,
+            Promise::new(move |x: Result<RegisterIterator, RequestError>| {
+                let _ = tx.send(x.map(f));
+            }),
+        )
+    }
+
     pub(crate) fn serialize(&self, cursor: &mut WriteCursor) -> Result<(), RequestError> {
         self.request.get().serialize(cursor)
     }
@@ -86,12 +129,16 @@ impl ReadRegisters {
         &mut self,
         mut cursor: ReadCursor,
         function: FunctionCode,
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
         let response = Self::parse_registers_response(self.request.get(), &mut cursor)?;
 
         if decode.enabled() {
+            let range = self.request.get();
             tracing::info!(
+                fc = function.get_value(),
+                start = range.start,
+                count = range.count,
                 "PDU RX - {} {}",
                 function,
                 RegisterIteratorDisplay::new(decode, response)
@@ -102,13 +149,12 @@ impl ReadRegisters {
         Ok(())
     }
 
-    fn parse_registers_response<'a>(
+    pub(crate) fn parse_registers_response<'a>(
         range: AddressRange,
         cursor: &'a mut ReadCursor,
     ) -> Result<RegisterIterator<'a>, RequestError> {
-        // there's a byte-count here that we don't actually need
-        cursor.read_u8()?;
-        // the reset is a sequence of bits
+        crate::common::parse::read_byte_count(cursor, 2 * (range.count as usize))?;
+        // the rest is a sequence of registers
         RegisterIterator::parse_all(range, cursor)
     }
 }
@@ -1,53 +1,279 @@
-use crate::client::message::Promise;
-use crate::common::function::FunctionCode;
-use crate::common::traits::{Parse, Serialize};
-use crate::decode::AppDecodeLevel;
+use crate::common::traits::Parse;
 use crate::error::RequestError;
-use crate::error::{AduParseError, InvalidRequest};
+use crate::error::{AduParseError, InvalidRange, InvalidRequest};
 use crate::types::{AddressRange, Indexed};
+#[cfg(feature = "runtime")]
+use crate::{
+    client::message::Promise, common::function::FunctionCode, common::traits::Serialize,
+    decode::AppDecode,
+};
 
-use scursor::{ReadCursor, WriteCursor};
+use scursor::ReadCursor;
+#[cfg(feature = "runtime")]
+use scursor::WriteCursor;
 use std::convert::TryFrom;
 
 /// Collection of values and starting address
 ///
 /// Used when making write multiple coil/register requests
+///
+/// With the `serde` feature, requests are represented by the starting address and the values. The
+/// same limits as [`WriteMultiple::from`] are validated when deserializing.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "WriteMultipleFields<T>",
+        try_from = "WriteMultipleFields<T>",
+        bound(
+            serialize = "T: Clone + serde::Serialize",
+            deserialize = "T: WriteMultipleValue + serde::Deserialize<'de>"
+        )
+    )
+)]
 pub struct WriteMultiple<T> {
     /// starting address
     pub(crate) range: AddressRange,
-    /// vector of values
+    /// vector of values, empty if the values are packed
     pub(crate) values: Vec<T>,
+    /// values in the encoding of the request, see [`WriteMultiple::from_packed`]
+    pub(crate) packed: Option<Packed<T>>,
+}
+
+/// Values stored in the encoding of the request rather than as a vector
+#[derive(Debug, Clone)]
+pub(crate) struct Packed<T> {
+    pub(crate) bytes: Vec<u8>,
+    // reads the value at an offset from the start of the range
+    get: fn(&[u8], u16) -> T,
+}
+
+impl<T> Packed<T> {
+    fn get(&self, offset: u16) -> T {
+        (self.get)(&self.bytes, offset)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WriteMultipleFields<T> {
+    start: u16,
+    values: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> From<WriteMultiple<T>> for WriteMultipleFields<T> {
+    fn from(value: WriteMultiple<T>) -> Self {
+        let values = match value.packed {
+            Some(packed) => (0..value.range.count).map(|x| packed.get(x)).collect(),
+            None => value.values,
+        };
+        Self {
+            start: value.range.start,
+            values,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<WriteMultipleFields<T>> for WriteMultiple<T>
+where
+    T: WriteMultipleValue,
+{
+    type Error = InvalidRequest;
+
+    fn try_from(value: WriteMultipleFields<T>) -> Result<Self, Self::Error> {
+        WriteMultiple::from(value.start, value.values)
+    }
 }
 
 pub(crate) struct WriteMultipleIterator<'a, T> {
     range: AddressRange,
     pos: u16,
     iter: std::slice::Iter<'a, T>,
+    packed: Option<&'a Packed<T>>,
 }
 
-impl<T> WriteMultiple<T> {
+/// Types that may be written using a write multiple request
+///
+/// This trait is implemented for `bool` (coils) and `u16` (registers)
+pub trait WriteMultipleValue: Copy {
+    /// Maximum number of values allowed in a single request
+    const MAX_COUNT: u16;
+}
+
+impl WriteMultipleValue for bool {
+    const MAX_COUNT: u16 = crate::constants::limits::MAX_WRITE_COILS_COUNT;
+}
+
+impl WriteMultipleValue for u16 {
+    const MAX_COUNT: u16 = crate::constants::limits::MAX_WRITE_REGISTERS_COUNT;
+}
+
+/// Builder used to accumulate values for one or more [`WriteMultiple`] requests
+#[derive(Debug, Clone)]
+pub struct WriteMultipleBuilder<T> {
+    start: u16,
+    values: Vec<T>,
+}
+
+impl<T> WriteMultiple<T>
+where
+    T: WriteMultipleValue,
+{
     /// Create new collection of values
+    ///
+    /// Fails if the range of addresses overflows or if the number of values exceeds the
+    /// maximum allowed by the specification for the type (0x7B0 coils or 0x7B registers).
     pub fn from(start: u16, values: Vec<T>) -> Result<Self, InvalidRequest> {
-        let count = match u16::try_from(values.len()) {
+        let range = Self::range_of(start, values.len())?;
+        Ok(Self {
+            range,
+            values,
+            packed: None,
+        })
+    }
+
+    /// Create a collection of values from an iterator, e.g. over a slice or generated values
+    ///
+    /// The length reported by the iterator is validated before any value is collected, so
+    /// oversized requests fail without allocating. Fails for the same reasons as
+    /// [`WriteMultiple::from`].
+    pub fn from_iter_checked<I>(start: u16, values: I) -> Result<Self, InvalidRequest>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        Self::range_of(start, values.len())?;
+        // the reported length isn't trusted, the collected values are validated again
+        Self::from(start, values.collect())
+    }
+
+    fn range_of(start: u16, len: usize) -> Result<AddressRange, InvalidRequest> {
+        let count = match u16::try_from(len) {
             Ok(x) => x,
-            Err(_) => return Err(InvalidRequest::CountTooBigForU16(values.len())),
+            Err(_) => return Err(InvalidRequest::CountTooBigForU16(len)),
         };
         let range = AddressRange::try_from(start, count)?;
-        Ok(Self { range, values })
+        if count > T::MAX_COUNT {
+            return Err(InvalidRequest::CountTooBigForType(count, T::MAX_COUNT));
+        }
+        Ok(range)
+    }
+
+    /// Create a builder that accumulates values starting at the specified address
+    pub fn builder(start: u16) -> WriteMultipleBuilder<T> {
+        WriteMultipleBuilder {
+            start,
+            values: Vec::new(),
+        }
     }
+}
 
+impl WriteMultiple<bool> {
+    /// Create a request from coil states that are already packed into bytes
+    ///
+    /// The bytes use the encoding of the request: the first coil is the least significant bit of
+    /// the first byte. Only the bytes needed for `count` coils are kept, and the unused bits of
+    /// the last byte are cleared, so `bytes` may be longer than required.
+    ///
+    /// Fails for the same reasons as [`WriteMultiple::from`], or with [`InvalidRequest::NotEnoughBytes`]
+    /// if `bytes` doesn't contain `count` coils.
+    pub fn from_packed(start: u16, count: u16, bytes: &[u8]) -> Result<Self, InvalidRequest> {
+        let range = AddressRange::try_from(start, count)?;
+        if count > bool::MAX_COUNT {
+            return Err(InvalidRequest::CountTooBigForType(count, bool::MAX_COUNT));
+        }
+        let required = crate::common::bits::num_bytes_for_bits(count);
+        let bytes = match bytes.get(..required) {
+            Some(x) => x,
+            None => return Err(InvalidRequest::NotEnoughBytes(bytes.len(), required)),
+        };
+        let mut bytes = bytes.to_vec();
+        crate::common::bits::clear_unused_bits(&mut bytes, count);
+        Ok(Self {
+            range,
+            values: Vec::new(),
+            packed: Some(Packed {
+                bytes,
+                get: crate::common::bits::get_bit,
+            }),
+        })
+    }
+}
+
+impl<T> WriteMultiple<T> {
     pub(crate) fn iter(&self) -> WriteMultipleIterator<'_, T> {
-        WriteMultipleIterator::new(self.range, self.values.iter())
+        WriteMultipleIterator::new(self.range, self.values.iter(), self.packed.as_ref())
+    }
+}
+
+impl<T> WriteMultipleBuilder<T>
+where
+    T: WriteMultipleValue,
+{
+    /// Append a value at the next address
+    pub fn push(mut self, value: T) -> Self {
+        self.values.push(value);
+        self
+    }
+
+    /// Append values at consecutive addresses
+    pub fn push_range<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.values.extend(values);
+        self
+    }
+
+    /// Build a single request, validating the same limits as [`WriteMultiple::from`]
+    pub fn build(self) -> Result<WriteMultiple<T>, InvalidRequest> {
+        WriteMultiple::from(self.start, self.values)
+    }
+
+    /// Split the values into consecutive requests containing at most `max` values each
+    ///
+    /// `max` is further limited to the maximum allowed by the specification for the type,
+    /// so every request is valid even if `max` is larger.
+    pub fn split_into_chunks(self, max: u16) -> Result<Vec<WriteMultiple<T>>, InvalidRequest> {
+        let max = max.min(T::MAX_COUNT);
+        if max == 0 {
+            return Err(InvalidRequest::BadRange(InvalidRange::CountOfZero));
+        }
+        if self.values.is_empty() {
+            return Err(InvalidRequest::BadRange(InvalidRange::CountOfZero));
+        }
+
+        let total = u16::try_from(self.values.len()).unwrap_or(u16::MAX);
+        let mut start = Some(self.start);
+        let mut requests = Vec::new();
+        for chunk in self.values.chunks(max as usize) {
+            // the previous chunk ended on the last address, but more values remain
+            let chunk_start = start.ok_or(InvalidRequest::BadRange(
+                InvalidRange::AddressOverflow(self.start, total),
+            ))?;
+            let request = WriteMultiple::from(chunk_start, chunk.to_vec())?;
+            start = chunk_start.checked_add(request.range.count);
+            requests.push(request);
+        }
+        Ok(requests)
     }
 }
 
 impl<'a, T> WriteMultipleIterator<'a, T> {
-    fn new(range: AddressRange, iter: std::slice::Iter<'a, T>) -> Self {
+    fn new(
+        range: AddressRange,
+        iter: std::slice::Iter<'a, T>,
+        packed: Option<&'a Packed<T>>,
+    ) -> Self {
         Self {
             range,
             pos: 0,
             iter,
+            packed,
         }
     }
 }
@@ -59,11 +285,15 @@ where
     type Item = Indexed<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.iter.next();
+        let next = match self.packed {
+            Some(packed) if self.pos < self.range.count => Some(packed.get(self.pos)),
+            Some(_) => None,
+            None => self.iter.next().copied(),
+        };
 
         match next {
             Some(next) => {
-                let result = Indexed::new(self.range.start + self.pos, *next);
+                let result = Indexed::new(self.range.start + self.pos, next);
                 self.pos += 1;
                 Some(result)
             }
@@ -78,6 +308,7 @@ where
     }
 }
 
+#[cfg(feature = "runtime")]
 pub(crate) struct MultipleWriteRequest<T>
 where
     WriteMultiple<T>: Serialize,
@@ -86,6 +317,7 @@ where
     promise: Promise<AddressRange>,
 }
 
+#[cfg(feature = "runtime")]
 impl<T> MultipleWriteRequest<T>
 where
     WriteMultiple<T>: Serialize,
@@ -106,26 +338,149 @@ where
         &mut self,
         cursor: ReadCursor,
         function: FunctionCode,
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
         let response = self.parse_all(cursor)?;
 
         if decode.data_headers() {
-            tracing::info!("PDU RX - {} {}", function, response);
+            tracing::info!(
+                fc = function.get_value(),
+                start = response.start,
+                count = response.count,
+                "PDU RX - {} {}",
+                function,
+                response
+            );
         } else if decode.header() {
-            tracing::info!("PDU RX - {}", function);
+            tracing::info!(fc = function.get_value(), "PDU RX - {}", function);
         }
 
         self.promise.success(response);
         Ok(())
     }
 
-    fn parse_all(&self, mut cursor: ReadCursor) -> Result<AddressRange, RequestError> {
-        let range = AddressRange::parse(&mut cursor)?;
-        if range != self.request.range {
-            return Err(RequestError::BadResponse(AduParseError::ReplyEchoMismatch));
-        }
-        cursor.expect_empty()?;
-        Ok(range)
+    fn parse_all(&self, cursor: ReadCursor) -> Result<AddressRange, RequestError> {
+        parse_range_echo(self.request.range, cursor)
+    }
+}
+
+/// Parse a response that must echo the range of the request
+pub(crate) fn parse_range_echo(
+    request: AddressRange,
+    mut cursor: ReadCursor,
+) -> Result<AddressRange, RequestError> {
+    let range = AddressRange::parse(&mut cursor)?;
+    if range != request {
+        return Err(RequestError::BadResponse(AduParseError::ReplyEchoMismatch));
+    }
+    cursor.expect_empty()?;
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_register_count_limit() {
+        assert!(WriteMultiple::from(0, vec![0u16; 123]).is_ok());
+        assert_eq!(
+            WriteMultiple::from(0, vec![0u16; 124]).unwrap_err(),
+            InvalidRequest::CountTooBigForType(124, 123)
+        );
+    }
+
+    #[test]
+    fn enforces_coil_count_limit() {
+        assert!(WriteMultiple::from(0, vec![false; 1968]).is_ok());
+        assert_eq!(
+            WriteMultiple::from(0, vec![false; 1969]).unwrap_err(),
+            InvalidRequest::CountTooBigForType(1969, 1968)
+        );
+    }
+
+    #[test]
+    fn creates_requests_from_iterators() {
+        let request = WriteMultiple::from_iter_checked(3, [1u16, 2, 3].iter().copied()).unwrap();
+        assert_eq!(request.range, AddressRange::try_from(3, 3).unwrap());
+        assert_eq!(request.values, vec![1, 2, 3]);
+
+        let request = WriteMultiple::from_iter_checked(0, (0..10).map(|x| x % 2 == 0)).unwrap();
+        assert_eq!(request.range.count, 10);
+
+        assert_eq!(
+            WriteMultiple::from_iter_checked(0, std::iter::repeat(0u16).take(124)).unwrap_err(),
+            InvalidRequest::CountTooBigForType(124, 123)
+        );
+        assert_eq!(
+            WriteMultiple::from_iter_checked(0, (0..70000u32).map(|_| 0u16)).unwrap_err(),
+            InvalidRequest::CountTooBigForU16(70000)
+        );
+    }
+
+    #[test]
+    fn builder_accumulates_values() {
+        let request = WriteMultiple::builder(10)
+            .push(1u16)
+            .push_range([2, 3])
+            .build()
+            .unwrap();
+        assert_eq!(request.range, AddressRange::try_from(10, 3).unwrap());
+        assert_eq!(request.values, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn splits_large_blocks_into_valid_requests() {
+        let requests = WriteMultiple::builder(100)
+            .push_range(0..250u16)
+            .split_into_chunks(u16::MAX)
+            .unwrap();
+        let ranges: Vec<AddressRange> = requests.iter().map(|x| x.range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                AddressRange::try_from(100, 123).unwrap(),
+                AddressRange::try_from(223, 123).unwrap(),
+                AddressRange::try_from(346, 4).unwrap(),
+            ]
+        );
+        assert_eq!(requests[2].values, vec![246, 247, 248, 249]);
+    }
+
+    #[test]
+    fn split_rejects_address_overflow() {
+        let result = WriteMultiple::builder(u16::MAX - 1)
+            .push_range([true; 3])
+            .split_into_chunks(2);
+        assert!(matches!(result, Err(InvalidRequest::BadRange(_))));
+    }
+
+    #[test]
+    fn packed_coils_are_masked_and_serialized_directly() {
+        // 10 coils with extra bytes and unused bits set in the last byte
+        let request = WriteMultiple::from_packed(1, 10, &[0b1010_0101, 0xFF, 0xAA]).unwrap();
+        let values: Vec<bool> = request.iter().map(|x| x.value).collect();
+        assert_eq!(
+            values,
+            vec![true, false, true, false, false, true, false, true, true, true]
+        );
+
+        let mut buffer = [0u8; 7];
+        let mut cursor = WriteCursor::new(&mut buffer);
+        request.serialize(&mut cursor).unwrap();
+        assert_eq!(buffer, [0x00, 0x01, 0x00, 0x0A, 0x02, 0b1010_0101, 0x03]);
+    }
+
+    #[test]
+    fn packed_coils_require_enough_bytes() {
+        assert_eq!(
+            WriteMultiple::from_packed(0, 9, &[0xFF]).unwrap_err(),
+            InvalidRequest::NotEnoughBytes(1, 2)
+        );
+        assert!(WriteMultiple::from_packed(0, 8, &[0xFF]).is_ok());
+        assert_eq!(
+            WriteMultiple::from_packed(0, 0, &[]).unwrap_err(),
+            InvalidRequest::BadRange(InvalidRange::CountOfZero)
+        );
     }
 }
@@ -1,30 +1,34 @@
-use std::fmt::Display;
-
-use crate::client::message::Promise;
-use crate::common::function::FunctionCode;
-use crate::decode::AppDecodeLevel;
 use crate::error::AduParseError;
 use crate::error::RequestError;
 use crate::types::{coil_from_u16, coil_to_u16, Indexed};
+#[cfg(feature = "runtime")]
+use crate::{
+    client::message::Promise, common::function::FunctionCode, common::traits::Loggable,
+    decode::AppDecode, types::Redact, types::ValueDisplay,
+};
 
 use scursor::{ReadCursor, WriteCursor};
+#[cfg(feature = "runtime")]
+use std::fmt::Display;
 
 pub(crate) trait SingleWriteOperation: Sized + PartialEq {
     fn serialize(&self, cursor: &mut WriteCursor) -> Result<(), RequestError>;
     fn parse(cursor: &mut ReadCursor) -> Result<Self, RequestError>;
 }
 
+#[cfg(feature = "runtime")]
 pub(crate) struct SingleWrite<T>
 where
-    T: SingleWriteOperation + Display + Send + 'static,
+    T: SingleWriteOperation + Loggable + Display + Redact + Send + 'static,
 {
     pub(crate) request: T,
     promise: Promise<T>,
 }
 
+#[cfg(feature = "runtime")]
 impl<T> SingleWrite<T>
 where
-    T: SingleWriteOperation + Display + Send + 'static,
+    T: SingleWriteOperation + Loggable + Display + Redact + Send + 'static,
 {
     pub(crate) fn new(request: T, promise: Promise<T>) -> Self {
         Self { request, promise }
@@ -42,28 +46,44 @@ where
         &mut self,
         cursor: ReadCursor,
         function: FunctionCode,
-        decode: AppDecodeLevel,
+        decode: AppDecode,
     ) -> Result<(), RequestError> {
         let response = self.parse_all(cursor)?;
 
         if decode.data_headers() {
-            tracing::info!("PDU RX - {} {}", function, response);
+            let range = response.address_range();
+            tracing::info!(
+                fc = function.get_value(),
+                start = range.map(|x| x.start),
+                count = range.map(|x| x.count),
+                "PDU RX - {} {}",
+                function,
+                ValueDisplay::new(decode, &response)
+            );
         } else if decode.header() {
-            tracing::info!("PDU RX - {}", function);
+            tracing::info!(fc = function.get_value(), "PDU RX - {}", function);
         }
 
         self.promise.success(response);
         Ok(())
     }
 
-    fn parse_all(&self, mut cursor: ReadCursor) -> Result<T, RequestError> {
-        let response = T::parse(&mut cursor)?;
-        cursor.expect_empty()?;
-        if self.request != response {
-            return Err(AduParseError::ReplyEchoMismatch.into());
-        }
-        Ok(response)
+    fn parse_all(&self, cursor: ReadCursor) -> Result<T, RequestError> {
+        parse_echo(&self.request, cursor)
+    }
+}
+
+/// Parse a response that must echo the request
+pub(crate) fn parse_echo<T>(request: &T, mut cursor: ReadCursor) -> Result<T, RequestError>
+where
+    T: SingleWriteOperation,
+{
+    let response = T::parse(&mut cursor)?;
+    cursor.expect_empty()?;
+    if *request != response {
+        return Err(AduParseError::ReplyEchoMismatch.into());
     }
+    Ok(response)
 }
 
 impl SingleWriteOperation for Indexed<bool> {
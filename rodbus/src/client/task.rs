@@ -2,10 +2,15 @@ use std::time::Duration;
 
 use tracing::Instrument;
 
+use crate::capture::Capture;
 use crate::common::phys::PhysLayer;
 use tokio::time::Instant;
 
-use crate::client::message::{Command, Request, Setting};
+use crate::client::message::{Command, Completion, Request, Setting};
+use crate::client::{
+    ChannelState, ChannelStatistics, ClientState, Listener, Outcome, StaleResponsePolicy,
+    StateTracker, TransactionOutcome,
+};
 use crate::common::frame::{FrameHeader, FrameWriter, FramedReader, TxId};
 use crate::error::*;
 use crate::DecodeLevel;
@@ -21,6 +26,11 @@ pub(crate) enum SessionError {
     BadFrame,
     /// channel was disabled
     Disabled,
+    /// no requests were made within the idle timeout
+    Idle,
+    /// the serial port must be re-opened with new settings
+    #[cfg_attr(not(feature = "serial"), allow(dead_code))]
+    SerialSettingsChanged,
     /// the mpsc is closed (dropped) on the sender side
     Shutdown,
 }
@@ -55,9 +65,15 @@ impl std::fmt::Display for SessionError {
             SessionError::Disabled => {
                 write!(f, "Channel was disabled")
             }
+            SessionError::Idle => {
+                write!(f, "No requests within the idle timeout")
+            }
             SessionError::Shutdown => {
                 write!(f, "Shutdown was requested")
             }
+            SessionError::SerialSettingsChanged => {
+                write!(f, "Serial settings were changed")
+            }
         }
     }
 }
@@ -80,6 +96,37 @@ pub(crate) struct ClientLoop {
     tx_id: TxId,
     decode: DecodeLevel,
     enabled: bool,
+    idle_timeout: Option<Duration>,
+    last_activity: Instant,
+    // request that triggered a reconnect after an idle disconnect
+    pending: Option<Request>,
+    // response timeouts for requests that don't specify one
+    timeouts: crate::client::TimeoutTable,
+    // quiet period after each transaction
+    inter_request_delay: Option<Duration>,
+    stale_response_policy: StaleResponsePolicy,
+    // stale responses received since the last response with the expected tx id
+    consecutive_stale_responses: usize,
+    accept_mismatched_unit_id: bool,
+    statistics: ChannelStatistics,
+    transaction_listener: Option<Box<dyn Listener<TransactionOutcome>>>,
+    // callers waiting for the channel to start connecting
+    enable_waiters: Vec<Completion>,
+    // callers waiting for the channel to be closed and disabled
+    disable_waiters: Vec<Completion>,
+    // receives the raw bytes of each session
+    capture: Option<Capture>,
+    // last state reported to the listener of the channel
+    state: std::sync::Arc<tokio::sync::watch::Sender<ChannelState>>,
+    // only serial channels accept new serial settings
+    #[cfg(feature = "serial")]
+    accepts_serial_settings: bool,
+    // settings to apply the next time the serial port is opened
+    #[cfg(feature = "serial")]
+    serial_settings: Option<crate::serial::SerialSettings>,
+    // parameters of the TLS session while one is established
+    #[cfg(feature = "tls")]
+    tls_info: Option<crate::tcp::tls::TlsInfo>,
 }
 
 impl ClientLoop {
@@ -96,32 +143,103 @@ impl ClientLoop {
             tx_id: TxId::default(),
             decode,
             enabled: false,
+            idle_timeout: None,
+            last_activity: Instant::now(),
+            pending: None,
+            timeouts: Default::default(),
+            inter_request_delay: None,
+            stale_response_policy: StaleResponsePolicy::Discard,
+            consecutive_stale_responses: 0,
+            accept_mismatched_unit_id: false,
+            statistics: ChannelStatistics::default(),
+            transaction_listener: None,
+            enable_waiters: Vec::new(),
+            disable_waiters: Vec::new(),
+            capture: None,
+            state: std::sync::Arc::new(tokio::sync::watch::Sender::new(ChannelState::Client(
+                ClientState::Disabled,
+            ))),
+            #[cfg(feature = "serial")]
+            accepts_serial_settings: false,
+            #[cfg(feature = "serial")]
+            serial_settings: None,
+            #[cfg(feature = "tls")]
+            tls_info: None,
         }
     }
 
+    /// Accept [`Setting::SerialSettings`], which are retrieved with `take_serial_settings`
+    #[cfg(feature = "serial")]
+    pub(crate) fn accept_serial_settings(&mut self) {
+        self.accepts_serial_settings = true;
+    }
+
+    /// New serial settings received since the last call, if any
+    #[cfg(feature = "serial")]
+    pub(crate) fn take_serial_settings(&mut self) -> Option<crate::serial::SerialSettings> {
+        self.serial_settings.take()
+    }
+
+    /// Wrap the listener of the channel so that the states it receives can be queried
+    pub(crate) fn track_state<T>(&self, listener: Box<dyn Listener<T>>) -> Box<dyn Listener<T>>
+    where
+        T: Into<ChannelState> + Copy + Send + 'static,
+    {
+        Box::new(StateTracker::new(listener, self.state.clone()))
+    }
+
+    /// Close the session if no requests are made for the specified duration
+    pub(crate) fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
     pub(crate) fn is_enabled(&self) -> bool {
         self.enabled
     }
 
+    pub(crate) fn has_pending_request(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub(crate) fn fail_pending_request(&mut self) {
+        if let Some(mut request) = self.pending.take() {
+            request.details.fail(RequestError::NoConnection);
+        }
+    }
+
     async fn run_cmd(&mut self, cmd: Command, io: &mut PhysLayer) -> Result<(), SessionError> {
         match cmd {
             Command::Setting(setting) => {
                 self.change_setting(setting);
+                io.set_capture(self.capture.clone());
                 if !self.enabled {
                     return Err(SessionError::Disabled);
                 }
+                #[cfg(feature = "serial")]
+                if self.serial_settings.is_some() {
+                    return Err(SessionError::SerialSettingsChanged);
+                }
                 Ok(())
             }
-            Command::Request(mut request) => self.run_one_request(io, &mut request).await,
+            Command::Request(request) => self.run_one_request(io, request).await,
         }
     }
 
     pub(crate) async fn wait_for_enabled(&mut self) -> Result<(), Shutdown> {
+        if !self.enabled {
+            // a request waiting to be retried is not sent once the channel is disabled
+            self.fail_pending_request();
+        }
+
         loop {
             if self.enabled {
+                Self::complete(&mut self.enable_waiters);
                 return Ok(());
             }
 
+            // any session has been closed by the time we get here
+            Self::complete(&mut self.disable_waiters);
+
             if let Err(StateChange::Shutdown) = self.fail_next_request().await {
                 return Err(Shutdown);
             }
@@ -129,15 +247,54 @@ impl ClientLoop {
     }
 
     pub(crate) async fn run(&mut self, io: &mut PhysLayer) -> SessionError {
+        #[cfg(feature = "tls")]
+        {
+            self.tls_info = io.tls_info();
+        }
+        io.set_capture(self.capture.clone());
+        let err = self.run_session(io).await;
+        #[cfg(feature = "tls")]
+        {
+            self.tls_info = None;
+        }
+        err
+    }
+
+    async fn run_session(&mut self, io: &mut PhysLayer) -> SessionError {
+        self.last_activity = Instant::now();
+        // bytes left over from a previous session must not be parsed as part of this one
+        self.reader.discard();
+        self.consecutive_stale_responses = 0;
+
+        if let Some(request) = self.pending.take() {
+            if let Err(err) = self.run_one_request(io, request).await {
+                tracing::warn!("ending session: {}", err);
+                return err;
+            }
+            self.last_activity = Instant::now();
+        }
+
         loop {
             if let Err(err) = self.poll(io).await {
-                tracing::warn!("ending session: {}", err);
+                if err == SessionError::Idle {
+                    tracing::info!("ending session: {}", err);
+                } else {
+                    tracing::warn!("ending session: {}", err);
+                }
                 return err;
             }
         }
     }
 
+    async fn idle_timer(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
     async fn poll(&mut self, io: &mut PhysLayer) -> Result<(), SessionError> {
+        let idle_deadline = self.idle_timeout.map(|x| self.last_activity + x);
         tokio::select! {
             frame = self.reader.next_frame(io, self.decode) => {
                 match frame {
@@ -153,7 +310,12 @@ impl ClientLoop {
             }
             res = self.rx.recv() => {
                 let cmd: Command = res?;
-                self.run_cmd(cmd, io).await
+                let result = self.run_cmd(cmd, io).await;
+                self.last_activity = Instant::now();
+                result
+            }
+            _ = Self::idle_timer(idle_deadline) => {
+                Err(SessionError::Idle)
             }
         }
     }
@@ -161,28 +323,116 @@ impl ClientLoop {
     async fn run_one_request(
         &mut self,
         io: &mut PhysLayer,
-        request: &mut Request,
+        mut request: Request,
     ) -> Result<(), SessionError> {
-        let tx_id = self.tx_id.next();
-        let result = self
-            .execute_request(io, request, tx_id)
-            .instrument(tracing::info_span!("Transaction", tx_id = %tx_id))
-            .await;
+        loop {
+            if request.cancel.as_ref().is_some_and(|x| x.is_cancelled()) {
+                tracing::info!("request cancelled before it was sent");
+                request.details.fail(RequestError::Cancelled);
+                return Ok(());
+            }
+
+            if let Some(deadline) = request.deadline {
+                if deadline <= Instant::now() {
+                    // the device was never asked, so the request isn't retried
+                    tracing::warn!(
+                        "deadline passed {:?} after the request was queued, request not sent",
+                        request.queued.elapsed()
+                    );
+                    request.details.fail(RequestError::ResponseTimeout);
+                    return Ok(());
+                }
+            }
+
+            let tx_id = self.tx_id.next();
+            let unit = request.id.value;
+            let start = Instant::now();
+            let started = *request.started.get_or_insert(start);
+            let span = tracing::info_span!(
+                "Transaction",
+                tx_id = %tx_id,
+                unit,
+                correlation_id = tracing::field::Empty
+            );
+            if let Some(id) = request.correlation_id {
+                span.record("correlation_id", id);
+            }
+            let result = self
+                .execute_request(io, &mut request, tx_id)
+                .instrument(span)
+                .await;
+            request.attempts = request.attempts.saturating_add(1);
+            // measured before the inter-request delay, which isn't part of the round trip
+            let elapsed = started.elapsed();
+            let attempt = start.elapsed();
+
+            crate::common::metrics::client_request(request.details.function(), &result, attempt);
+            self.record_transaction(&request, &result).await;
+
+            // applies to failed transactions too, the device may still be transmitting a late reply
+            if let Some(delay) = self.inter_request_delay {
+                tokio::time::sleep(delay).await;
+            }
+
+            let mut err = match result {
+                Ok(()) => {
+                    request.complete_meta(tx_id, elapsed);
+                    return Ok(());
+                }
+                Err(err) => err,
+            };
+
+            // some request errors are a session error that will
+            // bubble up and close the session
+            let session_err = SessionError::from_request_err(err);
+
+            if err == RequestError::ResponseTimeout {
+                // tells a slow device apart from a request that waited behind others
+                tracing::warn!(
+                    "response timeout after {:?} in the queue and {:?} on the wire",
+                    request.queue_wait(),
+                    attempt
+                );
+            }
+
+            if let Some(cause) = request.retry_cause(err) {
+                if request.retries > 0 {
+                    request.retries -= 1;
+                    tracing::warn!(
+                        "request error: {}, retrying ({} retries remaining)",
+                        err,
+                        request.retries
+                    );
+                    match session_err {
+                        // the session is still usable, retry right away
+                        None => continue,
+                        // retry before any other request once a new session is established
+                        Some(session_err) => {
+                            self.pending = Some(request);
+                            return Err(session_err);
+                        }
+                    }
+                }
+
+                if request.attempts > 1 {
+                    err = RequestError::RetriesExhausted(RetriesExhausted {
+                        attempts: request.attempts,
+                        cause,
+                    });
+                }
+            }
 
-        if let Err(err) = result {
             // Fail the request in ONE place. If the whole future
             // gets dropped, then the request gets failed with Shutdown
             tracing::warn!("request error: {}", err);
             request.details.fail(err);
+            request.complete_meta(tx_id, elapsed);
 
-            // some request errors are a session error that will
-            // bubble up and close the session
-            if let Some(err) = SessionError::from_request_err(err) {
-                return Err(err);
-            }
+            return match session_err {
+                Some(err) => Err(err),
+                None => Ok(()),
+            };
         }
-
-        Ok(())
     }
 
     async fn execute_request(
@@ -198,35 +448,126 @@ impl ClientLoop {
             self.decode,
         )?;
 
-        io.write(bytes, self.decode.physical).await?;
+        // serial drivers may still be clocking the bytes out once the write completes,
+        // so the response window starts after the estimated transmission time
+        let transmit_time = io.transmit_time(bytes.len());
+
+        match request.write_timeout {
+            None => io.write(bytes, self.decode).await?,
+            Some(timeout) => tokio::time::timeout(timeout, io.write(bytes, self.decode))
+                .await
+                .map_err(|_| RequestError::Io(std::io::ErrorKind::TimedOut))??,
+        }
+
+        let timeout = request
+            .timeout
+            .unwrap_or_else(|| self.timeouts.get(request.details.function()));
+        let sent = Instant::now();
+        let deadline = request
+            .deadline
+            .unwrap_or_else(|| sent + transmit_time + timeout);
 
-        let deadline = Instant::now() + request.timeout;
+        let mut cancelled = false;
 
         // loop until we get a response with the correct tx id or we timeout
         let response = loop {
             let frame = tokio::select! {
                 _ = tokio::time::sleep_until(deadline) => {
+                    if cancelled {
+                        return Err(RequestError::Cancelled);
+                    }
                     return Err(RequestError::ResponseTimeout);
                 }
+                _ = crate::client::cancel::cancelled(request.cancel.as_ref()), if !cancelled => {
+                    // the caller stops waiting right away
+                    request.details.fail(RequestError::Cancelled);
+                    if self.reader.has_tx_id() {
+                        // the late response is discarded as a stale response
+                        return Err(RequestError::Cancelled);
+                    }
+                    // RTU responses can't be matched to requests, so the late response is
+                    // consumed before the next request is sent
+                    cancelled = true;
+                    continue;
+                }
                 frame = self.reader.next_frame(io, self.decode) => {
                     frame?
                 }
             };
 
+            if cancelled {
+                tracing::info!("discarding response to cancelled request");
+                return Err(RequestError::Cancelled);
+            }
+
             if let Some(received_tx_id) = frame.header.tx_id {
                 // Check that the received transaction ID matches (only in TCP MBAP)
                 if received_tx_id != tx_id {
-                    tracing::warn!("received {:?} while expecting {:?}", received_tx_id, tx_id);
+                    self.on_stale_response(received_tx_id, tx_id, sent.elapsed())?;
                     continue; // next iteration of loop
                 }
             }
 
+            let received_unit_id = frame.header.destination.into_unit_id();
+            if received_unit_id != request.id && !self.accept_mismatched_unit_id {
+                self.statistics.mismatched_unit_ids += 1;
+                tracing::warn!(
+                    "discarding response with unit id {} while expecting {}",
+                    received_unit_id,
+                    request.id
+                );
+                continue;
+            }
+
+            self.consecutive_stale_responses = 0;
+
             break frame;
         };
 
         // once we have a response, handle it. This may complete a promise
         // successfully or bubble up an error
-        request.handle_response(response.payload(), self.decode.app)
+        request.handle_response(response.payload(), self.decode.app_decode())
+    }
+
+    /// count the outcome of a transaction and report it to the listener
+    async fn record_transaction(&mut self, request: &Request, result: &Result<(), RequestError>) {
+        let outcome = match Outcome::from_result(result) {
+            Some(x) => x,
+            None => return,
+        };
+        self.statistics.record(outcome);
+        if let Some(listener) = self.transaction_listener.as_mut() {
+            let transaction = TransactionOutcome {
+                unit_id: request.id,
+                function: request.details.function(),
+                outcome,
+            };
+            listener.update(transaction).get().await;
+        }
+    }
+
+    fn on_stale_response(
+        &mut self,
+        received: TxId,
+        expected: TxId,
+        age: Duration,
+    ) -> Result<(), RequestError> {
+        self.statistics.stale_responses += 1;
+        self.consecutive_stale_responses += 1;
+        tracing::warn!(
+            "discarding response with tx id {} while expecting {}, {:?} after the request was sent",
+            received,
+            expected,
+            age
+        );
+        match self.stale_response_policy {
+            StaleResponsePolicy::AbortSession(limit)
+                if self.consecutive_stale_responses >= limit.get() =>
+            {
+                Err(FrameParseError::StaleResponses(self.consecutive_stale_responses).into())
+            }
+            _ => Ok(()),
+        }
     }
 
     pub(crate) fn change_setting(&mut self, setting: Setting) {
@@ -235,19 +576,105 @@ impl ClientLoop {
                 tracing::info!("Decode level changed: {:?}", level);
                 self.decode = level;
             }
-            Setting::Enable => {
-                if !self.enabled {
+            Setting::Enable(waiter) => {
+                if self.enabled {
+                    // nothing to transition, the channel is already connecting or connected
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(());
+                    }
+                } else {
                     self.enabled = true;
                     tracing::info!("channel enabled");
+                    self.enable_waiters.extend(waiter);
                 }
             }
-            Setting::Disable => {
+            Setting::Disable(waiter) => {
                 if self.enabled {
                     self.enabled = false;
                     tracing::info!("channel disabled");
                 }
+                // completed by wait_for_enabled once the session is closed
+                self.disable_waiters.extend(waiter);
+            }
+            Setting::Timeouts(table) => {
+                tracing::info!("Response timeouts changed: {:?}", table);
+                self.timeouts = table;
+            }
+            Setting::InterRequestDelay(delay) => {
+                tracing::info!("Inter-request delay changed: {:?}", delay);
+                self.inter_request_delay = delay;
+            }
+            Setting::Capture(capture) => {
+                tracing::info!("Capture enabled: {}", capture.is_some());
+                self.capture = capture;
+            }
+            #[cfg(feature = "serial")]
+            Setting::SerialSettings(settings, reply) => {
+                if self.accepts_serial_settings {
+                    tracing::info!("Serial settings changed: {:?}", settings);
+                    self.serial_settings = Some(settings);
+                    let _ = reply.send(Ok(()));
+                } else {
+                    let _ = reply.send(Err(crate::client::SerialSettingsError::NotSerial));
+                }
+            }
+            Setting::StaleResponsePolicy(policy) => {
+                tracing::info!("Stale response policy changed: {:?}", policy);
+                self.stale_response_policy = policy;
+            }
+            Setting::AcceptMismatchedUnitId(accept) => {
+                tracing::info!("Accept mismatched unit id changed: {}", accept);
+                self.accept_mismatched_unit_id = accept;
+            }
+            Setting::PurgeQueue(reply) => {
+                let settings = self.purge_queue(reply);
+                // settings queued behind the purged requests still apply
+                for setting in settings {
+                    self.change_setting(setting);
+                }
+            }
+            Setting::TransactionListener(listener) => {
+                self.transaction_listener = listener;
+            }
+            Setting::GetStatistics(reply) => {
+                let _ = reply.send(self.statistics);
+            }
+            Setting::SubscribeState(reply) => {
+                let _ = reply.send(self.state.subscribe());
+            }
+            #[cfg(feature = "tls")]
+            Setting::GetTlsInfo(reply) => {
+                let _ = reply.send(self.tls_info.clone());
+            }
+        }
+    }
+
+    /// Fail the queued requests and the request waiting to be retried, returning the queued settings
+    fn purge_queue(&mut self, reply: tokio::sync::oneshot::Sender<usize>) -> Vec<Setting> {
+        let mut count = 0;
+        let mut settings = Vec::new();
+        if let Some(mut request) = self.pending.take() {
+            request.details.fail(RequestError::Cancelled);
+            count += 1;
+        }
+        while let Some(cmd) = self.rx.try_recv() {
+            match cmd {
+                Command::Request(mut request) => {
+                    request.details.fail(RequestError::Cancelled);
+                    count += 1;
+                }
+                Command::Setting(setting) => settings.push(setting),
             }
         }
+        tracing::info!("purged {} queued request(s)", count);
+        let _ = reply.send(count);
+        settings
+    }
+
+    fn complete(waiters: &mut Vec<Completion>) {
+        for waiter in waiters.drain(..) {
+            let _ = waiter.send(());
+        }
     }
 
     async fn fail_next_request(&mut self) -> Result<(), StateChange> {
@@ -275,6 +702,50 @@ impl ClientLoop {
         }
     }
 
+    /// Wait for the next request after an idle disconnect, applying any settings received in the meantime
+    ///
+    /// The request is executed as soon as the session is re-established by the next call to `run`
+    pub(crate) async fn wait_for_request(&mut self) -> Result<(), StateChange> {
+        loop {
+            match self.rx.recv().await? {
+                Command::Request(request) => {
+                    self.pending = Some(request);
+                    return Ok(());
+                }
+                Command::Setting(x) => {
+                    self.change_setting(x);
+                    if !self.enabled {
+                        return Err(StateChange::Disable);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fail all requests until the channel is explicitly enabled (returns `Ok`) or disabled
+    pub(crate) async fn fail_requests_until_enabled(&mut self) -> Result<(), StateChange> {
+        loop {
+            match self.rx.recv().await? {
+                Command::Request(mut req) => {
+                    req.details.fail(RequestError::NoConnection);
+                }
+                Command::Setting(Setting::Enable(waiter)) => {
+                    self.enabled = true;
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(());
+                    }
+                    return Ok(());
+                }
+                Command::Setting(x) => {
+                    self.change_setting(x);
+                    if !self.enabled {
+                        return Err(StateChange::Disable);
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) async fn fail_requests_for(
         &mut self,
         duration: Duration,
@@ -297,7 +768,8 @@ mod tests {
     use std::io::ErrorKind;
 
     use super::*;
-    use crate::client::{Channel, RequestParam};
+    use crate::capture::{Direction, FrameSink};
+    use crate::client::{Channel, RequestParam, ResponseMeta, RetryOn, SnapshotSpec};
     use crate::common::function::FunctionCode;
     use crate::common::traits::{Loggable, Serialize};
     use crate::decode::*;
@@ -307,33 +779,69 @@ mod tests {
 
     use sfio_tokio_mock_io::Event;
 
+    // creates the writer and reader of a framing
+    type Framing = fn() -> (FrameWriter, FramedReader);
+    // frames a PDU
+    type FrameFn = fn(&[u8]) -> Vec<u8>;
+
     fn spawn_client_loop() -> (
         Channel,
         tokio::task::JoinHandle<SessionError>,
         sfio_tokio_mock_io::Handle,
     ) {
-        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        spawn_client_loop_with_write_delay(Duration::ZERO)
+    }
+
+    fn spawn_client_loop_with_write_delay(
+        write_delay: Duration,
+    ) -> (
+        Channel,
+        tokio::task::JoinHandle<SessionError>,
+        sfio_tokio_mock_io::Handle,
+    ) {
+        spawn_client_loop_with(write_delay, None)
+    }
+
+    fn spawn_client_loop_with(
+        write_delay: Duration,
+        capture: Option<Capture>,
+    ) -> (
+        Channel,
+        tokio::task::JoinHandle<SessionError>,
+        sfio_tokio_mock_io::Handle,
+    ) {
+        let (tx, rx) = crate::channel::channel(16);
         let (mock, io_handle) = sfio_tokio_mock_io::mock();
         let mut client_loop = ClientLoop::new(
-            rx.into(),
+            rx,
             FrameWriter::tcp(),
             FramedReader::tcp(),
             DecodeLevel::default().application(AppDecodeLevel::DataValues),
         );
+        client_loop.capture = capture;
+        // sessions only run while enabled, otherwise every setting ends the session
+        client_loop.enabled = true;
         let join_handle = tokio::spawn(async move {
-            let mut phys = PhysLayer::new_mock(mock);
+            let mut phys = PhysLayer::new_mock_with_write_delay(mock, write_delay);
             client_loop.run(&mut phys).await
         });
-        let channel = Channel { tx };
+        let channel = Channel::new(tx);
         (channel, join_handle, io_handle)
     }
 
     fn get_framed_adu<T>(function: FunctionCode, payload: &T) -> Vec<u8>
+    where
+        T: Serialize + Loggable + Sized,
+    {
+        get_framed_adu_with_tx_id(function, payload, TxId::new(0))
+    }
+
+    fn get_framed_adu_with_tx_id<T>(function: FunctionCode, payload: &T, tx_id: TxId) -> Vec<u8>
     where
         T: Serialize + Loggable + Sized,
     {
         let mut fmt = FrameWriter::tcp();
-        let header = FrameHeader::new_tcp_header(UnitId::new(1), TxId::new(0));
+        let header = FrameHeader::new_tcp_header(UnitId::new(1), tx_id);
         let bytes = fmt
             .format_request(header, function, payload, DecodeLevel::nothing())
             .unwrap();
@@ -394,74 +902,1349 @@ mod tests {
         assert_eq!(result, Err(RequestError::ResponseTimeout));
     }
 
-    #[tokio::test]
-    async fn returns_shutdown_when_task_dropped() {
-        let (mut channel, task, mut io) = spawn_client_loop();
+    #[tokio::test(start_paused = true)]
+    async fn response_timeout_starts_after_write_completes() {
+        // the write takes longer than the response timeout
+        let (mut channel, _task, mut io) =
+            spawn_client_loop_with_write_delay(Duration::from_secs(2));
 
-        // the expected request
         let range = AddressRange::try_from(7, 2).unwrap();
         let request = get_framed_adu(FunctionCode::ReadCoils, &range);
+        let response = get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        );
 
-        // spawn a task that will perform the read coils
-        let request_task = tokio::spawn(async move {
+        let coils = tokio::spawn(async move {
             channel
                 .read_coils(
-                    RequestParam::new(UnitId::new(1), Duration::from_secs(5)),
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
                     range,
                 )
                 .await
         });
-        // wait until the task writes the request so that we know it's in the correct state
-        assert_eq!(io.next_event().await, Event::Write(request));
 
-        // now drop the task
-        task.abort();
-        assert!(task.await.is_err());
+        assert_eq!(io.next_event().await, Event::Write(request));
+        // most of the response window remains once the write completes
+        tokio::time::advance(Duration::from_millis(900)).await;
+        io.read(&response);
 
-        // the promise will get dropped causing the request to fail with Shutdown
-        let res = request_task.await.unwrap();
-        assert_eq!(res, Err(RequestError::Shutdown));
+        assert_eq!(
+            coils.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, true)]
+        );
     }
 
-    #[tokio::test]
-    async fn framing_errors_kill_the_session_while_idle() {
-        let (_channel, task, mut io) = spawn_client_loop();
+    #[tokio::test(start_paused = true)]
+    async fn waits_between_transactions_when_inter_request_delay_is_set() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+        let delay = Duration::from_millis(250);
+        channel.set_inter_request_delay(Some(delay)).await.unwrap();
 
-        io.read(&[0x00, 0x00, 0xCA, 0xFE, 0x00, 0x01, 0x01]); // non-Modbus protocol id
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let request =
+            |tx_id| get_framed_adu_with_tx_id(FunctionCode::ReadCoils, &range, TxId::new(tx_id));
+        let response = get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        );
 
-        assert_eq!(task.await.unwrap(), SessionError::BadFrame);
+        let mut requests = Vec::new();
+        for _ in 0..3 {
+            let mut channel = channel.clone();
+            requests.push(tokio::spawn(async move {
+                channel
+                    .read_coils(
+                        RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                        range,
+                    )
+                    .await
+            }));
+        }
+        let mut requests = requests.into_iter();
+
+        // the first transaction succeeds
+        assert_eq!(io.next_event().await, Event::Write(request(0)));
+        tokio::time::advance(Duration::from_millis(10)).await;
+        io.read(&response);
+        assert_eq!(io.next_event().await, Event::Read);
+        let completed = Instant::now();
+        assert!(requests.next().unwrap().await.unwrap().is_ok());
+
+        // the second transaction times out
+        assert_eq!(io.next_event().await, Event::Write(request(1)));
+        assert_eq!(completed.elapsed(), delay);
+        let sent = Instant::now();
+        assert_eq!(
+            requests.next().unwrap().await.unwrap(),
+            Err(RequestError::ResponseTimeout)
+        );
+
+        assert_eq!(io.next_event().await, Event::Write(request(2)));
+        assert_eq!(sent.elapsed(), Duration::from_secs(1) + delay);
     }
 
-    #[tokio::test]
-    async fn transmit_read_coils_when_requested() {
+    #[tokio::test(start_paused = true)]
+    async fn returns_response_meta_with_correlation_id() {
+        let logs = crate::decode::test_log::LogCapture::default();
+        let _guard = logs.install();
         let (mut channel, _task, mut io) = spawn_client_loop();
 
         let range = AddressRange::try_from(7, 2).unwrap();
         let request = get_framed_adu(FunctionCode::ReadCoils, &range);
         let response = get_framed_adu(
             FunctionCode::ReadCoils,
-            &BitWriter::new(ReadBitsRange { inner: range }, |idx| match idx {
-                7 => Ok(true),
-                8 => Ok(false),
-                _ => Err(ExceptionCode::IllegalDataAddress),
-            }),
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
         );
 
         let coils = tokio::spawn(async move {
-            channel
-                .read_coils(
-                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
-                    range,
-                )
-                .await
+            let param =
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1)).with_correlation_id(42);
+            channel.read_coils_with_meta(param, range).await
         });
 
         assert_eq!(io.next_event().await, Event::Write(request));
+        tokio::time::advance(Duration::from_millis(300)).await;
         io.read(&response);
 
+        let (result, meta) = coils.await.unwrap();
+        assert!(result.is_ok());
         assert_eq!(
-            coils.await.unwrap().unwrap(),
-            vec![Indexed::new(7, true), Indexed::new(8, false)]
+            meta,
+            ResponseMeta {
+                tx_id: Some(0),
+                correlation_id: Some(42),
+                queue_wait: Duration::ZERO,
+                elapsed: Duration::from_millis(300),
+            }
+        );
+
+        let logs = logs.take();
+        let span = "Transaction{tx_id=0x00 unit=1 correlation_id=42}";
+        assert!(logs.contains(span), "{span} not found in:\n{logs}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn response_meta_reports_time_spent_in_the_queue() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+        let first = AddressRange::try_from(0, 1).unwrap();
+        let second = AddressRange::try_from(1, 1).unwrap();
+        let read = |range| {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils_with_meta(param, range).await })
+        };
+
+        let first_task = read(first);
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &first))
+        );
+        // queued behind the first request
+        let second_task = read(second);
+        // let the spawned task enqueue the request before the time advances
+        tokio::task::yield_now().await;
+        tokio::time::advance(Duration::from_millis(300)).await;
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: first }, |_| Ok(true)),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+        let (_, meta) = first_task.await.unwrap();
+        assert_eq!(meta.queue_wait, Duration::ZERO);
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &second,
+                TxId::new(1)
+            ))
+        );
+        tokio::time::advance(Duration::from_millis(100)).await;
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: second }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+        let (result, meta) = second_task.await.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(meta.queue_wait, Duration::from_millis(300));
+        assert_eq!(meta.elapsed, Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_with_a_deadline_time_out_at_the_deadline() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let read = |range, param| {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils(param, range).await })
+        };
+        let first = AddressRange::try_from(0, 1).unwrap();
+        let expired = AddressRange::try_from(1, 1).unwrap();
+        let last = AddressRange::try_from(2, 1).unwrap();
+
+        let first_task = read(
+            first,
+            param.with_deadline(Instant::now() + Duration::from_millis(200)),
+        );
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &first))
+        );
+        // the deadline passes while the first request is in progress
+        let expired_task = read(
+            expired,
+            param.with_deadline(Instant::now() + Duration::from_millis(100)),
+        );
+        let last_task = read(last, param);
+        tokio::task::yield_now().await;
+
+        // the deadline replaces the response timeout of 5 seconds
+        tokio::time::advance(Duration::from_millis(200)).await;
+        assert_eq!(
+            first_task.await.unwrap(),
+            Err(RequestError::ResponseTimeout)
+        );
+        assert_eq!(
+            expired_task.await.unwrap(),
+            Err(RequestError::ResponseTimeout)
+        );
+
+        // the expired request is never sent
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &last,
+                TxId::new(1)
+            ))
+        );
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: last }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+        assert!(last_task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn identical_concurrent_reads_are_sent_once_when_coalescing() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        channel.set_read_coalescing(Some(crate::client::ReadCoalescing::new(
+            Duration::from_secs(1),
+        )));
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let range = AddressRange::try_from(0, 3).unwrap();
+        let read = || {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils(param, range).await })
+        };
+
+        let tasks = [read(), read(), read()];
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
         );
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+        let expected: Vec<_> = range.iter().map(|x| Indexed::new(x, true)).collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), Ok(expected.clone()));
+        }
+
+        // a completed transaction isn't reused without a cache TTL
+        let last = read();
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &range,
+                TxId::new(1)
+            ))
+        );
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(last.await.unwrap(), Err(RequestError::ResponseTimeout));
+        assert_eq!(channel.coalescer.entries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn decode_level_changes_apply_to_tcp_and_rtu_channels() {
+        let logs = crate::decode::test_log::LogCapture::default();
+        let _guard = logs.install();
+
+        for (writer, reader, prefix) in [
+            (FrameWriter::tcp(), FramedReader::tcp(), "MBAP TX"),
+            (FrameWriter::rtu(), FramedReader::rtu_response(), "RTU TX"),
+        ] {
+            let (tx, rx) = crate::channel::channel(16);
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let mut client_loop = ClientLoop::new(rx, writer, reader, DecodeLevel::nothing());
+            client_loop.enabled = true;
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                client_loop.run(&mut phys).await
+            });
+            let mut channel = Channel::new(tx);
+
+            for (level, logged) in [
+                (DecodeLevel::nothing(), false),
+                (DecodeLevel::nothing().frame(FrameDecodeLevel::Header), true),
+                (DecodeLevel::nothing(), false),
+            ] {
+                channel.set_decode_level(level).await.unwrap();
+                let mut requester = channel.clone();
+                let request = tokio::spawn(async move {
+                    requester
+                        .read_coils(
+                            RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                            AddressRange::try_from(0, 1).unwrap(),
+                        )
+                        .await
+                });
+                assert!(matches!(io.next_event().await, Event::Write(_)));
+                assert_eq!(request.await.unwrap(), Err(RequestError::ResponseTimeout));
+                assert_eq!(logs.take().contains(prefix), logged);
+            }
+        }
+    }
+
+    fn mbap_frame(pdu: &[u8]) -> Vec<u8> {
+        mbap_frame_with_unit_id(0x01, pdu)
+    }
+
+    fn mbap_frame_with_unit_id(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+        // transaction id 0, protocol id 0, length including the unit id
+        let length = (pdu.len() + 1) as u16;
+        let mut frame = vec![0x00, 0x00, 0x00, 0x00];
+        frame.extend_from_slice(&length.to_be_bytes());
+        frame.push(unit_id);
+        frame.extend_from_slice(pdu);
+        frame
+    }
+
+    fn rtu_frame(pdu: &[u8]) -> Vec<u8> {
+        rtu_frame_with_unit_id(0x01, pdu)
+    }
+
+    fn rtu_frame_with_unit_id(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+        let mut frame = vec![unit_id];
+        frame.extend_from_slice(pdu);
+        let crc = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS).checksum(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    #[tokio::test]
+    async fn discards_responses_from_another_unit_id_unless_accepted() {
+        // read of one holding register with the value 42
+        const PDU: &[u8] = &[0x03, 0x02, 0x00, 0x2A];
+
+        // frames a PDU with a transaction id and unit id
+        type FrameFn = fn(u16, u8, &[u8]) -> Vec<u8>;
+
+        let cases: [(Framing, FrameFn); 2] = [
+            (
+                || (FrameWriter::tcp(), FramedReader::tcp()),
+                |tx_id, unit_id, pdu| {
+                    let mut frame = mbap_frame_with_unit_id(unit_id, pdu);
+                    frame[..2].copy_from_slice(&tx_id.to_be_bytes());
+                    frame
+                },
+            ),
+            (
+                || (FrameWriter::rtu(), FramedReader::rtu_response()),
+                |_, unit_id, pdu| rtu_frame_with_unit_id(unit_id, pdu),
+            ),
+        ];
+
+        for (framing, frame) in cases {
+            let (writer, reader) = framing();
+            let (tx, rx) = crate::channel::channel(16);
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let mut client_loop = ClientLoop::new(rx, writer, reader, DecodeLevel::nothing());
+            client_loop.enabled = true;
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                client_loop.run(&mut phys).await
+            });
+            let mut channel = Channel::new(tx);
+            let read = |channel: &Channel| {
+                let mut channel = channel.clone();
+                tokio::spawn(async move {
+                    channel
+                        .read_holding_registers(
+                            RequestParam::new(UnitId::new(5), Duration::from_secs(1)),
+                            AddressRange::try_from(0, 1).unwrap(),
+                        )
+                        .await
+                })
+            };
+
+            // the response from unit 0 is discarded and the channel keeps waiting
+            let request = read(&channel);
+            assert!(matches!(io.next_event().await, Event::Write(_)));
+            io.read(&frame(0, 0, PDU));
+            assert_eq!(io.next_event().await, Event::Read);
+            io.read(&frame(0, 5, PDU));
+            assert_eq!(io.next_event().await, Event::Read);
+            assert_eq!(request.await.unwrap(), Ok(vec![Indexed::new(0, 42)]));
+            assert_eq!(channel.statistics().await.unwrap().mismatched_unit_ids, 1);
+
+            channel.set_accept_mismatched_unit_id(true).await.unwrap();
+            let request = read(&channel);
+            assert!(matches!(io.next_event().await, Event::Write(_)));
+            io.read(&frame(1, 0, PDU));
+            assert_eq!(io.next_event().await, Event::Read);
+            assert_eq!(request.await.unwrap(), Ok(vec![Indexed::new(0, 42)]));
+            assert_eq!(channel.statistics().await.unwrap().mismatched_unit_ids, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_read_responses_that_do_not_match_the_requested_quantity() {
+        let registers = AddressRange::try_from(0, 1).unwrap();
+        let coils = AddressRange::try_from(0, 10).unwrap();
+
+        let tcp: (Framing, FrameFn) = (|| (FrameWriter::tcp(), FramedReader::tcp()), mbap_frame);
+        let rtu: (Framing, FrameFn) = (
+            || (FrameWriter::rtu(), FramedReader::rtu_response()),
+            rtu_frame,
+        );
+
+        let cases: &[(_, FunctionCode, AddressRange, &[u8], AduParseError)] = &[
+            // too many registers
+            (
+                tcp,
+                FunctionCode::ReadHoldingRegisters,
+                registers,
+                &[0x03, 0x04, 0x00, 0x01, 0x00, 0x02],
+                AduParseError::ByteCountMismatch(4, 2),
+            ),
+            (
+                rtu,
+                FunctionCode::ReadHoldingRegisters,
+                registers,
+                &[0x03, 0x04, 0x00, 0x01, 0x00, 0x02],
+                AduParseError::ByteCountMismatch(4, 2),
+            ),
+            // too few registers
+            (
+                rtu,
+                FunctionCode::ReadHoldingRegisters,
+                registers,
+                &[0x03, 0x00],
+                AduParseError::ByteCountMismatch(0, 2),
+            ),
+            // byte count is correct, but the payload is truncated
+            (
+                tcp,
+                FunctionCode::ReadHoldingRegisters,
+                registers,
+                &[0x03, 0x02, 0x00],
+                AduParseError::InsufficientBytesForByteCount(2, 1),
+            ),
+            // byte count is correct, but the payload is too long
+            (
+                tcp,
+                FunctionCode::ReadHoldingRegisters,
+                registers,
+                &[0x03, 0x02, 0x00, 0x01, 0xFF],
+                AduParseError::TrailingBytes(1),
+            ),
+            // 10 coils require 2 bytes
+            (
+                tcp,
+                FunctionCode::ReadCoils,
+                coils,
+                &[0x01, 0x03, 0xFF, 0x03, 0x00],
+                AduParseError::ByteCountMismatch(3, 2),
+            ),
+            (
+                rtu,
+                FunctionCode::ReadCoils,
+                coils,
+                &[0x01, 0x01, 0xFF],
+                AduParseError::ByteCountMismatch(1, 2),
+            ),
+        ];
+
+        for ((framing, frame), function, range, pdu, expected) in cases.iter().copied() {
+            let (writer, reader) = framing();
+            let (tx, rx) = crate::channel::channel(16);
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let mut client_loop = ClientLoop::new(rx, writer, reader, DecodeLevel::nothing());
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                client_loop.run(&mut phys).await
+            });
+            let mut channel = Channel::new(tx);
+
+            let request = tokio::spawn(async move {
+                let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+                match function {
+                    FunctionCode::ReadCoils => channel.read_coils(param, range).await.map(|_| ()),
+                    _ => channel
+                        .read_holding_registers(param, range)
+                        .await
+                        .map(|_| ()),
+                }
+            });
+            assert!(matches!(io.next_event().await, Event::Write(_)));
+            io.read(&frame(pdu));
+            assert_eq!(
+                request.await.unwrap(),
+                Err(RequestError::BadResponse(expected)),
+                "{function} response: {pdu:02X?}"
+            );
+        }
+    }
+
+    type Records = std::sync::Arc<std::sync::Mutex<Vec<(Direction, Vec<u8>)>>>;
+
+    /// sink that stores every record
+    struct RecordingSink(Records);
+
+    impl FrameSink for RecordingSink {
+        fn record(&mut self, _timestamp: std::time::SystemTime, direction: Direction, data: &[u8]) {
+            self.0.lock().unwrap().push((direction, data.to_vec()));
+        }
+    }
+
+    #[tokio::test]
+    async fn captures_bytes_written_and_read() {
+        let records = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let capture = crate::capture::new_capture(Box::new(RecordingSink(records.clone())));
+        let (mut channel, _task, mut io) = spawn_client_loop_with(Duration::ZERO, Some(capture));
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let request = get_framed_adu(FunctionCode::ReadCoils, &range);
+        let response = get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        );
+
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                    range,
+                )
+                .await
+        });
+
+        assert_eq!(io.next_event().await, Event::Write(request.clone()));
+        io.read(&response);
+        assert!(coils.await.unwrap().is_ok());
+
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![(Direction::Tx, request), (Direction::Rx, response)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_request_when_write_times_out() {
+        let (mut channel, task, _io) = spawn_client_loop_with_write_delay(Duration::from_secs(2));
+
+        let result = channel
+            .read_coils(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(5))
+                    .with_write_timeout(Some(Duration::from_secs(1))),
+                AddressRange::try_from(7, 2).unwrap(),
+            )
+            .await;
+
+        assert_eq!(result, Err(RequestError::Io(ErrorKind::TimedOut)));
+        assert_eq!(
+            task.await.unwrap(),
+            SessionError::IoError(ErrorKind::TimedOut)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn default_timeout_is_selected_by_function_code() {
+        let table = crate::client::TimeoutTable::new(Duration::from_secs(1))
+            .with(FunctionCode::ReadDiscreteInputs, Duration::from_secs(3));
+        let range = AddressRange::try_from(7, 2).unwrap();
+
+        // read coils uses the default of the table and expires
+        let (mut channel, _task, mut io) = spawn_client_loop();
+        channel.enable_nowait().await.unwrap();
+        channel.set_timeout_table(table.clone()).await.unwrap();
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(RequestParam::default_timeout(UnitId::new(1)), range)
+                .await
+        });
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
+        );
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        assert_eq!(coils.await.unwrap(), Err(RequestError::ResponseTimeout));
+
+        // read discrete inputs uses the override and is still waiting
+        let (mut channel, _task, mut io) = spawn_client_loop();
+        channel.enable_nowait().await.unwrap();
+        channel.set_timeout_table(table).await.unwrap();
+        let inputs = tokio::spawn(async move {
+            channel
+                .read_discrete_inputs(RequestParam::default_timeout(UnitId::new(1)), range)
+                .await
+        });
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadDiscreteInputs, &range))
+        );
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        io.read(&get_framed_adu(
+            FunctionCode::ReadDiscreteInputs,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        ));
+        assert_eq!(
+            inputs.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, true)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn explicit_timeout_overrides_timeout_table() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+        channel.enable_nowait().await.unwrap();
+        channel
+            .set_timeout_table(crate::client::TimeoutTable::new(Duration::from_millis(100)))
+            .await
+            .unwrap();
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                    range,
+                )
+                .await
+        });
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
+        );
+        tokio::time::advance(Duration::from_millis(500)).await;
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+        ));
+        assert_eq!(
+            coils.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, true)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_read_after_response_timeout() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1))
+                        .with_retries(2, RetryOn::Timeout),
+                    range,
+                )
+                .await
+        });
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
+        );
+        // the request is sent again right away once the first attempt times out
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &range,
+                TxId::new(1)
+            ))
+        );
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+
+        assert_eq!(
+            coils.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, true)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_attempts_when_retries_are_exhausted() {
+        let (mut channel, _task, _io) = spawn_client_loop();
+
+        let result = channel
+            .read_coils(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1))
+                    .with_retries(2, RetryOn::Timeout),
+                AddressRange::try_from(7, 2).unwrap(),
+            )
+            .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(
+            err,
+            RequestError::RetriesExhausted(RetriesExhausted {
+                attempts: 3,
+                cause: RetryCause::ResponseTimeout,
+            })
+        );
+        assert!(err.is_timeout());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_retry_writes_unless_enabled() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+
+        let result = channel
+            .write_single_register(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1))
+                    .with_retries(2, RetryOn::Timeout),
+                Indexed::new(1, 0xCAFE),
+            )
+            .await;
+
+        assert_eq!(result, Err(RequestError::ResponseTimeout));
+        assert!(matches!(io.pop_event(), Some(Event::Write(_))));
+        assert_eq!(io.pop_event(), None);
+    }
+
+    #[tokio::test]
+    async fn retries_read_after_bad_frame_in_next_session() {
+        let (tx, rx) = crate::channel::channel(16);
+        let mut channel = Channel::new(tx);
+        let mut client_loop = ClientLoop::new(
+            rx,
+            FrameWriter::tcp(),
+            FramedReader::tcp(),
+            DecodeLevel::default(),
+        );
+        let (first, mut first_io) = sfio_tokio_mock_io::mock();
+        let (second, mut second_io) = sfio_tokio_mock_io::mock();
+        let task = tokio::spawn(async move {
+            let first = client_loop.run(&mut PhysLayer::new_mock(first)).await;
+            let second = client_loop.run(&mut PhysLayer::new_mock(second)).await;
+            (first, second)
+        });
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(5))
+                        .with_retries(1, RetryOn::TimeoutOrBadFrame),
+                    range,
+                )
+                .await
+        });
+
+        assert_eq!(
+            first_io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
+        );
+        // unknown protocol id
+        first_io.read(&[0x00, 0x00, 0xCA, 0xFE, 0x00, 0x02, 0x01, 0x01]);
+
+        // the request is the first one sent in the next session
+        assert_eq!(
+            second_io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &range,
+                TxId::new(1)
+            ))
+        );
+        second_io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+
+        assert_eq!(
+            coils.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, true)]
+        );
+        // the channel was dropped with the request task
+        assert_eq!(
+            task.await.unwrap(),
+            (SessionError::BadFrame, SessionError::Shutdown)
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_high_priority_requests_before_queued_requests() {
+        let (channel, _task, mut io) = spawn_client_loop();
+
+        let read = |range: AddressRange, param: RequestParam| {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils(param, range).await })
+        };
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let in_flight = AddressRange::try_from(0, 1).unwrap();
+        let normal = AddressRange::try_from(1, 1).unwrap();
+        let high = AddressRange::try_from(2, 1).unwrap();
+
+        let first = read(in_flight, param);
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &in_flight))
+        );
+
+        // queued while the first transaction is in progress
+        let second = read(normal, param);
+        tokio::task::yield_now().await;
+        let third = read(high, param.high_priority());
+        tokio::task::yield_now().await;
+
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: in_flight }, |_| Ok(true)),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(first.await.unwrap().is_ok());
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &high,
+                TxId::new(1)
+            ))
+        );
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: high }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(third.await.unwrap().is_ok());
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &normal,
+                TxId::new(2)
+            ))
+        );
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: normal }, |_| Ok(true)),
+            TxId::new(2),
+        ));
+        assert!(second.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn reports_snapshot_failures_per_block() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let coils = AddressRange::try_from(0, 2).unwrap();
+        let registers = AddressRange::try_from(5, 1).unwrap();
+        let spec = SnapshotSpec {
+            coils: Some(coils),
+            holding_registers: Some(registers),
+            ..Default::default()
+        };
+
+        let snapshot = tokio::spawn(async move {
+            channel
+                .read_snapshot(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(5)),
+                    spec,
+                )
+                .await
+        });
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &coils))
+        );
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: coils }, |_| Ok(true)),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadHoldingRegisters,
+                &registers,
+                TxId::new(1)
+            ))
+        );
+        // illegal data address exception with transaction id 1
+        io.read(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x02]);
+
+        let snapshot = snapshot.await.unwrap();
+        assert_eq!(
+            snapshot.coils,
+            Some(Ok(vec![Indexed::new(0, true), Indexed::new(1, true)]))
+        );
+        assert_eq!(snapshot.discrete_inputs, None);
+        assert_eq!(
+            snapshot.holding_registers,
+            Some(Err(RequestError::Exception(
+                ExceptionCode::IllegalDataAddress
+            )))
+        );
+        assert_eq!(snapshot.input_registers, None);
+    }
+
+    #[tokio::test]
+    async fn purges_queued_requests_but_completes_the_request_in_progress() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let read = |range| {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils(param, range).await })
+        };
+        let in_flight = AddressRange::try_from(0, 1).unwrap();
+
+        let first = read(in_flight);
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &in_flight))
+        );
+        let queued: Vec<_> = (1..4)
+            .map(|x| read(AddressRange::try_from(x, 1).unwrap()))
+            .collect();
+        tokio::task::yield_now().await;
+
+        let purge = {
+            let channel = channel.clone();
+            tokio::spawn(async move { channel.purge_queue().await })
+        };
+        tokio::task::yield_now().await;
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: in_flight }, |_| Ok(true)),
+        ));
+
+        assert!(first.await.unwrap().is_ok());
+        assert_eq!(purge.await.unwrap(), Ok(3));
+        for request in queued {
+            assert_eq!(request.await.unwrap(), Err(RequestError::Cancelled));
+        }
+        assert_eq!(channel.purge_queue().await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_are_not_sent_and_stop_waiting_for_the_response() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let token = crate::client::CancellationToken::new();
+        let cancellable = channel.with_cancellation(token.clone());
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let read = |channel: &Channel, range| {
+            let mut channel = channel.clone();
+            tokio::spawn(async move { channel.read_coils(param, range).await })
+        };
+        let first = AddressRange::try_from(0, 1).unwrap();
+        let second = AddressRange::try_from(1, 1).unwrap();
+        let other = AddressRange::try_from(2, 1).unwrap();
+
+        let in_flight = read(&cancellable, first);
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &first))
+        );
+        let queued = read(&cancellable, second);
+        tokio::task::yield_now().await;
+        let unaffected = read(&channel, other);
+        tokio::task::yield_now().await;
+
+        token.cancel();
+        assert_eq!(in_flight.await.unwrap(), Err(RequestError::Cancelled));
+        assert_eq!(queued.await.unwrap(), Err(RequestError::Cancelled));
+
+        // the queued request was never sent
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &other,
+                TxId::new(1)
+            ))
+        );
+        // the late response to the cancelled request is discarded as stale
+        io.read(&get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: first }, |_| Ok(true)),
+        ));
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: other }, |_| Ok(true)),
+            TxId::new(1),
+        ));
+        assert_eq!(unaffected.await.unwrap(), Ok(vec![Indexed::new(2, true)]));
+        assert_eq!(channel.statistics().await.unwrap().stale_responses, 1);
+    }
+
+    #[tokio::test]
+    async fn rtu_channels_consume_the_late_response_of_a_cancelled_request() {
+        let (tx, rx) = crate::channel::channel(16);
+        let (mock, mut io) = sfio_tokio_mock_io::mock();
+        let mut client_loop = ClientLoop::new(
+            rx,
+            FrameWriter::rtu(),
+            FramedReader::rtu_response(),
+            DecodeLevel::nothing(),
+        );
+        let _task = tokio::spawn(async move {
+            let mut phys = PhysLayer::new_mock(mock);
+            client_loop.run(&mut phys).await
+        });
+        let channel = Channel::new(tx);
+        channel.enable_nowait().await.unwrap();
+        let token = crate::client::CancellationToken::new();
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let read = |mut channel: Channel, address: u16| {
+            tokio::spawn(async move {
+                channel
+                    .read_coils(param, AddressRange::try_from(address, 1).unwrap())
+                    .await
+            })
+        };
+
+        let cancelled = read(channel.with_cancellation(token.clone()), 0);
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+        let next = read(channel.clone(), 1);
+        tokio::task::yield_now().await;
+        token.cancel();
+        assert_eq!(cancelled.await.unwrap(), Err(RequestError::Cancelled));
+
+        // the next request is only sent once the late response has been consumed
+        io.read(&rtu_frame(&[0x01, 0x01, 0x00]));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+        io.read(&rtu_frame(&[0x01, 0x01, 0x01]));
+        assert_eq!(next.await.unwrap(), Ok(vec![Indexed::new(1, true)]));
+    }
+
+    #[tokio::test]
+    async fn returns_shutdown_when_task_dropped() {
+        let (mut channel, task, mut io) = spawn_client_loop();
+
+        // the expected request
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let request = get_framed_adu(FunctionCode::ReadCoils, &range);
+
+        // spawn a task that will perform the read coils
+        let request_task = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(5)),
+                    range,
+                )
+                .await
+        });
+        // wait until the task writes the request so that we know it's in the correct state
+        assert_eq!(io.next_event().await, Event::Write(request));
+
+        // now drop the task
+        task.abort();
+        assert!(task.await.is_err());
+
+        // the promise will get dropped causing the request to fail with Shutdown
+        let res = request_task.await.unwrap();
+        assert_eq!(res, Err(RequestError::Shutdown));
+    }
+
+    #[tokio::test]
+    async fn channel_is_closed_when_task_terminates() {
+        let (channel, task, _io) = spawn_client_loop();
+        assert!(!channel.is_closed());
+
+        task.abort();
+        assert!(task.await.is_err());
+        assert!(channel.is_closed());
+        assert!(channel.clone().is_closed());
+    }
+
+    #[test]
+    fn channel_is_send_sync_and_clone() {
+        fn assert_traits<T: Send + Sync + Clone + 'static>() {}
+        assert_traits::<Channel>();
+    }
+
+    #[cfg(feature = "serial")]
+    #[tokio::test]
+    async fn rejects_serial_settings_on_tcp_channels() {
+        let (mut channel, _task, _io) = spawn_client_loop();
+        channel.enable_nowait().await.unwrap();
+
+        let result = channel
+            .change_serial_settings(crate::serial::SerialSettings::default())
+            .await;
+        assert_eq!(result, Err(crate::client::SerialSettingsError::NotSerial));
+    }
+
+    #[tokio::test]
+    async fn counts_stale_responses_and_aborts_the_session_when_configured() {
+        let (mut channel, task, mut io) = spawn_client_loop();
+        // settings received while disabled end the session
+        channel.enable_nowait().await.unwrap();
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let response = |tx_id| {
+            get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+                TxId::new(tx_id),
+            )
+        };
+        let read_coils = |mut channel: Channel| {
+            tokio::spawn(async move {
+                channel
+                    .read_coils(
+                        RequestParam::new(UnitId::new(1), Duration::from_secs(5)),
+                        range,
+                    )
+                    .await
+            })
+        };
+
+        // a late response to an earlier transaction is discarded
+        let coils = read_coils(channel.clone());
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu(FunctionCode::ReadCoils, &range))
+        );
+        io.read(&[response(0xFF), response(0)].concat());
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(coils.await.unwrap().is_ok());
+        assert_eq!(channel.statistics().await.unwrap().stale_responses, 1);
+
+        channel
+            .set_stale_response_policy(StaleResponsePolicy::AbortSession(
+                std::num::NonZeroUsize::new(2).unwrap(),
+            ))
+            .await
+            .unwrap();
+        let coils = read_coils(channel.clone());
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(get_framed_adu_with_tx_id(
+                FunctionCode::ReadCoils,
+                &range,
+                TxId::new(1)
+            ))
+        );
+        io.read(&[response(0), response(0)].concat());
+        assert_eq!(io.next_event().await, Event::Read);
+        assert_eq!(
+            coils.await.unwrap(),
+            Err(RequestError::BadFrame(FrameParseError::StaleResponses(2)))
+        );
+        assert_eq!(task.await.unwrap(), SessionError::BadFrame);
+    }
+
+    #[tokio::test]
+    async fn framing_errors_kill_the_session_while_idle() {
+        let (_channel, task, mut io) = spawn_client_loop();
+
+        io.read(&[0x00, 0x00, 0xCA, 0xFE, 0x00, 0x01, 0x01]); // non-Modbus protocol id
+
+        assert_eq!(task.await.unwrap(), SessionError::BadFrame);
+    }
+
+    #[tokio::test]
+    async fn transmit_read_coils_when_requested() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+
+        let range = AddressRange::try_from(7, 2).unwrap();
+        let request = get_framed_adu(FunctionCode::ReadCoils, &range);
+        let response = get_framed_adu(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |idx| match idx {
+                7 => Ok(true),
+                8 => Ok(false),
+                _ => Err(ExceptionCode::IllegalDataAddress),
+            }),
+        );
+
+        let coils = tokio::spawn(async move {
+            channel
+                .read_coils(
+                    RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                    range,
+                )
+                .await
+        });
+
+        assert_eq!(io.next_event().await, Event::Write(request));
+        io.read(&response);
+
+        assert_eq!(
+            coils.await.unwrap().unwrap(),
+            vec![Indexed::new(7, true), Indexed::new(8, false)]
+        );
+    }
+
+    struct TransactionSender(tokio::sync::mpsc::UnboundedSender<TransactionOutcome>);
+
+    impl Listener<TransactionOutcome> for TransactionSender {
+        fn update(&mut self, value: TransactionOutcome) -> crate::MaybeAsync<()> {
+            let _ = self.0.send(value);
+            crate::MaybeAsync::ready(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_transaction_outcomes_and_counts_them() {
+        let (mut channel, _task, mut io) = spawn_client_loop();
+        let (tx, mut outcomes) = tokio::sync::mpsc::unbounded_channel();
+        channel.enable_nowait().await.unwrap();
+        channel
+            .set_transaction_listener(Some(Box::new(TransactionSender(tx))))
+            .await
+            .unwrap();
+        let range = AddressRange::try_from(0, 1).unwrap();
+        let outcome = |outcome| TransactionOutcome {
+            unit_id: UnitId::new(1),
+            function: FunctionCode::ReadCoils,
+            outcome,
+        };
+
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+        let mut reader = channel.clone();
+        let read = tokio::spawn(async move { reader.read_coils(param, range).await });
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+        io.read(&mbap_frame(&[0x81, 0x02]));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert_eq!(
+            read.await.unwrap(),
+            Err(RequestError::Exception(ExceptionCode::IllegalDataAddress))
+        );
+        assert_eq!(
+            outcomes.recv().await,
+            Some(outcome(Outcome::Exception(
+                ExceptionCode::IllegalDataAddress
+            )))
+        );
+
+        let short = RequestParam::new(UnitId::new(1), Duration::from_millis(10));
+        let mut reader = channel.clone();
+        let read = tokio::spawn(async move { reader.read_coils(short, range).await });
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+        assert_eq!(read.await.unwrap(), Err(RequestError::ResponseTimeout));
+        assert_eq!(outcomes.recv().await, Some(outcome(Outcome::Timeout)));
+
+        let mut reader = channel.clone();
+        let read = tokio::spawn(async move { reader.read_coils(param, range).await });
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+        io.read(&get_framed_adu_with_tx_id(
+            FunctionCode::ReadCoils,
+            &BitWriter::new(ReadBitsRange { inner: range }, |_| Ok(true)),
+            TxId::new(2),
+        ));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert_eq!(read.await.unwrap(), Ok(vec![Indexed::new(0, true)]));
+        assert_eq!(outcomes.recv().await, Some(outcome(Outcome::Ok)));
+
+        let statistics = channel.statistics().await.unwrap();
+        assert_eq!(
+            (
+                statistics.successes,
+                statistics.exceptions,
+                statistics.timeouts
+            ),
+            (1, 1, 1)
+        );
+    }
+
+    fn mbap_frame_with_tx_id(tx_id: u16, pdu: &[u8]) -> Vec<u8> {
+        let mut frame = mbap_frame(pdu);
+        frame[0..2].copy_from_slice(&tx_id.to_be_bytes());
+        frame
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn verified_writes_fail_when_the_server_lies_on_readback() {
+        let (channel, _task, mut io) = spawn_client_loop();
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+
+        // the register is acknowledged, but it reads back with another value
+        let mut writer = channel.clone();
+        let write = tokio::spawn(async move {
+            writer
+                .write_single_register_verified(param, Indexed::new(7, 0x1234))
+                .await
+        });
+        let request = [0x06, 0x00, 0x07, 0x12, 0x34];
+        assert_eq!(io.next_event().await, Event::Write(mbap_frame(&request)));
+        io.read(&mbap_frame(&request));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert_eq!(
+            io.next_event().await,
+            Event::Write(mbap_frame_with_tx_id(1, &[0x03, 0x00, 0x07, 0x00, 0x01]))
+        );
+        io.read(&mbap_frame_with_tx_id(1, &[0x03, 0x02, 0x12, 0x00]));
+        assert_eq!(io.next_event().await, Event::Read);
+        assert_eq!(
+            write.await.unwrap(),
+            Err(RequestError::VerifyFailed(VerifyError {
+                index: 7,
+                expected: 0x1234,
+                actual: 0x1200,
+            }))
+        );
+
+        // the coils are read back after the delay, first with the written values then without
+        let param = param.with_verify_delay(Some(Duration::from_millis(100)));
+        let values = vec![true, false, true];
+        for (tx_id, readback, result) in [
+            (2, 0b101, Ok(AddressRange::try_from(0, 3).unwrap())),
+            (
+                4,
+                0b001,
+                Err(RequestError::VerifyFailed(VerifyError {
+                    index: 2,
+                    expected: 1,
+                    actual: 0,
+                })),
+            ),
+        ] {
+            let mut writer = channel.clone();
+            let request = crate::client::WriteMultiple::from(0, values.clone()).unwrap();
+            let write =
+                tokio::spawn(
+                    async move { writer.write_multiple_coils_verified(param, request).await },
+                );
+            assert!(matches!(io.next_event().await, Event::Write(_)));
+            io.read(&mbap_frame_with_tx_id(
+                tx_id,
+                &[0x0F, 0x00, 0x00, 0x00, 0x03],
+            ));
+            assert_eq!(io.next_event().await, Event::Read);
+            let start = tokio::time::Instant::now();
+            assert_eq!(
+                io.next_event().await,
+                Event::Write(mbap_frame_with_tx_id(
+                    tx_id + 1,
+                    &[0x01, 0x00, 0x00, 0x00, 0x03]
+                ))
+            );
+            assert!(start.elapsed() >= Duration::from_millis(100));
+            io.read(&mbap_frame_with_tx_id(tx_id + 1, &[0x01, 0x01, readback]));
+            assert_eq!(io.next_event().await, Event::Read);
+            assert_eq!(write.await.unwrap(), result);
+        }
     }
 }
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::FunctionCode;
+
+/// Response timeout used by [`TimeoutTable::default`]
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Response timeouts of a channel selected by function code
+///
+/// The table is only consulted for requests made with [`RequestParam::default_timeout`](crate::client::RequestParam::default_timeout),
+/// explicit per-request timeouts always take precedence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeoutTable {
+    default: Duration,
+    overrides: BTreeMap<FunctionCode, Duration>,
+}
+
+impl Default for TimeoutTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESPONSE_TIMEOUT)
+    }
+}
+
+impl TimeoutTable {
+    /// Create a table that uses the same timeout for every function code
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Use a different timeout for the specified function code
+    pub fn with(mut self, function: FunctionCode, timeout: Duration) -> Self {
+        self.overrides.insert(function, timeout);
+        self
+    }
+
+    /// Timeout that applies to the specified function code
+    pub fn get(&self, function: FunctionCode) -> Duration {
+        self.overrides
+            .get(&function)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_take_precedence_over_the_default() {
+        let table = TimeoutTable::new(Duration::from_millis(20))
+            .with(FunctionCode::WriteMultipleRegisters, Duration::from_secs(3));
+
+        assert_eq!(
+            table.get(FunctionCode::ReadHoldingRegisters),
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            table.get(FunctionCode::WriteMultipleRegisters),
+            Duration::from_secs(3)
+        );
+    }
+}
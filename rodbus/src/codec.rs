@@ -0,0 +1,328 @@
+use crate::client::requests::read_bits::ReadBits;
+use crate::client::requests::read_registers::ReadRegisters;
+use crate::client::requests::write_multiple::parse_range_echo;
+use crate::client::requests::write_single::parse_echo;
+use crate::client::{FrameFormat, WriteMultiple};
+use crate::common::buffer::ReadBuffer;
+use crate::common::frame::constants::MAX_FRAME_LENGTH;
+use crate::common::frame::{FrameHeader, FrameParser, FunctionField, TxId};
+use crate::common::traits::Serialize;
+use crate::decode::DecodeLevel;
+use crate::error::{AduParseError, FrameParseError, RequestError};
+use crate::rtu::frame::RtuParser;
+use crate::tcp::frame::MbapParser;
+use crate::types::{AddressRange, Indexed, UnitId};
+use crate::FunctionCode;
+
+use scursor::{ReadCursor, WriteCursor};
+
+/// Header fields of a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Unit id of the request or response
+    pub unit_id: UnitId,
+    /// Transaction id of an MBAP frame, always `None` for RTU frames
+    ///
+    /// `None` is encoded as zero in MBAP frames
+    pub tx_id: Option<u16>,
+}
+
+impl Header {
+    /// Create a header with the specified unit id and no transaction id
+    pub fn new(unit_id: UnitId) -> Self {
+        Self {
+            unit_id,
+            tx_id: None,
+        }
+    }
+
+    /// Set the transaction id used in MBAP frames
+    pub fn with_tx_id(mut self, tx_id: u16) -> Self {
+        self.tx_id = Some(tx_id);
+        self
+    }
+}
+
+/// Request that can be encoded
+#[derive(Copy, Clone, Debug)]
+pub enum Request<'a> {
+    /// Read coils
+    ReadCoils(AddressRange),
+    /// Read discrete inputs
+    ReadDiscreteInputs(AddressRange),
+    /// Read holding registers
+    ReadHoldingRegisters(AddressRange),
+    /// Read input registers
+    ReadInputRegisters(AddressRange),
+    /// Write a single coil
+    WriteSingleCoil(Indexed<bool>),
+    /// Write a single register
+    WriteSingleRegister(Indexed<u16>),
+    /// Write multiple contiguous coils
+    WriteMultipleCoils(&'a WriteMultiple<bool>),
+    /// Write multiple contiguous registers
+    WriteMultipleRegisters(&'a WriteMultiple<u16>),
+}
+
+impl Request<'_> {
+    /// Function code of the request
+    pub fn function(&self) -> FunctionCode {
+        match self {
+            Request::ReadCoils(_) => FunctionCode::ReadCoils,
+            Request::ReadDiscreteInputs(_) => FunctionCode::ReadDiscreteInputs,
+            Request::ReadHoldingRegisters(_) => FunctionCode::ReadHoldingRegisters,
+            Request::ReadInputRegisters(_) => FunctionCode::ReadInputRegisters,
+            Request::WriteSingleCoil(_) => FunctionCode::WriteSingleCoil,
+            Request::WriteSingleRegister(_) => FunctionCode::WriteSingleRegister,
+            Request::WriteMultipleCoils(_) => FunctionCode::WriteMultipleCoils,
+            Request::WriteMultipleRegisters(_) => FunctionCode::WriteMultipleRegisters,
+        }
+    }
+
+    fn validate(&self) -> Result<(), RequestError> {
+        match self {
+            Request::ReadCoils(x) | Request::ReadDiscreteInputs(x) => {
+                x.of_read_bits()?;
+            }
+            Request::ReadHoldingRegisters(x) | Request::ReadInputRegisters(x) => {
+                x.of_read_registers()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Request<'_> {
+    fn serialize(&self, cursor: &mut WriteCursor) -> Result<(), RequestError> {
+        match self {
+            Request::ReadCoils(x)
+            | Request::ReadDiscreteInputs(x)
+            | Request::ReadHoldingRegisters(x)
+            | Request::ReadInputRegisters(x) => x.serialize(cursor),
+            Request::WriteSingleCoil(x) => x.serialize(cursor),
+            Request::WriteSingleRegister(x) => x.serialize(cursor),
+            Request::WriteMultipleCoils(x) => x.serialize(cursor),
+            Request::WriteMultipleRegisters(x) => x.serialize(cursor),
+        }
+    }
+}
+
+/// Decoded response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// Values of the coils or discrete inputs that were read
+    Bits(Vec<Indexed<bool>>),
+    /// Values of the holding or input registers that were read
+    Registers(Vec<Indexed<u16>>),
+    /// Coil that was written
+    SingleCoil(Indexed<bool>),
+    /// Register that was written
+    SingleRegister(Indexed<u16>),
+    /// Range of coils or registers that were written
+    MultipleWrite(AddressRange),
+}
+
+/// Encode a request frame into `buffer`, returning the number of bytes written
+///
+/// A buffer of 260 bytes, the maximum size of an MBAP frame, is always large enough.
+pub fn encode_request(
+    buffer: &mut [u8],
+    format: FrameFormat,
+    header: Header,
+    request: Request,
+) -> Result<usize, RequestError> {
+    request.validate()?;
+    let mut cursor = WriteCursor::new(buffer);
+    let function = FunctionField::Valid(request.function());
+    // the RTU formatter ignores the transaction id, the same as in the client task
+    let header = FrameHeader::new_tcp_header(header.unit_id, TxId::new(header.tx_id.unwrap_or(0)));
+    match format {
+        FrameFormat::Mbap => {
+            crate::tcp::frame::format_mbap(&mut cursor, header, function, &request)?;
+        }
+        FrameFormat::Rtu => {
+            crate::rtu::frame::format_rtu_pdu(&mut cursor, header, function, &request)?;
+        }
+    }
+    Ok(cursor.position())
+}
+
+/// Decode a complete response frame to `request`
+///
+/// Modbus exceptions are returned as [`RequestError::Exception`]
+pub fn decode_response(
+    frame: &[u8],
+    format: FrameFormat,
+    request: Request,
+) -> Result<(Header, Response), RequestError> {
+    let mut parser = match format {
+        FrameFormat::Mbap => FrameParser::Tcp(MbapParser::new()),
+        FrameFormat::Rtu => FrameParser::Rtu(RtuParser::new_response_parser()),
+    };
+
+    let mut buffer = ReadBuffer::new();
+    if buffer.extend(frame) < frame.len() {
+        return Err(FrameParseError::FrameLengthTooBig(frame.len(), MAX_FRAME_LENGTH).into());
+    }
+
+    let parsed = match parser.parse(&mut buffer, DecodeLevel::nothing())? {
+        Some(x) => x,
+        None => return Err(AduParseError::InsufficientBytes.into()),
+    };
+    if !buffer.is_empty() {
+        return Err(AduParseError::TrailingBytes(buffer.len()).into());
+    }
+
+    let header = Header {
+        unit_id: parsed.header.destination.into_unit_id(),
+        tx_id: parsed.header.tx_id.map(|x| x.to_u16()),
+    };
+
+    let mut cursor = ReadCursor::new(parsed.payload());
+    crate::client::requests::parse_function(&mut cursor, request.function())?;
+
+    let response = match request {
+        Request::ReadCoils(range) | Request::ReadDiscreteInputs(range) => {
+            Response::Bits(ReadBits::parse_bits_response(range, &mut cursor)?.collect())
+        }
+        Request::ReadHoldingRegisters(range) | Request::ReadInputRegisters(range) => {
+            Response::Registers(
+                ReadRegisters::parse_registers_response(range, &mut cursor)?.collect(),
+            )
+        }
+        Request::WriteSingleCoil(x) => Response::SingleCoil(parse_echo(&x, cursor)?),
+        Request::WriteSingleRegister(x) => Response::SingleRegister(parse_echo(&x, cursor)?),
+        Request::WriteMultipleCoils(x) => {
+            Response::MultipleWrite(parse_range_echo(x.range, cursor)?)
+        }
+        Request::WriteMultipleRegisters(x) => {
+            Response::MultipleWrite(parse_range_echo(x.range, cursor)?)
+        }
+    };
+
+    Ok((header, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExceptionCode;
+
+    fn encode(format: FrameFormat, header: Header, request: Request) -> Vec<u8> {
+        let mut buffer = [0; 256];
+        let count = encode_request(&mut buffer, format, header, request).unwrap();
+        buffer[..count].to_vec()
+    }
+
+    #[test]
+    fn encodes_mbap_request() {
+        let header = Header::new(UnitId::new(1)).with_tx_id(7);
+        let range = AddressRange::try_from(2, 3).unwrap();
+        assert_eq!(
+            encode(
+                FrameFormat::Mbap,
+                header,
+                Request::ReadHoldingRegisters(range)
+            ),
+            [0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x02, 0x00, 0x03]
+        );
+    }
+
+    #[test]
+    fn encodes_rtu_request_with_crc() {
+        let header = Header::new(UnitId::new(1));
+        let range = AddressRange::try_from(0, 10).unwrap();
+        assert_eq!(
+            encode(FrameFormat::Rtu, header, Request::ReadCoils(range)),
+            [0x01, 0x01, 0x00, 0x00, 0x00, 0x0A, 0xBC, 0x0D]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_request() {
+        let range = AddressRange::try_from(0, 126).unwrap();
+        let mut buffer = [0; 256];
+        assert_eq!(
+            encode_request(
+                &mut buffer,
+                FrameFormat::Mbap,
+                Header::new(UnitId::new(1)),
+                Request::ReadInputRegisters(range)
+            ),
+            Err(RequestError::BadRequest(
+                crate::InvalidRange::CountTooLargeForType(126, 125).into()
+            ))
+        );
+    }
+
+    #[test]
+    fn decodes_mbap_response() {
+        let range = AddressRange::try_from(2, 2).unwrap();
+        let frame = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x07, 0x01, 0x04, 0x04, 0xCA, 0xFE, 0xBE, 0xEF,
+        ];
+        assert_eq!(
+            decode_response(
+                &frame,
+                FrameFormat::Mbap,
+                Request::ReadInputRegisters(range)
+            ),
+            Ok((
+                Header::new(UnitId::new(1)).with_tx_id(7),
+                Response::Registers(vec![Indexed::new(2, 0xCAFE), Indexed::new(3, 0xBEEF)])
+            ))
+        );
+    }
+
+    #[test]
+    fn decodes_rtu_write_response() {
+        let value = Indexed::new(1, true);
+        let frame = [0x01, 0x05, 0x00, 0x01, 0xFF, 0x00, 0xDD, 0xFA];
+        assert_eq!(
+            decode_response(&frame, FrameFormat::Rtu, Request::WriteSingleCoil(value)),
+            Ok((Header::new(UnitId::new(1)), Response::SingleCoil(value)))
+        );
+    }
+
+    #[test]
+    fn round_trips_through_both_formats() {
+        let request = WriteMultiple::from(3, vec![true, false, true]).unwrap();
+        let request = Request::WriteMultipleCoils(&request);
+        let header = Header::new(UnitId::new(0x11)).with_tx_id(0x1234);
+
+        let rtu = encode(FrameFormat::Rtu, header, request);
+        assert_eq!(rtu[0], 0x11);
+        assert_eq!(&rtu[1..7], &[0x0F, 0x00, 0x03, 0x00, 0x03, 0x01]);
+
+        let mbap = encode(FrameFormat::Mbap, header, request);
+        assert_eq!(&mbap[..2], &[0x12, 0x34]);
+        assert_eq!(&mbap[7..13], &rtu[1..7]);
+    }
+
+    #[test]
+    fn decodes_exception() {
+        let range = AddressRange::try_from(0, 1).unwrap();
+        let frame = [0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x81, 0x02];
+        assert_eq!(
+            decode_response(&frame, FrameFormat::Mbap, Request::ReadCoils(range)),
+            Err(RequestError::Exception(ExceptionCode::IllegalDataAddress))
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_and_trailing_bytes() {
+        let range = AddressRange::try_from(0, 1).unwrap();
+        let frame = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x01, 0x01, 0x01, 0x01, 0xFF,
+        ];
+        assert_eq!(
+            decode_response(&frame[..9], FrameFormat::Mbap, Request::ReadCoils(range)),
+            Err(AduParseError::InsufficientBytes.into())
+        );
+        assert_eq!(
+            decode_response(&frame, FrameFormat::Mbap, Request::ReadCoils(range)),
+            Err(AduParseError::TrailingBytes(1).into())
+        );
+    }
+}
@@ -1,7 +1,39 @@
+// newer clippy versions flag the original expression
+#[allow(clippy::manual_div_ceil)]
 pub(crate) fn num_bytes_for_bits(count: u16) -> usize {
     (count as usize + 7) / 8
 }
 
+/// value of the bit at `offset` when bits are packed LSB-first, false if it's out of bounds
+pub(crate) fn get_bit(bytes: &[u8], offset: u16) -> bool {
+    match bytes.get((offset / 8) as usize) {
+        Some(byte) => byte & (1 << (offset % 8)) != 0,
+        None => false,
+    }
+}
+
+/// true if the bits that follow the first `count` bits in the last byte are zero
+pub(crate) fn unused_bits_are_clear(bytes: &[u8], count: u16) -> bool {
+    let used = count % 8;
+    if used == 0 {
+        return true;
+    }
+    match bytes.get((count / 8) as usize) {
+        Some(last) => *last & !((1u8 << used) - 1) == 0,
+        None => true,
+    }
+}
+
+/// clear the bits that follow the first `count` bits in the last byte
+pub(crate) fn clear_unused_bits(bytes: &mut [u8], count: u16) {
+    let used = count % 8;
+    if used != 0 {
+        if let Some(last) = bytes.get_mut((count / 8) as usize) {
+            *last &= (1 << used) - 1;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
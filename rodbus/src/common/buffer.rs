@@ -1,7 +1,9 @@
+#[cfg(feature = "runtime")]
 use crate::common::phys::PhysLayer;
 
 use crate::error::InternalError;
-use crate::PhysDecodeLevel;
+#[cfg(feature = "runtime")]
+use crate::DecodeLevel;
 
 pub(crate) struct ReadBuffer {
     buffer: [u8; crate::common::frame::constants::MAX_FRAME_LENGTH],
@@ -26,6 +28,11 @@ impl ReadBuffer {
         self.begin == self.end
     }
 
+    pub(crate) fn clear(&mut self) {
+        self.begin = 0;
+        self.end = 0;
+    }
+
     pub(crate) fn read(&mut self, count: usize) -> Result<&[u8], InternalError> {
         if self.len() < count {
             return Err(InternalError::InsufficientBytesForRead(count, self.len()));
@@ -53,7 +60,6 @@ impl ReadBuffer {
         }
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn peek_at(&mut self, idx: usize) -> Result<u8, InternalError> {
         let len = self.len();
         if len < idx {
@@ -71,18 +77,13 @@ impl ReadBuffer {
         Ok((b1 << 8) | b2)
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn read_u16_le(&mut self) -> Result<u16, InternalError> {
         let b1 = self.read_u8()? as u16;
         let b2 = self.read_u8()? as u16;
         Ok((b2 << 8) | b1)
     }
 
-    pub(crate) async fn read_some(
-        &mut self,
-        io: &mut PhysLayer,
-        decode_level: PhysDecodeLevel,
-    ) -> Result<usize, std::io::Error> {
+    fn make_room(&mut self) {
         // before we read any data, check to see if the buffer is empty and adjust the indices
         // this allows use to make the biggest read possible, and avoids subsequent buffer shifting later
         if self.is_empty() {
@@ -91,12 +92,31 @@ impl ReadBuffer {
         }
 
         // if we've reached capacity, but still need more data we have to shift
-        if self.end == self.len() {
+        if self.end == self.buffer.len() {
             let length = self.len();
             self.buffer.copy_within(self.begin..self.end, 0);
             self.begin = 0;
             self.end = length;
         }
+    }
+
+    /// Copy as many bytes as will fit into the buffer, returning the number copied
+    #[cfg(any(feature = "fuzz", feature = "codec"))]
+    pub(crate) fn extend(&mut self, data: &[u8]) -> usize {
+        self.make_room();
+        let count = data.len().min(self.buffer.len() - self.end);
+        self.buffer[self.end..self.end + count].copy_from_slice(&data[..count]);
+        self.end += count;
+        count
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) async fn read_some(
+        &mut self,
+        io: &mut PhysLayer,
+        decode_level: DecodeLevel,
+    ) -> Result<usize, std::io::Error> {
+        self.make_room();
 
         let count = io.read(&mut self.buffer[self.end..], decode_level).await?;
 
@@ -111,7 +131,7 @@ impl ReadBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::decode::PhysDecodeLevel;
+    use crate::decode::DecodeLevel;
     use tokio_test::*;
 
     #[test]
@@ -136,14 +156,14 @@ mod tests {
 
         {
             let mut task =
-                tokio_test::task::spawn(buffer.read_some(&mut phys, PhysDecodeLevel::Nothing));
+                tokio_test::task::spawn(buffer.read_some(&mut phys, DecodeLevel::nothing()));
             tokio_test::assert_pending!(task.poll());
         }
 
         {
             let mut task = task::spawn(async {
                 buffer
-                    .read_some(&mut phys, PhysDecodeLevel::Nothing)
+                    .read_some(&mut phys, DecodeLevel::nothing())
                     .await
                     .unwrap()
             });
@@ -156,7 +176,7 @@ mod tests {
         {
             let mut task = task::spawn(async {
                 buffer
-                    .read_some(&mut phys, PhysDecodeLevel::Nothing)
+                    .read_some(&mut phys, DecodeLevel::nothing())
                     .await
                     .unwrap()
             });
@@ -166,4 +186,41 @@ mod tests {
 
         assert_eq!(buffer.read(3).unwrap(), &[0x03, 0x04, 0x05]);
     }
+
+    #[test]
+    fn shifts_data_when_full() {
+        let mut buffer = ReadBuffer::new();
+        let capacity = crate::common::frame::constants::MAX_FRAME_LENGTH;
+
+        let (io, mut io_handle) = sfio_tokio_mock_io::mock();
+        let mut phys = PhysLayer::new_mock(io);
+
+        let data: Vec<u8> = (0..capacity).map(|x| x as u8).collect();
+        {
+            let mut task = task::spawn(async {
+                buffer
+                    .read_some(&mut phys, DecodeLevel::nothing())
+                    .await
+                    .unwrap()
+            });
+            io_handle.read(&data);
+            assert_ready_eq!(task.poll(), capacity);
+        }
+
+        // consume part of the data so that only the tail remains at the end of the buffer
+        buffer.read(capacity - 1).unwrap();
+
+        {
+            let mut task = task::spawn(async {
+                buffer
+                    .read_some(&mut phys, DecodeLevel::nothing())
+                    .await
+                    .unwrap()
+            });
+            io_handle.read(&[0xAA]);
+            assert_ready_eq!(task.poll(), 1);
+        }
+
+        assert_eq!(buffer.read(2).unwrap(), &[data[capacity - 1], 0xAA]);
+    }
 }
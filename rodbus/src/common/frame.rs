@@ -1,3 +1,4 @@
+#[cfg(feature = "runtime")]
 use crate::common::phys::PhysLayer;
 use std::ops::Range;
 
@@ -7,7 +8,7 @@ use crate::common::traits::{Loggable, LoggableDisplay, Serialize};
 use crate::error::RequestError;
 use crate::tcp::frame::{MbapDisplay, MbapHeader, MbapParser};
 use crate::types::UnitId;
-use crate::{DecodeLevel, ExceptionCode, FrameDecodeLevel};
+use crate::{DecodeLevel, ExceptionCode};
 
 use scursor::WriteCursor;
 
@@ -22,20 +23,10 @@ pub(crate) mod constants {
 
     pub(crate) const MAX_ADU_LENGTH: usize = 253;
 
-    #[cfg(feature = "serial")]
-    const fn serial_frame_size() -> usize {
-        crate::serial::frame::constants::MAX_FRAME_LENGTH
-    }
-
-    #[cfg(not(feature = "serial"))]
-    const fn serial_frame_size() -> usize {
-        0
-    }
-
-    /// the maximum size of a TCP or serial frame
+    /// the maximum size of a TCP or RTU frame
     pub(crate) const MAX_FRAME_LENGTH: usize = max(
         crate::tcp::frame::constants::MAX_FRAME_LENGTH,
-        serial_frame_size(),
+        crate::rtu::frame::constants::MAX_FRAME_LENGTH,
     );
 }
 
@@ -131,7 +122,6 @@ impl FrameHeader {
         }
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn new_rtu_header(destination: FrameDestination) -> Self {
         FrameHeader {
             destination,
@@ -172,8 +162,7 @@ impl Frame {
 
 ///  Defines an interface for parsing frames (TCP or RTU)
 pub(crate) enum FrameParser {
-    #[cfg(feature = "serial")]
-    Rtu(crate::serial::frame::RtuParser),
+    Rtu(crate::rtu::frame::RtuParser),
     Tcp(MbapParser),
 }
 
@@ -187,10 +176,9 @@ impl FrameParser {
     pub(crate) fn parse(
         &mut self,
         cursor: &mut ReadBuffer,
-        decode_level: FrameDecodeLevel,
+        decode_level: DecodeLevel,
     ) -> Result<Option<Frame>, RequestError> {
         match self {
-            #[cfg(feature = "serial")]
             FrameParser::Rtu(x) => x.parse(cursor, decode_level),
             FrameParser::Tcp(x) => x.parse(cursor, decode_level),
         }
@@ -199,7 +187,6 @@ impl FrameParser {
     /// Reset the parser state. Called whenever an error occurs
     pub(crate) fn reset(&mut self) {
         match self {
-            #[cfg(feature = "serial")]
             FrameParser::Rtu(x) => x.reset(),
             FrameParser::Tcp(x) => x.reset(),
         }
@@ -208,7 +195,6 @@ impl FrameParser {
 
 pub(crate) enum FrameType {
     Mbap(MbapHeader),
-    #[cfg(feature = "serial")]
     // destination and CRC
     Rtu(FrameDestination, u16),
 }
@@ -229,9 +215,9 @@ impl FrameInfo {
     }
 }
 
+#[derive(Copy, Clone)]
 enum FormatType {
     Tcp,
-    #[cfg(feature = "serial")]
     Rtu,
 }
 
@@ -245,15 +231,19 @@ impl FormatType {
     ) -> Result<FrameInfo, RequestError> {
         match self {
             FormatType::Tcp => crate::tcp::frame::format_mbap(cursor, header, function, body),
-            #[cfg(feature = "serial")]
-            FormatType::Rtu => crate::serial::frame::format_rtu_pdu(cursor, header, function, body),
+            FormatType::Rtu => crate::rtu::frame::format_rtu_pdu(cursor, header, function, body),
         }
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct FrameWriter {
     format_type: FormatType,
     buffer: [u8; constants::MAX_FRAME_LENGTH],
+    /// length of the last formatted frame
+    length: usize,
+    /// true if the last formatted frame was an exception response
+    exception: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -299,9 +289,20 @@ impl FrameWriter {
         Self {
             format_type,
             buffer: [0; constants::MAX_FRAME_LENGTH],
+            length: 0,
+            exception: false,
         }
     }
 
+    pub(crate) fn wrote_exception(&self) -> bool {
+        self.exception
+    }
+
+    /// bytes of the last formatted frame
+    pub(crate) fn last_frame(&self) -> &[u8] {
+        &self.buffer[..self.length]
+    }
+
     pub(crate) fn format_reply<T>(
         &mut self,
         header: FrameHeader,
@@ -364,20 +365,37 @@ impl FrameWriter {
     where
         T: Serialize + Loggable,
     {
-        let (frame_type, frame_bytes, pdu_body) = {
+        self.exception = !matches!(function, FunctionField::Valid(_));
+
+        let (frame_type, frame_bytes, pdu) = {
             let mut cursor = WriteCursor::new(self.buffer.as_mut());
             let info = self
                 .format_type
                 .format(&mut cursor, header, function, body)?;
             let end = cursor.position();
-            (info.frame_type, 0..end, &self.buffer[info.pdu_body])
+            // the PDU starts with the function code that precedes the body
+            (
+                info.frame_type,
+                0..end,
+                info.pdu_body.start - 1..info.pdu_body.end,
+            )
         };
+        let pdu_body = &self.buffer[pdu.start + 1..pdu.end];
+        self.length = frame_bytes.end;
 
         if decode_level.app.enabled() {
+            let range = body.address_range();
             tracing::info!(
+                fc = function.get_value(),
+                unit = header.destination.value(),
+                tx_id = header.tx_id.map(|x| x.to_u16()),
+                start = range.map(|x| x.start),
+                count = range.map(|x| x.count),
+                exception = body.exception().map(u8::from),
+                len = pdu_body.len(),
                 "PDU TX - {} {}",
                 function,
-                LoggableDisplay::new(body, pdu_body, decode_level.app)
+                LoggableDisplay::new(body, pdu_body, decode_level.app_decode())
             );
         }
 
@@ -386,18 +404,24 @@ impl FrameWriter {
             match frame_type {
                 FrameType::Mbap(header) => {
                     tracing::info!(
+                        tx_id = header.tx_id.to_u16(),
+                        unit = header.unit_id.value,
+                        len = header.len_field,
                         "MBAP TX - {}",
-                        MbapDisplay::new(decode_level.frame, header, frame_bytes)
+                        MbapDisplay::new(decode_level, header, frame_bytes, pdu)
                     );
                 }
-                #[cfg(feature = "serial")]
                 FrameType::Rtu(dest, crc) => {
                     tracing::info!(
+                        unit = dest.value(),
+                        len = frame_bytes.len(),
+                        crc,
                         "RTU TX - {}",
-                        crate::serial::frame::RtuDisplay::new(
-                            decode_level.frame,
+                        crate::rtu::frame::RtuDisplay::new(
+                            decode_level,
                             dest,
                             frame_bytes,
+                            pdu,
                             crc
                         )
                     );
@@ -412,33 +436,35 @@ impl FrameWriter {
         Self::new(FormatType::Tcp)
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn rtu() -> Self {
         Self::new(FormatType::Rtu)
     }
 }
 
+#[cfg(feature = "runtime")]
 pub(crate) struct FramedReader {
     parser: FrameParser,
     buffer: ReadBuffer,
+    frame_timeout: Option<std::time::Duration>,
+    // time at which the first byte of the frame currently being parsed was received
+    frame_start: Option<tokio::time::Instant>,
 }
 
+#[cfg(feature = "runtime")]
 impl FramedReader {
     pub(crate) fn tcp() -> Self {
         Self::new(FrameParser::Tcp(MbapParser::new()))
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn rtu_request() -> Self {
         Self::new(FrameParser::Rtu(
-            crate::serial::frame::RtuParser::new_request_parser(),
+            crate::rtu::frame::RtuParser::new_request_parser(),
         ))
     }
 
-    #[cfg(feature = "serial")]
     pub(crate) fn rtu_response() -> Self {
         Self::new(FrameParser::Rtu(
-            crate::serial::frame::RtuParser::new_response_parser(),
+            crate::rtu::frame::RtuParser::new_response_parser(),
         ))
     }
 
@@ -446,25 +472,101 @@ impl FramedReader {
         Self {
             parser,
             buffer: ReadBuffer::new(),
+            frame_timeout: None,
+            frame_start: None,
         }
     }
 
+    /// Bound how long the remainder of a frame may take to arrive once its first byte is received
+    /// true if frames carry a transaction id that identifies the request they answer
+    pub(crate) fn has_tx_id(&self) -> bool {
+        matches!(self.parser, FrameParser::Tcp(_))
+    }
+
+    pub(crate) fn set_frame_timeout(&mut self, value: Option<std::time::Duration>) {
+        self.frame_timeout = value;
+    }
+
+    /// Restart the frame timeout of a partially received frame
+    ///
+    /// Used when the session deliberately stops reading, e.g. while throttling, so that the time
+    /// spent not reading doesn't count against the peer.
+    pub(crate) fn restart_frame_timer(&mut self) {
+        if self.frame_start.is_some() {
+            self.frame_start = Some(tokio::time::Instant::now());
+        }
+    }
+
+    /// Drop any partially parsed frame and all buffered bytes
+    pub(crate) fn discard(&mut self) {
+        self.parser.reset();
+        self.buffer.clear();
+        self.frame_start = None;
+    }
+
     pub(crate) async fn next_frame(
         &mut self,
         io: &mut PhysLayer,
         decode_level: DecodeLevel,
     ) -> Result<Frame, RequestError> {
         loop {
-            match self.parser.parse(&mut self.buffer, decode_level.frame) {
-                Ok(Some(frame)) => return Ok(frame),
+            match self.parser.parse(&mut self.buffer, decode_level) {
+                Ok(Some(frame)) => {
+                    // any remaining bytes belong to the next frame
+                    self.frame_start = if self.buffer.is_empty() {
+                        None
+                    } else {
+                        Some(tokio::time::Instant::now())
+                    };
+                    return Ok(frame);
+                }
                 Ok(None) => {
-                    self.buffer.read_some(io, decode_level.physical).await?;
+                    self.read_some(io, decode_level).await?;
                 }
                 Err(err) => {
                     self.parser.reset();
+                    self.frame_start = None;
                     return Err(err);
                 }
             }
         }
     }
+
+    async fn read_some(
+        &mut self,
+        io: &mut PhysLayer,
+        decode_level: DecodeLevel,
+    ) -> Result<(), RequestError> {
+        let deadline = match (self.frame_start, self.frame_timeout) {
+            (Some(start), Some(timeout)) => Some(start + timeout),
+            _ => None,
+        };
+
+        match deadline {
+            None => {
+                self.buffer.read_some(io, decode_level).await?;
+            }
+            Some(deadline) => {
+                let read = self.buffer.read_some(io, decode_level);
+                match tokio::time::timeout_at(deadline, read).await {
+                    Ok(res) => {
+                        res?;
+                    }
+                    Err(_) => {
+                        // discard the partial frame so that the next frame starts from a clean state
+                        self.parser.reset();
+                        self.buffer.clear();
+                        self.frame_start = None;
+                        return Err(crate::error::FrameParseError::PartialFrameTimeout.into());
+                    }
+                }
+            }
+        }
+
+        if self.frame_start.is_none() {
+            self.frame_start = Some(tokio::time::Instant::now());
+        }
+
+        Ok(())
+    }
 }
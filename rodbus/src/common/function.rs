@@ -11,16 +11,25 @@ mod constants {
     pub(crate) const WRITE_MULTIPLE_REGISTERS: u8 = 16;
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// Function codes supported by the library
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u8)]
-pub(crate) enum FunctionCode {
+pub enum FunctionCode {
+    /// Read coils (0x01)
     ReadCoils = constants::READ_COILS,
+    /// Read discrete inputs (0x02)
     ReadDiscreteInputs = constants::READ_DISCRETE_INPUTS,
+    /// Read holding registers (0x03)
     ReadHoldingRegisters = constants::READ_HOLDING_REGISTERS,
+    /// Read input registers (0x04)
     ReadInputRegisters = constants::READ_INPUT_REGISTERS,
+    /// Write single coil (0x05)
     WriteSingleCoil = constants::WRITE_SINGLE_COIL,
+    /// Write single register (0x06)
     WriteSingleRegister = constants::WRITE_SINGLE_REGISTER,
+    /// Write multiple coils (0x0F)
     WriteMultipleCoils = constants::WRITE_MULTIPLE_COILS,
+    /// Write multiple registers (0x10)
     WriteMultipleRegisters = constants::WRITE_MULTIPLE_REGISTERS,
 }
 
@@ -0,0 +1,138 @@
+//! Instrumentation recorded via the `metrics` facade when the `metrics` feature is enabled
+//!
+//! Every function compiles to nothing when the feature is disabled. Labels are limited to
+//! bounded values: function code names, unit ids, and result categories.
+
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+
+use std::time::Duration;
+
+use crate::common::frame::FunctionField;
+use crate::common::function::FunctionCode;
+use crate::error::RequestError;
+
+/// Outcome of a request processed by a server session
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ServerResult {
+    /// a normal response was sent
+    Success,
+    /// an exception response was produced
+    Exception,
+    /// the request was dropped without being processed, e.g. an unmapped unit id
+    Ignored,
+}
+
+/// Record a request completed by a client
+pub(crate) fn client_request(
+    function: FunctionCode,
+    result: &Result<(), RequestError>,
+    duration: Duration,
+) {
+    #[cfg(feature = "metrics")]
+    {
+        let result = match result {
+            Ok(()) => "success",
+            Err(err) => error_label(err),
+        };
+        let function = function_label(function);
+        metrics::counter!(
+            "rodbus_client_requests_total",
+            "function" => function,
+            "result" => result
+        )
+        .increment(1);
+        metrics::histogram!(
+            "rodbus_client_request_duration_seconds",
+            "function" => function
+        )
+        .record(duration.as_secs_f64());
+    }
+}
+
+/// Record that a client lost its connection and will attempt to re-establish it
+pub(crate) fn client_reconnect() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("rodbus_client_reconnects_total").increment(1);
+}
+
+/// Record a connection that a server closed without starting a session
+pub(crate) fn server_rejected_connection(reason: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("rodbus_server_rejected_connections_total", "reason" => reason).increment(1);
+}
+
+/// Record a request processed by a server session
+pub(crate) fn server_request(function: FunctionField, unit: u8, result: ServerResult) {
+    #[cfg(feature = "metrics")]
+    {
+        let function = match function {
+            FunctionField::Valid(x) | FunctionField::Exception(x) => function_label(x),
+            FunctionField::UnknownFunction(_) => "unknown",
+        };
+        let result = match result {
+            ServerResult::Success => "success",
+            ServerResult::Exception => "exception",
+            ServerResult::Ignored => "ignored",
+        };
+        metrics::counter!(
+            "rodbus_server_requests_total",
+            "function" => function,
+            "unit" => unit.to_string(),
+            "result" => result
+        )
+        .increment(1);
+    }
+}
+
+/// Tracks a server session in the `rodbus_server_active_sessions` gauge for as long as it lives
+pub(crate) struct ActiveSession {
+    _private: (),
+}
+
+impl ActiveSession {
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("rodbus_server_active_sessions").increment(1.0);
+        Self { _private: () }
+    }
+}
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("rodbus_server_active_sessions").decrement(1.0);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn function_label(function: FunctionCode) -> &'static str {
+    match function {
+        FunctionCode::ReadCoils => "read_coils",
+        FunctionCode::ReadDiscreteInputs => "read_discrete_inputs",
+        FunctionCode::ReadHoldingRegisters => "read_holding_registers",
+        FunctionCode::ReadInputRegisters => "read_input_registers",
+        FunctionCode::WriteSingleCoil => "write_single_coil",
+        FunctionCode::WriteSingleRegister => "write_single_register",
+        FunctionCode::WriteMultipleCoils => "write_multiple_coils",
+        FunctionCode::WriteMultipleRegisters => "write_multiple_registers",
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn error_label(err: &RequestError) -> &'static str {
+    match err {
+        RequestError::Io(_) => "io",
+        RequestError::Exception(_) => "exception",
+        RequestError::BadRequest(_) => "bad_request",
+        RequestError::BadFrame(_) => "bad_frame",
+        RequestError::BadResponse(_) => "bad_response",
+        RequestError::Internal(_) => "internal",
+        RequestError::ResponseTimeout => "timeout",
+        RequestError::NoConnection => "no_connection",
+        RequestError::Shutdown => "shutdown",
+        RequestError::RetriesExhausted(_) => "retries_exhausted",
+        RequestError::TooManyRequests => "too_many_requests",
+        RequestError::Cancelled => "cancelled",
+        RequestError::VerifyFailed(_) => "verify_failed",
+    }
+}
@@ -4,6 +4,9 @@ pub(crate) mod traits;
 pub(crate) mod bits;
 pub(crate) mod buffer;
 pub(crate) mod frame;
-mod parse;
+#[cfg(feature = "runtime")]
+pub(crate) mod metrics;
+pub(crate) mod parse;
 pub(crate) mod phys;
+pub(crate) mod redact;
 mod serialize;
@@ -28,6 +28,26 @@ impl Parse for Indexed<u16> {
     }
 }
 
+/// Read the byte count of a read response and check it against the requested quantity
+/// and the number of bytes that follow it
+pub(crate) fn read_byte_count(
+    cursor: &mut ReadCursor,
+    expected: usize,
+) -> Result<(), RequestError> {
+    let count = cursor.read_u8()? as usize;
+    if count != expected {
+        return Err(AduParseError::ByteCountMismatch(count, expected).into());
+    }
+    let remaining = cursor.remaining();
+    if remaining < count {
+        return Err(AduParseError::InsufficientBytesForByteCount(count, remaining).into());
+    }
+    if remaining > count {
+        return Err(AduParseError::TrailingBytes(remaining - count).into());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod coils {
     use crate::common::traits::Parse;
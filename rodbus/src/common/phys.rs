@@ -1,53 +1,91 @@
-use crate::decode::PhysDecodeLevel;
+#[cfg(feature = "runtime")]
+use crate::capture::{Capture, Direction};
+use crate::decode::{BytesFormat, BytesStyle, DecodeLevel, PhysDecodeLevel};
 use std::fmt::Write;
+use std::ops::Range;
+#[cfg(feature = "runtime")]
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+#[cfg(feature = "runtime")]
 pub(crate) struct PhysLayer {
     layer: PhysLayerImpl,
+    capture: Option<Capture>,
 }
 
 // encapsulates all possible physical layers as an enum
+#[cfg(feature = "runtime")]
 pub(crate) enum PhysLayerImpl {
     Tcp(tokio::net::TcpStream),
+    // connected UDP socket, every read and write is a single datagram
+    Udp(tokio::net::UdpSocket),
     #[cfg(feature = "serial")]
     Serial(
         tokio_serial::SerialStream,
         tokio::time::Duration,
         Option<tokio::time::Instant>,
+        Option<crate::serial::RtsControl>,
     ),
     // TLS type is boxed because its size is huge
     #[cfg(feature = "tls")]
     Tls(Box<tokio_rustls::TlsStream<tokio::net::TcpStream>>),
+    // in-memory connection created by memory::in_memory_pair
+    Memory(tokio::io::DuplexStream),
+    // delay applied before each write to simulate a slow link
     #[cfg(test)]
-    Mock(sfio_tokio_mock_io::Mock),
+    Mock(sfio_tokio_mock_io::Mock, tokio::time::Duration),
 }
 
+#[cfg(feature = "runtime")]
 impl std::fmt::Debug for PhysLayer {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match &self.layer {
             PhysLayerImpl::Tcp(_) => f.write_str("Tcp"),
+            PhysLayerImpl::Udp(_) => f.write_str("Udp"),
             #[cfg(feature = "serial")]
-            PhysLayerImpl::Serial(_, _, _) => f.write_str("Serial"),
+            PhysLayerImpl::Serial(_, _, _, _) => f.write_str("Serial"),
             #[cfg(feature = "tls")]
             PhysLayerImpl::Tls(_) => f.write_str("Tls"),
+            PhysLayerImpl::Memory(_) => f.write_str("Memory"),
             #[cfg(test)]
-            PhysLayerImpl::Mock(_) => f.write_str("Mock"),
+            PhysLayerImpl::Mock(_, _) => f.write_str("Mock"),
         }
     }
 }
 
+#[cfg(feature = "runtime")]
 impl PhysLayer {
     pub(crate) fn new_tcp(socket: tokio::net::TcpStream) -> Self {
         Self {
             layer: PhysLayerImpl::Tcp(socket),
+            capture: None,
+        }
+    }
+
+    pub(crate) fn new_udp(socket: tokio::net::UdpSocket) -> Self {
+        Self {
+            layer: PhysLayerImpl::Udp(socket),
+            capture: None,
         }
     }
 
     #[cfg(feature = "serial")]
-    pub(crate) fn new_serial(stream: tokio_serial::SerialStream) -> Self {
+    pub(crate) fn new_serial(
+        mut stream: tokio_serial::SerialStream,
+        rs485: crate::serial::Rs485Direction,
+    ) -> Self {
+        use tokio_serial::SerialPort;
+
         let calculate_inter_character_delay = calculate_inter_character_delay(&stream);
+        let rts = rs485.rts_control();
+        if let Some(rts) = rts {
+            // listen until the first frame is written
+            if let Err(err) = stream.write_request_to_send(!rts.tx_level) {
+                tracing::warn!("unable to set RTS: {}", err);
+            }
+        }
         Self {
-            layer: PhysLayerImpl::Serial(stream, calculate_inter_character_delay, None),
+            layer: PhysLayerImpl::Serial(stream, calculate_inter_character_delay, None, rts),
+            capture: None,
         }
     }
 
@@ -55,77 +93,159 @@ impl PhysLayer {
     pub(crate) fn new_tls(socket: tokio_rustls::TlsStream<tokio::net::TcpStream>) -> Self {
         Self {
             layer: PhysLayerImpl::Tls(Box::new(socket)),
+            capture: None,
+        }
+    }
+
+    pub(crate) fn new_memory(stream: tokio::io::DuplexStream) -> Self {
+        Self {
+            layer: PhysLayerImpl::Memory(stream),
+            capture: None,
         }
     }
 
     #[cfg(test)]
     pub(crate) fn new_mock(mock: sfio_tokio_mock_io::Mock) -> Self {
         Self {
-            layer: PhysLayerImpl::Mock(mock),
+            layer: PhysLayerImpl::Mock(mock, tokio::time::Duration::ZERO),
+            capture: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn new_mock_with_write_delay(
+        mock: sfio_tokio_mock_io::Mock,
+        delay: tokio::time::Duration,
+    ) -> Self {
+        Self {
+            layer: PhysLayerImpl::Mock(mock, delay),
+            capture: None,
+        }
+    }
+
+    /// Copy the bytes of every subsequent read and write to `capture`
+    pub(crate) fn set_capture(&mut self, capture: Option<Capture>) {
+        self.capture = capture;
+    }
+
+    /// details of the TLS session if this is a TLS connection
+    #[cfg(feature = "tls")]
+    pub(crate) fn tls_info(&self) -> Option<crate::tcp::tls::TlsInfo> {
+        match &self.layer {
+            PhysLayerImpl::Tls(x) => Some(crate::tcp::tls::TlsInfo::new(x.get_ref().1)),
+            _ => None,
         }
     }
 
     pub(crate) async fn read(
         &mut self,
         buffer: &mut [u8],
-        decode_level: PhysDecodeLevel,
+        decode_level: DecodeLevel,
     ) -> Result<usize, std::io::Error> {
         let length = match &mut self.layer {
             PhysLayerImpl::Tcp(x) => x.read(buffer).await?,
+            PhysLayerImpl::Udp(x) => x.recv(buffer).await?,
             #[cfg(feature = "serial")]
-            PhysLayerImpl::Serial(x, _, _) => x.read(buffer).await?,
+            PhysLayerImpl::Serial(x, _, _, _) => x.read(buffer).await?,
             #[cfg(feature = "tls")]
             PhysLayerImpl::Tls(x) => x.read(buffer).await?,
+            PhysLayerImpl::Memory(x) => x.read(buffer).await?,
             #[cfg(test)]
-            PhysLayerImpl::Mock(x) => x.read(buffer).await?,
+            PhysLayerImpl::Mock(x, _) => x.read(buffer).await?,
         };
 
-        if decode_level.enabled() {
+        if decode_level.physical.enabled() {
             if let Some(x) = buffer.get(0..length) {
                 tracing::info!("PHYS RX - {}", PhysDisplay::new(decode_level, x))
             }
         }
 
+        if let Some(capture) = &self.capture {
+            if let Some(x) = buffer.get(0..length) {
+                crate::capture::record(capture, Direction::Rx, x);
+            }
+        }
+
         Ok(length)
     }
 
+    /// Time needed to clock out the specified number of bytes that may still be queued by the driver
+    /// after a write completes
+    ///
+    /// Only serial ports are slow enough for this to matter, it's zero for all other layers.
+    #[cfg_attr(not(feature = "serial"), allow(unused_variables))]
+    pub(crate) fn transmit_time(&self, len: usize) -> tokio::time::Duration {
+        match &self.layer {
+            #[cfg(feature = "serial")]
+            PhysLayerImpl::Serial(x, _, _, _) => {
+                use tokio_serial::SerialPort;
+                match x.baud_rate() {
+                    Ok(baud_rate) => character_time(baud_rate) * len as u32,
+                    Err(_) => tokio::time::Duration::ZERO,
+                }
+            }
+            _ => tokio::time::Duration::ZERO,
+        }
+    }
+
     pub(crate) async fn write(
         &mut self,
         data: &[u8],
-        decode_level: PhysDecodeLevel,
+        decode_level: DecodeLevel,
     ) -> Result<(), std::io::Error> {
-        if decode_level.enabled() {
+        if decode_level.physical.enabled() {
             tracing::info!("PHYS TX - {}", PhysDisplay::new(decode_level, data));
         }
 
         match &mut self.layer {
             PhysLayerImpl::Tcp(x) => x.write_all(data).await,
+            PhysLayerImpl::Udp(x) => x.send(data).await.map(|_| ()),
             #[cfg(feature = "serial")]
-            PhysLayerImpl::Serial(x, inter_char_delay, last_activity) => {
+            PhysLayerImpl::Serial(x, inter_char_delay, last_activity, rts) => {
                 // Respect inter-character delay
                 if let Some(last_activity) = last_activity {
                     tokio::time::sleep_until(*last_activity + *inter_char_delay).await;
                 }
                 *last_activity = Some(tokio::time::Instant::now());
 
-                x.write_all(data).await
+                match rts {
+                    Some(rts) => write_with_rts(x, *rts, data).await,
+                    None => x.write_all(data).await,
+                }
             }
             #[cfg(feature = "tls")]
             PhysLayerImpl::Tls(x) => x.write_all(data).await,
+            PhysLayerImpl::Memory(x) => x.write_all(data).await,
             #[cfg(test)]
-            PhysLayerImpl::Mock(x) => x.write_all(data).await,
+            PhysLayerImpl::Mock(x, delay) => {
+                tokio::time::sleep(*delay).await;
+                x.write_all(data).await
+            }
+        }?;
+
+        if let Some(capture) = &self.capture {
+            crate::capture::record(capture, Direction::Tx, data);
         }
+
+        Ok(())
     }
 }
 
 pub(crate) struct PhysDisplay<'a> {
     level: PhysDecodeLevel,
+    format: BytesFormat,
+    redact_values: bool,
     data: &'a [u8],
 }
 
 impl<'a> PhysDisplay<'a> {
-    pub(crate) fn new(level: PhysDecodeLevel, data: &'a [u8]) -> Self {
-        PhysDisplay { level, data }
+    pub(crate) fn new(decode_level: DecodeLevel, data: &'a [u8]) -> Self {
+        PhysDisplay {
+            level: decode_level.physical,
+            format: decode_level.bytes,
+            redact_values: decode_level.redact_values,
+            data,
+        }
     }
 }
 
@@ -133,20 +253,64 @@ impl<'a> std::fmt::Display for PhysDisplay<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{} bytes", self.data.len())?;
         if self.level.data_enabled() {
-            format_bytes(f, self.data)?;
+            let redacted = if self.redact_values {
+                crate::common::redact::phys_values(self.data)
+            } else {
+                Vec::new()
+            };
+            format_bytes(f, self.data, self.format, &redacted)?;
         }
         Ok(())
     }
 }
 
+#[cfg(feature = "serial")]
+fn character_time(baud_rate: u32) -> tokio::time::Duration {
+    // Modbus RTU uses 11-bit characters (1 start, 8 data, 1 parity or stop, 1 stop)
+    const NUM_BITS_IN_CHAR: u64 = 11;
+
+    tokio::time::Duration::from_secs(NUM_BITS_IN_CHAR) / baud_rate.max(1)
+}
+
+/// Write a frame while RTS drives a half-duplex RS-485 transceiver to transmit
+#[cfg(feature = "serial")]
+async fn write_with_rts(
+    port: &mut tokio_serial::SerialStream,
+    rts: crate::serial::RtsControl,
+    data: &[u8],
+) -> Result<(), std::io::Error> {
+    use tokio_serial::SerialPort;
+
+    port.write_request_to_send(rts.tx_level)?;
+    tokio::time::sleep(rts.pre_delay).await;
+
+    let start = tokio::time::Instant::now();
+    let result = async {
+        port.write_all(data).await?;
+        port.flush().await
+    }
+    .await;
+
+    if result.is_ok() {
+        // the write completes once the driver has queued the bytes, so wait until the last byte
+        // has been clocked out before releasing the bus
+        let transmit_time = match port.baud_rate() {
+            Ok(baud_rate) => character_time(baud_rate) * data.len() as u32,
+            Err(_) => tokio::time::Duration::ZERO,
+        };
+        tokio::time::sleep_until(start + transmit_time + rts.post_delay).await;
+    }
+
+    // return to receive even if the write failed
+    port.write_request_to_send(!rts.tx_level)?;
+    result
+}
+
 #[cfg(feature = "serial")]
 fn calculate_inter_character_delay(serial: &tokio_serial::SerialStream) -> tokio::time::Duration {
     use tokio::time::Duration;
     use tokio_serial::SerialPort;
 
-    // Modbus RTU uses 11-bit characters (1 start, 8 data, 1 parity or stop, 1 stop)
-    const NUM_BITS_IN_CHAR: u64 = 11;
-
     // If the baud rate is higher than a certain threshold, then we fix the delay
     // These constants are taken from the remark on page 13
     const MAX_BAUD_RATE: u32 = 19200;
@@ -154,8 +318,7 @@ fn calculate_inter_character_delay(serial: &tokio_serial::SerialStream) -> tokio
 
     match serial.baud_rate() {
         Ok(baud_rate) if baud_rate <= MAX_BAUD_RATE => {
-            let character_time = Duration::from_secs(NUM_BITS_IN_CHAR) / baud_rate;
-            35 * character_time / 10 // multiply by 3.5
+            35 * character_time(baud_rate) / 10 // multiply by 3.5
         }
         Ok(_) => MIN_DELAY,
         Err(_) => {
@@ -169,18 +332,157 @@ fn calculate_inter_character_delay(serial: &tokio_serial::SerialStream) -> tokio
 }
 
 const BYTES_PER_DECODE_LINE: usize = 18;
+const BYTES_PER_HEX_DUMP_LINE: usize = 16;
 
-pub(crate) fn format_bytes(f: &mut std::fmt::Formatter, bytes: &[u8]) -> std::fmt::Result {
-    for chunk in bytes.chunks(BYTES_PER_DECODE_LINE) {
-        writeln!(f)?;
-        let mut first = true;
-        for byte in chunk {
-            if !first {
-                f.write_char(' ')?;
+pub(crate) fn format_bytes(
+    f: &mut std::fmt::Formatter,
+    bytes: &[u8],
+    format: BytesFormat,
+    redacted: &[Range<usize>],
+) -> std::fmt::Result {
+    let shown = match format.max_bytes {
+        Some(max) if max < bytes.len() => &bytes[..max],
+        _ => bytes,
+    };
+    let is_redacted = |pos: usize| redacted.iter().any(|x| x.contains(&pos));
+
+    match format.style {
+        BytesStyle::SpacedHex => {
+            for (line, chunk) in shown.chunks(BYTES_PER_DECODE_LINE).enumerate() {
+                writeln!(f)?;
+                for (i, byte) in chunk.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(' ')?;
+                    }
+                    write_byte(f, *byte, is_redacted(line * BYTES_PER_DECODE_LINE + i))?;
+                }
             }
-            first = false;
-            write!(f, "{byte:02X?}")?;
         }
+        BytesStyle::CompactHex => {
+            writeln!(f)?;
+            for (pos, byte) in shown.iter().enumerate() {
+                write_byte(f, *byte, is_redacted(pos))?;
+            }
+        }
+        BytesStyle::HexDump => {
+            for (line, chunk) in shown.chunks(BYTES_PER_HEX_DUMP_LINE).enumerate() {
+                let offset = line * BYTES_PER_HEX_DUMP_LINE;
+                writeln!(f)?;
+                write!(f, "{offset:04X} ")?;
+                for (i, byte) in chunk.iter().enumerate() {
+                    f.write_char(' ')?;
+                    write_byte(f, *byte, is_redacted(offset + i))?;
+                }
+                // align the ASCII column of the last line
+                for _ in chunk.len()..BYTES_PER_HEX_DUMP_LINE {
+                    f.write_str("   ")?;
+                }
+                f.write_str("  |")?;
+                for (i, byte) in chunk.iter().enumerate() {
+                    let c = if is_redacted(offset + i) {
+                        '*'
+                    } else if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    };
+                    f.write_char(c)?;
+                }
+                f.write_char('|')?;
+            }
+        }
+    }
+
+    if shown.len() < bytes.len() {
+        writeln!(f)?;
+        write!(f, "... ({} bytes total)", bytes.len())?;
     }
+
     Ok(())
 }
+
+fn write_byte(f: &mut std::fmt::Formatter, byte: u8, redacted: bool) -> std::fmt::Result {
+    if redacted {
+        f.write_str("**")
+    } else {
+        write!(f, "{byte:02X?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(bytes: &[u8], format: BytesFormat) -> String {
+        let level = DecodeLevel::nothing()
+            .physical(PhysDecodeLevel::Data)
+            .bytes(format);
+        PhysDisplay::new(level, bytes).to_string()
+    }
+
+    #[test]
+    fn formats_spaced_hex_by_default() {
+        let bytes: Vec<u8> = (0..20).collect();
+        assert_eq!(
+            display(&bytes, BytesFormat::default()),
+            "20 bytes\n00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F 10 11\n12 13"
+        );
+    }
+
+    #[test]
+    fn truncates_after_max_bytes() {
+        let bytes: Vec<u8> = (0..20).collect();
+        let format = BytesFormat::default()
+            .style(BytesStyle::CompactHex)
+            .max_bytes(Some(4));
+        assert_eq!(
+            display(&bytes, format),
+            "20 bytes\n00010203\n... (20 bytes total)"
+        );
+        // nothing is truncated if the limit isn't exceeded
+        let format = format.max_bytes(Some(20));
+        assert!(!display(&bytes, format).contains("..."));
+    }
+
+    #[test]
+    fn formats_hex_dump_with_ascii() {
+        let mut bytes = b"Modbus TCP frame".to_vec();
+        bytes.extend_from_slice(&[0x00, 0xFF]);
+        let format = BytesFormat::default().style(BytesStyle::HexDump);
+        assert_eq!(
+            display(&bytes, format),
+            "18 bytes\n\
+            0000  4D 6F 64 62 75 73 20 54 43 50 20 66 72 61 6D 65  |Modbus TCP frame|\n\
+            0010  00 FF                                            |..|"
+        );
+    }
+
+    #[test]
+    fn redacts_values_of_complete_frames() {
+        //            |   tx id  |  proto id |  length  | unit | fc |   addr   |   value  |
+        let frame = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x02, 0xCA, 0xFE,
+        ];
+        let level = DecodeLevel::nothing()
+            .physical(PhysDecodeLevel::Data)
+            .redact_values(true);
+        assert_eq!(
+            PhysDisplay::new(level, &frame).to_string(),
+            "12 bytes\n00 01 00 00 00 06 01 06 00 02 ** **"
+        );
+        // a partial frame can't be decoded so every byte is redacted
+        let level = level.bytes(BytesFormat::default().style(BytesStyle::HexDump));
+        assert_eq!(
+            PhysDisplay::new(level, &frame[..4]).to_string(),
+            "4 bytes\n0000  ** ** ** **                                      |****|"
+        );
+    }
+
+    #[cfg(feature = "serial")]
+    #[test]
+    fn character_time_uses_eleven_bits() {
+        // 250 bytes at 1200 baud take ~2.3 seconds to clock out
+        let time = character_time(1200) * 250;
+        assert_eq!(time.as_millis(), 2291);
+    }
+}
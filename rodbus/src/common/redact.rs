@@ -0,0 +1,152 @@
+//! Location of the data values within raw bytes so that they can be redacted in the logs
+//!
+//! Requests and responses aren't distinguished by the bytes themselves, so whenever a PDU could
+//! be either, the bytes that could be values in one of them are redacted.
+
+use std::ops::Range;
+
+use crate::rtu::frame::CRC;
+
+/// Range of the bytes of a PDU, starting with the function code, that carry data values
+pub(crate) fn pdu_values(pdu: &[u8]) -> Range<usize> {
+    let len = pdu.len();
+    let function = match pdu.first() {
+        Some(x) => *x,
+        None => return 0..0,
+    };
+
+    match function {
+        // requests are a start and a count, responses are a byte count and the values
+        0x01..=0x04 => match pdu.get(1) {
+            Some(count) if len == 5 && *count != 3 => len..len,
+            _ => 2.min(len)..len,
+        },
+        // requests and responses are an address and a value
+        0x05 | 0x06 => 3.min(len)..len,
+        // requests are a start, a count, a byte count and the values, responses are a start and a count
+        0x0F | 0x10 => {
+            if len == 5 {
+                len..len
+            } else {
+                6.min(len)..len
+            }
+        }
+        // exceptions only carry the exception code
+        x if x & 0x80 != 0 => len..len,
+        // the layout of other functions isn't known
+        _ => 1..len,
+    }
+}
+
+/// Range of the data values within the bytes of a frame, given the range of its PDU
+pub(crate) fn frame_values(frame: &[u8], pdu: Range<usize>) -> Option<Range<usize>> {
+    let values = pdu_values(frame.get(pdu.clone())?);
+    Some(pdu.start + values.start..pdu.start + values.end)
+}
+
+/// Ranges of the data values within raw bytes of the physical layer
+///
+/// The data is matched against a sequence of complete MBAP frames and against a single RTU frame.
+/// The values of every layout that matches are redacted, and everything is redacted if none does.
+pub(crate) fn phys_values(data: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mbap = mbap_values(data, &mut ranges);
+    let rtu = rtu_values(data, &mut ranges);
+    if !mbap && !rtu {
+        ranges.push(0..data.len());
+    }
+    ranges
+}
+
+fn mbap_values(data: &[u8], ranges: &mut Vec<Range<usize>>) -> bool {
+    const HEADER_LENGTH: usize = crate::tcp::frame::constants::HEADER_LENGTH;
+
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let header = match data.get(pos..pos + HEADER_LENGTH) {
+            Some(x) => x,
+            None => return false,
+        };
+        // protocol id and length field including the unit id
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if header[2..4] != [0, 0] || length < 2 {
+            return false;
+        }
+        let pdu = pos + HEADER_LENGTH..pos + HEADER_LENGTH + length - 1;
+        let values = match data.get(pdu.clone()) {
+            Some(x) => pdu_values(x),
+            None => return false,
+        };
+        found.push(pdu.start + values.start..pdu.start + values.end);
+        pos = pdu.end;
+    }
+
+    ranges.extend(found);
+    !data.is_empty()
+}
+
+fn rtu_values(data: &[u8], ranges: &mut Vec<Range<usize>>) -> bool {
+    // unit id, function code and CRC
+    if data.len() < 4 {
+        return false;
+    }
+    let crc_start = data.len() - 2;
+    let crc = u16::from_le_bytes([data[crc_start], data[crc_start + 1]]);
+    if CRC.checksum(&data[..crc_start]) != crc {
+        return false;
+    }
+    let values = pdu_values(&data[1..crc_start]);
+    ranges.push(1 + values.start..1 + values.end);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_values_of_requests_and_responses() {
+        // read holding registers request and response
+        assert_eq!(pdu_values(&[0x03, 0x00, 0x10, 0x00, 0x02]), 5..5);
+        assert_eq!(pdu_values(&[0x03, 0x04, 0xCA, 0xFE, 0xBE, 0xEF]), 2..6);
+        // a request starting at 0x03XX is redacted as if it were a response with 3 bytes
+        assert_eq!(pdu_values(&[0x01, 0x03, 0x10, 0x00, 0x02]), 2..5);
+        // write single coil
+        assert_eq!(pdu_values(&[0x05, 0x00, 0x01, 0xFF, 0x00]), 3..5);
+        // write multiple registers request and response
+        assert_eq!(
+            pdu_values(&[0x10, 0x00, 0x01, 0x00, 0x01, 0x02, 0xCA, 0xFE]),
+            6..8
+        );
+        assert_eq!(pdu_values(&[0x10, 0x00, 0x01, 0x00, 0x01]), 5..5);
+        // exception and unknown function
+        assert_eq!(pdu_values(&[0x83, 0x02]), 2..2);
+        assert_eq!(pdu_values(&[0x2B, 0x0E, 0x01]), 1..3);
+        assert_eq!(pdu_values(&[]), 0..0);
+    }
+
+    #[test]
+    fn finds_values_of_mbap_frames() {
+        let frames = [
+            // write single register
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x02, 0xCA, 0xFE,
+            // read coils response
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x04, 0x01, 0x01, 0x01, 0x05,
+        ];
+        assert_eq!(phys_values(&frames), vec![10..12, 21..22]);
+        // a segmented frame is redacted entirely
+        assert_eq!(phys_values(&frames[..16]), vec![0..16]);
+    }
+
+    #[test]
+    fn finds_values_of_rtu_frames() {
+        let mut frame = vec![0x01, 0x03, 0x02, 0xCA, 0xFE];
+        let crc = CRC.checksum(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        assert_eq!(phys_values(&frame), vec![3..5]);
+        // a bad CRC is redacted entirely
+        frame[6] ^= 0xFF;
+        assert_eq!(phys_values(&frame), vec![0..7]);
+    }
+}
@@ -5,11 +5,11 @@ use crate::common::traits::Loggable;
 use crate::common::traits::Parse;
 use crate::common::traits::Serialize;
 use crate::error::{InternalError, RequestError};
+#[cfg(feature = "runtime")]
 use crate::server::response::{BitWriter, RegisterWriter};
-use crate::types::{
-    coil_from_u16, coil_to_u16, AddressRange, BitIterator, BitIteratorDisplay, Indexed,
-    RegisterIterator, RegisterIteratorDisplay,
-};
+use crate::types::{coil_from_u16, coil_to_u16, AddressRange, Indexed, ValueDisplay};
+#[cfg(feature = "runtime")]
+use crate::types::{BitIterator, BitIteratorDisplay, RegisterIterator, RegisterIteratorDisplay};
 
 use scursor::{ReadCursor, WriteCursor};
 
@@ -38,7 +38,7 @@ impl Loggable for AddressRange {
     fn log(
         &self,
         payload: &[u8],
-        level: crate::decode::AppDecodeLevel,
+        level: crate::decode::AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         if level.data_headers() {
@@ -51,6 +51,10 @@ impl Loggable for AddressRange {
 
         Ok(())
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        Some(*self)
+    }
 }
 
 impl Serialize for crate::exception::ExceptionCode {
@@ -72,7 +76,7 @@ impl Loggable for Indexed<bool> {
     fn log(
         &self,
         payload: &[u8],
-        level: crate::decode::AppDecodeLevel,
+        level: crate::decode::AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         if level.data_headers() {
@@ -92,11 +96,15 @@ impl Loggable for Indexed<bool> {
             };
             let value = Indexed::new(index, coil_value);
 
-            write!(f, "{value}")?;
+            write!(f, "{}", ValueDisplay::new(level, &value))?;
         }
 
         Ok(())
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        AddressRange::try_from(self.index, 1).ok()
+    }
 }
 
 impl Serialize for Indexed<u16> {
@@ -111,7 +119,7 @@ impl Loggable for Indexed<u16> {
     fn log(
         &self,
         payload: &[u8],
-        level: crate::decode::AppDecodeLevel,
+        level: crate::decode::AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         if level.data_headers() {
@@ -127,11 +135,15 @@ impl Loggable for Indexed<u16> {
             };
             let value = Indexed::new(index, raw_value);
 
-            write!(f, "{value}")?;
+            write!(f, "{}", ValueDisplay::new(level, &value))?;
         }
 
         Ok(())
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        AddressRange::try_from(self.index, 1).ok()
+    }
 }
 
 impl Serialize for &[bool] {
@@ -155,6 +167,7 @@ impl Serialize for &[bool] {
     }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> Serialize for BitWriter<T>
 where
     T: Fn(u16) -> Result<bool, crate::exception::ExceptionCode>,
@@ -192,6 +205,7 @@ where
     }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> Loggable for BitWriter<T>
 where
     T: Fn(u16) -> Result<bool, crate::exception::ExceptionCode>,
@@ -199,7 +213,7 @@ where
     fn log(
         &self,
         payload: &[u8],
-        level: crate::decode::AppDecodeLevel,
+        level: crate::decode::AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         if level.data_headers() {
@@ -216,8 +230,13 @@ where
 
         Ok(())
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        Some(self.range.get())
+    }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> Serialize for RegisterWriter<T>
 where
     T: Fn(u16) -> Result<u16, crate::exception::ExceptionCode>,
@@ -237,6 +256,7 @@ where
     }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> Loggable for RegisterWriter<T>
 where
     T: Fn(u16) -> Result<u16, crate::exception::ExceptionCode>,
@@ -244,7 +264,7 @@ where
     fn log(
         &self,
         payload: &[u8],
-        level: crate::decode::AppDecodeLevel,
+        level: crate::decode::AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         if level.data_headers() {
@@ -261,6 +281,10 @@ where
 
         Ok(())
     }
+
+    fn address_range(&self) -> Option<AddressRange> {
+        Some(self.range.get())
+    }
 }
 
 impl Serialize for &[u16] {
@@ -279,7 +303,17 @@ impl Serialize for &[u16] {
 impl Serialize for WriteMultiple<bool> {
     fn serialize(&self, cursor: &mut WriteCursor) -> Result<(), RequestError> {
         self.range.serialize(cursor)?;
-        self.values.as_slice().serialize(cursor)
+        match &self.packed {
+            // already in the encoding of the request
+            Some(packed) => {
+                cursor.write_u8(calc_bytes_for_bits(self.range.count as usize)?)?;
+                for byte in packed.bytes.iter() {
+                    cursor.write_u8(*byte)?;
+                }
+                Ok(())
+            }
+            None => self.values.as_slice().serialize(cursor),
+        }
     }
 }
 
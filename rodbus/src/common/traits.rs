@@ -1,5 +1,6 @@
-use crate::decode::AppDecodeLevel;
+use crate::decode::AppDecode;
 use crate::error::*;
+use crate::types::AddressRange;
 use crate::ExceptionCode;
 
 use scursor::{ReadCursor, WriteCursor};
@@ -9,22 +10,27 @@ pub(crate) trait Serialize {
 }
 
 pub(crate) trait Loggable {
-    fn log(
-        &self,
-        bytes: &[u8],
-        level: AppDecodeLevel,
-        f: &mut std::fmt::Formatter,
-    ) -> std::fmt::Result;
+    fn log(&self, bytes: &[u8], level: AppDecode, f: &mut std::fmt::Formatter) -> std::fmt::Result;
+
+    /// address range recorded as structured `start` and `count` fields
+    fn address_range(&self) -> Option<AddressRange> {
+        None
+    }
+
+    /// exception code recorded as a structured `exception` field
+    fn exception(&self) -> Option<ExceptionCode> {
+        None
+    }
 }
 
 pub(crate) struct LoggableDisplay<'a, 'b> {
     loggable: &'a dyn Loggable,
     bytes: &'b [u8],
-    level: AppDecodeLevel,
+    level: AppDecode,
 }
 
 impl<'a, 'b> LoggableDisplay<'a, 'b> {
-    pub(crate) fn new(loggable: &'a dyn Loggable, bytes: &'b [u8], level: AppDecodeLevel) -> Self {
+    pub(crate) fn new(loggable: &'a dyn Loggable, bytes: &'b [u8], level: AppDecode) -> Self {
         Self {
             loggable,
             bytes,
@@ -47,9 +53,13 @@ impl Loggable for ExceptionCode {
     fn log(
         &self,
         _bytes: &[u8],
-        _level: AppDecodeLevel,
+        _level: AppDecode,
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         write!(f, "{self:?}")
     }
+
+    fn exception(&self) -> Option<ExceptionCode> {
+        Some(*self)
+    }
 }
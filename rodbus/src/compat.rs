@@ -0,0 +1,263 @@
+//! Deprecated names of the 0.x client API, implemented on top of [`Channel`]
+//!
+//! Code written against the 0.x API can import this module and be ported one file at a time.
+//! Every item is deprecated and the compiler warnings point at its replacement:
+//!
+//! * `channel.create_session(unit_id, timeout)` returns a [`Session`] which passes the same
+//!   [`RequestParam`] to every request. Pass a [`RequestParam`] to the methods of [`Channel`]
+//!   instead.
+//! * [`strategy::default`] and [`strategy::doubling`] are
+//!   [`default_retry_strategy`](crate::default_retry_strategy) and
+//!   [`doubling_retry_strategy`](crate::doubling_retry_strategy).
+//! * [`error::Error`] and the types of [`error::details`] are the error types of the crate root.
+//!
+//! The module only covers the client API. `AddressRange::new` and `WriteMultiple::new` were
+//! replaced by the fallible [`AddressRange::try_from`] and [`WriteMultiple::from`] and have no
+//! alias.
+//!
+//! ```no_run
+//! # #![allow(deprecated)]
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::time::Duration;
+//!
+//! use rodbus::compat::*;
+//! use rodbus::*;
+//!
+//! let channel = spawn_tcp_client_task("127.0.0.1:502".parse()?, 10, strategy::default());
+//! channel.enable().await?;
+//! let mut session = channel.create_session(UnitId::new(1), Duration::from_secs(1));
+//! let coils = session.read_coils(AddressRange::try_from(0, 5).unwrap()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#![allow(deprecated)]
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::client::{Channel, HostAddr, RequestParam, WriteMultiple};
+use crate::decode::DecodeLevel;
+use crate::error::RequestError;
+use crate::retry::RetryStrategy;
+use crate::types::{AddressRange, Indexed, UnitId};
+
+/// Retry strategies of the 0.x API
+pub mod strategy {
+    use std::time::Duration;
+
+    use crate::retry::RetryStrategy;
+
+    /// Return the default [`RetryStrategy`]
+    #[deprecated(since = "2.0.0", note = "Use rodbus::default_retry_strategy")]
+    pub fn default() -> Box<dyn RetryStrategy> {
+        crate::retry::default_retry_strategy()
+    }
+
+    /// Return a [`RetryStrategy`] that doubles on failure up to a maximum value
+    #[deprecated(since = "2.0.0", note = "Use rodbus::doubling_retry_strategy")]
+    pub fn doubling(min: Duration, max: Duration) -> Box<dyn RetryStrategy> {
+        crate::retry::doubling_retry_strategy(min, max)
+    }
+}
+
+/// Error types of the 0.x API
+pub mod error {
+    /// Error returned by the requests of a [`Session`](super::Session)
+    #[deprecated(since = "2.0.0", note = "Use rodbus::RequestError")]
+    pub type Error = crate::error::RequestError;
+
+    /// Types carried by the variants of [`Error`]
+    pub mod details {
+        /// Exception returned by the server
+        #[deprecated(since = "2.0.0", note = "Use rodbus::ExceptionCode")]
+        pub type ExceptionCode = crate::exception::ExceptionCode;
+        /// Invalid range of addresses
+        #[deprecated(since = "2.0.0", note = "Use rodbus::InvalidRange")]
+        pub type InvalidRange = crate::error::InvalidRange;
+        /// Request that can't be sent
+        #[deprecated(since = "2.0.0", note = "Use rodbus::InvalidRequest")]
+        pub type InvalidRequest = crate::error::InvalidRequest;
+        /// Invalid response
+        #[deprecated(since = "2.0.0", note = "Use rodbus::AduParseError")]
+        pub type ADUParseError = crate::error::AduParseError;
+        /// Invalid frame
+        #[deprecated(since = "2.0.0", note = "Use rodbus::FrameParseError")]
+        pub type FrameParseError = crate::error::FrameParseError;
+        /// Error in the library itself
+        #[deprecated(since = "2.0.0", note = "Use rodbus::InternalError")]
+        pub type InternalError = crate::error::InternalError;
+    }
+}
+
+/// Spawn a TCP client task with the 0.x signature
+///
+/// The channel doesn't decode anything and has no listener. Like every channel, it must be
+/// enabled before making requests.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[deprecated(
+    since = "2.0.0",
+    note = "Use rodbus::client::spawn_tcp_client_task with a HostAddr, a DecodeLevel and a listener"
+)]
+pub fn spawn_tcp_client_task(
+    addr: SocketAddr,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+) -> Channel {
+    crate::client::spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        max_queued_requests,
+        retry,
+        DecodeLevel::default(),
+        None,
+    )
+}
+
+/// Adds the `create_session` method of the 0.x API to [`Channel`]
+#[deprecated(
+    since = "2.0.0",
+    note = "Pass a RequestParam to the methods of Channel"
+)]
+pub trait CreateSession {
+    /// Create a [`Session`] that sends requests to `unit_id` with a response timeout
+    fn create_session(&self, unit_id: UnitId, timeout: Duration) -> Session;
+}
+
+impl CreateSession for Channel {
+    fn create_session(&self, unit_id: UnitId, timeout: Duration) -> Session {
+        Session::new(self.clone(), RequestParam::new(unit_id, timeout))
+    }
+}
+
+/// Channel bound to a unit id and a response timeout, as in the 0.x API
+#[deprecated(
+    since = "2.0.0",
+    note = "Use Channel and pass a RequestParam to each request"
+)]
+#[derive(Debug, Clone)]
+pub struct Session {
+    channel: Channel,
+    param: RequestParam,
+}
+
+impl Session {
+    /// Create a session that makes every request on `channel` with `param`
+    pub fn new(channel: Channel, param: RequestParam) -> Self {
+        Self { channel, param }
+    }
+
+    /// Read coils from the server
+    pub async fn read_coils(
+        &mut self,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        self.channel.read_coils(self.param, range).await
+    }
+
+    /// Read discrete inputs from the server
+    pub async fn read_discrete_inputs(
+        &mut self,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<bool>>, RequestError> {
+        self.channel.read_discrete_inputs(self.param, range).await
+    }
+
+    /// Read holding registers from the server
+    pub async fn read_holding_registers(
+        &mut self,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        self.channel.read_holding_registers(self.param, range).await
+    }
+
+    /// Read input registers from the server
+    pub async fn read_input_registers(
+        &mut self,
+        range: AddressRange,
+    ) -> Result<Vec<Indexed<u16>>, RequestError> {
+        self.channel.read_input_registers(self.param, range).await
+    }
+
+    /// Write a single coil on the server
+    pub async fn write_single_coil(
+        &mut self,
+        value: Indexed<bool>,
+    ) -> Result<Indexed<bool>, RequestError> {
+        self.channel.write_single_coil(self.param, value).await
+    }
+
+    /// Write a single register on the server
+    pub async fn write_single_register(
+        &mut self,
+        value: Indexed<u16>,
+    ) -> Result<Indexed<u16>, RequestError> {
+        self.channel.write_single_register(self.param, value).await
+    }
+
+    /// Write multiple contiguous coils on the server
+    pub async fn write_multiple_coils(
+        &mut self,
+        value: WriteMultiple<bool>,
+    ) -> Result<AddressRange, RequestError> {
+        self.channel.write_multiple_coils(self.param, value).await
+    }
+
+    /// Write multiple contiguous registers on the server
+    pub async fn write_multiple_registers(
+        &mut self,
+        value: WriteMultiple<u16>,
+    ) -> Result<AddressRange, RequestError> {
+        self.channel
+            .write_multiple_registers(self.param, value)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::FrameFormat;
+    use crate::server::{DatabaseLayout, MemoryDatabase, RequestHandler, ServerHandlerMap};
+
+    #[tokio::test]
+    async fn session_makes_requests_with_its_parameters() {
+        let (client_io, server_io) = crate::memory::in_memory_pair(256);
+        let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+        database.add_holding_register(0, 1);
+        database.add_holding_register(1, 2);
+        let _server = crate::server::spawn_in_memory_server_task(
+            server_io,
+            FrameFormat::Mbap,
+            ServerHandlerMap::single(UnitId::new(7), database.wrap()),
+            DecodeLevel::default(),
+        );
+
+        let mut client_io = Some(client_io);
+        let channel = crate::client::spawn_in_memory_client_task(
+            Box::new(move || client_io.take()),
+            FrameFormat::Mbap,
+            1,
+            strategy::default(),
+            DecodeLevel::default(),
+            None,
+        );
+        channel.enable().await.unwrap();
+
+        let mut session = channel.create_session(UnitId::new(7), Duration::from_secs(1));
+        let range = AddressRange::try_from(0, 2).unwrap();
+        let result: Result<_, error::Error> = session.read_holding_registers(range).await;
+        assert_eq!(result, Ok(vec![Indexed::new(0, 1), Indexed::new(1, 2)]));
+        assert_eq!(
+            session.write_single_register(Indexed::new(1, 5)).await,
+            Ok(Indexed::new(1, 5))
+        );
+
+        // requests to another unit id aren't answered
+        let mut other = channel.create_session(UnitId::new(8), Duration::from_millis(50));
+        assert_eq!(
+            other.read_holding_registers(range).await,
+            Err(RequestError::ResponseTimeout)
+        );
+    }
+}
@@ -7,6 +7,28 @@ pub(crate) mod coil {
 }
 
 /// Limits of request sizes
+///
+/// The specification limits the count of each request so that the request and its response fit
+/// in a frame. Clients reject requests that exceed these limits before they are sent, and servers
+/// answer them with [`ExceptionCode::IllegalDataValue`](crate::ExceptionCode::IllegalDataValue).
+///
+/// | Request                                | Function codes | Maximum count |
+/// |----------------------------------------|----------------|---------------|
+/// | read coils or discrete inputs          | 0x01, 0x02     | 2000          |
+/// | read holding or input registers        | 0x03, 0x04     | 125           |
+/// | write multiple coils                   | 0x0F           | 1968          |
+/// | write multiple registers               | 0x10           | 123           |
+///
+/// The write single coil and write single register requests always contain a single value.
+///
+/// ```
+/// use rodbus::constants::limits::*;
+///
+/// assert_eq!(MAX_READ_COILS_COUNT, 2000);
+/// assert_eq!(MAX_READ_REGISTERS_COUNT, 125);
+/// assert_eq!(MAX_WRITE_COILS_COUNT, 1968);
+/// assert_eq!(MAX_WRITE_REGISTERS_COUNT, 123);
+/// ```
 pub mod limits {
     /// Maximum count allowed in a read coils/discrete inputs request
     pub const MAX_READ_COILS_COUNT: u16 = 0x07D0;
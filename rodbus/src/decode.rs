@@ -1,5 +1,6 @@
 /// Controls the decoding of transmitted and received data at the application, frame, and physical layer
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DecodeLevel {
     /// Controls decoding of the application layer (PDU)
     pub app: AppDecodeLevel,
@@ -7,12 +8,24 @@ pub struct DecodeLevel {
     pub frame: FrameDecodeLevel,
     /// Controls the logging of physical layer read/write
     pub physical: PhysDecodeLevel,
+    /// Controls how raw bytes are printed by [`FrameDecodeLevel::Payload`] and [`PhysDecodeLevel::Data`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bytes: BytesFormat,
+    /// Replace the data values with `**` at every level, keeping the addresses, counts,
+    /// function codes, and CRCs visible
+    ///
+    /// Registers and coils are redacted in the decoded PDUs, and their bytes are redacted in the
+    /// payload of frames and in the data of the physical layer. Physical layer data that can't be
+    /// matched to complete frames is redacted entirely.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub redact_values: bool,
 }
 
 /// Controls how transmitted and received message at the application layer are decoded at the INFO log level
 ///
 /// Application-layer messages are referred to as Protocol Data Units (PDUs) in the specification.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AppDecodeLevel {
     /// Decode nothing
     Nothing,
@@ -31,6 +44,7 @@ pub enum AppDecodeLevel {
 ///
 /// On TCP, this is the MBAP decoding. On serial, this controls the serial line PDU.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrameDecodeLevel {
     /// Decode nothing
     Nothing,
@@ -42,6 +56,7 @@ pub enum FrameDecodeLevel {
 
 /// Controls how data transmitted at the physical layer (TCP, serial, etc) is logged
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PhysDecodeLevel {
     /// Log nothing
     Nothing,
@@ -51,6 +66,47 @@ pub enum PhysDecodeLevel {
     Data,
 }
 
+/// Layout of the raw bytes printed by the frame and physical layer decoding
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BytesStyle {
+    /// Hexadecimal bytes separated by spaces, 18 bytes per line (the default)
+    #[default]
+    SpacedHex,
+    /// Hexadecimal bytes without separators on a single line
+    CompactHex,
+    /// 16 bytes per line preceded by the offset and followed by their ASCII representation
+    HexDump,
+}
+
+/// Controls how raw bytes are printed by the frame and physical layer decoding
+///
+/// The default prints every byte as [`BytesStyle::SpacedHex`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BytesFormat {
+    /// Layout of the bytes
+    pub style: BytesStyle,
+    /// Maximum number of bytes printed, followed by an ellipsis and the total length if exceeded
+    ///
+    /// `None` prints every byte
+    pub max_bytes: Option<usize>,
+}
+
+impl BytesFormat {
+    /// Change the layout of the bytes
+    pub fn style(mut self, style: BytesStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Change the maximum number of bytes printed
+    pub fn max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
 impl DecodeLevel {
     /// construct a `DecodeLevel` with nothing enabled
     pub fn nothing() -> Self {
@@ -63,6 +119,8 @@ impl DecodeLevel {
             app: pdu,
             frame: adu,
             physical,
+            bytes: BytesFormat::default(),
+            redact_values: false,
         }
     }
 
@@ -83,6 +141,25 @@ impl DecodeLevel {
         self.physical = level;
         self
     }
+
+    /// Change how raw bytes are printed
+    pub fn bytes(mut self, format: BytesFormat) -> Self {
+        self.bytes = format;
+        self
+    }
+
+    /// Change whether data values are redacted
+    pub fn redact_values(mut self, redact_values: bool) -> Self {
+        self.redact_values = redact_values;
+        self
+    }
+
+    pub(crate) fn app_decode(&self) -> AppDecode {
+        AppDecode {
+            level: self.app,
+            redact_values: self.redact_values,
+        }
+    }
 }
 
 impl Default for DecodeLevel {
@@ -91,6 +168,8 @@ impl Default for DecodeLevel {
             app: AppDecodeLevel::Nothing,
             frame: FrameDecodeLevel::Nothing,
             physical: PhysDecodeLevel::Nothing,
+            bytes: BytesFormat::default(),
+            redact_values: false,
         }
     }
 }
@@ -101,10 +180,46 @@ impl From<AppDecodeLevel> for DecodeLevel {
             app: pdu,
             frame: FrameDecodeLevel::Nothing,
             physical: PhysDecodeLevel::Nothing,
+            bytes: BytesFormat::default(),
+            redact_values: false,
         }
     }
 }
 
+/// Application decode level along with the redaction of data values
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AppDecode {
+    pub(crate) level: AppDecodeLevel,
+    pub(crate) redact_values: bool,
+}
+
+impl From<AppDecodeLevel> for AppDecode {
+    fn from(level: AppDecodeLevel) -> Self {
+        Self {
+            level,
+            redact_values: false,
+        }
+    }
+}
+
+impl AppDecode {
+    pub(crate) fn enabled(&self) -> bool {
+        self.level.enabled()
+    }
+
+    pub(crate) fn header(&self) -> bool {
+        self.level.header()
+    }
+
+    pub(crate) fn data_headers(&self) -> bool {
+        self.level.data_headers()
+    }
+
+    pub(crate) fn data_values(&self) -> bool {
+        self.level.data_values()
+    }
+}
+
 impl AppDecodeLevel {
     pub(crate) fn enabled(&self) -> bool {
         self.header()
@@ -181,3 +296,59 @@ impl PhysDecodeLevel {
         }
     }
 }
+
+/// Captures the log output of the current thread so that tests can check what is decoded
+#[cfg(test)]
+pub(crate) mod test_log {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    pub(crate) struct LogCapture(Arc<Mutex<Vec<u8>>>);
+
+    impl LogCapture {
+        /// Capture the logs of the current thread until the guard is dropped
+        ///
+        /// Tasks spawned by a current-thread runtime log to the same thread.
+        pub(crate) fn install(&self) -> tracing::subscriber::DefaultGuard {
+            self.install_with_level(tracing::Level::INFO)
+        }
+
+        /// Same as `install`, but also captures the events more verbose than info up to `level`
+        pub(crate) fn install_with_level(
+            &self,
+            level: tracing::Level,
+        ) -> tracing::subscriber::DefaultGuard {
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(level)
+                .with_writer(self.clone())
+                .with_ansi(false)
+                .finish();
+            tracing::subscriber::set_default(subscriber)
+        }
+
+        /// Take the output captured so far
+        pub(crate) fn take(&self) -> String {
+            let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+            String::from_utf8(bytes).unwrap()
+        }
+    }
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogCapture {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+}
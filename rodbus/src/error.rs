@@ -1,7 +1,7 @@
 use scursor::WriteError;
 
 /// The task processing requests has terminated
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Shutdown;
 
 impl std::error::Error for Shutdown {}
@@ -13,10 +13,18 @@ impl std::fmt::Display for Shutdown {
 }
 
 /// Top level error type for the client API
+///
+/// New variants may be added in minor releases. Applications deciding whether to retry
+/// a request should prefer the predicates such as [`RequestError::is_timeout`] over
+/// matching on every variant.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum RequestError {
     /// An I/O error occurred
-    Io(::std::io::ErrorKind),
+    ///
+    /// With the `serde` feature, the kind is serialized using its name, e.g. "ConnectionRefused"
+    Io(#[cfg_attr(feature = "serde", serde(with = "io_error_kind"))] ::std::io::ErrorKind),
     /// A Modbus exception was returned by the server
     Exception(crate::exception::ExceptionCode),
     /// Request was not performed because it is invalid
@@ -35,6 +43,119 @@ pub enum RequestError {
     NoConnection,
     /// Task processing requests has been shutdown
     Shutdown,
+    /// Request failed on every attempt permitted by its retry policy
+    RetriesExhausted(RetriesExhausted),
+    /// Request was rejected because the request queue of the channel was full
+    ///
+    /// Only returned by the non-blocking FFI channel, [`Channel`](crate::client::Channel) waits for
+    /// room in the queue instead.
+    TooManyRequests,
+    /// Request was removed from the queue by [`Channel::purge_queue`](crate::client::Channel::purge_queue)
+    /// before it was sent
+    Cancelled,
+    /// Value read back by a verified write such as
+    /// [`Channel::write_single_register_verified`](crate::client::Channel::write_single_register_verified)
+    /// differs from the value that was written
+    VerifyFailed(VerifyError),
+}
+
+/// Value read back after a verified write that differs from the value that was written
+///
+/// Coils are reported as 0 or 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyError {
+    /// Index of the first point whose value differs
+    pub index: u16,
+    /// Value that was written
+    pub expected: u16,
+    /// Value that was read back
+    pub actual: u16,
+}
+
+impl std::error::Error for VerifyError {}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "value read back at index {} is {:#06X} instead of {:#06X}",
+            self.index, self.actual, self.expected
+        )
+    }
+}
+
+/// Final error of a request that was retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetriesExhausted {
+    /// Number of times the request was sent
+    pub attempts: u16,
+    /// Error of the last attempt
+    pub cause: RetryCause,
+}
+
+/// Errors that cause a request to be retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RetryCause {
+    /// Timeout occurred before receiving a response from the server
+    ResponseTimeout,
+    /// Unable to parse a frame from the server
+    BadFrame(FrameParseError),
+}
+
+impl std::fmt::Display for RetryCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            RetryCause::ResponseTimeout => f.write_str("response timeout"),
+            RetryCause::BadFrame(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<RetryCause> for RequestError {
+    fn from(cause: RetryCause) -> Self {
+        match cause {
+            RetryCause::ResponseTimeout => RequestError::ResponseTimeout,
+            RetryCause::BadFrame(err) => RequestError::BadFrame(err),
+        }
+    }
+}
+
+impl RequestError {
+    /// True if the request timed out, either waiting for the response or writing the request
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            RequestError::ResponseTimeout
+                | RequestError::Io(std::io::ErrorKind::TimedOut)
+                | RequestError::RetriesExhausted(RetriesExhausted {
+                    cause: RetryCause::ResponseTimeout,
+                    ..
+                })
+        )
+    }
+
+    /// True if the request failed because of the connection to the server
+    ///
+    /// The connection is re-established automatically, so these errors are usually transient
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, RequestError::Io(_) | RequestError::NoConnection)
+    }
+
+    /// True if the server responded with a Modbus exception
+    pub fn is_exception(&self) -> bool {
+        matches!(self, RequestError::Exception(_))
+    }
+
+    /// Exception returned by the server, if any
+    pub fn exception_code(&self) -> Option<crate::exception::ExceptionCode> {
+        match self {
+            RequestError::Exception(ex) => Some(*ex),
+            _ => None,
+        }
+    }
 }
 
 impl std::error::Error for RequestError {}
@@ -51,6 +172,12 @@ impl std::fmt::Display for RequestError {
             RequestError::ResponseTimeout => f.write_str("response timeout"),
             RequestError::NoConnection => f.write_str("no connection to server"),
             RequestError::Shutdown => f.write_str("channel shutdown"),
+            RequestError::RetriesExhausted(x) => {
+                write!(f, "{} after {} attempt(s)", x.cause, x.attempts)
+            }
+            RequestError::TooManyRequests => f.write_str("request queue is full"),
+            RequestError::Cancelled => f.write_str("request cancelled"),
+            RequestError::VerifyFailed(err) => err.fmt(f),
         }
     }
 }
@@ -80,6 +207,24 @@ impl From<InvalidRequest> for RequestError {
     }
 }
 
+impl From<crate::profile::ProfileError> for InvalidRequest {
+    fn from(err: crate::profile::ProfileError) -> Self {
+        InvalidRequest::OutOfProfile(err)
+    }
+}
+
+impl From<crate::profile::ProfileError> for RequestError {
+    fn from(err: crate::profile::ProfileError) -> Self {
+        RequestError::BadRequest(err.into())
+    }
+}
+
+impl From<VerifyError> for RequestError {
+    fn from(err: VerifyError) -> Self {
+        RequestError::VerifyFailed(err)
+    }
+}
+
 impl From<InternalError> for RequestError {
     fn from(err: InternalError) -> Self {
         RequestError::Internal(err)
@@ -110,18 +255,21 @@ impl From<InvalidRange> for InvalidRequest {
     }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for RequestError {
     fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
         RequestError::Shutdown
     }
 }
 
+#[cfg(feature = "runtime")]
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for Shutdown {
     fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
         Shutdown
     }
 }
 
+#[cfg(feature = "runtime")]
 impl From<tokio::sync::oneshot::error::RecvError> for RequestError {
     fn from(_: tokio::sync::oneshot::error::RecvError) -> Self {
         RequestError::Shutdown
@@ -148,6 +296,7 @@ impl From<scursor::TrailingBytes> for RequestError {
 
 /// Errors that can be produced when validating start/count
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InvalidRange {
     /// Count of zero not allowed
     CountOfZero,
@@ -159,6 +308,7 @@ pub enum InvalidRange {
 
 /// Errors that indicate faulty logic in the library itself if they occur
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InternalError {
     /// Insufficient space for write operation
     InsufficientWriteSpace(usize, usize), // written vs remaining space
@@ -201,9 +351,13 @@ impl std::fmt::Display for InternalError {
 
 /// Errors that occur while parsing a frame off a stream (TCP or serial)
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum FrameParseError {
     /// Received TCP frame with the length field set to zero
     MbapLengthZero,
+    /// Received TCP frame with a length field too small to contain a function code
+    MbapLengthTooSmall(usize),
     /// Received TCP or RTU frame with length that exceeds max allowed size
     FrameLengthTooBig(usize, usize), // actual size and the maximum size
     /// Received TCP frame within non-Modbus protocol id
@@ -212,6 +366,10 @@ pub enum FrameParseError {
     UnknownFunctionCode(u8),
     /// RTU CRC validation failed
     CrcValidationFailure(u16, u16), // received CRC, expected CRC
+    /// The remainder of a partially received frame did not arrive within the frame timeout
+    PartialFrameTimeout,
+    /// Received the specified number of consecutive responses with an unexpected transaction id
+    StaleResponses(usize),
 }
 
 impl std::error::Error for FrameParseError {}
@@ -222,6 +380,10 @@ impl std::fmt::Display for FrameParseError {
             FrameParseError::MbapLengthZero => {
                 f.write_str("Received TCP frame with the length field set to zero")
             }
+            FrameParseError::MbapLengthTooSmall(size) => write!(
+                f,
+                "Received TCP frame with length ({size}) too small to contain a function code"
+            ),
             FrameParseError::FrameLengthTooBig(size, max) => write!(
                 f,
                 "Received TCP frame with length ({size}) that exceeds max allowed size ({max})"
@@ -238,12 +400,21 @@ impl std::fmt::Display for FrameParseError {
                     "Received incorrect CRC value {received:#06X}, expected {expected:#06X}"
                 )
             }
+            FrameParseError::PartialFrameTimeout => {
+                f.write_str("Timeout waiting for the remainder of a partially received frame")
+            }
+            FrameParseError::StaleResponses(count) => write!(
+                f,
+                "Received {count} consecutive responses with an unexpected transaction id"
+            ),
         }
     }
 }
 
 /// Errors that occur while parsing requests and responses
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum AduParseError {
     /// Response is too short to be valid
     InsufficientBytes,
@@ -257,6 +428,10 @@ pub enum AduParseError {
     UnknownResponseFunction(u8, u8, u8), // actual, expected, expected error
     /// Bad value for the coil state
     UnknownCoilState(u16),
+    /// Byte count of a read response doesn't match the requested quantity
+    ByteCountMismatch(usize, usize), // actual / expected
+    /// Function code of an exception response doesn't have its most significant bit set
+    NotAnException(u8),
 }
 
 impl std::error::Error for AduParseError {}
@@ -283,12 +458,22 @@ impl std::fmt::Display for AduParseError {
                 f,
                 "received coil state with unspecified value: 0x{value:04X}"
             ),
+            AduParseError::ByteCountMismatch(actual, expected) => write!(
+                f,
+                "response byte count ({actual}) doesn't match the {expected} bytes required by the requested quantity"
+            ),
+            AduParseError::NotAnException(function) => write!(
+                f,
+                "function code 0x{function:02X} is not the function code of an exception response"
+            ),
         }
     }
 }
 
 /// Errors that result because of bad request parameter
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum InvalidRequest {
     /// Request contained an invalid range
     BadRange(InvalidRange),
@@ -296,6 +481,10 @@ pub enum InvalidRequest {
     CountTooBigForU16(usize),
     /// Count too big for specific request
     CountTooBigForType(u16, u16),
+    /// Request lies outside of the [`DeviceProfile`](crate::DeviceProfile) it was checked against
+    OutOfProfile(crate::profile::ProfileError),
+    /// Packed values contained fewer bytes (first value) than the count requires (second value)
+    NotEnoughBytes(usize, usize),
 }
 
 impl std::error::Error for InvalidRequest {}
@@ -313,6 +502,11 @@ impl std::fmt::Display for InvalidRequest {
                 f,
                 "the request count of {count} exceeds maximum allowed count of {max} for this type"
             ),
+            InvalidRequest::OutOfProfile(err) => write!(f, "{err}"),
+            InvalidRequest::NotEnoughBytes(actual, required) => write!(
+                f,
+                "{actual} byte(s) of packed values is less than the {required} byte(s) required by the count"
+            ),
         }
     }
 }
@@ -332,3 +526,79 @@ impl std::fmt::Display for InvalidRange {
         }
     }
 }
+
+#[cfg(feature = "serde")]
+mod io_error_kind {
+    use std::io::ErrorKind;
+
+    // kinds that round-trip, anything else is deserialized as ErrorKind::Other
+    const KINDS: &[ErrorKind] = &[
+        ErrorKind::NotFound,
+        ErrorKind::PermissionDenied,
+        ErrorKind::ConnectionRefused,
+        ErrorKind::ConnectionReset,
+        ErrorKind::ConnectionAborted,
+        ErrorKind::NotConnected,
+        ErrorKind::AddrInUse,
+        ErrorKind::AddrNotAvailable,
+        ErrorKind::BrokenPipe,
+        ErrorKind::AlreadyExists,
+        ErrorKind::WouldBlock,
+        ErrorKind::InvalidInput,
+        ErrorKind::InvalidData,
+        ErrorKind::TimedOut,
+        ErrorKind::WriteZero,
+        ErrorKind::Interrupted,
+        ErrorKind::Unsupported,
+        ErrorKind::UnexpectedEof,
+        ErrorKind::OutOfMemory,
+        ErrorKind::Other,
+    ];
+
+    pub(super) fn serialize<S>(kind: &ErrorKind, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&format_args!("{kind:?}"))
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<ErrorKind, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name: std::borrow::Cow<str> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(KINDS
+            .iter()
+            .find(|x| format!("{x:?}") == name)
+            .copied()
+            .unwrap_or(ErrorKind::Other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExceptionCode;
+
+    #[test]
+    fn predicates_classify_errors() {
+        let timeout = RequestError::ResponseTimeout;
+        assert!(timeout.is_timeout());
+        assert!(!timeout.is_connection_error());
+
+        let write_timeout = RequestError::Io(std::io::ErrorKind::TimedOut);
+        assert!(write_timeout.is_timeout());
+        assert!(write_timeout.is_connection_error());
+
+        assert!(RequestError::NoConnection.is_connection_error());
+        assert!(!RequestError::Shutdown.is_connection_error());
+
+        let exception = RequestError::Exception(ExceptionCode::ServerDeviceBusy);
+        assert!(exception.is_exception());
+        assert_eq!(
+            exception.exception_code(),
+            Some(ExceptionCode::ServerDeviceBusy)
+        );
+        assert_eq!(RequestError::ResponseTimeout.exception_code(), None);
+    }
+}
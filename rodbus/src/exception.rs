@@ -1,5 +1,16 @@
+use crate::common::frame::FunctionField;
+use crate::error::{AduParseError, RequestError};
+use crate::FunctionCode;
+
 /// Exception codes defined in the Modbus specification
+///
+/// With the `serde` feature, exception codes are serialized as their numeric value
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "u8", into = "u8")
+)]
 pub enum ExceptionCode {
     /// The function code received in the query is not an allowable action for the server
     IllegalFunction,
@@ -38,8 +49,11 @@ pub enum ExceptionCode {
     Unknown(u8),
 }
 
-impl From<u8> for ExceptionCode {
-    fn from(value: u8) -> Self {
+impl ExceptionCode {
+    /// Create an exception code from its value on the wire
+    ///
+    /// Values not defined in the standard map to [`ExceptionCode::Unknown`]
+    pub const fn from_u8(value: u8) -> Self {
         match value {
             crate::constants::exceptions::ILLEGAL_FUNCTION => ExceptionCode::IllegalFunction,
             crate::constants::exceptions::ILLEGAL_DATA_ADDRESS => ExceptionCode::IllegalDataAddress,
@@ -59,11 +73,10 @@ impl From<u8> for ExceptionCode {
             _ => ExceptionCode::Unknown(value),
         }
     }
-}
 
-impl From<ExceptionCode> for u8 {
-    fn from(ex: ExceptionCode) -> Self {
-        match ex {
+    /// Value of the exception code on the wire
+    pub const fn as_u8(self) -> u8 {
+        match self {
             ExceptionCode::IllegalFunction => crate::constants::exceptions::ILLEGAL_FUNCTION,
             ExceptionCode::IllegalDataAddress => crate::constants::exceptions::ILLEGAL_DATA_ADDRESS,
             ExceptionCode::IllegalDataValue => crate::constants::exceptions::ILLEGAL_DATA_VALUE,
@@ -84,8 +97,72 @@ impl From<ExceptionCode> for u8 {
     }
 }
 
+impl From<u8> for ExceptionCode {
+    fn from(value: u8) -> Self {
+        Self::from_u8(value)
+    }
+}
+
+impl From<ExceptionCode> for u8 {
+    fn from(ex: ExceptionCode) -> Self {
+        ex.as_u8()
+    }
+}
+
 impl std::error::Error for ExceptionCode {}
 
+/// Exception response PDU, i.e. the function code with its most significant bit set followed by
+/// the exception code
+///
+/// Useful to answer on behalf of another device, e.g. in a gateway, or to inspect captured
+/// frames. The PDU doesn't include the MBAP header or the RTU address and CRC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExceptionResponse {
+    /// Function code of the request, without the exception bit
+    pub function: u8,
+    /// Exception code of the response
+    pub code: ExceptionCode,
+}
+
+impl ExceptionResponse {
+    /// Length of the PDU in bytes
+    pub const LENGTH: usize = 2;
+
+    /// Create an exception response to a request with the specified function code
+    pub fn new(function: u8, code: ExceptionCode) -> Self {
+        Self { function, code }
+    }
+
+    /// Write the PDU to the start of `buffer`, returning the number of bytes written
+    pub fn to_pdu(&self, buffer: &mut [u8]) -> Result<usize, RequestError> {
+        let mut cursor = scursor::WriteCursor::new(buffer);
+        cursor.write_u8(self.function_field().get_value())?;
+        cursor.write_u8(self.code.as_u8())?;
+        Ok(cursor.position())
+    }
+
+    /// Parse a complete PDU
+    pub fn parse_pdu(pdu: &[u8]) -> Result<Self, AduParseError> {
+        match pdu {
+            [function, code] => {
+                if function & 0x80 == 0 {
+                    return Err(AduParseError::NotAnException(*function));
+                }
+                Ok(Self::new(function & 0x7F, ExceptionCode::from_u8(*code)))
+            }
+            [] | [_] => Err(AduParseError::InsufficientBytes),
+            _ => Err(AduParseError::TrailingBytes(pdu.len() - Self::LENGTH)),
+        }
+    }
+
+    pub(crate) fn function_field(&self) -> FunctionField {
+        match FunctionCode::get(self.function) {
+            Some(function) => FunctionField::Exception(function),
+            None => FunctionField::unknown(self.function),
+        }
+    }
+}
+
 impl std::fmt::Display for ExceptionCode {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
@@ -102,3 +179,70 @@ impl std::fmt::Display for ExceptionCode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_codes_round_trip() {
+        let codes = [
+            (0x01, ExceptionCode::IllegalFunction),
+            (0x02, ExceptionCode::IllegalDataAddress),
+            (0x03, ExceptionCode::IllegalDataValue),
+            (0x04, ExceptionCode::ServerDeviceFailure),
+            (0x05, ExceptionCode::Acknowledge),
+            (0x06, ExceptionCode::ServerDeviceBusy),
+            (0x08, ExceptionCode::MemoryParityError),
+            (0x0A, ExceptionCode::GatewayPathUnavailable),
+            (0x0B, ExceptionCode::GatewayTargetDeviceFailedToRespond),
+        ];
+
+        for (value, code) in codes {
+            assert_eq!(ExceptionCode::from_u8(value), code);
+            assert_eq!(code.as_u8(), value);
+            assert_eq!(u8::from(ExceptionCode::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn formats_and_parses_exception_responses() {
+        let mut buffer = [0; 4];
+        let response = ExceptionResponse::new(0x03, ExceptionCode::IllegalDataAddress);
+        assert_eq!(response.to_pdu(&mut buffer), Ok(2));
+        assert_eq!(&buffer[..2], &[0x83, 0x02]);
+        assert_eq!(ExceptionResponse::parse_pdu(&buffer[..2]), Ok(response));
+
+        // function codes that rodbus doesn't implement
+        let response = ExceptionResponse::new(0x2B, ExceptionCode::Unknown(0x42));
+        assert_eq!(response.to_pdu(&mut buffer), Ok(2));
+        assert_eq!(&buffer[..2], &[0xAB, 0x42]);
+        assert_eq!(ExceptionResponse::parse_pdu(&buffer[..2]), Ok(response));
+
+        assert!(response.to_pdu(&mut buffer[..1]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_exception_responses() {
+        assert_eq!(
+            ExceptionResponse::parse_pdu(&[0x83]),
+            Err(AduParseError::InsufficientBytes)
+        );
+        assert_eq!(
+            ExceptionResponse::parse_pdu(&[0x83, 0x02, 0x00]),
+            Err(AduParseError::TrailingBytes(1))
+        );
+        assert_eq!(
+            ExceptionResponse::parse_pdu(&[0x03, 0x02]),
+            Err(AduParseError::NotAnException(0x03))
+        );
+    }
+
+    #[test]
+    fn undefined_codes_round_trip_as_unknown() {
+        for value in [0x00, 0x07, 0x09, 0x0C, 0xFF] {
+            assert_eq!(ExceptionCode::from_u8(value), ExceptionCode::Unknown(value));
+            assert_eq!(ExceptionCode::Unknown(value).as_u8(), value);
+        }
+    }
+}
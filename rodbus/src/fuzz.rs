@@ -0,0 +1,98 @@
+//! Entry points for the cargo-fuzz targets in `rodbus/fuzz`
+//!
+//! These functions are not part of the public API and are not covered by semver.
+
+use crate::common::buffer::ReadBuffer;
+use crate::common::frame::FrameParser;
+use crate::common::function::FunctionCode;
+use crate::decode::{AppDecodeLevel, DecodeLevel};
+use crate::rtu::frame::RtuParser;
+use crate::server::request::{Request, RequestDisplay};
+use crate::tcp::frame::MbapParser;
+
+use scursor::ReadCursor;
+
+/// Parse arbitrary bytes as a stream of MBAP frames, decoding the PDU of each one as a request
+pub fn mbap(data: &[u8]) {
+    parse_stream(FrameParser::Tcp(MbapParser::new()), data, parse_request);
+}
+
+/// Parse arbitrary bytes as a stream of RTU requests, decoding the PDU of each one
+pub fn rtu_request(data: &[u8]) {
+    parse_stream(
+        FrameParser::Rtu(RtuParser::new_request_parser()),
+        data,
+        parse_request,
+    );
+}
+
+/// Parse arbitrary bytes as a stream of RTU responses
+pub fn rtu_response(data: &[u8]) {
+    parse_stream(
+        FrameParser::Rtu(RtuParser::new_response_parser()),
+        data,
+        |_| {},
+    );
+}
+
+fn parse_stream(mut parser: FrameParser, mut data: &[u8], on_frame: fn(&[u8])) {
+    let mut buffer = ReadBuffer::new();
+    loop {
+        let count = buffer.extend(data);
+        data = &data[count..];
+
+        loop {
+            match parser.parse(&mut buffer, DecodeLevel::nothing()) {
+                Ok(Some(frame)) => on_frame(frame.payload()),
+                Ok(None) => break,
+                Err(_) => {
+                    // same recovery as FrameErrorPolicy::Discard
+                    parser.reset();
+                    buffer.clear();
+                }
+            }
+        }
+
+        if data.is_empty() {
+            return;
+        }
+
+        // the parser must never wait for more data than the buffer can hold
+        assert!(count > 0, "parser stalled with a full buffer");
+    }
+}
+
+fn parse_request(payload: &[u8]) {
+    let mut cursor = ReadCursor::new(payload);
+    let function = match cursor.read_u8().ok().and_then(FunctionCode::get) {
+        Some(x) => x,
+        None => return,
+    };
+    if let Ok(request) = Request::parse(function, &mut cursor) {
+        let _ = RequestDisplay::new(AppDecodeLevel::DataValues.into(), &request).to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_from_garbage_between_frames() {
+        let mut data = vec![0x00, 0x07, 0xCA, 0xFE, 0x00, 0x01, 0x2A];
+        data.extend_from_slice(&[
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01,
+        ]);
+        mbap(&data);
+        rtu_request(&data);
+        rtu_response(&data);
+    }
+
+    #[test]
+    fn handles_input_larger_than_the_buffer() {
+        let data: Vec<u8> = (0..4096).map(|x| x as u8).collect();
+        mbap(&data);
+        rtu_request(&data);
+        rtu_response(&data);
+    }
+}
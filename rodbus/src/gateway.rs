@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use crate::client::{Channel, RequestParam, WriteMultiple};
+use crate::exception::ExceptionCode;
+use crate::maybe_async::MaybeAsync;
+use crate::server::{AsyncRequest, AsyncResponse, RequestHandler};
+use crate::types::{AddressRange, Indexed, UnitId};
+use crate::RequestError;
+
+/// Server handler that forwards every request to a client channel, e.g. to build a TCP to RTU gateway
+///
+/// Requests are processed asynchronously, see [`RequestHandler::process_async`]. Errors of the
+/// forwarded request are returned to the client as exceptions:
+///
+/// * exceptions returned by the target device are passed through unchanged
+/// * timeouts and invalid responses become [`ExceptionCode::GatewayTargetDeviceFailedToRespond`]
+/// * connection errors become [`ExceptionCode::GatewayPathUnavailable`]
+///
+/// By default every request is forwarded to the unit id of the [`RequestParam`]. Install a
+/// [`UnitIdMap`] to select the outgoing unit id from the unit id of each request instead.
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    channel: Channel,
+    param: RequestParam,
+    unit_ids: Option<UnitIdMap>,
+}
+
+/// Translates the unit ids of requests received by a [`Bridge`] to the unit ids of the target devices
+///
+/// The bridge must receive the requests for every unit id in the map, e.g. by registering it as
+/// the only handler with [`UnitIdRouting::AnyToSingle`](crate::server::UnitIdRouting::AnyToSingle).
+/// Responses always echo the unit id of the request. Requests for unit ids that are not in the
+/// map are answered with [`ExceptionCode::GatewayTargetDeviceFailedToRespond`]. Broadcast requests
+/// are only forwarded if [`UnitId::broadcast`] is in the map.
+///
+/// The map of a running bridge can be modified with
+/// [`UpdateHandler::update`](crate::server::UpdateHandler::update) and [`Bridge::unit_ids_mut`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitIdMap {
+    map: BTreeMap<UnitId, UnitId>,
+}
+
+impl UnitIdMap {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward requests for `incoming` to `outgoing`, returning the previous outgoing unit id
+    pub fn insert(&mut self, incoming: UnitId, outgoing: UnitId) -> Option<UnitId> {
+        self.map.insert(incoming, outgoing)
+    }
+
+    /// Stop forwarding requests for `incoming`, returning its outgoing unit id
+    pub fn remove(&mut self, incoming: UnitId) -> Option<UnitId> {
+        self.map.remove(&incoming)
+    }
+
+    /// Outgoing unit id for requests addressed to `incoming`
+    pub fn get(&self, incoming: UnitId) -> Option<UnitId> {
+        self.map.get(&incoming).copied()
+    }
+}
+
+impl FromIterator<(UnitId, UnitId)> for UnitIdMap {
+    fn from_iter<I: IntoIterator<Item = (UnitId, UnitId)>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Bridge {
+    /// Create a bridge that forwards requests on `channel` using `param`, including its unit id
+    pub fn new(channel: Channel, param: RequestParam) -> Self {
+        Self {
+            channel,
+            param,
+            unit_ids: None,
+        }
+    }
+
+    /// Create a bridge that forwards requests on `channel` to the unit ids selected by `unit_ids`
+    ///
+    /// The unit id of `param` is ignored.
+    pub fn with_unit_ids(channel: Channel, param: RequestParam, unit_ids: UnitIdMap) -> Self {
+        Self {
+            channel,
+            param,
+            unit_ids: Some(unit_ids),
+        }
+    }
+
+    /// Install or remove the [`UnitIdMap`]
+    pub fn set_unit_ids(&mut self, unit_ids: Option<UnitIdMap>) {
+        self.unit_ids = unit_ids;
+    }
+
+    /// Mutable reference to the installed [`UnitIdMap`], if any
+    pub fn unit_ids_mut(&mut self) -> Option<&mut UnitIdMap> {
+        self.unit_ids.as_mut()
+    }
+}
+
+impl RequestHandler for Bridge {
+    fn process_async(
+        &mut self,
+        unit_id: UnitId,
+        request: AsyncRequest,
+    ) -> Option<MaybeAsync<Result<AsyncResponse, ExceptionCode>>> {
+        let mut param = self.param;
+        if let Some(unit_ids) = &self.unit_ids {
+            match unit_ids.get(unit_id) {
+                Some(outgoing) => param.id = outgoing,
+                None => {
+                    tracing::warn!("no target device for unit id: {}", unit_id);
+                    return Some(MaybeAsync::ready(Err(
+                        ExceptionCode::GatewayTargetDeviceFailedToRespond,
+                    )));
+                }
+            }
+        }
+        let mut channel = self.channel.clone();
+        // the values of write requests borrow the frame, so they are copied before forwarding
+        let request = match ForwardedRequest::new(request) {
+            Ok(x) => x,
+            Err(ex) => return Some(MaybeAsync::ready(Err(ex))),
+        };
+        Some(MaybeAsync::asynchronous(async move {
+            request
+                .forward(&mut channel, param)
+                .await
+                .map_err(to_exception)
+        }))
+    }
+}
+
+enum ForwardedRequest {
+    ReadCoils(AddressRange),
+    ReadDiscreteInputs(AddressRange),
+    ReadHoldingRegisters(AddressRange),
+    ReadInputRegisters(AddressRange),
+    WriteSingleCoil(Indexed<bool>),
+    WriteSingleRegister(Indexed<u16>),
+    WriteMultipleCoils(WriteMultiple<bool>),
+    WriteMultipleRegisters(WriteMultiple<u16>),
+}
+
+impl ForwardedRequest {
+    fn new(request: AsyncRequest) -> Result<Self, ExceptionCode> {
+        let request = match request {
+            AsyncRequest::ReadCoils(x) => Self::ReadCoils(x),
+            AsyncRequest::ReadDiscreteInputs(x) => Self::ReadDiscreteInputs(x),
+            AsyncRequest::ReadHoldingRegisters(x) => Self::ReadHoldingRegisters(x),
+            AsyncRequest::ReadInputRegisters(x) => Self::ReadInputRegisters(x),
+            AsyncRequest::WriteSingleCoil(x) => Self::WriteSingleCoil(x),
+            AsyncRequest::WriteSingleRegister(x) => Self::WriteSingleRegister(x),
+            AsyncRequest::WriteMultipleCoils(x) => Self::WriteMultipleCoils(
+                WriteMultiple::from_packed(
+                    x.range.start,
+                    x.range.count,
+                    &x.iterator.packed_bytes(),
+                )
+                .map_err(|_| ExceptionCode::IllegalDataValue)?,
+            ),
+            AsyncRequest::WriteMultipleRegisters(x) => Self::WriteMultipleRegisters(
+                WriteMultiple::from(x.range.start, x.iterator.map(|x| x.value).collect())
+                    .map_err(|_| ExceptionCode::IllegalDataValue)?,
+            ),
+        };
+        Ok(request)
+    }
+
+    async fn forward(
+        self,
+        channel: &mut Channel,
+        param: RequestParam,
+    ) -> Result<AsyncResponse, RequestError> {
+        let response = match self {
+            Self::ReadCoils(range) => {
+                AsyncResponse::Bits(values(channel.read_coils(param, range).await?))
+            }
+            Self::ReadDiscreteInputs(range) => {
+                AsyncResponse::Bits(values(channel.read_discrete_inputs(param, range).await?))
+            }
+            Self::ReadHoldingRegisters(range) => AsyncResponse::Registers(values(
+                channel.read_holding_registers(param, range).await?,
+            )),
+            Self::ReadInputRegisters(range) => {
+                AsyncResponse::Registers(values(channel.read_input_registers(param, range).await?))
+            }
+            Self::WriteSingleCoil(x) => {
+                channel.write_single_coil(param, x).await?;
+                AsyncResponse::Written
+            }
+            Self::WriteSingleRegister(x) => {
+                channel.write_single_register(param, x).await?;
+                AsyncResponse::Written
+            }
+            Self::WriteMultipleCoils(x) => {
+                channel.write_multiple_coils(param, x).await?;
+                AsyncResponse::Written
+            }
+            Self::WriteMultipleRegisters(x) => {
+                channel.write_multiple_registers(param, x).await?;
+                AsyncResponse::Written
+            }
+        };
+        Ok(response)
+    }
+}
+
+fn values<T>(items: Vec<Indexed<T>>) -> Vec<T> {
+    items.into_iter().map(|x| x.value).collect()
+}
+
+fn to_exception(err: RequestError) -> ExceptionCode {
+    match err {
+        RequestError::Exception(ex) => ex,
+        RequestError::ResponseTimeout
+        | RequestError::BadFrame(_)
+        | RequestError::BadResponse(_)
+        | RequestError::RetriesExhausted(_) => ExceptionCode::GatewayTargetDeviceFailedToRespond,
+        RequestError::Io(_)
+        | RequestError::NoConnection
+        | RequestError::Shutdown
+        | RequestError::Cancelled => ExceptionCode::GatewayPathUnavailable,
+        RequestError::BadRequest(_) => ExceptionCode::IllegalDataValue,
+        RequestError::Internal(_) | RequestError::VerifyFailed(_) => {
+            ExceptionCode::ServerDeviceFailure
+        }
+        RequestError::TooManyRequests => ExceptionCode::ServerDeviceBusy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_errors_to_exceptions() {
+        assert_eq!(
+            to_exception(RequestError::Exception(ExceptionCode::IllegalDataAddress)),
+            ExceptionCode::IllegalDataAddress
+        );
+        assert_eq!(
+            to_exception(RequestError::ResponseTimeout),
+            ExceptionCode::GatewayTargetDeviceFailedToRespond
+        );
+        assert_eq!(
+            to_exception(RequestError::NoConnection),
+            ExceptionCode::GatewayPathUnavailable
+        );
+        assert_eq!(
+            to_exception(RequestError::Io(std::io::ErrorKind::ConnectionReset)),
+            ExceptionCode::GatewayPathUnavailable
+        );
+    }
+}
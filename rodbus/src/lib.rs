@@ -109,33 +109,69 @@
 //!}
 //!```
 
+// the codec compiles the parsers and formatters shared with the clients and servers without them
+#![cfg_attr(not(feature = "runtime"), allow(dead_code))]
+
 /// Current version of the library
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Blocking client API for applications that don't use async
+#[cfg(feature = "runtime")]
+pub mod blocking;
+/// Capture of the raw frames sent and received by channels and servers
+#[cfg(feature = "runtime")]
+pub mod capture;
 /// Client API
 pub mod client;
+/// Encoding of client requests and decoding of responses without a transport
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "compat")]
+pub mod compat;
 /// Public constant values related to the Modbus specification
 pub mod constants;
+/// Forwarding of server requests to a client channel
+#[cfg(feature = "runtime")]
+pub mod gateway;
+/// In-memory transport for channels and servers in the same process
+#[cfg(feature = "runtime")]
+pub mod memory;
 
 /// Server API
+#[cfg(feature = "runtime")]
 pub mod server;
+/// Scripted server handlers and in-process client/server pairs for integration tests
+// also compiled for the unit tests of the crate, which share its helpers
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+#[cfg(feature = "fuzz")]
+#[doc(hidden)]
+pub mod fuzz;
 
 // modules that are re-exported
+#[cfg(feature = "runtime")]
 pub(crate) mod channel;
 pub(crate) mod decode;
 pub(crate) mod error;
 pub(crate) mod exception;
 pub(crate) mod maybe_async;
+pub(crate) mod profile;
 pub(crate) mod retry;
 #[cfg(feature = "serial")]
 mod serial;
 pub(crate) mod types;
 
 // re-exports
+pub use crate::common::function::FunctionCode;
 pub use crate::decode::*;
 pub use crate::error::*;
 pub use crate::exception::*;
 pub use crate::maybe_async::*;
+pub use crate::profile::*;
 pub use crate::retry::*;
 #[cfg(feature = "serial")]
 pub use crate::serial::*;
@@ -143,4 +179,7 @@ pub use crate::types::*;
 
 // internal modules
 mod common;
+mod rtu;
 mod tcp;
+#[cfg(feature = "runtime")]
+mod udp;
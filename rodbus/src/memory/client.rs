@@ -0,0 +1,262 @@
+use tracing::Instrument;
+
+use crate::client::message::Command;
+use crate::client::task::{ClientLoop, SessionError, StateChange};
+use crate::client::{
+    Channel, ClientState, ConnectFailReason, DisconnectReason, FrameFormat, Listener,
+};
+use crate::common::frame::{FrameWriter, FramedReader};
+use crate::common::phys::PhysLayer;
+use crate::decode::DecodeLevel;
+use crate::error::Shutdown;
+use crate::memory::MemoryConnector;
+use crate::retry::RetryStrategy;
+
+pub(crate) fn spawn_memory_channel(
+    connect: MemoryConnector,
+    format: FrameFormat,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Box<dyn Listener<ClientState>>,
+) -> Channel {
+    let (tx, rx) = crate::channel::channel(max_queued_requests);
+    let task = async move {
+        MemoryChannelTask::new(connect, format, rx, retry, decode, listener)
+            .run()
+            .instrument(tracing::info_span!("Modbus-Client-Memory", ?format))
+            .await;
+    };
+    tokio::spawn(task);
+    Channel::new(tx)
+}
+
+pub(crate) struct MemoryChannelTask {
+    connect: MemoryConnector,
+    retry: Box<dyn RetryStrategy>,
+    client_loop: ClientLoop,
+    listener: Box<dyn Listener<ClientState>>,
+}
+
+impl MemoryChannelTask {
+    pub(crate) fn new(
+        connect: MemoryConnector,
+        format: FrameFormat,
+        rx: crate::channel::Receiver<Command>,
+        retry: Box<dyn RetryStrategy>,
+        decode: DecodeLevel,
+        listener: Box<dyn Listener<ClientState>>,
+    ) -> Self {
+        let (writer, reader) = match format {
+            FrameFormat::Mbap => (FrameWriter::tcp(), FramedReader::tcp()),
+            FrameFormat::Rtu => (FrameWriter::rtu(), FramedReader::rtu_response()),
+        };
+        let client_loop = ClientLoop::new(rx, writer, reader, decode);
+        let listener = client_loop.track_state(listener);
+        Self {
+            connect,
+            retry,
+            client_loop,
+            listener,
+        }
+    }
+
+    // runs until it is shut down
+    pub(crate) async fn run(&mut self) -> Shutdown {
+        self.listener.update(ClientState::Disabled).get().await;
+        let ret = self.run_inner().await;
+        self.listener.update(ClientState::Shutdown).get().await;
+        ret
+    }
+
+    async fn run_inner(&mut self) -> Shutdown {
+        loop {
+            if let Err(Shutdown) = self.client_loop.wait_for_enabled().await {
+                return Shutdown;
+            }
+
+            if let Err(StateChange::Shutdown) = self.try_connect_and_run().await {
+                return Shutdown;
+            }
+
+            if !self.client_loop.is_enabled() {
+                self.listener.update(ClientState::Disabled).get().await;
+            }
+        }
+    }
+
+    async fn try_connect_and_run(&mut self) -> Result<(), StateChange> {
+        self.listener.update(ClientState::Connecting).get().await;
+        let stream = match (self.connect)() {
+            Some(x) => x,
+            None => {
+                self.client_loop.fail_pending_request();
+                let delay = self.retry.after_failed_connect();
+                tracing::warn!(
+                    "in-memory connection refused - waiting {} ms before next attempt",
+                    delay.as_millis()
+                );
+                self.listener
+                    .update(ClientState::WaitAfterFailedConnect {
+                        reason: ConnectFailReason::ConnectionRefused,
+                        retry_in: delay,
+                    })
+                    .get()
+                    .await;
+                return self.client_loop.fail_requests_for(delay).await;
+            }
+        };
+
+        self.listener.update(ClientState::Connected).get().await;
+        self.retry.reset();
+        let mut phys = PhysLayer::new_memory(stream.inner);
+        let result = self.client_loop.run(&mut phys).await;
+        // close the connection before waiting or reporting the new state
+        drop(phys);
+        let reason = match result {
+            // the mpsc was closed, end the task
+            SessionError::Shutdown => return Err(StateChange::Shutdown),
+            // no idle timeout is configured, so idle is treated like a disable
+            // serial settings are rejected by in-memory channels
+            SessionError::Disabled | SessionError::Idle | SessionError::SerialSettingsChanged => {
+                return Ok(())
+            }
+            SessionError::IoError(kind) => DisconnectReason::Io(kind),
+            SessionError::BadFrame => DisconnectReason::BadFrame,
+        };
+
+        crate::common::metrics::client_reconnect();
+        let delay = self.retry.after_disconnect();
+        tracing::warn!("waiting {:?} to reconnect", delay);
+        self.listener
+            .update(ClientState::WaitAfterDisconnect {
+                reason,
+                retry_in: delay,
+            })
+            .get()
+            .await;
+        self.client_loop.fail_requests_for(delay).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::client::{ChannelState, NullListener, RequestParam};
+    use crate::memory::{in_memory_pair, MemoryStream};
+    use crate::server::{RequestHandler, ServerHandlerMap};
+    use crate::testing::StateListener;
+    use crate::types::{AddressRange, Indexed, UnitId};
+
+    struct CoilHandler;
+
+    impl RequestHandler for CoilHandler {
+        fn read_coil(&self, _address: u16) -> Result<bool, crate::ExceptionCode> {
+            Ok(true)
+        }
+    }
+
+    async fn next<T>(rx: &mut tokio::sync::mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn read_coil(channel: &mut Channel) {
+        let result = channel
+            .read_coils(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                AddressRange::try_from(0, 1).unwrap(),
+            )
+            .await;
+        assert_eq!(result, Ok(vec![Indexed::new(0, true)]));
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_server_end_is_dropped() {
+        // every connection attempt hands the server end of a new pair to the test
+        let (accept_tx, mut accept_rx) = tokio::sync::mpsc::unbounded_channel::<MemoryStream>();
+        let connect = Box::new(move || {
+            let (client, server) = in_memory_pair(64);
+            accept_tx.send(server).ok().map(|_| client)
+        });
+        let (state_tx, mut states) = tokio::sync::mpsc::unbounded_channel();
+        let delay = Duration::from_millis(10);
+        let mut channel = spawn_memory_channel(
+            connect,
+            FrameFormat::Mbap,
+            10,
+            crate::client::doubling_retry_strategy(delay, delay),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx: state_tx }),
+        );
+        channel.enable().await.unwrap();
+
+        assert_eq!(next(&mut states).await, ClientState::Disabled);
+        for _ in 0..2 {
+            assert_eq!(next(&mut states).await, ClientState::Connecting);
+            assert_eq!(next(&mut states).await, ClientState::Connected);
+            let server = crate::server::spawn_in_memory_server_task(
+                next(&mut accept_rx).await,
+                FrameFormat::Mbap,
+                ServerHandlerMap::single(UnitId::new(1), CoilHandler.wrap()),
+                DecodeLevel::nothing(),
+            );
+            read_coil(&mut channel).await;
+
+            // stops the session, which closes its end of the connection
+            drop(server);
+            assert_eq!(
+                next(&mut states).await,
+                ClientState::WaitAfterDisconnect {
+                    reason: DisconnectReason::Io(std::io::ErrorKind::UnexpectedEof),
+                    retry_in: delay,
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn state_can_be_queried_through_the_channel() {
+        // the server end is kept open, but no server runs on it
+        let (client, _server) = in_memory_pair(64);
+        let mut client = Some(client);
+        let connect = Box::new(move || client.take());
+        let channel = spawn_memory_channel(
+            connect,
+            FrameFormat::Mbap,
+            10,
+            crate::client::default_retry_strategy(),
+            DecodeLevel::nothing(),
+            NullListener::create(),
+        );
+
+        assert_eq!(
+            channel.current_state().await,
+            Ok(ChannelState::Client(ClientState::Disabled))
+        );
+        assert_eq!(
+            channel.wait_for_connected(Duration::from_millis(10)).await,
+            Ok(false)
+        );
+
+        channel.enable().await.unwrap();
+        assert_eq!(
+            channel.wait_for_connected(Duration::from_secs(5)).await,
+            Ok(true)
+        );
+        assert_eq!(
+            channel.current_state().await,
+            Ok(ChannelState::Client(ClientState::Connected))
+        );
+
+        channel.disable().await.unwrap();
+        assert_eq!(
+            channel.current_state().await,
+            Ok(ChannelState::Client(ClientState::Disabled))
+        );
+    }
+}
@@ -0,0 +1,33 @@
+pub(crate) mod client;
+
+/// One end of an in-memory connection created by [`in_memory_pair`]
+pub struct MemoryStream {
+    pub(crate) inner: tokio::io::DuplexStream,
+}
+
+impl std::fmt::Debug for MemoryStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("MemoryStream")
+    }
+}
+
+/// Produces the stream of each connection attempt of
+/// [`spawn_in_memory_client_task`](crate::client::spawn_in_memory_client_task)
+///
+/// Returning `None` fails the attempt as if the connection was refused.
+pub type MemoryConnector = Box<dyn FnMut() -> Option<MemoryStream> + Send>;
+
+/// Create the two ends of an in-memory connection
+///
+/// The ends behave like a connected pair of sockets. Each direction buffers up to `capacity`
+/// bytes (at least one), and writes wait for the other end to read once the buffer is full. When
+/// one end is dropped, reads on the other end fail with [`std::io::ErrorKind::UnexpectedEof`] and
+/// writes fail with [`std::io::ErrorKind::BrokenPipe`], so channels and servers handle it like
+/// a closed connection.
+///
+/// Pass one end to [`spawn_in_memory_server_task`](crate::server::spawn_in_memory_server_task)
+/// and return the other from the [`MemoryConnector`] of a channel.
+pub fn in_memory_pair(capacity: usize) -> (MemoryStream, MemoryStream) {
+    let (a, b) = tokio::io::duplex(capacity.max(1));
+    (MemoryStream { inner: a }, MemoryStream { inner: b })
+}
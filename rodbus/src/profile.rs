@@ -0,0 +1,377 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::common::function::FunctionCode;
+use crate::exception::ExceptionCode;
+use crate::types::AddressRange;
+
+/// Address space accessed by a function code
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegisterSpace {
+    /// Coils, read with FC 01 and written with FC 05 / FC 15
+    Coils,
+    /// Discrete inputs, read with FC 02
+    DiscreteInputs,
+    /// Holding registers, read with FC 03 and written with FC 06 / FC 16
+    HoldingRegisters,
+    /// Input registers, read with FC 04
+    InputRegisters,
+}
+
+impl From<FunctionCode> for RegisterSpace {
+    fn from(function: FunctionCode) -> Self {
+        match function {
+            FunctionCode::ReadCoils
+            | FunctionCode::WriteSingleCoil
+            | FunctionCode::WriteMultipleCoils => RegisterSpace::Coils,
+            FunctionCode::ReadDiscreteInputs => RegisterSpace::DiscreteInputs,
+            FunctionCode::ReadHoldingRegisters
+            | FunctionCode::WriteSingleRegister
+            | FunctionCode::WriteMultipleRegisters => RegisterSpace::HoldingRegisters,
+            FunctionCode::ReadInputRegisters => RegisterSpace::InputRegisters,
+        }
+    }
+}
+
+impl std::fmt::Display for RegisterSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            RegisterSpace::Coils => f.write_str("coils"),
+            RegisterSpace::DiscreteInputs => f.write_str("discrete inputs"),
+            RegisterSpace::HoldingRegisters => f.write_str("holding registers"),
+            RegisterSpace::InputRegisters => f.write_str("input registers"),
+        }
+    }
+}
+
+/// Operations permitted on a range of a [`DeviceProfile`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Access {
+    /// The range may be read and written
+    ReadWrite,
+    /// The range may only be read
+    ReadOnly,
+    /// The range may only be written
+    WriteOnly,
+}
+
+impl Access {
+    fn permits(self, write: bool) -> bool {
+        match self {
+            Access::ReadWrite => true,
+            Access::ReadOnly => !write,
+            Access::WriteOnly => write,
+        }
+    }
+}
+
+/// Error returned when a request falls outside of a [`DeviceProfile`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProfileError {
+    /// Part of the range isn't declared in the profile
+    OutOfProfile(RegisterSpace, AddressRange),
+    /// The range is declared, but part of it may only be read
+    ReadOnly(RegisterSpace, AddressRange),
+    /// The range is declared, but part of it may only be written
+    WriteOnly(RegisterSpace, AddressRange),
+}
+
+impl std::error::Error for ProfileError {}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ProfileError::OutOfProfile(space, range) => {
+                write!(f, "{space} {range} are not part of the device profile")
+            }
+            ProfileError::ReadOnly(space, range) => {
+                write!(f, "{space} {range} may only be read")
+            }
+            ProfileError::WriteOnly(space, range) => {
+                write!(f, "{space} {range} may only be written")
+            }
+        }
+    }
+}
+
+/// Servers answer requests outside of the profile with [`ExceptionCode::IllegalDataAddress`]
+impl From<ProfileError> for ExceptionCode {
+    fn from(_: ProfileError) -> Self {
+        ExceptionCode::IllegalDataAddress
+    }
+}
+
+/// Ranges of addresses a device exposes for each [`RegisterSpace`]
+///
+/// The same profile can validate requests before a client sends them, see
+/// [`Channel::read_holding_registers_checked`](crate::client::Channel::read_holding_registers_checked),
+/// and reject requests in a server handler, as [`ProfileError`] converts to
+/// [`ExceptionCode::IllegalDataAddress`]:
+///
+/// ```
+/// use rodbus::*;
+///
+/// let profile = DeviceProfile::new()
+///     .holding(0..100)
+///     .input(0..50)
+///     .coils(0..16);
+///
+/// let range = AddressRange::try_from(90, 20).unwrap();
+/// assert!(profile.validate(FunctionCode::ReadHoldingRegisters, range).is_err());
+/// // the same range is valid if read with FC 04
+/// let range = AddressRange::try_from(40, 10).unwrap();
+/// assert!(profile.validate(FunctionCode::ReadInputRegisters, range).is_ok());
+/// ```
+///
+/// A request is valid if every address it accesses lies in a declared range that permits the
+/// operation. Declared ranges may overlap or be adjacent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceProfile {
+    ranges: Vec<ProfileRange>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct ProfileRange {
+    space: RegisterSpace,
+    // inclusive, so that address 65535 can be declared
+    first: u16,
+    last: u16,
+    access: Access,
+}
+
+impl DeviceProfile {
+    /// Create a profile without any valid range
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a range of coils that may be read and written
+    pub fn coils(self, range: impl RangeBounds<u16>) -> Self {
+        self.range(RegisterSpace::Coils, range, Access::ReadWrite)
+    }
+
+    /// Declare a range of discrete inputs
+    pub fn discrete_inputs(self, range: impl RangeBounds<u16>) -> Self {
+        self.range(RegisterSpace::DiscreteInputs, range, Access::ReadOnly)
+    }
+
+    /// Declare a range of holding registers that may be read and written
+    pub fn holding(self, range: impl RangeBounds<u16>) -> Self {
+        self.range(RegisterSpace::HoldingRegisters, range, Access::ReadWrite)
+    }
+
+    /// Declare a range of input registers
+    pub fn input(self, range: impl RangeBounds<u16>) -> Self {
+        self.range(RegisterSpace::InputRegisters, range, Access::ReadOnly)
+    }
+
+    /// Declare a range of any space with the specified access, e.g. read-only holding registers
+    ///
+    /// Discrete inputs and input registers can't be written regardless of `access`. Empty ranges
+    /// are ignored.
+    pub fn range(
+        mut self,
+        space: RegisterSpace,
+        range: impl RangeBounds<u16>,
+        access: Access,
+    ) -> Self {
+        let first = match range.start_bound() {
+            Bound::Included(x) => Some(*x),
+            Bound::Excluded(x) => x.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let last = match range.end_bound() {
+            Bound::Included(x) => Some(*x),
+            Bound::Excluded(x) => x.checked_sub(1),
+            Bound::Unbounded => Some(u16::MAX),
+        };
+        if let (Some(first), Some(last)) = (first, last) {
+            if first <= last {
+                self.ranges.push(ProfileRange {
+                    space,
+                    first,
+                    last,
+                    access,
+                });
+            }
+        }
+        self
+    }
+
+    /// Check that a request with the specified function code and range lies within the profile
+    pub fn validate(
+        &self,
+        function: FunctionCode,
+        range: AddressRange,
+    ) -> Result<(), ProfileError> {
+        let space = RegisterSpace::from(function);
+        let write = is_write(function);
+        // last address accessed, saturated in case the range itself is invalid
+        let last = range.start.saturating_add(range.count.saturating_sub(1));
+
+        // only report an access error if the addresses are declared with the wrong access
+        let mut denied = None;
+        let mut next = range.start;
+        loop {
+            let covering = self
+                .ranges
+                .iter()
+                .filter(|x| x.space == space && x.first <= next && next <= x.last);
+
+            let mut end = None;
+            for x in covering {
+                if x.access.permits(write) {
+                    end = end.max(Some(x.last));
+                } else {
+                    denied = Some(x.access);
+                }
+            }
+
+            match end {
+                Some(end) if end >= last => return Ok(()),
+                // end < last <= u16::MAX, so this can't overflow
+                Some(end) => next = end + 1,
+                None => {
+                    return Err(match denied {
+                        Some(Access::ReadOnly) => ProfileError::ReadOnly(space, range),
+                        Some(Access::WriteOnly) => ProfileError::WriteOnly(space, range),
+                        _ => ProfileError::OutOfProfile(space, range),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Check that a single address accessed with the specified function code lies within the profile
+    ///
+    /// Convenient in the per-address methods of [`RequestHandler`](crate::server::RequestHandler).
+    pub fn validate_address(
+        &self,
+        function: FunctionCode,
+        address: u16,
+    ) -> Result<(), ProfileError> {
+        self.validate(
+            function,
+            AddressRange {
+                start: address,
+                count: 1,
+            },
+        )
+    }
+}
+
+fn is_write(function: FunctionCode) -> bool {
+    match function {
+        FunctionCode::ReadCoils
+        | FunctionCode::ReadDiscreteInputs
+        | FunctionCode::ReadHoldingRegisters
+        | FunctionCode::ReadInputRegisters => false,
+        FunctionCode::WriteSingleCoil
+        | FunctionCode::WriteSingleRegister
+        | FunctionCode::WriteMultipleCoils
+        | FunctionCode::WriteMultipleRegisters => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u16, count: u16) -> AddressRange {
+        AddressRange::try_from(start, count).unwrap()
+    }
+
+    #[test]
+    fn validates_ranges_per_register_space() {
+        let profile = DeviceProfile::new()
+            .holding(0..100)
+            .input(0..50)
+            .coils(0..16);
+
+        assert_eq!(
+            profile.validate(FunctionCode::ReadHoldingRegisters, range(0, 100)),
+            Ok(())
+        );
+        assert_eq!(
+            profile.validate(FunctionCode::ReadInputRegisters, range(40, 20)),
+            Err(ProfileError::OutOfProfile(
+                RegisterSpace::InputRegisters,
+                range(40, 20)
+            ))
+        );
+        assert_eq!(
+            profile.validate(FunctionCode::WriteMultipleCoils, range(0, 16)),
+            Ok(())
+        );
+        assert_eq!(
+            profile.validate(FunctionCode::ReadDiscreteInputs, range(0, 1)),
+            Err(ProfileError::OutOfProfile(
+                RegisterSpace::DiscreteInputs,
+                range(0, 1)
+            ))
+        );
+    }
+
+    #[test]
+    fn requests_may_span_adjacent_ranges() {
+        let profile = DeviceProfile::new()
+            .holding(0..10)
+            .holding(10..=20)
+            .holding(30..);
+
+        assert_eq!(
+            profile.validate(FunctionCode::ReadHoldingRegisters, range(5, 16)),
+            Ok(())
+        );
+        assert!(profile
+            .validate(FunctionCode::ReadHoldingRegisters, range(5, 17))
+            .is_err());
+        assert_eq!(
+            profile.validate_address(FunctionCode::WriteSingleRegister, u16::MAX),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn enforces_access_of_ranges() {
+        let profile = DeviceProfile::new()
+            .range(RegisterSpace::HoldingRegisters, 0..10, Access::ReadOnly)
+            .range(RegisterSpace::HoldingRegisters, 10..20, Access::WriteOnly)
+            .range(RegisterSpace::InputRegisters, 0..10, Access::ReadWrite);
+
+        assert_eq!(
+            profile.validate(FunctionCode::ReadHoldingRegisters, range(0, 10)),
+            Ok(())
+        );
+        assert_eq!(
+            profile.validate(FunctionCode::WriteMultipleRegisters, range(5, 10)),
+            Err(ProfileError::ReadOnly(
+                RegisterSpace::HoldingRegisters,
+                range(5, 10)
+            ))
+        );
+        assert_eq!(
+            profile.validate_address(FunctionCode::ReadHoldingRegisters, 15),
+            Err(ProfileError::WriteOnly(
+                RegisterSpace::HoldingRegisters,
+                range(15, 1)
+            ))
+        );
+        // input registers are never written, the function code determines the operation
+        assert_eq!(
+            profile.validate(FunctionCode::ReadInputRegisters, range(0, 10)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn servers_answer_with_illegal_data_address() {
+        let profile = DeviceProfile::new().coils(0..16);
+        let result: Result<(), ExceptionCode> = profile
+            .validate_address(FunctionCode::ReadCoils, 16)
+            .map_err(Into::into);
+        assert_eq!(result, Err(ExceptionCode::IllegalDataAddress));
+    }
+}
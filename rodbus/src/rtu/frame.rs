@@ -1,10 +1,12 @@
+use std::ops::Range;
+
 use crate::common::buffer::ReadBuffer;
 use crate::common::frame::{
     Frame, FrameDestination, FrameHeader, FrameInfo, FrameType, FunctionField,
 };
 use crate::common::function::FunctionCode;
 use crate::common::traits::Serialize;
-use crate::decode::FrameDecodeLevel;
+use crate::decode::{BytesFormat, DecodeLevel, FrameDecodeLevel};
 use crate::error::{FrameParseError, RequestError};
 use crate::types::UnitId;
 
@@ -19,7 +21,7 @@ pub(crate) mod constants {
 }
 
 /// precomputes the CRC table as a constant!
-const CRC: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS);
+pub(crate) const CRC: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS);
 
 #[derive(Clone, Copy)]
 enum ParserType {
@@ -104,7 +106,7 @@ impl RtuParser {
     pub(crate) fn parse(
         &mut self,
         cursor: &mut ReadBuffer,
-        decode_level: FrameDecodeLevel,
+        decode_level: DecodeLevel,
     ) -> Result<Option<Frame>, RequestError> {
         match self.state {
             ParseState::Start => {
@@ -189,10 +191,19 @@ impl RtuParser {
                     ));
                 }
 
-                if decode_level.enabled() {
+                if decode_level.frame.enabled() {
                     tracing::info!(
+                        unit = destination.value(),
+                        len = frame.payload().len(),
+                        crc = received_crc,
                         "RTU RX - {}",
-                        RtuDisplay::new(decode_level, destination, frame.payload(), received_crc)
+                        RtuDisplay::new(
+                            decode_level,
+                            destination,
+                            frame.payload(),
+                            0..frame.payload().len(),
+                            received_crc,
+                        )
                     );
                 }
 
@@ -231,22 +242,30 @@ pub(crate) fn format_rtu_pdu(
 
 pub(crate) struct RtuDisplay<'a> {
     level: FrameDecodeLevel,
+    format: BytesFormat,
+    redact_values: bool,
     destination: FrameDestination,
     payload: &'a [u8],
+    pdu: Range<usize>,
     crc: u16,
 }
 
 impl<'a> RtuDisplay<'a> {
+    /// `pdu` is the range of the function code and the data within `payload`
     pub(crate) fn new(
-        level: FrameDecodeLevel,
+        decode_level: DecodeLevel,
         destination: FrameDestination,
         payload: &'a [u8],
+        pdu: Range<usize>,
         crc: u16,
     ) -> Self {
         RtuDisplay {
-            level,
+            level: decode_level.frame,
+            format: decode_level.bytes,
+            redact_values: decode_level.redact_values,
             destination,
             payload,
+            pdu,
             crc,
         }
     }
@@ -262,7 +281,12 @@ impl<'a> std::fmt::Display for RtuDisplay<'a> {
             self.payload.len(),
         )?;
         if self.level.payload_enabled() {
-            crate::common::phys::format_bytes(f, self.payload)?;
+            let redacted = if self.redact_values {
+                crate::common::redact::frame_values(self.payload, self.pdu.clone())
+            } else {
+                None
+            };
+            crate::common::phys::format_bytes(f, self.payload, self.format, redacted.as_slice())?;
         }
         Ok(())
     }
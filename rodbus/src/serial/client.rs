@@ -1,6 +1,5 @@
-use crate::common::phys::PhysLayer;
 use crate::decode::DecodeLevel;
-use crate::serial::SerialSettings;
+use crate::serial::{PortSource, SerialSettings};
 
 use crate::client::message::Command;
 use crate::client::task::{ClientLoop, SessionError, StateChange};
@@ -9,7 +8,7 @@ use crate::common::frame::{FrameWriter, FramedReader};
 use crate::error::Shutdown;
 
 pub(crate) struct SerialChannelTask {
-    path: String,
+    port: PortSource,
     serial_settings: SerialSettings,
     retry: Box<dyn RetryStrategy>,
     client_loop: ClientLoop,
@@ -18,23 +17,22 @@ pub(crate) struct SerialChannelTask {
 
 impl SerialChannelTask {
     pub(crate) fn new(
-        path: &str,
+        port: PortSource,
         serial_settings: SerialSettings,
         rx: crate::channel::Receiver<Command>,
         retry: Box<dyn RetryStrategy>,
         decode: DecodeLevel,
         listener: Box<dyn Listener<PortState>>,
     ) -> Self {
+        let mut client_loop =
+            ClientLoop::new(rx, FrameWriter::rtu(), FramedReader::rtu_response(), decode);
+        client_loop.accept_serial_settings();
+        let listener = client_loop.track_state(listener);
         Self {
-            path: path.to_string(),
+            port,
             serial_settings,
             retry,
-            client_loop: ClientLoop::new(
-                rx,
-                FrameWriter::rtu(),
-                FramedReader::rtu_response(),
-                decode,
-            ),
+            client_loop,
             listener,
         }
     }
@@ -64,31 +62,53 @@ impl SerialChannelTask {
     }
 
     pub(crate) async fn try_open_and_run(&mut self) -> Result<(), StateChange> {
-        match crate::serial::open(self.path.as_str(), self.serial_settings) {
+        // settings received while the port was closed
+        if let Some(settings) = self.client_loop.take_serial_settings() {
+            self.serial_settings = settings;
+        }
+
+        let (path, mut phys) = match self.port.open(self.serial_settings) {
+            Ok(x) => x,
             Err(err) => {
                 let delay = self.retry.after_failed_connect();
-                self.listener.update(PortState::Wait(delay)).get().await;
+                self.listener
+                    .update(PortState::WaitAfterFailedOpen(delay))
+                    .get()
+                    .await;
                 tracing::warn!("{} - waiting {} ms to re-open port", err, delay.as_millis());
-                self.client_loop.fail_requests_for(delay).await
+                self.client_loop.fail_pending_request();
+                return self.client_loop.fail_requests_for(delay).await;
+            }
+        };
+
+        self.retry.reset();
+        self.listener.update(PortState::Open).get().await;
+        tracing::info!("serial port open: {}", path);
+
+        let result = self.client_loop.run(&mut phys).await;
+        // close the port before waiting so that a re-plugged device can re-use its name
+        drop(phys);
+
+        match result {
+            // the mpsc was closed, end the task
+            SessionError::Shutdown => Err(StateChange::Shutdown),
+            // don't wait, we're disabled (idle disconnect is never configured for serial)
+            SessionError::Disabled | SessionError::Idle => Ok(()),
+            // re-open the port right away with the new settings
+            SessionError::SerialSettingsChanged => {
+                self.listener.update(PortState::Reconfiguring).get().await;
+                Ok(())
             }
-            Ok(serial) => {
-                self.retry.reset();
-                self.listener.update(PortState::Open).get().await;
-                let mut phys = PhysLayer::new_serial(serial);
-                tracing::info!("serial port open");
-                match self.client_loop.run(&mut phys).await {
-                    // the mpsc was closed, end the task
-                    SessionError::Shutdown => Err(StateChange::Shutdown),
-                    // don't wait, we're disabled
-                    SessionError::Disabled => Ok(()),
-                    // wait before retrying
-                    SessionError::IoError(_) | SessionError::BadFrame => {
-                        let delay = self.retry.after_disconnect();
-                        self.listener.update(PortState::Wait(delay)).get().await;
-                        tracing::warn!("waiting {} ms to re-open port", delay.as_millis());
-                        self.client_loop.fail_requests_for(delay).await
-                    }
-                }
+            // wait before retrying
+            SessionError::IoError(_) | SessionError::BadFrame => {
+                crate::common::metrics::client_reconnect();
+                let delay = self.retry.after_disconnect();
+                self.listener
+                    .update(PortState::WaitAfterPortError(delay))
+                    .get()
+                    .await;
+                tracing::warn!("waiting {} ms to re-open port", delay.as_millis());
+                self.client_loop.fail_requests_for(delay).await
             }
         }
     }
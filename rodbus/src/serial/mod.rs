@@ -1,12 +1,15 @@
+use crate::common::phys::PhysLayer;
 use tokio_serial::SerialStream;
 pub use tokio_serial::{DataBits, FlowControl, Parity, StopBits};
 
 pub(crate) mod client;
-pub(crate) mod frame;
 pub(crate) mod server;
+#[cfg(test)]
+pub(crate) mod virtual_port;
 
 /// Serial port settings
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerialSettings {
     /// Baud rate of the port
     pub baud_rate: u32,
@@ -18,6 +21,66 @@ pub struct SerialSettings {
     pub stop_bits: StopBits,
     /// Parity setting
     pub parity: Parity,
+    /// Direction control of a half-duplex RS-485 transceiver
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rs485: Rs485Direction,
+}
+
+/// Controls the direction of a half-duplex RS-485 transceiver with the RTS line
+///
+/// Only needed for transceivers without automatic direction control. Before each frame is
+/// written, RTS is set to its transmit level and the pre-delay elapses. Once the last byte has
+/// been clocked out and the post-delay elapsed, RTS returns to its receive level so that the
+/// response can be received. RTS is set to its receive level when the port is opened.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rs485Direction {
+    /// RTS is not used, e.g. RS-232 or transceivers that switch direction on their own
+    #[default]
+    None,
+    /// RTS is high while transmitting and low while receiving
+    RtsHighDuringTx {
+        /// Delay between setting RTS and writing the first byte, in microseconds
+        pre_delay_us: u32,
+        /// Delay between the transmission of the last byte and resetting RTS, in microseconds
+        post_delay_us: u32,
+    },
+    /// RTS is low while transmitting and high while receiving
+    RtsLowDuringTx {
+        /// Delay between setting RTS and writing the first byte, in microseconds
+        pre_delay_us: u32,
+        /// Delay between the transmission of the last byte and resetting RTS, in microseconds
+        post_delay_us: u32,
+    },
+}
+
+/// RTS levels and delays of an [`Rs485Direction`] other than [`Rs485Direction::None`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RtsControl {
+    pub(crate) tx_level: bool,
+    pub(crate) pre_delay: std::time::Duration,
+    pub(crate) post_delay: std::time::Duration,
+}
+
+impl Rs485Direction {
+    pub(crate) fn rts_control(self) -> Option<RtsControl> {
+        let (tx_level, pre_delay_us, post_delay_us) = match self {
+            Self::None => return None,
+            Self::RtsHighDuringTx {
+                pre_delay_us,
+                post_delay_us,
+            } => (true, pre_delay_us, post_delay_us),
+            Self::RtsLowDuringTx {
+                pre_delay_us,
+                post_delay_us,
+            } => (false, pre_delay_us, post_delay_us),
+        };
+        Some(RtsControl {
+            tx_level,
+            pre_delay: std::time::Duration::from_micros(pre_delay_us as u64),
+            post_delay: std::time::Duration::from_micros(post_delay_us as u64),
+        })
+    }
 }
 
 impl SerialSettings {
@@ -42,11 +105,291 @@ impl Default for SerialSettings {
             flow_control: FlowControl::None,
             stop_bits: StopBits::One,
             parity: Parity::None,
+            rs485: Rs485Direction::None,
         }
     }
 }
 
+/// Identifies the serial port to open
+///
+/// The path is resolved again on every attempt to open the port, so USB adapters that
+/// re-enumerate under a different name after being re-plugged are found automatically
+/// when using [`SerialPath::AnyOf`] or [`SerialPath::Pattern`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SerialPath {
+    /// A single path, e.g. `/dev/ttyUSB0` on Linux or `COM1` on Windows
+    Fixed(String),
+    /// Candidate paths that are tried in order until one of them opens
+    AnyOf(Vec<String>),
+    /// Pattern matched against the names of the available serial ports, e.g. `/dev/ttyUSB*`
+    ///
+    /// `*` matches any sequence of characters and `?` matches a single character.
+    /// Matching ports are tried in sorted order until one of them opens.
+    Pattern(String),
+}
+
+impl From<&str> for SerialPath {
+    fn from(value: &str) -> Self {
+        Self::Fixed(value.to_string())
+    }
+}
+
+impl From<String> for SerialPath {
+    fn from(value: String) -> Self {
+        Self::Fixed(value)
+    }
+}
+
+impl std::fmt::Display for SerialPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(path) => f.write_str(path),
+            Self::AnyOf(paths) => write!(f, "any of {paths:?}"),
+            Self::Pattern(pattern) => write!(f, "pattern {pattern}"),
+        }
+    }
+}
+
+impl SerialPath {
+    /// resolve the candidate paths at this moment in time
+    fn candidates(&self) -> tokio_serial::Result<Vec<String>> {
+        match self {
+            Self::Fixed(path) => Ok(vec![path.clone()]),
+            Self::AnyOf(paths) => Ok(paths.clone()),
+            Self::Pattern(pattern) => {
+                let mut paths: Vec<String> = tokio_serial::available_ports()?
+                    .into_iter()
+                    .map(|x| x.port_name)
+                    .filter(|x| wildcard_match(pattern, x))
+                    .collect();
+                paths.sort();
+                Ok(paths)
+            }
+        }
+    }
+}
+
+/// Information about a serial port present on the system
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerialPortInfo {
+    /// Path or name of the port, e.g. `/dev/ttyUSB0` or `COM3`
+    pub path: String,
+    /// USB vendor id if the port is a USB device
+    pub usb_vid: Option<u16>,
+    /// USB product id if the port is a USB device
+    pub usb_pid: Option<u16>,
+    /// USB product string if the port is a USB device that reports one
+    pub product: Option<String>,
+}
+
+impl From<tokio_serial::SerialPortInfo> for SerialPortInfo {
+    fn from(x: tokio_serial::SerialPortInfo) -> Self {
+        match x.port_type {
+            tokio_serial::SerialPortType::UsbPort(usb) => Self {
+                path: x.port_name,
+                usb_vid: Some(usb.vid),
+                usb_pid: Some(usb.pid),
+                product: usb.product,
+            },
+            _ => Self {
+                path: x.port_name,
+                usb_vid: None,
+                usb_pid: None,
+                product: None,
+            },
+        }
+    }
+}
+
+/// Enumerate the serial ports currently present on the system, sorted by path
+///
+/// Useful for populating a port selector or for choosing a [`SerialPath::AnyOf`] list
+pub fn available_ports() -> Result<Vec<SerialPortInfo>, std::io::Error> {
+    let mut ports: Vec<SerialPortInfo> = tokio_serial::available_ports()?
+        .into_iter()
+        .map(SerialPortInfo::from)
+        .collect();
+    ports.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(ports)
+}
+
+/// match text against a pattern where `*` matches any sequence and `?` matches any character
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // position of the last '*' in the pattern and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // let the last '*' consume one more character
+                Some((star, pos)) => {
+                    backtrack = Some((star, pos + 1));
+                    p = star + 1;
+                    t = pos + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub(crate) fn open(path: &str, settings: SerialSettings) -> tokio_serial::Result<SerialStream> {
     let builder = settings.apply(tokio_serial::new(path, settings.baud_rate));
     SerialStream::open(&builder)
 }
+
+/// open the first candidate path that succeeds, returning the path that was opened
+pub(crate) fn open_any(
+    path: &SerialPath,
+    settings: SerialSettings,
+) -> tokio_serial::Result<(String, SerialStream)> {
+    let mut last_error = None;
+    for candidate in path.candidates()? {
+        match open(&candidate, settings) {
+            Ok(stream) => return Ok((candidate, stream)),
+            Err(err) => {
+                tracing::debug!("unable to open {}: {}", candidate, err);
+                last_error = Some(err);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        tokio_serial::Error::new(
+            tokio_serial::ErrorKind::NoDevice,
+            format!("no serial port matches {path}"),
+        )
+    }))
+}
+
+/// Ports opened by serial channels and RTU servers
+pub(crate) enum PortSource {
+    /// serial ports of the operating system
+    System(SerialPath),
+    /// one end of an in-process port pair used to test the serial tasks
+    #[cfg(test)]
+    Virtual(virtual_port::VirtualPort),
+}
+
+impl std::fmt::Display for PortSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::System(path) => write!(f, "{path}"),
+            #[cfg(test)]
+            Self::Virtual(port) => write!(f, "virtual {}", port.name()),
+        }
+    }
+}
+
+impl PortSource {
+    /// open a port, returning the name of the port that was opened
+    pub(crate) fn open(
+        &mut self,
+        settings: SerialSettings,
+    ) -> Result<(String, PhysLayer), std::io::Error> {
+        match self {
+            Self::System(path) => {
+                let (path, stream) = open_any(path, settings)?;
+                Ok((path, PhysLayer::new_serial(stream, settings.rs485)))
+            }
+            #[cfg(test)]
+            Self::Virtual(port) => port.open(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_matching() {
+        assert!(wildcard_match("/dev/ttyUSB*", "/dev/ttyUSB0"));
+        assert!(wildcard_match("/dev/ttyUSB*", "/dev/ttyUSB12"));
+        assert!(wildcard_match("/dev/ttyUSB*", "/dev/ttyUSB"));
+        assert!(!wildcard_match("/dev/ttyUSB*", "/dev/ttyACM0"));
+        assert!(wildcard_match("COM?", "COM3"));
+        assert!(!wildcard_match("COM?", "COM10"));
+        assert!(wildcard_match("*USB*", "/dev/ttyUSB0"));
+        assert!(wildcard_match("/dev/tty*0", "/dev/ttyUSB10"));
+        assert!(!wildcard_match("/dev/tty*0", "/dev/ttyUSB01"));
+        assert!(wildcard_match("exact", "exact"));
+        assert!(!wildcard_match("exact", "exact1"));
+    }
+
+    #[test]
+    fn maps_rs485_direction_to_rts_levels() {
+        assert_eq!(Rs485Direction::None.rts_control(), None);
+        assert_eq!(
+            Rs485Direction::RtsHighDuringTx {
+                pre_delay_us: 100,
+                post_delay_us: 2500,
+            }
+            .rts_control(),
+            Some(RtsControl {
+                tx_level: true,
+                pre_delay: std::time::Duration::from_micros(100),
+                post_delay: std::time::Duration::from_micros(2500),
+            })
+        );
+        assert_eq!(
+            Rs485Direction::RtsLowDuringTx {
+                pre_delay_us: 0,
+                post_delay_us: 0,
+            }
+            .rts_control()
+            .map(|x| x.tx_level),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn open_any_reports_an_error_if_nothing_opens() {
+        let path = SerialPath::AnyOf(Vec::new());
+        let err = open_any(&path, SerialSettings::default()).err().unwrap();
+        assert_eq!(err.kind(), tokio_serial::ErrorKind::NoDevice);
+
+        let path = SerialPath::AnyOf(vec!["/does/not/exist".to_string()]);
+        assert!(open_any(&path, SerialSettings::default()).is_err());
+    }
+
+    #[test]
+    fn converts_usb_port_info() {
+        let info = tokio_serial::SerialPortInfo {
+            port_name: "/dev/ttyUSB0".to_string(),
+            port_type: tokio_serial::SerialPortType::UsbPort(tokio_serial::UsbPortInfo {
+                vid: 0x0403,
+                pid: 0x6001,
+                serial_number: None,
+                manufacturer: None,
+                product: Some("FT232R".to_string()),
+            }),
+        };
+        let info = SerialPortInfo::from(info);
+        assert_eq!(info.path, "/dev/ttyUSB0");
+        assert_eq!(info.usb_vid, Some(0x0403));
+        assert_eq!(info.usb_pid, Some(0x6001));
+        assert_eq!(info.product.as_deref(), Some("FT232R"));
+
+        let info = SerialPortInfo::from(tokio_serial::SerialPortInfo {
+            port_name: "COM1".to_string(),
+            port_type: tokio_serial::SerialPortType::PciPort,
+        });
+        assert_eq!(info.usb_vid, None);
+        assert_eq!(info.product, None);
+    }
+}
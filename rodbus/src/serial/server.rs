@@ -1,13 +1,14 @@
-use crate::common::phys::PhysLayer;
+use crate::serial::PortSource;
 use crate::server::task::SessionTask;
 use crate::server::RequestHandler;
+use crate::server::SessionCloseReason;
 use crate::{RequestError, RetryStrategy, SerialSettings, Shutdown};
 
 pub(crate) struct RtuServerTask<T>
 where
     T: RequestHandler,
 {
-    pub(crate) port: String,
+    pub(crate) port: PortSource,
     pub(crate) retry: Box<dyn RetryStrategy>,
     pub(crate) settings: SerialSettings,
     pub(crate) session: SessionTask<T>,
@@ -19,14 +20,26 @@ where
 {
     pub(crate) async fn run(&mut self) -> Shutdown {
         loop {
-            match crate::serial::open(&self.port, self.settings) {
-                Ok(serial) => {
+            // settings received while the port was closed
+            if let Some(settings) = self.session.take_serial_settings() {
+                self.settings = settings;
+            }
+            match self.port.open(self.settings) {
+                Ok((path, mut phys)) => {
                     self.retry.reset();
-                    tracing::info!("opened port");
+                    tracing::info!("opened port: {}", path);
                     // run an open port until shutdown or failure
-                    let mut phys = PhysLayer::new_serial(serial);
-                    if let RequestError::Shutdown = self.session.run(&mut phys).await {
-                        return Shutdown;
+                    let reason = self.session.run(&mut phys).await;
+                    // close the port before waiting so that a re-plugged device can re-use its name
+                    drop(phys);
+                    match reason {
+                        SessionCloseReason::Error(RequestError::Shutdown) => return Shutdown,
+                        // re-open the port right away with the new settings
+                        SessionCloseReason::SerialSettingsChanged => {
+                            tracing::info!("re-opening port with new settings");
+                            continue;
+                        }
+                        _ => {}
                     }
                     // we wait here to prevent any kind of rapid retry scenario if the port opens and immediately fails
                     let delay = self.retry.after_disconnect();
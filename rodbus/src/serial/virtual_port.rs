@@ -0,0 +1,408 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::mpsc;
+
+use crate::common::phys::PhysLayer;
+
+// room for the largest RTU frame
+const BUFFER_SIZE: usize = 1024;
+
+// sender of the bytes received by a port while it's open
+type Receiver = Arc<Mutex<Option<mpsc::UnboundedSender<Vec<u8>>>>>;
+
+/// Create the two ends of a virtual serial line
+///
+/// Like a real serial line, the line doesn't depend on the ports opened at its ends. Each end can be
+/// opened any number of times, one at a time, and the bytes written while the other end is closed
+/// are lost.
+pub(crate) fn virtual_pair() -> (VirtualPort, VirtualPort) {
+    let a = Receiver::default();
+    let b = Receiver::default();
+    (
+        VirtualPort::new("A", a.clone(), b.clone()),
+        VirtualPort::new("B", b, a),
+    )
+}
+
+/// One end of a virtual serial line created by [`virtual_pair`]
+pub(crate) struct VirtualPort {
+    name: &'static str,
+    local: Receiver,
+    remote: Receiver,
+    noise: Noise,
+}
+
+/// Corrupts the bytes written by a [`VirtualPort`] as if there was noise on the line
+#[derive(Clone, Default)]
+pub(crate) struct Noise {
+    corrupt_next_write: Arc<AtomicBool>,
+}
+
+impl Noise {
+    /// flip the bits of the last byte of the next write, breaking the CRC of the frame
+    pub(crate) fn corrupt_next_write(&self) {
+        self.corrupt_next_write.store(true, Ordering::Relaxed);
+    }
+
+    fn apply(&self, data: &mut [u8]) {
+        if self.corrupt_next_write.swap(false, Ordering::Relaxed) {
+            if let Some(x) = data.last_mut() {
+                *x = !*x;
+            }
+        }
+    }
+}
+
+impl VirtualPort {
+    fn new(name: &'static str, local: Receiver, remote: Receiver) -> Self {
+        Self {
+            name,
+            local,
+            remote,
+            noise: Noise::default(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// noise applied to the bytes written by this port
+    pub(crate) fn noise(&self) -> Noise {
+        self.noise.clone()
+    }
+
+    pub(crate) fn open(&mut self) -> Result<(String, PhysLayer), std::io::Error> {
+        let (port, line) = tokio::io::duplex(BUFFER_SIZE);
+        let (tx, rx) = mpsc::unbounded_channel();
+        // replaces the sender of a previous port that hasn't noticed it was closed yet
+        *self.local.lock().unwrap() = Some(tx.clone());
+        tokio::spawn(pump(
+            line,
+            tx,
+            rx,
+            self.local.clone(),
+            self.remote.clone(),
+            self.noise.clone(),
+        ));
+        Ok((self.name.to_string(), PhysLayer::new_memory(port)))
+    }
+}
+
+/// move bytes between an open port and the line until the port is closed
+async fn pump(
+    line: DuplexStream,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    mut rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    local: Receiver,
+    remote: Receiver,
+    noise: Noise,
+) {
+    let (mut reader, mut writer) = tokio::io::split(line);
+    let mut buffer = [0; BUFFER_SIZE];
+    loop {
+        tokio::select! {
+            count = reader.read(&mut buffer) => {
+                let mut data = match count {
+                    Ok(0) | Err(_) => break,
+                    Ok(count) => buffer[..count].to_vec(),
+                };
+                noise.apply(&mut data);
+                // lost if the other end isn't open
+                if let Some(remote) = remote.lock().unwrap().as_ref() {
+                    let _ = remote.send(data);
+                }
+            }
+            data = rx.recv() => {
+                match data {
+                    Some(data) => {
+                        if writer.write_all(&data).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    // stop receiving unless the end was already opened again
+    let mut local = local.lock().unwrap();
+    if local.as_ref().is_some_and(|x| x.same_channel(&tx)) {
+        *local = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::client::{Channel, Listener, PortState, RequestParam, WriteMultiple};
+    use crate::decode::DecodeLevel;
+    use crate::error::{FrameParseError, RequestError};
+    use crate::maybe_async::MaybeAsync;
+    use crate::serial::{PortSource, SerialSettings};
+    use crate::server::{
+        DatabaseLayout, MemoryDatabase, RequestHandler, ServerHandlerMap, ServerHandlerType,
+        ServerOptions,
+    };
+    use crate::types::{AddressRange, Indexed, UnitId};
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+    const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+    struct PortStates {
+        tx: mpsc::UnboundedSender<PortState>,
+    }
+
+    impl Listener<PortState> for PortStates {
+        fn update(&mut self, value: PortState) -> MaybeAsync<()> {
+            let _ = self.tx.send(value);
+            MaybeAsync::ready(())
+        }
+    }
+
+    struct Rtu {
+        channel: Channel,
+        states: mpsc::UnboundedReceiver<PortState>,
+        client_noise: Noise,
+        server_noise: Noise,
+        _server: crate::server::ServerHandle,
+    }
+
+    impl Rtu {
+        async fn wait_for_open(&mut self) {
+            loop {
+                if self.states.recv().await.unwrap() == PortState::Open {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn database() -> MemoryDatabase {
+        let mut db = MemoryDatabase::new(DatabaseLayout::Contiguous);
+        for i in 0..10 {
+            db.add_coil(i, false);
+            db.add_discrete_input(i, i % 2 == 0);
+            db.add_holding_register(i, 0);
+            db.add_input_register(i, 0xCAF0 + i);
+        }
+        db
+    }
+
+    async fn spawn_rtu(handlers: ServerHandlerMap<MemoryDatabase>) -> Rtu {
+        let (client, server) = virtual_pair();
+        let client_noise = client.noise();
+        let server_noise = server.noise();
+
+        let server = crate::server::spawn_rtu_server_task_on(
+            PortSource::Virtual(server),
+            SerialSettings::default(),
+            crate::doubling_retry_strategy(RETRY_DELAY, RETRY_DELAY),
+            handlers,
+            DecodeLevel::default(),
+            ServerOptions::default(),
+        );
+
+        let (tx, states) = mpsc::unbounded_channel();
+        let channel = Channel::spawn_rtu(
+            PortSource::Virtual(client),
+            SerialSettings::default(),
+            10,
+            crate::doubling_retry_strategy(RETRY_DELAY, RETRY_DELAY),
+            DecodeLevel::default(),
+            Some(Box::new(PortStates { tx })),
+        );
+        channel.enable().await.unwrap();
+
+        let mut rtu = Rtu {
+            channel,
+            states,
+            client_noise,
+            server_noise,
+            _server: server,
+        };
+        rtu.wait_for_open().await;
+        rtu
+    }
+
+    fn single(db: &ServerHandlerType<MemoryDatabase>) -> ServerHandlerMap<MemoryDatabase> {
+        ServerHandlerMap::single(UnitId::new(1), db.clone())
+    }
+
+    fn param(unit_id: u8) -> RequestParam {
+        RequestParam::new(UnitId::new(unit_id), TIMEOUT)
+    }
+
+    fn range(start: u16, count: u16) -> AddressRange {
+        AddressRange::try_from(start, count).unwrap()
+    }
+
+    async fn read_holding_register(rtu: &mut Rtu, unit_id: u8) -> Result<u16, RequestError> {
+        let values = rtu
+            .channel
+            .read_holding_registers(param(unit_id), range(0, 1))
+            .await?;
+        Ok(values[0].value)
+    }
+
+    #[tokio::test]
+    async fn ports_exchange_bytes_only_while_both_ends_are_open() {
+        let (mut a, mut b) = virtual_pair();
+        let decode = DecodeLevel::nothing();
+
+        let (_, mut phys_a) = a.open().unwrap();
+        phys_a.write(&[0x01, 0x02], decode).await.unwrap();
+        // the bytes were written while the other end was closed
+        let (_, mut phys_b) = b.open().unwrap();
+        phys_b.write(&[0x03], decode).await.unwrap();
+
+        let mut buffer = [0; 8];
+        let count = phys_a.read(&mut buffer, decode).await.unwrap();
+        assert_eq!(&buffer[..count], &[0x03]);
+
+        // re-open an end while the line stays in place
+        drop(phys_a);
+        let (_, mut phys_a) = a.open().unwrap();
+        b.noise().corrupt_next_write();
+        phys_b.write(&[0x04, 0x05], decode).await.unwrap();
+        let count = phys_a.read(&mut buffer, decode).await.unwrap();
+        assert_eq!(&buffer[..count], &[0x04, 0xFA]);
+    }
+
+    #[tokio::test]
+    async fn client_and_server_exchange_every_function_code() {
+        let db = database().wrap();
+        let mut rtu = spawn_rtu(single(&db)).await;
+        let channel = &mut rtu.channel;
+
+        assert_eq!(
+            channel
+                .read_discrete_inputs(param(1), range(0, 3))
+                .await
+                .unwrap(),
+            vec![
+                Indexed::new(0, true),
+                Indexed::new(1, false),
+                Indexed::new(2, true)
+            ]
+        );
+        assert_eq!(
+            channel
+                .read_input_registers(param(1), range(1, 2))
+                .await
+                .unwrap(),
+            vec![Indexed::new(1, 0xCAF1), Indexed::new(2, 0xCAF2)]
+        );
+
+        assert_eq!(
+            channel
+                .write_single_coil(param(1), Indexed::new(1, true))
+                .await
+                .unwrap(),
+            Indexed::new(1, true)
+        );
+        assert_eq!(
+            channel
+                .write_multiple_coils(
+                    param(1),
+                    WriteMultiple::from(2, vec![true, false, true]).unwrap()
+                )
+                .await
+                .unwrap(),
+            range(2, 3)
+        );
+        assert_eq!(
+            channel.read_coils(param(1), range(0, 5)).await.unwrap(),
+            vec![
+                Indexed::new(0, false),
+                Indexed::new(1, true),
+                Indexed::new(2, true),
+                Indexed::new(3, false),
+                Indexed::new(4, true)
+            ]
+        );
+
+        assert_eq!(
+            channel
+                .write_single_register(param(1), Indexed::new(0, 0xABCD))
+                .await
+                .unwrap(),
+            Indexed::new(0, 0xABCD)
+        );
+        assert_eq!(
+            channel
+                .write_multiple_registers(param(1), WriteMultiple::from(1, vec![1, 2]).unwrap())
+                .await
+                .unwrap(),
+            range(1, 2)
+        );
+        assert_eq!(
+            channel
+                .read_holding_registers(param(1), range(0, 3))
+                .await
+                .unwrap(),
+            vec![
+                Indexed::new(0, 0xABCD),
+                Indexed::new(1, 1),
+                Indexed::new(2, 2)
+            ]
+        );
+
+        // the values reached the handler
+        let db = db.lock().unwrap();
+        assert_eq!(db.get_coil(4), Some(true));
+        assert_eq!(db.get_holding_register(2), Some(2));
+    }
+
+    #[tokio::test]
+    async fn recovers_from_corrupted_frames() {
+        let db = database().wrap();
+        let mut rtu = spawn_rtu(single(&db)).await;
+
+        // the server closes its port on the bad request and doesn't respond
+        rtu.client_noise.corrupt_next_write();
+        assert_eq!(
+            read_holding_register(&mut rtu, 1).await,
+            Err(RequestError::ResponseTimeout)
+        );
+        assert_eq!(read_holding_register(&mut rtu, 1).await, Ok(0));
+
+        // the client rejects the bad response and re-opens its port
+        rtu.server_noise.corrupt_next_write();
+        assert!(matches!(
+            read_holding_register(&mut rtu, 1).await,
+            Err(RequestError::BadFrame(
+                FrameParseError::CrcValidationFailure(_, _)
+            ))
+        ));
+        rtu.wait_for_open().await;
+        assert_eq!(read_holding_register(&mut rtu, 1).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn broadcast_writes_reach_every_unit_without_a_response() {
+        let first = database().wrap();
+        let second = database().wrap();
+        let mut handlers = ServerHandlerMap::new();
+        handlers.add(UnitId::new(1), first.clone());
+        handlers.add(UnitId::new(2), second.clone());
+        let mut rtu = spawn_rtu(handlers).await;
+
+        let result = rtu
+            .channel
+            .write_single_register(
+                RequestParam::new(UnitId::broadcast(), Duration::from_millis(100)),
+                Indexed::new(0, 0x1234),
+            )
+            .await;
+        assert_eq!(result, Err(RequestError::ResponseTimeout));
+
+        assert_eq!(read_holding_register(&mut rtu, 1).await, Ok(0x1234));
+        assert_eq!(read_holding_register(&mut rtu, 2).await, Ok(0x1234));
+    }
+}
@@ -60,6 +60,84 @@ impl WildcardIPv4 {
     }
 }
 
+/// Represents an IPv4 or IPv6 subnet in CIDR notation, e.g. "10.0.0.0/8" or "fe80::/10"
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct IpSubnet {
+    addr: std::net::IpAddr,
+    prefix: u8,
+}
+
+/// Error returned when a subnet is not in the correct format or the prefix length is too long
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BadIpSubnet;
+
+impl IpSubnet {
+    /// Create a subnet from a network address and a prefix length
+    ///
+    /// The prefix may not exceed 32 bits for IPv4 or 128 bits for IPv6. Any host bits in the
+    /// address beyond the prefix length are ignored when matching.
+    pub fn new(addr: std::net::IpAddr, prefix: u8) -> Result<Self, BadIpSubnet> {
+        let max = match addr {
+            std::net::IpAddr::V4(_) => 32,
+            std::net::IpAddr::V6(_) => 128,
+        };
+        if prefix > max {
+            return Err(BadIpSubnet);
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    /// Create a subnet that matches a single address
+    pub fn host(addr: std::net::IpAddr) -> Self {
+        let prefix = match addr {
+            std::net::IpAddr::V4(_) => 32,
+            std::net::IpAddr::V6(_) => 128,
+        };
+        Self { addr, prefix }
+    }
+
+    /// Network address of the subnet
+    pub fn addr(&self) -> std::net::IpAddr {
+        self.addr
+    }
+
+    /// Prefix length of the subnet in bits
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub(crate) fn matches(&self, addr: std::net::IpAddr) -> bool {
+        match (self.addr, addr) {
+            (std::net::IpAddr::V4(net), std::net::IpAddr::V4(x)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                (u32::from(net) & mask) == (u32::from(x) & mask)
+            }
+            (std::net::IpAddr::V6(net), std::net::IpAddr::V6(x)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                (u128::from(net) & mask) == (u128::from(x) & mask)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpSubnet {
+    type Err = BadIpSubnet;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = s.split_once('/').ok_or(BadIpSubnet)?;
+        let addr: std::net::IpAddr = addr.parse().map_err(|_| BadIpSubnet)?;
+        let prefix: u8 = prefix.parse().map_err(|_| BadIpSubnet)?;
+        Self::new(addr, prefix)
+    }
+}
+
+impl std::fmt::Display for IpSubnet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
 /// Address filter used to control which master address(es) may connect to an outstation.
 ///
 /// Note: User code cannot exhaustively match against this enum as new variants may be added in the future.
@@ -74,6 +152,21 @@ pub enum AddressFilter {
     AnyOf(std::collections::HashSet<std::net::IpAddr>),
     /// Matches against an IPv4 address with wildcards
     WildcardIpv4(WildcardIPv4),
+    /// Allow any address within an IPv4 or IPv6 subnet
+    Subnet(IpSubnet),
+    /// Allow any address within any of a list of subnets
+    ///
+    /// Individual addresses may be included using [`IpSubnet::host`]
+    AnyOfSubnets(Vec<IpSubnet>),
+    /// Reject any address within the deny list, otherwise apply the `allow` filter
+    ///
+    /// The deny list is always evaluated before the allow rules
+    Deny {
+        /// Subnets that are always rejected
+        deny: Vec<IpSubnet>,
+        /// Filter applied to addresses that are not in the deny list
+        allow: Box<AddressFilter>,
+    },
 }
 
 impl AddressFilter {
@@ -83,13 +176,18 @@ impl AddressFilter {
             AddressFilter::Exact(x) => *x == addr,
             AddressFilter::AnyOf(set) => set.contains(&addr),
             AddressFilter::WildcardIpv4(wc) => wc.matches(addr),
+            AddressFilter::Subnet(subnet) => subnet.matches(addr),
+            AddressFilter::AnyOfSubnets(subnets) => subnets.iter().any(|x| x.matches(addr)),
+            AddressFilter::Deny { deny, allow } => {
+                !deny.iter().any(|x| x.matches(addr)) && allow.matches(addr)
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{BadIpv4Wildcard, WildcardIPv4};
+    use super::{AddressFilter, BadIpSubnet, BadIpv4Wildcard, IpSubnet, WildcardIPv4};
     use std::net::IpAddr;
 
     #[test]
@@ -148,4 +246,68 @@ mod test {
         assert!(wc.matches(ip1));
         assert!(!wc.matches(ip2));
     }
+
+    #[test]
+    fn parses_ipv4_and_ipv6_subnets() {
+        let v4: IpSubnet = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(v4.addr(), "10.0.0.0".parse::<IpAddr>().unwrap());
+        assert_eq!(v4.prefix(), 8);
+
+        let v6: IpSubnet = "fe80::/10".parse().unwrap();
+        assert_eq!(v6.addr(), "fe80::".parse::<IpAddr>().unwrap());
+        assert_eq!(v6.prefix(), 10);
+    }
+
+    #[test]
+    fn rejects_bad_subnets() {
+        let bad_input = [
+            "10.0.0.0",
+            "10.0.0.0/33",
+            "fe80::/129",
+            "10.0.0/8",
+            "10.0.0.0/",
+            "10.0.0.0/8/8",
+        ];
+
+        for x in bad_input {
+            let res: Result<IpSubnet, BadIpSubnet> = x.parse();
+            assert_eq!(res, Err(BadIpSubnet), "{x}");
+        }
+    }
+
+    #[test]
+    fn subnet_matching_works() {
+        let v4: IpSubnet = "10.0.0.0/8".parse().unwrap();
+        assert!(v4.matches("10.200.3.4".parse().unwrap()));
+        assert!(!v4.matches("11.0.0.1".parse().unwrap()));
+        assert!(!v4.matches("::1".parse().unwrap()));
+
+        let v6: IpSubnet = "2001:db8::/48".parse().unwrap();
+        assert!(v6.matches("2001:db8:0:ffff::1".parse().unwrap()));
+        assert!(!v6.matches("2001:db8:1::1".parse().unwrap()));
+        assert!(!v6.matches("10.0.0.1".parse().unwrap()));
+
+        let all: IpSubnet = "0.0.0.0/0".parse().unwrap();
+        assert!(all.matches("1.2.3.4".parse().unwrap()));
+
+        let host = IpSubnet::host("192.168.0.1".parse().unwrap());
+        assert!(host.matches("192.168.0.1".parse().unwrap()));
+        assert!(!host.matches("192.168.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn deny_list_is_evaluated_before_allow_rules() {
+        let filter = AddressFilter::Deny {
+            deny: vec![IpSubnet::host("10.0.0.5".parse().unwrap())],
+            allow: Box::new(AddressFilter::AnyOfSubnets(vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "fe80::/10".parse().unwrap(),
+            ])),
+        };
+
+        assert!(filter.matches("10.0.0.4".parse().unwrap()));
+        assert!(filter.matches("fe80::1".parse().unwrap()));
+        assert!(!filter.matches("10.0.0.5".parse().unwrap()));
+        assert!(!filter.matches("192.168.0.1".parse().unwrap()));
+    }
 }
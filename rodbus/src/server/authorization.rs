@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use crate::common::function::FunctionCode;
+use crate::server::{Authorization, AuthorizationHandler};
+use crate::types::{AddressRange, UnitId};
+
+const READ_FUNCTIONS: [FunctionCode; 4] = [
+    FunctionCode::ReadCoils,
+    FunctionCode::ReadDiscreteInputs,
+    FunctionCode::ReadHoldingRegisters,
+    FunctionCode::ReadInputRegisters,
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Rule {
+    function: FunctionCode,
+    unit_id: Option<UnitId>,
+    range: Option<AddressRange>,
+}
+
+impl Rule {
+    fn allows(&self, function: FunctionCode, unit_id: UnitId, range: AddressRange) -> bool {
+        if self.function != function {
+            return false;
+        }
+        if self.unit_id.is_some_and(|x| x != unit_id) {
+            return false;
+        }
+        match self.range {
+            None => true,
+            Some(allowed) => {
+                let end = range.start as u32 + range.count as u32;
+                let allowed_end = allowed.start as u32 + allowed.count as u32;
+                range.start >= allowed.start && end <= allowed_end
+            }
+        }
+    }
+}
+
+/// [`AuthorizationHandler`] that authorizes requests from a table of permissions per role
+///
+/// Each permission allows one function code, optionally restricted to a unit id and to a range
+/// of addresses. A request is allowed if any permission of the role covers its function, its
+/// unit id, and all of its addresses. Requests from roles that aren't in the table are denied.
+///
+/// ```
+/// use rodbus::server::*;
+/// use rodbus::*;
+///
+/// let handler = RoleBasedAuthorizationHandler::builder()
+///     .role("viewer")
+///     .allow_reads()
+///     .role("operator")
+///     .allow_reads()
+///     .unit(UnitId::new(1))
+///     .allow_write_coils(AddressRange::try_from(0, 10).unwrap())
+///     .build();
+///
+/// let range = AddressRange::try_from(2, 3).unwrap();
+/// assert_eq!(handler.write_multiple_coils(UnitId::new(1), range, "operator"), Authorization::Allow);
+/// assert_eq!(handler.write_multiple_coils(UnitId::new(2), range, "operator"), Authorization::Deny);
+/// assert_eq!(handler.write_multiple_coils(UnitId::new(1), range, "viewer"), Authorization::Deny);
+/// assert_eq!(handler.read_coils(UnitId::new(1), range, "guest"), Authorization::Deny);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RoleBasedAuthorizationHandler {
+    roles: HashMap<String, Vec<Rule>>,
+}
+
+impl RoleBasedAuthorizationHandler {
+    /// Start building a handler without any role
+    pub fn builder() -> RoleBasedAuthorizationBuilder {
+        RoleBasedAuthorizationBuilder {
+            handler: Self::default(),
+        }
+    }
+
+    /// Names of the roles in the table, in no particular order
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.roles.keys().map(String::as_str)
+    }
+
+    fn authorize(
+        &self,
+        function: FunctionCode,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        let allowed = self
+            .roles
+            .get(role)
+            .is_some_and(|rules| rules.iter().any(|x| x.allows(function, unit_id, range)));
+
+        if allowed {
+            Authorization::Allow
+        } else {
+            Authorization::Deny
+        }
+    }
+
+    fn authorize_single(
+        &self,
+        function: FunctionCode,
+        unit_id: UnitId,
+        idx: u16,
+        role: &str,
+    ) -> Authorization {
+        match AddressRange::try_from(idx, 1) {
+            Ok(range) => self.authorize(function, unit_id, range, role),
+            Err(_) => Authorization::Deny,
+        }
+    }
+}
+
+/// Builder returned by [`RoleBasedAuthorizationHandler::builder`]
+#[derive(Debug, Clone)]
+pub struct RoleBasedAuthorizationBuilder {
+    handler: RoleBasedAuthorizationHandler,
+}
+
+impl RoleBasedAuthorizationBuilder {
+    /// Add permissions to a role, creating it if it's not in the table yet
+    pub fn role(self, name: &str) -> RolePermissions {
+        RolePermissions::new(self.handler, name)
+    }
+
+    /// Build the handler
+    pub fn build(self) -> RoleBasedAuthorizationHandler {
+        self.handler
+    }
+}
+
+/// Permissions of a single role, returned by [`RoleBasedAuthorizationBuilder::role`]
+///
+/// Permissions apply to every unit id until [`RolePermissions::unit`] is called.
+#[derive(Debug, Clone)]
+pub struct RolePermissions {
+    handler: RoleBasedAuthorizationHandler,
+    role: String,
+    unit_id: Option<UnitId>,
+}
+
+impl RolePermissions {
+    fn new(mut handler: RoleBasedAuthorizationHandler, role: &str) -> Self {
+        handler.roles.entry(role.to_string()).or_default();
+        Self {
+            handler,
+            role: role.to_string(),
+            unit_id: None,
+        }
+    }
+
+    /// Restrict the permissions added after this call to a unit id
+    pub fn unit(mut self, unit_id: UnitId) -> Self {
+        self.unit_id = Some(unit_id);
+        self
+    }
+
+    /// Apply the permissions added after this call to every unit id
+    pub fn any_unit(mut self) -> Self {
+        self.unit_id = None;
+        self
+    }
+
+    /// Allow a function code, optionally restricted to a range of addresses
+    ///
+    /// A request is only allowed if all of its addresses are within `range`.
+    pub fn allow(mut self, function: FunctionCode, range: Option<AddressRange>) -> Self {
+        let rule = Rule {
+            function,
+            unit_id: self.unit_id,
+            range,
+        };
+        if let Some(rules) = self.handler.roles.get_mut(&self.role) {
+            rules.push(rule);
+        }
+        self
+    }
+
+    /// Allow the four read function codes on any address
+    pub fn allow_reads(self) -> Self {
+        READ_FUNCTIONS
+            .into_iter()
+            .fold(self, |role, function| role.allow(function, None))
+    }
+
+    /// Allow writing single and multiple coils within a range of addresses
+    pub fn allow_write_coils(self, range: AddressRange) -> Self {
+        self.allow(FunctionCode::WriteSingleCoil, Some(range))
+            .allow(FunctionCode::WriteMultipleCoils, Some(range))
+    }
+
+    /// Allow writing single and multiple holding registers within a range of addresses
+    pub fn allow_write_registers(self, range: AddressRange) -> Self {
+        self.allow(FunctionCode::WriteSingleRegister, Some(range))
+            .allow(FunctionCode::WriteMultipleRegisters, Some(range))
+    }
+
+    /// Add permissions to another role
+    pub fn role(self, name: &str) -> RolePermissions {
+        RolePermissions::new(self.handler, name)
+    }
+
+    /// Build the handler
+    pub fn build(self) -> RoleBasedAuthorizationHandler {
+        self.handler
+    }
+}
+
+impl AuthorizationHandler for RoleBasedAuthorizationHandler {
+    fn read_coils(&self, unit_id: UnitId, range: AddressRange, role: &str) -> Authorization {
+        self.authorize(FunctionCode::ReadCoils, unit_id, range, role)
+    }
+
+    fn read_discrete_inputs(
+        &self,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        self.authorize(FunctionCode::ReadDiscreteInputs, unit_id, range, role)
+    }
+
+    fn read_holding_registers(
+        &self,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        self.authorize(FunctionCode::ReadHoldingRegisters, unit_id, range, role)
+    }
+
+    fn read_input_registers(
+        &self,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        self.authorize(FunctionCode::ReadInputRegisters, unit_id, range, role)
+    }
+
+    fn write_single_coil(&self, unit_id: UnitId, idx: u16, role: &str) -> Authorization {
+        self.authorize_single(FunctionCode::WriteSingleCoil, unit_id, idx, role)
+    }
+
+    fn write_single_register(&self, unit_id: UnitId, idx: u16, role: &str) -> Authorization {
+        self.authorize_single(FunctionCode::WriteSingleRegister, unit_id, idx, role)
+    }
+
+    fn write_multiple_coils(
+        &self,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        self.authorize(FunctionCode::WriteMultipleCoils, unit_id, range, role)
+    }
+
+    fn write_multiple_registers(
+        &self,
+        unit_id: UnitId,
+        range: AddressRange,
+        role: &str,
+    ) -> Authorization {
+        self.authorize(FunctionCode::WriteMultipleRegisters, unit_id, range, role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u16, count: u16) -> AddressRange {
+        AddressRange::try_from(start, count).unwrap()
+    }
+
+    fn handler() -> RoleBasedAuthorizationHandler {
+        RoleBasedAuthorizationHandler::builder()
+            .role("viewer")
+            .allow_reads()
+            .role("operator")
+            .allow_reads()
+            .unit(UnitId::new(1))
+            .allow_write_coils(range(10, 10))
+            .any_unit()
+            .allow_write_registers(range(0, 5))
+            .role("nobody")
+            .build()
+    }
+
+    #[test]
+    fn unknown_and_empty_roles_deny_everything() {
+        let handler = handler();
+        for role in ["guest", "nobody", ""] {
+            assert_eq!(
+                handler.read_coils(UnitId::new(1), range(0, 1), role),
+                Authorization::Deny
+            );
+            assert_eq!(
+                handler.write_single_register(UnitId::new(1), 0, role),
+                Authorization::Deny
+            );
+        }
+    }
+
+    #[test]
+    fn reads_are_allowed_on_any_unit_and_address() {
+        let handler = handler();
+        for role in ["viewer", "operator"] {
+            assert_eq!(
+                handler.read_discrete_inputs(UnitId::new(7), range(0, 2000), role),
+                Authorization::Allow
+            );
+            assert_eq!(
+                handler.read_input_registers(UnitId::new(1), range(65530, 6), role),
+                Authorization::Allow
+            );
+        }
+        assert_eq!(
+            handler.write_single_coil(UnitId::new(1), 10, "viewer"),
+            Authorization::Deny
+        );
+    }
+
+    #[test]
+    fn writes_are_restricted_to_the_unit_and_range() {
+        let handler = handler();
+        assert_eq!(
+            handler.write_single_coil(UnitId::new(1), 19, "operator"),
+            Authorization::Allow
+        );
+        assert_eq!(
+            handler.write_single_coil(UnitId::new(2), 19, "operator"),
+            Authorization::Deny
+        );
+        assert_eq!(
+            handler.write_single_coil(UnitId::new(1), 20, "operator"),
+            Authorization::Deny
+        );
+        assert_eq!(
+            handler.write_multiple_coils(UnitId::new(1), range(10, 10), "operator"),
+            Authorization::Allow
+        );
+        assert_eq!(
+            handler.write_multiple_coils(UnitId::new(1), range(9, 2), "operator"),
+            Authorization::Deny
+        );
+        assert_eq!(
+            handler.write_multiple_registers(UnitId::new(3), range(0, 5), "operator"),
+            Authorization::Allow
+        );
+        assert_eq!(
+            handler.write_multiple_registers(UnitId::new(3), range(4, 2), "operator"),
+            Authorization::Deny
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::net::SocketAddr;
+
+use tracing::Instrument;
+
+use crate::decode::DecodeLevel;
+use crate::server::{
+    AddressFilter, RequestHandler, ServerHandle, ServerHandlerMap, ServerOptions, ServerSpawnError,
+};
+use crate::tcp::server::{ServerEndpoint, ServerTask, TcpServerConnectionHandler};
+
+/// Default maximum number of concurrent sessions of a [`ServerConfig`]
+pub const DEFAULT_MAX_SESSIONS: usize = 16;
+
+enum Transport {
+    Tcp,
+    RtuOverTcp,
+    #[cfg(feature = "tls")]
+    Tls(
+        crate::server::TlsServerConfig,
+        Option<std::sync::Arc<dyn crate::server::AuthorizationHandler>>,
+    ),
+}
+
+/// Builder for the servers spawned by [`spawn_tcp_server_task`](crate::server::spawn_tcp_server_task),
+/// [`spawn_rtu_over_tcp_server_task`](crate::server::spawn_rtu_over_tcp_server_task), and the TLS
+/// variants
+///
+/// Settings that aren't specified use the following defaults:
+///
+/// * at most [`DEFAULT_MAX_SESSIONS`] concurrent sessions. The oldest session is closed when a
+///   client connects while the limit is reached.
+/// * [`AddressFilter::Any`]
+/// * [`DecodeLevel::nothing`]
+/// * the defaults of [`ServerOptions`]
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use rodbus::server::*;
+/// use rodbus::*;
+///
+/// struct Handler;
+/// impl RequestHandler for Handler {}
+///
+/// let server = ServerConfig::tcp("0.0.0.0:502".parse()?)
+///     .max_sessions(4)
+///     .spawn(ServerHandlerMap::single(UnitId::new(1), Handler.wrap()))
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ServerConfig {
+    addr: SocketAddr,
+    transport: Transport,
+    max_sessions: usize,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+    options: ServerOptions,
+}
+
+impl ServerConfig {
+    /// Configure a server that accepts TCP connections on `addr`
+    pub fn tcp(addr: SocketAddr) -> Self {
+        Self::new(addr, Transport::Tcp)
+    }
+
+    /// Configure a server that accepts TCP connections carrying RTU frames on `addr`
+    pub fn rtu_over_tcp(addr: SocketAddr) -> Self {
+        Self::new(addr, Transport::RtuOverTcp)
+    }
+
+    /// Configure a server that accepts TLS connections on `addr` and allows all operations for
+    /// any authenticated client, like [`spawn_tls_server_task`](crate::server::spawn_tls_server_task)
+    #[cfg(feature = "tls")]
+    pub fn tls(addr: SocketAddr, tls_config: crate::server::TlsServerConfig) -> Self {
+        Self::new(addr, Transport::Tls(tls_config, None))
+    }
+
+    /// Configure a server that accepts TLS connections on `addr` and authorizes requests based on
+    /// the role of the client, like [`spawn_tls_server_task_with_authz`](crate::server::spawn_tls_server_task_with_authz)
+    #[cfg(feature = "tls")]
+    pub fn tls_with_authz(
+        addr: SocketAddr,
+        tls_config: crate::server::TlsServerConfig,
+        auth_handler: std::sync::Arc<dyn crate::server::AuthorizationHandler>,
+    ) -> Self {
+        Self::new(addr, Transport::Tls(tls_config, Some(auth_handler)))
+    }
+
+    fn new(addr: SocketAddr, transport: Transport) -> Self {
+        Self {
+            addr,
+            transport,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            filter: AddressFilter::Any,
+            decode: DecodeLevel::nothing(),
+            options: ServerOptions::default(),
+        }
+    }
+
+    /// Maximum number of concurrent sessions
+    pub fn max_sessions(mut self, value: usize) -> Self {
+        self.max_sessions = value;
+        self
+    }
+
+    /// Restricts the IP addresses that may connect
+    pub fn filter(mut self, value: AddressFilter) -> Self {
+        self.filter = value;
+        self
+    }
+
+    /// Initial decode level, see [`ServerHandle::set_decode_level`]
+    pub fn decode(mut self, value: DecodeLevel) -> Self {
+        self.decode = value;
+        self
+    }
+
+    /// Additional settings of the sessions
+    pub fn options(mut self, value: ServerOptions) -> Self {
+        self.options = value;
+        self
+    }
+
+    /// Bind the address and spawn the server task onto the runtime
+    ///
+    /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+    pub async fn spawn<T: RequestHandler>(
+        self,
+        handlers: ServerHandlerMap<T>,
+    ) -> Result<ServerHandle, ServerSpawnError> {
+        match self.transport {
+            Transport::Tcp => {
+                crate::server::spawn_tcp_server_task_with_options(
+                    self.max_sessions,
+                    self.addr,
+                    handlers,
+                    self.filter,
+                    self.decode,
+                    self.options,
+                )
+                .await
+            }
+            Transport::RtuOverTcp => {
+                crate::server::spawn_rtu_over_tcp_server_task_with_options(
+                    self.max_sessions,
+                    self.addr,
+                    handlers,
+                    self.filter,
+                    self.decode,
+                    self.options,
+                )
+                .await
+            }
+            #[cfg(feature = "tls")]
+            Transport::Tls(tls_config, auth_handler) => {
+                crate::server::spawn_tls_server_task_with_options(
+                    self.max_sessions,
+                    self.addr,
+                    handlers,
+                    auth_handler,
+                    tls_config,
+                    self.filter,
+                    self.decode,
+                    self.options,
+                )
+                .await
+            }
+        }
+    }
+}
+
+struct Endpoint {
+    addr: SocketAddr,
+    transport: Transport,
+    filter: AddressFilter,
+}
+
+/// Builder for a server that accepts connections on several endpoints, e.g. plain TCP on port 502
+/// and TLS on port 802, with a single [`ServerHandlerMap`] and [`ServerHandle`]
+///
+/// Settings changed through the handle apply to the sessions of every endpoint, e.g.
+/// [`ServerHandle::set_address_filter`] replaces the filter of each endpoint.
+///
+/// The session limit is global: at most [`ServerBuilder::max_sessions`] sessions are active across
+/// all of the endpoints, [`DEFAULT_MAX_SESSIONS`] by default. When a client connects while the
+/// limit is reached, the oldest session of the same endpoint is closed. If that endpoint has no
+/// sessions, the oldest session of any endpoint is closed instead.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use rodbus::server::*;
+/// use rodbus::*;
+///
+/// struct Handler;
+/// impl RequestHandler for Handler {}
+///
+/// let server = ServerBuilder::new(ServerHandlerMap::single(UnitId::new(1), Handler.wrap()))
+///     .add_tcp("0.0.0.0:502".parse()?, AddressFilter::Any)
+///     .add_rtu_over_tcp("0.0.0.0:503".parse()?, AddressFilter::Any)
+///     .decode(DecodeLevel::default())
+///     .spawn()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ServerBuilder<T: RequestHandler> {
+    handlers: ServerHandlerMap<T>,
+    endpoints: Vec<Endpoint>,
+    max_sessions: usize,
+    decode: DecodeLevel,
+    options: ServerOptions,
+}
+
+impl<T: RequestHandler> ServerBuilder<T> {
+    /// Create a builder without any endpoints whose sessions share `handlers`
+    pub fn new(handlers: ServerHandlerMap<T>) -> Self {
+        Self {
+            handlers,
+            endpoints: Vec::new(),
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            decode: DecodeLevel::nothing(),
+            options: ServerOptions::default(),
+        }
+    }
+
+    /// Accept TCP connections on `addr` from the addresses allowed by `filter`
+    pub fn add_tcp(self, addr: SocketAddr, filter: AddressFilter) -> Self {
+        self.add(addr, Transport::Tcp, filter)
+    }
+
+    /// Accept TCP connections carrying RTU frames on `addr` from the addresses allowed by `filter`
+    pub fn add_rtu_over_tcp(self, addr: SocketAddr, filter: AddressFilter) -> Self {
+        self.add(addr, Transport::RtuOverTcp, filter)
+    }
+
+    /// Accept TLS connections on `addr` from the addresses allowed by `filter` and allow all
+    /// operations for any authenticated client
+    #[cfg(feature = "tls")]
+    pub fn add_tls(
+        self,
+        addr: SocketAddr,
+        tls_config: crate::server::TlsServerConfig,
+        filter: AddressFilter,
+    ) -> Self {
+        self.add(addr, Transport::Tls(tls_config, None), filter)
+    }
+
+    /// Accept TLS connections on `addr` from the addresses allowed by `filter` and authorize
+    /// requests based on the role of the client
+    #[cfg(feature = "tls")]
+    pub fn add_tls_with_authz(
+        self,
+        addr: SocketAddr,
+        tls_config: crate::server::TlsServerConfig,
+        auth_handler: std::sync::Arc<dyn crate::server::AuthorizationHandler>,
+        filter: AddressFilter,
+    ) -> Self {
+        self.add(addr, Transport::Tls(tls_config, Some(auth_handler)), filter)
+    }
+
+    fn add(mut self, addr: SocketAddr, transport: Transport, filter: AddressFilter) -> Self {
+        self.endpoints.push(Endpoint {
+            addr,
+            transport,
+            filter,
+        });
+        self
+    }
+
+    /// Maximum number of concurrent sessions across all of the endpoints
+    pub fn max_sessions(mut self, value: usize) -> Self {
+        self.max_sessions = value;
+        self
+    }
+
+    /// Initial decode level, see [`ServerHandle::set_decode_level`]
+    pub fn decode(mut self, value: DecodeLevel) -> Self {
+        self.decode = value;
+        self
+    }
+
+    /// Additional settings of the sessions
+    pub fn options(mut self, value: ServerOptions) -> Self {
+        self.options = value;
+        self
+    }
+
+    /// Bind the address of every endpoint and spawn the server task onto the runtime
+    ///
+    /// Fails if any of the addresses can't be bound, or with [`ServerSpawnError::NoEndpoint`] if
+    /// no endpoint was added.
+    ///
+    /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+    pub async fn spawn(self) -> Result<ServerHandle, ServerSpawnError> {
+        if self.endpoints.is_empty() {
+            return Err(ServerSpawnError::NoEndpoint);
+        }
+
+        let addrs: Vec<SocketAddr> = self.endpoints.iter().map(|x| x.addr).collect();
+        let mut endpoints = Vec::new();
+        for endpoint in self.endpoints {
+            let listener = tokio::net::TcpListener::bind(endpoint.addr).await?;
+            let connection_handler = match endpoint.transport {
+                Transport::Tcp => TcpServerConnectionHandler::Tcp,
+                Transport::RtuOverTcp => TcpServerConnectionHandler::RtuOverTcp,
+                #[cfg(feature = "tls")]
+                Transport::Tls(tls_config, auth_handler) => {
+                    TcpServerConnectionHandler::Tls(tls_config, auth_handler)
+                }
+            };
+            endpoints.push(ServerEndpoint::new(
+                listener,
+                connection_handler,
+                endpoint.filter,
+            ));
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::channel(crate::server::SERVER_SETTING_CHANNEL_CAPACITY);
+
+        let task = async move {
+            ServerTask::with_endpoints(
+                self.max_sessions,
+                endpoints,
+                self.handlers,
+                self.decode,
+                self.options,
+            )
+            .run(rx)
+            .instrument(tracing::info_span!("Modbus-Server", "listen" = ?addrs))
+            .await;
+        };
+
+        tokio::spawn(task);
+
+        Ok(ServerHandle::new(tx))
+    }
+}
@@ -0,0 +1,1134 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::exception::ExceptionCode;
+use crate::server::handler::RequestHandler;
+use crate::server::{WriteCoils, WriteRegisters};
+use crate::types::{AddressRange, Indexed};
+
+/// Determines how a [`MemoryDatabase`] stores the values of each point type
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DatabaseLayout {
+    /// Points are stored in a hash map, suitable for a few points scattered across the address space
+    #[default]
+    Sparse,
+    /// Points are stored in a vector spanning the lowest to highest address of each type,
+    /// suitable for blocks of consecutive points
+    Contiguous,
+}
+
+/// Storage for the points of a single type
+#[derive(Clone, Debug)]
+enum Points<T> {
+    Sparse(HashMap<u16, T>),
+    Contiguous { start: u16, values: Vec<Option<T>> },
+}
+
+impl<T> Points<T>
+where
+    T: Copy,
+{
+    fn new(layout: DatabaseLayout) -> Self {
+        match layout {
+            DatabaseLayout::Sparse => Self::Sparse(HashMap::new()),
+            DatabaseLayout::Contiguous => Self::Contiguous {
+                start: 0,
+                values: Vec::new(),
+            },
+        }
+    }
+
+    fn get_mut(&mut self, index: u16) -> Option<&mut T> {
+        match self {
+            Self::Sparse(map) => map.get_mut(&index),
+            Self::Contiguous { start, values } => index
+                .checked_sub(*start)
+                .and_then(|offset| values.get_mut(offset as usize))
+                .and_then(|x| x.as_mut()),
+        }
+    }
+
+    fn get(&self, index: u16) -> Option<T> {
+        match self {
+            Self::Sparse(map) => map.get(&index).copied(),
+            Self::Contiguous { start, values } => index
+                .checked_sub(*start)
+                .and_then(|offset| values.get(offset as usize))
+                .copied()
+                .flatten(),
+        }
+    }
+
+    fn contains(&self, index: u16) -> bool {
+        self.get(index).is_some()
+    }
+
+    fn add(&mut self, index: u16, value: T) -> bool {
+        match self {
+            Self::Sparse(map) => {
+                if map.contains_key(&index) {
+                    return false;
+                }
+                map.insert(index, value);
+                true
+            }
+            Self::Contiguous { start, values } => {
+                if values.is_empty() {
+                    *start = index;
+                } else if index < *start {
+                    // grow the storage downwards
+                    let count = (*start - index) as usize;
+                    values.splice(0..0, std::iter::repeat(None).take(count));
+                    *start = index;
+                }
+
+                let offset = (index - *start) as usize;
+                if offset >= values.len() {
+                    values.resize(offset + 1, None);
+                }
+
+                let slot = &mut values[offset];
+                if slot.is_some() {
+                    return false;
+                }
+                *slot = Some(value);
+                true
+            }
+        }
+    }
+
+    fn update(&mut self, index: u16, value: T) -> bool {
+        self.replace(index, value).is_some()
+    }
+
+    /// replace the value of an existing point, returning the previous value
+    fn replace(&mut self, index: u16, value: T) -> Option<T> {
+        self.get_mut(index).map(|x| std::mem::replace(x, value))
+    }
+
+    fn delete(&mut self, index: u16) -> bool {
+        match self {
+            Self::Sparse(map) => map.remove(&index).is_some(),
+            Self::Contiguous { start, values } => index
+                .checked_sub(*start)
+                .and_then(|offset| values.get_mut(offset as usize))
+                .and_then(|x| x.take())
+                .is_some(),
+        }
+    }
+
+    fn contains_all(&self, range: AddressRange) -> bool {
+        range.iter().all(|x| self.contains(x))
+    }
+
+    /// add the point or update its value if it already exists
+    fn set(&mut self, index: u16, value: T) {
+        if !self.update(index, value) {
+            self.add(index, value);
+        }
+    }
+
+    fn to_map(&self) -> BTreeMap<u16, T> {
+        match self {
+            Self::Sparse(map) => map.iter().map(|(index, value)| (*index, *value)).collect(),
+            Self::Contiguous { start, values } => values
+                .iter()
+                .enumerate()
+                .filter_map(|(offset, value)| value.map(|x| (*start + offset as u16, x)))
+                .collect(),
+        }
+    }
+}
+
+/// Values of every point of a [`MemoryDatabase`] at a point in time
+///
+/// Created with [`MemoryDatabase::snapshot`] and applied with [`MemoryDatabase::restore`].
+/// With the `serde` feature, the snapshot can be serialized to persist the values across restarts,
+/// see [`spawn_persist_task`](crate::server::spawn_persist_task).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatabaseSnapshot {
+    /// Value of each coil by address
+    pub coils: BTreeMap<u16, bool>,
+    /// Value of each discrete input by address
+    pub discrete_inputs: BTreeMap<u16, bool>,
+    /// Value of each holding register by address
+    pub holding_registers: BTreeMap<u16, u16>,
+    /// Value of each input register by address
+    pub input_registers: BTreeMap<u16, u16>,
+}
+
+/// Value of a point before and after a client wrote it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ValueChange<T> {
+    /// Address of the point
+    pub index: u16,
+    /// Value before the write
+    pub old: T,
+    /// Value after the write
+    pub new: T,
+}
+
+/// Points written by a client, reported to the listener set with [`MemoryDatabase::set_write_listener`]
+///
+/// Every write request produces a single event. A WriteMultipleRegisters request
+/// results in one event containing a change for every register in the request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointChange<'a> {
+    /// Coils written via WriteSingleCoil or WriteMultipleCoils
+    Coils(&'a [ValueChange<bool>]),
+    /// Holding registers written via WriteSingleRegister or WriteMultipleRegisters
+    HoldingRegisters(&'a [ValueChange<u16>]),
+}
+
+/// Callback invoked synchronously by [`MemoryDatabase`] after a client writes points
+pub type WriteListener = Box<dyn FnMut(PointChange) + Send>;
+
+/// point types that clients may write
+trait Writable: Copy {
+    fn to_point_change(changes: &[ValueChange<Self>]) -> PointChange<'_>;
+}
+
+impl Writable for bool {
+    fn to_point_change(changes: &[ValueChange<Self>]) -> PointChange<'_> {
+        PointChange::Coils(changes)
+    }
+}
+
+impl Writable for u16 {
+    fn to_point_change(changes: &[ValueChange<Self>]) -> PointChange<'_> {
+        PointChange::HoldingRegisters(changes)
+    }
+}
+
+/// Order of the registers of a value that spans multiple holding registers
+///
+/// The bytes within each register are always transmitted most significant byte first.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WordOrder {
+    /// The most significant register is at the lowest address
+    #[default]
+    BigEndian,
+    /// The least significant register is at the lowest address
+    LittleEndian,
+}
+
+impl WordOrder {
+    /// split the lowest `count` words of a value into registers
+    fn split(self, value: u64, count: u16) -> Vec<u16> {
+        let words = (0..count).rev().map(|i| (value >> (16 * i)) as u16);
+        match self {
+            Self::BigEndian => words.collect(),
+            Self::LittleEndian => words.rev().collect(),
+        }
+    }
+
+    /// join registers produced by `split`
+    fn join(self, registers: &[u16]) -> u64 {
+        let join = |acc: u64, x: &u16| (acc << 16) | *x as u64;
+        match self {
+            Self::BigEndian => registers.iter().fold(0, join),
+            Self::LittleEndian => registers.iter().rev().fold(0, join),
+        }
+    }
+}
+
+/// Types of values that span multiple holding registers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TypedKind {
+    F32,
+    U32,
+    U64,
+    String,
+}
+
+/// Definition of a value stored in consecutive holding registers
+#[derive(Copy, Clone, Debug)]
+struct TypedPoint {
+    kind: TypedKind,
+    order: WordOrder,
+    count: u16,
+    /// size of the value in bytes, which is odd for strings of an odd length
+    len: u16,
+}
+
+impl TypedPoint {
+    fn new(kind: TypedKind, order: WordOrder, count: u16) -> Self {
+        Self {
+            kind,
+            order,
+            count,
+            len: 2 * count,
+        }
+    }
+
+    fn string(len: u16) -> Self {
+        let count = (len as u32).div_ceil(2) as u16;
+        Self {
+            len,
+            ..Self::new(TypedKind::String, WordOrder::BigEndian, count)
+        }
+    }
+
+    /// address of the last register, which may not fit in a u16 if the point is invalid
+    fn last(&self, start: u16) -> u32 {
+        start as u32 + self.count as u32 - 1
+    }
+}
+
+/// pack the bytes of a string two per register, padding it with zeros
+fn string_to_registers(value: &str, len: u16, count: u16) -> Option<Vec<u16>> {
+    let bytes = value.as_bytes();
+    if bytes.len() > len as usize {
+        return None;
+    }
+    let mut registers: Vec<u16> = bytes
+        .chunks(2)
+        .map(|x| u16::from_be_bytes([x[0], x.get(1).copied().unwrap_or(0)]))
+        .collect();
+    registers.resize(count as usize, 0);
+    Some(registers)
+}
+
+fn registers_to_string(registers: &[u16]) -> String {
+    let mut bytes: Vec<u8> = registers.iter().flat_map(|x| x.to_be_bytes()).collect();
+    while bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// In-memory storage of coils, discrete inputs, holding registers, and input registers
+/// that implements [`RequestHandler`]
+///
+/// Reads of points that were never added fail with [`ExceptionCode::IllegalDataAddress`].
+/// Writes from clients only modify existing coils and holding registers. Multiple writes
+/// are applied atomically: if any address in the request does not exist, nothing is written.
+/// Use [`MemoryDatabase::set_write_listener`] to be notified of the values written by clients.
+///
+/// ```
+/// use rodbus::server::*;
+/// use rodbus::UnitId;
+///
+/// let mut db = MemoryDatabase::new(DatabaseLayout::Contiguous);
+/// for index in 0..10 {
+///     db.add_holding_register(index, 0);
+/// }
+///
+/// let db = db.wrap();
+/// let map = ServerHandlerMap::single(UnitId::new(1), db.clone());
+///
+/// // modify multiple points atomically while the server is running
+/// db.update(|db| {
+///     db.update_holding_register(0, 42);
+///     db.update_holding_register(1, 43);
+/// });
+/// ```
+///
+/// Values wider than a register are added with [`MemoryDatabase::add_f32`],
+/// [`MemoryDatabase::add_u32`], [`MemoryDatabase::add_u64`], and [`MemoryDatabase::add_string`].
+/// They are stored in consecutive holding registers that clients read and write like any other
+/// register, except that writes covering only part of such a value are rejected with
+/// [`ExceptionCode::IllegalDataAddress`]. The server locks the database for the duration of each
+/// request, so a client never reads half of a value that is updated locally.
+///
+/// ```
+/// use rodbus::server::*;
+///
+/// let mut db = MemoryDatabase::new(DatabaseLayout::Contiguous);
+/// // occupies registers 0 and 1, with the most significant word in register 0
+/// db.add_f32(0, WordOrder::BigEndian, 1.5);
+/// // occupies registers 2 to 5, with the least significant word in register 2
+/// db.add_u64(2, WordOrder::LittleEndian, 0);
+/// // up to 8 characters in registers 6 to 9
+/// db.add_string(6, 8);
+///
+/// assert_eq!(db.get_holding_register(0), Some(0x3FC0));
+/// assert_eq!(db.get_holding_register(1), Some(0x0000));
+///
+/// let db = db.wrap();
+/// db.update(|db| {
+///     db.update_f32(0, 2.5);
+///     db.update_u64(2, 0x0001_0002_0003_0004);
+///     db.update_string(6, "pump-1");
+/// });
+/// ```
+pub struct MemoryDatabase {
+    coils: Points<bool>,
+    discrete_inputs: Points<bool>,
+    holding_registers: Points<u16>,
+    input_registers: Points<u16>,
+    // values spanning multiple holding registers, by address of their first register
+    typed: BTreeMap<u16, TypedPoint>,
+    listener: Option<WriteListener>,
+    // number of point changes, observed by the persist task
+    revision: tokio::sync::watch::Sender<u64>,
+}
+
+impl std::fmt::Debug for MemoryDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryDatabase")
+            .field("coils", &self.coils)
+            .field("discrete_inputs", &self.discrete_inputs)
+            .field("holding_registers", &self.holding_registers)
+            .field("input_registers", &self.input_registers)
+            .field("typed", &self.typed)
+            .field("listener", &self.listener.is_some())
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
+
+impl Default for MemoryDatabase {
+    fn default() -> Self {
+        Self::new(DatabaseLayout::default())
+    }
+}
+
+impl MemoryDatabase {
+    /// Create an empty database with the specified storage layout
+    pub fn new(layout: DatabaseLayout) -> Self {
+        Self {
+            coils: Points::new(layout),
+            discrete_inputs: Points::new(layout),
+            holding_registers: Points::new(layout),
+            input_registers: Points::new(layout),
+            typed: BTreeMap::new(),
+            listener: None,
+            revision: tokio::sync::watch::Sender::new(0),
+        }
+    }
+
+    /// Number of point changes since the database was created
+    ///
+    /// Every point that is added, updated, deleted, restored, or written by a client counts as a
+    /// change, even if its value stays the same.
+    pub fn revision(&self) -> u64 {
+        *self.revision.borrow()
+    }
+
+    pub(crate) fn subscribe_revision(&self) -> tokio::sync::watch::Receiver<u64> {
+        self.revision.subscribe()
+    }
+
+    fn bump_revision(&self, count: usize) {
+        if count > 0 {
+            self.revision.send_modify(|x| *x += count as u64);
+        }
+    }
+
+    /// count the change if the point was modified, passing `changed` through
+    fn count_change(&self, changed: bool) -> bool {
+        if changed {
+            self.bump_revision(1);
+        }
+        changed
+    }
+
+    /// Copy the values of every point
+    pub fn snapshot(&self) -> DatabaseSnapshot {
+        DatabaseSnapshot {
+            coils: self.coils.to_map(),
+            discrete_inputs: self.discrete_inputs.to_map(),
+            holding_registers: self.holding_registers.to_map(),
+            input_registers: self.input_registers.to_map(),
+        }
+    }
+
+    /// Apply the values of a snapshot
+    ///
+    /// Points in the snapshot that don't exist are added. Points that aren't in the snapshot keep
+    /// their current value, so an application can add its points with default values and then
+    /// restore the values that were persisted. The write listener is not invoked.
+    pub fn restore(&mut self, snapshot: &DatabaseSnapshot) {
+        for (index, value) in &snapshot.coils {
+            self.coils.set(*index, *value);
+        }
+        for (index, value) in &snapshot.discrete_inputs {
+            self.discrete_inputs.set(*index, *value);
+        }
+        for (index, value) in &snapshot.holding_registers {
+            self.holding_registers.set(*index, *value);
+        }
+        for (index, value) in &snapshot.input_registers {
+            self.input_registers.set(*index, *value);
+        }
+        self.bump_revision(
+            snapshot.coils.len()
+                + snapshot.discrete_inputs.len()
+                + snapshot.holding_registers.len()
+                + snapshot.input_registers.len(),
+        );
+    }
+
+    /// Set a listener that is invoked after clients write coils or holding registers
+    ///
+    /// The listener is only invoked for writes received from the network, never for
+    /// changes made locally via the `add`, `update`, or `delete` methods. It is invoked while
+    /// the database is locked by the server, so it should return quickly.
+    pub fn set_write_listener(&mut self, listener: WriteListener) {
+        self.listener = Some(listener);
+    }
+
+    /// Remove the listener, if any
+    pub fn clear_write_listener(&mut self) {
+        self.listener = None;
+    }
+
+    fn write<T>(
+        points: &mut Points<T>,
+        listener: &mut Option<WriteListener>,
+        revision: &tokio::sync::watch::Sender<u64>,
+        range: AddressRange,
+        values: impl Iterator<Item = Indexed<T>>,
+    ) -> Result<(), ExceptionCode>
+    where
+        T: Writable,
+    {
+        if !points.contains_all(range) {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+
+        match listener {
+            None => {
+                for x in values {
+                    points.replace(x.index, x.value);
+                }
+            }
+            Some(listener) => {
+                let changes: Vec<ValueChange<T>> = values
+                    .filter_map(|x| {
+                        points.replace(x.index, x.value).map(|old| ValueChange {
+                            index: x.index,
+                            old,
+                            new: x.value,
+                        })
+                    })
+                    .collect();
+                listener(T::to_point_change(&changes));
+            }
+        }
+
+        revision.send_modify(|x| *x += range.count as u64);
+        Ok(())
+    }
+
+    /// Add a coil, returning false if it already exists
+    pub fn add_coil(&mut self, index: u16, value: bool) -> bool {
+        let changed = self.coils.add(index, value);
+        self.count_change(changed)
+    }
+
+    /// Add a discrete input, returning false if it already exists
+    pub fn add_discrete_input(&mut self, index: u16, value: bool) -> bool {
+        let changed = self.discrete_inputs.add(index, value);
+        self.count_change(changed)
+    }
+
+    /// Add a holding register, returning false if it already exists
+    pub fn add_holding_register(&mut self, index: u16, value: u16) -> bool {
+        let changed = self.holding_registers.add(index, value);
+        self.count_change(changed)
+    }
+
+    /// Add an input register, returning false if it already exists
+    pub fn add_input_register(&mut self, index: u16, value: u16) -> bool {
+        let changed = self.input_registers.add(index, value);
+        self.count_change(changed)
+    }
+
+    /// Get the value of a coil, if it exists
+    pub fn get_coil(&self, index: u16) -> Option<bool> {
+        self.coils.get(index)
+    }
+
+    /// Get the value of a discrete input, if it exists
+    pub fn get_discrete_input(&self, index: u16) -> Option<bool> {
+        self.discrete_inputs.get(index)
+    }
+
+    /// Get the value of a holding register, if it exists
+    pub fn get_holding_register(&self, index: u16) -> Option<u16> {
+        self.holding_registers.get(index)
+    }
+
+    /// Get the value of an input register, if it exists
+    pub fn get_input_register(&self, index: u16) -> Option<u16> {
+        self.input_registers.get(index)
+    }
+
+    /// Update the value of an existing coil, returning false if it does not exist
+    pub fn update_coil(&mut self, index: u16, value: bool) -> bool {
+        let changed = self.coils.update(index, value);
+        self.count_change(changed)
+    }
+
+    /// Update the value of an existing discrete input, returning false if it does not exist
+    pub fn update_discrete_input(&mut self, index: u16, value: bool) -> bool {
+        let changed = self.discrete_inputs.update(index, value);
+        self.count_change(changed)
+    }
+
+    /// Update the value of an existing holding register, returning false if it does not exist
+    pub fn update_holding_register(&mut self, index: u16, value: u16) -> bool {
+        let changed = self.holding_registers.update(index, value);
+        self.count_change(changed)
+    }
+
+    /// Update the value of an existing input register, returning false if it does not exist
+    pub fn update_input_register(&mut self, index: u16, value: u16) -> bool {
+        let changed = self.input_registers.update(index, value);
+        self.count_change(changed)
+    }
+
+    /// Delete a coil, returning false if it does not exist
+    pub fn delete_coil(&mut self, index: u16) -> bool {
+        let changed = self.coils.delete(index);
+        self.count_change(changed)
+    }
+
+    /// Delete a discrete input, returning false if it does not exist
+    pub fn delete_discrete_input(&mut self, index: u16) -> bool {
+        let changed = self.discrete_inputs.delete(index);
+        self.count_change(changed)
+    }
+
+    /// Delete a holding register, returning false if it does not exist
+    ///
+    /// Deleting any register of a value added with one of the typed methods, e.g.
+    /// [`MemoryDatabase::add_f32`], also removes the definition of the value. Its other registers
+    /// remain as individual registers.
+    pub fn delete_holding_register(&mut self, index: u16) -> bool {
+        let changed = self.holding_registers.delete(index);
+        if changed {
+            if let Some((start, _)) = self.typed_point_containing(index) {
+                self.typed.remove(&start);
+            }
+        }
+        self.count_change(changed)
+    }
+
+    /// Delete an input register, returning false if it does not exist
+    pub fn delete_input_register(&mut self, index: u16) -> bool {
+        let changed = self.input_registers.delete(index);
+        self.count_change(changed)
+    }
+
+    /// Add a 32-bit float stored in the holding registers `index` and `index + 1`
+    ///
+    /// Returns false if any of the registers already exists or if they don't fit in the address space.
+    pub fn add_f32(&mut self, index: u16, order: WordOrder, value: f32) -> bool {
+        let point = TypedPoint::new(TypedKind::F32, order, 2);
+        self.add_typed(index, point, order.split(value.to_bits() as u64, 2))
+    }
+
+    /// Add a 32-bit unsigned integer stored in the holding registers `index` and `index + 1`
+    ///
+    /// Returns false if any of the registers already exists or if they don't fit in the address space.
+    pub fn add_u32(&mut self, index: u16, order: WordOrder, value: u32) -> bool {
+        let point = TypedPoint::new(TypedKind::U32, order, 2);
+        self.add_typed(index, point, order.split(value as u64, 2))
+    }
+
+    /// Add a 64-bit unsigned integer stored in the holding registers `index` to `index + 3`
+    ///
+    /// Returns false if any of the registers already exists or if they don't fit in the address space.
+    pub fn add_u64(&mut self, index: u16, order: WordOrder, value: u64) -> bool {
+        let point = TypedPoint::new(TypedKind::U64, order, 4);
+        self.add_typed(index, point, order.split(value, 4))
+    }
+
+    /// Add an empty string of up to `len` bytes stored in holding registers starting at `index`
+    ///
+    /// Each register holds two bytes, the first one in its most significant half. Strings
+    /// shorter than `len` are padded with zeros. Returns false if `len` is zero, if any of the
+    /// registers already exists, or if they don't fit in the address space.
+    pub fn add_string(&mut self, index: u16, len: u16) -> bool {
+        let point = TypedPoint::string(len);
+        self.add_typed(index, point, vec![0; point.count as usize])
+    }
+
+    /// Get the value of a 32-bit float, if it exists
+    pub fn get_f32(&self, index: u16) -> Option<f32> {
+        self.get_typed(index, TypedKind::F32)
+            .map(|(point, registers)| f32::from_bits(point.order.join(&registers) as u32))
+    }
+
+    /// Get the value of a 32-bit unsigned integer, if it exists
+    pub fn get_u32(&self, index: u16) -> Option<u32> {
+        self.get_typed(index, TypedKind::U32)
+            .map(|(point, registers)| point.order.join(&registers) as u32)
+    }
+
+    /// Get the value of a 64-bit unsigned integer, if it exists
+    pub fn get_u64(&self, index: u16) -> Option<u64> {
+        self.get_typed(index, TypedKind::U64)
+            .map(|(point, registers)| point.order.join(&registers))
+    }
+
+    /// Get the value of a string without its padding, if it exists
+    ///
+    /// Bytes that aren't valid UTF-8 are replaced with `U+FFFD`.
+    pub fn get_string(&self, index: u16) -> Option<String> {
+        self.get_typed(index, TypedKind::String)
+            .map(|(_, registers)| registers_to_string(&registers))
+    }
+
+    /// Update the value of an existing 32-bit float, returning false if it does not exist
+    pub fn update_f32(&mut self, index: u16, value: f32) -> bool {
+        self.update_typed(index, TypedKind::F32, |point| {
+            Some(point.order.split(value.to_bits() as u64, point.count))
+        })
+    }
+
+    /// Update the value of an existing 32-bit unsigned integer, returning false if it does not exist
+    pub fn update_u32(&mut self, index: u16, value: u32) -> bool {
+        self.update_typed(index, TypedKind::U32, |point| {
+            Some(point.order.split(value as u64, point.count))
+        })
+    }
+
+    /// Update the value of an existing 64-bit unsigned integer, returning false if it does not exist
+    pub fn update_u64(&mut self, index: u16, value: u64) -> bool {
+        self.update_typed(index, TypedKind::U64, |point| {
+            Some(point.order.split(value, point.count))
+        })
+    }
+
+    /// Update the value of an existing string, returning false if it does not exist or if
+    /// the value is longer than the string
+    pub fn update_string(&mut self, index: u16, value: &str) -> bool {
+        self.update_typed(index, TypedKind::String, |point| {
+            string_to_registers(value, point.len, point.count)
+        })
+    }
+
+    fn add_typed(&mut self, index: u16, point: TypedPoint, registers: Vec<u16>) -> bool {
+        let range = match AddressRange::try_from(index, point.count) {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+        if range.iter().any(|x| self.holding_registers.contains(x)) {
+            return false;
+        }
+
+        for (index, value) in range.iter().zip(registers) {
+            self.holding_registers.add(index, value);
+        }
+        self.typed.insert(index, point);
+        self.bump_revision(point.count as usize);
+        true
+    }
+
+    fn get_typed(&self, index: u16, kind: TypedKind) -> Option<(TypedPoint, Vec<u16>)> {
+        let point = self.typed.get(&index).filter(|x| x.kind == kind)?;
+        let registers = (0..point.count)
+            .map(|offset| self.holding_registers.get(index + offset))
+            .collect::<Option<Vec<u16>>>()?;
+        Some((*point, registers))
+    }
+
+    fn update_typed(
+        &mut self,
+        index: u16,
+        kind: TypedKind,
+        registers: impl FnOnce(&TypedPoint) -> Option<Vec<u16>>,
+    ) -> bool {
+        let point = match self.typed.get(&index).filter(|x| x.kind == kind) {
+            Some(x) => *x,
+            None => return false,
+        };
+        let registers = match registers(&point) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        for (offset, value) in registers.into_iter().enumerate() {
+            self.holding_registers.update(index + offset as u16, value);
+        }
+        self.bump_revision(point.count as usize);
+        true
+    }
+
+    /// typed point that includes the register, along with the address of its first register
+    fn typed_point_containing(&self, index: u16) -> Option<(u16, TypedPoint)> {
+        let (start, point) = self.typed.range(..=index).next_back()?;
+        if point.last(*start) >= index as u32 {
+            Some((*start, *point))
+        } else {
+            None
+        }
+    }
+
+    /// check if a write to the range would modify only some registers of a typed point
+    fn splits_typed_point(&self, range: AddressRange) -> bool {
+        let first = range.start;
+        let last = range.start + (range.count - 1);
+        let splits_first = self
+            .typed_point_containing(first)
+            .is_some_and(|(start, _)| start < first);
+        let splits_last = self
+            .typed_point_containing(last)
+            .is_some_and(|(start, point)| point.last(start) > last as u32);
+        splits_first || splits_last
+    }
+}
+
+impl RequestHandler for MemoryDatabase {
+    fn read_coil(&self, address: u16) -> Result<bool, ExceptionCode> {
+        self.coils
+            .get(address)
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }
+
+    fn read_discrete_input(&self, address: u16) -> Result<bool, ExceptionCode> {
+        self.discrete_inputs
+            .get(address)
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }
+
+    fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+        self.holding_registers
+            .get(address)
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }
+
+    fn read_input_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+        self.input_registers
+            .get(address)
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }
+
+    fn write_single_coil(&mut self, value: Indexed<bool>) -> Result<(), ExceptionCode> {
+        let range = AddressRange {
+            start: value.index,
+            count: 1,
+        };
+        Self::write(
+            &mut self.coils,
+            &mut self.listener,
+            &self.revision,
+            range,
+            std::iter::once(value),
+        )
+    }
+
+    fn write_single_register(&mut self, value: Indexed<u16>) -> Result<(), ExceptionCode> {
+        let range = AddressRange {
+            start: value.index,
+            count: 1,
+        };
+        if self.splits_typed_point(range) {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        Self::write(
+            &mut self.holding_registers,
+            &mut self.listener,
+            &self.revision,
+            range,
+            std::iter::once(value),
+        )
+    }
+
+    fn write_multiple_coils(&mut self, values: WriteCoils) -> Result<(), ExceptionCode> {
+        Self::write(
+            &mut self.coils,
+            &mut self.listener,
+            &self.revision,
+            values.range,
+            values.iterator,
+        )
+    }
+
+    fn validate_write_registers(&self, range: AddressRange) -> Result<(), ExceptionCode> {
+        if !self.holding_registers.contains_all(range) || self.splits_typed_point(range) {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        Ok(())
+    }
+
+    fn write_multiple_registers(&mut self, values: WriteRegisters) -> Result<(), ExceptionCode> {
+        if self.splits_typed_point(values.range) {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        Self::write(
+            &mut self.holding_registers,
+            &mut self.listener,
+            &self.revision,
+            values.range,
+            values.iterator,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scursor::ReadCursor;
+
+    use super::*;
+    use crate::types::{BitIterator, RegisterIterator};
+
+    fn layouts() -> [DatabaseLayout; 2] {
+        [DatabaseLayout::Sparse, DatabaseLayout::Contiguous]
+    }
+
+    #[test]
+    fn add_get_update_delete() {
+        for layout in layouts() {
+            let mut db = MemoryDatabase::new(layout);
+            assert!(db.add_holding_register(10, 1));
+            assert!(!db.add_holding_register(10, 2));
+            assert!(db.add_holding_register(5, 3));
+            assert!(db.add_holding_register(u16::MAX, 4));
+
+            assert_eq!(db.get_holding_register(10), Some(1));
+            assert_eq!(db.get_holding_register(5), Some(3));
+            assert_eq!(db.get_holding_register(u16::MAX), Some(4));
+            assert_eq!(db.get_holding_register(6), None);
+            assert_eq!(db.get_holding_register(4), None);
+
+            assert!(db.update_holding_register(10, 7));
+            assert!(!db.update_holding_register(6, 7));
+            assert_eq!(db.get_holding_register(10), Some(7));
+
+            assert!(db.delete_holding_register(10));
+            assert!(!db.delete_holding_register(10));
+            assert!(!db.update_holding_register(10, 7));
+            assert_eq!(db.get_holding_register(10), None);
+
+            // point types are independent
+            assert_eq!(db.get_input_register(5), None);
+        }
+    }
+
+    #[test]
+    fn multiple_writes_are_all_or_nothing() {
+        for layout in layouts() {
+            let mut db = MemoryDatabase::new(layout);
+            db.add_coil(0, false);
+            db.add_coil(1, false);
+
+            let range = AddressRange::try_from(0, 3).unwrap();
+            let data = [0b0000_0111];
+            let mut cursor = ReadCursor::new(&data);
+            let values =
+                WriteCoils::new(range, BitIterator::parse_all(range, &mut cursor).unwrap());
+            assert_eq!(
+                db.write_multiple_coils(values),
+                Err(ExceptionCode::IllegalDataAddress)
+            );
+            assert_eq!(db.get_coil(0), Some(false));
+
+            db.add_coil(2, false);
+            let mut cursor = ReadCursor::new(&data);
+            let values =
+                WriteCoils::new(range, BitIterator::parse_all(range, &mut cursor).unwrap());
+            assert_eq!(db.write_multiple_coils(values), Ok(()));
+            assert_eq!(
+                (db.get_coil(0), db.get_coil(1), db.get_coil(2)),
+                (Some(true), Some(true), Some(true))
+            );
+        }
+    }
+
+    #[test]
+    fn snapshot_restores_values_and_counts_changes() {
+        for layout in layouts() {
+            let mut db = MemoryDatabase::new(layout);
+            db.add_coil(1, true);
+            db.add_holding_register(3, 7);
+            db.add_holding_register(5, 9);
+            assert_eq!(db.revision(), 3);
+
+            let snapshot = db.snapshot();
+            assert_eq!(snapshot.coils, BTreeMap::from([(1, true)]));
+            assert_eq!(snapshot.holding_registers, BTreeMap::from([(3, 7), (5, 9)]));
+            assert!(snapshot.discrete_inputs.is_empty());
+
+            // restored values replace existing ones and missing points are added
+            let mut restored = MemoryDatabase::new(layout);
+            restored.add_holding_register(3, 0);
+            restored.add_input_register(0, 11);
+            restored.restore(&snapshot);
+            assert_eq!(restored.get_coil(1), Some(true));
+            assert_eq!(restored.get_holding_register(3), Some(7));
+            assert_eq!(restored.get_holding_register(5), Some(9));
+            assert_eq!(restored.get_input_register(0), Some(11));
+            assert_eq!(restored.revision(), 5);
+
+            // failed local changes are not counted
+            assert!(!restored.update_coil(2, true));
+            restored.write_single_register(Indexed::new(5, 1)).unwrap();
+            assert_eq!(restored.revision(), 6);
+        }
+    }
+
+    fn write_registers(
+        db: &mut MemoryDatabase,
+        start: u16,
+        data: &[u8],
+    ) -> Result<(), ExceptionCode> {
+        let range = AddressRange::try_from(start, (data.len() / 2) as u16).unwrap();
+        let mut cursor = ReadCursor::new(data);
+        let values = WriteRegisters::new(
+            range,
+            RegisterIterator::parse_all(range, &mut cursor).unwrap(),
+        );
+        db.write_multiple_registers(values)
+    }
+
+    #[test]
+    fn splits_and_joins_words_in_both_orders() {
+        assert_eq!(
+            WordOrder::BigEndian.split(0x1122_3344, 2),
+            vec![0x1122, 0x3344]
+        );
+        assert_eq!(
+            WordOrder::LittleEndian.split(0x1122_3344, 2),
+            vec![0x3344, 0x1122]
+        );
+        assert_eq!(
+            WordOrder::BigEndian.split(0x1122_3344_5566_7788, 4),
+            vec![0x1122, 0x3344, 0x5566, 0x7788]
+        );
+        assert_eq!(
+            WordOrder::LittleEndian.split(0x1122_3344_5566_7788, 4),
+            vec![0x7788, 0x5566, 0x3344, 0x1122]
+        );
+
+        for order in [WordOrder::BigEndian, WordOrder::LittleEndian] {
+            for value in [0, 1, 0xFFFF_0000, u32::MAX, 1.5f32.to_bits()] {
+                assert_eq!(order.join(&order.split(value as u64, 2)), value as u64);
+            }
+            assert_eq!(order.join(&order.split(u64::MAX - 1, 4)), u64::MAX - 1);
+        }
+    }
+
+    #[test]
+    fn typed_points_are_stored_in_holding_registers() {
+        for layout in layouts() {
+            let mut db = MemoryDatabase::new(layout);
+            assert!(db.add_f32(0, WordOrder::BigEndian, 1.5));
+            assert!(db.add_f32(2, WordOrder::LittleEndian, 1.5));
+            assert!(db.add_u32(4, WordOrder::LittleEndian, 0xCAFE_BABE));
+            assert!(db.add_u64(6, WordOrder::BigEndian, 0x0102_0304_0506_0708));
+            assert!(db.add_string(10, 5));
+            assert_eq!(db.revision(), 13);
+
+            assert_eq!(db.read_holding_register(0), Ok(0x3FC0));
+            assert_eq!(db.read_holding_register(1), Ok(0x0000));
+            assert_eq!(db.read_holding_register(2), Ok(0x0000));
+            assert_eq!(db.read_holding_register(3), Ok(0x3FC0));
+            assert_eq!(db.read_holding_register(4), Ok(0xBABE));
+            assert_eq!(db.read_holding_register(5), Ok(0xCAFE));
+            assert_eq!(db.read_holding_register(6), Ok(0x0102));
+            assert_eq!(db.read_holding_register(9), Ok(0x0708));
+            // a string of 5 bytes occupies 3 registers
+            assert_eq!(db.read_holding_register(12), Ok(0));
+            assert_eq!(db.get_holding_register(13), None);
+
+            // overlapping or overflowing points are not added
+            assert!(!db.add_u32(1, WordOrder::BigEndian, 0));
+            assert!(!db.add_u64(u16::MAX - 2, WordOrder::BigEndian, 0));
+            assert!(!db.add_string(20, 0));
+
+            assert!(db.update_f32(2, -2.25));
+            assert!(db.update_string(10, "abcde"));
+            assert!(!db.update_string(10, "abcdef"));
+            // the type must match the point
+            assert!(!db.update_u32(0, 1));
+            assert_eq!(db.get_u32(0), None);
+
+            assert_eq!(db.get_f32(0), Some(1.5));
+            assert_eq!(db.get_f32(2), Some(-2.25));
+            assert_eq!(db.get_u32(4), Some(0xCAFE_BABE));
+            assert_eq!(db.get_u64(6), Some(0x0102_0304_0506_0708));
+            assert_eq!(db.get_string(10), Some("abcde".to_string()));
+            assert_eq!(db.read_holding_register(12), Ok(0x6500));
+        }
+    }
+
+    #[test]
+    fn client_writes_must_cover_whole_typed_points() {
+        for layout in layouts() {
+            let mut db = MemoryDatabase::new(layout);
+            db.add_holding_register(0, 0);
+            db.add_u32(1, WordOrder::BigEndian, 0);
+            db.add_u32(3, WordOrder::LittleEndian, 0);
+
+            assert_eq!(
+                db.write_single_register(Indexed::new(1, 1)),
+                Err(ExceptionCode::IllegalDataAddress)
+            );
+            assert_eq!(
+                write_registers(&mut db, 0, &[0, 1, 0, 2]),
+                Err(ExceptionCode::IllegalDataAddress)
+            );
+            assert_eq!(
+                write_registers(&mut db, 2, &[0, 1, 0, 2, 0, 3]),
+                Err(ExceptionCode::IllegalDataAddress)
+            );
+            assert_eq!(db.get_u32(1), Some(0));
+
+            assert_eq!(
+                write_registers(
+                    &mut db,
+                    0,
+                    &[0, 9, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]
+                ),
+                Ok(())
+            );
+            assert_eq!(db.get_holding_register(0), Some(9));
+            assert_eq!(db.get_u32(1), Some(0x1122_3344));
+            assert_eq!(db.get_u32(3), Some(0x7788_5566));
+
+            // deleting a register removes the definition of the point
+            assert!(db.delete_holding_register(4));
+            assert_eq!(db.get_u32(3), None);
+            assert_eq!(db.write_single_register(Indexed::new(3, 1)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn notifies_listener_of_network_writes() {
+        let changes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut db = MemoryDatabase::default();
+        db.add_coil(3, false);
+        for index in 0..3 {
+            db.add_holding_register(index, index);
+        }
+
+        let received = changes.clone();
+        db.set_write_listener(Box::new(move |change| {
+            let description = match change {
+                PointChange::Coils(x) => format!("coils {x:?}"),
+                PointChange::HoldingRegisters(x) => format!("registers {x:?}"),
+            };
+            received.lock().unwrap().push(description);
+        }));
+
+        // local changes are not reported
+        db.update_holding_register(0, 10);
+
+        db.write_single_coil(Indexed::new(3, true)).unwrap();
+
+        let range = AddressRange::try_from(1, 2).unwrap();
+        let data = [0xCA, 0xFE, 0xBB, 0xDD];
+        let mut cursor = ReadCursor::new(&data);
+        let values = WriteRegisters::new(
+            range,
+            RegisterIterator::parse_all(range, &mut cursor).unwrap(),
+        );
+        db.write_multiple_registers(values).unwrap();
+
+        // failed writes are not reported
+        assert!(db.write_single_register(Indexed::new(7, 1)).is_err());
+
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                "coils [ValueChange { index: 3, old: false, new: true }]".to_string(),
+                "registers [ValueChange { index: 1, old: 1, new: 51966 }, ValueChange { index: 2, old: 2, new: 48093 }]".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,39 @@
+/// Errors that prevent a server from being spawned
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ServerSpawnError {
+    /// The listening socket couldn't be bound or its address couldn't be determined, e.g.
+    /// because the address is in use or binding it requires privileges
+    Bind(std::io::Error),
+    /// The TLS configuration is invalid
+    #[cfg(feature = "tls")]
+    TlsConfig(crate::server::TlsError),
+    /// The server was spawned by a [`ServerBuilder`](crate::server::ServerBuilder) without any endpoints
+    NoEndpoint,
+}
+
+impl std::fmt::Display for ServerSpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bind(err) => write!(f, "unable to bind server socket: {err}"),
+            #[cfg(feature = "tls")]
+            Self::TlsConfig(err) => write!(f, "invalid TLS configuration: {err}"),
+            Self::NoEndpoint => f.write_str("server requires at least one endpoint"),
+        }
+    }
+}
+
+impl std::error::Error for ServerSpawnError {}
+
+impl From<std::io::Error> for ServerSpawnError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Bind(err)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl From<crate::server::TlsError> for ServerSpawnError {
+    fn from(err: crate::server::TlsError) -> Self {
+        Self::TlsConfig(err)
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::phys::PhysLayer;
+use crate::decode::DecodeLevel;
+use crate::error::RequestError;
+use crate::exception::ExceptionCode;
+
+/// Misbehavior that a server can simulate when responding to a request
+///
+/// Faults are intended for testing how clients cope with misbehaving devices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// Wait for the specified duration before responding
+    Delay(Duration),
+    /// Process the request, but do not send a response
+    DropResponse,
+    /// Respond with the specified exception without invoking the handler
+    Exception(ExceptionCode),
+    /// Respond with a transaction id that does not match the request (TCP and TLS only)
+    WrongTxId,
+    /// Send the specified number of random bytes instead of the response
+    Garbage(usize),
+    /// Respond with an invalid CRC (RTU only)
+    CorruptCrc,
+}
+
+/// Describes when a [`Fault`] is injected
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    function: Option<u8>,
+    probability: f64,
+    fault: Fault,
+}
+
+impl FaultRule {
+    /// Inject the fault for every request regardless of function code
+    pub fn new(fault: Fault) -> Self {
+        Self {
+            function: None,
+            probability: 1.0,
+            fault,
+        }
+    }
+
+    /// Only inject the fault for requests with the specified raw function code
+    pub fn function(mut self, code: u8) -> Self {
+        self.function = Some(code);
+        self
+    }
+
+    /// Inject the fault with the specified probability in the range [0.0, 1.0]
+    ///
+    /// Values outside of this range are clamped
+    pub fn probability(mut self, value: f64) -> Self {
+        self.probability = value.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// Handle used to configure which faults a server injects into its responses
+///
+/// Clones of the injector share the same rules, so the rules may be modified at runtime
+/// after the injector has been passed to [`ServerOptions::fault_injector`](crate::server::ServerOptions::fault_injector).
+#[derive(Clone, Debug)]
+pub struct FaultInjector {
+    inner: Arc<Mutex<FaultInjectorState>>,
+}
+
+#[derive(Debug)]
+struct FaultInjectorState {
+    rules: Vec<FaultRule>,
+    rng: XorShift,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultInjector {
+    /// Create an injector without any rules that uses a time-based random seed
+    pub fn new() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(seed)
+    }
+
+    /// Create an injector without any rules that uses a fixed random seed for reproducible tests
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(FaultInjectorState {
+                rules: Vec::new(),
+                rng: XorShift::new(seed),
+            })),
+        }
+    }
+
+    /// Add a rule to the injector
+    pub fn add_rule(&self, rule: FaultRule) {
+        self.inner.lock().unwrap().rules.push(rule);
+    }
+
+    /// Remove all rules from the injector
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().rules.clear();
+    }
+
+    /// select the faults to apply to a request with the specified function code
+    pub(crate) fn select(&self, function: u8) -> Vec<Fault> {
+        let mut state = self.inner.lock().unwrap();
+        let FaultInjectorState { rules, rng } = &mut *state;
+        rules
+            .iter()
+            .filter(|rule| rule.function.map_or(true, |x| x == function))
+            .filter(|rule| rng.next_f64() < rule.probability)
+            .map(|rule| rule.fault.clone())
+            .collect()
+    }
+
+    fn garbage(&self, count: usize) -> Vec<u8> {
+        let mut state = self.inner.lock().unwrap();
+        (0..count).map(|_| state.rng.next_u64() as u8).collect()
+    }
+
+    /// combine the faults selected for a request into a plan, if any were selected
+    pub(crate) fn plan(&self, function: u8) -> Option<FaultPlan> {
+        let faults = self.select(function);
+        if faults.is_empty() {
+            return None;
+        }
+
+        let mut plan = FaultPlan::default();
+        for fault in faults {
+            tracing::warn!("injecting fault: {:?}", fault);
+            match fault {
+                Fault::Delay(x) => plan.delay += x,
+                Fault::DropResponse => plan.drop_response = true,
+                Fault::Exception(x) => plan.exception = Some(x),
+                Fault::WrongTxId => plan.wrong_tx_id = true,
+                Fault::Garbage(count) => plan.garbage = Some(self.garbage(count)),
+                Fault::CorruptCrc => plan.corrupt_crc = true,
+            }
+        }
+        Some(plan)
+    }
+}
+
+/// Faults to apply to a single response
+#[derive(Debug, Default)]
+pub(crate) struct FaultPlan {
+    delay: Duration,
+    drop_response: bool,
+    pub(crate) exception: Option<ExceptionCode>,
+    pub(crate) wrong_tx_id: bool,
+    garbage: Option<Vec<u8>>,
+    corrupt_crc: bool,
+}
+
+impl FaultPlan {
+    /// write the response, applying the faults in the plan
+    pub(crate) async fn write(
+        &self,
+        io: &mut PhysLayer,
+        reply: &[u8],
+        is_rtu: bool,
+        level: DecodeLevel,
+    ) -> Result<(), RequestError> {
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+
+        if self.drop_response {
+            return Ok(());
+        }
+
+        if let Some(garbage) = &self.garbage {
+            io.write(garbage, level).await?;
+            return Ok(());
+        }
+
+        if self.corrupt_crc && is_rtu {
+            let mut reply = reply.to_vec();
+            if let Some(last) = reply.last_mut() {
+                *last = !*last;
+            }
+            io.write(&reply, level).await?;
+            return Ok(());
+        }
+
+        io.write(reply, level).await?;
+        Ok(())
+    }
+}
+
+/// xorshift64* generator, good enough for picking faults
+#[derive(Debug)]
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Self { state: seed.max(1) }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        // use the upper 53 bits to produce a value in [0.0, 1.0)
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_faults_by_function_code() {
+        let injector = FaultInjector::with_seed(42);
+        injector.add_rule(FaultRule::new(Fault::DropResponse).function(0x03));
+        injector.add_rule(FaultRule::new(Fault::Delay(Duration::from_millis(10))));
+
+        assert_eq!(
+            injector.select(0x03),
+            vec![Fault::DropResponse, Fault::Delay(Duration::from_millis(10))]
+        );
+        assert_eq!(
+            injector.select(0x06),
+            vec![Fault::Delay(Duration::from_millis(10))]
+        );
+
+        injector.clear();
+        assert!(injector.select(0x03).is_empty());
+    }
+
+    #[test]
+    fn respects_probability() {
+        let injector = FaultInjector::with_seed(7);
+        injector.add_rule(FaultRule::new(Fault::DropResponse).probability(0.0));
+        assert!((0..100).all(|_| injector.select(0x03).is_empty()));
+
+        injector.clear();
+        injector.add_rule(FaultRule::new(Fault::DropResponse).probability(0.5));
+        let count = (0..1000)
+            .filter(|_| !injector.select(0x03).is_empty())
+            .count();
+        assert!((400..600).contains(&count), "{count}");
+    }
+}
@@ -1,8 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 
 use crate::exception::ExceptionCode;
-use crate::server::{WriteCoils, WriteRegisters};
+use crate::maybe_async::MaybeAsync;
+use crate::server::{AsyncRequest, AsyncResponse, UnitIdRouting, WriteCoils, WriteRegisters};
 use crate::types::*;
 
 /// Trait implemented by the user to process requests received from the client
@@ -62,6 +63,39 @@ pub trait RequestHandler: Send + 'static {
     fn write_multiple_registers(&mut self, _values: WriteRegisters) -> Result<(), ExceptionCode> {
         Err(ExceptionCode::IllegalFunction)
     }
+
+    /// Check that every register in the range of a WriteMultipleRegisters request can be written
+    ///
+    /// Only invoked for unit ids with transactional writes, see
+    /// [`ServerHandlerMap::set_transactional_writes`]. The values are only passed to
+    /// [`RequestHandler::write_multiple_registers`] if this returns `Ok`, otherwise the error is
+    /// returned to the client and nothing is written.
+    ///
+    /// The default implementation checks each address with
+    /// [`RequestHandler::read_holding_register`], which is only correct if every readable register
+    /// is also writable. Handlers that opt in must implement this method to match the checks
+    /// performed when writing.
+    fn validate_write_registers(&self, range: AddressRange) -> Result<(), ExceptionCode> {
+        range
+            .iter()
+            .try_for_each(|x| self.read_holding_register(x).map(|_| ()))
+    }
+
+    /// Optionally process a request asynchronously, e.g. by querying a backend
+    ///
+    /// `unit_id` is the unit id the request was addressed to, before any [`UnitIdRouting`], or
+    /// [`UnitId::broadcast`] for broadcast requests.
+    ///
+    /// Returning `None`, the default, processes the request with the synchronous methods above.
+    /// Otherwise the session awaits the returned value without holding the lock on the handler,
+    /// and doesn't read the next request until the response has been sent.
+    fn process_async(
+        &mut self,
+        _unit_id: UnitId,
+        _request: AsyncRequest,
+    ) -> Option<MaybeAsync<Result<AsyncResponse, ExceptionCode>>> {
+        None
+    }
 }
 
 /// Trait useful for converting None into IllegalDataAddress
@@ -83,13 +117,42 @@ where
 }
 
 /// Server handler boxed inside a `Arc<Mutex>`.
+///
+/// Sessions lock the handler once per request and hold the lock while the request is processed,
+/// so the application should also modify the handler in short transactions, e.g. with
+/// [`UpdateHandler::update`].
 pub type ServerHandlerType<T> = Arc<Mutex<Box<T>>>;
 
+/// Extension trait for modifying a handler shared with a running server
+pub trait UpdateHandler<T> {
+    /// Lock the handler and modify it within the closure
+    ///
+    /// The server cannot process requests while the closure runs, so all of the changes
+    /// are observed by clients atomically.
+    fn update<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R;
+}
+
+impl<T> UpdateHandler<T> for ServerHandlerType<T>
+where
+    T: RequestHandler,
+{
+    fn update<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        f(self.lock().unwrap().as_mut())
+    }
+}
+
 /// Type that hides the underlying map implementation
 /// and allows lookups of a [`RequestHandler`] from a [`UnitId`]
 #[derive(Debug, Default)]
 pub struct ServerHandlerMap<T: RequestHandler> {
     handlers: BTreeMap<UnitId, ServerHandlerType<T>>,
+    read_only: BTreeSet<UnitId>,
+    transactional: BTreeSet<UnitId>,
 }
 
 // this couldn't be derived automatically
@@ -101,6 +164,8 @@ where
     fn clone(&self) -> Self {
         ServerHandlerMap {
             handlers: self.handlers.clone(),
+            read_only: self.read_only.clone(),
+            transactional: self.transactional.clone(),
         }
     }
 }
@@ -113,6 +178,8 @@ where
     pub fn new() -> Self {
         Self {
             handlers: BTreeMap::new(),
+            read_only: BTreeSet::new(),
+            transactional: BTreeSet::new(),
         }
     }
 
@@ -120,7 +187,11 @@ where
     pub fn single(id: UnitId, handler: ServerHandlerType<T>) -> Self {
         let mut map: BTreeMap<UnitId, ServerHandlerType<T>> = BTreeMap::new();
         map.insert(id, handler);
-        Self { handlers: map }
+        Self {
+            handlers: map,
+            read_only: BTreeSet::new(),
+            transactional: BTreeSet::new(),
+        }
     }
 
     /// Retrieve a mutable reference to a [`RequestHandler`]
@@ -129,16 +200,97 @@ where
     }
 
     /// Add a handler to the map
+    ///
+    /// The unit id accepts writes even if it was previously marked as read-only
     pub fn add(
         &mut self,
         id: UnitId,
         server: ServerHandlerType<T>,
     ) -> Option<ServerHandlerType<T>> {
+        self.read_only.remove(&id);
         self.handlers.insert(id, server)
     }
 
-    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut ServerHandlerType<T>> {
-        self.handlers.values_mut()
+    /// Add a handler to the map that only processes read requests
+    ///
+    /// Write requests to this unit id are answered with [`ExceptionCode::IllegalFunction`]
+    /// without invoking the handler. Use [`ServerHandlerMap::set_read_only`] or
+    /// [`ServerHandle::set_read_only`](crate::server::ServerHandle::set_read_only) to change this later.
+    pub fn add_read_only(
+        &mut self,
+        id: UnitId,
+        server: ServerHandlerType<T>,
+    ) -> Option<ServerHandlerType<T>> {
+        self.read_only.insert(id);
+        self.handlers.insert(id, server)
+    }
+
+    /// Enable or disable write protection for a unit id
+    pub fn set_read_only(&mut self, id: UnitId, read_only: bool) {
+        if read_only {
+            self.read_only.insert(id);
+        } else {
+            self.read_only.remove(&id);
+        }
+    }
+
+    /// Check if a unit id is write protected
+    pub fn is_read_only(&self, id: UnitId) -> bool {
+        self.read_only.contains(&id)
+    }
+
+    /// Enable or disable transactional WriteMultipleRegisters requests for a unit id
+    ///
+    /// By default, the values are passed directly to [`RequestHandler::write_multiple_registers`],
+    /// so a handler that fails on an address in the middle of the range may have already written
+    /// the preceding registers. When enabled, the server first calls
+    /// [`RequestHandler::validate_write_registers`] for the entire range and only writes the values
+    /// if it succeeds. Requests processed with [`RequestHandler::process_async`] are not validated.
+    pub fn set_transactional_writes(&mut self, id: UnitId, enabled: bool) {
+        if enabled {
+            self.transactional.insert(id);
+        } else {
+            self.transactional.remove(&id);
+        }
+    }
+
+    /// Check if a unit id validates WriteMultipleRegisters requests before writing them
+    pub fn has_transactional_writes(&self, id: UnitId) -> bool {
+        self.transactional.contains(&id)
+    }
+
+    /// Unit id of the handler that processes a request for the specified unit id
+    pub(crate) fn route(&self, id: UnitId, routing: UnitIdRouting) -> Option<UnitId> {
+        if self.handlers.contains_key(&id) {
+            return Some(id);
+        }
+
+        match routing {
+            UnitIdRouting::Strict => None,
+            UnitIdRouting::Wildcard {
+                default,
+                include_zero,
+            } => {
+                let wildcard = id.value == 0xFF || (include_zero && id.value == 0x00);
+                (wildcard && self.handlers.contains_key(&default)).then_some(default)
+            }
+            UnitIdRouting::AnyToSingle => match self.handlers.len() {
+                1 => self.handlers.keys().next().copied(),
+                _ => None,
+            },
+        }
+    }
+
+    /// iterate over the handlers that accept writes, along with whether their writes are transactional
+    pub(crate) fn iter_writable_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&mut ServerHandlerType<T>, bool)> {
+        let read_only = &self.read_only;
+        let transactional = &self.transactional;
+        self.handlers
+            .iter_mut()
+            .filter(move |(id, _)| !read_only.contains(id))
+            .map(move |(id, handler)| (handler, transactional.contains(id)))
     }
 }
 
@@ -346,4 +498,46 @@ mod tests {
         assert!(map.add(UnitId::new(2), DefaultHandler {}.wrap()).is_none());
         assert!(map.add(UnitId::new(1), DefaultHandler {}.wrap()).is_some());
     }
+
+    #[test]
+    fn routes_unit_ids_according_to_mode() {
+        let one = UnitId::new(1);
+        let mut map = ServerHandlerMap::single(one, DefaultHandler {}.wrap());
+        let wildcard = UnitIdRouting::Wildcard {
+            default: one,
+            include_zero: false,
+        };
+
+        assert_eq!(map.route(one, UnitIdRouting::Strict), Some(one));
+        assert_eq!(map.route(UnitId::new(0xFF), UnitIdRouting::Strict), None);
+
+        assert_eq!(map.route(UnitId::new(0xFF), wildcard), Some(one));
+        assert_eq!(map.route(UnitId::new(0x00), wildcard), None);
+        assert_eq!(
+            map.route(
+                UnitId::new(0x00),
+                UnitIdRouting::Wildcard {
+                    default: one,
+                    include_zero: true
+                }
+            ),
+            Some(one)
+        );
+        assert_eq!(map.route(UnitId::new(2), wildcard), None);
+
+        assert_eq!(
+            map.route(UnitId::new(7), UnitIdRouting::AnyToSingle),
+            Some(one)
+        );
+
+        // an exact match wins and single routing requires exactly one handler
+        let two = UnitId::new(2);
+        map.add(UnitId::new(0xFF), DefaultHandler {}.wrap());
+        map.add(two, DefaultHandler {}.wrap());
+        assert_eq!(
+            map.route(UnitId::new(0xFF), wildcard),
+            Some(UnitId::new(0xFF))
+        );
+        assert_eq!(map.route(UnitId::new(7), UnitIdRouting::AnyToSingle), None);
+    }
 }
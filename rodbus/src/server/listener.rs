@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+
+use crate::error::RequestError;
+
+/// Events related to connections on a TCP or TLS server
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServerConnectionEvent {
+    /// A connection was accepted and a session will be started
+    Accepted(SocketAddr),
+    /// A connection was rejected because the address did not match the [`AddressFilter`](crate::server::AddressFilter)
+    RejectedByFilter(SocketAddr),
+    /// A connection was rejected because the maximum number of sessions was reached and the
+    /// policy is [`MaxSessionsPolicy::RejectNew`](crate::server::MaxSessionsPolicy::RejectNew)
+    RejectedMaxSessions(SocketAddr),
+    /// A connection was rejected because the peer already has the maximum number of sessions,
+    /// see [`ServerOptions::max_sessions_per_ip`](crate::server::ServerOptions::max_sessions_per_ip)
+    RejectedMaxSessionsPerIp(SocketAddr),
+    /// An existing session was closed to make room for a new one because the maximum number of sessions was reached
+    ///
+    /// A [`ServerConnectionEvent::Closed`] event is also reported when the evicted session shuts down.
+    MaxSessionsEvicted(SocketAddr),
+    /// A session was closed
+    Closed(SocketAddr, SessionCloseReason),
+    /// The TLS handshake with the remote peer failed
+    HandshakeFailed(SocketAddr, String),
+    /// The TLS handshake with the remote peer completed
+    ///
+    /// Contains the negotiated parameters and the certificate presented by the client,
+    /// e.g. for audit logging
+    #[cfg(feature = "tls")]
+    TlsEstablished(SocketAddr, crate::tcp::tls::TlsInfo),
+}
+
+/// Reasons why a server session is closed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionCloseReason {
+    /// I/O error (including the peer closing the connection), framing error, or the server
+    /// closed the session because it was shut down or evicted
+    Error(RequestError),
+    /// No valid request was received within the idle timeout
+    IdleTimeout,
+    /// The session reached its maximum lifetime
+    MaxLifetime,
+    /// The serial port of an RTU server was closed to re-open it with new settings
+    SerialSettingsChanged,
+}
+
+impl From<RequestError> for SessionCloseReason {
+    fn from(err: RequestError) -> Self {
+        SessionCloseReason::Error(err)
+    }
+}
+
+impl std::fmt::Display for SessionCloseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SessionCloseReason::Error(err) => write!(f, "{err}"),
+            SessionCloseReason::IdleTimeout => {
+                f.write_str("no valid request received within idle timeout")
+            }
+            SessionCloseReason::MaxLifetime => f.write_str("maximum session lifetime reached"),
+            SessionCloseReason::SerialSettingsChanged => f.write_str("serial settings changed"),
+        }
+    }
+}
@@ -5,10 +5,19 @@ use tracing::Instrument;
 use crate::decode::DecodeLevel;
 use crate::server::task::ServerSetting;
 use crate::tcp::server::{ServerTask, TcpServerConnectionHandler};
+use crate::types::UnitId;
 
 /// server handling
 mod address_filter;
+mod authorization;
+mod builder;
+mod database;
+mod error;
+mod fault;
 pub(crate) mod handler;
+mod listener;
+pub(crate) mod options;
+mod persist;
 pub(crate) mod request;
 pub(crate) mod response;
 pub(crate) mod task;
@@ -20,12 +29,20 @@ pub(crate) const SERVER_SETTING_CHANNEL_CAPACITY: usize = 8;
 use crate::error::Shutdown;
 
 pub use address_filter::*;
+pub use authorization::*;
+pub use builder::*;
+pub use database::*;
+pub use error::*;
+pub use fault::*;
 pub use handler::*;
+pub use listener::*;
+pub use options::*;
+pub use persist::*;
 pub use types::*;
 
 // re-export to the public API
 #[cfg(feature = "tls")]
-pub use crate::tcp::tls::server::TlsServerConfig;
+pub use crate::tcp::tls::server::{extract_modbus_role, RoleError, TlsServerConfig};
 #[cfg(feature = "tls")]
 pub use crate::tcp::tls::*;
 
@@ -44,10 +61,107 @@ impl ServerHandle {
     }
 
     /// Change the decoding level for future sessions and all active sessions
+    ///
+    /// Applies to every transport, including the session of an RTU server.
     pub async fn set_decode_level(&mut self, level: DecodeLevel) -> Result<(), Shutdown> {
         self.tx.send(ServerSetting::ChangeDecoding(level)).await?;
         Ok(())
     }
+
+    /// Change the request throttle for future sessions and all active sessions
+    ///
+    /// `None` removes any throttling
+    pub async fn set_request_throttle(
+        &mut self,
+        throttle: Option<RequestThrottle>,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::ChangeThrottle(throttle))
+            .await?;
+        Ok(())
+    }
+
+    /// Enable or disable write protection of a unit id for future sessions and all active sessions
+    ///
+    /// See [`ServerHandlerMap::add_read_only`]
+    pub async fn set_read_only(
+        &mut self,
+        unit_id: UnitId,
+        read_only: bool,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::SetReadOnly(unit_id, read_only))
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the filter applied to future connections
+    ///
+    /// Existing sessions are kept open, even if their peer no longer matches. Has no effect on
+    /// RTU servers, which don't accept connections.
+    pub async fn set_address_filter(&mut self, filter: AddressFilter) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::SetAddressFilter(filter, false))
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the filter applied to future connections and close the existing sessions whose
+    /// peer no longer matches it
+    ///
+    /// A [`ServerConnectionEvent::Closed`] event is reported for each closed session.
+    pub async fn set_address_filter_and_disconnect(
+        &mut self,
+        filter: AddressFilter,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::SetAddressFilter(filter, true))
+            .await?;
+        Ok(())
+    }
+
+    /// Change the action taken when a connection is accepted while the maximum number of sessions is open
+    ///
+    /// Has no effect on RTU servers.
+    pub async fn set_max_sessions_policy(
+        &mut self,
+        policy: MaxSessionsPolicy,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::SetMaxSessionsPolicy(policy))
+            .await?;
+        Ok(())
+    }
+
+    /// Close the serial port of an RTU server and re-open it with different settings
+    ///
+    /// If the port is waiting to be re-opened, the settings are used at the next attempt. Has no
+    /// effect on TCP, TLS, and UDP servers.
+    #[cfg(feature = "serial")]
+    pub async fn change_serial_settings(
+        &mut self,
+        settings: crate::serial::SerialSettings,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::ChangeSerialSettings(settings))
+            .await?;
+        Ok(())
+    }
+
+    /// Install a sink that receives the raw bytes sent and received by all active and future sessions
+    ///
+    /// `None` removes any sink. All sessions share the same sink.
+    pub async fn set_capture(
+        &mut self,
+        sink: Option<Box<dyn crate::capture::FrameSink>>,
+    ) -> Result<(), Shutdown> {
+        self.tx
+            .send(ServerSetting::SetCapture(
+                sink.map(crate::capture::new_capture),
+            ))
+            .await?;
+        Ok(())
+    }
 }
 
 /// Spawns a TCP server task onto the runtime. This method can only
@@ -61,6 +175,8 @@ impl ServerHandle {
 /// * `handlers` - A map of handlers keyed by a unit id
 /// * `decode` - Decode log level
 ///
+/// [`ServerConfig`] provides the same settings with defaults.
+///
 /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
 pub async fn spawn_tcp_server_task<T: RequestHandler>(
     max_sessions: usize,
@@ -68,8 +184,53 @@ pub async fn spawn_tcp_server_task<T: RequestHandler>(
     handlers: ServerHandlerMap<T>,
     filter: AddressFilter,
     decode: DecodeLevel,
-) -> Result<ServerHandle, std::io::Error> {
+) -> Result<ServerHandle, ServerSpawnError> {
+    spawn_tcp_server_task_with_options(
+        max_sessions,
+        addr,
+        handlers,
+        filter,
+        decode,
+        ServerOptions::default(),
+    )
+    .await
+}
+
+/// Same as [`spawn_tcp_server_task`] but accepts additional [`ServerOptions`]
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub async fn spawn_tcp_server_task_with_options<T: RequestHandler>(
+    max_sessions: usize,
+    addr: SocketAddr,
+    handlers: ServerHandlerMap<T>,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
+    spawn_tcp_server_task_on(max_sessions, listener, handlers, filter, decode, options)
+}
+
+/// Same as [`spawn_tcp_server_task_with_options`] but accepts connections on a listener that is
+/// already bound instead of binding an address
+///
+/// This allows a supervisor to pass in the listening socket, e.g. with systemd socket activation,
+/// or a test to bind port 0 and learn the ephemeral port before spawning the server. A
+/// [`std::net::TcpListener`] must be put in non-blocking mode before it is converted with
+/// [`tokio::net::TcpListener::from_std`].
+///
+/// Fails with [`ServerSpawnError::Bind`] if the local address of the listener can't be determined.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub fn spawn_tcp_server_task_on<T: RequestHandler>(
+    max_sessions: usize,
+    listener: tokio::net::TcpListener,
+    handlers: ServerHandlerMap<T>,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
+    let addr = listener.local_addr()?;
 
     let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
 
@@ -81,6 +242,7 @@ pub async fn spawn_tcp_server_task<T: RequestHandler>(
             TcpServerConnectionHandler::Tcp,
             filter,
             decode,
+            options,
         )
         .run(rx)
         .instrument(tracing::info_span!("Modbus-Server-TCP", "listen" = ?addr))
@@ -92,9 +254,125 @@ pub async fn spawn_tcp_server_task<T: RequestHandler>(
     Ok(ServerHandle::new(tx))
 }
 
+/// Spawns a server task onto the runtime that accepts TCP connections carrying RTU frames,
+/// i.e. a unit id, PDU, and CRC without an MBAP header. This is the framing used by many
+/// radio modems and serial device servers that tunnel a serial line over TCP.
+///
+/// Each incoming connection will spawn a new task to handle it.
+///
+/// * `max_sessions` - Maximum number of concurrent sessions
+/// * `addr` - A socket address to bound to
+/// * `handlers` - A map of handlers keyed by a unit id
+/// * `filter` - Address filter which may be used to restrict the connecting IP address
+/// * `decode` - Decode log level
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub async fn spawn_rtu_over_tcp_server_task<T: RequestHandler>(
+    max_sessions: usize,
+    addr: SocketAddr,
+    handlers: ServerHandlerMap<T>,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+) -> Result<ServerHandle, ServerSpawnError> {
+    spawn_rtu_over_tcp_server_task_with_options(
+        max_sessions,
+        addr,
+        handlers,
+        filter,
+        decode,
+        ServerOptions::default(),
+    )
+    .await
+}
+
+/// Same as [`spawn_rtu_over_tcp_server_task`] but accepts additional [`ServerOptions`]
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub async fn spawn_rtu_over_tcp_server_task_with_options<T: RequestHandler>(
+    max_sessions: usize,
+    addr: SocketAddr,
+    handlers: ServerHandlerMap<T>,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+
+    let task = async move {
+        ServerTask::new(
+            max_sessions,
+            listener,
+            handlers,
+            TcpServerConnectionHandler::RtuOverTcp,
+            filter,
+            decode,
+            options,
+        )
+        .run(rx)
+        .instrument(tracing::info_span!("Modbus-Server-RTU-over-TCP", "listen" = ?addr))
+        .await;
+    };
+
+    tokio::spawn(task);
+
+    Ok(ServerHandle::new(tx))
+}
+
+/// Spawns a server task onto the runtime that processes requests received on one end of an
+/// in-memory connection, e.g. from a channel in the same process
+///
+/// The task handles a single session, like a TCP server does for each accepted connection. It
+/// completes when the other end of the connection is dropped or the returned handle is dropped.
+///
+/// * `stream` - One end of a connection created by [`in_memory_pair`](crate::memory::in_memory_pair)
+/// * `format` - Framing of the Modbus ADUs on the connection
+/// * `handlers` - A map of handlers keyed by a unit id
+/// * `decode` - Decode log level
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub fn spawn_in_memory_server_task<T: RequestHandler>(
+    stream: crate::memory::MemoryStream,
+    format: crate::client::FrameFormat,
+    handlers: ServerHandlerMap<T>,
+    decode: DecodeLevel,
+) -> ServerHandle {
+    let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+    let (writer, reader) = match format {
+        crate::client::FrameFormat::Mbap => (
+            crate::common::frame::FrameWriter::tcp(),
+            crate::common::frame::FramedReader::tcp(),
+        ),
+        crate::client::FrameFormat::Rtu => (
+            crate::common::frame::FrameWriter::rtu(),
+            crate::common::frame::FramedReader::rtu_request(),
+        ),
+    };
+    let mut session = crate::server::task::SessionTask::new(
+        handlers,
+        crate::server::task::AuthorizationType::None,
+        writer,
+        reader,
+        rx,
+        decode,
+    );
+
+    let task = async move {
+        let mut phys = crate::common::phys::PhysLayer::new_memory(stream.inner);
+        let reason = session.run(&mut phys).await;
+        tracing::info!("session closed: {}", reason);
+    };
+
+    tokio::spawn(task.instrument(tracing::info_span!("Modbus-Server-Memory", ?format)));
+
+    ServerHandle::new(tx)
+}
+
 /// Spawns a RTU server task onto the runtime.
 ///
 /// * `path` - Path to the serial device. Generally `/dev/tty0` on Linux and `COM1` on Windows.
+///   Accepts a [`SerialPath`](crate::SerialPath) to try multiple candidate paths or a pattern.
 /// * `settings` - Serial port settings
 /// * `retry` - A boxed trait object that controls when opening the serial port is retried after a failure
 /// * `handlers` - A map of handlers keyed by a unit id
@@ -103,14 +381,62 @@ pub async fn spawn_tcp_server_task<T: RequestHandler>(
 /// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
 #[cfg(feature = "serial")]
 pub fn spawn_rtu_server_task<T: RequestHandler>(
-    path: &str,
+    path: impl Into<crate::serial::SerialPath>,
     settings: crate::serial::SerialSettings,
     retry: Box<dyn crate::retry::RetryStrategy>,
     handlers: ServerHandlerMap<T>,
     decode: DecodeLevel,
-) -> Result<ServerHandle, std::io::Error> {
+) -> Result<ServerHandle, ServerSpawnError> {
+    spawn_rtu_server_task_with_options(
+        path,
+        settings,
+        retry,
+        handlers,
+        decode,
+        ServerOptions::default(),
+    )
+}
+
+/// Same as [`spawn_rtu_server_task`] but accepts additional [`ServerOptions`]
+///
+/// Only the [`frame_timeout`](ServerOptions::frame_timeout), [`frame_error_policy`](ServerOptions::frame_error_policy),
+/// [`request_throttle`](ServerOptions::request_throttle), [`fault_injector`](ServerOptions::fault_injector),
+/// [`strict_coil_padding`](ServerOptions::strict_coil_padding), and
+/// [`unknown_unit_id_policy`](ServerOptions::unknown_unit_id_policy) apply to RTU servers.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "serial")]
+pub fn spawn_rtu_server_task_with_options<T: RequestHandler>(
+    path: impl Into<crate::serial::SerialPath>,
+    settings: crate::serial::SerialSettings,
+    retry: Box<dyn crate::retry::RetryStrategy>,
+    handlers: ServerHandlerMap<T>,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
+    Ok(spawn_rtu_server_task_on(
+        crate::serial::PortSource::System(path.into()),
+        settings,
+        retry,
+        handlers,
+        decode,
+        options,
+    ))
+}
+
+/// Same as [`spawn_rtu_server_task_with_options`] but opens the port from any
+/// [`PortSource`](crate::serial::PortSource)
+#[cfg(feature = "serial")]
+pub(crate) fn spawn_rtu_server_task_on<T: RequestHandler>(
+    port: crate::serial::PortSource,
+    settings: crate::serial::SerialSettings,
+    retry: Box<dyn crate::retry::RetryStrategy>,
+    handlers: ServerHandlerMap<T>,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> ServerHandle {
     let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
-    let session = crate::server::task::SessionTask::new(
+    let mut session = crate::server::task::SessionTask::new(
         handlers,
         crate::server::task::AuthorizationType::None,
         crate::common::frame::FrameWriter::rtu(),
@@ -118,25 +444,31 @@ pub fn spawn_rtu_server_task<T: RequestHandler>(
         rx,
         decode,
     );
+    session.accept_serial_settings();
+    session.configure(crate::server::options::SessionConfig {
+        frame_timeout: options.frame_timeout,
+        frame_error_policy: options.frame_error_policy,
+        request_throttle: options.request_throttle,
+        fault_injector: options.fault_injector,
+        strict_coil_padding: options.strict_coil_padding,
+        unknown_unit_id_policy: options.unknown_unit_id_policy,
+        ..Default::default()
+    });
+
+    let span = tracing::info_span!("Modbus-Server-RTU", "port" = %port);
 
     let mut rtu = crate::serial::server::RtuServerTask {
-        port: path.to_string(),
+        port,
         retry,
         settings,
         session,
     };
 
-    let path = path.to_string();
-
-    let task = async move {
-        rtu.run()
-            .instrument(tracing::info_span!("Modbus-Server-RTU", "port" = ?path))
-            .await
-    };
+    let task = async move { rtu.run().instrument(span).await };
 
     tokio::spawn(task);
 
-    Ok(ServerHandle::new(tx))
+    ServerHandle::new(tx)
 }
 
 /// Spawns a "raw" TLS server task onto the runtime. This TLS server does NOT require that
@@ -161,8 +493,8 @@ pub async fn spawn_tls_server_task<T: RequestHandler>(
     tls_config: TlsServerConfig,
     filter: AddressFilter,
     decode: DecodeLevel,
-) -> Result<ServerHandle, std::io::Error> {
-    spawn_tls_server_task_impl(
+) -> Result<ServerHandle, ServerSpawnError> {
+    spawn_tls_server_task_with_options(
         max_sessions,
         addr,
         handlers,
@@ -170,6 +502,7 @@ pub async fn spawn_tls_server_task<T: RequestHandler>(
         tls_config,
         filter,
         decode,
+        ServerOptions::default(),
     )
     .await
 }
@@ -199,8 +532,8 @@ pub async fn spawn_tls_server_task_with_authz<T: RequestHandler>(
     tls_config: TlsServerConfig,
     filter: AddressFilter,
     decode: DecodeLevel,
-) -> Result<ServerHandle, std::io::Error> {
-    spawn_tls_server_task_impl(
+) -> Result<ServerHandle, ServerSpawnError> {
+    spawn_tls_server_task_with_options(
         max_sessions,
         addr,
         handlers,
@@ -208,12 +541,20 @@ pub async fn spawn_tls_server_task_with_authz<T: RequestHandler>(
         tls_config,
         filter,
         decode,
+        ServerOptions::default(),
     )
     .await
 }
 
+/// Same as [`spawn_tls_server_task`] and [`spawn_tls_server_task_with_authz`] but accepts additional [`ServerOptions`]
+///
+/// If `auth_handler` is `Some`, the server behaves like [`spawn_tls_server_task_with_authz`], otherwise
+/// it behaves like [`spawn_tls_server_task`].
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
 #[cfg(feature = "tls")]
-async fn spawn_tls_server_task_impl<T: RequestHandler>(
+#[allow(clippy::too_many_arguments)]
+pub async fn spawn_tls_server_task_with_options<T: RequestHandler>(
     max_sessions: usize,
     addr: SocketAddr,
     handlers: ServerHandlerMap<T>,
@@ -221,8 +562,40 @@ async fn spawn_tls_server_task_impl<T: RequestHandler>(
     tls_config: TlsServerConfig,
     filter: AddressFilter,
     decode: DecodeLevel,
-) -> Result<ServerHandle, std::io::Error> {
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
+    spawn_tls_server_task_on(
+        max_sessions,
+        listener,
+        handlers,
+        auth_handler,
+        tls_config,
+        filter,
+        decode,
+        options,
+    )
+}
+
+/// Same as [`spawn_tls_server_task_with_options`] but accepts connections on a listener that is
+/// already bound instead of binding an address
+///
+/// See [`spawn_tcp_server_task_on`] for how the listener may be obtained.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+#[cfg(feature = "tls")]
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_tls_server_task_on<T: RequestHandler>(
+    max_sessions: usize,
+    listener: tokio::net::TcpListener,
+    handlers: ServerHandlerMap<T>,
+    auth_handler: Option<std::sync::Arc<dyn AuthorizationHandler>>,
+    tls_config: TlsServerConfig,
+    filter: AddressFilter,
+    decode: DecodeLevel,
+    options: ServerOptions,
+) -> Result<ServerHandle, ServerSpawnError> {
+    let addr = listener.local_addr()?;
 
     let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
 
@@ -234,6 +607,7 @@ async fn spawn_tls_server_task_impl<T: RequestHandler>(
             TcpServerConnectionHandler::Tls(tls_config, auth_handler),
             filter,
             decode,
+            options,
         )
         .run(rx)
         .instrument(tracing::info_span!("Modbus-Server-TLS", "listen" = ?addr))
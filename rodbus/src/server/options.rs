@@ -0,0 +1,362 @@
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use crate::capture::Capture;
+use crate::client::Listener;
+use crate::exception::ExceptionCode;
+use crate::server::task::BlockingCalls;
+use crate::server::{FaultInjector, ServerConnectionEvent};
+use crate::types::UnitId;
+
+/// Default value for [`ServerOptions::frame_timeout`]
+pub const DEFAULT_FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Determines what happens when a request arrives before the minimum gap has elapsed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThrottleMode {
+    /// Delay reading the next request until the gap has elapsed, applying back-pressure to the client
+    Delay,
+    /// Reply to the early request with [`ExceptionCode::ServerDeviceBusy`](crate::ExceptionCode::ServerDeviceBusy)
+    Busy,
+}
+
+/// Determines how a session reacts to a frame that violates the framing rules
+///
+/// Examples include an MBAP header with a non-zero protocol id or an out-of-range length field,
+/// and an RTU frame with a bad CRC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum FrameErrorPolicy {
+    /// Close the session (the default). RTU servers reopen the port after the retry delay.
+    #[default]
+    Close,
+    /// Discard all buffered bytes and keep reading from the same session
+    Discard,
+}
+
+/// Determines how TCP and TLS servers select the handler for the unit id of a request
+///
+/// A handler registered for the exact unit id always takes precedence. In every mode the
+/// response echoes the unit id of the request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum UnitIdRouting {
+    /// Requests for unit ids without a handler are ignored (the default)
+    #[default]
+    Strict,
+    /// Route requests addressed to `0xFF`, the unit id recommended for TCP, to a default handler
+    Wildcard {
+        /// Unit id of the handler that processes the requests
+        default: UnitId,
+        /// Also treat `0x00` as a wildcard
+        include_zero: bool,
+    },
+    /// Route requests for any unit id to the handler if the map contains exactly one entry
+    AnyToSingle,
+}
+
+/// Determines how a server reacts to a request for a unit id without a handler
+///
+/// Applies after [`UnitIdRouting`] has failed to find a handler. Broadcast requests are never
+/// answered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum UnknownUnitIdPolicy {
+    /// Ignore the request without logging it (the default)
+    ///
+    /// This is the behavior required of RTU servers, which receive the requests addressed to
+    /// every device on the bus.
+    #[default]
+    Silent,
+    /// Ignore the request and log its function code and unit id at the debug level
+    LogOnly,
+    /// Reply with [`ExceptionCode::GatewayTargetDeviceFailedToRespond`]
+    ///
+    /// This doesn't comply with the specification and must not be used on a bus shared with
+    /// other devices. On a point to point link, it makes a client configured with the wrong unit
+    /// id fail fast instead of timing out.
+    RespondGatewayTargetFailed,
+}
+
+/// Limits the rate at which each session processes requests
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RequestThrottle {
+    pub(crate) min_request_gap: Duration,
+    pub(crate) mode: ThrottleMode,
+}
+
+impl RequestThrottle {
+    /// Require at least the specified duration between the processing of consecutive requests
+    ///
+    /// The mode defaults to [`ThrottleMode::Delay`]
+    pub fn min_request_gap(gap: Duration) -> Self {
+        Self {
+            min_request_gap: gap,
+            mode: ThrottleMode::Delay,
+        }
+    }
+
+    /// Process at most the specified number of requests per second
+    ///
+    /// The mode defaults to [`ThrottleMode::Delay`]
+    pub fn max_requests_per_second(count: NonZeroU32) -> Self {
+        Self::min_request_gap(Duration::from_secs(1) / count.get())
+    }
+
+    /// Change how requests that arrive too early are handled
+    pub fn mode(mut self, mode: ThrottleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// Limits how long a handler may take to process a request
+///
+/// Synchronous handlers are executed on Tokio's blocking thread pool so that the timeout can expire
+/// while they run. This costs a thread handoff and a copy of the request and reply for every request,
+/// so only enable it if a handler might block. A handler that doesn't return keeps its lock and a
+/// blocking thread. Until it returns, the server answers later requests for the same handler with
+/// the exception right away instead of running them on other threads.
+///
+/// The timeout doesn't cancel the handler. A write that timed out may still be applied once the
+/// handler returns, even though the client received an exception.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HandlerTimeout {
+    pub(crate) timeout: Duration,
+    pub(crate) exception: ExceptionCode,
+}
+
+impl HandlerTimeout {
+    /// Reply with an exception if the handler doesn't process a request within the specified duration
+    ///
+    /// The exception defaults to [`ExceptionCode::ServerDeviceBusy`]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            exception: ExceptionCode::ServerDeviceBusy,
+        }
+    }
+
+    /// Change the exception returned when the timeout expires, e.g. to [`ExceptionCode::ServerDeviceFailure`]
+    pub fn exception(mut self, exception: ExceptionCode) -> Self {
+        self.exception = exception;
+        self
+    }
+}
+
+/// Action taken by a TCP or TLS server when a connection is accepted while the maximum number
+/// of sessions is open
+///
+/// Sessions accepted by the same endpoint as the new connection are evicted first. The policy may
+/// be changed at runtime using [`ServerHandle::set_max_sessions_policy`](crate::server::ServerHandle::set_max_sessions_policy).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MaxSessionsPolicy {
+    /// Close the session that was opened first
+    #[default]
+    EvictOldest,
+    /// Close the new connection, reported as [`ServerConnectionEvent::RejectedMaxSessions`]
+    RejectNew,
+    /// Close the session whose last valid request is the oldest, or which was opened first if
+    /// it hasn't received a request
+    EvictLongestIdle,
+}
+
+/// Optional settings that control the behavior of a server
+///
+/// The default value preserves the historical behavior of the server, i.e. sessions
+/// remain open until the client disconnects or the session is evicted. The only exception
+/// is the [`frame_timeout`](ServerOptions::frame_timeout) which defaults to [`DEFAULT_FRAME_TIMEOUT`].
+///
+/// The frame timeout, frame error policy, request throttle, handler timeout, fault injector, strict coil padding, and unknown unit id policy apply to all servers, whereas the remaining
+/// settings only apply to TCP and TLS servers.
+pub struct ServerOptions {
+    pub(crate) max_sessions_policy: MaxSessionsPolicy,
+    pub(crate) max_sessions_per_ip: Option<usize>,
+    pub(crate) session_idle_timeout: Option<Duration>,
+    pub(crate) max_session_lifetime: Option<Duration>,
+    pub(crate) frame_timeout: Option<Duration>,
+    pub(crate) frame_error_policy: FrameErrorPolicy,
+    pub(crate) request_throttle: Option<RequestThrottle>,
+    pub(crate) handler_timeout: Option<HandlerTimeout>,
+    pub(crate) fault_injector: Option<FaultInjector>,
+    pub(crate) unit_id_routing: UnitIdRouting,
+    pub(crate) strict_coil_padding: bool,
+    pub(crate) unknown_unit_id_policy: UnknownUnitIdPolicy,
+    pub(crate) connection_listener: Option<Box<dyn Listener<ServerConnectionEvent>>>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            max_sessions_policy: MaxSessionsPolicy::EvictOldest,
+            max_sessions_per_ip: None,
+            session_idle_timeout: None,
+            max_session_lifetime: None,
+            frame_timeout: Some(DEFAULT_FRAME_TIMEOUT),
+            frame_error_policy: FrameErrorPolicy::Close,
+            request_throttle: None,
+            handler_timeout: None,
+            fault_injector: None,
+            unit_id_routing: UnitIdRouting::Strict,
+            strict_coil_padding: false,
+            unknown_unit_id_policy: UnknownUnitIdPolicy::Silent,
+            connection_listener: None,
+        }
+    }
+}
+
+/// Settings applied to each session, extracted from [`ServerOptions`]
+#[derive(Clone, Default)]
+pub(crate) struct SessionConfig {
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) max_lifetime: Option<Duration>,
+    pub(crate) frame_timeout: Option<Duration>,
+    pub(crate) frame_error_policy: FrameErrorPolicy,
+    pub(crate) request_throttle: Option<RequestThrottle>,
+    pub(crate) handler_timeout: Option<HandlerTimeout>,
+    // shared by the sessions that are configured with clones of the same value
+    pub(crate) blocking_calls: BlockingCalls,
+    pub(crate) fault_injector: Option<FaultInjector>,
+    pub(crate) unit_id_routing: UnitIdRouting,
+    pub(crate) strict_coil_padding: bool,
+    pub(crate) unknown_unit_id_policy: UnknownUnitIdPolicy,
+    // only installed at runtime with ServerHandle::set_capture
+    pub(crate) capture: Option<Capture>,
+}
+
+impl ServerOptions {
+    pub(crate) fn session_config(&self) -> SessionConfig {
+        SessionConfig {
+            idle_timeout: self.session_idle_timeout,
+            max_lifetime: self.max_session_lifetime,
+            frame_timeout: self.frame_timeout,
+            frame_error_policy: self.frame_error_policy,
+            request_throttle: self.request_throttle,
+            handler_timeout: self.handler_timeout,
+            blocking_calls: BlockingCalls::default(),
+            fault_injector: self.fault_injector.clone(),
+            unit_id_routing: self.unit_id_routing,
+            strict_coil_padding: self.strict_coil_padding,
+            unknown_unit_id_policy: self.unknown_unit_id_policy,
+            capture: None,
+        }
+    }
+
+    /// Choose what happens when a connection is accepted while the maximum number of sessions is open
+    ///
+    /// Defaults to [`MaxSessionsPolicy::EvictOldest`].
+    pub fn max_sessions_policy(mut self, value: MaxSessionsPolicy) -> Self {
+        self.max_sessions_policy = value;
+        self
+    }
+
+    /// Limit the number of concurrent sessions from the same IP address
+    ///
+    /// Connections that would exceed the limit are closed immediately instead of evicting an
+    /// existing session, and reported as [`ServerConnectionEvent::RejectedMaxSessionsPerIp`].
+    /// IPv4-mapped IPv6 addresses count as the IPv4 address they map. A limit of zero is
+    /// treated as one.
+    ///
+    /// `None` (the default) only applies the maximum number of sessions of the server.
+    pub fn max_sessions_per_ip(mut self, value: Option<usize>) -> Self {
+        self.max_sessions_per_ip = value;
+        self
+    }
+
+    /// Close sessions that do not receive a valid request within the specified duration
+    ///
+    /// `None` (the default) never closes idle sessions.
+    pub fn session_idle_timeout(mut self, value: Option<Duration>) -> Self {
+        self.session_idle_timeout = value;
+        self
+    }
+
+    /// Close sessions once they have been open for the specified duration, regardless of activity
+    ///
+    /// `None` (the default) places no limit on the lifetime of a session.
+    pub fn max_session_lifetime(mut self, value: Option<Duration>) -> Self {
+        self.max_session_lifetime = value;
+        self
+    }
+
+    /// Close the session if the remainder of a frame does not arrive within the specified duration
+    /// after its first byte is received
+    ///
+    /// This prevents a peer from tying up a session by trickling in a frame one byte at a time. RTU
+    /// servers discard the partial frame and reopen the port after the retry delay.
+    ///
+    /// `None` allows frames to take arbitrarily long to arrive.
+    pub fn frame_timeout(mut self, value: Option<Duration>) -> Self {
+        self.frame_timeout = value;
+        self
+    }
+
+    /// Choose between closing the session and discarding buffered data when a malformed frame is received
+    ///
+    /// Closing the session (the default) is the strictest option and is recommended for servers
+    /// exposed to untrusted networks.
+    pub fn frame_error_policy(mut self, value: FrameErrorPolicy) -> Self {
+        self.frame_error_policy = value;
+        self
+    }
+
+    /// Limit the rate at which each session processes requests
+    ///
+    /// The throttle may be changed at runtime using [`ServerHandle::set_request_throttle`](crate::server::ServerHandle::set_request_throttle).
+    ///
+    /// `None` (the default) processes requests as fast as they arrive.
+    pub fn request_throttle(mut self, value: Option<RequestThrottle>) -> Self {
+        self.request_throttle = value;
+        self
+    }
+
+    /// Limit how long a handler may take to process each request
+    ///
+    /// `None` (the default) waits for the handler indefinitely. See [`HandlerTimeout`] for the
+    /// performance cost of enabling it.
+    pub fn handler_timeout(mut self, value: Option<HandlerTimeout>) -> Self {
+        self.handler_timeout = value;
+        self
+    }
+
+    /// Inject faults into responses to simulate a misbehaving device when testing clients
+    ///
+    /// `None` (the default) never injects faults.
+    pub fn fault_injector(mut self, value: Option<FaultInjector>) -> Self {
+        self.fault_injector = value;
+        self
+    }
+
+    /// Choose how requests are routed to handlers based on their unit id
+    ///
+    /// Many TCP devices ignore the unit id, and some clients always send `0xFF`. The default,
+    /// [`UnitIdRouting::Strict`], ignores requests for unit ids without a handler.
+    pub fn unit_id_routing(mut self, value: UnitIdRouting) -> Self {
+        self.unit_id_routing = value;
+        self
+    }
+
+    /// Reply with [`ExceptionCode::IllegalDataValue`] to write multiple coils requests whose unused
+    /// bits in the last byte of values are not zero, as required by the specification
+    ///
+    /// Disabled by default, in which case the unused bits are ignored. See
+    /// [`BitIterator::has_clean_padding`](crate::BitIterator::has_clean_padding).
+    pub fn strict_coil_padding(mut self, value: bool) -> Self {
+        self.strict_coil_padding = value;
+        self
+    }
+
+    /// Choose how requests for unit ids without a handler are handled
+    ///
+    /// Defaults to [`UnknownUnitIdPolicy::Silent`].
+    pub fn unknown_unit_id_policy(mut self, value: UnknownUnitIdPolicy) -> Self {
+        self.unknown_unit_id_policy = value;
+        self
+    }
+
+    /// Receive notifications when connections are accepted, rejected, evicted, or closed
+    pub fn connection_listener(
+        mut self,
+        listener: Box<dyn Listener<ServerConnectionEvent>>,
+    ) -> Self {
+        self.connection_listener = Some(listener);
+        self
+    }
+}
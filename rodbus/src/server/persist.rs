@@ -0,0 +1,220 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::server::{DatabaseSnapshot, MemoryDatabase, ServerHandlerType};
+
+/// Encodes a [`DatabaseSnapshot`] before it is written to a file
+///
+/// With the `serde` feature, any serde format may be used, e.g.
+/// `Box::new(|x: &DatabaseSnapshot| Ok(serde_json::to_vec(x)?))`.
+pub type SnapshotEncoder =
+    Box<dyn Fn(&DatabaseSnapshot) -> Result<Vec<u8>, std::io::Error> + Send + Sync + 'static>;
+
+/// Determines when the task spawned by [`spawn_persist_task`] writes a snapshot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PersistPolicy {
+    pub(crate) interval: Duration,
+    pub(crate) change_threshold: Option<u64>,
+}
+
+impl PersistPolicy {
+    /// Write a snapshot at the specified interval if any point changed since the previous one
+    pub fn interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            change_threshold: None,
+        }
+    }
+
+    /// Also write a snapshot as soon as the specified number of point changes accumulates,
+    /// without waiting for the interval to elapse
+    ///
+    /// See [`MemoryDatabase::revision`] for what counts as a change.
+    pub fn change_threshold(mut self, count: u64) -> Self {
+        self.change_threshold = Some(count);
+        self
+    }
+}
+
+/// Handle to the task spawned by [`spawn_persist_task`]
+///
+/// When the handle is dropped, the task writes a final snapshot in the background if any point
+/// changed and then stops. Use [`PersistHandle::shutdown`] to wait for that final write.
+#[derive(Debug)]
+pub struct PersistHandle {
+    stop: tokio::sync::oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl PersistHandle {
+    /// Write a final snapshot if any point changed and wait for the task to stop
+    pub async fn shutdown(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// Spawn a task that periodically writes snapshots of a database to a file
+///
+/// Each snapshot replaces the file atomically: it is written to a temporary file next to `path`
+/// (`path` with a `.tmp` suffix), flushed to disk, and then renamed. A crash therefore leaves
+/// either the previous or the new snapshot, never a partial one. Errors are logged and the
+/// snapshot is retried at the next interval.
+///
+/// To load the snapshot on startup, decode the file and call [`MemoryDatabase::restore`] before
+/// the server is started.
+///
+/// Must be called from within a Tokio runtime.
+pub fn spawn_persist_task(
+    database: ServerHandlerType<MemoryDatabase>,
+    path: impl Into<PathBuf>,
+    policy: PersistPolicy,
+    encoder: SnapshotEncoder,
+) -> PersistHandle {
+    let (stop, rx) = tokio::sync::oneshot::channel();
+    // subscribe before returning so that every later change is observed
+    let mut revisions = database.lock().unwrap().subscribe_revision();
+    // the values loaded before the task was spawned don't need to be written again
+    let persisted = *revisions.borrow_and_update();
+    let task = PersistTask {
+        database,
+        path: path.into(),
+        policy,
+        encoder,
+    };
+    let task = tokio::spawn(task.run(persisted, revisions, rx));
+    PersistHandle { stop, task }
+}
+
+struct PersistTask {
+    database: ServerHandlerType<MemoryDatabase>,
+    path: PathBuf,
+    policy: PersistPolicy,
+    encoder: SnapshotEncoder,
+}
+
+impl PersistTask {
+    async fn run(
+        self,
+        mut persisted: u64,
+        mut revisions: tokio::sync::watch::Receiver<u64>,
+        mut stop: tokio::sync::oneshot::Receiver<()>,
+    ) {
+        let mut deadline = tokio::time::Instant::now() + self.policy.interval;
+
+        loop {
+            tokio::select! {
+                // sent by shutdown() or closed by dropping the handle
+                _ = &mut stop => {
+                    self.persist_if_changed(&mut persisted).await;
+                    return;
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    deadline += self.policy.interval;
+                    self.persist_if_changed(&mut persisted).await;
+                }
+                result = revisions.changed(), if self.policy.change_threshold.is_some() => {
+                    if result.is_err() {
+                        // unreachable while the task holds a reference to the database
+                        return;
+                    }
+                    let revision = *revisions.borrow_and_update();
+                    let threshold = self.policy.change_threshold.unwrap_or(u64::MAX);
+                    if revision - persisted >= threshold {
+                        self.persist_if_changed(&mut persisted).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn persist_if_changed(&self, persisted: &mut u64) {
+        let (snapshot, revision) = {
+            let database = self.database.lock().unwrap();
+            (database.snapshot(), database.revision())
+        };
+        if revision == *persisted {
+            return;
+        }
+
+        let data = match (self.encoder)(&snapshot) {
+            Ok(x) => x,
+            Err(err) => {
+                tracing::warn!("unable to encode database snapshot: {}", err);
+                return;
+            }
+        };
+
+        let path = self.path.clone();
+        let result = tokio::task::spawn_blocking(move || write_atomically(&path, &data)).await;
+        match result {
+            Ok(Ok(())) => *persisted = revision,
+            Ok(Err(err)) => tracing::warn!(
+                "unable to write database snapshot to {}: {}",
+                self.path.display(),
+                err
+            ),
+            Err(err) => tracing::warn!("database snapshot task failed: {}", err),
+        }
+    }
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), std::io::Error> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    // the data must be on disk before the rename replaces the previous snapshot
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{DatabaseLayout, RequestHandler, UpdateHandler};
+
+    fn encode(snapshot: &DatabaseSnapshot) -> Result<Vec<u8>, std::io::Error> {
+        Ok(format!("{:?}", snapshot.holding_registers).into_bytes())
+    }
+
+    #[tokio::test]
+    async fn writes_snapshots_on_threshold_and_shutdown() {
+        let path = std::env::temp_dir().join(format!("rodbus-persist-{}", std::process::id()));
+        let mut database = MemoryDatabase::new(DatabaseLayout::Contiguous);
+        database.add_holding_register(0, 1);
+        database.add_holding_register(1, 2);
+        let database = database.wrap();
+
+        let policy = PersistPolicy::interval(Duration::from_secs(3600)).change_threshold(2);
+        let handle = spawn_persist_task(database.clone(), &path, policy, Box::new(encode));
+
+        // a single change is below the threshold
+        database.update(|db| db.update_holding_register(0, 3));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!path.exists());
+
+        database.update(|db| db.update_holding_register(1, 4));
+        let mut contents = String::new();
+        for _ in 0..100 {
+            if let Ok(x) = std::fs::read_to_string(&path) {
+                contents = x;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(contents, "{0: 3, 1: 4}");
+
+        database.update(|db| db.update_holding_register(1, 5));
+        handle.shutdown().await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{0: 3, 1: 5}");
+
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
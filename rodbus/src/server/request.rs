@@ -1,7 +1,7 @@
 use crate::common::frame::{FrameHeader, FrameWriter, FunctionField};
 use crate::common::function::FunctionCode;
 use crate::common::traits::{Loggable, Parse, Serialize};
-use crate::decode::AppDecodeLevel;
+use crate::decode::AppDecode;
 use crate::error::RequestError;
 use crate::exception::ExceptionCode;
 use crate::server::handler::RequestHandler;
@@ -33,9 +33,21 @@ pub(crate) enum BroadcastRequest<'a> {
     WriteMultipleRegisters(WriteRegisters<'a>),
 }
 
+/// write the registers, validating the entire range first if the writes are transactional
+fn write_multiple_registers<T: RequestHandler + ?Sized>(
+    handler: &mut T,
+    values: WriteRegisters,
+    transactional: bool,
+) -> Result<(), ExceptionCode> {
+    if transactional {
+        handler.validate_write_registers(values.range)?;
+    }
+    handler.write_multiple_registers(values)
+}
+
 impl<'a> BroadcastRequest<'a> {
     // execute a broadcast request against the handler
-    pub(crate) fn execute<T: RequestHandler>(&self, handler: &mut T) {
+    pub(crate) fn execute<T: RequestHandler>(&self, handler: &mut T, transactional: bool) {
         match self {
             BroadcastRequest::WriteSingleCoil(x) => {
                 let _ = handler.write_single_coil(*x);
@@ -47,13 +59,37 @@ impl<'a> BroadcastRequest<'a> {
                 let _ = handler.write_multiple_coils(*x);
             }
             BroadcastRequest::WriteMultipleRegisters(x) => {
-                let _ = handler.write_multiple_registers(*x);
+                let _ = write_multiple_registers(handler, *x, transactional);
             }
         }
     }
 }
 
+impl<'a> BroadcastRequest<'a> {
+    pub(crate) fn as_async(&self) -> AsyncRequest<'a> {
+        match self {
+            BroadcastRequest::WriteSingleCoil(x) => AsyncRequest::WriteSingleCoil(*x),
+            BroadcastRequest::WriteSingleRegister(x) => AsyncRequest::WriteSingleRegister(*x),
+            BroadcastRequest::WriteMultipleCoils(x) => AsyncRequest::WriteMultipleCoils(*x),
+            BroadcastRequest::WriteMultipleRegisters(x) => AsyncRequest::WriteMultipleRegisters(*x),
+        }
+    }
+}
+
 impl<'a> Request<'a> {
+    pub(crate) fn as_async(&self) -> AsyncRequest<'a> {
+        match self {
+            Request::ReadCoils(x) => AsyncRequest::ReadCoils(x.inner),
+            Request::ReadDiscreteInputs(x) => AsyncRequest::ReadDiscreteInputs(x.inner),
+            Request::ReadHoldingRegisters(x) => AsyncRequest::ReadHoldingRegisters(x.inner),
+            Request::ReadInputRegisters(x) => AsyncRequest::ReadInputRegisters(x.inner),
+            Request::WriteSingleCoil(x) => AsyncRequest::WriteSingleCoil(*x),
+            Request::WriteSingleRegister(x) => AsyncRequest::WriteSingleRegister(*x),
+            Request::WriteMultipleCoils(x) => AsyncRequest::WriteMultipleCoils(*x),
+            Request::WriteMultipleRegisters(x) => AsyncRequest::WriteMultipleRegisters(*x),
+        }
+    }
+
     pub(crate) fn get_function(&self) -> FunctionCode {
         match self {
             Request::ReadCoils(_) => FunctionCode::ReadCoils,
@@ -67,6 +103,38 @@ impl<'a> Request<'a> {
         }
     }
 
+    pub(crate) fn is_write(&self) -> bool {
+        match self {
+            Request::ReadCoils(_) => false,
+            Request::ReadDiscreteInputs(_) => false,
+            Request::ReadHoldingRegisters(_) => false,
+            Request::ReadInputRegisters(_) => false,
+            Request::WriteSingleCoil(_) => true,
+            Request::WriteSingleRegister(_) => true,
+            Request::WriteMultipleCoils(_) => true,
+            Request::WriteMultipleRegisters(_) => true,
+        }
+    }
+
+    pub(crate) fn address_range(&self) -> AddressRange {
+        match self {
+            Request::ReadCoils(x) => x.inner,
+            Request::ReadDiscreteInputs(x) => x.inner,
+            Request::ReadHoldingRegisters(x) => x.inner,
+            Request::ReadInputRegisters(x) => x.inner,
+            Request::WriteSingleCoil(x) => AddressRange {
+                start: x.index,
+                count: 1,
+            },
+            Request::WriteSingleRegister(x) => AddressRange {
+                start: x.index,
+                count: 1,
+            },
+            Request::WriteMultipleCoils(x) => x.range,
+            Request::WriteMultipleRegisters(x) => x.range,
+        }
+    }
+
     pub(crate) fn into_broadcast_request(self) -> Option<BroadcastRequest<'a>> {
         match self {
             Request::ReadCoils(_) => None,
@@ -86,6 +154,7 @@ impl<'a> Request<'a> {
         handler: &mut dyn RequestHandler,
         writer: &'b mut FrameWriter,
         level: DecodeLevel,
+        transactional: bool,
     ) -> Result<&'b [u8], RequestError> {
         fn write_result<T>(
             function: FunctionCode,
@@ -136,14 +205,71 @@ impl<'a> Request<'a> {
                 write_result(function, header, writer, result, level)
             }
             Request::WriteMultipleRegisters(items) => {
-                let result = handler
-                    .write_multiple_registers(*items)
-                    .map(|_| items.range);
+                let result =
+                    write_multiple_registers(handler, *items, transactional).map(|_| items.range);
                 write_result(function, header, writer, result, level)
             }
         }
     }
 
+    /// format the reply to a request processed by [`RequestHandler::process_async`]
+    pub(crate) fn get_async_reply<'b>(
+        &self,
+        header: FrameHeader,
+        result: Result<AsyncResponse, ExceptionCode>,
+        writer: &'b mut FrameWriter,
+        level: DecodeLevel,
+    ) -> Result<&'b [u8], RequestError> {
+        fn get<T: Copy>(values: &[T], start: u16, index: u16) -> Result<T, ExceptionCode> {
+            values
+                .get((index - start) as usize)
+                .copied()
+                .ok_or(ExceptionCode::ServerDeviceFailure)
+        }
+
+        let function = self.get_function();
+        let response = match result {
+            Ok(x) => x,
+            Err(ex) => {
+                return writer.format_ex(header, FunctionField::Exception(function), ex, level)
+            }
+        };
+
+        match (self, response) {
+            (Request::ReadCoils(range), AsyncResponse::Bits(values))
+            | (Request::ReadDiscreteInputs(range), AsyncResponse::Bits(values)) => {
+                let bits = BitWriter::new(*range, |i| get(&values, range.inner.start, i));
+                writer.format_reply(header, function, &bits, level)
+            }
+            (Request::ReadHoldingRegisters(range), AsyncResponse::Registers(values))
+            | (Request::ReadInputRegisters(range), AsyncResponse::Registers(values)) => {
+                let registers = RegisterWriter::new(*range, |i| get(&values, range.inner.start, i));
+                writer.format_reply(header, function, &registers, level)
+            }
+            (Request::WriteSingleCoil(x), AsyncResponse::Written) => {
+                writer.format_reply(header, function, x, level)
+            }
+            (Request::WriteSingleRegister(x), AsyncResponse::Written) => {
+                writer.format_reply(header, function, x, level)
+            }
+            (Request::WriteMultipleCoils(x), AsyncResponse::Written) => {
+                writer.format_reply(header, function, &x.range, level)
+            }
+            (Request::WriteMultipleRegisters(x), AsyncResponse::Written) => {
+                writer.format_reply(header, function, &x.range, level)
+            }
+            (_, response) => {
+                tracing::warn!("handler returned {:?} for {}", response, function);
+                writer.format_ex(
+                    header,
+                    FunctionField::Exception(function),
+                    ExceptionCode::ServerDeviceFailure,
+                    level,
+                )
+            }
+        }
+    }
+
     pub(crate) fn parse(
         function: FunctionCode,
         cursor: &'a mut ReadCursor,
@@ -183,7 +309,7 @@ impl<'a> Request<'a> {
                 Ok(x)
             }
             FunctionCode::WriteMultipleCoils => {
-                let range = AddressRange::parse(cursor)?;
+                let range = AddressRange::parse(cursor)?.of_write_coils()?;
                 // don't care about the count, validated b/c all bytes are consumed
                 cursor.read_u8()?;
                 Ok(Request::WriteMultipleCoils(WriteCoils::new(
@@ -192,7 +318,7 @@ impl<'a> Request<'a> {
                 )))
             }
             FunctionCode::WriteMultipleRegisters => {
-                let range = AddressRange::parse(cursor)?;
+                let range = AddressRange::parse(cursor)?.of_write_registers()?;
                 // don't care about the count, validated b/c all bytes are consumed
                 cursor.read_u8()?;
                 Ok(Request::WriteMultipleRegisters(WriteRegisters::new(
@@ -206,11 +332,11 @@ impl<'a> Request<'a> {
 
 pub(crate) struct RequestDisplay<'a, 'b> {
     request: &'a Request<'b>,
-    level: AppDecodeLevel,
+    level: AppDecode,
 }
 
 impl<'a, 'b> RequestDisplay<'a, 'b> {
-    pub(crate) fn new(level: AppDecodeLevel, request: &'a Request<'b>) -> Self {
+    pub(crate) fn new(level: AppDecode, request: &'a Request<'b>) -> Self {
         Self { request, level }
     }
 }
@@ -234,10 +360,10 @@ impl std::fmt::Display for RequestDisplay<'_, '_> {
                     write!(f, " {}", range.get())?;
                 }
                 Request::WriteSingleCoil(request) => {
-                    write!(f, " {request}")?;
+                    write!(f, " {}", ValueDisplay::new(self.level, request))?;
                 }
                 Request::WriteSingleRegister(request) => {
-                    write!(f, " {request}")?;
+                    write!(f, " {}", ValueDisplay::new(self.level, request))?;
                 }
                 Request::WriteMultipleCoils(items) => {
                     write!(
@@ -326,6 +452,22 @@ mod tests {
         }
     }
 
+    mod single_coil {
+        use scursor::ReadCursor;
+
+        use super::super::*;
+        use crate::error::AduParseError;
+
+        #[test]
+        fn rejects_values_other_than_on_and_off() {
+            let mut cursor = ReadCursor::new(&[0x00, 0x01, 0x00, 0x01]);
+            let err = Request::parse(FunctionCode::WriteSingleCoil, &mut cursor)
+                .err()
+                .unwrap();
+            assert_eq!(err, AduParseError::UnknownCoilState(0x0001).into());
+        }
+    }
+
     mod registers {
         use scursor::ReadCursor;
 
@@ -386,4 +528,101 @@ mod tests {
             )
         }
     }
+
+    mod limits {
+        use scursor::ReadCursor;
+
+        use super::super::*;
+        use crate::constants::limits::*;
+        use crate::error::InvalidRange;
+
+        // range starting at zero followed by the byte count and values of a write request
+        fn request(count: u16, value_bytes: Option<usize>) -> Vec<u8> {
+            let mut bytes = vec![0x00, 0x00];
+            bytes.extend_from_slice(&count.to_be_bytes());
+            if let Some(length) = value_bytes {
+                bytes.push(length as u8);
+                bytes.resize(bytes.len() + length, 0);
+            }
+            bytes
+        }
+
+        fn parse(function: FunctionCode, bytes: &[u8]) -> Result<(), RequestError> {
+            let mut cursor = ReadCursor::new(bytes);
+            Request::parse(function, &mut cursor).map(|_| ())
+        }
+
+        fn too_large(count: u16, limit: u16) -> Result<(), RequestError> {
+            Err(InvalidRange::CountTooLargeForType(count, limit).into())
+        }
+
+        #[test]
+        fn parses_reads_up_to_the_limit() {
+            let limits = [
+                (FunctionCode::ReadCoils, MAX_READ_COILS_COUNT),
+                (FunctionCode::ReadDiscreteInputs, MAX_READ_COILS_COUNT),
+                (FunctionCode::ReadHoldingRegisters, MAX_READ_REGISTERS_COUNT),
+                (FunctionCode::ReadInputRegisters, MAX_READ_REGISTERS_COUNT),
+            ];
+            for (function, limit) in limits {
+                assert_eq!(parse(function, &request(limit, None)), Ok(()));
+                assert_eq!(
+                    parse(function, &request(limit + 1, None)),
+                    too_large(limit + 1, limit)
+                );
+            }
+        }
+
+        #[test]
+        fn parses_write_multiple_coils_up_to_the_limit() {
+            let limit = MAX_WRITE_COILS_COUNT;
+            let bytes =
+                |count| request(count, Some(crate::common::bits::num_bytes_for_bits(count)));
+            assert_eq!(
+                parse(FunctionCode::WriteMultipleCoils, &bytes(limit)),
+                Ok(())
+            );
+            assert_eq!(
+                parse(FunctionCode::WriteMultipleCoils, &bytes(limit + 1)),
+                too_large(limit + 1, limit)
+            );
+        }
+
+        #[test]
+        fn parses_write_multiple_registers_up_to_the_limit() {
+            let limit = MAX_WRITE_REGISTERS_COUNT;
+            let bytes = |count: u16| request(count, Some(2 * count as usize));
+            assert_eq!(
+                parse(FunctionCode::WriteMultipleRegisters, &bytes(limit)),
+                Ok(())
+            );
+            assert_eq!(
+                parse(FunctionCode::WriteMultipleRegisters, &bytes(limit + 1)),
+                too_large(limit + 1, limit)
+            );
+        }
+    }
+
+    mod fields {
+        use scursor::ReadCursor;
+
+        use super::super::*;
+
+        #[test]
+        fn reports_address_range_of_requests() {
+            let mut cursor = ReadCursor::new(&[0x00, 0x07, 0xFF, 0x00]);
+            let request = Request::parse(FunctionCode::WriteSingleCoil, &mut cursor).unwrap();
+            assert_eq!(
+                request.address_range(),
+                AddressRange::try_from(7, 1).unwrap()
+            );
+
+            let mut cursor = ReadCursor::new(&[0x00, 0x01, 0x00, 0x0A]);
+            let request = Request::parse(FunctionCode::ReadHoldingRegisters, &mut cursor).unwrap();
+            assert_eq!(
+                request.address_range(),
+                AddressRange::try_from(1, 10).unwrap()
+            );
+        }
+    }
 }
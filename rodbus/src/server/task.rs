@@ -1,23 +1,49 @@
+use crate::capture::Capture;
 use crate::common::phys::PhysLayer;
-use crate::server::{Authorization, AuthorizationHandler};
+use crate::server::fault::FaultPlan;
+use crate::server::options::SessionConfig;
+use crate::server::FaultInjector;
+use crate::server::{
+    AddressFilter, AsyncResponse, Authorization, AuthorizationHandler, FrameErrorPolicy,
+    HandlerTimeout, RequestThrottle, ServerHandlerType, SessionCloseReason, ThrottleMode,
+    UnitIdRouting, UnknownUnitIdPolicy,
+};
 use crate::{DecodeLevel, UnitId};
 
 use crate::common::frame::{
-    Frame, FrameDestination, FrameHeader, FrameWriter, FramedReader, FunctionField,
+    Frame, FrameDestination, FrameHeader, FrameWriter, FramedReader, FunctionField, TxId,
 };
 use crate::common::function::FunctionCode;
+use crate::common::metrics::{self, ActiveSession, ServerResult};
 use crate::error::*;
 use crate::exception::ExceptionCode;
+use crate::maybe_async::MaybeAsync;
 use crate::server::handler::{RequestHandler, ServerHandlerMap};
 use crate::server::request::{Request, RequestDisplay};
 
 use scursor::ReadCursor;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::Instrument;
+
+/// Time of the last valid request of a session, shared with the server task
+pub(crate) type LastActivity = Arc<std::sync::Mutex<Instant>>;
 
 /// Messages that can be sent to change server settings dynamically
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub enum ServerSetting {
     ChangeDecoding(DecodeLevel),
+    ChangeThrottle(Option<RequestThrottle>),
+    SetReadOnly(UnitId, bool),
+    SetCapture(Option<Capture>),
+    // the bool closes existing sessions whose peer doesn't match
+    SetAddressFilter(AddressFilter, bool),
+    SetMaxSessionsPolicy(crate::server::MaxSessionsPolicy),
+    // re-open the serial port of an RTU server
+    #[cfg(feature = "serial")]
+    ChangeSerialSettings(crate::serial::SerialSettings),
 }
 
 pub(crate) struct SessionTask<T>
@@ -30,6 +56,27 @@ where
     writer: FrameWriter,
     reader: FramedReader,
     decode: DecodeLevel,
+    idle_timeout: Option<Duration>,
+    max_lifetime: Option<Duration>,
+    last_request: Instant,
+    throttle: Option<RequestThrottle>,
+    handler_timeout: Option<HandlerTimeout>,
+    blocking_calls: BlockingCalls,
+    // time at which the last request was passed to a handler
+    last_processed: Option<Instant>,
+    fault_injector: Option<FaultInjector>,
+    frame_error_policy: FrameErrorPolicy,
+    unit_id_routing: UnitIdRouting,
+    strict_coil_padding: bool,
+    unknown_unit_id_policy: UnknownUnitIdPolicy,
+    capture: Option<Capture>,
+    activity: Option<LastActivity>,
+    // only the session of an RTU server accepts new serial settings
+    #[cfg(feature = "serial")]
+    accepts_serial_settings: bool,
+    // settings to apply the next time the serial port is opened
+    #[cfg(feature = "serial")]
+    serial_settings: Option<crate::serial::SerialSettings>,
 }
 
 impl<T> SessionTask<T>
@@ -51,7 +98,102 @@ where
             writer,
             reader,
             decode,
+            idle_timeout: None,
+            max_lifetime: None,
+            last_request: Instant::now(),
+            throttle: None,
+            handler_timeout: None,
+            blocking_calls: BlockingCalls::default(),
+            last_processed: None,
+            fault_injector: None,
+            frame_error_policy: FrameErrorPolicy::Close,
+            unit_id_routing: UnitIdRouting::Strict,
+            strict_coil_padding: false,
+            unknown_unit_id_policy: UnknownUnitIdPolicy::Silent,
+            capture: None,
+            activity: None,
+            #[cfg(feature = "serial")]
+            accepts_serial_settings: false,
+            #[cfg(feature = "serial")]
+            serial_settings: None,
+        }
+    }
+
+    /// Accept [`ServerSetting::ChangeSerialSettings`], which are retrieved with `take_serial_settings`
+    #[cfg(feature = "serial")]
+    pub(crate) fn accept_serial_settings(&mut self) {
+        self.accepts_serial_settings = true;
+    }
+
+    /// New serial settings received since the last call, if any
+    #[cfg(feature = "serial")]
+    pub(crate) fn take_serial_settings(&mut self) -> Option<crate::serial::SerialSettings> {
+        self.serial_settings.take()
+    }
+
+    /// Limit how long the session may go without a valid request, how long it may remain open,
+    /// and how long a partially received frame may take to complete. Also configures request
+    /// throttling, fault injection, the reaction to malformed frames, unit id routing, and capture.
+    pub(crate) fn configure(&mut self, limits: SessionConfig) {
+        self.idle_timeout = limits.idle_timeout;
+        self.max_lifetime = limits.max_lifetime;
+        self.reader.set_frame_timeout(limits.frame_timeout);
+        self.frame_error_policy = limits.frame_error_policy;
+        self.unit_id_routing = limits.unit_id_routing;
+        self.strict_coil_padding = limits.strict_coil_padding;
+        self.unknown_unit_id_policy = limits.unknown_unit_id_policy;
+        self.throttle = limits.request_throttle;
+        self.handler_timeout = limits.handler_timeout;
+        self.blocking_calls = limits.blocking_calls;
+        self.fault_injector = limits.fault_injector;
+        self.capture = limits.capture;
+    }
+
+    /// Record the time of each valid request in `activity`
+    pub(crate) fn set_activity(&mut self, activity: LastActivity) {
+        self.activity = Some(activity);
+    }
+
+    /// earliest time at which the next request may be processed, if throttling is enabled
+    fn throttle_deadline(&self, mode: ThrottleMode) -> Option<Instant> {
+        match (self.throttle, self.last_processed) {
+            (Some(throttle), Some(last)) if throttle.mode == mode => {
+                Some(last + throttle.min_request_gap)
+            }
+            _ => None,
+        }
+    }
+
+    async fn on_unknown_unit_id(
+        &mut self,
+        io: &mut PhysLayer,
+        header: FrameHeader,
+        function: FunctionCode,
+        unit_id: UnitId,
+    ) -> Result<(), RequestError> {
+        match self.unknown_unit_id_policy {
+            UnknownUnitIdPolicy::Silent => {}
+            UnknownUnitIdPolicy::LogOnly => {
+                tracing::debug!("ignoring {} for unmapped unit id: {}", function, unit_id);
+            }
+            UnknownUnitIdPolicy::RespondGatewayTargetFailed => {
+                tracing::warn!("rejecting {} for unmapped unit id: {}", function, unit_id);
+                return self
+                    .reply_with_error(
+                        io,
+                        header,
+                        function,
+                        ExceptionCode::GatewayTargetDeviceFailedToRespond,
+                    )
+                    .await;
+            }
         }
+        metrics::server_request(
+            FunctionField::Valid(function),
+            unit_id.value,
+            ServerResult::Ignored,
+        );
+        Ok(())
     }
 
     async fn reply_with_error(
@@ -72,23 +214,37 @@ where
         func: FunctionField,
         ex: ExceptionCode,
     ) -> Result<(), RequestError> {
+        metrics::server_request(func, header.destination.value(), ServerResult::Exception);
         // do not answer on broadcast
         if header.destination != FrameDestination::Broadcast {
             let bytes = self.writer.format_ex(header, func, ex, self.decode)?;
-            io.write(bytes, self.decode.physical).await?;
+            io.write(bytes, self.decode).await?;
         }
         Ok(())
     }
 
-    pub(crate) async fn run(&mut self, io: &mut PhysLayer) -> RequestError {
+    pub(crate) async fn run(&mut self, io: &mut PhysLayer) -> SessionCloseReason {
+        let _active = ActiveSession::new();
+        let lifetime_deadline = self.max_lifetime.map(|x| Instant::now() + x);
+        self.last_request = Instant::now();
+        io.set_capture(self.capture.clone());
         loop {
-            if let Err(err) = self.run_one(io).await {
-                tracing::warn!("session error: {}", err);
+            if let Err(err) = self.run_one(io, lifetime_deadline).await {
+                if let SessionCloseReason::Error(err) = err {
+                    tracing::warn!("session error: {}", err);
+                }
                 return err;
             }
         }
     }
 
+    async fn sleep_until(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
     #[cfg(feature = "serial")]
     pub(crate) async fn sleep_for(
         &mut self,
@@ -114,21 +270,63 @@ where
         }
     }
 
-    async fn run_one(&mut self, io: &mut PhysLayer) -> Result<(), RequestError> {
+    async fn run_one(
+        &mut self,
+        io: &mut PhysLayer,
+        lifetime_deadline: Option<Instant>,
+    ) -> Result<(), SessionCloseReason> {
+        let idle_deadline = self.idle_timeout.map(|x| self.last_request + x);
+        // in delay mode, we don't read the next request until the gap has elapsed
+        let throttle_deadline = self
+            .throttle_deadline(ThrottleMode::Delay)
+            .filter(|x| *x > Instant::now());
         tokio::select! {
-            frame = self.reader.next_frame(io, self.decode) => {
-                let frame = frame?;
-                self.handle_frame(io, frame).await
+            frame = self.reader.next_frame(io, self.decode), if throttle_deadline.is_none() => {
+                let frame = match frame {
+                    Err(RequestError::BadFrame(err)) if self.frame_error_policy == FrameErrorPolicy::Discard => {
+                        tracing::warn!("discarding buffered data: {}", err);
+                        self.reader.discard();
+                        return Ok(());
+                    }
+                    x => x?,
+                };
+                let span = tracing::info_span!(
+                    "Transaction",
+                    unit = frame.header.destination.value(),
+                    tx_id = tracing::field::Empty
+                );
+                // RTU frames don't have a transaction id
+                if let Some(tx_id) = frame.header.tx_id {
+                    span.record("tx_id", tracing::field::display(tx_id));
+                }
+                self.handle_frame(io, frame).instrument(span).await?;
+                Ok(())
+            }
+            _ = Self::sleep_until(throttle_deadline), if throttle_deadline.is_some() => {
+                // bytes of the next request may have been buffered before the delay
+                self.reader.restart_frame_timer();
+                Ok(())
             }
             cmd = self.commands.recv() => {
                match cmd {
-                    None => Err(crate::error::RequestError::Shutdown),
+                    None => Err(crate::error::RequestError::Shutdown.into()),
                     Some(setting) => {
                         self.apply_setting(setting);
+                        io.set_capture(self.capture.clone());
+                        #[cfg(feature = "serial")]
+                        if self.serial_settings.is_some() {
+                            return Err(SessionCloseReason::SerialSettingsChanged);
+                        }
                         Ok(())
                     }
                }
             }
+            _ = Self::sleep_until(idle_deadline) => {
+                Err(SessionCloseReason::IdleTimeout)
+            }
+            _ = Self::sleep_until(lifetime_deadline) => {
+                Err(SessionCloseReason::MaxLifetime)
+            }
         }
     }
 
@@ -137,6 +335,24 @@ where
             ServerSetting::ChangeDecoding(level) => {
                 self.decode = level;
             }
+            ServerSetting::ChangeThrottle(throttle) => {
+                self.throttle = throttle;
+            }
+            ServerSetting::SetReadOnly(unit_id, read_only) => {
+                self.handlers.set_read_only(unit_id, read_only);
+            }
+            ServerSetting::SetCapture(capture) => {
+                self.capture = capture;
+            }
+            // applied by the server task when accepting connections
+            ServerSetting::SetAddressFilter(..) | ServerSetting::SetMaxSessionsPolicy(_) => {}
+            #[cfg(feature = "serial")]
+            ServerSetting::ChangeSerialSettings(settings) => {
+                if self.accepts_serial_settings {
+                    tracing::info!("changed serial settings to {:?}", settings);
+                    self.serial_settings = Some(settings);
+                }
+            }
         }
     }
 
@@ -174,10 +390,40 @@ where
             }
         };
 
+        if self.strict_coil_padding {
+            if let Request::WriteMultipleCoils(x) = &request {
+                if !x.iterator.has_clean_padding() {
+                    tracing::warn!(
+                        "unused bits of the last byte of {:?} request are not zero",
+                        function
+                    );
+                    return self
+                        .reply_with_error(
+                            io,
+                            frame.header,
+                            function,
+                            ExceptionCode::IllegalDataValue,
+                        )
+                        .await;
+                }
+            }
+        }
+
+        self.last_request = Instant::now();
+        if let Some(activity) = &self.activity {
+            *activity.lock().unwrap() = self.last_request;
+        }
+
         if self.decode.app.enabled() {
+            let range = request.address_range();
             tracing::info!(
+                fc = function.get_value(),
+                unit = frame.header.destination.value(),
+                tx_id = frame.header.tx_id.map(|x| x.to_u16()),
+                start = range.start,
+                count = range.count,
                 "PDU RX - {}",
-                RequestDisplay::new(self.decode.app, &request)
+                RequestDisplay::new(self.decode.app_decode(), &request)
             );
         }
 
@@ -198,33 +444,160 @@ where
             return Ok(());
         }
 
+        // in busy mode, reply to requests that arrive before the gap has elapsed with an exception
+        if let Some(deadline) = self.throttle_deadline(ThrottleMode::Busy) {
+            if Instant::now() < deadline {
+                if !frame.header.destination.is_broadcast() {
+                    self.reply_with_error(
+                        io,
+                        frame.header,
+                        request.get_function(),
+                        ExceptionCode::ServerDeviceBusy,
+                    )
+                    .await?;
+                }
+                return Ok(());
+            }
+        }
+
+        self.last_processed = Some(Instant::now());
+
         // if no addresses match, then don't respond
         match frame.header.destination {
             FrameDestination::UnitId(unit_id) => {
-                let handler = match self.handlers.get(unit_id) {
+                // the reply header still echoes the requested unit id
+                let target = self
+                    .handlers
+                    .route(unit_id, self.unit_id_routing)
+                    .unwrap_or(unit_id);
+                let read_only = self.handlers.is_read_only(target);
+                let transactional = self.handlers.has_transactional_writes(target);
+                let handler = match self.handlers.get(target) {
                     None => {
-                        tracing::warn!("received frame for unmapped unit id: {}", unit_id);
-                        return Ok(());
+                        return self
+                            .on_unknown_unit_id(io, frame.header, function, unit_id)
+                            .await;
                     }
                     Some(handler) => handler,
                 };
+                if read_only && request.is_write() {
+                    tracing::warn!("rejecting {} to read-only unit id: {}", function, unit_id);
+                    return self
+                        .reply_with_error(
+                            io,
+                            frame.header,
+                            request.get_function(),
+                            ExceptionCode::IllegalFunction,
+                        )
+                        .await;
+                }
+                let faults = self
+                    .fault_injector
+                    .as_ref()
+                    .and_then(|x| x.plan(function.get_value()));
+                let mut header = frame.header;
+                if let Some(FaultPlan {
+                    wrong_tx_id: true, ..
+                }) = faults
+                {
+                    header.tx_id = header.tx_id.map(|x| TxId::new(x.to_u16().wrapping_add(1)));
+                }
                 // get the reply data (or exception reply)
-                let reply: &[u8] = request.get_reply(
-                    frame.header,
-                    handler.lock().unwrap().as_mut(),
-                    &mut self.writer,
-                    self.decode,
-                )?;
-                io.write(reply, self.decode.physical).await?;
+                let reply: &[u8] = match faults.as_ref().and_then(|x| x.exception) {
+                    Some(ex) => self.writer.format_ex(
+                        header,
+                        FunctionField::Exception(request.get_function()),
+                        ex,
+                        self.decode,
+                    )?,
+                    None if self.handler_timeout.is_some() => {
+                        Self::get_reply_with_timeout(
+                            &mut self.writer,
+                            handler.clone(),
+                            &self.blocking_calls,
+                            &request,
+                            frame.payload(),
+                            header,
+                            self.decode,
+                            transactional,
+                            self.handler_timeout.unwrap(),
+                        )
+                        .await?
+                    }
+                    None => {
+                        // the lock is acquired once per request and released before an
+                        // asynchronous result is awaited
+                        let pending = {
+                            let mut handler = handler.lock().unwrap();
+                            let pending = handler.process_async(unit_id, request.as_async());
+                            if pending.is_none() {
+                                request.get_reply(
+                                    header,
+                                    handler.as_mut(),
+                                    &mut self.writer,
+                                    self.decode,
+                                    transactional,
+                                )?;
+                            }
+                            pending
+                        };
+                        match pending {
+                            None => self.writer.last_frame(),
+                            Some(result) => {
+                                let result = result.get().await;
+                                request.get_async_reply(
+                                    header,
+                                    result,
+                                    &mut self.writer,
+                                    self.decode,
+                                )?
+                            }
+                        }
+                    }
+                };
+                match faults {
+                    None => io.write(reply, self.decode).await?,
+                    Some(faults) => {
+                        let is_rtu = header.tx_id.is_none();
+                        faults.write(io, reply, is_rtu, self.decode).await?
+                    }
+                }
+                let result = if self.writer.wrote_exception() {
+                    ServerResult::Exception
+                } else {
+                    ServerResult::Success
+                };
+                metrics::server_request(FunctionField::Valid(function), unit_id.value, result);
             }
             FrameDestination::Broadcast => match request.into_broadcast_request() {
                 None => {
                     tracing::warn!("broadcast is not supported for {}", function);
+                    metrics::server_request(
+                        FunctionField::Valid(function),
+                        UnitId::broadcast().value,
+                        ServerResult::Ignored,
+                    );
                 }
                 Some(request) => {
-                    for handler in self.handlers.iter_mut() {
-                        request.execute(handler.lock().unwrap().as_mut());
+                    for (handler, transactional) in self.handlers.iter_writable_mut() {
+                        let pending = {
+                            let mut handler = handler.lock().unwrap();
+                            let pending =
+                                handler.process_async(UnitId::broadcast(), request.as_async());
+                            if pending.is_none() {
+                                request.execute(handler.as_mut(), transactional);
+                            }
+                            pending
+                        };
+                        if let Some(result) = pending {
+                            let _ = result.get().await;
+                        }
                     }
+                    metrics::server_request(
+                        FunctionField::Valid(function),
+                        UnitId::broadcast().value,
+                        ServerResult::Success,
+                    );
                 }
             },
         }
@@ -233,6 +606,167 @@ where
     }
 }
 
+/// Deadlines of the handler calls running on the blocking thread pool, shared by the sessions of a server
+///
+/// A call that is past its deadline still holds the lock of its handler, so later requests to that
+/// handler are answered with the exception right away instead of occupying more blocking threads.
+#[derive(Clone, Default)]
+pub(crate) struct BlockingCalls {
+    deadlines: Arc<Mutex<HashMap<usize, Vec<Instant>>>>,
+}
+
+impl BlockingCalls {
+    /// Record a call to the handler, or return `None` if a previous call is past its deadline
+    fn start<T>(&self, handler: &ServerHandlerType<T>, deadline: Instant) -> Option<BlockingCall> {
+        let key = Arc::as_ptr(handler) as usize;
+        let mut deadlines = self.deadlines.lock().unwrap();
+        let calls = deadlines.entry(key).or_default();
+        let now = Instant::now();
+        if calls.iter().any(|x| *x <= now) {
+            return None;
+        }
+        calls.push(deadline);
+        Some(BlockingCall {
+            calls: self.clone(),
+            key,
+            deadline,
+        })
+    }
+}
+
+/// Removes the deadline of a call from [`BlockingCalls`] once the handler returns
+struct BlockingCall {
+    calls: BlockingCalls,
+    key: usize,
+    deadline: Instant,
+}
+
+impl Drop for BlockingCall {
+    fn drop(&mut self) {
+        let mut deadlines = self.calls.deadlines.lock().unwrap();
+        if let Some(calls) = deadlines.get_mut(&self.key) {
+            if let Some(pos) = calls.iter().position(|x| *x == self.deadline) {
+                calls.swap_remove(pos);
+            }
+            if calls.is_empty() {
+                deadlines.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// Result of running a handler on the blocking thread pool
+enum BlockingReply {
+    /// The reply was formatted by the synchronous handler
+    Formatted(Box<FrameWriter>),
+    /// The handler returned an asynchronous result
+    Pending(MaybeAsync<Result<AsyncResponse, ExceptionCode>>),
+}
+
+impl<T> SessionTask<T>
+where
+    T: RequestHandler,
+{
+    /// Run the handler on the blocking thread pool so that the timeout can expire while a synchronous handler executes
+    #[allow(clippy::too_many_arguments)]
+    async fn get_reply_with_timeout<'a>(
+        writer: &'a mut FrameWriter,
+        handler: ServerHandlerType<T>,
+        blocking_calls: &BlockingCalls,
+        request: &Request<'_>,
+        payload: &[u8],
+        header: FrameHeader,
+        decode: DecodeLevel,
+        transactional: bool,
+        limit: HandlerTimeout,
+    ) -> Result<&'a [u8], RequestError> {
+        let deadline = Instant::now() + limit.timeout;
+        let function = request.get_function();
+        let call = match blocking_calls.start(&handler, deadline) {
+            Some(call) => call,
+            None => {
+                tracing::warn!(
+                    "handler for unit id {} has not returned from a request that timed out",
+                    header.destination.value()
+                );
+                return writer.format_ex(
+                    header,
+                    FunctionField::Exception(function),
+                    limit.exception,
+                    decode,
+                );
+            }
+        };
+        let payload = payload.to_vec();
+        let unit_id = header.destination.into_unit_id();
+        let mut blocking_writer = writer.clone();
+
+        let task = tokio::task::spawn_blocking(move || -> Result<BlockingReply, RequestError> {
+            // dropped when the handler returns, even if it panics
+            let _call = call;
+            // the request is parsed again from a copy because the task may outlive the frame
+            let mut cursor = ReadCursor::new(&payload);
+            cursor.read_u8()?;
+            let request = Request::parse(function, &mut cursor)?;
+            let mut handler = handler.lock().unwrap();
+            match handler.process_async(unit_id, request.as_async()) {
+                Some(result) => Ok(BlockingReply::Pending(result)),
+                None => {
+                    request.get_reply(
+                        header,
+                        handler.as_mut(),
+                        &mut blocking_writer,
+                        decode,
+                        transactional,
+                    )?;
+                    Ok(BlockingReply::Formatted(Box::new(blocking_writer)))
+                }
+            }
+        });
+
+        let reply = match tokio::time::timeout_at(deadline, task).await {
+            Ok(Ok(reply)) => reply?,
+            Ok(Err(err)) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Ok(Err(_)) => return Err(RequestError::Shutdown),
+            Err(_) => return Self::reply_after_timeout(writer, header, function, decode, limit),
+        };
+
+        match reply {
+            BlockingReply::Formatted(formatted) => {
+                *writer = *formatted;
+                Ok(writer.last_frame())
+            }
+            BlockingReply::Pending(result) => {
+                match tokio::time::timeout_at(deadline, result.get()).await {
+                    Ok(result) => request.get_async_reply(header, result, writer, decode),
+                    Err(_) => Self::reply_after_timeout(writer, header, function, decode, limit),
+                }
+            }
+        }
+    }
+
+    fn reply_after_timeout(
+        writer: &mut FrameWriter,
+        header: FrameHeader,
+        function: FunctionCode,
+        decode: DecodeLevel,
+        limit: HandlerTimeout,
+    ) -> Result<&[u8], RequestError> {
+        tracing::warn!(
+            "handler for unit id {} did not process {} within {:?}",
+            header.destination.value(),
+            function,
+            limit.timeout
+        );
+        writer.format_ex(
+            header,
+            FunctionField::Exception(function),
+            limit.exception,
+            decode,
+        )
+    }
+}
+
 /// Determines how authorization of user defined requests are handled
 pub(crate) enum AuthorizationType {
     /// Requests do not require authorization checks (TCP / RTU)
@@ -284,3 +818,228 @@ impl AuthorizationType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::test_log::LogCapture;
+    use crate::decode::FrameDecodeLevel;
+    use crate::server::{ServerHandle, SERVER_SETTING_CHANNEL_CAPACITY};
+
+    use sfio_tokio_mock_io::Event;
+
+    struct DefaultHandler;
+    impl RequestHandler for DefaultHandler {}
+
+    // read a single holding register from unit 1
+    const MBAP_REQUEST: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+    ];
+    const RTU_REQUEST: &[u8] = &[0x01, 0x03, 0x00, 0x00, 0x00, 0x01, 0x84, 0x0A];
+
+    #[tokio::test]
+    async fn decode_level_changes_apply_to_tcp_and_rtu_sessions() {
+        let logs = LogCapture::default();
+        let _guard = logs.install();
+
+        for (writer, reader, request, prefix) in [
+            (
+                FrameWriter::tcp(),
+                FramedReader::tcp(),
+                MBAP_REQUEST,
+                "MBAP TX",
+            ),
+            (
+                FrameWriter::rtu(),
+                FramedReader::rtu_request(),
+                RTU_REQUEST,
+                "RTU TX",
+            ),
+        ] {
+            let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+            let mut session = SessionTask::new(
+                ServerHandlerMap::single(UnitId::new(1), DefaultHandler.wrap()),
+                AuthorizationType::None,
+                writer,
+                reader,
+                rx,
+                DecodeLevel::nothing(),
+            );
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                session.run(&mut phys).await
+            });
+            let mut handle = ServerHandle::new(tx);
+
+            for (level, logged) in [
+                (DecodeLevel::nothing(), false),
+                (DecodeLevel::nothing().frame(FrameDecodeLevel::Header), true),
+                (DecodeLevel::nothing(), false),
+            ] {
+                handle.set_decode_level(level).await.unwrap();
+                // let the session apply the setting before the request arrives
+                tokio::task::yield_now().await;
+                io.read(request);
+                assert_eq!(io.next_event().await, Event::Read);
+                assert!(matches!(io.next_event().await, Event::Write(_)));
+                assert_eq!(logs.take().contains(prefix), logged);
+            }
+        }
+    }
+
+    fn rtu_frame(adu: &[u8]) -> Vec<u8> {
+        let mut frame = adu.to_vec();
+        let crc = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS).checksum(adu);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    #[tokio::test]
+    async fn redacted_values_are_not_logged_at_any_level() {
+        // write 0xBEEF to the holding register that contains 0xCAFE
+        let mbap_write: &[u8] = &[
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x00, 0xBE, 0xEF,
+        ];
+        let rtu_write = rtu_frame(&[0x01, 0x06, 0x00, 0x00, 0xBE, 0xEF]);
+        let level = DecodeLevel::new(
+            crate::decode::AppDecodeLevel::DataValues,
+            FrameDecodeLevel::Payload,
+            crate::decode::PhysDecodeLevel::Data,
+        )
+        .redact_values(true);
+        let logs = LogCapture::default();
+        let _guard = logs.install();
+
+        for (writer, reader, read, write) in [
+            (
+                FrameWriter::tcp(),
+                FramedReader::tcp(),
+                MBAP_REQUEST,
+                mbap_write,
+            ),
+            (
+                FrameWriter::rtu(),
+                FramedReader::rtu_request(),
+                RTU_REQUEST,
+                rtu_write.as_slice(),
+            ),
+        ] {
+            let mut database =
+                crate::server::MemoryDatabase::new(crate::server::DatabaseLayout::Sparse);
+            database.add_holding_register(0, 0xCAFE);
+            let (_tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+            let mut session = SessionTask::new(
+                ServerHandlerMap::single(UnitId::new(1), database.wrap()),
+                AuthorizationType::None,
+                writer,
+                reader,
+                rx,
+                level,
+            );
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                session.run(&mut phys).await
+            });
+
+            for request in [read, write] {
+                io.read(request);
+                assert_eq!(io.next_event().await, Event::Read);
+                assert!(matches!(io.next_event().await, Event::Write(_)));
+            }
+
+            let output = logs.take();
+            assert!(output.contains("idx: 0x0000 value: **"), "{output}");
+            assert!(output.contains("00 00 ** **"), "{output}");
+            for value in ["CAFE", "CA FE", "BEEF", "BE EF"] {
+                assert!(!output.contains(value), "{output}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rtu_session_applies_the_unknown_unit_id_policy() {
+        // read a single holding register from unit 2, which has no handler
+        let unknown = rtu_frame(&[0x02, 0x03, 0x00, 0x00, 0x00, 0x01]);
+        let rejected = rtu_frame(&[0x02, 0x83, 0x0B]);
+        let logs = LogCapture::default();
+        let _guard = logs.install_with_level(tracing::Level::DEBUG);
+
+        for policy in [
+            UnknownUnitIdPolicy::Silent,
+            UnknownUnitIdPolicy::LogOnly,
+            UnknownUnitIdPolicy::RespondGatewayTargetFailed,
+        ] {
+            let (_tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+            let mut session = SessionTask::new(
+                ServerHandlerMap::single(UnitId::new(1), DefaultHandler.wrap()),
+                AuthorizationType::None,
+                FrameWriter::rtu(),
+                FramedReader::rtu_request(),
+                rx,
+                DecodeLevel::nothing(),
+            );
+            session.configure(SessionConfig {
+                unknown_unit_id_policy: policy,
+                ..Default::default()
+            });
+            let (mock, mut io) = sfio_tokio_mock_io::mock();
+            let _task = tokio::spawn(async move {
+                let mut phys = PhysLayer::new_mock(mock);
+                session.run(&mut phys).await
+            });
+
+            io.read(&unknown);
+            assert_eq!(io.next_event().await, Event::Read);
+            if policy == UnknownUnitIdPolicy::RespondGatewayTargetFailed {
+                assert_eq!(io.next_event().await, Event::Write(rejected.clone()));
+            }
+            // the next write is the reply to the request for the mapped unit id
+            io.read(RTU_REQUEST);
+            assert_eq!(io.next_event().await, Event::Read);
+            match io.next_event().await {
+                Event::Write(bytes) => assert_eq!(bytes[0], 0x01),
+                other => panic!("unexpected event: {other:?}"),
+            }
+
+            let logged = logs.take().contains("ignoring READ HOLDING REGISTERS");
+            assert_eq!(logged, policy == UnknownUnitIdPolicy::LogOnly, "{policy:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_delay_does_not_time_out_a_buffered_partial_frame() {
+        let (_tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+        let mut session = SessionTask::new(
+            ServerHandlerMap::single(UnitId::new(1), DefaultHandler.wrap()),
+            AuthorizationType::None,
+            FrameWriter::tcp(),
+            FramedReader::tcp(),
+            rx,
+            DecodeLevel::nothing(),
+        );
+        session.configure(SessionConfig {
+            frame_timeout: Some(Duration::from_secs(1)),
+            request_throttle: Some(RequestThrottle::min_request_gap(Duration::from_secs(5))),
+            ..Default::default()
+        });
+        let (mock, mut io) = sfio_tokio_mock_io::mock();
+        let _task = tokio::spawn(async move {
+            let mut phys = PhysLayer::new_mock(mock);
+            session.run(&mut phys).await
+        });
+
+        // the first half of the second request arrives with the first request
+        let (head, tail) = MBAP_REQUEST.split_at(6);
+        io.read(&[MBAP_REQUEST, head].concat());
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+
+        // the rest arrives after the delay, within the frame timeout counted from the end of the delay
+        tokio::time::sleep(Duration::from_millis(5500)).await;
+        io.read(tail);
+        assert_eq!(io.next_event().await, Event::Read);
+        assert!(matches!(io.next_event().await, Event::Write(_)));
+    }
+}
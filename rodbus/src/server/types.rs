@@ -1,4 +1,4 @@
-use crate::types::{AddressRange, BitIterator, RegisterIterator};
+use crate::types::{AddressRange, BitIterator, Indexed, RegisterIterator};
 
 /// Request to write coils received by the server
 #[derive(Debug, Copy, Clone)]
@@ -29,3 +29,42 @@ impl<'a> WriteRegisters<'a> {
         Self { range, iterator }
     }
 }
+
+/// Request passed to [`RequestHandler::process_async`](crate::server::RequestHandler::process_async)
+///
+/// The values of write requests borrow the received frame. Copy them if the future that
+/// processes the request needs them.
+#[derive(Debug, Copy, Clone)]
+pub enum AsyncRequest<'a> {
+    /// Read coils
+    ReadCoils(AddressRange),
+    /// Read discrete inputs
+    ReadDiscreteInputs(AddressRange),
+    /// Read holding registers
+    ReadHoldingRegisters(AddressRange),
+    /// Read input registers
+    ReadInputRegisters(AddressRange),
+    /// Write a single coil
+    WriteSingleCoil(Indexed<bool>),
+    /// Write a single register
+    WriteSingleRegister(Indexed<u16>),
+    /// Write multiple coils
+    WriteMultipleCoils(WriteCoils<'a>),
+    /// Write multiple registers
+    WriteMultipleRegisters(WriteRegisters<'a>),
+}
+
+/// Successful result of a request processed by [`RequestHandler::process_async`](crate::server::RequestHandler::process_async)
+///
+/// Values are ordered from the start of the requested range. If fewer values than requested are
+/// returned, or the variant doesn't match the request, [`ExceptionCode::ServerDeviceFailure`](crate::ExceptionCode::ServerDeviceFailure)
+/// is returned to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsyncResponse {
+    /// Values of the coils or discrete inputs that were read
+    Bits(Vec<bool>),
+    /// Values of the holding or input registers that were read
+    Registers(Vec<u16>),
+    /// The write request was performed
+    Written,
+}
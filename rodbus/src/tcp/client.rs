@@ -1,6 +1,9 @@
 use tracing::Instrument;
 
-use crate::client::{Channel, ClientState, HostAddr, Listener};
+use crate::client::{
+    Channel, ClientOptions, ClientState, ConnectFailReason, ConnectionInfo, DisconnectReason,
+    HostAddr, Listener,
+};
 use crate::common::phys::PhysLayer;
 use crate::decode::DecodeLevel;
 
@@ -12,45 +15,71 @@ use crate::retry::RetryStrategy;
 
 use tokio::net::TcpStream;
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn spawn_tcp_channel(
     host: HostAddr,
+    connection_handler: TcpTaskConnectionHandler,
     max_queued_requests: usize,
     connect_retry: Box<dyn RetryStrategy>,
     decode: DecodeLevel,
     listener: Box<dyn Listener<ClientState>>,
+    connection_listener: Box<dyn Listener<ConnectionInfo>>,
+    options: ClientOptions,
 ) -> Channel {
-    let (handle, task) =
-        create_tcp_channel(host, max_queued_requests, connect_retry, decode, listener);
+    let (handle, task) = create_tcp_channel(
+        host,
+        connection_handler,
+        max_queued_requests,
+        connect_retry,
+        decode,
+        listener,
+        connection_listener,
+        options,
+    );
     tokio::spawn(task);
     handle
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_tcp_channel(
     host: HostAddr,
+    connection_handler: TcpTaskConnectionHandler,
     max_queued_requests: usize,
     connect_retry: Box<dyn RetryStrategy>,
     decode: DecodeLevel,
     listener: Box<dyn Listener<ClientState>>,
+    connection_listener: Box<dyn Listener<ConnectionInfo>>,
+    options: ClientOptions,
 ) -> (Channel, impl std::future::Future<Output = ()>) {
-    let (tx, rx) = tokio::sync::mpsc::channel(max_queued_requests);
+    let (tx, rx) = crate::channel::channel(max_queued_requests);
+    let span = match connection_handler {
+        TcpTaskConnectionHandler::RtuOverTcp => {
+            tracing::info_span!("Modbus-Client-RTU-over-TCP", endpoint = ?host)
+        }
+        _ => tracing::info_span!("Modbus-Client-TCP", endpoint = ?host),
+    };
     let task = async move {
         TcpChannelTask::new(
             host.clone(),
-            rx.into(),
-            TcpTaskConnectionHandler::Tcp,
+            rx,
+            connection_handler,
             connect_retry,
             decode,
             listener,
+            connection_listener,
+            options,
         )
         .run()
-        .instrument(tracing::info_span!("Modbus-Client-TCP", endpoint = ?host))
+        .instrument(span)
         .await;
     };
-    (Channel { tx }, task)
+    (Channel::new(tx), task)
 }
 
 pub(crate) enum TcpTaskConnectionHandler {
     Tcp,
+    // plain TCP socket that carries RTU frames instead of MBAP
+    RtuOverTcp,
     #[cfg(feature = "tls")]
     Tls(crate::tcp::tls::TlsClientConfig),
 }
@@ -60,13 +89,20 @@ impl TcpTaskConnectionHandler {
         &mut self,
         socket: TcpStream,
         _endpoint: &HostAddr,
-    ) -> Result<PhysLayer, String> {
+    ) -> Result<PhysLayer, (ConnectFailReason, String)> {
         match self {
-            Self::Tcp => Ok(PhysLayer::new_tcp(socket)),
+            Self::Tcp | Self::RtuOverTcp => Ok(PhysLayer::new_tcp(socket)),
             #[cfg(feature = "tls")]
             Self::Tls(config) => config.handle_connection(socket, _endpoint).await,
         }
     }
+
+    fn framing(&self) -> (FrameWriter, FramedReader) {
+        match self {
+            Self::RtuOverTcp => (FrameWriter::rtu(), FramedReader::rtu_response()),
+            _ => (FrameWriter::tcp(), FramedReader::tcp()),
+        }
+    }
 }
 
 pub(crate) struct TcpChannelTask {
@@ -75,9 +111,14 @@ pub(crate) struct TcpChannelTask {
     connection_handler: TcpTaskConnectionHandler,
     client_loop: ClientLoop,
     listener: Box<dyn Listener<ClientState>>,
+    connection_listener: Box<dyn Listener<ConnectionInfo>>,
+    max_connect_attempts: Option<std::num::NonZeroUsize>,
+    failed_connect_attempts: usize,
+    options: ClientOptions,
 }
 
 impl TcpChannelTask {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         host: HostAddr,
         rx: crate::channel::Receiver<Command>,
@@ -85,13 +126,23 @@ impl TcpChannelTask {
         connect_retry: Box<dyn RetryStrategy>,
         decode: DecodeLevel,
         listener: Box<dyn Listener<ClientState>>,
+        connection_listener: Box<dyn Listener<ConnectionInfo>>,
+        options: ClientOptions,
     ) -> Self {
+        let (writer, reader) = connection_handler.framing();
+        let mut client_loop = ClientLoop::new(rx, writer, reader, decode);
+        client_loop.set_idle_timeout(options.idle_disconnect);
+        let listener = client_loop.track_state(listener);
         Self {
             host,
             connect_retry,
             connection_handler,
-            client_loop: ClientLoop::new(rx, FrameWriter::tcp(), FramedReader::tcp(), decode),
+            client_loop,
             listener,
+            connection_listener,
+            max_connect_attempts: options.max_connect_attempts,
+            failed_connect_attempts: 0,
+            options,
         }
     }
 
@@ -114,14 +165,23 @@ impl TcpChannelTask {
             }
 
             if !self.client_loop.is_enabled() {
+                self.failed_connect_attempts = 0;
                 self.listener.update(ClientState::Disabled).get().await;
             }
         }
     }
 
-    async fn connect(&mut self) -> Result<Result<TcpStream, std::io::Error>, StateChange> {
+    #[allow(clippy::type_complexity)]
+    async fn connect(
+        &mut self,
+    ) -> Result<Result<TcpStream, (ConnectFailReason, std::io::Error)>, StateChange> {
+        if self.client_loop.has_pending_request() {
+            // reconnecting on demand after an idle disconnect, requests are queued in the meantime
+            return Ok(self.host.connect(&self.options).await);
+        }
+
         tokio::select! {
-            res = self.host.connect() => {
+            res = self.host.connect(&self.options) => {
                 Ok(res)
             }
             res = self.client_loop.fail_requests() => {
@@ -133,61 +193,55 @@ impl TcpChannelTask {
     async fn try_connect_and_run(&mut self) -> Result<(), StateChange> {
         self.listener.update(ClientState::Connecting).get().await;
         match self.connect().await? {
-            Err(err) => {
-                let delay = self.connect_retry.after_failed_connect();
-                tracing::warn!(
-                    "failed to connect to {}: {} - waiting {} ms before next attempt",
-                    self.host,
-                    err,
-                    delay.as_millis()
-                );
-                self.listener
-                    .update(ClientState::WaitAfterFailedConnect(delay))
-                    .get()
-                    .await;
-                self.client_loop.fail_requests_for(delay).await
+            Err((reason, err)) => {
+                self.on_failed_connect(
+                    reason,
+                    format!("failed to connect to {}: {}", self.host, err),
+                )
+                .await
             }
             Ok(socket) => {
-                if let Ok(addr) = socket.peer_addr() {
-                    tracing::info!("connected to: {}", addr);
-                }
+                let info = match (socket.local_addr(), socket.peer_addr()) {
+                    (Ok(local), Ok(peer)) => {
+                        tracing::info!("connected to: {} from: {}", peer, local);
+                        Some(ConnectionInfo { local, peer })
+                    }
+                    _ => None,
+                };
                 if let Err(err) = socket.set_nodelay(true) {
                     tracing::warn!("unable to enable TCP_NODELAY: {}", err);
                 }
                 match self.connection_handler.handle(socket, &self.host).await {
-                    Err(err) => {
-                        let delay = self.connect_retry.after_failed_connect();
-                        tracing::warn!(
-                            "{} - waiting {} ms before next attempt",
-                            err,
-                            delay.as_millis()
-                        );
-                        self.listener
-                            .update(ClientState::WaitAfterFailedConnect(delay))
-                            .get()
-                            .await;
-                        self.client_loop.fail_requests_for(delay).await
-                    }
+                    Err((reason, err)) => self.on_failed_connect(reason, err).await,
                     Ok(mut phys) => {
+                        if let Some(info) = info {
+                            self.connection_listener.update(info).get().await;
+                        }
                         self.listener.update(ClientState::Connected).get().await;
                         // reset the retry strategy now that we have a successful connection
                         // we do this here so that the reset happens after a TLS handshake
                         self.connect_retry.reset();
+                        self.failed_connect_attempts = 0;
                         // run the physical layer independent processing loop
-                        match self.client_loop.run(&mut phys).await {
+                        let result = self.client_loop.run(&mut phys).await;
+                        // close the connection before waiting or reporting the new state
+                        drop(phys);
+                        match result {
                             // the mpsc was closed, end the task
                             SessionError::Shutdown => Err(StateChange::Shutdown),
+                            // don't wait, we're disabled (serial settings are rejected by TCP channels)
+                            SessionError::Disabled | SessionError::SerialSettingsChanged => Ok(()),
+                            // reconnect immediately once another request is made
+                            SessionError::Idle => {
+                                self.listener.update(ClientState::Idle).get().await;
+                                self.client_loop.wait_for_request().await
+                            }
                             // re-establish the connection
-                            SessionError::Disabled
-                            | SessionError::IoError(_)
-                            | SessionError::BadFrame => {
-                                let delay = self.connect_retry.after_disconnect();
-                                tracing::warn!("waiting {:?} to reconnect", delay);
-                                self.listener
-                                    .update(ClientState::WaitAfterDisconnect(delay))
-                                    .get()
-                                    .await;
-                                self.client_loop.fail_requests_for(delay).await
+                            SessionError::IoError(kind) => {
+                                self.on_disconnect(DisconnectReason::Io(kind)).await
+                            }
+                            SessionError::BadFrame => {
+                                self.on_disconnect(DisconnectReason::BadFrame).await
                             }
                         }
                     }
@@ -195,4 +249,401 @@ impl TcpChannelTask {
             }
         }
     }
+
+    async fn on_disconnect(&mut self, reason: DisconnectReason) -> Result<(), StateChange> {
+        crate::common::metrics::client_reconnect();
+        let delay = self.connect_retry.after_disconnect();
+        tracing::warn!("waiting {:?} to reconnect", delay);
+        self.listener
+            .update(ClientState::WaitAfterDisconnect {
+                reason,
+                retry_in: delay,
+            })
+            .get()
+            .await;
+        self.client_loop.fail_requests_for(delay).await
+    }
+
+    async fn on_failed_connect(
+        &mut self,
+        reason: ConnectFailReason,
+        message: String,
+    ) -> Result<(), StateChange> {
+        self.client_loop.fail_pending_request();
+        self.failed_connect_attempts += 1;
+
+        if let Some(max) = self.max_connect_attempts {
+            if self.failed_connect_attempts >= max.get() {
+                tracing::warn!(
+                    "{} - giving up after {} consecutive failed attempt(s)",
+                    message,
+                    self.failed_connect_attempts
+                );
+                self.listener
+                    .update(ClientState::Failed { reason })
+                    .get()
+                    .await;
+                self.client_loop.fail_requests_until_enabled().await?;
+                // the user explicitly re-enabled the channel, start over
+                tracing::info!("channel re-enabled after connection failure");
+                self.failed_connect_attempts = 0;
+                self.connect_retry.reset();
+                return Ok(());
+            }
+        }
+
+        let delay = self.connect_retry.after_failed_connect();
+        tracing::warn!(
+            "{} - waiting {} ms before next attempt",
+            message,
+            delay.as_millis()
+        );
+        self.listener
+            .update(ClientState::WaitAfterFailedConnect {
+                reason,
+                retry_in: delay,
+            })
+            .get()
+            .await;
+        self.client_loop.fail_requests_for(delay).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::client::RequestParam;
+    use crate::error::RequestError;
+    use crate::server::RequestHandler;
+    use crate::testing::StateListener;
+    use crate::types::{AddressRange, UnitId};
+
+    // find a local port on which nothing is listening so that connections are refused
+    fn closed_port_addr() -> HostAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        HostAddr::ip(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    struct CoilHandler;
+
+    impl crate::server::RequestHandler for CoilHandler {
+        fn read_coil(&self, _address: u16) -> Result<bool, crate::ExceptionCode> {
+            Ok(true)
+        }
+    }
+
+    async fn spawn_server() -> (crate::server::ServerHandle, HostAddr) {
+        // the server keeps the listener so that the ephemeral port can't be taken in between
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let server = crate::server::spawn_tcp_server_task_on(
+            1,
+            listener,
+            crate::server::ServerHandlerMap::single(UnitId::new(1), CoilHandler.wrap()),
+            crate::server::AddressFilter::Any,
+            DecodeLevel::nothing(),
+            crate::server::ServerOptions::default(),
+        )
+        .unwrap();
+        (server, socket_addr.into())
+    }
+
+    fn spawn_channel(
+        max_attempts: usize,
+        retry_delay: Duration,
+    ) -> (Channel, tokio::sync::mpsc::UnboundedReceiver<ClientState>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let channel = spawn_tcp_channel(
+            closed_port_addr(),
+            TcpTaskConnectionHandler::Tcp,
+            10,
+            crate::client::doubling_retry_strategy(retry_delay, retry_delay),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx }),
+            crate::client::NullListener::create(),
+            ClientOptions::default().max_connect_attempts(NonZeroUsize::new(max_attempts)),
+        );
+        (channel, rx)
+    }
+
+    async fn expect_state(
+        rx: &mut tokio::sync::mpsc::UnboundedReceiver<ClientState>,
+        expected: ClientState,
+    ) {
+        let state = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state, expected);
+    }
+
+    async fn expect_failed_attempts(
+        rx: &mut tokio::sync::mpsc::UnboundedReceiver<ClientState>,
+        count: usize,
+        delay: Duration,
+    ) {
+        for _ in 0..count - 1 {
+            expect_state(rx, ClientState::Connecting).await;
+            expect_state(
+                rx,
+                ClientState::WaitAfterFailedConnect {
+                    reason: ConnectFailReason::ConnectionRefused,
+                    retry_in: delay,
+                },
+            )
+            .await;
+        }
+        expect_state(rx, ClientState::Connecting).await;
+        expect_state(
+            rx,
+            ClientState::Failed {
+                reason: ConnectFailReason::ConnectionRefused,
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_connect_attempts_until_enabled() {
+        let delay = Duration::from_millis(1);
+        let (mut channel, mut rx) = spawn_channel(3, delay);
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_failed_attempts(&mut rx, 3, delay).await;
+
+        // requests fail immediately while in the failed state
+        let result = channel
+            .read_coils(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                AddressRange::try_from(0, 1).unwrap(),
+            )
+            .await;
+        assert_eq!(result, Err(RequestError::NoConnection));
+
+        // enabling again resets the attempt counter
+        channel.enable().await.unwrap();
+        expect_failed_attempts(&mut rx, 3, delay).await;
+    }
+
+    #[tokio::test]
+    async fn disabling_mid_way_resets_connect_attempts() {
+        let delay = Duration::from_millis(200);
+        let (channel, mut rx) = spawn_channel(3, delay);
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(
+            &mut rx,
+            ClientState::WaitAfterFailedConnect {
+                reason: ConnectFailReason::ConnectionRefused,
+                retry_in: delay,
+            },
+        )
+        .await;
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(
+            &mut rx,
+            ClientState::WaitAfterFailedConnect {
+                reason: ConnectFailReason::ConnectionRefused,
+                retry_in: delay,
+            },
+        )
+        .await;
+
+        // disable while waiting to make the last attempt
+        channel.disable().await.unwrap();
+        expect_state(&mut rx, ClientState::Disabled).await;
+
+        // a full set of attempts is made after re-enabling
+        channel.enable().await.unwrap();
+        expect_failed_attempts(&mut rx, 3, delay).await;
+
+        // disabling from the failed state is reported as well
+        channel.disable().await.unwrap();
+        expect_state(&mut rx, ClientState::Disabled).await;
+    }
+
+    #[tokio::test]
+    async fn reports_dns_failures() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let channel = spawn_tcp_channel(
+            HostAddr::dns("does-not-exist.invalid".to_string(), 502),
+            TcpTaskConnectionHandler::Tcp,
+            10,
+            crate::client::doubling_retry_strategy(Duration::from_secs(1), Duration::from_secs(1)),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx }),
+            crate::client::NullListener::create(),
+            ClientOptions::default(),
+        );
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(
+            &mut rx,
+            ClientState::WaitAfterFailedConnect {
+                reason: ConnectFailReason::Dns,
+                retry_in: Duration::from_secs(1),
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn disable_completes_after_the_connection_is_closed() {
+        let (_server, addr) = spawn_server().await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let channel = spawn_tcp_channel(
+            addr,
+            TcpTaskConnectionHandler::Tcp,
+            10,
+            crate::client::doubling_retry_strategy(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            ),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx }),
+            crate::client::NullListener::create(),
+            ClientOptions::default(),
+        );
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(&mut rx, ClientState::Connected).await;
+
+        // the transition has already been reported when disable() returns
+        channel.disable().await.unwrap();
+        assert_eq!(rx.try_recv().unwrap(), ClientState::Disabled);
+
+        // disabling an already disabled channel completes immediately
+        channel.disable().await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn reconnects_on_demand_after_idle_disconnect() {
+        let (_server, addr) = spawn_server().await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut channel = spawn_tcp_channel(
+            addr,
+            TcpTaskConnectionHandler::Tcp,
+            10,
+            // a long retry delay proves that reconnection doesn't wait on the strategy
+            crate::client::doubling_retry_strategy(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            ),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx }),
+            crate::client::NullListener::create(),
+            ClientOptions::default().idle_disconnect(Some(Duration::from_millis(50))),
+        );
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(&mut rx, ClientState::Connected).await;
+        expect_state(&mut rx, ClientState::Idle).await;
+
+        let param = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+        let range = AddressRange::try_from(0, 2).unwrap();
+
+        // the request is queued while reconnecting rather than failed
+        let result = channel.read_coils(param, range).await.unwrap();
+        assert_eq!(result.len(), 2);
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(&mut rx, ClientState::Connected).await;
+
+        // and then the connection goes idle again
+        expect_state(&mut rx, ClientState::Idle).await;
+    }
+
+    #[tokio::test]
+    async fn binds_the_local_address_before_connecting() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote = listener.local_addr().unwrap();
+        // reserve a free local port
+        let local = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let options = ClientOptions::default().local_address(Some(local));
+        let _stream = HostAddr::from(remote).connect(&options).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer, local);
+
+        // remote addresses of a different IP version are skipped
+        let options = ClientOptions::default().local_address(Some("[::1]:0".parse().unwrap()));
+        let (reason, _) = HostAddr::from(remote).connect(&options).await.unwrap_err();
+        assert_eq!(
+            reason,
+            ConnectFailReason::Io(std::io::ErrorKind::AddrNotAvailable)
+        );
+    }
+
+    #[tokio::test]
+    async fn invokes_the_socket_callback_before_connecting() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote = HostAddr::from(listener.local_addr().unwrap());
+
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let options = ClientOptions::default().on_socket(Some(Box::new(move |socket| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            socket.set_reuseaddr(true)
+        })));
+        remote.connect(&options).await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+        // an error fails the attempt
+        let options = ClientOptions::default().on_socket(Some(Box::new(|_| {
+            Err(std::io::ErrorKind::PermissionDenied.into())
+        })));
+        let (reason, _) = remote.connect(&options).await.unwrap_err();
+        assert_eq!(
+            reason,
+            ConnectFailReason::Io(std::io::ErrorKind::PermissionDenied)
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_connection_info_before_connected() {
+        let (_server, addr) = spawn_server().await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let (info_tx, mut info_rx) = tokio::sync::mpsc::unbounded_channel();
+        let local = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let channel = spawn_tcp_channel(
+            addr.clone(),
+            TcpTaskConnectionHandler::Tcp,
+            10,
+            crate::client::default_retry_strategy(),
+            DecodeLevel::nothing(),
+            Box::new(StateListener { tx }),
+            Box::new(StateListener { tx: info_tx }),
+            ClientOptions::default().local_address(Some(local)),
+        );
+
+        expect_state(&mut rx, ClientState::Disabled).await;
+        channel.enable().await.unwrap();
+        expect_state(&mut rx, ClientState::Connecting).await;
+        expect_state(&mut rx, ClientState::Connected).await;
+
+        let info = info_rx.try_recv().unwrap();
+        assert_eq!(info.local, local);
+        assert_eq!(info.peer.to_string(), addr.to_string());
+    }
 }
@@ -1,7 +1,9 @@
+use std::ops::Range;
+
 use crate::common::buffer::ReadBuffer;
 use crate::common::frame::{Frame, FrameHeader, FrameInfo, FrameType, FunctionField, TxId};
 use crate::common::traits::Serialize;
-use crate::decode::FrameDecodeLevel;
+use crate::decode::{BytesFormat, DecodeLevel, FrameDecodeLevel};
 use crate::error::{FrameParseError, RequestError};
 use crate::types::UnitId;
 
@@ -17,9 +19,9 @@ pub(crate) mod constants {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) struct MbapHeader {
-    tx_id: TxId,
-    len_field: u16,
-    unit_id: UnitId,
+    pub(crate) tx_id: TxId,
+    pub(crate) len_field: u16,
+    pub(crate) unit_id: UnitId,
 }
 
 #[derive(Clone, Copy)]
@@ -64,6 +66,11 @@ impl MbapParser {
             .checked_sub(1)
             .ok_or(FrameParseError::MbapLengthZero)?;
 
+        // every PDU contains at least a function code
+        if adu_length == 0 {
+            return Err(FrameParseError::MbapLengthTooSmall(length).into());
+        }
+
         Ok((
             MbapHeader {
                 tx_id,
@@ -87,7 +94,7 @@ impl MbapParser {
     pub(crate) fn parse(
         &mut self,
         cursor: &mut ReadBuffer,
-        decode_level: FrameDecodeLevel,
+        decode_level: DecodeLevel,
     ) -> Result<Option<Frame>, RequestError> {
         match self.state {
             ParseState::Header(header, adu_length) => {
@@ -98,10 +105,18 @@ impl MbapParser {
                 let frame = Self::parse_body(&header, adu_length, cursor)?;
                 self.state = ParseState::Begin;
 
-                if decode_level.enabled() {
+                if decode_level.frame.enabled() {
                     tracing::info!(
+                        tx_id = header.tx_id.to_u16(),
+                        unit = header.unit_id.value,
+                        len = header.len_field,
                         "MBAP RX - {}",
-                        MbapDisplay::new(decode_level, header, frame.payload())
+                        MbapDisplay::new(
+                            decode_level,
+                            header,
+                            frame.payload(),
+                            0..frame.payload().len()
+                        )
                     );
                 }
 
@@ -170,16 +185,28 @@ pub(crate) fn format_mbap(
 
 pub(crate) struct MbapDisplay<'a> {
     level: FrameDecodeLevel,
+    format: BytesFormat,
+    redact_values: bool,
     header: MbapHeader,
     bytes: &'a [u8],
+    pdu: Range<usize>,
 }
 
 impl<'a> MbapDisplay<'a> {
-    pub(crate) fn new(level: FrameDecodeLevel, header: MbapHeader, bytes: &'a [u8]) -> Self {
+    /// `pdu` is the range of the function code and the data within `bytes`
+    pub(crate) fn new(
+        decode_level: DecodeLevel,
+        header: MbapHeader,
+        bytes: &'a [u8],
+        pdu: Range<usize>,
+    ) -> Self {
         MbapDisplay {
-            level,
+            level: decode_level.frame,
+            format: decode_level.bytes,
+            redact_values: decode_level.redact_values,
             header,
             bytes,
+            pdu,
         }
     }
 }
@@ -192,7 +219,12 @@ impl<'a> std::fmt::Display for MbapDisplay<'a> {
             self.header.tx_id, self.header.unit_id, self.header.len_field
         )?;
         if self.level.payload_enabled() {
-            crate::common::phys::format_bytes(f, self.bytes)?;
+            let redacted = if self.redact_values {
+                crate::common::redact::frame_values(self.bytes, self.pdu.clone())
+            } else {
+                None
+            };
+            crate::common::phys::format_bytes(f, self.bytes, self.format, redacted.as_slice())?;
         }
         Ok(())
     }
@@ -358,6 +390,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn errors_on_length_without_function_code() {
+        let frame = &[0x00, 0x07, 0x00, 0x00, 0x00, 0x01, 0x2A];
+        assert_eq!(
+            test_error(frame),
+            RequestError::BadFrame(FrameParseError::MbapLengthTooSmall(1))
+        );
+    }
+
     #[test]
     fn errors_when_mbap_length_too_big() {
         let frame = &[0x00, 0x07, 0x00, 0x00, 0x00, 0xFF, 0x2A];
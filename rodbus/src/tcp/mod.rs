@@ -1,5 +1,7 @@
+#[cfg(feature = "runtime")]
 pub(crate) mod client;
 pub(crate) mod frame;
+#[cfg(feature = "runtime")]
 pub(crate) mod server;
 
 #[cfg(feature = "tls")]
@@ -2,38 +2,71 @@ use std::collections::BTreeMap;
 
 use tracing::Instrument;
 
+use crate::client::{Listener, NullListener};
 use crate::common::frame::{FrameWriter, FramedReader};
 use crate::common::phys::PhysLayer;
 use crate::decode::DecodeLevel;
 use crate::server::handler::{RequestHandler, ServerHandlerMap};
-use crate::server::task::{AuthorizationType, ServerSetting};
+use crate::server::options::SessionConfig;
+use crate::server::task::{AuthorizationType, LastActivity, ServerSetting};
 
-use crate::server::AddressFilter;
-use std::net::SocketAddr;
+use crate::server::{
+    AddressFilter, MaxSessionsPolicy, ServerConnectionEvent, ServerOptions, SessionCloseReason,
+};
+use std::net::{IpAddr, SocketAddr};
 use tokio::net::TcpListener;
 
 #[cfg(feature = "tls")]
 use crate::server::AuthorizationHandler;
 
-/// event sent back to the server task when a session ends
-struct SessionClose(u128);
+/// event sent back to the server task by a session
+struct SessionEvent {
+    id: u128,
+    event: ServerConnectionEvent,
+    // the session has ended and can be removed from the tracker
+    closed: bool,
+}
+
+struct SessionRecord {
+    tx: tokio::sync::mpsc::Sender<ServerSetting>,
+    addr: SocketAddr,
+    // index of the endpoint that accepted the connection
+    endpoint: usize,
+    activity: LastActivity,
+}
 
 struct SessionTracker {
     max_sessions: usize,
+    policy: MaxSessionsPolicy,
+    max_sessions_per_ip: Option<usize>,
     id: u128,
-    sessions: BTreeMap<u128, tokio::sync::mpsc::Sender<ServerSetting>>,
+    sessions: BTreeMap<u128, SessionRecord>,
 }
 
 impl SessionTracker {
-    fn new(max_sessions: usize) -> SessionTracker {
+    fn new(
+        max_sessions: usize,
+        policy: MaxSessionsPolicy,
+        max_sessions_per_ip: Option<usize>,
+    ) -> SessionTracker {
         let max_sessions = if max_sessions == 0 {
             tracing::warn!("Max sessions to 0, defaulting to 1");
             1
         } else {
             max_sessions
         };
+        let max_sessions_per_ip = max_sessions_per_ip.map(|max| {
+            if max == 0 {
+                tracing::warn!("Max sessions per IP set to 0, defaulting to 1");
+                1
+            } else {
+                max
+            }
+        });
         Self {
             max_sessions,
+            policy,
+            max_sessions_per_ip,
             id: 0,
             sessions: BTreeMap::new(),
         }
@@ -45,32 +78,114 @@ impl SessionTracker {
         ret
     }
 
-    pub(crate) fn add(&mut self, sender: tokio::sync::mpsc::Sender<ServerSetting>) -> u128 {
+    /// true if a new connection must be rejected because the maximum number of sessions is open
+    fn rejects_new(&self) -> bool {
+        self.policy == MaxSessionsPolicy::RejectNew && self.sessions.len() >= self.max_sessions
+    }
+
+    /// add a session, returning its id and the address of the evicted session (if any)
+    ///
+    /// The limit applies to the sessions of all endpoints. When it is reached, a session of the
+    /// same endpoint is closed according to the policy, or a session of any endpoint if the
+    /// endpoint has none.
+    pub(crate) fn add(
+        &mut self,
+        sender: tokio::sync::mpsc::Sender<ServerSetting>,
+        addr: SocketAddr,
+        endpoint: usize,
+        activity: LastActivity,
+    ) -> (u128, Option<SocketAddr>) {
+        let mut evicted = None;
         if self.sessions.len() >= self.max_sessions {
-            if let Some(oldest) = self.sessions.keys().next().copied() {
+            let same_endpoint = self
+                .sessions
+                .iter()
+                .any(|(_, record)| record.endpoint == endpoint);
+            let candidates = self
+                .sessions
+                .iter()
+                .filter(|(_, record)| !same_endpoint || record.endpoint == endpoint);
+            let selected = match self.policy {
+                // sessions are ordered by id, so the first one is the oldest
+                MaxSessionsPolicy::EvictOldest | MaxSessionsPolicy::RejectNew => {
+                    candidates.map(|(id, _)| *id).next()
+                }
+                MaxSessionsPolicy::EvictLongestIdle => candidates
+                    .min_by_key(|(_, record)| *record.activity.lock().unwrap())
+                    .map(|(id, _)| *id),
+            };
+            if let Some(selected) = selected {
                 tracing::warn!(
-                    "exceeded max connections, closing oldest session: {}",
-                    oldest
+                    "exceeded max connections, closing session: {} ({:?})",
+                    selected,
+                    self.policy
                 );
                 // when the record drops, and there are no more senders,
                 // the other end will stop the task
-                self.sessions.remove(&oldest);
+                evicted = self.sessions.remove(&selected).map(|x| x.addr);
             }
         }
 
         let id = self.get_next_id();
-        self.sessions.insert(id, sender);
-        id
+        self.sessions.insert(
+            id,
+            SessionRecord {
+                tx: sender,
+                addr,
+                endpoint,
+                activity,
+            },
+        );
+        (id, evicted)
     }
 
     pub(crate) fn remove(&mut self, id: u128) {
         self.sessions.remove(&id);
     }
+
+    /// true if a session from `ip` would exceed the maximum number of sessions per IP
+    fn exceeds_ip_limit(&self, ip: IpAddr) -> bool {
+        let max = match self.max_sessions_per_ip {
+            Some(x) => x,
+            None => return false,
+        };
+        let host = normalize(ip);
+        let count = self
+            .sessions
+            .values()
+            .filter(|record| normalize(record.addr.ip()) == host)
+            .count();
+        count >= max
+    }
+
+    /// close the sessions whose peer doesn't match the filter, returning their addresses
+    fn remove_unmatched(&mut self, filter: &AddressFilter) -> Vec<SocketAddr> {
+        let mut removed = Vec::new();
+        // dropping the record closes the other end, which stops the session
+        self.sessions.retain(|_, record| {
+            let matches = filter.matches(record.addr.ip());
+            if !matches {
+                removed.push(record.addr);
+            }
+            matches
+        });
+        removed
+    }
+}
+
+/// IPv4-mapped IPv6 addresses are the same host as the IPv4 address
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
 }
 
 #[derive(Clone)]
 pub(crate) enum TcpServerConnectionHandler {
     Tcp,
+    // plain TCP socket that carries RTU frames instead of MBAP
+    RtuOverTcp,
     #[cfg(feature = "tls")]
     Tls(
         crate::tcp::tls::TlsServerConfig,
@@ -84,7 +199,9 @@ impl TcpServerConnectionHandler {
         socket: tokio::net::TcpStream,
     ) -> Result<(PhysLayer, AuthorizationType), String> {
         match self {
-            Self::Tcp => Ok((PhysLayer::new_tcp(socket), AuthorizationType::None)),
+            Self::Tcp | Self::RtuOverTcp => {
+                Ok((PhysLayer::new_tcp(socket), AuthorizationType::None))
+            }
             #[cfg(feature = "tls")]
             Self::Tls(config, auth_handler) => {
                 let res = config.handle_connection(socket, auth_handler.clone()).await;
@@ -95,17 +212,45 @@ impl TcpServerConnectionHandler {
             }
         }
     }
+
+    fn framing(&self) -> (FrameWriter, FramedReader) {
+        match self {
+            Self::RtuOverTcp => (FrameWriter::rtu(), FramedReader::rtu_request()),
+            _ => (FrameWriter::tcp(), FramedReader::tcp()),
+        }
+    }
+}
+
+/// Listening socket of a server and the settings of the connections that it accepts
+pub(crate) struct ServerEndpoint {
+    pub(crate) listener: TcpListener,
+    pub(crate) connection_handler: TcpServerConnectionHandler,
+    pub(crate) filter: AddressFilter,
+}
+
+impl ServerEndpoint {
+    pub(crate) fn new(
+        listener: TcpListener,
+        connection_handler: TcpServerConnectionHandler,
+        filter: AddressFilter,
+    ) -> Self {
+        Self {
+            listener,
+            connection_handler,
+            filter,
+        }
+    }
 }
 
 pub(crate) struct ServerTask<T: RequestHandler> {
-    listener: TcpListener,
+    endpoints: Vec<ServerEndpoint>,
     handlers: ServerHandlerMap<T>,
     tracker: SessionTracker,
-    connection_handler: TcpServerConnectionHandler,
-    filter: AddressFilter,
     decode: DecodeLevel,
-    tx: tokio::sync::mpsc::Sender<SessionClose>,
-    rx: tokio::sync::mpsc::Receiver<SessionClose>,
+    config: SessionConfig,
+    connection_listener: Box<dyn Listener<ServerConnectionEvent>>,
+    tx: tokio::sync::mpsc::Sender<SessionEvent>,
+    rx: tokio::sync::mpsc::Receiver<SessionEvent>,
 }
 
 impl<T> ServerTask<T>
@@ -119,16 +264,40 @@ where
         connection_handler: TcpServerConnectionHandler,
         filter: AddressFilter,
         decode: DecodeLevel,
+        options: ServerOptions,
+    ) -> Self {
+        Self::with_endpoints(
+            max_sessions,
+            vec![ServerEndpoint::new(listener, connection_handler, filter)],
+            handlers,
+            decode,
+            options,
+        )
+    }
+
+    /// server that accepts connections on several endpoints, sharing the handlers and the session limit
+    pub(crate) fn with_endpoints(
+        max_sessions: usize,
+        endpoints: Vec<ServerEndpoint>,
+        handlers: ServerHandlerMap<T>,
+        decode: DecodeLevel,
+        options: ServerOptions,
     ) -> Self {
         let (tx, rx) = tokio::sync::mpsc::channel(8);
 
         Self {
-            listener,
+            endpoints,
             handlers,
-            tracker: SessionTracker::new(max_sessions),
-            connection_handler,
-            filter,
+            tracker: SessionTracker::new(
+                max_sessions,
+                options.max_sessions_policy,
+                options.max_sessions_per_ip,
+            ),
             decode,
+            config: options.session_config(),
+            connection_listener: options
+                .connection_listener
+                .unwrap_or_else(|| NullListener::create()),
             tx,
             rx,
         }
@@ -141,12 +310,54 @@ where
                 tracing::info!("changed decoding level to {:?}", level);
                 self.decode = level;
             }
+            ServerSetting::ChangeThrottle(throttle) => {
+                tracing::info!("changed request throttle to {:?}", throttle);
+                self.config.request_throttle = throttle;
+            }
+            ServerSetting::SetReadOnly(unit_id, read_only) => {
+                tracing::info!(
+                    "changed read-only mode of unit {} to {}",
+                    unit_id,
+                    read_only
+                );
+                self.handlers.set_read_only(unit_id, read_only);
+            }
+            ServerSetting::SetCapture(ref capture) => {
+                tracing::info!("capture enabled: {}", capture.is_some());
+                self.config.capture = capture.clone();
+            }
+            ServerSetting::SetAddressFilter(filter, disconnect) => {
+                tracing::info!("changed address filter to {:?}", filter);
+                if disconnect {
+                    for addr in self.tracker.remove_unmatched(&filter) {
+                        tracing::warn!(
+                            "IP address {:?} does not match filter {:?}, closing session",
+                            addr.ip(),
+                            filter
+                        );
+                    }
+                }
+                for endpoint in self.endpoints.iter_mut() {
+                    endpoint.filter = filter.clone();
+                }
+                // sessions don't use the filter
+                return;
+            }
+            ServerSetting::SetMaxSessionsPolicy(policy) => {
+                tracing::info!("changed max sessions policy to {:?}", policy);
+                self.tracker.policy = policy;
+                // sessions don't use the policy
+                return;
+            }
+            // only applies to RTU servers
+            #[cfg(feature = "serial")]
+            ServerSetting::ChangeSerialSettings(_) => return,
         }
 
-        for sender in self.tracker.sessions.values_mut() {
+        for session in self.tracker.sessions.values_mut() {
             // best effort to send the setting to each session this isn't critical so we wouldn't
             // want to slow the server down by awaiting it
-            let _ = sender.send(setting).await;
+            let _ = session.tx.send(setting.clone()).await;
         }
     }
 
@@ -164,24 +375,38 @@ where
                }
                shutdown = self.rx.recv() => {
                    // this will never be None b/c we always keep a tx live
-                   let id = shutdown.unwrap().0;
+                   let SessionEvent { id, event, closed } = shutdown.unwrap();
 
-                   self.tracker.remove(id);
+                   if closed {
+                       self.tracker.remove(id);
+                   }
+                   self.connection_listener.update(event).get().await;
                }
-               result = self.listener.accept() => {
+               (endpoint, result) = accept(&self.endpoints) => {
                    match result {
                         Err(err) => {
                             tracing::error!("error accepting connection: {}", err);
                             return;
                         }
                         Ok((socket, addr)) => {
-                            if self.filter.matches(addr.ip()) {
+                            let filter = &self.endpoints[endpoint].filter;
+                            if !filter.matches(addr.ip()) {
+                                tracing::warn!("IP address {:?} does not match filter {:?}, closing connection", addr.ip(), filter);
+                                crate::common::metrics::server_rejected_connection("filter");
+                                self.connection_listener.update(ServerConnectionEvent::RejectedByFilter(addr)).get().await;
+                            } else if self.tracker.rejects_new() {
+                                tracing::warn!("exceeded max connections, closing connection from {}", addr);
+                                crate::common::metrics::server_rejected_connection("max_sessions");
+                                self.connection_listener.update(ServerConnectionEvent::RejectedMaxSessions(addr)).get().await;
+                            } else if self.tracker.exceeds_ip_limit(addr.ip()) {
+                                tracing::warn!("IP address {:?} reached the maximum number of sessions, closing connection", addr.ip());
+                                crate::common::metrics::server_rejected_connection("max_sessions_per_ip");
+                                self.connection_listener.update(ServerConnectionEvent::RejectedMaxSessionsPerIp(addr)).get().await;
+                            } else {
                                 if let Err(err) = socket.set_nodelay(true) {
                                     tracing::warn!("unable to enable TCP_NODELAY: {}", err);
                                 }
-                                self.handle(socket, addr).await
-                            } else {
-                                tracing::warn!("IP address {:?} does not match filter {:?}, closing connection", addr.ip(), self.filter);
+                                self.handle(socket, addr, endpoint).await
                             }
                         }
                    }
@@ -190,69 +415,845 @@ where
         }
     }
 
-    async fn handle(&mut self, socket: tokio::net::TcpStream, addr: SocketAddr) {
+    async fn handle(&mut self, socket: tokio::net::TcpStream, addr: SocketAddr, endpoint: usize) {
         let (tx, rx) = tokio::sync::mpsc::channel(8); // all we do is change settings, so a constant is fine
-        let id = self.tracker.add(tx);
+        let activity = LastActivity::new(std::sync::Mutex::new(tokio::time::Instant::now()));
+        let (id, evicted) = self.tracker.add(tx, addr, endpoint, activity.clone());
         tracing::info!(
             "accepted connection from: {} - assigned session id: {}",
             addr,
             id
         );
 
+        self.connection_listener
+            .update(ServerConnectionEvent::Accepted(addr))
+            .get()
+            .await;
+        if let Some(evicted) = evicted {
+            self.connection_listener
+                .update(ServerConnectionEvent::MaxSessionsEvicted(evicted))
+                .get()
+                .await;
+        }
+
         #[allow(unused_mut)]
         let mut notify_close = self.tx.clone();
-        let connection_handler = self.connection_handler.clone();
+        let connection_handler = self.endpoints[endpoint].connection_handler.clone();
         let handler_map = self.handlers.clone();
         let decode_level = self.decode;
+        let config = self.config.clone();
 
         let session = async move {
-            run_session(
+            let event = run_session(
+                id,
+                &notify_close,
                 socket,
                 addr,
                 connection_handler,
                 decode_level,
+                config,
                 handler_map,
                 rx,
+                activity,
             )
             .await;
 
             // no matter what happens, we send the id back to the server
-            let _ = notify_close.send(SessionClose(id)).await;
+            let _ = notify_close
+                .send(SessionEvent {
+                    id,
+                    event,
+                    closed: true,
+                })
+                .await;
 
             tracing::info!("session shutdown");
         };
 
-        let session =
-            session.instrument(tracing::info_span!("Session", "id" = ?id, "remote" = ?addr));
+        let session = session.instrument(tracing::info_span!(
+            "Modbus-Session",
+            peer = %addr,
+            session_id = %id
+        ));
 
         // spawn the session off onto another task
         tokio::spawn(session);
     }
 }
 
+/// accept a connection on any of the endpoints, returning the index of the endpoint
+async fn accept(
+    endpoints: &[ServerEndpoint],
+) -> (usize, std::io::Result<(tokio::net::TcpStream, SocketAddr)>) {
+    std::future::poll_fn(|cx| {
+        for (index, endpoint) in endpoints.iter().enumerate() {
+            if let std::task::Poll::Ready(result) = endpoint.listener.poll_accept(cx) {
+                return std::task::Poll::Ready((index, result));
+            }
+        }
+        std::task::Poll::Pending
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn run_session<T: RequestHandler>(
+    _id: u128,
+    _notify: &tokio::sync::mpsc::Sender<SessionEvent>,
     socket: tokio::net::TcpStream,
     addr: SocketAddr,
     mut handler: TcpServerConnectionHandler,
     decode: DecodeLevel,
+    config: SessionConfig,
     handlers: ServerHandlerMap<T>,
     commands: tokio::sync::mpsc::Receiver<ServerSetting>,
-) {
+    activity: LastActivity,
+) -> ServerConnectionEvent {
+    let (writer, reader) = handler.framing();
     match handler.handle(socket).await {
         Err(err) => {
             tracing::warn!("error from {}: {}", addr, err);
+            ServerConnectionEvent::HandshakeFailed(addr, err)
         }
         Ok((mut phys, auth)) => {
-            let _ = crate::server::task::SessionTask::new(
-                handlers,
-                auth,
-                FrameWriter::tcp(),
-                FramedReader::tcp(),
-                commands,
-                decode,
+            #[cfg(feature = "tls")]
+            if let Some(info) = phys.tls_info() {
+                let _ = _notify
+                    .send(SessionEvent {
+                        id: _id,
+                        event: ServerConnectionEvent::TlsEstablished(addr, info),
+                        closed: false,
+                    })
+                    .await;
+            }
+            let mut session = crate::server::task::SessionTask::new(
+                handlers, auth, writer, reader, commands, decode,
+            );
+            session.configure(config);
+            session.set_activity(activity);
+            let reason = session.run(&mut phys).await;
+            if let SessionCloseReason::IdleTimeout | SessionCloseReason::MaxLifetime = reason {
+                tracing::info!("closing session with {}: {}", addr, reason);
+            }
+            ServerConnectionEvent::Closed(addr, reason)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::decode::DecodeLevel;
+    use crate::exception::ExceptionCode;
+    use crate::server::*;
+    use crate::types::{Indexed, UnitId};
+
+    // read a single holding register from unit 1
+    const READ_REQUEST: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    struct DefaultHandler;
+    impl RequestHandler for DefaultHandler {}
+
+    async fn spawn_server(options: ServerOptions) -> (ServerHandle, std::net::SocketAddr) {
+        spawn_server_with_filter(AddressFilter::Any, options).await
+    }
+
+    async fn spawn_server_with_max_sessions(
+        max_sessions: usize,
+        options: ServerOptions,
+    ) -> (ServerHandle, std::net::SocketAddr) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = spawn_tcp_server_task_on(
+            max_sessions,
+            listener,
+            ServerHandlerMap::single(UnitId::new(1), DefaultHandler.wrap()),
+            AddressFilter::Any,
+            DecodeLevel::nothing(),
+            options,
+        )
+        .unwrap();
+        (server, addr)
+    }
+
+    async fn spawn_server_with_filter(
+        filter: AddressFilter,
+        options: ServerOptions,
+    ) -> (ServerHandle, std::net::SocketAddr) {
+        // the ephemeral port is known before the server is spawned
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = spawn_tcp_server_task_on(
+            1,
+            listener,
+            ServerHandlerMap::single(UnitId::new(1), DefaultHandler.wrap()),
+            filter,
+            DecodeLevel::nothing(),
+            options,
+        )
+        .unwrap();
+        (server, addr)
+    }
+
+    struct EventListener {
+        tx: tokio::sync::mpsc::UnboundedSender<ServerConnectionEvent>,
+    }
+
+    impl crate::client::Listener<ServerConnectionEvent> for EventListener {
+        fn update(&mut self, value: ServerConnectionEvent) -> crate::MaybeAsync<()> {
+            let _ = self.tx.send(value);
+            crate::MaybeAsync::ready(())
+        }
+    }
+
+    fn event_listener() -> (
+        ServerOptions,
+        tokio::sync::mpsc::UnboundedReceiver<ServerConnectionEvent>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let options = ServerOptions::default().connection_listener(Box::new(EventListener { tx }));
+        (options, rx)
+    }
+
+    async fn next_event(
+        rx: &mut tokio::sync::mpsc::UnboundedReceiver<ServerConnectionEvent>,
+    ) -> ServerConnectionEvent {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    // returns true if the server closed the connection
+    async fn read_until_closed(stream: &mut tokio::net::TcpStream) -> bool {
+        let mut buffer = [0; 256];
+        loop {
+            match stream.read(&mut buffer).await {
+                Ok(0) | Err(_) => return true,
+                Ok(_) => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn closes_session_without_valid_requests_after_idle_timeout() {
+        let (_server, addr) = spawn_server(
+            ServerOptions::default().session_idle_timeout(Some(Duration::from_millis(100))),
+        )
+        .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_until_closed(&mut stream))
+            .await
+            .unwrap();
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn valid_requests_keep_idle_session_open() {
+        let (_server, addr) = spawn_server(
+            ServerOptions::default().session_idle_timeout(Some(Duration::from_millis(200))),
+        )
+        .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0; 256];
+        for _ in 0..5 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stream.write_all(READ_REQUEST).await.unwrap();
+            // the response is an exception, but the request is valid
+            let count = stream.read(&mut buffer).await.unwrap();
+            assert_ne!(count, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn closes_session_when_partial_frame_times_out() {
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().frame_timeout(Some(Duration::from_millis(100))))
+                .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(&READ_REQUEST[..1]).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_until_closed(&mut stream))
+            .await
+            .unwrap();
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn frame_timeout_does_not_apply_between_frames() {
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().frame_timeout(Some(Duration::from_millis(200))))
+                .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0; 256];
+        for _ in 0..2 {
+            // wait longer than the frame timeout between frames
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            // split the frame, but complete it within the frame timeout
+            stream.write_all(&READ_REQUEST[..3]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(&READ_REQUEST[3..]).await.unwrap();
+            let count = stream.read(&mut buffer).await.unwrap();
+            assert_ne!(count, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn address_filter_changes_apply_to_future_connections() {
+        let (mut server, addr) = spawn_server(ServerOptions::default()).await;
+        let mut buffer = [0; 9];
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+
+        let other = AddressFilter::Exact("10.0.0.1".parse().unwrap());
+        server.set_address_filter(other.clone()).await.unwrap();
+
+        // new connections are rejected
+        let mut rejected = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_until_closed(&mut rejected))
+            .await
+            .unwrap();
+        assert!(closed);
+
+        // the existing session remains usable
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+
+        server
+            .set_address_filter_and_disconnect(other)
+            .await
+            .unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_until_closed(&mut stream))
+            .await
+            .unwrap();
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn closes_session_on_malformed_header_by_default() {
+        let (_server, addr) = spawn_server(ServerOptions::default()).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        // length field of 1 leaves no room for a function code
+        stream
+            .write_all(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01])
+            .await
+            .unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_until_closed(&mut stream))
+            .await
+            .unwrap();
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn discards_malformed_header_when_configured() {
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().frame_error_policy(FrameErrorPolicy::Discard))
+                .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        // non-zero protocol id
+        stream
+            .write_all(&[0x00, 0x01, 0xCA, 0xFE, 0x00, 0x06, 0x01])
+            .await
+            .unwrap();
+        // give the server a chance to discard the bad header on its own
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // the session is still usable afterwards
+        let mut buffer = [0; 9];
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalFunction));
+    }
+
+    #[tokio::test]
+    async fn routes_wildcard_unit_id_and_echoes_it() {
+        let (_server, addr) = spawn_server(ServerOptions::default().unit_id_routing(
+            UnitIdRouting::Wildcard {
+                default: UnitId::new(1),
+                include_zero: false,
+            },
+        ))
+        .await;
+
+        let mut request = READ_REQUEST.to_vec();
+        request[6] = 0xFF;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0; 9];
+        stream.write_all(&request).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        // unit id of the request and the exception from the handler for unit 1
+        assert_eq!(buffer[6], 0xFF);
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalFunction));
+    }
+
+    #[tokio::test]
+    async fn strict_coil_padding_rejects_dirty_unused_bits() {
+        // write a single coil at address 0 with the unused bits of the value set
+        const WRITE_REQUEST: &[u8] = &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x08, 0x01, 0x0F, 0x00, 0x00, 0x00, 0x01, 0x01, 0x03,
+        ];
+
+        let mut buffer = [0; 9];
+
+        // the request reaches the handler, which doesn't support writes
+        let (_server, addr) = spawn_server(ServerOptions::default()).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(WRITE_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalFunction));
+
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().strict_coil_padding(true)).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(WRITE_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[7], 0x8F);
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalDataValue));
+    }
+
+    #[tokio::test]
+    async fn delay_throttle_spaces_out_pipelined_requests() {
+        let throttle = RequestThrottle::min_request_gap(Duration::from_millis(100));
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().request_throttle(Some(throttle))).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let start = tokio::time::Instant::now();
+        for _ in 0..4 {
+            stream.write_all(READ_REQUEST).await.unwrap();
+        }
+        // each exception response is 9 bytes
+        let mut buffer = [0; 4 * 9];
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn busy_throttle_can_be_enabled_at_runtime() {
+        let (mut server, addr) = spawn_server(ServerOptions::default()).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0; 9];
+
+        // without a throttle, back-to-back requests are processed normally
+        for _ in 0..2 {
+            stream.write_all(READ_REQUEST).await.unwrap();
+            stream.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(buffer[8], u8::from(crate::ExceptionCode::IllegalFunction));
+        }
+
+        let throttle =
+            RequestThrottle::min_request_gap(Duration::from_secs(10)).mode(ThrottleMode::Busy);
+        server.set_request_throttle(Some(throttle)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // the last request was processed less than 10 seconds ago
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[8], u8::from(crate::ExceptionCode::ServerDeviceBusy));
+    }
+
+    #[tokio::test]
+    async fn read_only_units_reject_writes_until_disabled() {
+        struct RegisterHandler {
+            value: u16,
+        }
+        impl RequestHandler for RegisterHandler {
+            fn read_holding_register(&self, _address: u16) -> Result<u16, ExceptionCode> {
+                Ok(self.value)
+            }
+            fn write_single_register(&mut self, value: Indexed<u16>) -> Result<(), ExceptionCode> {
+                self.value = value.value;
+                Ok(())
+            }
+        }
+
+        // write 0x0005 to holding register 0 on unit 1
+        const WRITE_REQUEST: &[u8] = &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x00, 0x00, 0x05,
+        ];
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = RegisterHandler { value: 0 }.wrap();
+        let mut map = ServerHandlerMap::new();
+        map.add_read_only(UnitId::new(1), handler.clone());
+        let mut server = spawn_tcp_server_task_on(
+            1,
+            listener,
+            map,
+            AddressFilter::Any,
+            DecodeLevel::nothing(),
+            ServerOptions::default(),
+        )
+        .unwrap();
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+        // reads are allowed
+        let mut buffer = [0; 11];
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[7], 0x03);
+
+        let mut buffer = [0; 9];
+        stream.write_all(WRITE_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer[7], 0x86);
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalFunction));
+        assert_eq!(handler.lock().unwrap().value, 0);
+
+        server.set_read_only(UnitId::new(1), false).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut buffer = [0; 12];
+        stream.write_all(WRITE_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, WRITE_REQUEST);
+        assert_eq!(handler.lock().unwrap().value, 5);
+    }
+
+    #[tokio::test]
+    async fn logs_within_session_and_transaction_spans() {
+        let logs = crate::decode::test_log::LogCapture::default();
+        let _guard = logs.install();
+
+        let (mut server, addr) = spawn_server(ServerOptions::default()).await;
+        server
+            .set_decode_level(DecodeLevel::nothing().frame(crate::FrameDecodeLevel::Header))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut buffer = [0; 9];
+        let mut peers = Vec::new();
+        for _ in 0..2 {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(READ_REQUEST).await.unwrap();
+            stream.read_exact(&mut buffer).await.unwrap();
+            peers.push(stream.local_addr().unwrap());
+        }
+
+        let logs = logs.take();
+        for (id, peer) in peers.iter().enumerate() {
+            let span = format!(
+                "Modbus-Session{{peer={peer} session_id={id}}}:Transaction{{unit=1 tx_id=0x01}}"
+            );
+            assert!(logs.contains(&span), "{span} not found in:\n{logs}");
+        }
+    }
+
+    #[tokio::test]
+    async fn injects_faults_configured_at_runtime() {
+        let injector = FaultInjector::with_seed(1);
+        let (_server, addr) =
+            spawn_server(ServerOptions::default().fault_injector(Some(injector.clone()))).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0; 9];
+
+        injector.add_rule(
+            FaultRule::new(Fault::Exception(ExceptionCode::ServerDeviceFailure)).function(0x03),
+        );
+        injector.add_rule(FaultRule::new(Fault::WrongTxId));
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        // the request used tx id 1
+        assert_eq!(&buffer[0..2], &[0x00, 0x02]);
+        assert_eq!(buffer[8], u8::from(ExceptionCode::ServerDeviceFailure));
+
+        injector.clear();
+        injector.add_rule(FaultRule::new(Fault::DropResponse));
+        stream.write_all(READ_REQUEST).await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), stream.read(&mut buffer))
+                .await
+                .is_err()
+        );
+
+        injector.clear();
+        stream.write_all(READ_REQUEST).await.unwrap();
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[0..2], &[0x00, 0x01]);
+        assert_eq!(buffer[8], u8::from(ExceptionCode::IllegalFunction));
+    }
+
+    #[tokio::test]
+    async fn closes_active_session_after_max_lifetime() {
+        let (_server, addr) = spawn_server(
+            ServerOptions::default().max_session_lifetime(Some(Duration::from_millis(200))),
+        )
+        .await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let start = tokio::time::Instant::now();
+        let mut buffer = [0; 256];
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            if stream.write_all(READ_REQUEST).await.is_err() {
+                break;
+            }
+            match stream.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn reports_accepted_evicted_and_closed_connections() {
+        let (options, mut events) = event_listener();
+        let (_server, addr) = spawn_server(options).await;
+
+        let first = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let first_addr = first.local_addr().unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(first_addr)
+        );
+
+        // max sessions is 1, so the first session gets evicted
+        let second = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let second_addr = second.local_addr().unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(second_addr)
+        );
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::MaxSessionsEvicted(first_addr)
+        );
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Closed(
+                first_addr,
+                SessionCloseReason::Error(crate::RequestError::Shutdown)
             )
-            .run(&mut phys)
-            .await;
+        );
+
+        drop(second);
+        match next_event(&mut events).await {
+            ServerConnectionEvent::Closed(addr, SessionCloseReason::Error(_)) => {
+                assert_eq!(addr, second_addr)
+            }
+            x => panic!("unexpected event: {x:?}"),
         }
     }
+
+    #[tokio::test]
+    async fn reports_connections_rejected_by_filter() {
+        let (options, mut events) = event_listener();
+        let (_server, addr) = spawn_server_with_filter(
+            AddressFilter::Exact("192.168.0.1".parse().unwrap()),
+            options,
+        )
+        .await;
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::RejectedByFilter(stream.local_addr().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_connections_over_the_limit_per_ip_instead_of_evicting() {
+        let (options, mut events) = event_listener();
+        let (_server, addr) = spawn_server(options.max_sessions_per_ip(Some(1))).await;
+
+        let mut first = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(first.local_addr().unwrap())
+        );
+
+        let mut second = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::RejectedMaxSessionsPerIp(second.local_addr().unwrap())
+        );
+        assert!(read_until_closed(&mut second).await);
+
+        // the first session is still served, the response is an exception
+        first.write_all(READ_REQUEST).await.unwrap();
+        let mut buffer = [0; 256];
+        assert_ne!(first.read(&mut buffer).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn counts_ipv4_mapped_ipv6_addresses_as_the_same_host() {
+        let mut tracker = super::SessionTracker::new(10, MaxSessionsPolicy::EvictOldest, Some(2));
+        let activity =
+            || super::LastActivity::new(std::sync::Mutex::new(tokio::time::Instant::now()));
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let v4: std::net::SocketAddr = "192.168.0.1:1000".parse().unwrap();
+        let mapped: std::net::SocketAddr = "[::ffff:192.168.0.1]:1001".parse().unwrap();
+        tracker.add(tx.clone(), v4, 0, activity());
+        assert!(!tracker.exceeds_ip_limit(mapped.ip()));
+        let (id, _) = tracker.add(tx, mapped, 0, activity());
+
+        assert!(tracker.exceeds_ip_limit(v4.ip()));
+        assert!(tracker.exceeds_ip_limit(mapped.ip()));
+        assert!(!tracker.exceeds_ip_limit("192.168.0.2".parse().unwrap()));
+        assert!(!tracker.exceeds_ip_limit("::1".parse().unwrap()));
+
+        // closed sessions no longer count
+        tracker.remove(id);
+        assert!(!tracker.exceeds_ip_limit(v4.ip()));
+    }
+
+    // connects and waits until the connection is reported, returning the local address
+    async fn connect(
+        addr: std::net::SocketAddr,
+        events: &mut tokio::sync::mpsc::UnboundedReceiver<ServerConnectionEvent>,
+    ) -> (tokio::net::TcpStream, std::net::SocketAddr) {
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let local = stream.local_addr().unwrap();
+        assert_eq!(
+            next_event(events).await,
+            ServerConnectionEvent::Accepted(local)
+        );
+        (stream, local)
+    }
+
+    #[tokio::test]
+    async fn evict_oldest_policy_closes_the_first_session() {
+        let (options, mut events) = event_listener();
+        let (_server, addr) = spawn_server_with_max_sessions(2, options).await;
+
+        let (mut first, first_addr) = connect(addr, &mut events).await;
+        let (_second, _) = connect(addr, &mut events).await;
+        let (_third, _) = connect(addr, &mut events).await;
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::MaxSessionsEvicted(first_addr)
+        );
+        assert!(read_until_closed(&mut first).await);
+    }
+
+    #[tokio::test]
+    async fn reject_new_policy_closes_the_new_connection() {
+        let (options, mut events) = event_listener();
+        let (mut server, addr) = spawn_server_with_max_sessions(2, options).await;
+        // the policy is changed at runtime
+        server
+            .set_max_sessions_policy(MaxSessionsPolicy::RejectNew)
+            .await
+            .unwrap();
+
+        let (mut first, _) = connect(addr, &mut events).await;
+        let (_second, _) = connect(addr, &mut events).await;
+        let mut third = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::RejectedMaxSessions(third.local_addr().unwrap())
+        );
+        assert!(read_until_closed(&mut third).await);
+
+        // the existing sessions are still served
+        first.write_all(READ_REQUEST).await.unwrap();
+        let mut buffer = [0; 256];
+        assert_ne!(first.read(&mut buffer).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn evict_longest_idle_policy_closes_the_session_without_recent_requests() {
+        let (options, mut events) = event_listener();
+        let (_server, addr) = spawn_server_with_max_sessions(
+            2,
+            options.max_sessions_policy(MaxSessionsPolicy::EvictLongestIdle),
+        )
+        .await;
+
+        let (mut first, _) = connect(addr, &mut events).await;
+        let (mut second, second_addr) = connect(addr, &mut events).await;
+
+        // the first session makes a request after the second one was opened
+        first.write_all(READ_REQUEST).await.unwrap();
+        let mut buffer = [0; 256];
+        assert_ne!(first.read(&mut buffer).await.unwrap(), 0);
+
+        let (_third, _) = connect(addr, &mut events).await;
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::MaxSessionsEvicted(second_addr)
+        );
+        assert!(read_until_closed(&mut second).await);
+    }
+
+    #[tokio::test]
+    async fn evicts_sessions_of_the_same_endpoint_first() {
+        let (options, mut events) = event_listener();
+        let (first, second) = {
+            let first = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let second = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            (first.local_addr().unwrap(), second.local_addr().unwrap())
+        };
+        let _server = ServerBuilder::new(ServerHandlerMap::single(
+            UnitId::new(1),
+            DefaultHandler.wrap(),
+        ))
+        .add_tcp(first, AddressFilter::Any)
+        .add_tcp(second, AddressFilter::Any)
+        .max_sessions(2)
+        .options(options)
+        .spawn()
+        .await
+        .unwrap();
+
+        let other = tokio::net::TcpStream::connect(second).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(other.local_addr().unwrap())
+        );
+        let mut oldest = tokio::net::TcpStream::connect(first).await.unwrap();
+        let oldest_addr = oldest.local_addr().unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(oldest_addr)
+        );
+
+        // both endpoints are served by the same handlers
+        let mut buffer = [0; 9];
+        oldest.write_all(READ_REQUEST).await.unwrap();
+        oldest.read_exact(&mut buffer).await.unwrap();
+
+        // the limit is shared, but the session of the other endpoint is kept even though it's older
+        let newest = tokio::net::TcpStream::connect(first).await.unwrap();
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::Accepted(newest.local_addr().unwrap())
+        );
+        assert_eq!(
+            next_event(&mut events).await,
+            ServerConnectionEvent::MaxSessionsEvicted(oldest_addr)
+        );
+    }
+
+    #[tokio::test]
+    async fn builder_requires_an_endpoint() {
+        let err = ServerBuilder::new(ServerHandlerMap::single(
+            UnitId::new(1),
+            DefaultHandler.wrap(),
+        ))
+        .spawn()
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServerSpawnError::NoEndpoint));
+    }
 }
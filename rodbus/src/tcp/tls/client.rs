@@ -10,7 +10,10 @@ use tokio_rustls::rustls;
 use tokio_rustls::rustls::pki_types::InvalidDnsNameError;
 use tracing::Instrument;
 
-use crate::client::{Channel, ClientState, HostAddr, Listener, RetryStrategy};
+use crate::client::{
+    Channel, ClientOptions, ClientState, ConnectFailReason, ConnectionInfo, HostAddr, Listener,
+    RetryStrategy,
+};
 use crate::common::phys::PhysLayer;
 use crate::tcp::client::{TcpChannelTask, TcpTaskConnectionHandler};
 use crate::tcp::tls::{CertificateMode, MinTlsVersion, TlsError};
@@ -23,6 +26,7 @@ pub struct TlsClientConfig {
     config: Arc<rustls::ClientConfig>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn spawn_tls_channel(
     host: HostAddr,
     max_queued_requests: usize,
@@ -30,6 +34,8 @@ pub(crate) fn spawn_tls_channel(
     tls_config: TlsClientConfig,
     decode: DecodeLevel,
     listener: Box<dyn Listener<ClientState>>,
+    connection_listener: Box<dyn Listener<ConnectionInfo>>,
+    options: ClientOptions,
 ) -> Channel {
     let (handle, task) = create_tls_channel(
         host,
@@ -38,11 +44,14 @@ pub(crate) fn spawn_tls_channel(
         tls_config,
         decode,
         listener,
+        connection_listener,
+        options,
     );
     tokio::spawn(task);
     handle
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_tls_channel(
     host: HostAddr,
     max_queued_requests: usize,
@@ -50,22 +59,26 @@ pub(crate) fn create_tls_channel(
     tls_config: TlsClientConfig,
     decode: DecodeLevel,
     listener: Box<dyn Listener<ClientState>>,
+    connection_listener: Box<dyn Listener<ConnectionInfo>>,
+    options: ClientOptions,
 ) -> (Channel, impl std::future::Future<Output = ()>) {
-    let (tx, rx) = tokio::sync::mpsc::channel(max_queued_requests);
+    let (tx, rx) = crate::channel::channel(max_queued_requests);
     let task = async move {
         TcpChannelTask::new(
             host.clone(),
-            rx.into(),
+            rx,
             TcpTaskConnectionHandler::Tls(tls_config),
             connect_retry,
             decode,
             listener,
+            connection_listener,
+            options,
         )
         .run()
         .instrument(tracing::info_span!("Modbus-Client-TCP", endpoint = ?host))
         .await;
     };
-    (Channel { tx }, task)
+    (Channel::new(tx), task)
 }
 
 impl TlsClientConfig {
@@ -182,17 +195,44 @@ impl TlsClientConfig {
         &mut self,
         socket: TcpStream,
         endpoint: &HostAddr,
-    ) -> Result<PhysLayer, String> {
+    ) -> Result<PhysLayer, (ConnectFailReason, String)> {
         let connector = tokio_rustls::TlsConnector::from(self.config.clone());
         match connector.connect(self.server_name.clone(), socket).await {
-            Err(err) => Err(format!(
-                "failed to establish TLS session with {endpoint}: {err}"
+            Err(err) => Err((
+                handshake_fail_reason(&err),
+                format!("failed to establish TLS session with {endpoint}: {err}"),
             )),
             Ok(stream) => Ok(PhysLayer::new_tls(tokio_rustls::TlsStream::from(stream))),
         }
     }
 }
 
+fn handshake_fail_reason(err: &std::io::Error) -> ConnectFailReason {
+    use rustls::AlertDescription;
+
+    match err
+        .get_ref()
+        .and_then(|x| x.downcast_ref::<rustls::Error>())
+    {
+        Some(
+            rustls::Error::InvalidCertificate(_)
+            | rustls::Error::NoCertificatesPresented
+            | rustls::Error::AlertReceived(
+                AlertDescription::BadCertificate
+                | AlertDescription::UnsupportedCertificate
+                | AlertDescription::CertificateRevoked
+                | AlertDescription::CertificateExpired
+                | AlertDescription::CertificateUnknown
+                | AlertDescription::UnknownCA
+                | AlertDescription::CertificateRequired,
+            ),
+        ) => ConnectFailReason::BadCertificate,
+        Some(_) => ConnectFailReason::TlsHandshake,
+        // e.g. the server closed the connection during the handshake
+        None => ConnectFailReason::from_io(err),
+    }
+}
+
 impl From<InvalidDnsNameError> for TlsError {
     fn from(_: InvalidDnsNameError) -> Self {
         TlsError::InvalidDnsName
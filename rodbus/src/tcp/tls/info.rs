@@ -0,0 +1,176 @@
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use tokio_rustls::rustls::CommonState;
+
+/// Parameters of an established TLS session and the certificate presented by the peer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Negotiated protocol version, e.g. `TLSv1_3`
+    pub protocol_version: String,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`
+    pub cipher_suite: String,
+    /// End-entity certificate presented by the peer, if any
+    pub peer_certificate: Option<PeerCertificate>,
+}
+
+/// Fields extracted from the end-entity certificate presented by a TLS peer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerCertificate {
+    /// Common name (CN) of the subject, if present
+    pub subject_common_name: Option<String>,
+    /// DNS names, IP addresses, e-mail addresses, and URIs of the Subject Alternative Name extension
+    pub subject_alt_names: Vec<String>,
+    /// Serial number as upper-case hexadecimal without separators
+    pub serial_number: String,
+    /// Start of the validity period
+    pub not_before: SystemTime,
+    /// End of the validity period
+    pub not_after: SystemTime,
+    /// DER encoding of the certificate for any additional processing
+    pub der: Vec<u8>,
+}
+
+impl TlsInfo {
+    pub(crate) fn new(state: &CommonState) -> Self {
+        let peer_certificate = state
+            .peer_certificates()
+            .and_then(|x| x.first())
+            .and_then(|cert| match PeerCertificate::parse(cert) {
+                Ok(x) => Some(x),
+                Err(err) => {
+                    tracing::warn!("unable to parse peer certificate: {}", err);
+                    None
+                }
+            });
+
+        Self {
+            protocol_version: state
+                .protocol_version()
+                .map(|x| format!("{x:?}"))
+                .unwrap_or_default(),
+            cipher_suite: state
+                .negotiated_cipher_suite()
+                .map(|x| format!("{:?}", x.suite()))
+                .unwrap_or_default(),
+            peer_certificate,
+        }
+    }
+}
+
+impl PeerCertificate {
+    pub(crate) fn parse(der: &[u8]) -> Result<Self, String> {
+        let cert =
+            rx509::x509::Certificate::parse(der).map_err(|err| format!("ASNError: {err}"))?;
+        let tbs = &cert.tbs_certificate.value;
+
+        let subject = tbs
+            .subject
+            .parse()
+            .map_err(|err| format!("unable to parse subject: {err}"))?;
+
+        let mut subject_alt_names = Vec::new();
+        if let Some(extensions) = tbs.extensions.as_ref() {
+            let extensions = extensions
+                .parse()
+                .map_err(|err| format!("unable to parse cert extensions with rasn: {err:?}"))?;
+            for ext in extensions {
+                if let rx509::x509::ext::SpecificExtension::SubjectAlternativeName(san) =
+                    ext.content
+                {
+                    subject_alt_names.extend(san.names.iter().filter_map(general_name));
+                }
+            }
+        }
+
+        Ok(Self {
+            subject_common_name: subject.common_name.map(|x| x.to_string()),
+            subject_alt_names,
+            serial_number: format_serial_number(tbs.serial_number.bytes),
+            not_before: to_system_time(tbs.validity.not_before.value),
+            not_after: to_system_time(tbs.validity.not_after.value),
+            der: der.to_vec(),
+        })
+    }
+}
+
+fn general_name(name: &rx509::x509::ext::GeneralName) -> Option<String> {
+    use rx509::x509::ext::GeneralName;
+
+    match name {
+        GeneralName::DnsName(x) | GeneralName::Rfc822Name(x) => Some(x.to_string()),
+        GeneralName::UniformResourceIdentifier(x) => Some(x.to_string()),
+        GeneralName::IpAddress(x) => match x.len() {
+            4 => Some(IpAddr::from(<[u8; 4]>::try_from(*x).ok()?).to_string()),
+            16 => Some(IpAddr::from(<[u8; 16]>::try_from(*x).ok()?).to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn format_serial_number(bytes: &[u8]) -> String {
+    // DER integers may carry a leading zero to keep the value positive
+    let start = bytes
+        .iter()
+        .position(|x| *x != 0)
+        .unwrap_or(bytes.len().saturating_sub(1));
+    bytes[start..].iter().map(|x| format!("{x:02X}")).collect()
+}
+
+fn to_system_time(seconds_since_epoch: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(seconds_since_epoch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_serial_numbers() {
+        assert_eq!(format_serial_number(&[0x00, 0x8F, 0x01]), "8F01");
+        assert_eq!(format_serial_number(&[0x12, 0x00]), "1200");
+        assert_eq!(format_serial_number(&[0x00]), "00");
+        assert_eq!(format_serial_number(&[]), "");
+    }
+
+    #[test]
+    fn parses_test_certificate() {
+        let pem = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../certs/ca_chain/server_cert.pem"
+        ))
+        .unwrap();
+        let der = pem_to_der(&pem);
+        let cert = PeerCertificate::parse(&der).unwrap();
+        assert_eq!(cert.subject_common_name.as_deref(), Some("DO NOT USE"));
+        assert_eq!(cert.subject_alt_names, vec!["test.com".to_string()]);
+        assert_eq!(cert.serial_number, "02");
+        // Jul 21 19:52:35 2022 GMT to Jul 18 19:52:35 2032 GMT
+        assert_eq!(cert.not_before, to_system_time(1658433155));
+        assert_eq!(cert.not_after, to_system_time(1973793155));
+        assert_eq!(cert.der, der);
+    }
+
+    fn pem_to_der(pem: &str) -> Vec<u8> {
+        let body: String = pem.lines().filter(|x| !x.starts_with("-----")).collect();
+        base64_decode(&body)
+    }
+
+    fn base64_decode(input: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = Vec::new();
+        let mut acc = 0u32;
+        let mut bits = 0;
+        for c in input.bytes().filter(|x| *x != b'=') {
+            let value = ALPHABET.iter().position(|x| *x == c).unwrap() as u32;
+            acc = (acc << 6) | value;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((acc >> bits) as u8);
+            }
+        }
+        out
+    }
+}
@@ -1,6 +1,9 @@
 pub(crate) mod client;
+mod info;
 pub(crate) mod server;
 
+pub use info::*;
+
 pub(crate) use client::*;
 pub(crate) use server::*;
 
@@ -71,9 +71,7 @@ impl TlsServerConfig {
                             .and_then(|x| x.first())
                             .ok_or_else(|| "No peer certificate".to_string())?;
 
-                        let parsed = rx509::x509::Certificate::parse(peer_cert)
-                            .map_err(|err| format!("ASNError: {err}"))?;
-                        let role = extract_modbus_role(&parsed)?;
+                        let role = extract_modbus_role(peer_cert).map_err(|err| err.to_string())?;
 
                         tracing::info!("client role: {}", role);
                         AuthorizationType::Handler(handler, role)
@@ -88,18 +86,52 @@ impl TlsServerConfig {
     }
 }
 
-fn extract_modbus_role(cert: &rx509::x509::Certificate) -> Result<String, String> {
+/// Error returned by [`extract_modbus_role`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleError {
+    /// The certificate or its extensions couldn't be parsed
+    BadCertificate(String),
+    /// The certificate doesn't contain the Modbus Role extension
+    MissingRole,
+    /// The certificate contains more than one Modbus Role extension
+    MultipleRoles,
+}
+
+impl std::fmt::Display for RoleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadCertificate(err) => write!(f, "unable to parse certificate: {err}"),
+            Self::MissingRole => f.write_str("certificate doesn't contain Modbus role extension"),
+            Self::MultipleRoles => f.write_str("certificate has more than one Modbus extension"),
+        }
+    }
+}
+
+impl std::error::Error for RoleError {}
+
+/// Extract the value of the Modbus Role extension (OID 1.3.6.1.4.1.50316.802.1) from a
+/// DER encoded certificate
+///
+/// This is the role passed to the [`AuthorizationHandler`] of a server spawned with
+/// [`spawn_tls_server_task_with_authz`](crate::server::spawn_tls_server_task_with_authz).
+/// The certificate must contain exactly one role extension.
+pub fn extract_modbus_role(cert: &[u8]) -> Result<String, RoleError> {
+    let cert = rx509::x509::Certificate::parse(cert)
+        .map_err(|err| RoleError::BadCertificate(format!("ASNError: {err}")))?;
+
     // Parse the extensions
     let extensions = cert
         .tbs_certificate
         .value
         .extensions
         .as_ref()
-        .ok_or_else(|| "certificate doesn't contain Modbus role extension".to_string())?;
+        .ok_or(RoleError::MissingRole)?;
 
-    let extensions = extensions
-        .parse()
-        .map_err(|err| format!("unable to parse cert extensions with rasn: {err:?}"))?;
+    let extensions = extensions.parse().map_err(|err| {
+        RoleError::BadCertificate(format!(
+            "unable to parse cert extensions with rasn: {err:?}"
+        ))
+    })?;
 
     // Extract the ModbusRole extensions
     let mut it = extensions.into_iter().filter_map(|ext| match ext.content {
@@ -108,14 +140,25 @@ fn extract_modbus_role(cert: &rx509::x509::Certificate) -> Result<String, String
     });
 
     // Extract the first ModbusRole extension
-    let role = it
-        .next()
-        .ok_or_else(|| "certificate doesn't have Modbus extension".to_string())?;
+    let role = it.next().ok_or(RoleError::MissingRole)?;
 
     // Check that there is only one role extension
     if it.next().is_some() {
-        return Err("certificate has more than one Modbus extension".to_string());
+        return Err(RoleError::MultipleRoles);
     }
 
     Ok(role.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_data_that_is_not_a_certificate() {
+        assert!(matches!(
+            extract_modbus_role(&[0x30, 0x03, 0x02, 0x01, 0x00]),
+            Err(RoleError::BadCertificate(_))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, CustomExtension, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+
+use crate::client::TlsClientConfig;
+use crate::server::TlsServerConfig;
+use crate::tcp::tls::{CertificateMode, MinTlsVersion, TlsError};
+
+// id-ModbusRole from the Modbus/TCP Security specification
+const MODBUS_ROLE_OID: &[u64] = &[1, 3, 6, 1, 4, 1, 50316, 802, 1];
+
+/// Files written by [`generate_certificates`]
+///
+/// Every certificate is signed by the same CA and the keys are not encrypted. The files are
+/// for tests and examples only, they must never be used to secure a real device.
+#[derive(Clone, Debug)]
+pub struct TestCertificates {
+    dir: PathBuf,
+    server_name: String,
+}
+
+impl TestCertificates {
+    /// Certificate of the CA that signed the server and client certificates
+    pub fn ca_cert(&self) -> PathBuf {
+        self.dir.join("ca_cert.pem")
+    }
+
+    /// Certificate of the server, with the server name in its SAN extension
+    pub fn server_cert(&self) -> PathBuf {
+        self.dir.join("server_cert.pem")
+    }
+
+    /// Private key of the server
+    pub fn server_key(&self) -> PathBuf {
+        self.dir.join("server_key.pem")
+    }
+
+    /// Certificate of the client, with the Modbus Role extension
+    pub fn client_cert(&self) -> PathBuf {
+        self.dir.join("client_cert.pem")
+    }
+
+    /// Private key of the client
+    pub fn client_key(&self) -> PathBuf {
+        self.dir.join("client_key.pem")
+    }
+
+    /// Server configuration that requires a client certificate signed by the CA
+    pub fn server_config(&self) -> Result<TlsServerConfig, TlsError> {
+        TlsServerConfig::new(
+            &self.ca_cert(),
+            &self.server_cert(),
+            &self.server_key(),
+            None,
+            MinTlsVersion::V1_2,
+            CertificateMode::AuthorityBased,
+        )
+    }
+
+    /// Client configuration that verifies the server certificate and its name
+    pub fn client_config(&self) -> Result<TlsClientConfig, TlsError> {
+        TlsClientConfig::full_pki(
+            Some(self.server_name.clone()),
+            &self.ca_cert(),
+            &self.client_cert(),
+            &self.client_key(),
+            None,
+            MinTlsVersion::V1_2,
+        )
+    }
+}
+
+/// Generate a CA, a server certificate, and a client certificate, and write them to `dir`
+///
+/// The server certificate contains `server_name` as a DNS name in its SAN extension and the
+/// client certificate contains `client_role` in the Modbus Role extension, as required by
+/// [`spawn_tls_server_task_with_authz`](crate::server::spawn_tls_server_task_with_authz).
+/// The directory is created if it does not exist and existing files are replaced.
+pub fn generate_certificates(
+    dir: &Path,
+    server_name: &str,
+    client_role: &str,
+) -> Result<TestCertificates, std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+
+    let ca_key = KeyPair::generate().map_err(std::io::Error::other)?;
+    let mut ca = CertificateParams::new(Vec::<String>::new()).map_err(std::io::Error::other)?;
+    ca.distinguished_name
+        .push(DnType::CommonName, "rodbus test CA");
+    ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    let ca_cert = ca.self_signed(&ca_key).map_err(std::io::Error::other)?;
+
+    let mut server =
+        CertificateParams::new(vec![server_name.to_string()]).map_err(std::io::Error::other)?;
+    server
+        .distinguished_name
+        .push(DnType::CommonName, server_name);
+    server.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+
+    let mut client = CertificateParams::new(Vec::<String>::new()).map_err(std::io::Error::other)?;
+    client
+        .distinguished_name
+        .push(DnType::CommonName, "rodbus test client");
+    client.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    client
+        .custom_extensions
+        .push(CustomExtension::from_oid_content(
+            MODBUS_ROLE_OID,
+            utf8_string(client_role),
+        ));
+
+    let certs = TestCertificates {
+        dir: dir.to_owned(),
+        server_name: server_name.to_string(),
+    };
+
+    std::fs::write(certs.ca_cert(), ca_cert.pem())?;
+    write_signed(
+        server,
+        &ca_cert,
+        &ca_key,
+        &certs.server_cert(),
+        &certs.server_key(),
+    )?;
+    write_signed(
+        client,
+        &ca_cert,
+        &ca_key,
+        &certs.client_cert(),
+        &certs.client_key(),
+    )?;
+
+    Ok(certs)
+}
+
+fn write_signed(
+    params: CertificateParams,
+    ca_cert: &Certificate,
+    ca_key: &KeyPair,
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<(), std::io::Error> {
+    let key = KeyPair::generate().map_err(std::io::Error::other)?;
+    let cert = params
+        .signed_by(&key, ca_cert, ca_key)
+        .map_err(std::io::Error::other)?;
+    std::fs::write(cert_path, cert.pem())?;
+    std::fs::write(key_path, key.serialize_pem())
+}
+
+/// DER encoding of an ASN.1 UTF8String, the content of the Modbus Role extension
+fn utf8_string(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut der = vec![0x0C];
+    match bytes.len() {
+        len if len < 0x80 => der.push(len as u8),
+        len if len <= 0xFF => der.extend([0x81, len as u8]),
+        len => {
+            let len = u16::try_from(len).expect("role is too long");
+            der.push(0x82);
+            der.extend(len.to_be_bytes());
+        }
+    }
+    der.extend_from_slice(bytes);
+    der
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_role_as_utf8_string() {
+        assert_eq!(utf8_string("op"), [0x0C, 0x02, b'o', b'p']);
+        assert_eq!(utf8_string(&"a".repeat(0x80))[..3], [0x0C, 0x81, 0x80]);
+        assert_eq!(
+            utf8_string(&"a".repeat(0x100))[..4],
+            [0x0C, 0x82, 0x01, 0x00]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use crate::client::Listener;
+use crate::maybe_async::MaybeAsync;
+
+/// Forwards every reported value, e.g. the states of a channel, to an unbounded channel
+pub(crate) struct StateListener<T> {
+    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<T>,
+}
+
+impl<T: Send> Listener<T> for StateListener<T> {
+    fn update(&mut self, value: T) -> MaybeAsync<()> {
+        let _ = self.tx.send(value);
+        MaybeAsync::ready(())
+    }
+}
@@ -0,0 +1,11 @@
+mod listener;
+mod pair;
+mod script;
+
+/// Certificates generated at runtime for tests and examples that use TLS
+#[cfg(feature = "test-certs")]
+pub mod certs;
+
+pub(crate) use listener::StateListener;
+pub use pair::*;
+pub use script::*;
@@ -0,0 +1,172 @@
+use std::net::SocketAddr;
+
+use tracing::Instrument;
+
+use crate::client::{Channel, ClientState, FrameFormat, HostAddr};
+use crate::decode::DecodeLevel;
+use crate::server::{
+    AddressFilter, RequestHandler, ServerHandle, ServerHandlerMap, ServerOptions,
+    SERVER_SETTING_CHANNEL_CAPACITY,
+};
+use crate::tcp::server::{ServerTask, TcpServerConnectionHandler};
+use crate::testing::StateListener;
+
+const MAX_SESSIONS: usize = 16;
+const MAX_QUEUED_REQUESTS: usize = 16;
+// room for the largest RTU frame in each direction
+const LINE_BUFFER_SIZE: usize = 1024;
+
+/// Spawn a TCP server on a free port of the loopback interface and a client channel connected to it
+///
+/// The server and the channel use the default [`DecodeLevel`] and the channel uses the
+/// [`default_retry_strategy`](crate::default_retry_strategy). The channel is enabled and connected
+/// when the function returns, so requests can be sent right away.
+///
+/// Returns an error if the port can't be bound or the channel fails to connect.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub async fn spawn_pair<T: RequestHandler>(
+    handlers: ServerHandlerMap<T>,
+) -> Result<(Channel, ServerHandle, SocketAddr), std::io::Error> {
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+    let addr = listener.local_addr()?;
+
+    let (tx, rx) = tokio::sync::mpsc::channel(SERVER_SETTING_CHANNEL_CAPACITY);
+    let mut server = ServerTask::new(
+        MAX_SESSIONS,
+        listener,
+        handlers,
+        TcpServerConnectionHandler::Tcp,
+        AddressFilter::Any,
+        DecodeLevel::default(),
+        ServerOptions::default(),
+    );
+    tokio::spawn(
+        async move { server.run(rx).await }
+            .instrument(tracing::info_span!("Modbus-Server-TCP", "listen" = ?addr)),
+    );
+
+    let (states, mut state) = tokio::sync::mpsc::unbounded_channel();
+    let channel = crate::client::spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        MAX_QUEUED_REQUESTS,
+        crate::default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(StateListener { tx: states })),
+    );
+    channel.enable().await.map_err(shutdown)?;
+
+    loop {
+        match state.recv().await {
+            Some(ClientState::Connected) => break,
+            Some(ClientState::WaitAfterFailedConnect { reason, .. }) => {
+                return Err(std::io::Error::other(format!(
+                    "unable to connect to {addr}: {reason}"
+                )))
+            }
+            Some(_) => {}
+            None => return Err(shutdown(crate::Shutdown)),
+        }
+    }
+
+    Ok((channel, ServerHandle::new(tx), addr))
+}
+
+/// Spawn an RTU server and a client channel connected to it by an in-memory serial line
+///
+/// Unlike a virtual serial port pair, the in-memory line works on every platform and doesn't
+/// require any setup. Bytes are delivered without the inter-character delays of a real serial
+/// port. The line is never re-established, so the channel fails every request once the server
+/// task stops.
+///
+/// The server and the channel use the default [`DecodeLevel`] and the channel uses the
+/// [`default_retry_strategy`](crate::default_retry_strategy). The channel is enabled when the
+/// function returns.
+///
+/// `WARNING`: This function must be called from with the context of the Tokio runtime or it will panic.
+pub async fn spawn_rtu_pair<T: RequestHandler>(
+    handlers: ServerHandlerMap<T>,
+) -> (Channel, ServerHandle) {
+    let (client_io, server_io) = crate::memory::in_memory_pair(LINE_BUFFER_SIZE);
+    let server = crate::server::spawn_in_memory_server_task(
+        server_io,
+        FrameFormat::Rtu,
+        handlers,
+        DecodeLevel::default(),
+    );
+
+    let mut client_io = Some(client_io);
+    let channel = crate::client::spawn_in_memory_client_task(
+        Box::new(move || client_io.take()),
+        FrameFormat::Rtu,
+        MAX_QUEUED_REQUESTS,
+        crate::default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    // the task only stops when the channel is dropped
+    let _ = channel.enable().await;
+
+    (channel, server)
+}
+
+fn shutdown(_: crate::Shutdown) -> std::io::Error {
+    std::io::Error::other("channel was shut down")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::client::RequestParam;
+    use crate::exception::ExceptionCode;
+    use crate::server::RequestHandler;
+    use crate::types::{AddressRange, Indexed, UnitId};
+
+    struct Registers;
+
+    impl RequestHandler for Registers {
+        fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+            Ok(address + 10)
+        }
+    }
+
+    async fn read_registers(channel: &mut Channel) -> Vec<Indexed<u16>> {
+        channel
+            .read_holding_registers(
+                RequestParam::new(UnitId::new(1), Duration::from_secs(1)),
+                AddressRange::try_from(1, 2).unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tcp_pair_is_connected_when_returned() {
+        let (mut channel, _server, addr) =
+            spawn_pair(ServerHandlerMap::single(UnitId::new(1), Registers.wrap()))
+                .await
+                .unwrap();
+
+        assert!(addr.ip().is_loopback());
+        assert_ne!(addr.port(), 0);
+        assert_eq!(
+            read_registers(&mut channel).await,
+            vec![Indexed::new(1, 11), Indexed::new(2, 12)]
+        );
+    }
+
+    #[tokio::test]
+    async fn rtu_pair_communicates_in_memory() {
+        let (mut channel, _server) =
+            spawn_rtu_pair(ServerHandlerMap::single(UnitId::new(1), Registers.wrap())).await;
+
+        for _ in 0..2 {
+            assert_eq!(
+                read_registers(&mut channel).await,
+                vec![Indexed::new(1, 11), Indexed::new(2, 12)]
+            );
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use crate::exception::ExceptionCode;
+use crate::maybe_async::MaybeAsync;
+use crate::server::{AsyncRequest, AsyncResponse, RequestHandler};
+use crate::types::{AddressRange, UnitId};
+use crate::FunctionCode;
+
+/// Identifies the requests answered by an entry of a [`ScriptedHandler`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RequestMatcher {
+    /// Unit id the request is addressed to
+    pub unit_id: UnitId,
+    /// Function code of the request
+    pub function: FunctionCode,
+    /// Range of the request, a range of one for single writes
+    pub range: AddressRange,
+}
+
+impl RequestMatcher {
+    /// Create a matcher from its fields
+    pub fn new(unit_id: UnitId, function: FunctionCode, range: AddressRange) -> Self {
+        Self {
+            unit_id,
+            function,
+            range,
+        }
+    }
+
+    fn from_request(unit_id: UnitId, request: &AsyncRequest) -> Self {
+        let single = |index| AddressRange {
+            start: index,
+            count: 1,
+        };
+        let (function, range) = match request {
+            AsyncRequest::ReadCoils(x) => (FunctionCode::ReadCoils, *x),
+            AsyncRequest::ReadDiscreteInputs(x) => (FunctionCode::ReadDiscreteInputs, *x),
+            AsyncRequest::ReadHoldingRegisters(x) => (FunctionCode::ReadHoldingRegisters, *x),
+            AsyncRequest::ReadInputRegisters(x) => (FunctionCode::ReadInputRegisters, *x),
+            AsyncRequest::WriteSingleCoil(x) => (FunctionCode::WriteSingleCoil, single(x.index)),
+            AsyncRequest::WriteSingleRegister(x) => {
+                (FunctionCode::WriteSingleRegister, single(x.index))
+            }
+            AsyncRequest::WriteMultipleCoils(x) => (FunctionCode::WriteMultipleCoils, x.range),
+            AsyncRequest::WriteMultipleRegisters(x) => {
+                (FunctionCode::WriteMultipleRegisters, x.range)
+            }
+        };
+        Self::new(unit_id, function, range)
+    }
+}
+
+struct Entry {
+    matcher: RequestMatcher,
+    response: Result<AsyncResponse, ExceptionCode>,
+    used: bool,
+}
+
+/// Server handler that answers requests from a script, e.g. to replay captured traffic in tests
+///
+/// Each request is answered by the first unused entry with a matching unit id, function code,
+/// and range. Once all of the matching entries have been used, the last one is repeated. Values
+/// of write requests are not compared.
+///
+/// Requests without a matching entry are answered with [`ExceptionCode::IllegalFunction`] and
+/// recorded. Panicking within the server would only terminate the session, so tests should call
+/// [`ScriptedHandler::assert_all_matched`] once the client is done.
+pub struct ScriptedHandler {
+    entries: Vec<Entry>,
+    unmatched: Vec<RequestMatcher>,
+}
+
+impl ScriptedHandler {
+    /// Create a handler from a list of requests and their responses
+    pub fn new<I>(script: I) -> Self
+    where
+        I: IntoIterator<Item = (RequestMatcher, Result<AsyncResponse, ExceptionCode>)>,
+    {
+        Self {
+            entries: script
+                .into_iter()
+                .map(|(matcher, response)| Entry {
+                    matcher,
+                    response,
+                    used: false,
+                })
+                .collect(),
+            unmatched: Vec::new(),
+        }
+    }
+
+    /// Load the exchanges of a pcap file written by [`PcapWriter`](crate::capture::PcapWriter)
+    ///
+    /// Only Modbus/TCP framing is supported. Requests are paired with responses by transaction id.
+    pub fn from_pcap(path: impl AsRef<std::path::Path>) -> Result<Self, LoadError> {
+        Self::from_pcap_bytes(&std::fs::read(path)?)
+    }
+
+    /// Load the exchanges of a pcap file from memory, see [`ScriptedHandler::from_pcap`]
+    pub fn from_pcap_bytes(data: &[u8]) -> Result<Self, LoadError> {
+        Ok(Self::new(pcap::load(data)?))
+    }
+
+    /// Requests that did not match any entry of the script
+    pub fn unmatched(&self) -> &[RequestMatcher] {
+        &self.unmatched
+    }
+
+    /// Panic if any request did not match an entry of the script
+    pub fn assert_all_matched(&self) {
+        assert!(
+            self.unmatched.is_empty(),
+            "unmatched requests: {:?}",
+            self.unmatched
+        );
+    }
+
+    fn respond(&mut self, matcher: RequestMatcher) -> Result<AsyncResponse, ExceptionCode> {
+        let entry = match self
+            .entries
+            .iter()
+            .position(|x| x.matcher == matcher && !x.used)
+        {
+            Some(pos) => self.entries.get_mut(pos),
+            None => self.entries.iter_mut().rev().find(|x| x.matcher == matcher),
+        };
+        match entry {
+            Some(entry) => {
+                entry.used = true;
+                entry.response.clone()
+            }
+            None => {
+                tracing::warn!("unmatched request: {:?}", matcher);
+                self.unmatched.push(matcher);
+                Err(ExceptionCode::IllegalFunction)
+            }
+        }
+    }
+}
+
+impl RequestHandler for ScriptedHandler {
+    fn process_async(
+        &mut self,
+        unit_id: UnitId,
+        request: AsyncRequest,
+    ) -> Option<MaybeAsync<Result<AsyncResponse, ExceptionCode>>> {
+        let matcher = RequestMatcher::from_request(unit_id, &request);
+        Some(MaybeAsync::ready(self.respond(matcher)))
+    }
+}
+
+/// Errors that can occur while loading a script
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not a pcap file written by [`PcapWriter`](crate::capture::PcapWriter)
+    BadFormat(&'static str),
+}
+
+impl std::error::Error for LoadError {}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            LoadError::Io(err) => write!(f, "I/O error: {err}"),
+            LoadError::BadFormat(msg) => write!(f, "bad format: {msg}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+mod pcap {
+    use std::collections::BTreeMap;
+
+    use scursor::ReadCursor;
+
+    use super::*;
+    use crate::client::requests::read_bits::ReadBits;
+    use crate::client::requests::read_registers::ReadRegisters;
+    use crate::server::request::Request;
+
+    const MAGIC: u32 = 0xA1B2_C3D4;
+    const LINKTYPE_IPV4: u32 = 228;
+    const SERVER_PORT: u16 = 502;
+    const MBAP_HEADER_LENGTH: usize = 7;
+
+    // a request and the response that was captured for it
+    type Exchange = (RequestMatcher, Result<AsyncResponse, ExceptionCode>);
+    // transaction id, unit id, and PDU
+    type Adu<'a> = (u16, u8, &'a [u8]);
+
+    /// Requests sent to port 502 and the responses sent from it
+    pub(super) fn load(mut data: &[u8]) -> Result<Vec<Exchange>, LoadError> {
+        let header = take(&mut data, 24)?;
+        if u32_le(&header[0..4]) != MAGIC {
+            return Err(LoadError::BadFormat("not a little-endian pcap file"));
+        }
+        if u32_le(&header[20..24]) != LINKTYPE_IPV4 {
+            return Err(LoadError::BadFormat("link type is not IPv4"));
+        }
+
+        // captured reads may contain partial ADUs, so the payloads are reassembled per direction
+        let mut requests = Vec::new();
+        let mut responses = Vec::new();
+        while !data.is_empty() {
+            let record = take(&mut data, 16)?;
+            let length = u32_le(&record[8..12]) as usize;
+            let (src_port, dst_port, payload) = tcp_payload(take(&mut data, length)?)?;
+            if dst_port == SERVER_PORT {
+                requests.extend_from_slice(payload);
+            } else if src_port == SERVER_PORT {
+                responses.extend_from_slice(payload);
+            }
+        }
+
+        // requests by transaction id, waiting for their response
+        let mut pending = BTreeMap::new();
+        let mut script = Vec::new();
+        let mut requests = requests.as_slice();
+        let mut responses = responses.as_slice();
+        while let Some((tx_id, unit_id, pdu)) = next_adu(&mut requests)? {
+            let (function, body) = split_function(pdu)?;
+            // the server answers these requests without invoking the handler
+            let Some(function) = FunctionCode::get(function) else {
+                continue;
+            };
+            let mut cursor = ReadCursor::new(body);
+            let Ok(request) = Request::parse(function, &mut cursor) else {
+                continue;
+            };
+            pending.insert(
+                tx_id,
+                RequestMatcher::from_request(UnitId::new(unit_id), &request.as_async()),
+            );
+        }
+        while let Some((tx_id, _, pdu)) = next_adu(&mut responses)? {
+            if let Some(matcher) = pending.remove(&tx_id) {
+                script.push((matcher, parse_response(&matcher, pdu)?));
+            }
+        }
+
+        Ok(script)
+    }
+
+    fn parse_response(
+        matcher: &RequestMatcher,
+        pdu: &[u8],
+    ) -> Result<Result<AsyncResponse, ExceptionCode>, LoadError> {
+        let (function, body) = split_function(pdu)?;
+        if function & 0x80 != 0 {
+            let ex = body
+                .first()
+                .ok_or(LoadError::BadFormat("missing exception code"))?;
+            return Ok(Err(ExceptionCode::from(*ex)));
+        }
+
+        let mut cursor = ReadCursor::new(body);
+        let bad_response = |_| LoadError::BadFormat("invalid response");
+        let response = match matcher.function {
+            FunctionCode::ReadCoils | FunctionCode::ReadDiscreteInputs => AsyncResponse::Bits(
+                ReadBits::parse_bits_response(matcher.range, &mut cursor)
+                    .map_err(bad_response)?
+                    .map(|x| x.value)
+                    .collect(),
+            ),
+            FunctionCode::ReadHoldingRegisters | FunctionCode::ReadInputRegisters => {
+                AsyncResponse::Registers(
+                    ReadRegisters::parse_registers_response(matcher.range, &mut cursor)
+                        .map_err(bad_response)?
+                        .map(|x| x.value)
+                        .collect(),
+                )
+            }
+            _ => AsyncResponse::Written,
+        };
+        Ok(Ok(response))
+    }
+
+    fn split_function(pdu: &[u8]) -> Result<(u8, &[u8]), LoadError> {
+        pdu.split_first()
+            .map(|(function, body)| (*function, body))
+            .ok_or(LoadError::BadFormat("empty PDU"))
+    }
+
+    /// transaction id, unit id, and PDU of the next complete MBAP frame
+    fn next_adu<'a>(data: &mut &'a [u8]) -> Result<Option<Adu<'a>>, LoadError> {
+        if data.len() < MBAP_HEADER_LENGTH {
+            return Ok(None);
+        }
+        let tx_id = u16::from_be_bytes([data[0], data[1]]);
+        // the length includes the unit id
+        let length = u16::from_be_bytes([data[4], data[5]]) as usize;
+        let unit_id = data[6];
+        if length == 0 || data.len() < MBAP_HEADER_LENGTH - 1 + length {
+            return Ok(None);
+        }
+        take(data, MBAP_HEADER_LENGTH)?;
+        Ok(Some((tx_id, unit_id, take(data, length - 1)?)))
+    }
+
+    /// source port, destination port, and payload of an IPv4 packet that carries TCP
+    fn tcp_payload(packet: &[u8]) -> Result<(u16, u16, &[u8]), LoadError> {
+        let ip_length = packet
+            .first()
+            .map(|x| (x & 0x0F) as usize * 4)
+            .ok_or(LoadError::BadFormat("empty packet"))?;
+        if packet.get(9) != Some(&6) {
+            return Err(LoadError::BadFormat("packet does not contain TCP"));
+        }
+        let tcp = packet
+            .get(ip_length..)
+            .ok_or(LoadError::BadFormat("truncated IPv4 header"))?;
+        if tcp.len() < 20 {
+            return Err(LoadError::BadFormat("truncated TCP header"));
+        }
+        let src_port = u16::from_be_bytes([tcp[0], tcp[1]]);
+        let dst_port = u16::from_be_bytes([tcp[2], tcp[3]]);
+        let payload = tcp
+            .get((tcp[12] >> 4) as usize * 4..)
+            .ok_or(LoadError::BadFormat("truncated TCP header"))?;
+        Ok((src_port, dst_port, payload))
+    }
+
+    fn take<'a>(data: &mut &'a [u8], count: usize) -> Result<&'a [u8], LoadError> {
+        if data.len() < count {
+            return Err(LoadError::BadFormat("truncated file"));
+        }
+        let (head, tail) = data.split_at(count);
+        *data = tail;
+        Ok(head)
+    }
+
+    fn u32_le(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::{Direction, Endpoint, FrameSink, PcapWriter};
+    use std::time::SystemTime;
+
+    fn holding_registers(start: u16, count: u16) -> RequestMatcher {
+        RequestMatcher::new(
+            UnitId::new(1),
+            FunctionCode::ReadHoldingRegisters,
+            AddressRange::try_from(start, count).unwrap(),
+        )
+    }
+
+    #[test]
+    fn answers_in_script_order_and_repeats_the_last_response() {
+        let mut handler = ScriptedHandler::new([
+            (
+                holding_registers(0, 1),
+                Ok(AsyncResponse::Registers(vec![1])),
+            ),
+            (
+                holding_registers(0, 1),
+                Ok(AsyncResponse::Registers(vec![2])),
+            ),
+        ]);
+
+        assert_eq!(
+            handler.respond(holding_registers(0, 1)),
+            Ok(AsyncResponse::Registers(vec![1]))
+        );
+        assert_eq!(
+            handler.respond(holding_registers(0, 1)),
+            Ok(AsyncResponse::Registers(vec![2]))
+        );
+        assert_eq!(
+            handler.respond(holding_registers(0, 1)),
+            Ok(AsyncResponse::Registers(vec![2]))
+        );
+        assert!(handler.unmatched().is_empty());
+
+        assert_eq!(
+            handler.respond(holding_registers(1, 1)),
+            Err(ExceptionCode::IllegalFunction)
+        );
+        assert_eq!(handler.unmatched(), &[holding_registers(1, 1)]);
+    }
+
+    #[test]
+    fn loads_exchanges_from_server_capture() {
+        let mut writer = PcapWriter::new(Vec::new(), Endpoint::Server).unwrap();
+        let now = SystemTime::now();
+        // read 2 holding registers at 7, the request is received in two reads
+        writer.record(now, Direction::Rx, &[0x00, 0x05, 0x00, 0x00, 0x00]);
+        writer.record(
+            now,
+            Direction::Rx,
+            &[0x06, 0x01, 0x03, 0x00, 0x07, 0x00, 0x02],
+        );
+        writer.record(
+            now,
+            Direction::Tx,
+            &[
+                0x00, 0x05, 0x00, 0x00, 0x00, 0x07, 0x01, 0x03, 0x04, 0xCA, 0xFE, 0xBE, 0xEF,
+            ],
+        );
+        // write single coil 3, answered with an exception
+        writer.record(
+            now,
+            Direction::Rx,
+            &[
+                0x00, 0x06, 0x00, 0x00, 0x00, 0x06, 0x01, 0x05, 0x00, 0x03, 0xFF, 0x00,
+            ],
+        );
+        writer.record(
+            now,
+            Direction::Tx,
+            &[0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0x01, 0x85, 0x02],
+        );
+
+        let mut handler = ScriptedHandler::from_pcap_bytes(&writer.into_inner()).unwrap();
+        assert_eq!(
+            handler.respond(holding_registers(7, 2)),
+            Ok(AsyncResponse::Registers(vec![0xCAFE, 0xBEEF]))
+        );
+        assert_eq!(
+            handler.respond(RequestMatcher::new(
+                UnitId::new(1),
+                FunctionCode::WriteSingleCoil,
+                AddressRange::try_from(3, 1).unwrap()
+            )),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::decode::AppDecodeLevel;
+use crate::decode::AppDecode;
 use crate::error::{AduParseError, InvalidRange};
 
 use scursor::ReadCursor;
@@ -7,6 +7,11 @@ use crate::error::RequestError;
 
 /// Modbus unit identifier, just a type-safe wrapper around `u8`
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct UnitId {
     /// underlying raw value
     pub value: u8,
@@ -14,7 +19,14 @@ pub struct UnitId {
 
 /// Start and count tuple used when making various requests
 /// Cannot be constructed with invalid start/count
+///
+/// With the `serde` feature, the same checks as [`AddressRange::try_from`] are validated when deserializing.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "AddressRangeFields")
+)]
 pub struct AddressRange {
     /// Starting address of the range
     pub start: u16,
@@ -22,6 +34,22 @@ pub struct AddressRange {
     pub count: u16,
 }
 
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct AddressRangeFields {
+    start: u16,
+    count: u16,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<AddressRangeFields> for AddressRange {
+    type Error = InvalidRange;
+
+    fn try_from(value: AddressRangeFields) -> Result<Self, Self::Error> {
+        AddressRange::try_from(value.start, value.count)
+    }
+}
+
 /// Specialized wrapper around an address
 /// range only valid for ReadCoils / ReadDiscreteInputs
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -50,8 +78,21 @@ impl ReadRegistersRange {
     }
 }
 
+/// Value of a coil as encoded in a write single coil request or response
+///
+/// The specification only permits `0xFF00` (ON) and `0x0000` (OFF), any other value is rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoilState {
+    /// Encoded as `0x0000`
+    Off,
+    /// Encoded as `0xFF00`
+    On,
+}
+
 /// Value and its address
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Indexed<T> {
     /// Address of the value
     pub index: u16,
@@ -65,11 +106,12 @@ pub struct BitIterator<'a> {
     bytes: &'a [u8],
     range: AddressRange,
     pos: u16,
+    clean_padding: bool,
 }
 
 pub(crate) struct BitIteratorDisplay<'a> {
     iterator: BitIterator<'a>,
-    level: AppDecodeLevel,
+    level: AppDecode,
 }
 
 /// Zero-copy type used to iterate over a collection of registers
@@ -82,7 +124,7 @@ pub struct RegisterIterator<'a> {
 
 pub(crate) struct RegisterIteratorDisplay<'a> {
     iterator: RegisterIterator<'a>,
-    level: AppDecodeLevel,
+    level: AppDecode,
 }
 
 impl std::fmt::Display for UnitId {
@@ -102,12 +144,49 @@ impl<'a> BitIterator<'a> {
             bytes,
             range,
             pos: 0,
+            clean_padding: crate::common::bits::unused_bits_are_clear(bytes, range.count),
         })
     }
 }
 
+impl BitIterator<'_> {
+    /// True if the unused bits of the last byte, which follow the last bit of the range, are zero
+    ///
+    /// The specification requires them to be zero, but they are otherwise ignored.
+    pub fn has_clean_padding(&self) -> bool {
+        self.clean_padding
+    }
+
+    /// Remaining bits packed into bytes, the first bit in the least significant bit of the first byte
+    ///
+    /// This is the encoding used by Modbus, so the bits can be forwarded without expanding them
+    /// into `bool` values, e.g. with [`WriteMultiple::from_packed`](crate::client::WriteMultiple::from_packed).
+    /// The unused bits of the last byte are cleared.
+    pub fn packed_bytes(&self) -> Vec<u8> {
+        let remaining = self.range.count - self.pos;
+        let count = crate::common::bits::num_bytes_for_bits(remaining);
+        let start = (self.pos / 8) as usize;
+        let mut bytes = match self.bytes.get(start..start + count) {
+            // the remaining bits are byte aligned and can be copied
+            Some(x) if self.pos % 8 == 0 => x.to_vec(),
+            _ => {
+                let mut bytes = vec![0; count];
+                let remaining_bits = *self;
+                for (offset, bit) in remaining_bits.enumerate() {
+                    if bit.value {
+                        bytes[offset / 8] |= 1 << (offset % 8);
+                    }
+                }
+                bytes
+            }
+        };
+        crate::common::bits::clear_unused_bits(&mut bytes, remaining);
+        bytes
+    }
+}
+
 impl<'a> BitIteratorDisplay<'a> {
-    pub(crate) fn new(level: AppDecodeLevel, iterator: BitIterator<'a>) -> Self {
+    pub(crate) fn new(level: AppDecode, iterator: BitIterator<'a>) -> Self {
         Self { iterator, level }
     }
 }
@@ -118,7 +197,7 @@ impl std::fmt::Display for BitIteratorDisplay<'_> {
 
         if self.level.data_values() {
             for x in self.iterator {
-                write!(f, "\n{x}")?;
+                write!(f, "\n{}", ValueDisplay::new(self.level, &x))?;
             }
         }
 
@@ -142,7 +221,7 @@ impl<'a> RegisterIterator<'a> {
 }
 
 impl<'a> RegisterIteratorDisplay<'a> {
-    pub(crate) fn new(level: AppDecodeLevel, iterator: RegisterIterator<'a>) -> Self {
+    pub(crate) fn new(level: AppDecode, iterator: RegisterIterator<'a>) -> Self {
         Self { iterator, level }
     }
 }
@@ -153,7 +232,7 @@ impl std::fmt::Display for RegisterIteratorDisplay<'_> {
 
         if self.level.data_values() {
             for x in self.iterator {
-                write!(f, "\n{x}")?;
+                write!(f, "\n{}", ValueDisplay::new(self.level, &x))?;
             }
         }
 
@@ -227,19 +306,11 @@ where
 }
 
 pub(crate) fn coil_from_u16(value: u16) -> Result<bool, AduParseError> {
-    match value {
-        crate::constants::coil::ON => Ok(true),
-        crate::constants::coil::OFF => Ok(false),
-        _ => Err(AduParseError::UnknownCoilState(value)),
-    }
+    CoilState::try_from(value).map(bool::from)
 }
 
 pub(crate) fn coil_to_u16(value: bool) -> u16 {
-    if value {
-        crate::constants::coil::ON
-    } else {
-        crate::constants::coil::OFF
-    }
+    CoilState::from(value).into()
 }
 
 impl AddressRange {
@@ -265,8 +336,60 @@ impl AddressRange {
         start..end
     }
 
-    pub(crate) fn iter(&self) -> impl Iterator<Item = u16> {
-        AddressIterator::new(self.start, self.count)
+    /// Iterate over the addresses in the range
+    pub fn iter(&self) -> AddressIterator {
+        AddressIterator::new(self.start, (self.end() - self.start as u32) as u16)
+    }
+
+    /// Check if the address is contained within the range
+    pub fn contains(&self, address: u16) -> bool {
+        let address = address as u32;
+        address >= self.start as u32 && address < self.end()
+    }
+
+    /// Check if the two ranges have at least one address in common
+    pub fn overlaps(&self, other: &AddressRange) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Compute the range of addresses contained in both ranges, if any
+    pub fn intersection(&self, other: &AddressRange) -> Option<AddressRange> {
+        let start = self.start.max(other.start);
+        let end = self.end().min(other.end());
+        if (start as u32) < end {
+            Some(AddressRange {
+                start,
+                count: (end - start as u32) as u16,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Split the range into consecutive ranges containing at most `max` addresses each
+    ///
+    /// A `max` of zero is treated as one.
+    pub fn split_at_max(&self, max: u16) -> impl Iterator<Item = AddressRange> {
+        let max = max.max(1) as u32;
+        let end = self.end();
+        let mut next = self.start as u32;
+        std::iter::from_fn(move || {
+            if next >= end {
+                return None;
+            }
+            let count = (end - next).min(max);
+            let range = AddressRange {
+                start: next as u16,
+                count: count as u16,
+            };
+            next += count;
+            Some(range)
+        })
+    }
+
+    /// one past the last address, which may be 65536 when the range ends at 0xFFFF
+    fn end(&self) -> u32 {
+        (self.start as u32 + self.count as u32).min(u16::MAX as u32 + 1)
     }
 
     pub(crate) fn of_read_bits(self) -> Result<ReadBitsRange, InvalidRange> {
@@ -281,6 +404,14 @@ impl AddressRange {
         })
     }
 
+    pub(crate) fn of_write_coils(self) -> Result<Self, InvalidRange> {
+        self.limited_count(crate::constants::limits::MAX_WRITE_COILS_COUNT)
+    }
+
+    pub(crate) fn of_write_registers(self) -> Result<Self, InvalidRange> {
+        self.limited_count(crate::constants::limits::MAX_WRITE_REGISTERS_COUNT)
+    }
+
     fn limited_count(self, limit: u16) -> Result<Self, InvalidRange> {
         if self.count > limit {
             return Err(InvalidRange::CountTooLargeForType(self.count, limit));
@@ -295,7 +426,28 @@ impl std::fmt::Display for AddressRange {
     }
 }
 
-pub(crate) struct AddressIterator {
+impl IntoIterator for AddressRange {
+    type Item = u16;
+    type IntoIter = AddressIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for &AddressRange {
+    type Item = u16;
+    type IntoIter = AddressIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the addresses of an [`AddressRange`]
+#[derive(Clone, Debug)]
+#[allow(missing_copy_implementations)] // iterators should not be implicitly copied
+pub struct AddressIterator {
     pub(crate) current: u16,
     pub(crate) remain: u16,
 }
@@ -313,15 +465,24 @@ impl Iterator for AddressIterator {
         match self.remain.checked_sub(1) {
             Some(x) => {
                 let ret = self.current;
-                self.current += 1;
+                // the last address of a range may be 0xFFFF
+                self.current = self.current.wrapping_add(1);
                 self.remain = x;
                 Some(ret)
             }
             None => None,
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remain as usize, Some(self.remain as usize))
+    }
 }
 
+impl ExactSizeIterator for AddressIterator {}
+
+impl std::iter::FusedIterator for AddressIterator {}
+
 impl<T> Indexed<T> {
     /// Create a new indexed value
     pub fn new(index: u16, value: T) -> Self {
@@ -341,6 +502,88 @@ impl std::fmt::Display for Indexed<u16> {
     }
 }
 
+/// Value whose data can be replaced by `**` in the logs
+pub(crate) trait Redact {
+    fn fmt_redacted(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+}
+
+impl<T> Redact for Indexed<T> {
+    fn fmt_redacted(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "idx: {:#06X} value: **", self.index)
+    }
+}
+
+/// Displays a value, or its redacted form if the decode level redacts values
+pub(crate) struct ValueDisplay<'a, T> {
+    level: AppDecode,
+    value: &'a T,
+}
+
+impl<'a, T> ValueDisplay<'a, T> {
+    pub(crate) fn new(level: AppDecode, value: &'a T) -> Self {
+        Self { level, value }
+    }
+}
+
+impl<T> std::fmt::Display for ValueDisplay<'_, T>
+where
+    T: std::fmt::Display + Redact,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.level.redact_values {
+            self.value.fmt_redacted(f)
+        } else {
+            self.value.fmt(f)
+        }
+    }
+}
+
+impl From<bool> for CoilState {
+    fn from(value: bool) -> Self {
+        if value {
+            Self::On
+        } else {
+            Self::Off
+        }
+    }
+}
+
+impl From<CoilState> for bool {
+    fn from(value: CoilState) -> Self {
+        value == CoilState::On
+    }
+}
+
+impl From<CoilState> for u16 {
+    fn from(value: CoilState) -> Self {
+        match value {
+            CoilState::Off => crate::constants::coil::OFF,
+            CoilState::On => crate::constants::coil::ON,
+        }
+    }
+}
+
+impl TryFrom<u16> for CoilState {
+    type Error = AduParseError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            crate::constants::coil::ON => Ok(Self::On),
+            crate::constants::coil::OFF => Ok(Self::Off),
+            _ => Err(AduParseError::UnknownCoilState(value)),
+        }
+    }
+}
+
+impl std::fmt::Display for CoilState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => f.write_str("OFF"),
+            Self::On => f.write_str("ON"),
+        }
+    }
+}
+
 impl UnitId {
     /// Create a new UnitId
     pub fn new(value: u8) -> Self {
@@ -373,9 +616,22 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn coil_state_round_trips_through_bool_and_u16() {
+        for state in [CoilState::Off, CoilState::On] {
+            assert_eq!(CoilState::from(bool::from(state)), state);
+            assert_eq!(CoilState::try_from(u16::from(state)), Ok(state));
+        }
+        assert_eq!(u16::from(CoilState::On), 0xFF00);
+        assert_eq!(
+            CoilState::try_from(0x0001),
+            Err(AduParseError::UnknownCoilState(0x0001))
+        );
+    }
+
     #[test]
     fn address_start_max_count_of_one_is_allowed() {
-        AddressRange::try_from(std::u16::MAX, 1).unwrap();
+        AddressRange::try_from(u16::MAX, 1).unwrap();
     }
 
     #[test]
@@ -396,6 +652,136 @@ mod tests {
         );
     }
 
+    #[test]
+    fn limits_the_count_of_each_request_type() {
+        use crate::constants::limits::*;
+
+        let range = |count| AddressRange::try_from(0, count).unwrap();
+        let too_large = |count, limit| Some(InvalidRange::CountTooLargeForType(count, limit));
+
+        let limit = MAX_READ_COILS_COUNT;
+        assert!(range(limit).of_read_bits().is_ok());
+        assert_eq!(
+            range(limit + 1).of_read_bits().err(),
+            too_large(limit + 1, limit)
+        );
+
+        let limit = MAX_READ_REGISTERS_COUNT;
+        assert!(range(limit).of_read_registers().is_ok());
+        assert_eq!(
+            range(limit + 1).of_read_registers().err(),
+            too_large(limit + 1, limit)
+        );
+
+        let limit = MAX_WRITE_COILS_COUNT;
+        assert_eq!(range(limit).of_write_coils(), Ok(range(limit)));
+        assert_eq!(
+            range(limit + 1).of_write_coils().err(),
+            too_large(limit + 1, limit)
+        );
+
+        let limit = MAX_WRITE_REGISTERS_COUNT;
+        assert_eq!(range(limit).of_write_registers(), Ok(range(limit)));
+        assert_eq!(
+            range(limit + 1).of_write_registers().err(),
+            too_large(limit + 1, limit)
+        );
+    }
+
+    /// ranges that start near zero or end near the 0xFFFF boundary
+    fn boundary_ranges() -> impl Iterator<Item = AddressRange> {
+        let starts = (0..4).chain(0xFFF0..=0xFFFF);
+        starts.flat_map(|start| {
+            [1, 2, 3, 7, 0x0F, 0x10, 0x11, 0xFFFF]
+                .into_iter()
+                .filter_map(move |count| AddressRange::try_from(start, count).ok())
+        })
+    }
+
+    /// addresses as u32 so that the reference model cannot overflow
+    fn model(range: AddressRange) -> std::ops::Range<u32> {
+        range.start as u32..range.start as u32 + range.count as u32
+    }
+
+    #[test]
+    fn iterates_up_to_the_last_address() {
+        let range = AddressRange::try_from(0xFFFE, 2).unwrap();
+        assert_eq!(
+            range.into_iter().collect::<Vec<u16>>(),
+            vec![0xFFFE, 0xFFFF]
+        );
+
+        for range in boundary_ranges() {
+            let iter = range.iter();
+            assert_eq!(iter.len(), range.count as usize);
+            let actual: Vec<u32> = iter.map(|x| x as u32).collect();
+            assert_eq!(actual, model(range).collect::<Vec<u32>>(), "{range}");
+        }
+    }
+
+    #[test]
+    fn contains_matches_model() {
+        let addresses = [
+            0, 1, 2, 3, 4, 0x10, 0x11, 0x12, 0xFFEF, 0xFFF0, 0xFFFE, 0xFFFF,
+        ];
+        for range in boundary_ranges() {
+            for address in addresses {
+                assert_eq!(
+                    range.contains(address),
+                    model(range).contains(&(address as u32)),
+                    "{range} {address:#06X}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn intersection_and_overlap_match_model() {
+        for a in boundary_ranges() {
+            for b in boundary_ranges() {
+                let (x, y) = (model(a), model(b));
+                let start = x.start.max(y.start);
+                let end = x.end.min(y.end);
+                let expected = (start < end)
+                    .then(|| AddressRange::try_from(start as u16, (end - start) as u16).unwrap());
+                assert_eq!(a.intersection(&b), expected, "{a} {b}");
+                assert_eq!(b.intersection(&a), expected, "{a} {b}");
+                assert_eq!(a.overlaps(&b), expected.is_some(), "{a} {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn split_at_max_covers_range_exactly() {
+        for range in boundary_ranges() {
+            for max in [0, 1, 2, 7, 0x7D, 0xFFFF] {
+                let chunks: Vec<AddressRange> = range.split_at_max(max).collect();
+                assert!(
+                    chunks.iter().all(|x| x.count <= max.max(1)),
+                    "{range} {max}"
+                );
+                let addresses: Vec<u16> = chunks.iter().flat_map(|x| x.iter()).collect();
+                assert_eq!(
+                    addresses,
+                    range.iter().collect::<Vec<u16>>(),
+                    "{range} {max}"
+                );
+            }
+        }
+
+        let chunks: Vec<AddressRange> = AddressRange::try_from(0xFFFA, 6)
+            .unwrap()
+            .split_at_max(4)
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![
+                AddressRange::try_from(0xFFFA, 4).unwrap(),
+                AddressRange::try_from(0xFFFE, 2).unwrap()
+            ]
+        );
+    }
+
     #[test]
     fn correctly_iterates_over_low_order_bits() {
         let mut cursor = ReadCursor::new(&[0x03]);
@@ -413,6 +799,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reports_whether_the_padding_bits_are_clean() {
+        fn is_clean(count: u16, bytes: &[u8]) -> bool {
+            let mut cursor = ReadCursor::new(bytes);
+            BitIterator::parse_all(AddressRange::try_from(0, count).unwrap(), &mut cursor)
+                .unwrap()
+                .has_clean_padding()
+        }
+
+        assert!(is_clean(1, &[0x01]));
+        assert!(!is_clean(1, &[0x03]));
+        assert!(is_clean(7, &[0x7F]));
+        assert!(!is_clean(7, &[0xFF]));
+        // every bit of the byte is used
+        assert!(is_clean(8, &[0xFF]));
+        assert!(is_clean(9, &[0xFF, 0x01]));
+        assert!(!is_clean(9, &[0xFF, 0x81]));
+    }
+
+    #[test]
+    fn packs_the_remaining_bits() {
+        let bytes = [0b1010_0101, 0xFF];
+        let mut cursor = ReadCursor::new(&bytes);
+        let mut iterator =
+            BitIterator::parse_all(AddressRange::try_from(0, 10).unwrap(), &mut cursor).unwrap();
+        // the unused bits of the last byte are cleared
+        assert_eq!(iterator.packed_bytes(), vec![0b1010_0101, 0x03]);
+
+        // the remaining 7 bits are no longer byte aligned
+        iterator.nth(2);
+        assert_eq!(iterator.packed_bytes(), vec![0b0111_0100]);
+
+        iterator.nth(4);
+        assert_eq!(iterator.packed_bytes(), vec![0x03]);
+    }
+
     #[test]
     fn correctly_iterates_over_registers() {
         let mut cursor = ReadCursor::new(&[0xFF, 0xFF, 0x01, 0xCC]);
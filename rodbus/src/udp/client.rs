@@ -0,0 +1,145 @@
+use tracing::Instrument;
+
+use crate::client::{Channel, ClientState, DisconnectReason, FrameFormat, HostAddr, Listener};
+use crate::common::phys::PhysLayer;
+use crate::decode::DecodeLevel;
+
+use crate::client::message::Command;
+use crate::client::task::{ClientLoop, SessionError, StateChange};
+use crate::common::frame::{FrameWriter, FramedReader};
+use crate::error::Shutdown;
+use crate::retry::RetryStrategy;
+
+pub(crate) fn spawn_udp_channel(
+    host: HostAddr,
+    format: FrameFormat,
+    max_queued_requests: usize,
+    retry: Box<dyn RetryStrategy>,
+    decode: DecodeLevel,
+    listener: Box<dyn Listener<ClientState>>,
+) -> Channel {
+    let (tx, rx) = crate::channel::channel(max_queued_requests);
+    let task = async move {
+        UdpChannelTask::new(host.clone(), format, rx, retry, decode, listener)
+            .run()
+            .instrument(tracing::info_span!("Modbus-Client-UDP", endpoint = ?host, ?format))
+            .await;
+    };
+    tokio::spawn(task);
+    Channel::new(tx)
+}
+
+pub(crate) struct UdpChannelTask {
+    host: HostAddr,
+    retry: Box<dyn RetryStrategy>,
+    client_loop: ClientLoop,
+    listener: Box<dyn Listener<ClientState>>,
+}
+
+impl UdpChannelTask {
+    pub(crate) fn new(
+        host: HostAddr,
+        format: FrameFormat,
+        rx: crate::channel::Receiver<Command>,
+        retry: Box<dyn RetryStrategy>,
+        decode: DecodeLevel,
+        listener: Box<dyn Listener<ClientState>>,
+    ) -> Self {
+        let (writer, reader) = match format {
+            FrameFormat::Mbap => (FrameWriter::tcp(), FramedReader::tcp()),
+            FrameFormat::Rtu => (FrameWriter::rtu(), FramedReader::rtu_response()),
+        };
+        let client_loop = ClientLoop::new(rx, writer, reader, decode);
+        let listener = client_loop.track_state(listener);
+        Self {
+            host,
+            retry,
+            client_loop,
+            listener,
+        }
+    }
+
+    // runs until it is shut down
+    pub(crate) async fn run(&mut self) -> Shutdown {
+        self.listener.update(ClientState::Disabled).get().await;
+        let ret = self.run_inner().await;
+        self.listener.update(ClientState::Shutdown).get().await;
+        ret
+    }
+
+    async fn run_inner(&mut self) -> Shutdown {
+        loop {
+            if let Err(Shutdown) = self.client_loop.wait_for_enabled().await {
+                return Shutdown;
+            }
+
+            if let Err(StateChange::Shutdown) = self.try_bind_and_run().await {
+                return Shutdown;
+            }
+
+            if !self.client_loop.is_enabled() {
+                self.listener.update(ClientState::Disabled).get().await;
+            }
+        }
+    }
+
+    async fn try_bind_and_run(&mut self) -> Result<(), StateChange> {
+        self.listener.update(ClientState::Connecting).get().await;
+        let socket = match self.host.connect_udp().await {
+            Ok(x) => x,
+            Err((reason, err)) => {
+                let delay = self.retry.after_failed_connect();
+                tracing::warn!(
+                    "unable to create UDP socket for {}: {} - waiting {} ms before next attempt",
+                    self.host,
+                    err,
+                    delay.as_millis()
+                );
+                self.listener
+                    .update(ClientState::WaitAfterFailedConnect {
+                        reason,
+                        retry_in: delay,
+                    })
+                    .get()
+                    .await;
+                self.client_loop.fail_pending_request();
+                return self.client_loop.fail_requests_for(delay).await;
+            }
+        };
+
+        if let Ok(addr) = socket.peer_addr() {
+            tracing::info!("sending datagrams to: {}", addr);
+        }
+        self.retry.reset();
+        self.listener.update(ClientState::Connected).get().await;
+
+        // lost datagrams are covered by the response timeout of each request
+        let mut phys = PhysLayer::new_udp(socket);
+        let result = self.client_loop.run(&mut phys).await;
+        drop(phys);
+        let reason = match result {
+            // the mpsc was closed, end the task
+            SessionError::Shutdown => return Err(StateChange::Shutdown),
+            // don't wait, we're disabled (idle disconnect is never configured for UDP)
+            // serial settings are rejected by UDP channels
+            SessionError::Disabled | SessionError::Idle | SessionError::SerialSettingsChanged => {
+                return Ok(())
+            }
+            // e.g. ICMP port unreachable, wait before creating a new socket
+            SessionError::IoError(kind) => DisconnectReason::Io(kind),
+            SessionError::BadFrame => DisconnectReason::BadFrame,
+        };
+
+        crate::common::metrics::client_reconnect();
+        let delay = self.retry.after_disconnect();
+        tracing::warn!("waiting {:?} to re-create the UDP socket", delay);
+        self.listener
+            .update(ClientState::WaitAfterDisconnect {
+                reason,
+                retry_in: delay,
+            })
+            .get()
+            .await;
+        self.client_loop.fail_requests_for(delay).await
+    }
+}
@@ -1,3 +1,5 @@
+//! Integration tests for client/server interaction
+
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::time::Duration;
@@ -204,6 +206,50 @@ async fn test_requests_and_responses() {
         vec![Indexed::new(0, 0x0000), Indexed::new(1, 0xABCD)]
     );
 
+    // read into caller-provided buffers, reusing their allocations
+    let mut registers = vec![0xFFFF; 8];
+    let capacity = registers.capacity();
+    channel
+        .read_holding_registers_into(
+            params,
+            AddressRange::try_from(0, 2).unwrap(),
+            &mut registers,
+        )
+        .await
+        .unwrap();
+    assert_eq!(registers, vec![0x0000, 0xABCD]);
+    assert_eq!(registers.capacity(), capacity);
+
+    let mut coils = Vec::new();
+    channel
+        .read_coils_into(params, AddressRange::try_from(0, 2).unwrap(), &mut coils)
+        .await
+        .unwrap();
+    assert_eq!(coils, vec![false, true]);
+
+    // borrow the response values without collecting them
+    assert_eq!(
+        channel
+            .read_holding_registers_with(params, AddressRange::try_from(0, 2).unwrap(), |iter| {
+                iter.map(|x| x.value as u32).sum::<u32>()
+            })
+            .await
+            .unwrap(),
+        0xABCD
+    );
+
+    // the closure only needs to be Send, so it may capture state that isn't Sync
+    let count = std::cell::Cell::new(0);
+    let count = channel
+        .read_coils_with(params, AddressRange::try_from(0, 2).unwrap(), move |iter| {
+            iter.filter(|x| x.value)
+                .for_each(|_| count.set(count.get() + 1));
+            count.get()
+        })
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
     // write multiple coils and verify that they were written
     assert_eq!(
         channel
@@ -249,6 +295,27 @@ async fn test_requests_and_responses() {
             Indexed::new(2, 0x0506)
         ]
     );
+
+    // the callback channel reports the result without a future
+    let callbacks = CallbackChannel::new(channel.clone());
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    callbacks
+        .read_holding_registers(params, AddressRange::try_from(1, 2).unwrap(), |result| {
+            let _ = tx.send(result);
+        })
+        .unwrap();
+    assert_eq!(
+        rx.await.unwrap().unwrap(),
+        vec![Indexed::new(1, 0x0304), Indexed::new(2, 0x0506)]
+    );
+
+    // invalid requests are rejected without invoking the callback
+    assert_eq!(
+        callbacks.read_coils(params, AddressRange { start: 0, count: 0 }, |_| {
+            panic!("callback invoked")
+        }),
+        Err(QueueError::BadRequest(InvalidRange::CountOfZero.into()))
+    );
 }
 
 #[test]
@@ -256,3 +323,1158 @@ fn can_read_and_write_values() {
     let rt = Runtime::new().unwrap();
     rt.block_on(test_requests_and_responses())
 }
+
+async fn test_rtu_over_tcp() {
+    let handler = Handler::new().wrap();
+    let addr = SocketAddr::from_str("127.0.0.1:40001").unwrap();
+
+    let _server = spawn_rtu_over_tcp_server_task(
+        1,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), handler.clone()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let listener = ClientStateListener { tx };
+
+    let mut channel = spawn_rtu_over_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(listener)),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(0x01), Duration::from_secs(1));
+
+    assert_eq!(
+        channel
+            .write_single_register(params, Indexed::new(1, 0xABCD))
+            .await
+            .unwrap(),
+        Indexed::new(1, 0xABCD)
+    );
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 2).unwrap())
+            .await
+            .unwrap(),
+        vec![Indexed::new(0, 0x0000), Indexed::new(1, 0xABCD)]
+    );
+}
+
+#[test]
+fn can_read_and_write_values_with_rtu_over_tcp() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_rtu_over_tcp())
+}
+
+async fn test_builders() {
+    let handler = Handler::new().wrap();
+    let addr = SocketAddr::from_str("127.0.0.1:40010").unwrap();
+
+    let _server = ServerConfig::tcp(addr)
+        .max_sessions(1)
+        .spawn(ServerHandlerMap::single(UnitId::new(1), handler.clone()))
+        .await
+        .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = ClientChannelConfig::tcp(HostAddr::ip(addr.ip(), addr.port()))
+        .max_queued_requests(4)
+        .connect_timeout(Duration::from_secs(1))
+        .listener(Box::new(ClientStateListener { tx }))
+        .spawn();
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(0x01), Duration::from_secs(1));
+    assert_eq!(
+        channel
+            .write_single_register(params, Indexed::new(2, 0x1234))
+            .await
+            .unwrap(),
+        Indexed::new(2, 0x1234)
+    );
+    assert_eq!(handler.lock().unwrap().holding_registers[2], 0x1234);
+}
+
+#[test]
+fn builders_spawn_connected_client_and_server() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_builders())
+}
+
+async fn test_udp_client() {
+    let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let listener = ClientStateListener { tx };
+
+    let mut channel = spawn_udp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        FrameFormat::Mbap,
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(listener)),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until the socket is bound
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    // answers every request except the first one, which is "lost"
+    tokio::spawn(async move {
+        let mut buffer = [0; 512];
+        let mut first = true;
+        loop {
+            let (count, from) = server.recv_from(&mut buffer).await.unwrap();
+            // read a single holding register from unit 1
+            assert_eq!(
+                &buffer[2..count],
+                &[0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01]
+            );
+            if first {
+                first = false;
+                continue;
+            }
+            let response = [
+                buffer[0], buffer[1], 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x00, 0x2A,
+            ];
+            server.send_to(&response, from).await.unwrap();
+        }
+    });
+
+    let params = RequestParam::new(UnitId::new(0x01), Duration::from_millis(200));
+    let range = AddressRange::try_from(0, 1).unwrap();
+
+    assert_eq!(
+        channel.read_holding_registers(params, range).await,
+        Err(RequestError::ResponseTimeout)
+    );
+    assert_eq!(
+        channel.read_holding_registers(params, range).await.unwrap(),
+        vec![Indexed::new(0, 0x2A)]
+    );
+}
+
+#[test]
+fn udp_client_recovers_from_lost_datagrams() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_udp_client())
+}
+
+#[cfg(feature = "tls")]
+async fn test_tls_info() {
+    use std::path::Path;
+
+    struct EventListener {
+        tx: tokio::sync::mpsc::UnboundedSender<ServerConnectionEvent>,
+    }
+
+    impl Listener<ServerConnectionEvent> for EventListener {
+        fn update(&mut self, value: ServerConnectionEvent) -> MaybeAsync<()> {
+            let _ = self.tx.send(value);
+            MaybeAsync::ready(())
+        }
+    }
+
+    let certs = Path::new(env!("CARGO_MANIFEST_DIR")).join("../certs/ca_chain");
+    let addr = SocketAddr::from_str("127.0.0.1:40002").unwrap();
+
+    let server_config = TlsServerConfig::new(
+        &certs.join("ca_cert.pem"),
+        &certs.join("server_cert.pem"),
+        &certs.join("server_key.pem"),
+        None,
+        MinTlsVersion::V1_2,
+        CertificateMode::AuthorityBased,
+    )
+    .unwrap();
+
+    let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let _server = spawn_tls_server_task_with_options(
+        1,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), Handler::new().wrap()),
+        None,
+        server_config,
+        AddressFilter::Any,
+        DecodeLevel::default(),
+        ServerOptions::default().connection_listener(Box::new(EventListener { tx: events_tx })),
+    )
+    .await
+    .unwrap();
+
+    let client_config = TlsClientConfig::full_pki(
+        Some("test.com".to_string()),
+        &certs.join("ca_cert.pem"),
+        &certs.join("client_cert.pem"),
+        &certs.join("client_key.pem"),
+        None,
+        MinTlsVersion::V1_2,
+    )
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let channel = spawn_tls_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        client_config,
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+
+    // not connected yet
+    assert_eq!(channel.tls_info().await.unwrap(), None);
+
+    channel.enable().await.unwrap();
+    loop {
+        if rx.recv().await.unwrap() == ClientState::Connected {
+            break;
+        }
+    }
+
+    let client_info = channel.tls_info().await.unwrap().unwrap();
+    assert_eq!(client_info.protocol_version, "TLSv1_2");
+    assert!(!client_info.cipher_suite.is_empty());
+    let server_cert = client_info.peer_certificate.unwrap();
+    assert_eq!(server_cert.serial_number, "02");
+    assert_eq!(server_cert.subject_alt_names, vec!["test.com".to_string()]);
+
+    let server_info = loop {
+        if let ServerConnectionEvent::TlsEstablished(_, info) = events.recv().await.unwrap() {
+            break info;
+        }
+    };
+    assert_eq!(server_info.protocol_version, client_info.protocol_version);
+    assert_eq!(server_info.cipher_suite, client_info.cipher_suite);
+    let client_cert = server_info.peer_certificate.unwrap();
+    assert_eq!(client_cert.serial_number, "01");
+    assert_eq!(
+        client_cert.subject_common_name.as_deref(),
+        Some("DO NOT USE")
+    );
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn reports_tls_session_info() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_tls_info())
+}
+
+#[test]
+fn blocking_channel_reads_and_writes_values() {
+    use rodbus::blocking::BlockingChannel;
+    use std::time::Instant;
+
+    // the server runs on its own runtime, the test thread isn't within an async context
+    let rt = Runtime::new().unwrap();
+    let addr = SocketAddr::from_str("127.0.0.1:40003").unwrap();
+    let _server = rt
+        .block_on(spawn_tcp_server_task(
+            1,
+            addr,
+            ServerHandlerMap::single(UnitId::new(1), Handler::new().wrap()),
+            AddressFilter::Any,
+            DecodeLevel::default(),
+        ))
+        .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = BlockingChannel::spawn(|| {
+        spawn_tcp_client_task(
+            HostAddr::ip(addr.ip(), addr.port()),
+            10,
+            default_retry_strategy(),
+            DecodeLevel::default(),
+            Some(Box::new(ClientStateListener { tx })),
+        )
+    })
+    .unwrap();
+
+    let deadline = || Instant::now() + Duration::from_secs(5);
+    channel.enable(deadline()).unwrap();
+    while rx.blocking_recv().unwrap() != ClientState::Connected {}
+
+    let params = RequestParam::new(UnitId::new(0x01), Duration::from_secs(1));
+    assert_eq!(
+        channel
+            .write_multiple_registers(
+                params,
+                WriteMultiple::from(1, vec![0xCAFE, 0xBEEF]).unwrap(),
+                deadline()
+            )
+            .unwrap(),
+        AddressRange::try_from(1, 2).unwrap()
+    );
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 3).unwrap(), deadline())
+            .unwrap(),
+        vec![
+            Indexed::new(0, 0x0000),
+            Indexed::new(1, 0xCAFE),
+            Indexed::new(2, 0xBEEF)
+        ]
+    );
+
+    // an elapsed deadline fails the call without sending the request
+    assert_eq!(
+        channel.read_coils(
+            params,
+            AddressRange::try_from(0, 1).unwrap(),
+            Instant::now()
+        ),
+        Err(rodbus::blocking::BlockingError::DeadlineElapsed)
+    );
+}
+
+/// handler that processes register requests against a shared backend after a delay
+struct AsyncHandler {
+    registers: std::sync::Arc<std::sync::Mutex<[u16; 10]>>,
+}
+
+impl RequestHandler for AsyncHandler {
+    fn process_async(
+        &mut self,
+        _unit_id: UnitId,
+        request: AsyncRequest,
+    ) -> Option<MaybeAsync<Result<AsyncResponse, ExceptionCode>>> {
+        let registers = self.registers.clone();
+        match request {
+            AsyncRequest::ReadHoldingRegisters(range) => {
+                Some(MaybeAsync::asynchronous(async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    let registers = registers.lock().unwrap();
+                    let values: Option<Vec<u16>> = range
+                        .into_iter()
+                        .map(|i| registers.get(i as usize).copied())
+                        .collect();
+                    values
+                        .map(AsyncResponse::Registers)
+                        .ok_or(ExceptionCode::IllegalDataAddress)
+                }))
+            }
+            AsyncRequest::WriteSingleRegister(value) => {
+                Some(MaybeAsync::asynchronous(async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    match registers.lock().unwrap().get_mut(value.index as usize) {
+                        Some(x) => *x = value.value,
+                        None => return Err(ExceptionCode::IllegalDataAddress),
+                    }
+                    Ok(AsyncResponse::Written)
+                }))
+            }
+            // processed by the synchronous methods
+            _ => None,
+        }
+    }
+}
+
+async fn test_async_handler() {
+    let addr = SocketAddr::from_str("127.0.0.1:40004").unwrap();
+    let handler = AsyncHandler {
+        registers: Default::default(),
+    };
+
+    let _server = spawn_tcp_server_task(
+        1,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), handler.wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+
+    // requests queued back to back are processed one at a time, in order
+    let mut reader = channel.clone();
+    let (write, read) = tokio::join!(
+        channel.write_single_register(params, Indexed::new(1, 0xCAFE)),
+        async {
+            tokio::task::yield_now().await;
+            reader
+                .read_holding_registers(params, AddressRange::try_from(0, 2).unwrap())
+                .await
+        }
+    );
+    assert_eq!(write, Ok(Indexed::new(1, 0xCAFE)));
+    assert_eq!(read, Ok(vec![Indexed::new(0, 0), Indexed::new(1, 0xCAFE)]));
+
+    // exceptions from the future are returned to the client
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(9, 2).unwrap())
+            .await,
+        Err(RequestError::Exception(ExceptionCode::IllegalDataAddress))
+    );
+
+    // requests without an asynchronous result fall back to the synchronous methods
+    assert_eq!(
+        channel
+            .read_coils(params, AddressRange::try_from(0, 1).unwrap())
+            .await,
+        Err(RequestError::Exception(ExceptionCode::IllegalFunction))
+    );
+}
+
+#[test]
+fn processes_requests_with_async_handler() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_async_handler())
+}
+
+/// handler that blocks the thread the first time a register is written
+struct BlockingHandler {
+    first: bool,
+}
+
+impl RequestHandler for BlockingHandler {
+    fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+        Ok(address)
+    }
+
+    fn write_single_register(&mut self, _value: Indexed<u16>) -> Result<(), ExceptionCode> {
+        if std::mem::take(&mut self.first) {
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        Ok(())
+    }
+}
+
+async fn test_handler_timeout() {
+    let addr = SocketAddr::from_str("127.0.0.1:40005").unwrap();
+
+    let _server = spawn_tcp_server_task_with_options(
+        1,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), BlockingHandler { first: true }.wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+        ServerOptions::default().handler_timeout(Some(
+            HandlerTimeout::new(Duration::from_millis(100))
+                .exception(ExceptionCode::ServerDeviceFailure),
+        )),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+
+    // the session replies with the configured exception while the handler is still blocked
+    assert_eq!(
+        channel
+            .write_single_register(params, Indexed::new(0, 1))
+            .await,
+        Err(RequestError::Exception(ExceptionCode::ServerDeviceFailure))
+    );
+
+    // and keeps serving requests once the handler returns
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(7, 1).unwrap())
+            .await,
+        Ok(vec![Indexed::new(7, 7)])
+    );
+}
+
+#[test]
+fn replies_with_exception_when_handler_times_out() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_handler_timeout())
+}
+
+/// handler whose writes block until the test drops the sender of `release`
+struct StuckHandler {
+    release: std::sync::mpsc::Receiver<()>,
+    writes: u16,
+}
+
+impl RequestHandler for StuckHandler {
+    fn read_holding_register(&self, _address: u16) -> Result<u16, ExceptionCode> {
+        Ok(self.writes)
+    }
+
+    fn write_single_register(&mut self, _value: Indexed<u16>) -> Result<(), ExceptionCode> {
+        let _ = self.release.recv();
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+async fn test_stuck_handler() {
+    let addr = SocketAddr::from_str("127.0.0.1:40014").unwrap();
+    let (release, rx) = std::sync::mpsc::channel();
+    let timeout = Duration::from_millis(200);
+
+    let _server = spawn_tcp_server_task_with_options(
+        1,
+        addr,
+        ServerHandlerMap::single(
+            UnitId::new(1),
+            StuckHandler {
+                release: rx,
+                writes: 0,
+            }
+            .wrap(),
+        ),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+        ServerOptions::default().handler_timeout(Some(HandlerTimeout::new(timeout))),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+
+    // the first write blocks the handler until the timeout expires
+    assert_eq!(
+        channel
+            .write_single_register(params, Indexed::new(0, 1))
+            .await,
+        Err(RequestError::Exception(ExceptionCode::ServerDeviceBusy))
+    );
+
+    // the following requests are answered without waiting for the handler
+    for value in 2..6 {
+        let start = tokio::time::Instant::now();
+        assert_eq!(
+            channel
+                .write_single_register(params, Indexed::new(0, value))
+                .await,
+            Err(RequestError::Exception(ExceptionCode::ServerDeviceBusy))
+        );
+        assert!(start.elapsed() < timeout);
+    }
+
+    // once the handler returns, only the first write was applied
+    drop(release);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 1).unwrap())
+            .await,
+        Ok(vec![Indexed::new(0, 1)])
+    );
+}
+
+#[test]
+fn answers_requests_for_a_stuck_handler_without_queuing_them() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_stuck_handler())
+}
+
+async fn test_gateway() {
+    use rodbus::gateway::Bridge;
+
+    let backend_addr = SocketAddr::from_str("127.0.0.1:40006").unwrap();
+    let gateway_addr = SocketAddr::from_str("127.0.0.1:40007").unwrap();
+
+    let _backend = spawn_tcp_server_task(
+        1,
+        backend_addr,
+        ServerHandlerMap::single(UnitId::new(2), Handler::new().wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let backend_channel = spawn_tcp_client_task(
+        HostAddr::ip(backend_addr.ip(), backend_addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    backend_channel.enable().await.unwrap();
+
+    // requests for unit id 1 are forwarded to unit id 2 of the backend
+    let bridge = Bridge::new(
+        backend_channel,
+        RequestParam::new(UnitId::new(2), Duration::from_secs(1)),
+    );
+    let _gateway = spawn_tcp_server_task(
+        1,
+        gateway_addr,
+        ServerHandlerMap::single(UnitId::new(1), bridge.wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(gateway_addr.ip(), gateway_addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(2));
+
+    // the backend may still be connecting
+    let result = loop {
+        match channel
+            .write_multiple_registers(
+                params,
+                WriteMultiple::from(1, vec![0xCAFE, 0xBEEF]).unwrap(),
+            )
+            .await
+        {
+            Err(RequestError::Exception(ExceptionCode::GatewayPathUnavailable)) => {
+                tokio::time::sleep(Duration::from_millis(10)).await
+            }
+            other => break other,
+        }
+    };
+    assert_eq!(result, Ok(AddressRange::try_from(1, 2).unwrap()));
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 3).unwrap())
+            .await
+            .unwrap(),
+        vec![
+            Indexed::new(0, 0),
+            Indexed::new(1, 0xCAFE),
+            Indexed::new(2, 0xBEEF)
+        ]
+    );
+    assert_eq!(
+        channel
+            .write_single_coil(params, Indexed::new(3, true))
+            .await
+            .unwrap(),
+        Indexed::new(3, true)
+    );
+    assert_eq!(
+        channel
+            .read_coils(params, AddressRange::try_from(3, 1).unwrap())
+            .await
+            .unwrap(),
+        vec![Indexed::new(3, true)]
+    );
+
+    // exceptions from the backend are passed through
+    assert_eq!(
+        channel
+            .read_input_registers(params, AddressRange::try_from(9, 2).unwrap())
+            .await,
+        Err(RequestError::Exception(ExceptionCode::IllegalDataAddress))
+    );
+}
+
+#[test]
+fn gateway_forwards_requests_to_backend_server() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_gateway())
+}
+
+async fn test_gateway_unit_ids() {
+    use rodbus::gateway::{Bridge, UnitIdMap};
+
+    let backend_addr = SocketAddr::from_str("127.0.0.1:40008").unwrap();
+    let gateway_addr = SocketAddr::from_str("127.0.0.1:40009").unwrap();
+
+    let mut backend_handlers = ServerHandlerMap::new();
+    backend_handlers.add(UnitId::new(17), Handler::new().wrap());
+    backend_handlers.add(UnitId::new(23), Handler::new().wrap());
+    let _backend = spawn_tcp_server_task(
+        1,
+        backend_addr,
+        backend_handlers,
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let backend_channel = spawn_tcp_client_task(
+        HostAddr::ip(backend_addr.ip(), backend_addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    backend_channel.enable().await.unwrap();
+
+    // the bridge receives the requests for every unit id and only forwards unit id 1 to 17
+    let bridge = Bridge::with_unit_ids(
+        backend_channel,
+        RequestParam::new(UnitId::new(0), Duration::from_secs(1)),
+        [(UnitId::new(1), UnitId::new(17))]
+            .into_iter()
+            .collect::<UnitIdMap>(),
+    )
+    .wrap();
+    let _gateway = spawn_tcp_server_task_with_options(
+        1,
+        gateway_addr,
+        ServerHandlerMap::single(UnitId::new(1), bridge.clone()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+        ServerOptions::default().unit_id_routing(UnitIdRouting::AnyToSingle),
+    )
+    .await
+    .unwrap();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(gateway_addr.ip(), gateway_addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+    channel.enable().await.unwrap();
+
+    // wait until we're connected
+    loop {
+        let state = rx.recv().await.unwrap();
+        if state == ClientState::Connected {
+            break;
+        }
+    }
+
+    let unit_1 = RequestParam::new(UnitId::new(1), Duration::from_secs(2));
+    let unit_2 = RequestParam::new(UnitId::new(2), Duration::from_secs(2));
+
+    // the backend may still be connecting
+    let result = loop {
+        match channel
+            .write_single_register(unit_1, Indexed::new(0, 0xCAFE))
+            .await
+        {
+            Err(RequestError::Exception(ExceptionCode::GatewayPathUnavailable)) => {
+                tokio::time::sleep(Duration::from_millis(10)).await
+            }
+            other => break other,
+        }
+    };
+    assert_eq!(result, Ok(Indexed::new(0, 0xCAFE)));
+
+    // unit id 2 isn't mapped yet
+    assert_eq!(
+        channel
+            .read_holding_registers(unit_2, AddressRange::try_from(0, 1).unwrap())
+            .await,
+        Err(RequestError::Exception(
+            ExceptionCode::GatewayTargetDeviceFailedToRespond
+        ))
+    );
+
+    // add a device while the gateway is running
+    bridge.update(|bridge| {
+        bridge
+            .unit_ids_mut()
+            .unwrap()
+            .insert(UnitId::new(2), UnitId::new(23))
+    });
+
+    assert_eq!(
+        channel
+            .read_holding_registers(unit_1, AddressRange::try_from(0, 1).unwrap())
+            .await
+            .unwrap(),
+        vec![Indexed::new(0, 0xCAFE)]
+    );
+    assert_eq!(
+        channel
+            .read_holding_registers(unit_2, AddressRange::try_from(0, 1).unwrap())
+            .await
+            .unwrap(),
+        vec![Indexed::new(0, 0)]
+    );
+}
+
+#[test]
+fn gateway_translates_unit_ids() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_gateway_unit_ids())
+}
+
+/// handler whose registers contain their own address
+struct AddressHandler;
+
+impl RequestHandler for AddressHandler {
+    fn read_holding_register(&self, address: u16) -> Result<u16, ExceptionCode> {
+        Ok(address)
+    }
+}
+
+async fn test_shared_channel() {
+    const TASKS: u16 = 50;
+    const REQUESTS_PER_TASK: u16 = 20;
+
+    let addr = SocketAddr::from_str("127.0.0.1:40011").unwrap();
+
+    let _server = spawn_tcp_server_task(
+        4,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), AddressHandler.wrap()),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    // the queue is smaller than the number of tasks so that senders wait for space
+    let channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await.unwrap();
+    assert!(channel
+        .wait_for_connected(Duration::from_secs(5))
+        .await
+        .unwrap());
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(5));
+    let mut tasks = Vec::new();
+    for task in 0..TASKS {
+        let mut channel = channel.clone();
+        tasks.push(tokio::spawn(async move {
+            for request in 0..REQUESTS_PER_TASK {
+                // every request reads a distinct range, so any cross-talk changes the values
+                let range =
+                    AddressRange::try_from(task * 1000 + request * 10, 1 + request % 5).unwrap();
+                let expected: Vec<Indexed<u16>> =
+                    range.iter().map(|x| Indexed::new(x, x)).collect();
+                assert_eq!(
+                    channel.read_holding_registers(params, range).await,
+                    Ok(expected)
+                );
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert!(!channel.is_closed());
+}
+
+#[test]
+fn channel_shared_by_many_tasks_matches_responses_to_requests() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_shared_channel())
+}
+
+async fn test_transactional_writes() {
+    let addr = SocketAddr::from_str("127.0.0.1:40012").unwrap();
+    let partial = Handler::new().wrap();
+    let transactional = Handler::new().wrap();
+
+    let mut map = ServerHandlerMap::new();
+    map.add(UnitId::new(1), partial.clone());
+    map.add(UnitId::new(2), transactional.clone());
+    map.set_transactional_writes(UnitId::new(2), true);
+
+    let _server = spawn_tcp_server_task(1, addr, map, AddressFilter::Any, DecodeLevel::default())
+        .await
+        .unwrap();
+
+    let mut channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await.unwrap();
+    assert!(channel
+        .wait_for_connected(Duration::from_secs(5))
+        .await
+        .unwrap());
+
+    // the handler only has registers 0 to 9, so the write fails on the third register
+    let write = || WriteMultiple::from(8, vec![0xCAFE, 0xCAFE, 0xCAFE, 0xCAFE]).unwrap();
+    for (unit_id, handler, expected) in [(1, &partial, 0xCAFE), (2, &transactional, 0)] {
+        let params = RequestParam::new(UnitId::new(unit_id), Duration::from_secs(1));
+        assert_eq!(
+            channel.write_multiple_registers(params, write()).await,
+            Err(RequestError::Exception(ExceptionCode::IllegalDataAddress))
+        );
+        let registers = handler.lock().unwrap().holding_registers;
+        assert_eq!(registers[8..], [expected, expected]);
+    }
+
+    // writes within the range are applied as usual
+    let params = RequestParam::new(UnitId::new(2), Duration::from_secs(1));
+    let values = WriteMultiple::from(6, vec![1, 2, 3, 4]).unwrap();
+    channel
+        .write_multiple_registers(params, values)
+        .await
+        .unwrap();
+    assert_eq!(
+        transactional.lock().unwrap().holding_registers[6..],
+        [1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn transactional_writes_reject_partial_multi_register_writes() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_transactional_writes())
+}
+
+#[cfg(feature = "test-certs")]
+async fn test_tls_authorization() {
+    let dir = std::env::temp_dir().join(format!("rodbus-tls-authz-{}", std::process::id()));
+    let certs = rodbus::testing::certs::generate_certificates(&dir, "localhost", "viewer").unwrap();
+    let addr = SocketAddr::from_str("127.0.0.1:40013").unwrap();
+    let handler = Handler::new().wrap();
+
+    let _server = spawn_tls_server_task_with_authz(
+        1,
+        addr,
+        ServerHandlerMap::single(UnitId::new(1), handler.clone()),
+        ReadOnlyAuthorizationHandler::create(),
+        certs.server_config().unwrap(),
+        AddressFilter::Any,
+        DecodeLevel::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut channel = spawn_tls_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        10,
+        default_retry_strategy(),
+        certs.client_config().unwrap(),
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await.unwrap();
+    assert!(channel
+        .wait_for_connected(Duration::from_secs(5))
+        .await
+        .unwrap());
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 2).unwrap())
+            .await,
+        Ok(vec![Indexed::new(0, 0), Indexed::new(1, 0)])
+    );
+    assert_eq!(
+        channel
+            .write_single_register(params, Indexed::new(0, 42))
+            .await,
+        Err(RequestError::Exception(ExceptionCode::IllegalFunction))
+    );
+    assert_eq!(handler.lock().unwrap().holding_registers[0], 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "test-certs")]
+#[test]
+fn authorizes_requests_from_the_role_in_generated_certificates() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_tls_authorization())
+}
+
+/// Creates a pair of connected pseudo terminals with `socat` and removes them when dropped
+#[cfg(all(unix, feature = "serial"))]
+struct PtyPair {
+    socat: std::process::Child,
+    dir: std::path::PathBuf,
+}
+
+#[cfg(all(unix, feature = "serial"))]
+impl PtyPair {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("rodbus-pty-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let end = |name: &str| format!("pty,raw,echo=0,link={}", dir.join(name).display());
+        let socat = std::process::Command::new("socat")
+            .arg(end("a"))
+            .arg(end("b"))
+            .spawn()
+            .expect("socat must be installed to run this test");
+
+        let pair = Self { socat, dir };
+        // socat creates the links asynchronously
+        for _ in 0..50 {
+            if pair.path("a").exists() && pair.path("b").exists() {
+                return pair;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        panic!("socat didn't create the pseudo terminals");
+    }
+
+    fn path(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(name)
+    }
+}
+
+#[cfg(all(unix, feature = "serial"))]
+impl Drop for PtyPair {
+    fn drop(&mut self) {
+        let _ = self.socat.kill();
+        let _ = self.socat.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(all(unix, feature = "serial"))]
+async fn test_rtu_over_pty() {
+    let pty = PtyPair::new();
+    let handler = Handler::new().wrap();
+
+    let _server = spawn_rtu_server_task(
+        pty.path("b").to_str().unwrap(),
+        SerialSettings::default(),
+        default_retry_strategy(),
+        ServerHandlerMap::single(UnitId::new(1), handler.clone()),
+        DecodeLevel::default(),
+    )
+    .unwrap();
+
+    let mut channel = spawn_rtu_client_task(
+        pty.path("a").to_str().unwrap(),
+        SerialSettings::default(),
+        10,
+        default_retry_strategy(),
+        DecodeLevel::default(),
+        None,
+    );
+    channel.enable().await.unwrap();
+    assert!(channel
+        .wait_for_connected(Duration::from_secs(5))
+        .await
+        .unwrap());
+
+    let params = RequestParam::new(UnitId::new(1), Duration::from_secs(1));
+    assert_eq!(
+        channel
+            .write_multiple_registers(
+                params,
+                WriteMultiple::from(0, vec![0xCAFE, 0xBEEF]).unwrap()
+            )
+            .await,
+        Ok(AddressRange::try_from(0, 2).unwrap())
+    );
+    assert_eq!(
+        channel
+            .read_holding_registers(params, AddressRange::try_from(0, 2).unwrap())
+            .await,
+        Ok(vec![Indexed::new(0, 0xCAFE), Indexed::new(1, 0xBEEF)])
+    );
+    assert_eq!(
+        handler.lock().unwrap().holding_registers[..2],
+        [0xCAFE, 0xBEEF]
+    );
+}
+
+// requires socat, run with `cargo test -- --ignored`
+#[cfg(all(unix, feature = "serial"))]
+#[test]
+#[ignore]
+fn reads_and_writes_values_over_pseudo_terminals() {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(test_rtu_over_pty())
+}
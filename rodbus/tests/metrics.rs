@@ -0,0 +1,83 @@
+//! Tests of the instrumentation recorded with the `metrics` feature
+
+#![cfg(feature = "metrics")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use rodbus::client::*;
+use rodbus::*;
+
+struct ClientStateListener {
+    tx: tokio::sync::mpsc::UnboundedSender<ClientState>,
+}
+
+impl Listener<ClientState> for ClientStateListener {
+    fn update(&mut self, value: ClientState) -> MaybeAsync<()> {
+        let _ = self.tx.send(value);
+        MaybeAsync::ready(())
+    }
+}
+
+async fn wait_for(
+    states: &mut tokio::sync::mpsc::UnboundedReceiver<ClientState>,
+    matches: fn(&ClientState) -> bool,
+) {
+    loop {
+        let state = states.recv().await.unwrap();
+        if matches(&state) {
+            return;
+        }
+    }
+}
+
+fn reconnects(handle: &PrometheusHandle) -> u64 {
+    handle
+        .render()
+        .lines()
+        .find_map(|x| x.strip_prefix("rodbus_client_reconnects_total "))
+        .map(|x| x.parse().unwrap())
+        .unwrap_or(0)
+}
+
+// the current thread runtime runs the client task on the thread of the local recorder
+#[tokio::test]
+async fn reconnects_are_only_counted_when_the_connection_is_lost() {
+    let recorder = PrometheusBuilder::new().build_recorder();
+    let handle = recorder.handle();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let server = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (tx, mut states) = tokio::sync::mpsc::unbounded_channel();
+    let channel = spawn_tcp_client_task(
+        HostAddr::ip(addr.ip(), addr.port()),
+        1,
+        doubling_retry_strategy(Duration::from_millis(10), Duration::from_millis(10)),
+        DecodeLevel::nothing(),
+        Some(Box::new(ClientStateListener { tx })),
+    );
+
+    // disabling the channel closes the connection without a reconnect
+    channel.enable().await.unwrap();
+    let (socket, _) = server.accept().await.unwrap();
+    wait_for(&mut states, |x| *x == ClientState::Connected).await;
+    channel.disable().await.unwrap();
+    wait_for(&mut states, |x| *x == ClientState::Disabled).await;
+    drop(socket);
+    assert_eq!(reconnects(&handle), 0);
+
+    // closing the connection from the server is counted
+    channel.enable().await.unwrap();
+    let (socket, _) = server.accept().await.unwrap();
+    wait_for(&mut states, |x| *x == ClientState::Connected).await;
+    drop(socket);
+    wait_for(&mut states, |x| {
+        matches!(x, ClientState::WaitAfterDisconnect { .. })
+    })
+    .await;
+    assert_eq!(reconnects(&handle), 1);
+}
@@ -0,0 +1,148 @@
+//! Round-trip tests for the types covered by the `serde` feature
+
+#![cfg(feature = "serde")]
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use rodbus::client::{HostAddr, RequestParam, WriteMultiple};
+use rodbus::*;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn round_trip<T>(value: &T, expected: &str) -> T
+where
+    T: Serialize + DeserializeOwned,
+{
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(json, expected);
+    serde_json::from_str(&json).unwrap()
+}
+
+fn assert_round_trip<T>(value: T, expected: &str)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    assert_eq!(round_trip(&value, expected), value);
+}
+
+#[test]
+fn request_types() {
+    assert_round_trip(UnitId::new(7), "7");
+    assert_round_trip(
+        AddressRange::try_from(1, 10).unwrap(),
+        r#"{"start":1,"count":10}"#,
+    );
+    for invalid in [r#"{"start":1,"count":0}"#, r#"{"start":65535,"count":2}"#] {
+        assert!(serde_json::from_str::<AddressRange>(invalid).is_err());
+    }
+    assert_round_trip(Indexed::new(3, true), r#"{"index":3,"value":true}"#);
+    assert_round_trip(Indexed::new(4, 0xCAFEu16), r#"{"index":4,"value":51966}"#);
+
+    let param = RequestParam::new(UnitId::new(1), Duration::from_millis(1500))
+        .with_deadline(tokio::time::Instant::now());
+    let param = round_trip(
+        &param,
+        r#"{"id":1,"response_timeout":{"secs":1,"nanos":500000000},"write_timeout":null,"retries":0,"retry_on":"Timeout","retry_writes":false,"high_priority":false,"correlation_id":null,"verify_delay":null}"#,
+    );
+    assert_eq!(param.id, UnitId::new(1));
+    assert_eq!(param.response_timeout, Duration::from_millis(1500));
+    // the deadline is an instant of the Tokio clock, which is not serialized
+    assert_eq!(param.deadline(), None);
+    // only the unit id and the response timeout are required
+    let param: RequestParam =
+        serde_json::from_str(r#"{"id":1,"response_timeout":{"secs":1,"nanos":500000000}}"#)
+            .unwrap();
+    assert_eq!(param.retries, 0);
+    assert_eq!(param.verify_delay, None);
+
+    let host = round_trip(
+        &HostAddr::ip("127.0.0.1".parse().unwrap(), 502),
+        r#"{"addr":{"IpAddr":"127.0.0.1"},"port":502}"#,
+    );
+    assert_eq!(host.to_string(), "127.0.0.1:502");
+}
+
+#[test]
+fn write_multiple_validates_limits() {
+    let request = round_trip(
+        &WriteMultiple::from(5, vec![1u16, 2, 3]).unwrap(),
+        r#"{"start":5,"values":[1,2,3]}"#,
+    );
+    assert_eq!(
+        serde_json::to_string(&request).unwrap(),
+        r#"{"start":5,"values":[1,2,3]}"#
+    );
+
+    let too_many = format!(r#"{{"start":0,"values":{:?}}}"#, vec![0u16; 124]);
+    assert!(serde_json::from_str::<WriteMultiple<u16>>(&too_many).is_err());
+}
+
+#[test]
+fn exception_codes_use_numeric_values() {
+    assert_round_trip(ExceptionCode::IllegalDataAddress, "2");
+    assert_round_trip(ExceptionCode::ServerDeviceBusy, "6");
+    assert_round_trip(ExceptionCode::Unknown(0x42), "66");
+}
+
+#[test]
+fn errors() {
+    assert_round_trip(RequestError::ResponseTimeout, r#""ResponseTimeout""#);
+    assert_round_trip(
+        RequestError::Io(std::io::ErrorKind::ConnectionRefused),
+        r#"{"Io":"ConnectionRefused"}"#,
+    );
+    assert_round_trip(
+        RequestError::Exception(ExceptionCode::IllegalFunction),
+        r#"{"Exception":1}"#,
+    );
+    assert_round_trip(
+        RequestError::BadRequest(InvalidRequest::CountTooBigForType(124, 123)),
+        r#"{"BadRequest":{"CountTooBigForType":[124,123]}}"#,
+    );
+    assert_round_trip(
+        RequestError::BadFrame(FrameParseError::CrcValidationFailure(1, 2)),
+        r#"{"BadFrame":{"CrcValidationFailure":[1,2]}}"#,
+    );
+    assert_round_trip(
+        RequestError::BadResponse(AduParseError::TrailingBytes(3)),
+        r#"{"BadResponse":{"TrailingBytes":3}}"#,
+    );
+}
+
+#[test]
+fn decode_level() {
+    assert_round_trip(
+        DecodeLevel::new(
+            AppDecodeLevel::DataValues,
+            FrameDecodeLevel::Header,
+            PhysDecodeLevel::Length,
+        ),
+        r#"{"app":"DataValues","frame":"Header","physical":"Length","bytes":{"style":"SpacedHex","max_bytes":null},"redact_values":false}"#,
+    );
+    assert_round_trip(
+        DecodeLevel::nothing().bytes(
+            BytesFormat::default()
+                .style(BytesStyle::HexDump)
+                .max_bytes(Some(64)),
+        ),
+        r#"{"app":"Nothing","frame":"Nothing","physical":"Nothing","bytes":{"style":"HexDump","max_bytes":64},"redact_values":false}"#,
+    );
+    // the byte format is optional
+    let level: DecodeLevel =
+        serde_json::from_str(r#"{"app":"DataValues","frame":"Header","physical":"Length"}"#)
+            .unwrap();
+    assert_eq!(level.bytes, BytesFormat::default());
+}
+
+#[cfg(feature = "serial")]
+#[test]
+fn serial_settings() {
+    let settings = round_trip(
+        &SerialSettings::default(),
+        r#"{"baud_rate":9600,"data_bits":"Eight","flow_control":"None","stop_bits":"One","parity":"None","rs485":"None"}"#,
+    );
+    assert_eq!(settings.baud_rate, 9600);
+    assert_eq!(settings.data_bits, DataBits::Eight);
+}
@@ -0,0 +1,253 @@
+//! Conformance tests that check the frames in `tests/vectors` against the encoder and decoder
+//!
+//! See `tests/vectors/README.md` for the format of the files.
+
+#![cfg(feature = "codec")]
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use rodbus::client::{FrameFormat, WriteMultiple};
+use rodbus::codec::{decode_response, encode_request, Header, Request, Response};
+use rodbus::*;
+
+use serde_json::Value;
+
+/// Every function code supported by the library
+///
+/// The match in `name` fails to compile when a function code is added, as a reminder to add
+/// it here and to ship vectors for it.
+const FUNCTION_CODES: [FunctionCode; 8] = [
+    FunctionCode::ReadCoils,
+    FunctionCode::ReadDiscreteInputs,
+    FunctionCode::ReadHoldingRegisters,
+    FunctionCode::ReadInputRegisters,
+    FunctionCode::WriteSingleCoil,
+    FunctionCode::WriteSingleRegister,
+    FunctionCode::WriteMultipleCoils,
+    FunctionCode::WriteMultipleRegisters,
+];
+
+fn name(function: FunctionCode) -> &'static str {
+    match function {
+        FunctionCode::ReadCoils => "ReadCoils",
+        FunctionCode::ReadDiscreteInputs => "ReadDiscreteInputs",
+        FunctionCode::ReadHoldingRegisters => "ReadHoldingRegisters",
+        FunctionCode::ReadInputRegisters => "ReadInputRegisters",
+        FunctionCode::WriteSingleCoil => "WriteSingleCoil",
+        FunctionCode::WriteSingleRegister => "WriteSingleRegister",
+        FunctionCode::WriteMultipleCoils => "WriteMultipleCoils",
+        FunctionCode::WriteMultipleRegisters => "WriteMultipleRegisters",
+    }
+}
+
+fn function_code(name: &str) -> FunctionCode {
+    FUNCTION_CODES
+        .into_iter()
+        .find(|x| self::name(*x) == name)
+        .unwrap_or_else(|| panic!("unknown function: {name}"))
+}
+
+fn vector_files() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .filter(|x| x.extension().is_some_and(|x| x == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn hex(value: &Value) -> Vec<u8> {
+    value
+        .as_str()
+        .unwrap()
+        .split_whitespace()
+        .map(|x| u8::from_str_radix(x, 16).unwrap())
+        .collect()
+}
+
+fn u16_field(value: &Value, field: &str) -> u16 {
+    value[field]
+        .as_u64()
+        .and_then(|x| u16::try_from(x).ok())
+        .unwrap_or_else(|| panic!("missing or invalid field: {field}"))
+}
+
+fn bools(value: &Value) -> Vec<bool> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x.as_bool().unwrap())
+        .collect()
+}
+
+fn registers(value: &Value) -> Vec<u16> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| u16::try_from(x.as_u64().unwrap()).unwrap())
+        .collect()
+}
+
+fn range(value: &Value) -> AddressRange {
+    AddressRange::try_from(u16_field(value, "start"), u16_field(value, "count")).unwrap()
+}
+
+fn indexed<T>(start: u16, values: Vec<T>) -> Vec<Indexed<T>> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(offset, value)| Indexed::new(start + offset as u16, value))
+        .collect()
+}
+
+/// values of the write multiple requests, which are borrowed by [`Request`]
+enum Values {
+    Empty,
+    Coils(WriteMultiple<bool>),
+    Registers(WriteMultiple<u16>),
+}
+
+impl Values {
+    fn new(function: FunctionCode, request: &Value) -> Self {
+        match function {
+            FunctionCode::WriteMultipleCoils => Self::Coils(
+                WriteMultiple::from(u16_field(request, "start"), bools(&request["values"]))
+                    .unwrap(),
+            ),
+            FunctionCode::WriteMultipleRegisters => Self::Registers(
+                WriteMultiple::from(u16_field(request, "start"), registers(&request["values"]))
+                    .unwrap(),
+            ),
+            _ => Self::Empty,
+        }
+    }
+}
+
+fn request<'a>(function: FunctionCode, request: &Value, values: &'a Values) -> Request<'a> {
+    match (function, values) {
+        (FunctionCode::ReadCoils, _) => Request::ReadCoils(range(request)),
+        (FunctionCode::ReadDiscreteInputs, _) => Request::ReadDiscreteInputs(range(request)),
+        (FunctionCode::ReadHoldingRegisters, _) => Request::ReadHoldingRegisters(range(request)),
+        (FunctionCode::ReadInputRegisters, _) => Request::ReadInputRegisters(range(request)),
+        (FunctionCode::WriteSingleCoil, _) => Request::WriteSingleCoil(Indexed::new(
+            u16_field(request, "index"),
+            request["value"].as_bool().unwrap(),
+        )),
+        (FunctionCode::WriteSingleRegister, _) => Request::WriteSingleRegister(Indexed::new(
+            u16_field(request, "index"),
+            u16_field(request, "value"),
+        )),
+        (FunctionCode::WriteMultipleCoils, Values::Coils(x)) => Request::WriteMultipleCoils(x),
+        (FunctionCode::WriteMultipleRegisters, Values::Registers(x)) => {
+            Request::WriteMultipleRegisters(x)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn response(request: Request, response: &Value) -> Result<Response, RequestError> {
+    if let Some(code) = response.get("exception") {
+        let code = u8::try_from(code.as_u64().unwrap()).unwrap();
+        return Err(RequestError::Exception(ExceptionCode::from(code)));
+    }
+
+    let response = match request {
+        Request::ReadCoils(range) | Request::ReadDiscreteInputs(range) => {
+            Response::Bits(indexed(range.start, bools(&response["values"])))
+        }
+        Request::ReadHoldingRegisters(range) | Request::ReadInputRegisters(range) => {
+            Response::Registers(indexed(range.start, registers(&response["values"])))
+        }
+        Request::WriteSingleCoil(_) => Response::SingleCoil(Indexed::new(
+            u16_field(response, "index"),
+            response["value"].as_bool().unwrap(),
+        )),
+        Request::WriteSingleRegister(_) => Response::SingleRegister(Indexed::new(
+            u16_field(response, "index"),
+            u16_field(response, "value"),
+        )),
+        Request::WriteMultipleCoils(_) | Request::WriteMultipleRegisters(_) => {
+            Response::MultipleWrite(range(response))
+        }
+    };
+    Ok(response)
+}
+
+fn check_vector(function: FunctionCode, vector: &Value) -> Result<(), String> {
+    let format = match vector["format"].as_str() {
+        Some("mbap") => FrameFormat::Mbap,
+        Some("rtu") => FrameFormat::Rtu,
+        other => return Err(format!("unknown format: {other:?}")),
+    };
+    let mut header = Header::new(UnitId::new(u16_field(vector, "unit_id") as u8));
+    if vector.get("tx_id").is_some() {
+        header = header.with_tx_id(u16_field(vector, "tx_id"));
+    }
+
+    let values = Values::new(function, &vector["request"]);
+    let request = request(function, &vector["request"], &values);
+
+    let mut buffer = [0; 260];
+    let count = encode_request(&mut buffer, format, header, request)
+        .map_err(|err| format!("unable to encode the request: {err}"))?;
+    let expected = hex(&vector["request_frame"]);
+    if buffer[..count] != expected {
+        return Err(format!(
+            "encoded request {:02X?} != {:02X?}",
+            &buffer[..count],
+            expected
+        ));
+    }
+
+    let decoded = decode_response(&hex(&vector["response_frame"]), format, request);
+    let expected = response(request, &vector["response"]).map(|x| (header, x));
+    if decoded != expected {
+        return Err(format!("decoded response {decoded:?} != {expected:?}"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn frames_match_test_vectors() {
+    let mut failures = Vec::new();
+    let mut covered = BTreeSet::new();
+    let mut formats = BTreeSet::new();
+
+    for path in vector_files() {
+        let file: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|err| panic!("unable to parse {}: {err}", path.display()));
+        let function = function_code(file["function"].as_str().unwrap());
+        for vector in file["vectors"].as_array().unwrap() {
+            formats.insert((
+                function,
+                vector["format"].as_str().unwrap_or("").to_string(),
+            ));
+            if let Err(err) = check_vector(function, vector) {
+                failures.push(format!(
+                    "{} \"{}\": {err}",
+                    path.display(),
+                    vector["description"].as_str().unwrap_or("")
+                ));
+            }
+        }
+        covered.insert(function);
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+
+    for function in FUNCTION_CODES {
+        assert!(covered.contains(&function), "no vectors for {function}");
+        for format in ["mbap", "rtu"] {
+            assert!(
+                formats.contains(&(function, format.to_string())),
+                "no {format} vectors for {function}"
+            );
+        }
+    }
+}